@@ -0,0 +1,82 @@
+//! systemd socket activation: a `ts-bridge.socket` user unit listens on the
+//! daemon's socket and starts the service on the first editor connection,
+//! handing the listener over as file descriptor 3 with `LISTEN_PID` and
+//! `LISTEN_FDS` set (see `sd_listen_fds(3)`).
+
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+
+use anyhow::anyhow;
+
+/// The first inherited descriptor, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A listener systemd opened for the daemon.
+pub(crate) enum ActivatedListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Takes the listener systemd passed to this process, if any. The
+/// activation variables are cleared so tsserver and a restarted daemon do
+/// not mistake themselves for the activated service.
+pub(crate) fn take_listener() -> anyhow::Result<Option<ActivatedListener>> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    // SAFETY: the daemon reads these before starting any thread.
+    unsafe {
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+    }
+    let (Some(pid), Some(fds)) = (pid, fds) else {
+        return Ok(None);
+    };
+    if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(None);
+    }
+    match fds.trim().parse::<u32>() {
+        Ok(0) | Err(_) => return Ok(None),
+        Ok(1) => {}
+        Ok(count) => {
+            return Err(anyhow!(
+                "systemd passed {count} sockets; the daemon listens on exactly one"
+            ));
+        }
+    }
+
+    let fd = LISTEN_FDS_START;
+    // Keeps the listener from leaking into tsserver processes.
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+        return Err(anyhow!(std::io::Error::last_os_error()).context("inherited socket"));
+    }
+    match socket_family(fd)? {
+        libc::AF_UNIX => Ok(Some(ActivatedListener::Unix(unsafe {
+            UnixListener::from_raw_fd(fd)
+        }))),
+        libc::AF_INET | libc::AF_INET6 => Ok(Some(ActivatedListener::Tcp(unsafe {
+            TcpListener::from_raw_fd(fd)
+        }))),
+        family => Err(anyhow!(
+            "systemd passed a socket of unsupported address family {family}"
+        )),
+    }
+}
+
+fn socket_family(fd: RawFd) -> anyhow::Result<libc::c_int> {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockname(
+            fd,
+            (&mut storage as *mut libc::sockaddr_storage).cast(),
+            &mut len,
+        )
+    };
+    if result == -1 {
+        return Err(anyhow!(std::io::Error::last_os_error())
+            .context("inherited file descriptor 3 is not a socket"));
+    }
+    Ok(storage.ss_family as libc::c_int)
+}
@@ -1,7 +1,397 @@
-pub fn organize_imports_sync() {
-    todo!("Call protocol::text_document::organize_imports handler once implemented");
+//! Embedding ts-bridge without speaking LSP. A [`Bridge`] owns one project's
+//! tsserver and answers typed requests about the documents opened through
+//! it, using the same request handlers as the language server: the results
+//! are the `lsp_types` values an editor would receive, and settings files and
+//! `TS_BRIDGE_*` overrides apply as they do there.
+//!
+//! ```no_run
+//! use lsp_types::Position;
+//! use ts_bridge::Bridge;
+//!
+//! let mut bridge = Bridge::start("path/to/project")?;
+//! bridge.open_from_disk("src/main.ts")?;
+//! if let Some(hover) = bridge.hover("src/main.ts", Position::new(0, 13))? {
+//!     println!("{:?}", hover.contents);
+//! }
+//! bridge.shutdown();
+//! # anyhow::Ok(())
+//! ```
+//!
+//! Paths are absolute or relative to the project root; positions are LSP
+//! positions (zero-based lines, UTF-16 columns). Requests block until
+//! tsserver answers.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use lsp_types::{
+    CompletionItem, CompletionParams, CompletionResponse, Diagnostic, DocumentDiagnosticParams,
+    DocumentDiagnosticReport, DocumentSymbol, DocumentSymbolParams, ExecuteCommandParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, Location, Position,
+    ReferenceContext, ReferenceParams, RenameParams, TextDocumentIdentifier,
+    TextDocumentPositionParams, Uri, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::check::Checker;
+use crate::config::DiagnosticsSettings;
+use crate::protocol::text_document::completion::{CompletionConfig, typed_word};
+use crate::protocol::text_document::{
+    definition, diagnostic, did_change, did_close, did_open, document_symbol, hover, references,
+    rename,
+};
+use crate::protocol::workspace::execute_command;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::server::workspace_config;
+use crate::types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    TextDocumentContentChangeEvent, TextDocumentItem, VersionedTextDocumentIdentifier,
+};
+use crate::utils::file_path_to_uri;
+
+/// One project's tsserver, driven a request at a time. Dropping it shuts
+/// tsserver down.
+pub struct Bridge {
+    checker: Checker,
+    root: PathBuf,
+    /// Open documents: their version and text.
+    documents: HashMap<PathBuf, (i32, String)>,
+    hover_verbosity: u32,
+    completion: CompletionConfig,
+    diagnostics: DiagnosticsSettings,
+}
+
+impl Bridge {
+    /// Starts tsserver for the project at `root`, the directory holding its
+    /// tsconfig.json/jsconfig.json (or any directory, for loose files).
+    /// tsserver is looked up as the language server looks it up.
+    pub fn start(root: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let root = root.as_ref();
+        let root =
+            std::fs::canonicalize(root).with_context(|| format!("resolve {}", root.display()))?;
+        let (mut config, problems) = workspace_config(&root);
+        for problem in problems {
+            log::warn!("{problem}");
+        }
+        // Requests are answered in turn, so a second server would only cost
+        // memory.
+        config.plugin_mut().separate_diagnostic_server = false;
+        let plugin = config.plugin();
+        let hover_verbosity = plugin.hover.verbosity;
+        let completion = CompletionConfig {
+            complete_function_calls: plugin.complete_function_calls,
+            snippet_support: true,
+            max_entries: plugin.completion.max_entries,
+            entries_limit_strategy: plugin.completion.entries_limit_strategy,
+            commit_characters: plugin.completion.commit_characters.clone(),
+        };
+        let diagnostics = plugin.diagnostics.clone();
+        let checker = Checker::start(config, &root)?;
+        Ok(Self {
+            checker,
+            root,
+            documents: HashMap::new(),
+            hover_verbosity,
+            completion,
+            diagnostics,
+        })
+    }
+
+    /// The project root, canonicalized.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Opens `file` with `text` as its contents, whatever is on disk. Opening
+    /// an open document replaces its text.
+    pub fn open(&mut self, file: impl AsRef<Path>, text: impl Into<String>) -> anyhow::Result<()> {
+        let path = self.resolve(file.as_ref());
+        let text = text.into();
+        if self.documents.contains_key(&path) {
+            return self.change(&path, text);
+        }
+        let spec = did_open::handle(
+            DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri(&path)?.to_string(),
+                    language_id: None,
+                    version: 0,
+                    text: text.clone(),
+                },
+            },
+            &self.root,
+        );
+        self.checker.request(spec.payload)?;
+        self.documents.insert(path, (0, text));
+        Ok(())
+    }
+
+    /// Opens `file` with its contents on disk.
+    pub fn open_from_disk(&mut self, file: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = self.resolve(file.as_ref());
+        let text =
+            std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        self.open(path, text)
+    }
+
+    /// Replaces the text of the open document `file`.
+    pub fn change(
+        &mut self,
+        file: impl AsRef<Path>,
+        text: impl Into<String>,
+    ) -> anyhow::Result<()> {
+        let path = self.resolve(file.as_ref());
+        let Some((version, old)) = self.documents.get_mut(&path) else {
+            bail!("{} is not open", path.display());
+        };
+        let text = text.into();
+        *version += 1;
+        let spec = did_change::handle(
+            DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier {
+                    uri: uri(&path)?.to_string(),
+                    version: Some(*version),
+                },
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: Some(whole(old)),
+                    text: text.clone(),
+                }],
+            },
+            &self.root,
+        );
+        *old = text;
+        self.checker.request(spec.payload).map(|_| ())
+    }
+
+    /// Closes `file`; tsserver reads it from disk again.
+    pub fn close(&mut self, file: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = self.resolve(file.as_ref());
+        if self.documents.remove(&path).is_none() {
+            bail!("{} is not open", path.display());
+        }
+        let spec = did_close::handle(
+            DidCloseTextDocumentParams {
+                text_document: crate::types::TextDocumentIdentifier {
+                    uri: uri(&path)?.to_string(),
+                },
+            },
+            &self.root,
+        );
+        self.checker.request(spec.payload).map(|_| ())
+    }
+
+    /// The quick info at `position`: the symbol's type and documentation.
+    pub fn hover(
+        &mut self,
+        file: impl AsRef<Path>,
+        position: Position,
+    ) -> anyhow::Result<Option<Hover>> {
+        let params = HoverParams {
+            text_document_position_params: self.position(file.as_ref(), position)?,
+            work_done_progress_params: Default::default(),
+        };
+        let spec = hover::handle(params, self.hover_verbosity);
+        self.call(spec)
+    }
+
+    /// Where the symbol at `position` is defined.
+    pub fn definition(
+        &mut self,
+        file: impl AsRef<Path>,
+        position: Position,
+    ) -> anyhow::Result<Vec<Location>> {
+        let params = definition::DefinitionParams {
+            base: GotoDefinitionParams {
+                text_document_position_params: self.position(file.as_ref(), position)?,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+            context: None,
+        };
+        let response: Option<GotoDefinitionResponse> = self.call(definition::handle(params))?;
+        Ok(match response {
+            None => Vec::new(),
+            Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+            Some(GotoDefinitionResponse::Array(locations)) => locations,
+            Some(GotoDefinitionResponse::Link(links)) => links
+                .into_iter()
+                .map(|link| Location::new(link.target_uri, link.target_selection_range))
+                .collect(),
+        })
+    }
+
+    /// Every reference to the symbol at `position`, its declaration
+    /// included when `include_declaration` is set.
+    pub fn references(
+        &mut self,
+        file: impl AsRef<Path>,
+        position: Position,
+        include_declaration: bool,
+    ) -> anyhow::Result<Vec<Location>> {
+        let params = ReferenceParams {
+            text_document_position: self.position(file.as_ref(), position)?,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration,
+            },
+        };
+        let locations: Option<Vec<Location>> = self.call(references::handle(params))?;
+        Ok(locations.unwrap_or_default())
+    }
+
+    /// The completions at `position`, ranked and limited by the
+    /// `completion` settings.
+    pub fn completion(
+        &mut self,
+        file: impl AsRef<Path>,
+        position: Position,
+    ) -> anyhow::Result<Vec<CompletionItem>> {
+        let text_document_position = self.position(file.as_ref(), position)?;
+        let path = self.resolve(file.as_ref());
+        let word = self
+            .documents
+            .get(&path)
+            .and_then(|(_, text)| text.lines().nth(position.line as usize))
+            .map(|line| typed_word(line, position.character))
+            .unwrap_or_default();
+        let params = CompletionParams {
+            text_document_position,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let spec = crate::protocol::text_document::completion::handle(
+            params,
+            self.completion.clone(),
+            &word,
+        );
+        let response: Option<CompletionResponse> = self.call(spec)?;
+        Ok(match response {
+            None => Vec::new(),
+            Some(CompletionResponse::Array(items)) => items,
+            Some(CompletionResponse::List(list)) => list.items,
+        })
+    }
+
+    /// The outline of `file`.
+    pub fn document_symbols(
+        &mut self,
+        file: impl AsRef<Path>,
+    ) -> anyhow::Result<Vec<DocumentSymbol>> {
+        let params = DocumentSymbolParams {
+            text_document: self.document(file.as_ref())?,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let symbols: Option<Vec<DocumentSymbol>> = self.call(document_symbol::handle(params))?;
+        Ok(symbols.unwrap_or_default())
+    }
+
+    /// The syntactic, semantic and suggestion diagnostics of `file`, filtered
+    /// by the `diagnostics` settings.
+    pub fn diagnostics(&mut self, file: impl AsRef<Path>) -> anyhow::Result<Vec<Diagnostic>> {
+        let params = DocumentDiagnosticParams {
+            text_document: self.document(file.as_ref())?,
+            identifier: None,
+            previous_result_id: None,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let spec = diagnostic::handle(params, String::new(), self.diagnostics.clone());
+        match self.call(spec)? {
+            Some(DocumentDiagnosticReport::Full(report)) => {
+                Ok(report.full_document_diagnostic_report.items)
+            }
+            Some(DocumentDiagnosticReport::Unchanged(_)) | None => Ok(Vec::new()),
+        }
+    }
+
+    /// The edit renaming the symbol at `position` to `new_name`, across the
+    /// project. Nothing is written to disk.
+    pub fn rename(
+        &mut self,
+        file: impl AsRef<Path>,
+        position: Position,
+        new_name: &str,
+    ) -> anyhow::Result<WorkspaceEdit> {
+        let params = RenameParams {
+            text_document_position: self.position(file.as_ref(), position)?,
+            new_name: new_name.to_string(),
+            work_done_progress_params: Default::default(),
+        };
+        let edit: Option<WorkspaceEdit> = self.call(rename::handle(params))?;
+        Ok(edit.unwrap_or_default())
+    }
+
+    /// The edit sorting and pruning the imports of `file`. Nothing is
+    /// written to disk.
+    pub fn organize_imports(&mut self, file: impl AsRef<Path>) -> anyhow::Result<WorkspaceEdit> {
+        let document = self.document(file.as_ref())?;
+        let spec = execute_command::handle(ExecuteCommandParams {
+            command: "TSBOrganizeImports".to_string(),
+            arguments: vec![json!({ "uri": document.uri.as_str() })],
+            work_done_progress_params: Default::default(),
+        })
+        .context("TSBOrganizeImports takes no such argument")?;
+        let edit: Option<WorkspaceEdit> = self.call(spec)?;
+        Ok(edit.unwrap_or_default())
+    }
+
+    /// Stops tsserver; dropping the bridge does the same.
+    pub fn shutdown(mut self) {
+        self.checker.shutdown();
+    }
+
+    fn call<T: DeserializeOwned>(&mut self, spec: RequestSpec) -> anyhow::Result<Option<T>> {
+        let value = self.checker.drive(AdapterResult::Continue(spec))?;
+        serde_json::from_value(value).context("decode the tsserver answer")
+    }
+
+    fn resolve(&self, file: &Path) -> PathBuf {
+        self.root.join(file)
+    }
+
+    fn document(&self, file: &Path) -> anyhow::Result<TextDocumentIdentifier> {
+        Ok(TextDocumentIdentifier::new(uri(&self.resolve(file))?))
+    }
+
+    fn position(
+        &self,
+        file: &Path,
+        position: Position,
+    ) -> anyhow::Result<TextDocumentPositionParams> {
+        Ok(TextDocumentPositionParams::new(
+            self.document(file)?,
+            position,
+        ))
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.checker.shutdown();
+    }
+}
+
+fn uri(path: &Path) -> anyhow::Result<Uri> {
+    file_path_to_uri(&path.to_string_lossy())
+        .with_context(|| format!("{} is not a valid file path", path.display()))
 }
 
-pub fn request_diagnostics() {
-    todo!("Bridge to custom diagnostic request");
+/// The range covering all of `text`.
+fn whole(text: &str) -> crate::types::Range {
+    let last = text.rsplit('\n').next().unwrap_or_default();
+    crate::types::Range {
+        start: crate::types::Position {
+            line: 0,
+            character: 0,
+        },
+        end: crate::types::Position {
+            line: text.matches('\n').count() as u32,
+            character: last.encode_utf16().count() as u32,
+        },
+    }
 }
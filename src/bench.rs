@@ -0,0 +1,362 @@
+//! `ts-bridge bench PROJECT`: times the requests an editor sends most. A
+//! fresh in-process session (the one `ts-bridge replay` uses) opens a source
+//! file of the project, and every cycle edits it, then asks for hover,
+//! completion and pull diagnostics at a different identifier. Each response
+//! is timed from the moment the request is handed to the bridge, so the
+//! numbers cover the bridge and tsserver together; run it against two
+//! TypeScript versions or two builds of ts-bridge to compare them.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, anyhow, bail};
+use crossbeam_channel::RecvTimeoutError;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use serde_json::{Value, json};
+
+use crate::check::{is_source_file, walk};
+use crate::utils::file_path_to_uri;
+
+/// How long one request may take before the benchmark gives up.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+pub const DEFAULT_ITERATIONS: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// The project directory, or a tsconfig.json/jsconfig.json in it.
+    pub project: PathBuf,
+    /// The file to open; defaults to the project's largest source file.
+    pub file: Option<PathBuf>,
+    /// Hover/completion/diagnostics cycles to time.
+    pub iterations: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            project: PathBuf::from("."),
+            file: None,
+            iterations: DEFAULT_ITERATIONS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub file: PathBuf,
+    /// `serverInfo.version` from `initialize`, which names the TypeScript
+    /// version and where it came from.
+    pub server: Option<String>,
+    /// Until the first hover was answered: tsserver start and project load.
+    pub first_response: Duration,
+    /// Response times by LSP method, error responses included.
+    pub latencies: BTreeMap<String, Vec<Duration>>,
+    /// Error responses by LSP method: how many, and the first message.
+    pub errors: BTreeMap<String, (usize, String)>,
+}
+
+impl BenchReport {
+    fn note_error(&mut self, method: &str, response: Response) {
+        if let Some(error) = response.error {
+            self.errors
+                .entry(method.to_string())
+                .or_insert((0, error.message))
+                .0 += 1;
+        }
+    }
+}
+
+/// Runs the benchmark; see the module docs.
+pub fn run(options: &BenchOptions) -> anyhow::Result<BenchReport> {
+    if options.iterations == 0 {
+        bail!("bench needs at least one iteration");
+    }
+    let project = std::fs::canonicalize(&options.project)
+        .with_context(|| format!("resolve {}", options.project.display()))?;
+    let root = if project.is_dir() {
+        project
+    } else {
+        project
+            .parent()
+            .map(Path::to_path_buf)
+            .context("the project has no directory")?
+    };
+    let file = match &options.file {
+        Some(file) => std::fs::canonicalize(root.join(file))
+            .with_context(|| format!("resolve {}", file.display()))?,
+        None => largest_source_file(&root)?,
+    };
+    let text =
+        std::fs::read_to_string(&file).with_context(|| format!("read {}", file.display()))?;
+    let positions = identifier_positions(&text, options.iterations + 1);
+    if positions.is_empty() {
+        bail!("{} has no identifiers to ask about", file.display());
+    }
+    let uri = file_path_to_uri(&file.to_string_lossy())
+        .with_context(|| format!("{} is not a valid file path", file.display()))?;
+    let root_uri = file_path_to_uri(&root.to_string_lossy())
+        .with_context(|| format!("{} is not a valid file path", root.display()))?;
+
+    let (server, client) = Connection::memory();
+    let session = thread::spawn(move || crate::server::run_connection_session(server));
+    let mut bench = Client {
+        connection: client,
+        next_id: 0,
+    };
+    let measured = (|| {
+        let initialized = bench.call(
+            "initialize",
+            json!({
+                "processId": null,
+                "rootUri": root_uri.as_str(),
+                "capabilities": {},
+            }),
+        )?;
+        let mut report = BenchReport {
+            file: file.clone(),
+            server: initialized
+                .pointer("/serverInfo/version")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            ..BenchReport::default()
+        };
+        bench.notify("initialized", json!({}))?;
+        bench.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": uri.as_str(),
+                "languageId": language_id(&file),
+                "version": 0,
+                "text": text,
+            } }),
+        )?;
+        let document = json!({ "uri": uri.as_str() });
+        let (elapsed, response) = bench.request(
+            "textDocument/hover",
+            json!({ "textDocument": document, "position": positions[0].0 }),
+        )?;
+        report.first_response = elapsed;
+        report.note_error("textDocument/hover", response);
+
+        for (cycle, (start, end)) in positions
+            .iter()
+            .cycle()
+            .skip(1)
+            .take(options.iterations)
+            .enumerate()
+        {
+            // A keystroke's worth of change, so diagnostics are recomputed
+            // instead of served from tsserver's cache.
+            let edited = if cycle % 2 == 0 {
+                format!("{text}\n")
+            } else {
+                text.clone()
+            };
+            bench.notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri.as_str(), "version": cycle + 1 },
+                    "contentChanges": [{ "text": edited }],
+                }),
+            )?;
+            for (method, params) in [
+                (
+                    "textDocument/hover",
+                    json!({ "textDocument": document, "position": start }),
+                ),
+                (
+                    "textDocument/completion",
+                    json!({ "textDocument": document, "position": end }),
+                ),
+                (
+                    "textDocument/diagnostic",
+                    json!({ "textDocument": document }),
+                ),
+            ] {
+                let (elapsed, response) = bench.request(method, params)?;
+                report
+                    .latencies
+                    .entry(method.to_string())
+                    .or_default()
+                    .push(elapsed);
+                report.note_error(method, response);
+            }
+        }
+        bench.call("shutdown", Value::Null)?;
+        Ok(report)
+    })();
+    // The session ends on `shutdown`; `exit` may find nobody listening.
+    let _ = bench.notify("exit", Value::Null);
+    drop(bench);
+    session
+        .join()
+        .map_err(|_| anyhow!("the benchmarked session panicked"))??;
+    measured
+}
+
+/// The timings table, preceded by what was measured.
+pub fn render(report: &BenchReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "file: {}", report.file.display());
+    if let Some(server) = &report.server {
+        let _ = writeln!(out, "ts-bridge {server}");
+    }
+    let _ = writeln!(
+        out,
+        "first response: {:.1} ms",
+        report.first_response.as_secs_f64() * 1000.0
+    );
+    out.push_str(&crate::replay::latency_table(&report.latencies));
+    for (method, (count, message)) in &report.errors {
+        let _ = writeln!(out, "{method}: {count} error response(s), first: {message}");
+    }
+    out
+}
+
+/// The LSP client side of the benchmarked session.
+struct Client {
+    connection: Connection,
+    next_id: i32,
+}
+
+impl Client {
+    fn notify(&self, method: &str, params: Value) -> anyhow::Result<()> {
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                method.to_string(),
+                params,
+            )))
+            .map_err(|_| anyhow!("the session ended early"))
+    }
+
+    /// A request the benchmark cannot go on without.
+    fn call(&mut self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let (_, response) = self.request(method, params)?;
+        if let Some(error) = response.error {
+            bail!("{method} failed: {}", error.message);
+        }
+        Ok(response.result.unwrap_or(Value::Null))
+    }
+
+    /// Sends a request and waits for its response, answering the requests
+    /// ts-bridge sends in the meantime.
+    fn request(&mut self, method: &str, params: Value) -> anyhow::Result<(Duration, Response)> {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let sent = Instant::now();
+        self.connection
+            .sender
+            .send(Message::Request(Request::new(
+                id.clone(),
+                method.to_string(),
+                params,
+            )))
+            .map_err(|_| anyhow!("the session ended early"))?;
+        let deadline = sent + RESPONSE_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let message = match self.connection.receiver.recv_timeout(timeout) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => bail!(
+                    "{method} was not answered within {}s",
+                    RESPONSE_TIMEOUT.as_secs()
+                ),
+                Err(RecvTimeoutError::Disconnected) => {
+                    bail!("the session ended while waiting for {method}")
+                }
+            };
+            match message {
+                Message::Response(response) if response.id == id => {
+                    return Ok((sent.elapsed(), response));
+                }
+                Message::Request(request) => {
+                    let _ = self
+                        .connection
+                        .sender
+                        .send(Message::Response(Response::new_ok(request.id, Value::Null)));
+                }
+                Message::Response(_) | Message::Notification(_) => {}
+            }
+        }
+    }
+}
+
+fn largest_source_file(root: &Path) -> anyhow::Result<PathBuf> {
+    walk(root)?
+        .into_iter()
+        .filter(|path| is_source_file(path))
+        .filter_map(|path| Some((std::fs::metadata(&path).ok()?.len(), path)))
+        .max_by(|(a_len, a), (b_len, b)| a_len.cmp(b_len).then(b.cmp(a)))
+        .map(|(_, path)| path)
+        .with_context(|| format!("{} holds no TypeScript or JavaScript files", root.display()))
+}
+
+fn language_id(file: &Path) -> &'static str {
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("tsx") => "typescriptreact",
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("jsx") => "javascriptreact",
+        _ => "typescript",
+    }
+}
+
+/// Up to `count` identifiers spread over `text`, each as the LSP positions
+/// of its start and end (UTF-16 columns).
+fn identifier_positions(text: &str, count: usize) -> Vec<(Value, Value)> {
+    let mut identifiers = Vec::new();
+    for (line, content) in text.lines().enumerate() {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with("//") || trimmed.starts_with('*') || trimmed.starts_with("/*") {
+            continue;
+        }
+        let mut column = 0u32;
+        let mut start = None;
+        for ch in content.chars().chain(std::iter::once(' ')) {
+            let word = ch.is_alphanumeric() || ch == '_' || ch == '$';
+            match (start, word) {
+                (None, true) if !ch.is_ascii_digit() => start = Some(column),
+                (Some(begin), false) => {
+                    let position = |character: u32| json!({ "line": line, "character": character });
+                    identifiers.push((position(begin), position(column)));
+                    start = None;
+                }
+                _ => {}
+            }
+            column += ch.len_utf16() as u32;
+        }
+    }
+    if identifiers.len() <= count {
+        return identifiers;
+    }
+    (0..count)
+        .map(|index| identifiers[index * identifiers.len() / count].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifiers_are_spread_over_the_file_and_skip_comments() {
+        let text = "// header comment\nconst value = 1;\nexport function twice(n: number) {\n  return n * 2;\n}\n";
+        let all = identifier_positions(text, 100);
+        assert_eq!(
+            all[0],
+            (
+                json!({ "line": 1, "character": 0 }),
+                json!({ "line": 1, "character": 5 })
+            )
+        );
+        assert_eq!(all.len(), 9);
+        let spread = identifier_positions(text, 3);
+        assert_eq!(spread.len(), 3);
+        assert_eq!(spread[0], all[0]);
+        assert_eq!(spread[1], all[3]);
+        assert_eq!(spread[2], all[6]);
+    }
+}
@@ -0,0 +1,739 @@
+//! `ts-bridge check`: the editor's diagnostics pipeline without an editor.
+//! tsserver is started for the project, every file is checked with
+//! `syntacticDiagnosticsSync` and `semanticDiagnosticsSync` through the same
+//! walk `workspace/diagnostic` uses, and the results are printed for a
+//! person, as JSON, or as GitHub Actions annotations. The settings file and
+//! `TS_BRIDGE_*` overrides apply exactly as they do in the editor, so
+//! `diagnostics.ignore_codes` and `diagnostics.severity_overrides` hide and
+//! re-grade the same diagnostics in CI.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, anyhow, bail};
+use globset::GlobBuilder;
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, NumberOrString, WorkspaceDiagnosticParams,
+    WorkspaceDiagnosticReport, WorkspaceDocumentDiagnosticReport,
+};
+use serde_json::{Value, json};
+
+use crate::config::Config;
+use crate::protocol::AdapterResult;
+use crate::protocol::workspace::diagnostic::{self, ReportCollector};
+use crate::provider::Provider;
+use crate::rpc::{HealthEvent, Priority, Route, Service};
+use crate::server::{tsserver_configure_args, workspace_config};
+use crate::utils::uri_to_file_path;
+use walkdir::WalkDir;
+
+/// How long a single tsserver request may take before the check gives up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Files tried when looking for one that belongs to the requested tsconfig.
+const MAX_ANCHOR_CANDIDATES: usize = 20;
+const SOURCE_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// `path:line:col - error TS2322: message`, like `tsc --pretty false`.
+    #[default]
+    Human,
+    Json,
+    /// `::error file=...` workflow commands that annotate the pull request.
+    Github,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "github" => Ok(Self::Github),
+            other => Err(anyhow!(
+                "unknown format {other:?}; expected human, json or github"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    /// Files, directories or globs (`*`, `?`, `**`, `{a,b}`) relative to the
+    /// working directory. Empty means "check the project".
+    pub targets: Vec<String>,
+    /// A tsconfig.json/jsconfig.json, or the directory holding one. Defaults
+    /// to the one in the working directory.
+    pub project: Option<PathBuf>,
+}
+
+/// The diagnostics of one checked file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiagnostics {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Boots tsserver in `cwd`, checks what `options` selects and returns every
+/// file that was checked, with or without diagnostics.
+pub fn run(options: &CheckOptions, cwd: &Path) -> anyhow::Result<Vec<FileDiagnostics>> {
+    if !options.targets.is_empty() && options.project.is_some() {
+        bail!("pass either --project or files to check, not both");
+    }
+    let cwd = std::fs::canonicalize(cwd).with_context(|| format!("resolve {}", cwd.display()))?;
+    let (root, files, tsconfig) = if options.targets.is_empty() {
+        let tsconfig = find_tsconfig(options.project.as_deref(), &cwd)?;
+        let root = tsconfig
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| cwd.clone());
+        (root, Vec::new(), Some(tsconfig))
+    } else {
+        let mut files = BTreeSet::new();
+        for target in &options.targets {
+            files.extend(expand_target(target, &cwd)?);
+        }
+        (cwd, files.into_iter().collect(), None)
+    };
+
+    let (mut config, problems) = workspace_config(&root);
+    for problem in problems {
+        eprintln!("warning: {problem}");
+    }
+    // One server answers both kinds of diagnostics; a second would only
+    // double the memory for a batch run.
+    config.plugin_mut().separate_diagnostic_server = false;
+    let settings = config.plugin().diagnostics.clone();
+    let mut checker = Checker::start(config, &root)?;
+
+    let report = match tsconfig {
+        Some(tsconfig) => {
+            let anchor = checker.find_anchor(&tsconfig)?;
+            let params = WorkspaceDiagnosticParams {
+                identifier: None,
+                previous_result_ids: Vec::new(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let spec = diagnostic::handle(params, &anchor, 0, HashMap::new(), settings);
+            checker.drive_walk(AdapterResult::Continue(spec))?
+        }
+        None => {
+            let files: Vec<String> = files
+                .iter()
+                .map(|file| file.to_string_lossy().into_owned())
+                .collect();
+            checker.open(&files)?;
+            checker.drive_walk(diagnostic::walk_files(files, settings)?)?
+        }
+    };
+    checker.shutdown();
+
+    let report: WorkspaceDiagnosticReport =
+        serde_json::from_value(report).context("decode diagnostics report")?;
+    Ok(report
+        .items
+        .into_iter()
+        .filter_map(|item| match item {
+            WorkspaceDocumentDiagnosticReport::Full(full) => Some(FileDiagnostics {
+                path: PathBuf::from(uri_to_file_path(full.uri.as_str())?),
+                diagnostics: full.full_document_diagnostic_report.items,
+            }),
+            WorkspaceDocumentDiagnosticReport::Unchanged(_) => None,
+        })
+        .collect())
+}
+
+/// Whether any diagnostic is an error, which fails the check.
+pub fn has_errors(files: &[FileDiagnostics]) -> bool {
+    files
+        .iter()
+        .flat_map(|file| &file.diagnostics)
+        .any(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+}
+
+/// Formats `files` for output; paths are shown relative to `cwd` when they
+/// are inside it.
+pub fn render(format: OutputFormat, files: &[FileDiagnostics], cwd: &Path) -> String {
+    let entries = files.iter().flat_map(|file| {
+        let path = display_path(&file.path, cwd);
+        file.diagnostics
+            .iter()
+            .map(move |diagnostic| (path.clone(), diagnostic))
+    });
+    let mut out = String::new();
+    match format {
+        OutputFormat::Human => {
+            let mut errors = 0;
+            let mut warnings = 0;
+            let mut failing_files = BTreeSet::new();
+            for (path, diagnostic) in entries {
+                let severity = severity_label(diagnostic.severity);
+                match severity {
+                    "error" => errors += 1,
+                    "warning" => warnings += 1,
+                    _ => {}
+                }
+                failing_files.insert(path.clone());
+                let _ = writeln!(
+                    out,
+                    "{path}:{}:{} - {severity} {}: {}",
+                    diagnostic.range.start.line + 1,
+                    diagnostic.range.start.character + 1,
+                    code_label(diagnostic),
+                    diagnostic.message
+                );
+            }
+            if failing_files.is_empty() {
+                let _ = writeln!(out, "No problems found in {} file(s).", files.len());
+            } else {
+                let _ = writeln!(
+                    out,
+                    "\nFound {errors} error(s) and {warnings} warning(s) in {} of {} file(s).",
+                    failing_files.len(),
+                    files.len()
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let entries: Vec<Value> = entries
+                .map(|(path, diagnostic)| {
+                    json!({
+                        "file": path,
+                        "line": diagnostic.range.start.line + 1,
+                        "column": diagnostic.range.start.character + 1,
+                        "endLine": diagnostic.range.end.line + 1,
+                        "endColumn": diagnostic.range.end.character + 1,
+                        "severity": severity_label(diagnostic.severity),
+                        "code": diagnostic.code,
+                        "message": diagnostic.message,
+                    })
+                })
+                .collect();
+            out = serde_json::to_string_pretty(&entries).unwrap_or_default();
+            out.push('\n');
+        }
+        OutputFormat::Github => {
+            for (path, diagnostic) in entries {
+                let command = match diagnostic.severity {
+                    Some(DiagnosticSeverity::ERROR) | None => "error",
+                    Some(DiagnosticSeverity::WARNING) => "warning",
+                    _ => "notice",
+                };
+                let _ = writeln!(
+                    out,
+                    "::{command} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+                    escape_property(&path),
+                    diagnostic.range.start.line + 1,
+                    diagnostic.range.start.character + 1,
+                    diagnostic.range.end.line + 1,
+                    diagnostic.range.end.character + 1,
+                    escape_property(&code_label(diagnostic)),
+                    escape_data(&diagnostic.message)
+                );
+            }
+        }
+    }
+    out
+}
+
+/// A tsserver instance driven one request at a time; `ts-bridge codemod`
+/// drives its edits through it too.
+pub(crate) struct Checker {
+    service: Service,
+    root: PathBuf,
+}
+
+impl Checker {
+    pub(crate) fn start(config: Config, root: &Path) -> anyhow::Result<Self> {
+        let configure = tsserver_configure_args(&config, false);
+        let inferred = config.plugin().inferred_project_options.compiler_options();
+        let mut service = Service::new(config, Provider::new(root));
+        service.start().context("start tsserver")?;
+        let mut checker = Self {
+            service,
+            root: root.to_path_buf(),
+        };
+        checker.request(json!({ "command": "configure", "arguments": configure }))?;
+        checker.request(json!({
+            "command": "compilerOptionsForInferredProjects",
+            "arguments": { "options": inferred },
+        }))?;
+        Ok(checker)
+    }
+
+    pub(crate) fn open(&mut self, files: &[String]) -> anyhow::Result<()> {
+        self.update_open(
+            json!(
+                files
+                    .iter()
+                    .map(|file| json!({ "file": file }))
+                    .collect::<Vec<_>>()
+            ),
+            json!([]),
+        )
+    }
+
+    fn update_open(&mut self, open: Value, closed: Value) -> anyhow::Result<()> {
+        let payload = json!({
+            "command": "updateOpen",
+            "arguments": {
+                "projectRootPath": self.root.to_string_lossy(),
+                "openFiles": open,
+                "changedFiles": [],
+                "closedFiles": closed,
+            }
+        });
+        self.request(payload).map(|_| ())
+    }
+
+    /// Opens files next to `tsconfig` until one lands in its project, so
+    /// `projectInfo` lists that project's files.
+    fn find_anchor(&mut self, tsconfig: &Path) -> anyhow::Result<String> {
+        let dir = tsconfig.parent().unwrap_or(&self.root).to_path_buf();
+        let candidates = walk(&dir)?
+            .into_iter()
+            .filter(|path| is_source_file(path))
+            .take(MAX_ANCHOR_CANDIDATES);
+        for candidate in candidates {
+            let file = candidate.to_string_lossy().into_owned();
+            self.open(std::slice::from_ref(&file))?;
+            let info = self.request(json!({
+                "command": "projectInfo",
+                "arguments": { "file": file, "needFileNameList": false },
+            }))?;
+            let config_file = info
+                .pointer("/body/configFileName")
+                .and_then(Value::as_str)
+                .map(PathBuf::from);
+            if config_file.as_deref() == Some(tsconfig) {
+                return Ok(file);
+            }
+            self.update_open(json!([]), json!([file]))?;
+        }
+        bail!(
+            "no source file near {} belongs to its project; pass the files to check instead",
+            tsconfig.display()
+        )
+    }
+
+    /// Sends each request the walk chains until it produces its report.
+    pub(crate) fn drive(&mut self, result: AdapterResult) -> anyhow::Result<Value> {
+        self.drive_with(result, |_| {})
+    }
+
+    /// Like [`Self::drive`], folding the per-file reports a diagnostics walk
+    /// emits along the way into its final report.
+    fn drive_walk(&mut self, result: AdapterResult) -> anyhow::Result<Value> {
+        let mut collector = ReportCollector::default();
+        let last = self.drive_with(result, |partial| collector.push(partial))?;
+        Ok(collector.finish(last))
+    }
+
+    fn drive_with(
+        &mut self,
+        mut result: AdapterResult,
+        mut on_partial: impl FnMut(Value),
+    ) -> anyhow::Result<Value> {
+        loop {
+            let spec = match result {
+                AdapterResult::Ready(value) => return Ok(value),
+                AdapterResult::Continue(spec) => spec,
+                AdapterResult::Partial { value, next } => {
+                    on_partial(value);
+                    next
+                }
+            };
+            let response = self.request(spec.payload)?;
+            let adapter = spec
+                .on_response
+                .context("tsserver request without a response adapter")?;
+            result = adapter(&response, spec.response_context.as_ref())?;
+        }
+    }
+
+    pub(crate) fn shutdown(&mut self) {
+        self.service.shutdown();
+    }
+
+    pub(crate) fn request(&mut self, payload: Value) -> anyhow::Result<Value> {
+        let command = payload
+            .get("command")
+            .and_then(Value::as_str)
+            .unwrap_or("request")
+            .to_string();
+        let receipt = self
+            .service
+            .dispatch_request(Route::Syntax, payload, Priority::Normal)?
+            .into_iter()
+            .next()
+            .with_context(|| format!("tsserver did not take {command}"))?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            for event in self.service.poll_responses() {
+                let payload = event.payload;
+                if event.server != receipt.server
+                    || payload.get("type").and_then(Value::as_str) != Some("response")
+                    || payload.get("request_seq").and_then(Value::as_u64) != Some(receipt.seq)
+                {
+                    continue;
+                }
+                if payload.get("success").and_then(Value::as_bool) == Some(false) {
+                    let message = payload
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("no message");
+                    bail!("tsserver {command} failed: {message}");
+                }
+                return Ok(payload);
+            }
+            for event in self.service.poll_health() {
+                if let HealthEvent::Exited { reason, .. } = event {
+                    bail!("tsserver {reason} while answering {command}");
+                }
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "tsserver did not answer {command} within {}s",
+                    REQUEST_TIMEOUT.as_secs()
+                );
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// `--project` as given (a config file or a directory holding one), or the
+/// working directory's tsconfig.json/jsconfig.json.
+fn find_tsconfig(project: Option<&Path>, cwd: &Path) -> anyhow::Result<PathBuf> {
+    let path = cwd.join(project.unwrap_or(Path::new(".")));
+    let found = if path.is_dir() {
+        ["tsconfig.json", "jsconfig.json"]
+            .iter()
+            .map(|name| path.join(name))
+            .find(|candidate| candidate.is_file())
+    } else {
+        path.is_file().then(|| path.clone())
+    };
+    let found = found.with_context(|| {
+        format!(
+            "no tsconfig.json or jsconfig.json at {}; pass --project or files to check",
+            path.display()
+        )
+    })?;
+    std::fs::canonicalize(&found).with_context(|| format!("resolve {}", found.display()))
+}
+
+/// The files `target` names: itself, or the sources under a directory or
+/// matching a glob. A target that matches nothing is an error so a typo
+/// cannot make CI pass by checking nothing.
+pub(crate) fn expand_target(target: &str, cwd: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !is_glob(target) {
+        let path = cwd.join(target);
+        if path.is_file() {
+            return Ok(vec![path]);
+        }
+        if path.is_dir() {
+            let files: Vec<PathBuf> = walk(&path)?
+                .into_iter()
+                .filter(|path| is_source_file(path))
+                .collect();
+            if files.is_empty() {
+                bail!("{target} contains no TypeScript or JavaScript files");
+            }
+            return Ok(files);
+        }
+        bail!("{target} does not exist");
+    }
+
+    // Walk from the deepest directory the glob spells out literally and
+    // match the rest against paths relative to it.
+    let components: Vec<Component> = Path::new(target).components().collect();
+    let literal = components
+        .iter()
+        .take_while(|component| !is_glob(&component.as_os_str().to_string_lossy()))
+        .count();
+    let base = cwd.join(components[..literal].iter().collect::<PathBuf>());
+    let pattern = components[literal..]
+        .iter()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let matcher = GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("{target} is not a valid glob"))?
+        .compile_matcher();
+    let files: Vec<PathBuf> = if base.is_dir() {
+        walk(&base)?
+            .into_iter()
+            .filter(|path| {
+                is_source_file(path)
+                    && path
+                        .strip_prefix(&base)
+                        .is_ok_and(|relative| matcher.is_match(relative))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if files.is_empty() {
+        bail!("{target} matched no files");
+    }
+    Ok(files)
+}
+
+fn is_glob(text: &str) -> bool {
+    text.contains(['*', '?', '{', '['])
+}
+
+/// Every file under `dir`, sorted, leaving out `node_modules` and hidden
+/// directories. Symlinks are followed, except ones looping back to an
+/// ancestor or pointing nowhere; a file reachable through several links is
+/// listed once.
+pub(crate) fn walk(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let entries = WalkDir::new(dir)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || (name != "node_modules" && !name.starts_with('.'))
+        });
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) if err.loop_ancestor().is_some() => continue,
+            Err(err) if err.path().is_some_and(Path::is_symlink) => continue,
+            Err(err) => return Err(err).with_context(|| format!("read {}", dir.display())),
+        };
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.into_path();
+        let real = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if seen.insert(real) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub(crate) fn is_source_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let declaration = [".d.ts", ".d.mts", ".d.cts"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix));
+    !declaration
+        && path
+            .extension()
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&&*extension.to_string_lossy()))
+}
+
+pub(crate) fn display_path(path: &Path, cwd: &Path) -> String {
+    path.strip_prefix(cwd)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn severity_label(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    }
+}
+
+fn code_label(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::Number(code)) => format!("TS{code}"),
+        Some(NumberOrString::String(code)) => code.clone(),
+        None => "TS".to_string(),
+    }
+}
+
+/// Workflow command data: `%`, CR and LF must be percent-encoded.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Workflow command properties additionally encode `:` and `,`.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, Range};
+
+    fn diagnostic(severity: DiagnosticSeverity, code: i32, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(2, 6), Position::new(2, 9)),
+            severity: Some(severity),
+            code: Some(NumberOrString::Number(code)),
+            message: message.to_string(),
+            ..Diagnostic::default()
+        }
+    }
+
+    fn files() -> Vec<FileDiagnostics> {
+        vec![
+            FileDiagnostics {
+                path: PathBuf::from("/work/src/a.ts"),
+                diagnostics: vec![diagnostic(
+                    DiagnosticSeverity::ERROR,
+                    2322,
+                    "Type 'string' is not assignable to type 'number'.",
+                )],
+            },
+            FileDiagnostics {
+                path: PathBuf::from("/work/src/b.ts"),
+                diagnostics: vec![diagnostic(
+                    DiagnosticSeverity::WARNING,
+                    6133,
+                    "'x' is declared\nbut never read: 100%",
+                )],
+            },
+            FileDiagnostics {
+                path: PathBuf::from("/work/src/c.ts"),
+                diagnostics: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn targets_expand_against_the_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "src/a.ts",
+            "src/nested/b.tsx",
+            "src/types.d.ts",
+            "src/data.json",
+            "node_modules/x/c.ts",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let names = |target: &str| -> Vec<String> {
+            expand_target(target, dir.path())
+                .unwrap()
+                .iter()
+                .map(|path| display_path(path, dir.path()))
+                .collect()
+        };
+
+        assert_eq!(names("src/**/*.ts"), ["src/a.ts"]);
+        assert_eq!(names("src/**"), ["src/a.ts", "src/nested/b.tsx"]);
+        assert_eq!(names("src"), ["src/a.ts", "src/nested/b.tsx"]);
+        assert_eq!(names("**/*.tsx"), ["src/nested/b.tsx"]);
+        assert_eq!(names("src/*.ts"), ["src/a.ts"]);
+        assert_eq!(names("src/?.ts"), ["src/a.ts"]);
+        assert_eq!(names("src/**/*.{ts,tsx}"), ["src/a.ts", "src/nested/b.tsx"]);
+        assert_eq!(
+            names("{src,src/nested}/*.{ts,tsx}"),
+            ["src/a.ts", "src/nested/b.tsx"]
+        );
+        assert_eq!(
+            names("src/{a,*}.{ts,json}"),
+            ["src/a.ts"],
+            "overlapping braces list a file once"
+        );
+        assert!(expand_target("lib/**/*.ts", dir.path()).is_err());
+        assert!(expand_target("src/*.json", dir.path()).is_err());
+        assert!(expand_target("missing.ts", dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_follows_symlinks_once() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let shared = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/a.ts"), "").unwrap();
+        std::fs::write(shared.path().join("b.ts"), "").unwrap();
+        symlink(shared.path(), dir.path().join("src/linked")).unwrap();
+        symlink(dir.path().join("src/a.ts"), dir.path().join("src/alias.ts")).unwrap();
+        symlink(dir.path().join("src"), dir.path().join("src/loop")).unwrap();
+        symlink(dir.path().join("gone"), dir.path().join("src/dangling.ts")).unwrap();
+
+        let names: Vec<String> = walk(dir.path())
+            .unwrap()
+            .iter()
+            .map(|path| display_path(path, dir.path()))
+            .collect();
+        assert_eq!(names, ["src/a.ts", "src/linked/b.ts"]);
+        assert_eq!(
+            expand_target("src/**/*.ts", dir.path())
+                .unwrap()
+                .iter()
+                .map(|path| display_path(path, dir.path()))
+                .collect::<Vec<_>>(),
+            ["src/a.ts", "src/linked/b.ts"]
+        );
+    }
+
+    #[test]
+    fn human_output_lists_diagnostics_and_a_summary() {
+        let out = render(OutputFormat::Human, &files(), Path::new("/work"));
+        assert!(out.starts_with(
+            "src/a.ts:3:7 - error TS2322: Type 'string' is not assignable to type 'number'.\n"
+        ));
+        assert!(out.contains("src/b.ts:3:7 - warning TS6133:"));
+        assert!(out.ends_with("Found 1 error(s) and 1 warning(s) in 2 of 3 file(s).\n"));
+        assert!(has_errors(&files()));
+        assert!(!has_errors(&files()[1..]));
+    }
+
+    #[test]
+    fn json_output_has_one_entry_per_diagnostic() {
+        let out = render(OutputFormat::Json, &files(), Path::new("/work"));
+        let entries: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            entries[0],
+            json!({
+                "file": "src/a.ts",
+                "line": 3,
+                "column": 7,
+                "endLine": 3,
+                "endColumn": 10,
+                "severity": "error",
+                "code": 2322,
+                "message": "Type 'string' is not assignable to type 'number'.",
+            })
+        );
+        assert_eq!(entries.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn github_output_escapes_workflow_commands() {
+        let out = render(OutputFormat::Github, &files(), Path::new("/work"));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            "::error file=src/a.ts,line=3,col=7,endLine=3,endColumn=10,title=TS2322::Type 'string' is not assignable to type 'number'."
+        );
+        assert_eq!(
+            lines[1],
+            "::warning file=src/b.ts,line=3,col=7,endLine=3,endColumn=10,title=TS6133::'x' is declared%0Abut never read: 100%25"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! `ts-bridge codemod`: editor refactors as scriptable commands. Each codemod
+//! starts the project's tsserver, runs the same `workspace/executeCommand`
+//! request the editor sends (`TSBOrganizeImports`, `TSBRenameFile`) and
+//! writes the resulting workspace edit to disk instead of handing it to a
+//! client. Settings files and `TS_BRIDGE_*` overrides apply, so
+//! `format_options`/`preferences` shape the edits as they do in the editor.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use lsp_types::{ExecuteCommandParams, Position, TextEdit, Uri, WorkspaceEdit};
+use serde_json::{Value, json};
+
+use crate::check::{Checker, display_path, expand_target, is_source_file, walk};
+use crate::protocol::AdapterResult;
+use crate::protocol::workspace::execute_command;
+use crate::server::workspace_config;
+use crate::utils::{file_path_to_uri, uri_to_file_path};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Codemod {
+    /// Organizes the imports of every file the targets select: files,
+    /// directories or globs relative to the working directory.
+    OrganizeImports { targets: Vec<String> },
+    /// Moves a file or directory and rewrites the imports that point into it.
+    RenameFile { old: PathBuf, new: PathBuf },
+}
+
+/// Runs `codemod` in `cwd` and returns the files it rewrote, sorted.
+pub fn run(codemod: &Codemod, cwd: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let cwd = std::fs::canonicalize(cwd).with_context(|| format!("resolve {}", cwd.display()))?;
+    let (mut config, problems) = workspace_config(&cwd);
+    for problem in problems {
+        eprintln!("warning: {problem}");
+    }
+    config.plugin_mut().separate_diagnostic_server = false;
+
+    match codemod {
+        Codemod::OrganizeImports { targets } => {
+            if targets.is_empty() {
+                bail!("organize-imports requires FILE, DIR or GLOB");
+            }
+            let mut files = BTreeSet::new();
+            for target in targets {
+                files.extend(expand_target(target, &cwd)?);
+            }
+            let files: Vec<String> = files
+                .iter()
+                .map(|file| file.to_string_lossy().into_owned())
+                .collect();
+            let mut checker = Checker::start(config, &cwd)?;
+            checker.open(&files)?;
+            let mut changes = Vec::new();
+            for file in &files {
+                let uri = file_path_to_uri(file)
+                    .with_context(|| format!("{file} is not a valid file path"))?;
+                let edit = command(
+                    &mut checker,
+                    "TSBOrganizeImports",
+                    json!({ "uri": uri.as_str() }),
+                )?;
+                changes.extend(edit.changes.unwrap_or_default());
+            }
+            checker.shutdown();
+            apply(changes)
+        }
+        Codemod::RenameFile { old, new } => {
+            let old = std::fs::canonicalize(cwd.join(old))
+                .with_context(|| format!("resolve {}", old.display()))?;
+            let new = cwd.join(new);
+            if new.exists() {
+                bail!("{} already exists", new.display());
+            }
+            // tsserver answers for the project of an open file; any source
+            // file being moved loads it.
+            let anchor = if old.is_dir() {
+                walk(&old)?
+                    .into_iter()
+                    .find(|path| is_source_file(path))
+                    .with_context(|| {
+                        format!(
+                            "{} contains no TypeScript or JavaScript files",
+                            old.display()
+                        )
+                    })?
+            } else {
+                old.clone()
+            };
+            let mut checker = Checker::start(config, &cwd)?;
+            checker.open(&[anchor.to_string_lossy().into_owned()])?;
+            let edit = command(
+                &mut checker,
+                "TSBRenameFile",
+                json!({
+                    "oldUri": old.to_string_lossy(),
+                    "newUri": new.to_string_lossy(),
+                }),
+            )?;
+            checker.shutdown();
+            // The edits name the moved files by their old paths.
+            let mut changed = apply(edit.changes.unwrap_or_default())?;
+            if let Some(parent) = new.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("create {}", parent.display()))?;
+            }
+            std::fs::rename(&old, &new)
+                .with_context(|| format!("move {} to {}", old.display(), new.display()))?;
+            for path in &mut changed {
+                if let Ok(rest) = path.strip_prefix(&old) {
+                    *path = if rest.as_os_str().is_empty() {
+                        new.clone()
+                    } else {
+                        new.join(rest)
+                    };
+                }
+            }
+            changed.sort();
+            Ok(changed)
+        }
+    }
+}
+
+/// One line per rewritten file, then a summary.
+pub fn render(codemod: &Codemod, changed: &[PathBuf], cwd: &Path) -> String {
+    let cwd = std::fs::canonicalize(cwd).unwrap_or_else(|_| cwd.to_path_buf());
+    let mut out: String = changed
+        .iter()
+        .map(|path| format!("{}\n", display_path(path, &cwd)))
+        .collect();
+    out.push_str(&match codemod {
+        Codemod::OrganizeImports { .. } => {
+            format!("Organized imports in {} file(s).\n", changed.len())
+        }
+        Codemod::RenameFile { old, new } => format!(
+            "Renamed {} to {}; rewrote {} file(s).\n",
+            old.display(),
+            new.display(),
+            changed.len()
+        ),
+    });
+    out
+}
+
+/// Sends a `workspace/executeCommand` command and decodes its edit.
+fn command(checker: &mut Checker, name: &str, argument: Value) -> anyhow::Result<WorkspaceEdit> {
+    let spec = execute_command::handle(ExecuteCommandParams {
+        command: name.to_string(),
+        arguments: vec![argument],
+        work_done_progress_params: Default::default(),
+    })
+    .with_context(|| format!("{name} takes no such argument"))?;
+    let value = checker.drive(AdapterResult::Continue(spec))?;
+    serde_json::from_value(value).with_context(|| format!("decode the {name} edit"))
+}
+
+/// Writes the edits to disk and returns the files whose text changed.
+fn apply(changes: impl IntoIterator<Item = (Uri, Vec<TextEdit>)>) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files: BTreeMap<PathBuf, Vec<TextEdit>> = BTreeMap::new();
+    for (uri, mut edits) in changes {
+        let path = uri_to_file_path(uri.as_str())
+            .with_context(|| format!("{} is not a file", uri.as_str()))?;
+        files
+            .entry(PathBuf::from(path))
+            .or_default()
+            .append(&mut edits);
+    }
+    let mut changed = Vec::new();
+    for (path, edits) in files {
+        let text =
+            std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        let edited = apply_text_edits(&text, edits);
+        if edited != text {
+            std::fs::write(&path, edited).with_context(|| format!("write {}", path.display()))?;
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
+
+/// Applies edits whose ranges all refer to `text`, as a client applies a
+/// `TextEdit[]`: inserts at the same position keep their order.
+fn apply_text_edits(text: &str, edits: Vec<TextEdit>) -> String {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+    let offset = |position: Position| {
+        let Some(&start) = line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let line = &text[start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let mut units = 0;
+        for (index, ch) in line.char_indices() {
+            if units >= position.character {
+                return start + index;
+            }
+            units += ch.len_utf16() as u32;
+        }
+        start + line.len()
+    };
+    let mut spans: Vec<(usize, usize, String)> = edits
+        .into_iter()
+        .map(|edit| {
+            let start = offset(edit.range.start);
+            (start, offset(edit.range.end).max(start), edit.new_text)
+        })
+        .collect();
+    // A stable sort keeps same-position inserts in array order.
+    spans.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (start, end, new_text) in spans {
+        if start > cursor {
+            out.push_str(&text[cursor..start]);
+        }
+        out.push_str(&new_text);
+        cursor = cursor.max(end);
+    }
+    out.push_str(&text[cursor.min(text.len())..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextEdit {
+        TextEdit {
+            range: lsp_types::Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            },
+            new_text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn edits_apply_against_the_original_text() {
+        let text = "import { b } from \"./b\";\nimport { a } from \"./a\";\nuse(a, b);\n";
+        let edits = vec![
+            edit((0, 0), (2, 0), ""),
+            edit((0, 0), (0, 0), "import { a } from \"./a\";\n"),
+            edit((0, 0), (0, 0), "import { b } from \"./b\";\n"),
+            edit((2, 4), (2, 5), "x"),
+        ];
+        assert_eq!(
+            apply_text_edits(text, edits),
+            "import { a } from \"./a\";\nimport { b } from \"./b\";\nuse(x, b);\n"
+        );
+    }
+}
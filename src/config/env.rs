@@ -0,0 +1,145 @@
+//! Settings taken from the environment, for containers and CI where editing
+//! client configuration is awkward: `TS_BRIDGE_SETTINGS` holds a JSON blob in
+//! the LSP settings shape, and a few targeted variables set single options on
+//! top of it.
+
+use serde_json::{Map, Value, json};
+
+/// A JSON object applied like `initializationOptions`.
+pub const SETTINGS_ENV: &str = "TS_BRIDGE_SETTINGS";
+
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    Number,
+    Bool,
+}
+
+/// Variable, settings path, value type.
+const OVERRIDES: &[(&str, &[&str], Kind)] = &[
+    (
+        "TS_BRIDGE_LOG_DIR",
+        &["tsserver", "log_directory"],
+        Kind::String,
+    ),
+    (
+        "TS_BRIDGE_LOG_VERBOSITY",
+        &["tsserver", "log_verbosity"],
+        Kind::String,
+    ),
+    (
+        "TS_BRIDGE_MAX_OLD_SPACE_SIZE",
+        &["tsserver", "max_old_space_size"],
+        Kind::Number,
+    ),
+    (
+        "TS_BRIDGE_NODE_PATH",
+        &["tsserver", "node_path"],
+        Kind::String,
+    ),
+    ("TS_BRIDGE_TSDK", &["tsserver", "tsdk"], Kind::String),
+    ("TS_BRIDGE_LOCALE", &["tsserver", "locale"], Kind::String),
+    (
+        "TS_BRIDGE_NPM_LOCATION",
+        &["tsserver", "npm_location"],
+        Kind::String,
+    ),
+    (
+        "TS_BRIDGE_DISABLE_AUTOMATIC_TYPING_ACQUISITION",
+        &["tsserver", "disable_automatic_typing_acquisition"],
+        Kind::Bool,
+    ),
+    (
+        "TS_BRIDGE_SEPARATE_DIAGNOSTIC_SERVER",
+        &["separate_diagnostic_server"],
+        Kind::Bool,
+    ),
+    (
+        "TS_BRIDGE_REQUEST_TIMEOUT_MS",
+        &["request_timeout_ms"],
+        Kind::Number,
+    ),
+];
+
+/// Settings payloads from the environment, in the order they apply.
+/// Malformed values are logged and skipped.
+pub fn overrides(lookup: impl Fn(&str) -> Option<String>) -> Vec<Value> {
+    let mut payloads = Vec::new();
+    if let Some(blob) = lookup(SETTINGS_ENV).filter(|blob| !blob.trim().is_empty()) {
+        match serde_json::from_str::<Value>(&blob) {
+            Ok(settings) if settings.is_object() => payloads.push(settings),
+            Ok(_) => log::warn!("{SETTINGS_ENV} must be a JSON object; ignoring it"),
+            Err(err) => log::warn!("{SETTINGS_ENV} is not valid JSON ({err}); ignoring it"),
+        }
+    }
+
+    let mut targeted = Map::new();
+    for (name, path, kind) in OVERRIDES {
+        let Some(raw) = lookup(name) else {
+            continue;
+        };
+        let Some(value) = parse(raw.trim(), *kind) else {
+            log::warn!("ignoring {name}={raw:?}: not a valid value");
+            continue;
+        };
+        insert(&mut targeted, path, value);
+    }
+    if !targeted.is_empty() {
+        payloads.push(Value::Object(targeted));
+    }
+    payloads
+}
+
+fn parse(raw: &str, kind: Kind) -> Option<Value> {
+    match kind {
+        Kind::String => Some(Value::from(raw)),
+        Kind::Number => raw.parse::<u64>().ok().map(Value::from),
+        Kind::Bool => match raw.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Some(Value::Bool(true)),
+            "0" | "false" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+fn insert(map: &mut Map<String, Value>, path: &[&str], value: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut target = map;
+    for key in parents {
+        target = target
+            .entry(key.to_string())
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("override paths only nest objects");
+    }
+    target.insert(last.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_and_targeted_variables_become_settings() {
+        let env = |name: &str| match name {
+            SETTINGS_ENV => Some(r#"{ "enable_inlay_hints": false }"#.to_string()),
+            "TS_BRIDGE_MAX_OLD_SPACE_SIZE" => Some("8192".to_string()),
+            "TS_BRIDGE_LOG_DIR" => Some("/var/log/ts".to_string()),
+            "TS_BRIDGE_SEPARATE_DIAGNOSTIC_SERVER" => Some("off".to_string()),
+            "TS_BRIDGE_REQUEST_TIMEOUT_MS" => Some("soon".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            overrides(env),
+            vec![
+                json!({ "enable_inlay_hints": false }),
+                json!({
+                    "tsserver": { "log_directory": "/var/log/ts", "max_old_space_size": 8192 },
+                    "separate_diagnostic_server": false,
+                }),
+            ]
+        );
+    }
+}
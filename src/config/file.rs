@@ -0,0 +1,91 @@
+//! Settings files, applied beneath `initializationOptions`: the user file
+//! from the config directory first, then the project file in the workspace
+//! root, then the file named by `--config`. All use the same shape as the LSP
+//! settings (TOML or JSON).
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// Looked up in the workspace root.
+pub const PROJECT_FILES: &[&str] = &[".ts-bridge.toml", "ts-bridge.json"];
+/// Looked up in `$XDG_CONFIG_HOME/ts-bridge` (or the platform equivalent).
+pub const USER_FILES: &[&str] = &["config.toml", "config.json"];
+
+static EXPLICIT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Applies `path` over the discovered files of every workspace for the rest
+/// of the process; the first call wins.
+pub fn set_explicit(path: PathBuf) {
+    let _ = EXPLICIT.set(path);
+}
+
+/// Existing settings files for `workspace_root`, lowest precedence first.
+pub fn discover(workspace_root: &Path) -> Vec<PathBuf> {
+    let user = user_config_dir()
+        .into_iter()
+        .flat_map(|dir| USER_FILES.iter().map(move |name| dir.join(name)));
+    let project = PROJECT_FILES.iter().map(|name| workspace_root.join(name));
+    user.chain(project)
+        .chain(EXPLICIT.get().cloned())
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Reads one settings file into the LSP settings shape.
+pub fn read(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).with_context(|| format!("invalid TOML in {}", path.display()))
+    } else {
+        serde_json::from_str(&text).with_context(|| format!("invalid JSON in {}", path.display()))
+    }
+}
+
+fn user_config_dir() -> Option<PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    var("XDG_CONFIG_HOME")
+        .or_else(|| var("APPDATA"))
+        .or_else(|| var("HOME").map(|home| home.join(".config")))
+        .map(|dir| dir.join("ts-bridge"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn toml_and_json_files_read_into_the_same_shape() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let toml_path = dir.path().join(".ts-bridge.toml");
+        std::fs::write(
+            &toml_path,
+            "enable_inlay_hints = false\n\n[tsserver]\nmax_old_space_size = 4096\n",
+        )
+        .expect("write toml");
+        let json_path = dir.path().join("ts-bridge.json");
+        std::fs::write(
+            &json_path,
+            r#"{ "enable_inlay_hints": false, "tsserver": { "max_old_space_size": 4096 } }"#,
+        )
+        .expect("write json");
+
+        let expected = json!({
+            "enable_inlay_hints": false,
+            "tsserver": { "max_old_space_size": 4096 }
+        });
+        assert_eq!(read(&toml_path).expect("toml parses"), expected);
+        assert_eq!(read(&json_path).expect("json parses"), expected);
+
+        std::fs::write(&json_path, "{ nope").expect("write broken json");
+        assert!(read(&json_path).is_err());
+    }
+}
@@ -6,8 +6,18 @@
 //! jsx helpers, tsserver memory limits, etc.) and exposes typed structures that
 //! other subsystems borrow.
 
-use std::path::PathBuf;
+mod env;
+mod file;
+mod validate;
+mod vscode;
 
+pub use file::{read as read_settings_file, set_explicit as set_settings_file};
+pub use validate::validate_settings;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 /// Settings that are evaluated once during plugin setup (analogous to the Lua
@@ -23,10 +33,47 @@ pub struct PluginSettings {
     pub tsserver: TsserverLaunchOptions,
     /// User preferences forwarded to the tsserver `configure` command.
     pub tsserver_preferences: Map<String, Value>,
+    /// Typed `preferences` block; `tsserver.preferences` keys win over it.
+    pub preferences: UserPreferences,
     /// Formatting options forwarded to the tsserver `configure` command.
     pub tsserver_format_options: Map<String, Value>,
     /// Gate for tsserver-backed inlay hints; allows users to disable the feature entirely.
     pub enable_inlay_hints: bool,
+    /// Which reference/implementation code lenses are produced (`off` by default).
+    pub code_lens: CodeLensMode,
+    /// Run `organizeImports` from `textDocument/willSaveWaitUntil`.
+    pub organize_imports_on_save: bool,
+    /// Run the fix-all chain from `textDocument/willSaveWaitUntil`.
+    pub fix_all_on_save: bool,
+    /// Insert function/method completions as call snippets from the list.
+    pub complete_function_calls: bool,
+    /// Limits applied to converted completion lists.
+    pub completion: CompletionSettings,
+    /// Insert the closing JSX tag after typing `>` or `/`.
+    pub jsx_close_tag: JsxCloseTagSettings,
+    /// Hover rendering options.
+    pub hover: HoverSettings,
+    /// Scheduling of edit-driven diagnostics requests.
+    pub diagnostics: DiagnosticsSettings,
+    /// Compiler options for files outside any tsconfig/jsconfig.
+    pub inferred_project_options: InferredProjectOptions,
+    /// Formatter settings sent for a file right before it is formatted.
+    pub format_options: FormatSettings,
+    /// `javascript = { ... }` overrides for JavaScript files.
+    pub javascript: ScopeSettings,
+    /// `typescript = { ... }` overrides for TypeScript files.
+    pub typescript: ScopeSettings,
+    /// Per-language-id overrides that turn ts-bridge, or single features,
+    /// off for documents of that language.
+    pub languages: BTreeMap<String, LanguageSettings>,
+    /// Forwarded as the `maximumHoverLength` preference; caps quickinfo text.
+    pub maximum_hover_length: Option<u32>,
+    /// Inlay hint labels longer than this are cut and end with `…`.
+    pub maximum_inlay_hint_length: Option<u32>,
+    /// Requests tsserver has not answered after this many milliseconds fail
+    /// with `RequestFailed`; `None` (the default) waits forever, since some
+    /// requests legitimately take minutes on large projects.
+    pub request_timeout_ms: Option<u32>,
 }
 
 impl Default for PluginSettings {
@@ -36,8 +83,25 @@ impl Default for PluginSettings {
             publish_diagnostic_on: DiagnosticPublishMode::InsertLeave,
             tsserver: TsserverLaunchOptions::default(),
             tsserver_preferences: Map::new(),
+            preferences: UserPreferences::default(),
             tsserver_format_options: Map::new(),
             enable_inlay_hints: true,
+            code_lens: CodeLensMode::Off,
+            organize_imports_on_save: false,
+            fix_all_on_save: false,
+            complete_function_calls: false,
+            completion: CompletionSettings::default(),
+            jsx_close_tag: JsxCloseTagSettings::default(),
+            hover: HoverSettings::default(),
+            diagnostics: DiagnosticsSettings::default(),
+            inferred_project_options: InferredProjectOptions::default(),
+            format_options: FormatSettings::default(),
+            javascript: ScopeSettings::default(),
+            typescript: ScopeSettings::default(),
+            languages: BTreeMap::new(),
+            maximum_hover_length: None,
+            maximum_inlay_hint_length: None,
+            request_timeout_ms: None,
         }
     }
 }
@@ -59,6 +123,884 @@ impl DiagnosticPublishMode {
     }
 }
 
+/// Code lens modes mirroring typescript-tools.nvim's `code_lens` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeLensMode {
+    Off,
+    All,
+    ReferencesOnly,
+    ImplementationsOnly,
+}
+
+impl CodeLensMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "all" => Some(Self::All),
+            "references_only" => Some(Self::ReferencesOnly),
+            "implementations_only" => Some(Self::ImplementationsOnly),
+            _ => None,
+        }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self != Self::Off
+    }
+
+    pub fn references(self) -> bool {
+        matches!(self, Self::All | Self::ReferencesOnly)
+    }
+
+    pub fn implementations(self) -> bool {
+        matches!(self, Self::All | Self::ImplementationsOnly)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::All => "all",
+            Self::ReferencesOnly => "references_only",
+            Self::ImplementationsOnly => "implementations_only",
+        }
+    }
+}
+
+/// How a completion list longer than `completion.max_entries` is ranked
+/// before it is cut.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntriesLimitStrategy {
+    /// Keep the entries with the lowest tsserver `sortText`.
+    #[default]
+    SortText,
+    /// Keep entries starting with the typed word first, then by `sortText`.
+    Prefix,
+}
+
+impl EntriesLimitStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sort_text" => Some(Self::SortText),
+            "prefix" => Some(Self::Prefix),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SortText => "sort_text",
+            Self::Prefix => "prefix",
+        }
+    }
+}
+
+/// `completion = { ... }` settings table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletionSettings {
+    /// Maximum number of items returned per list; `None` keeps everything.
+    pub max_entries: Option<u32>,
+    pub entries_limit_strategy: EntriesLimitStrategy,
+    pub commit_characters: CommitCharacterSettings,
+}
+
+/// Commit characters attached to completion items.  `overrides` maps an LSP
+/// item kind name (`"function"`, `"property"`, ...) to the characters used
+/// instead of the built-in defaults; an empty list disables them for that kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitCharacterSettings {
+    pub enabled: bool,
+    #[serde(default)]
+    pub overrides: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for CommitCharacterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompletionSettings {
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        if map.contains_key("max_entries") {
+            let next = positive_u32(map.get("max_entries"));
+            if self.max_entries != next {
+                self.max_entries = next;
+                changed = true;
+            }
+        }
+
+        if let Some(strategy) = map
+            .get("entries_limit_strategy")
+            .and_then(|v| v.as_str())
+            .and_then(EntriesLimitStrategy::parse)
+            && self.entries_limit_strategy != strategy
+        {
+            self.entries_limit_strategy = strategy;
+            changed = true;
+        }
+
+        if let Some(value) = map.get("commit_characters").and_then(|v| v.as_bool())
+            && self.commit_characters.enabled != value
+        {
+            self.commit_characters.enabled = value;
+            changed = true;
+        }
+
+        if map.contains_key("commit_characters_overrides") {
+            let next: BTreeMap<String, Vec<String>> = map
+                .get("commit_characters_overrides")
+                .and_then(|v| v.as_object())
+                .map(|overrides| {
+                    overrides
+                        .iter()
+                        .filter_map(|(kind, chars)| Some((kind.clone(), string_list(chars)?)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if self.commit_characters.overrides != next {
+                self.commit_characters.overrides = next;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// `jsx_close_tag = { enable, filetypes }`, as in typescript-tools.nvim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsxCloseTagSettings {
+    pub enable: bool,
+    /// Language ids the feature runs in.
+    pub filetypes: Vec<String>,
+}
+
+impl Default for JsxCloseTagSettings {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            filetypes: vec!["javascriptreact".to_string(), "typescriptreact".to_string()],
+        }
+    }
+}
+
+impl JsxCloseTagSettings {
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        if let Some(value) = map.get("enable").and_then(|v| v.as_bool())
+            && self.enable != value
+        {
+            self.enable = value;
+            changed = true;
+        }
+
+        if let Some(list) = map.get("filetypes").and_then(string_list)
+            && self.filetypes != list
+        {
+            self.filetypes = list;
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+/// `hover = { ... }` settings table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HoverSettings {
+    /// quickinfo `verbosityLevel`; 0 keeps tsserver's default rendering.
+    pub verbosity: u32,
+}
+
+impl HoverSettings {
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        if let Some(verbosity) = value
+            .get("verbosity")
+            .and_then(|v| v.as_u64())
+            .and_then(|v| u32::try_from(v).ok())
+            && self.verbosity != verbosity
+        {
+            self.verbosity = verbosity;
+            return true;
+        }
+        false
+    }
+}
+
+/// `diagnostics = { debounce_ms, ignore_codes, severity_overrides,
+/// region_line_threshold, include_suggestions }` settings table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsSettings {
+    /// Quiet period after an edit before its file's `geterr` goes out; edits
+    /// within it push the request back. 0 requests right away.
+    pub debounce_ms: u32,
+    /// tsserver diagnostic codes that are never reported.
+    #[serde(default)]
+    pub ignore_codes: BTreeSet<i32>,
+    /// Severity reported instead of tsserver's category, by code.
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<i32, DiagnosticLevel>,
+    /// Files with more lines get the semantic diagnostics of their visible
+    /// ranges first (TypeScript 5.6+); 0 turns that off.
+    #[serde(default)]
+    pub region_line_threshold: u32,
+    /// Whether `suggestionDiag` results (unused code, "could be converted"
+    /// hints) are published alongside errors.
+    pub include_suggestions: bool,
+}
+
+const DEFAULT_DIAGNOSTICS_DEBOUNCE_MS: u32 = 200;
+const DEFAULT_REGION_LINE_THRESHOLD: u32 = 1_000;
+
+impl Default for DiagnosticsSettings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: DEFAULT_DIAGNOSTICS_DEBOUNCE_MS,
+            ignore_codes: BTreeSet::new(),
+            severity_overrides: BTreeMap::new(),
+            region_line_threshold: DEFAULT_REGION_LINE_THRESHOLD,
+            include_suggestions: true,
+        }
+    }
+}
+
+/// Severity names accepted by `diagnostics.severity_overrides`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl DiagnosticLevel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "error" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "information" | "info" => Some(Self::Information),
+            "hint" => Some(Self::Hint),
+            _ => None,
+        }
+    }
+}
+
+impl DiagnosticsSettings {
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        if map.contains_key("debounce_ms") {
+            let next = map
+                .get("debounce_ms")
+                .and_then(|v| v.as_u64())
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(DEFAULT_DIAGNOSTICS_DEBOUNCE_MS);
+            if self.debounce_ms != next {
+                self.debounce_ms = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("region_line_threshold") {
+            let next = map
+                .get("region_line_threshold")
+                .and_then(|v| v.as_u64())
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(DEFAULT_REGION_LINE_THRESHOLD);
+            if self.region_line_threshold != next {
+                self.region_line_threshold = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("include_suggestions") {
+            let next = map
+                .get("include_suggestions")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            if self.include_suggestions != next {
+                self.include_suggestions = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("ignore_codes") {
+            let next: BTreeSet<i32> = map
+                .get("ignore_codes")
+                .and_then(|v| v.as_array())
+                .map(|codes| codes.iter().filter_map(diagnostic_code).collect())
+                .unwrap_or_default();
+            if self.ignore_codes != next {
+                self.ignore_codes = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("severity_overrides") {
+            let next: BTreeMap<i32, DiagnosticLevel> = map
+                .get("severity_overrides")
+                .and_then(|v| v.as_object())
+                .map(|overrides| {
+                    overrides
+                        .iter()
+                        .filter_map(|(code, level)| {
+                            Some((code.parse().ok()?, DiagnosticLevel::parse(level.as_str()?)?))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            if self.severity_overrides != next {
+                self.severity_overrides = next;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Codes may be written as numbers or strings (`"80001"`).
+fn diagnostic_code(value: &Value) -> Option<i32> {
+    match value {
+        Value::Number(number) => number.as_i64()?.try_into().ok(),
+        Value::String(code) => code.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// `inferred_project_options = { target, module, jsx, check_js, strict }`,
+/// sent through `compilerOptionsForInferredProjects`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredProjectOptions {
+    pub target: String,
+    pub module: String,
+    pub jsx: String,
+    pub check_js: bool,
+    pub strict: bool,
+}
+
+impl Default for InferredProjectOptions {
+    fn default() -> Self {
+        Self {
+            target: "ES2020".to_string(),
+            module: "ESNext".to_string(),
+            jsx: "react-jsx".to_string(),
+            check_js: false,
+            strict: true,
+        }
+    }
+}
+
+impl InferredProjectOptions {
+    /// The `options` argument of `compilerOptionsForInferredProjects`.
+    pub fn compiler_options(&self) -> Map<String, Value> {
+        let mut options = Map::new();
+        options.insert("target".to_string(), Value::from(self.target.as_str()));
+        options.insert("module".to_string(), Value::from(self.module.as_str()));
+        options.insert("jsx".to_string(), Value::from(self.jsx.as_str()));
+        options.insert("checkJs".to_string(), Value::Bool(self.check_js));
+        options.insert("strict".to_string(), Value::Bool(self.strict));
+        // Replacing tsserver's defaults would otherwise drop these.
+        for key in [
+            "allowJs",
+            "allowSyntheticDefaultImports",
+            "allowNonTsExtensions",
+            "resolveJsonModule",
+        ] {
+            options.insert(key.to_string(), Value::Bool(true));
+        }
+        options
+    }
+
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        for (key, field) in [
+            ("target", &mut self.target),
+            ("module", &mut self.module),
+            ("jsx", &mut self.jsx),
+        ] {
+            if let Some(value) = map
+                .get(key)
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                && field != value
+            {
+                *field = value.to_string();
+                changed = true;
+            }
+        }
+
+        for (key, field) in [
+            ("check_js", &mut self.check_js),
+            ("strict", &mut self.strict),
+        ] {
+            if let Some(value) = map.get(key).and_then(|v| v.as_bool())
+                && *field != value
+            {
+                *field = value;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// `preferences = { ... }`: the tsserver user preferences people reach for
+/// most, under snake_case names. Unset fields keep tsserver's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserPreferences {
+    /// `shortest`, `project-relative`, `relative` or `non-relative`.
+    pub import_module_specifier_preference: Option<String>,
+    /// `auto`, `minimal`, `index` or `js`.
+    pub import_module_specifier_ending: Option<String>,
+    /// `auto`, `double` or `single`.
+    pub quote_preference: Option<String>,
+    /// `auto`, `on` or `off`.
+    pub include_package_json_auto_imports: Option<String>,
+    pub prefer_type_only_auto_imports: Option<bool>,
+    pub use_label_details_in_completion_entries: Option<bool>,
+    pub include_completions_for_module_exports: Option<bool>,
+    pub include_completions_for_import_statements: Option<bool>,
+    pub allow_rename_of_import_path: Option<bool>,
+    pub provide_prefix_and_suffix_text_for_rename: Option<bool>,
+    pub auto_import_file_exclude_patterns: Option<Vec<String>>,
+}
+
+impl UserPreferences {
+    /// The preferences as tsserver `configure` keys.
+    pub fn tsserver_preferences(&self) -> Map<String, Value> {
+        let mut preferences = Map::new();
+        for (key, value) in [
+            (
+                "importModuleSpecifierPreference",
+                &self.import_module_specifier_preference,
+            ),
+            (
+                "importModuleSpecifierEnding",
+                &self.import_module_specifier_ending,
+            ),
+            ("quotePreference", &self.quote_preference),
+            (
+                "includePackageJsonAutoImports",
+                &self.include_package_json_auto_imports,
+            ),
+        ] {
+            if let Some(value) = value {
+                preferences.insert(key.to_string(), Value::from(value.as_str()));
+            }
+        }
+        for (key, value) in [
+            (
+                "preferTypeOnlyAutoImports",
+                self.prefer_type_only_auto_imports,
+            ),
+            (
+                "useLabelDetailsInCompletionEntries",
+                self.use_label_details_in_completion_entries,
+            ),
+            (
+                "includeCompletionsForModuleExports",
+                self.include_completions_for_module_exports,
+            ),
+            (
+                "includeCompletionsForImportStatements",
+                self.include_completions_for_import_statements,
+            ),
+            ("allowRenameOfImportPath", self.allow_rename_of_import_path),
+            (
+                "providePrefixAndSuffixTextForRename",
+                self.provide_prefix_and_suffix_text_for_rename,
+            ),
+        ] {
+            if let Some(value) = value {
+                preferences.insert(key.to_string(), Value::Bool(value));
+            }
+        }
+        if let Some(patterns) = &self.auto_import_file_exclude_patterns {
+            preferences.insert(
+                "autoImportFileExcludePatterns".to_string(),
+                Value::from(patterns.clone()),
+            );
+        }
+        preferences
+    }
+
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        for (key, field, allowed) in [
+            (
+                "import_module_specifier_preference",
+                &mut self.import_module_specifier_preference,
+                &["shortest", "project-relative", "relative", "non-relative"][..],
+            ),
+            (
+                "import_module_specifier_ending",
+                &mut self.import_module_specifier_ending,
+                &["auto", "minimal", "index", "js"][..],
+            ),
+            (
+                "quote_preference",
+                &mut self.quote_preference,
+                &["auto", "double", "single"][..],
+            ),
+            (
+                "include_package_json_auto_imports",
+                &mut self.include_package_json_auto_imports,
+                &["auto", "on", "off"][..],
+            ),
+        ] {
+            if map.contains_key(key) {
+                let next = map
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .filter(|v| allowed.contains(v))
+                    .map(str::to_string);
+                if *field != next {
+                    *field = next;
+                    changed = true;
+                }
+            }
+        }
+
+        for (key, field) in [
+            (
+                "prefer_type_only_auto_imports",
+                &mut self.prefer_type_only_auto_imports,
+            ),
+            (
+                "use_label_details_in_completion_entries",
+                &mut self.use_label_details_in_completion_entries,
+            ),
+            (
+                "include_completions_for_module_exports",
+                &mut self.include_completions_for_module_exports,
+            ),
+            (
+                "include_completions_for_import_statements",
+                &mut self.include_completions_for_import_statements,
+            ),
+            (
+                "allow_rename_of_import_path",
+                &mut self.allow_rename_of_import_path,
+            ),
+            (
+                "provide_prefix_and_suffix_text_for_rename",
+                &mut self.provide_prefix_and_suffix_text_for_rename,
+            ),
+        ] {
+            if map.contains_key(key) {
+                let next = map.get(key).and_then(|v| v.as_bool());
+                if *field != next {
+                    *field = next;
+                    changed = true;
+                }
+            }
+        }
+
+        if map.contains_key("auto_import_file_exclude_patterns") {
+            let next = map
+                .get("auto_import_file_exclude_patterns")
+                .and_then(string_list);
+            if self.auto_import_file_exclude_patterns != next {
+                self.auto_import_file_exclude_patterns = next;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// `format_options = { semicolons, quote_style, trailing_commas, indent_size,
+/// tab_size, convert_tabs_to_spaces, spacing }`. Unset fields fall back to the
+/// client's `FormattingOptions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatSettings {
+    /// `ignore`, `insert` or `remove`.
+    pub semicolons: Option<String>,
+    /// `auto`, `double` or `single`; sent as the `quotePreference` preference.
+    pub quote_style: Option<String>,
+    /// `ignore` or `remove`. tsserver's formatter leaves commas alone, so
+    /// ts-bridge removes trailing ones itself.
+    pub trailing_commas: Option<String>,
+    pub indent_size: Option<u32>,
+    pub tab_size: Option<u32>,
+    pub convert_tabs_to_spaces: Option<bool>,
+    /// tsserver `insertSpace*`/`placeOpenBrace*` flags, passed through.
+    pub spacing: Map<String, Value>,
+}
+
+impl FormatSettings {
+    /// The `FormatCodeSettings` keys these settings pin.
+    pub fn tsserver_options(&self) -> Map<String, Value> {
+        let mut options = self.spacing.clone();
+        if let Some(semicolons) = &self.semicolons {
+            options.insert("semicolons".to_string(), Value::from(semicolons.as_str()));
+        }
+        if let Some(size) = self.indent_size {
+            options.insert("indentSize".to_string(), Value::from(size));
+        }
+        if let Some(size) = self.tab_size {
+            options.insert("tabSize".to_string(), Value::from(size));
+        }
+        if let Some(spaces) = self.convert_tabs_to_spaces {
+            options.insert("convertTabsToSpaces".to_string(), Value::Bool(spaces));
+        }
+        options
+    }
+
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        for (key, field, allowed) in [
+            (
+                "semicolons",
+                &mut self.semicolons,
+                &["ignore", "insert", "remove"][..],
+            ),
+            (
+                "quote_style",
+                &mut self.quote_style,
+                &["auto", "double", "single"][..],
+            ),
+            (
+                "trailing_commas",
+                &mut self.trailing_commas,
+                &["ignore", "remove"][..],
+            ),
+        ] {
+            if map.contains_key(key) {
+                let next = map
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .filter(|v| allowed.contains(v))
+                    .map(str::to_string);
+                if *field != next {
+                    *field = next;
+                    changed = true;
+                }
+            }
+        }
+
+        for (key, field) in [
+            ("indent_size", &mut self.indent_size),
+            ("tab_size", &mut self.tab_size),
+        ] {
+            if map.contains_key(key) {
+                let next = positive_u32(map.get(key));
+                if *field != next {
+                    *field = next;
+                    changed = true;
+                }
+            }
+        }
+
+        if map.contains_key("convert_tabs_to_spaces") {
+            let next = map.get("convert_tabs_to_spaces").and_then(|v| v.as_bool());
+            if self.convert_tabs_to_spaces != next {
+                self.convert_tabs_to_spaces = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("spacing") {
+            let next = map
+                .get("spacing")
+                .and_then(|v| v.as_object())
+                .cloned()
+                .unwrap_or_default();
+            if self.spacing != next {
+                self.spacing = next;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Which of the `javascript`/`typescript` setting scopes a document uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageScope {
+    JavaScript,
+    TypeScript,
+}
+
+impl LanguageScope {
+    /// Picks the scope from the language id, falling back to the extension.
+    pub fn of(language_id: Option<&str>, file: &str) -> Option<Self> {
+        match language_id {
+            Some("javascript" | "javascriptreact") => return Some(Self::JavaScript),
+            Some("typescript" | "typescriptreact") => return Some(Self::TypeScript),
+            _ => {}
+        }
+        let extension = std::path::Path::new(file).extension()?.to_str()?;
+        match extension {
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "tsx" | "mts" | "cts" => Some(Self::TypeScript),
+            _ => None,
+        }
+    }
+}
+
+/// Preference, format and inlay hint overrides for one [`LanguageScope`],
+/// layered over the global settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeSettings {
+    pub preferences: UserPreferences,
+    pub format_options: FormatSettings,
+    /// Overrides `enable_inlay_hints` for the scope.
+    pub enable_inlay_hints: Option<bool>,
+    /// tsserver `includeInlay*` preferences replacing ts-bridge's defaults
+    /// while inlay hints are enabled.
+    pub inlay_hints: Map<String, Value>,
+}
+
+impl ScopeSettings {
+    fn update_from_value(&mut self, value: &Value) -> bool {
+        let Some(map) = value.as_object() else {
+            return false;
+        };
+        let mut changed = false;
+
+        if let Some(preferences) = map.get("preferences") {
+            changed |= self.preferences.update_from_value(preferences);
+        }
+
+        if let Some(format_options) = map.get("format_options") {
+            changed |= self.format_options.update_from_value(format_options);
+        }
+
+        if map.contains_key("enable_inlay_hints") {
+            let next = map.get("enable_inlay_hints").and_then(|v| v.as_bool());
+            if self.enable_inlay_hints != next {
+                self.enable_inlay_hints = next;
+                changed = true;
+            }
+        }
+
+        if let Some(inlay_hints) = map.get("inlay_hints") {
+            let next = inlay_hints.as_object().cloned().unwrap_or_default();
+            if self.inlay_hints != next {
+                self.inlay_hints = next;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+impl PluginSettings {
+    pub fn scope(&self, scope: LanguageScope) -> &ScopeSettings {
+        match scope {
+            LanguageScope::JavaScript => &self.javascript,
+            LanguageScope::TypeScript => &self.typescript,
+        }
+    }
+
+    /// Whether either scope overrides anything, which makes per-file
+    /// `configure` requests necessary.
+    pub fn has_scoped_settings(&self) -> bool {
+        self.javascript != ScopeSettings::default() || self.typescript != ScopeSettings::default()
+    }
+
+    /// `enable_inlay_hints` after the scope's override.
+    pub fn inlay_hints_enabled(&self, scope: Option<LanguageScope>) -> bool {
+        scope
+            .and_then(|scope| self.scope(scope).enable_inlay_hints)
+            .unwrap_or(self.enable_inlay_hints)
+    }
+
+    /// Whether any document can get inlay hints, i.e. the capability is
+    /// worth advertising.
+    pub fn any_inlay_hints_enabled(&self) -> bool {
+        self.enable_inlay_hints
+            || self.javascript.enable_inlay_hints == Some(true)
+            || self.typescript.enable_inlay_hints == Some(true)
+    }
+}
+
+/// One `languages.<id> = { enable = false, semantic_tokens = false, ... }`
+/// entry. Keys other than `enable` name features; `false` turns them off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageSettings {
+    /// `false` keeps documents of the language away from tsserver entirely.
+    pub enable: bool,
+    pub disabled_features: BTreeSet<String>,
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            disabled_features: BTreeSet::new(),
+        }
+    }
+}
+
+impl LanguageSettings {
+    fn from_value(value: &Value) -> Self {
+        let mut settings = Self::default();
+        let Some(map) = value.as_object() else {
+            return settings;
+        };
+        for (key, value) in map {
+            match (key.as_str(), value.as_bool()) {
+                ("enable", Some(enable)) => settings.enable = enable,
+                (feature, Some(false)) => {
+                    settings.disabled_features.insert(feature.to_string());
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+impl PluginSettings {
+    /// Whether documents of `language` are synced to tsserver at all.
+    pub fn language_enabled(&self, language: Option<&str>) -> bool {
+        language
+            .and_then(|language| self.languages.get(language))
+            .is_none_or(|settings| settings.enable)
+    }
+
+    /// Whether `feature` (e.g. `semantic_tokens`) runs for `language`.
+    pub fn language_feature_enabled(&self, language: Option<&str>, feature: &str) -> bool {
+        language
+            .and_then(|language| self.languages.get(language))
+            .is_none_or(|settings| settings.enable && !settings.disabled_features.contains(feature))
+    }
+}
+
 /// Global configuration facade that exposes read-only handles to each settings struct.
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Config {
@@ -83,11 +1025,81 @@ impl Config {
     pub fn apply_workspace_settings(&mut self, settings: &Value) -> bool {
         apply_settings_tree(settings, &mut self.plugin)
     }
+
+    /// Applies the user and project settings files for `workspace_root`
+    /// (see [`file`]) and returns the ones that were read, each with the
+    /// problems [`validate_settings`] found in it. Unreadable files are logged
+    /// and skipped.
+    pub fn apply_config_files(&mut self, workspace_root: &Path) -> Vec<(PathBuf, Vec<String>)> {
+        let mut applied = Vec::new();
+        for path in file::discover(workspace_root) {
+            match file::read(&path) {
+                Ok(settings) => {
+                    self.apply_workspace_settings(&settings);
+                    applied.push((path, validate_settings(&settings)));
+                }
+                Err(err) => log::warn!("ignoring settings file: {err:#}"),
+            }
+        }
+        applied
+    }
+
+    /// Applies `TS_BRIDGE_SETTINGS` and the targeted `TS_BRIDGE_*` variables
+    /// (see [`env`]). Returns `true` when any setting changed.
+    pub fn apply_env_overrides(&mut self) -> bool {
+        self.apply_env_overrides_from(|name| std::env::var(name).ok())
+    }
+
+    fn apply_env_overrides_from(&mut self, lookup: impl Fn(&str) -> Option<String>) -> bool {
+        let mut changed = false;
+        for settings in env::overrides(lookup) {
+            changed |= self.apply_workspace_settings(&settings);
+        }
+        changed
+    }
+
+    /// Applies settings sent by the client (`initializationOptions`,
+    /// `workspace/didChangeConfiguration`), then the `TS_BRIDGE_*` overrides
+    /// again so the environment keeps the last word. Returns `true` when the
+    /// settings ended up different.
+    pub fn apply_client_settings(&mut self, settings: &Value) -> bool {
+        self.apply_client_settings_from(settings, |name| std::env::var(name).ok())
+    }
+
+    fn apply_client_settings_from(
+        &mut self,
+        settings: &Value,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> bool {
+        let before = self.plugin.clone();
+        self.apply_workspace_settings(settings);
+        self.apply_env_overrides_from(lookup);
+        self.plugin != before
+    }
+
+    /// Uses the client's `InitializeParams.locale` for tsserver unless the
+    /// user picked `tsserver.locale` explicitly.
+    pub fn inherit_client_locale(&mut self, locale: Option<&str>) -> bool {
+        let Some(locale) = locale.filter(|locale| !locale.is_empty()) else {
+            return false;
+        };
+        if self.plugin.tsserver.locale.is_some() {
+            return false;
+        }
+        self.plugin.tsserver.locale = Some(locale.to_string());
+        true
+    }
 }
 
 fn apply_settings_tree(value: &Value, plugin: &mut PluginSettings) -> bool {
     let mut changed = false;
     if let Some(map) = value.as_object() {
+        // VS Code keys first so native keys in the same payload win.
+        if let Some(native) = vscode::translate(map)
+            && let Some(native) = native.as_object()
+        {
+            changed |= plugin.update_from_map(native);
+        }
         changed |= plugin.update_from_map(map);
 
         for key in POSSIBLE_SETTING_ROOTS {
@@ -167,6 +1179,116 @@ impl PluginSettings {
             }
         }
 
+        if let Some(mode) = map
+            .get("code_lens")
+            .and_then(|v| v.as_str())
+            .and_then(CodeLensMode::parse)
+            && self.code_lens != mode
+        {
+            self.code_lens = mode;
+            changed = true;
+        }
+
+        if let Some(value) = map
+            .get("organize_imports_on_save")
+            .and_then(|v| v.as_bool())
+            && self.organize_imports_on_save != value
+        {
+            self.organize_imports_on_save = value;
+            changed = true;
+        }
+
+        if let Some(value) = map.get("fix_all_on_save").and_then(|v| v.as_bool())
+            && self.fix_all_on_save != value
+        {
+            self.fix_all_on_save = value;
+            changed = true;
+        }
+
+        if let Some(value) = map.get("complete_function_calls").and_then(|v| v.as_bool())
+            && self.complete_function_calls != value
+        {
+            self.complete_function_calls = value;
+            changed = true;
+        }
+
+        if let Some(completion) = map.get("completion") {
+            changed |= self.completion.update_from_value(completion);
+        }
+
+        if let Some(jsx_close_tag) = map.get("jsx_close_tag") {
+            changed |= self.jsx_close_tag.update_from_value(jsx_close_tag);
+        }
+
+        if let Some(hover) = map.get("hover") {
+            changed |= self.hover.update_from_value(hover);
+        }
+
+        if let Some(diagnostics) = map.get("diagnostics") {
+            changed |= self.diagnostics.update_from_value(diagnostics);
+        }
+
+        if let Some(options) = map.get("inferred_project_options") {
+            changed |= self.inferred_project_options.update_from_value(options);
+        }
+
+        if let Some(preferences) = map.get("preferences") {
+            changed |= self.preferences.update_from_value(preferences);
+        }
+
+        if let Some(format_options) = map.get("format_options") {
+            changed |= self.format_options.update_from_value(format_options);
+        }
+
+        if let Some(javascript) = map.get("javascript") {
+            changed |= self.javascript.update_from_value(javascript);
+        }
+
+        if let Some(typescript) = map.get("typescript") {
+            changed |= self.typescript.update_from_value(typescript);
+        }
+
+        if map.contains_key("languages") {
+            let next = map
+                .get("languages")
+                .and_then(|v| v.as_object())
+                .map(|languages| {
+                    languages
+                        .iter()
+                        .map(|(id, value)| (id.clone(), LanguageSettings::from_value(value)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if self.languages != next {
+                self.languages = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("maximum_hover_length") {
+            let next = positive_u32(map.get("maximum_hover_length"));
+            if self.maximum_hover_length != next {
+                self.maximum_hover_length = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("maximum_inlay_hint_length") {
+            let next = positive_u32(map.get("maximum_inlay_hint_length"));
+            if self.maximum_inlay_hint_length != next {
+                self.maximum_inlay_hint_length = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("request_timeout_ms") {
+            let next = positive_u32(map.get("request_timeout_ms"));
+            if self.request_timeout_ms != next {
+                self.request_timeout_ms = next;
+                changed = true;
+            }
+        }
+
         changed
     }
 }
@@ -174,15 +1296,84 @@ impl PluginSettings {
 /// Launch-related knobs for the underlying `tsserver` Node process.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct TsserverLaunchOptions {
+    /// Explicit `tsserver.js`; skips the node_modules/Yarn/PATH lookup.
+    pub path: Option<PathBuf>,
+    /// Node executable used to run tsserver; found on PATH or through
+    /// volta/fnm/nvm when unset.
+    pub node_path: Option<PathBuf>,
+    /// A `typescript/lib` directory (like VS Code's `typescript.tsdk`),
+    /// preferred over node_modules and the Yarn SDK.
+    pub tsdk: Option<PathBuf>,
     pub locale: Option<String>,
     pub log_directory: Option<PathBuf>,
     pub log_verbosity: Option<TsserverLogVerbosity>,
     pub max_old_space_size: Option<u32>,
     pub global_plugins: Vec<String>,
     pub plugin_probe_dirs: Vec<PathBuf>,
+    /// Global plugins with runtime configuration, sent through
+    /// `configurePlugin`.
+    pub plugins: Vec<TsserverPlugin>,
     pub extra_args: Vec<String>,
+    /// Also send tsserver's stderr to the client as `window/logMessage`
+    /// (it always goes to the bridge log).
+    pub forward_stderr: bool,
+    /// How requests and responses travel between the bridge and tsserver.
+    pub transport: TsserverTransport,
+    /// Extra environment for the child; a `null` value removes the variable.
+    pub env: BTreeMap<String, Option<String>>,
+    /// Exported to the child as `NODE_OPTIONS`.
+    pub node_options: Option<String>,
+    /// When set, the child only inherits these variables (plus the few
+    /// [`ESSENTIAL_ENV`] ones node needs to start) from the bridge.
+    pub env_allowlist: Option<Vec<String>>,
+    /// Launch node with `--inspect=<port>` (falls back to `TSS_DEBUG`).
+    pub debug_port: Option<u16>,
+    /// `--inspect-brk` instead of `--inspect` (falls back to `TSS_DEBUG_BRK`).
+    pub debug_break: bool,
+    /// Which server gets the inspector; two processes cannot share a port.
+    pub debug_server: DebugServer,
+    /// Passes `--disableAutomaticTypingAcquisition` so tsserver never runs npm
+    /// to fetch `@types` packages.
+    pub disable_automatic_typing_acquisition: bool,
+    /// npm executable the typings installer uses (`--npmLocation`); also
+    /// used to install the bundled TypeScript.
+    pub npm_location: Option<String>,
+    /// Fail instead of installing the bundled TypeScript when no other
+    /// tsserver is found.
+    pub disable_bundled_typescript: bool,
+    /// `watchOptions` (watchFile, watchDirectory, fallbackPolling,
+    /// excludeDirectories) forwarded to the tsserver `configure` command.
+    pub watch_options: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DebugServer {
+    #[default]
+    Syntax,
+    Semantic,
+}
+
+impl DebugServer {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "syntax" => Some(Self::Syntax),
+            "semantic" => Some(Self::Semantic),
+            _ => None,
+        }
+    }
 }
 
+/// Always inherited, even with `env_allowlist`.
+pub const ESSENTIAL_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USERPROFILE",
+    "SystemRoot",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+];
+
 impl TsserverLaunchOptions {
     fn update_from_value(&mut self, value: &Value) -> bool {
         let map = match value.as_object() {
@@ -191,13 +1382,49 @@ impl TsserverLaunchOptions {
         };
         let mut changed = false;
 
-        if map.contains_key("locale") {
+        if map.contains_key("locale") {
+            let next = map
+                .get("locale")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if self.locale != next {
+                self.locale = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("path") {
+            let next = map
+                .get("path")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from);
+            if self.path != next {
+                self.path = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("node_path") {
             let next = map
-                .get("locale")
+                .get("node_path")
                 .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-            if self.locale != next {
-                self.locale = next;
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from);
+            if self.node_path != next {
+                self.node_path = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("tsdk") {
+            let next = map
+                .get("tsdk")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from);
+            if self.tsdk != next {
+                self.tsdk = next;
                 changed = true;
             }
         }
@@ -256,6 +1483,13 @@ impl TsserverLaunchOptions {
             }
         }
 
+        if let Some(list) = map.get("plugins").and_then(plugin_list)
+            && self.plugins != list
+        {
+            self.plugins = list;
+            changed = true;
+        }
+
         if let Some(list) = map.get("extra_args").and_then(|value| string_list(value)) {
             if self.extra_args != list {
                 self.extra_args = list;
@@ -263,10 +1497,195 @@ impl TsserverLaunchOptions {
             }
         }
 
+        if let Some(env) = map.get("env").and_then(|v| v.as_object()) {
+            let next: BTreeMap<String, Option<String>> = env
+                .iter()
+                .filter_map(|(name, value)| match value {
+                    Value::String(value) => Some((name.clone(), Some(value.clone()))),
+                    Value::Null => Some((name.clone(), None)),
+                    _ => None,
+                })
+                .collect();
+            if self.env != next {
+                self.env = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("node_options") {
+            let next = map
+                .get("node_options")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string);
+            if self.node_options != next {
+                self.node_options = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("env_allowlist") {
+            let next = map.get("env_allowlist").and_then(string_list);
+            if self.env_allowlist != next {
+                self.env_allowlist = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("debug_port") {
+            let next = map
+                .get("debug_port")
+                .and_then(|v| v.as_u64())
+                .and_then(|v| u16::try_from(v).ok());
+            if self.debug_port != next {
+                self.debug_port = next;
+                changed = true;
+            }
+        }
+
+        if let Some(debug_break) = map.get("debug_break").and_then(|v| v.as_bool())
+            && self.debug_break != debug_break
+        {
+            self.debug_break = debug_break;
+            changed = true;
+        }
+
+        if let Some(disable) = map
+            .get("disable_automatic_typing_acquisition")
+            .and_then(|v| v.as_bool())
+            && self.disable_automatic_typing_acquisition != disable
+        {
+            self.disable_automatic_typing_acquisition = disable;
+            changed = true;
+        }
+
+        if let Some(disable) = map
+            .get("disable_bundled_typescript")
+            .and_then(|v| v.as_bool())
+            && self.disable_bundled_typescript != disable
+        {
+            self.disable_bundled_typescript = disable;
+            changed = true;
+        }
+
+        if map.contains_key("watch_options") {
+            let next = map
+                .get("watch_options")
+                .and_then(|v| v.as_object())
+                .cloned()
+                .unwrap_or_default();
+            if self.watch_options != next {
+                self.watch_options = next;
+                changed = true;
+            }
+        }
+
+        if map.contains_key("npm_location") {
+            let next = map
+                .get("npm_location")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string);
+            if self.npm_location != next {
+                self.npm_location = next;
+                changed = true;
+            }
+        }
+
+        if let Some(server) = map
+            .get("debug_server")
+            .and_then(|v| v.as_str())
+            .and_then(DebugServer::parse)
+            && self.debug_server != server
+        {
+            self.debug_server = server;
+            changed = true;
+        }
+
+        if let Some(transport) = map
+            .get("transport")
+            .and_then(|v| v.as_str())
+            .and_then(TsserverTransport::parse)
+            && self.transport != transport
+        {
+            self.transport = transport;
+            changed = true;
+        }
+
+        if let Some(forward) = map.get("forward_stderr").and_then(|v| v.as_bool())
+            && self.forward_stderr != forward
+        {
+            self.forward_stderr = forward;
+            changed = true;
+        }
+
         changed
     }
 }
 
+/// Length limits: `null`, `0` or anything non-numeric means "no limit".
+fn positive_u32(value: Option<&Value>) -> Option<u32> {
+    value
+        .and_then(|v| v.as_u64())
+        .filter(|&v| v > 0)
+        .and_then(|v| v.try_into().ok())
+}
+
+impl TsserverLaunchOptions {
+    /// Language ids the configured plugins handle, without duplicates.
+    pub fn plugin_languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = Vec::new();
+        for language in self.plugins.iter().flat_map(|plugin| &plugin.languages) {
+            if !languages.contains(&language.as_str()) {
+                languages.push(language);
+            }
+        }
+        languages
+    }
+}
+
+/// `tsserver.plugins = { { name, location, config, languages }, ... }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsserverPlugin {
+    /// Package name, loaded as a `--globalPlugins` entry.
+    pub name: String,
+    /// Directory the package is installed under; added to
+    /// `--pluginProbeLocations`.
+    pub location: Option<PathBuf>,
+    /// Sent as the `configurePlugin` configuration when present.
+    pub config: Option<Value>,
+    /// Language ids whose documents the plugin handles (e.g. `vue`).
+    pub languages: Vec<String>,
+}
+
+fn plugin_list(value: &Value) -> Option<Vec<TsserverPlugin>> {
+    let array = value.as_array()?;
+    let mut result = Vec::with_capacity(array.len());
+    for entry in array {
+        let Some(name) = entry
+            .get("name")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+        else {
+            continue;
+        };
+        result.push(TsserverPlugin {
+            name: name.to_string(),
+            location: entry
+                .get("location")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            config: entry.get("config").filter(|v| !v.is_null()).cloned(),
+            languages: entry
+                .get("languages")
+                .and_then(string_list)
+                .unwrap_or_default(),
+        });
+    }
+    Some(result)
+}
+
 fn string_list(value: &Value) -> Option<Vec<String>> {
     let array = value.as_array()?;
     let mut result = Vec::with_capacity(array.len());
@@ -279,6 +1698,26 @@ fn string_list(value: &Value) -> Option<Vec<String>> {
     Some(result)
 }
 
+/// `stdio` speaks tsserver's stdin/stdout protocol; `ipc` launches it with
+/// `--useNodeIpc` and exchanges newline-delimited JSON over a socket, avoiding
+/// the `Content-Length` framing on output (Unix only).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TsserverTransport {
+    #[default]
+    Stdio,
+    Ipc,
+}
+
+impl TsserverTransport {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stdio" => Some(Self::Stdio),
+            "ipc" => Some(Self::Ipc),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TsserverLogVerbosity {
     Terse,
@@ -350,6 +1789,397 @@ mod tests {
         );
     }
 
+    #[test]
+    fn client_locale_only_fills_an_unset_tsserver_locale() {
+        let mut config = Config::new(PluginSettings::default());
+        assert!(config.inherit_client_locale(Some("de")));
+        assert_eq!(config.plugin().tsserver.locale.as_deref(), Some("de"));
+
+        let mut config = Config::new(PluginSettings::default());
+        config.apply_workspace_settings(&json!({ "tsserver": { "locale": "ja" } }));
+        assert!(!config.inherit_client_locale(Some("de")));
+        assert_eq!(config.plugin().tsserver.locale.as_deref(), Some("ja"));
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_code_lens_mode() {
+        let mut config = Config::new(PluginSettings::default());
+        assert_eq!(config.plugin().code_lens, CodeLensMode::Off);
+
+        let changed = config.apply_workspace_settings(&json!({
+            "ts-bridge": { "code_lens": "references_only" }
+        }));
+        assert!(changed);
+        assert_eq!(config.plugin().code_lens, CodeLensMode::ReferencesOnly);
+
+        let changed = config.apply_workspace_settings(&json!({
+            "ts-bridge": { "code_lens": "sometimes" }
+        }));
+        assert!(!changed, "unknown modes are ignored");
+        assert_eq!(config.plugin().code_lens, CodeLensMode::ReferencesOnly);
+    }
+
+    #[test]
+    fn request_timeout_is_opt_in() {
+        let mut config = Config::default();
+        assert_eq!(config.plugin().request_timeout_ms, None);
+        assert!(config.apply_workspace_settings(&json!({
+            "ts-bridge": { "request_timeout_ms": 30000 }
+        })));
+        assert_eq!(config.plugin().request_timeout_ms, Some(30_000));
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_diagnostics_debounce() {
+        let mut config = Config::default();
+        assert_eq!(config.plugin().diagnostics.debounce_ms, 200);
+        assert!(config.apply_workspace_settings(&json!({
+            "ts-bridge": { "diagnostics": { "debounce_ms": 0 } }
+        })));
+        assert_eq!(config.plugin().diagnostics.debounce_ms, 0);
+        assert!(config.apply_workspace_settings(&json!({
+            "ts-bridge": { "diagnostics": { "debounce_ms": null } }
+        })));
+        assert_eq!(config.plugin().diagnostics.debounce_ms, 200);
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_diagnostic_filters() {
+        let mut config = Config::default();
+        assert!(config.apply_workspace_settings(&json!({
+            "ts-bridge": {
+                "diagnostics": {
+                    "ignore_codes": [80001, "6133", "nope"],
+                    "severity_overrides": { "2304": "hint", "7016": "info", "1": "loud" }
+                }
+            }
+        })));
+        let diagnostics = &config.plugin().diagnostics;
+        assert_eq!(
+            diagnostics.ignore_codes.iter().copied().collect::<Vec<_>>(),
+            vec![6133, 80001]
+        );
+        assert_eq!(
+            diagnostics.severity_overrides.get(&2304),
+            Some(&DiagnosticLevel::Hint)
+        );
+        assert_eq!(
+            diagnostics.severity_overrides.get(&7016),
+            Some(&DiagnosticLevel::Information)
+        );
+        assert_eq!(diagnostics.severity_overrides.len(), 2);
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_completion_limits() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "completion": {
+                    "max_entries": 200,
+                    "entries_limit_strategy": "prefix"
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let completion = &config.plugin().completion;
+        assert_eq!(completion.max_entries, Some(200));
+        assert_eq!(
+            completion.entries_limit_strategy,
+            EntriesLimitStrategy::Prefix
+        );
+
+        let settings = json!({ "ts-bridge": { "completion": { "max_entries": null } } });
+        assert!(config.apply_workspace_settings(&settings));
+        assert_eq!(config.plugin().completion.max_entries, None);
+
+        let settings = json!({
+            "ts-bridge": {
+                "completion": {
+                    "commit_characters": false,
+                    "commit_characters_overrides": { "function": ["("] }
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let commit = &config.plugin().completion.commit_characters;
+        assert!(!commit.enabled);
+        assert_eq!(
+            commit.overrides.get("function"),
+            Some(&vec!["(".to_string()])
+        );
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_inferred_project_options() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "inferred_project_options": { "target": "ES2022", "check_js": true }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        assert!(!config.apply_workspace_settings(&settings));
+
+        let options = config.plugin().inferred_project_options.compiler_options();
+        assert_eq!(options.get("target"), Some(&json!("ES2022")));
+        assert_eq!(options.get("module"), Some(&json!("ESNext")));
+        assert_eq!(options.get("checkJs"), Some(&json!(true)));
+        assert_eq!(options.get("strict"), Some(&json!(true)));
+    }
+
+    #[test]
+    fn env_overrides_win_over_client_settings() {
+        let env = |name: &str| match name {
+            "TS_BRIDGE_MAX_OLD_SPACE_SIZE" => Some("8192".to_string()),
+            _ => None,
+        };
+        let mut config = Config::default();
+        config.apply_env_overrides_from(env);
+
+        let options = json!({
+            "enable_inlay_hints": false,
+            "tsserver": { "max_old_space_size": 2048 }
+        });
+        assert!(config.apply_client_settings_from(&options, env));
+        assert_eq!(config.plugin().tsserver.max_old_space_size, Some(8192));
+        assert!(!config.plugin().enable_inlay_hints);
+
+        let update = json!({ "tsserver": { "max_old_space_size": 4096 } });
+        assert!(!config.apply_client_settings_from(&update, env));
+        assert_eq!(config.plugin().tsserver.max_old_space_size, Some(8192));
+    }
+
+    #[test]
+    fn apply_workspace_settings_reads_watch_options() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "tsserver": {
+                    "watch_options": {
+                        "watchFile": "useFsEventsOnParentDirectory",
+                        "excludeDirectories": ["**/node_modules"]
+                    }
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        assert_eq!(
+            config.plugin().tsserver.watch_options.get("watchFile"),
+            Some(&json!("useFsEventsOnParentDirectory"))
+        );
+
+        let settings = json!({ "ts-bridge": { "tsserver": { "watch_options": null } } });
+        assert!(config.apply_workspace_settings(&settings));
+        assert!(config.plugin().tsserver.watch_options.is_empty());
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_tsserver_plugins() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "tsserver": {
+                    "plugins": [
+                        {
+                            "name": "@vue/typescript-plugin",
+                            "location": "/opt/vue",
+                            "languages": ["vue"],
+                            "config": { "enabled": true }
+                        },
+                        { "location": "/missing/name" }
+                    ]
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let plugins = &config.plugin().tsserver.plugins;
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].name, "@vue/typescript-plugin");
+        assert_eq!(plugins[0].location, Some(PathBuf::from("/opt/vue")));
+        assert_eq!(plugins[0].languages, vec!["vue".to_string()]);
+        assert_eq!(plugins[0].config, Some(json!({ "enabled": true })));
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_language_overrides() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "languages": {
+                    "javascript": { "semantic_tokens": false, "hover": true },
+                    "json": { "enable": false }
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let plugin = config.plugin();
+        assert!(!plugin.language_feature_enabled(Some("javascript"), "semantic_tokens"));
+        assert!(plugin.language_feature_enabled(Some("javascript"), "hover"));
+        assert!(plugin.language_enabled(Some("javascript")));
+        assert!(!plugin.language_enabled(Some("json")));
+        assert!(!plugin.language_feature_enabled(Some("json"), "hover"));
+        assert!(plugin.language_feature_enabled(Some("typescript"), "semantic_tokens"));
+        assert!(plugin.language_feature_enabled(None, "semantic_tokens"));
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_user_preferences() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "preferences": {
+                    "import_module_specifier_preference": "non-relative",
+                    "quote_preference": "nope",
+                    "prefer_type_only_auto_imports": true,
+                    "auto_import_file_exclude_patterns": ["**/dist/**"]
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let preferences = config.plugin().preferences.tsserver_preferences();
+        assert_eq!(
+            preferences.get("importModuleSpecifierPreference"),
+            Some(&json!("non-relative"))
+        );
+        assert_eq!(preferences.get("quotePreference"), None);
+        assert_eq!(
+            preferences.get("preferTypeOnlyAutoImports"),
+            Some(&json!(true))
+        );
+        assert_eq!(
+            preferences.get("autoImportFileExcludePatterns"),
+            Some(&json!(["**/dist/**"]))
+        );
+
+        let settings =
+            json!({ "ts-bridge": { "preferences": { "prefer_type_only_auto_imports": null } } });
+        assert!(config.apply_workspace_settings(&settings));
+        assert_eq!(
+            config.plugin().preferences.prefer_type_only_auto_imports,
+            None
+        );
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_language_scopes() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "enable_inlay_hints": false,
+                "typescript": {
+                    "enable_inlay_hints": true,
+                    "preferences": { "quote_preference": "single" }
+                },
+                "javascript": { "format_options": { "semicolons": "remove" } }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let plugin = config.plugin();
+        assert!(plugin.has_scoped_settings());
+        assert!(plugin.inlay_hints_enabled(Some(LanguageScope::TypeScript)));
+        assert!(!plugin.inlay_hints_enabled(Some(LanguageScope::JavaScript)));
+        assert!(!plugin.inlay_hints_enabled(None));
+        assert!(plugin.any_inlay_hints_enabled());
+        assert_eq!(
+            plugin.typescript.preferences.quote_preference.as_deref(),
+            Some("single")
+        );
+        assert_eq!(
+            plugin.javascript.format_options.semicolons.as_deref(),
+            Some("remove")
+        );
+        assert_eq!(
+            LanguageScope::of(None, "/w/index.mjs"),
+            Some(LanguageScope::JavaScript)
+        );
+        assert_eq!(
+            LanguageScope::of(Some("typescriptreact"), "/w/App"),
+            Some(LanguageScope::TypeScript)
+        );
+        assert_eq!(LanguageScope::of(Some("vue"), "/w/App.vue"), None);
+    }
+
+    #[test]
+    fn apply_workspace_settings_maps_vscode_settings() {
+        let mut config = Config::default();
+        let settings = json!({
+            "typescript.inlayHints.parameterNames.enabled": "all",
+            "typescript.preferences.importModuleSpecifier": "non-relative",
+            "javascript": { "preferences": { "quoteStyle": "single" } },
+            "typescript": { "tsserver": { "maxTsServerMemory": 8192 } }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let plugin = config.plugin();
+        assert_eq!(
+            plugin
+                .typescript
+                .inlay_hints
+                .get("includeInlayParameterNameHints"),
+            Some(&json!("all"))
+        );
+        assert_eq!(
+            plugin
+                .typescript
+                .preferences
+                .import_module_specifier_preference
+                .as_deref(),
+            Some("non-relative")
+        );
+        assert_eq!(
+            plugin.javascript.preferences.quote_preference.as_deref(),
+            Some("single")
+        );
+        assert_eq!(plugin.tsserver.max_old_space_size, Some(8192));
+        assert!(!config.apply_workspace_settings(&settings));
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_format_settings() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "format_options": {
+                    "semicolons": "remove",
+                    "quote_style": "sideways",
+                    "indent_size": 4,
+                    "spacing": { "insertSpaceAfterOpeningAndClosingNonemptyBraces": false }
+                }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let format = &config.plugin().format_options;
+        assert_eq!(format.quote_style, None);
+        let options = format.tsserver_options();
+        assert_eq!(options.get("semicolons"), Some(&json!("remove")));
+        assert_eq!(options.get("indentSize"), Some(&json!(4)));
+        assert_eq!(
+            options.get("insertSpaceAfterOpeningAndClosingNonemptyBraces"),
+            Some(&json!(false))
+        );
+        assert_eq!(options.get("tabSize"), None);
+    }
+
+    #[test]
+    fn apply_workspace_settings_parses_quote_style_and_trailing_commas() {
+        let mut config = Config::default();
+        let settings = json!({
+            "ts-bridge": {
+                "format_options": { "quote_style": "single", "trailing_commas": "remove" }
+            }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        let format = &config.plugin().format_options;
+        assert_eq!(format.quote_style.as_deref(), Some("single"));
+        assert_eq!(format.trailing_commas.as_deref(), Some("remove"));
+
+        let settings = json!({
+            "ts-bridge": { "format_options": { "trailing_commas": "insert" } }
+        });
+        assert!(config.apply_workspace_settings(&settings));
+        assert_eq!(config.plugin().format_options.trailing_commas, None);
+    }
+
     #[test]
     fn apply_workspace_settings_accepts_format_options_camel_case() {
         let mut config = Config::new(PluginSettings::default());
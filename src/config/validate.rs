@@ -0,0 +1,278 @@
+//! Checks a settings payload against the keys the parsers understand, so
+//! typos like `publish_diagnostics_on` are reported instead of silently
+//! ignored. Only the shape is checked; values the parsers reject (an unknown
+//! enum variant, say) are reported as well.
+
+use serde_json::{Map, Value};
+
+use super::POSSIBLE_SETTING_ROOTS;
+
+enum Expect {
+    Bool,
+    Number,
+    String,
+    StringList,
+    OneOf(&'static [&'static str]),
+    Object(&'static [(&'static str, Expect)]),
+    /// Passed through or free-form; only checked to be an object.
+    AnyObject,
+    Array,
+}
+
+const PLUGIN: &[(&str, Expect)] = &[
+    ("separate_diagnostic_server", Expect::Bool),
+    (
+        "publish_diagnostic_on",
+        Expect::OneOf(&["insert_leave", "change"]),
+    ),
+    ("tsserver", Expect::Object(TSSERVER)),
+    ("enable_inlay_hints", Expect::Bool),
+    (
+        "code_lens",
+        Expect::OneOf(&["off", "all", "references_only", "implementations_only"]),
+    ),
+    ("organize_imports_on_save", Expect::Bool),
+    ("fix_all_on_save", Expect::Bool),
+    ("complete_function_calls", Expect::Bool),
+    ("completion", Expect::Object(COMPLETION)),
+    ("jsx_close_tag", Expect::Object(JSX_CLOSE_TAG)),
+    ("hover", Expect::Object(&[("verbosity", Expect::Number)])),
+    (
+        "diagnostics",
+        Expect::Object(&[
+            ("debounce_ms", Expect::Number),
+            ("ignore_codes", Expect::Array),
+            ("severity_overrides", Expect::AnyObject),
+            ("region_line_threshold", Expect::Number),
+            ("include_suggestions", Expect::Bool),
+        ]),
+    ),
+    ("inferred_project_options", Expect::Object(INFERRED)),
+    ("preferences", Expect::Object(PREFERENCES)),
+    ("format_options", Expect::Object(FORMAT)),
+    // Native scope keys and VS Code `typescript.*` keys share these.
+    ("javascript", Expect::AnyObject),
+    ("typescript", Expect::AnyObject),
+    ("languages", Expect::AnyObject),
+    ("maximum_hover_length", Expect::Number),
+    ("maximum_inlay_hint_length", Expect::Number),
+    ("request_timeout_ms", Expect::Number),
+];
+
+const TSSERVER: &[(&str, Expect)] = &[
+    ("path", Expect::String),
+    ("node_path", Expect::String),
+    ("tsdk", Expect::String),
+    ("locale", Expect::String),
+    ("log_directory", Expect::String),
+    (
+        "log_verbosity",
+        Expect::OneOf(&["terse", "normal", "requestTime", "request_time", "verbose"]),
+    ),
+    ("max_old_space_size", Expect::Number),
+    ("global_plugins", Expect::StringList),
+    ("plugin_probe_dirs", Expect::StringList),
+    ("plugins", Expect::Array),
+    ("extra_args", Expect::StringList),
+    ("forward_stderr", Expect::Bool),
+    ("transport", Expect::OneOf(&["stdio", "ipc"])),
+    ("env", Expect::AnyObject),
+    ("node_options", Expect::String),
+    ("env_allowlist", Expect::StringList),
+    ("debug_port", Expect::Number),
+    ("debug_break", Expect::Bool),
+    ("debug_server", Expect::OneOf(&["syntax", "semantic"])),
+    ("disable_automatic_typing_acquisition", Expect::Bool),
+    ("npm_location", Expect::String),
+    ("disable_bundled_typescript", Expect::Bool),
+    ("preferences", Expect::AnyObject),
+    ("format_options", Expect::AnyObject),
+    ("formatOptions", Expect::AnyObject),
+    ("watch_options", Expect::AnyObject),
+];
+
+const COMPLETION: &[(&str, Expect)] = &[
+    ("max_entries", Expect::Number),
+    (
+        "entries_limit_strategy",
+        Expect::OneOf(&["sort_text", "prefix"]),
+    ),
+    ("commit_characters", Expect::Bool),
+    ("commit_characters_overrides", Expect::AnyObject),
+];
+
+const JSX_CLOSE_TAG: &[(&str, Expect)] =
+    &[("enable", Expect::Bool), ("filetypes", Expect::StringList)];
+
+const INFERRED: &[(&str, Expect)] = &[
+    ("target", Expect::String),
+    ("module", Expect::String),
+    ("jsx", Expect::String),
+    ("check_js", Expect::Bool),
+    ("strict", Expect::Bool),
+];
+
+const PREFERENCES: &[(&str, Expect)] = &[
+    (
+        "import_module_specifier_preference",
+        Expect::OneOf(&["shortest", "project-relative", "relative", "non-relative"]),
+    ),
+    (
+        "import_module_specifier_ending",
+        Expect::OneOf(&["auto", "minimal", "index", "js"]),
+    ),
+    (
+        "quote_preference",
+        Expect::OneOf(&["auto", "double", "single"]),
+    ),
+    (
+        "include_package_json_auto_imports",
+        Expect::OneOf(&["auto", "on", "off"]),
+    ),
+    ("prefer_type_only_auto_imports", Expect::Bool),
+    ("use_label_details_in_completion_entries", Expect::Bool),
+    ("include_completions_for_module_exports", Expect::Bool),
+    ("include_completions_for_import_statements", Expect::Bool),
+    ("allow_rename_of_import_path", Expect::Bool),
+    ("provide_prefix_and_suffix_text_for_rename", Expect::Bool),
+    ("auto_import_file_exclude_patterns", Expect::StringList),
+];
+
+const FORMAT: &[(&str, Expect)] = &[
+    ("semicolons", Expect::OneOf(&["ignore", "insert", "remove"])),
+    ("quote_style", Expect::OneOf(&["auto", "double", "single"])),
+    ("trailing_commas", Expect::OneOf(&["ignore", "remove"])),
+    ("indent_size", Expect::Number),
+    ("tab_size", Expect::Number),
+    ("convert_tabs_to_spaces", Expect::Bool),
+    ("spacing", Expect::AnyObject),
+];
+
+/// Problems found in `settings`, as `key.path: message` lines. When the
+/// payload contains a `ts-bridge` section (as whole-workspace settings
+/// objects do), only that section is checked.
+pub fn validate_settings(settings: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let Some(map) = settings.as_object() else {
+        return problems;
+    };
+    let roots: Vec<(&str, &Value)> = POSSIBLE_SETTING_ROOTS
+        .iter()
+        .filter_map(|root| Some((*root, map.get(*root)?)))
+        .collect();
+    if roots.is_empty() {
+        check_plugin(map, "", &mut problems);
+    } else {
+        for (root, value) in roots {
+            match value.as_object() {
+                Some(section) => check_plugin(section, root, &mut problems),
+                None => problems.push(format!("{root}: expected a table")),
+            }
+        }
+    }
+    problems
+}
+
+fn check_plugin(map: &Map<String, Value>, path: &str, problems: &mut Vec<String>) {
+    for (key, value) in map {
+        let key_path = join(path, key);
+        if key == "plugin" {
+            match value.as_object() {
+                Some(section) => check_plugin(section, &key_path, problems),
+                None => problems.push(format!("{key_path}: expected a table")),
+            }
+        } else if key.starts_with("typescript.") || key.starts_with("javascript.") {
+            // VS Code style dotted keys.
+        } else if !POSSIBLE_SETTING_ROOTS.contains(&key.as_str()) {
+            check_key(PLUGIN, key, value, &key_path, problems);
+        }
+    }
+}
+
+fn check_key(
+    known: &[(&str, Expect)],
+    key: &str,
+    value: &Value,
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    match known.iter().find(|(name, _)| *name == key) {
+        Some((_, expect)) => check_value(expect, value, path, problems),
+        None => problems.push(format!("{path}: unknown setting")),
+    }
+}
+
+fn check_value(expect: &Expect, value: &Value, path: &str, problems: &mut Vec<String>) {
+    if value.is_null() {
+        return;
+    }
+    let problem = match expect {
+        Expect::Bool => (!value.is_boolean()).then(|| "expected true or false".to_string()),
+        Expect::Number => (!value.is_u64()).then(|| "expected a non-negative integer".to_string()),
+        Expect::String => (!value.is_string()).then(|| "expected a string".to_string()),
+        Expect::StringList => (!value
+            .as_array()
+            .is_some_and(|items| items.iter().all(Value::is_string)))
+        .then(|| "expected a list of strings".to_string()),
+        Expect::Array => (!value.is_array()).then(|| "expected a list".to_string()),
+        Expect::AnyObject => (!value.is_object()).then(|| "expected a table".to_string()),
+        Expect::OneOf(allowed) => (!value.as_str().is_some_and(|v| allowed.contains(&v)))
+            .then(|| format!("expected one of {}", allowed.join(", "))),
+        Expect::Object(known) => match value.as_object() {
+            Some(map) => {
+                for (key, value) in map {
+                    check_key(known, key, value, &join(path, key), problems);
+                }
+                None
+            }
+            None => Some("expected a table".to_string()),
+        },
+    };
+    if let Some(problem) = problem {
+        problems.push(format!("{path}: {problem}"));
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn typos_and_type_mismatches_are_reported() {
+        let settings = json!({
+            "ts-bridge": {
+                "publish_diagnostics_on": "change",
+                "enable_inlay_hints": "yes",
+                "tsserver": { "max_old_space_size": 4096, "log_verbosty": "verbose" },
+                "code_lens": "everything",
+                "typescript": { "inlayHints": {} },
+                "request_timeout_ms": null
+            },
+            "Lua": { "whatever": true }
+        });
+        assert_eq!(
+            validate_settings(&settings),
+            vec![
+                "ts-bridge.code_lens: expected one of off, all, references_only, implementations_only",
+                "ts-bridge.enable_inlay_hints: expected true or false",
+                "ts-bridge.publish_diagnostics_on: unknown setting",
+                "ts-bridge.tsserver.log_verbosty: unknown setting",
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_without_a_root_are_checked_directly() {
+        let settings = json!({ "hover": { "verbosity": 1 }, "typescript.tsdk": "lib" });
+        assert!(validate_settings(&settings).is_empty());
+    }
+}
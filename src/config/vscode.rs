@@ -0,0 +1,285 @@
+//! Reads VS Code's `typescript.*` / `javascript.*` settings (nested objects or
+//! dotted keys, as coc.nvim and copied `settings.json` blobs send them) and
+//! rewrites the ones ts-bridge understands into its native settings shape, so
+//! they go through the regular parsers.
+
+use serde_json::{Map, Value};
+
+const SCOPES: &[&str] = &["typescript", "javascript"];
+
+/// `typescript.inlayHints.<name>.enabled` -> tsserver preference.
+const INLAY_HINTS: &[(&str, &str)] = &[
+    ("parameterNames", "includeInlayParameterNameHints"),
+    ("parameterTypes", "includeInlayFunctionParameterTypeHints"),
+    ("variableTypes", "includeInlayVariableTypeHints"),
+    (
+        "propertyDeclarationTypes",
+        "includeInlayPropertyDeclarationTypeHints",
+    ),
+    (
+        "functionLikeReturnTypes",
+        "includeInlayFunctionLikeReturnTypeHints",
+    ),
+    ("enumMemberValues", "includeInlayEnumMemberValueHints"),
+];
+
+/// `typescript.preferences.<key>` -> native `preferences` key.
+const PREFERENCES: &[(&str, &str)] = &[
+    (
+        "importModuleSpecifier",
+        "import_module_specifier_preference",
+    ),
+    (
+        "importModuleSpecifierEnding",
+        "import_module_specifier_ending",
+    ),
+    ("quoteStyle", "quote_preference"),
+    (
+        "includePackageJsonAutoImports",
+        "include_package_json_auto_imports",
+    ),
+    ("preferTypeOnlyAutoImports", "prefer_type_only_auto_imports"),
+    (
+        "autoImportFileExcludePatterns",
+        "auto_import_file_exclude_patterns",
+    ),
+    (
+        "useAliasesForRenames",
+        "provide_prefix_and_suffix_text_for_rename",
+    ),
+];
+
+/// Returns the native settings equivalent to the VS Code keys in `map`, or
+/// `None` when there are none.
+pub(super) fn translate(map: &Map<String, Value>) -> Option<Value> {
+    let mut native = Map::new();
+    for scope in SCOPES {
+        let Some(settings) = scope_settings(map, scope) else {
+            continue;
+        };
+        let translated = translate_scope(&settings);
+        if !translated.is_empty() {
+            native.insert(scope.to_string(), Value::Object(translated));
+        }
+        if *scope == "typescript" {
+            translate_global(&settings, &mut native);
+        }
+    }
+    (!native.is_empty()).then_some(Value::Object(native))
+}
+
+/// The `scope` object with dotted `scope.a.b` keys folded into it.
+fn scope_settings(map: &Map<String, Value>, scope: &str) -> Option<Map<String, Value>> {
+    let mut settings = map
+        .get(scope)
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let prefix = format!("{scope}.");
+    for (key, value) in map {
+        if let Some(path) = key.strip_prefix(&prefix) {
+            insert_path(&mut settings, path, value.clone());
+        }
+    }
+    (!settings.is_empty()).then_some(settings)
+}
+
+fn insert_path(map: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            let entry = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            if let Some(child) = entry.as_object_mut() {
+                insert_path(child, rest, value);
+            }
+        }
+        None => {
+            map.insert(path.to_string(), value);
+        }
+    }
+}
+
+fn lookup<'a>(settings: &'a Map<String, Value>, path: &[&str]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(settings.get(*first)?, |value, key| value.get(key))
+}
+
+fn translate_scope(settings: &Map<String, Value>) -> Map<String, Value> {
+    let mut scope = Map::new();
+
+    let mut inlay_hints = Map::new();
+    for (name, preference) in INLAY_HINTS {
+        if let Some(value) = lookup(settings, &["inlayHints", name, "enabled"]) {
+            inlay_hints.insert(preference.to_string(), value.clone());
+        }
+    }
+    if let Some(suppress) = lookup(
+        settings,
+        &[
+            "inlayHints",
+            "parameterNames",
+            "suppressWhenArgumentMatchesName",
+        ],
+    )
+    .and_then(Value::as_bool)
+    {
+        inlay_hints.insert(
+            "includeInlayParameterNameHintsWhenArgumentMatchesName".to_string(),
+            Value::Bool(!suppress),
+        );
+    }
+    if let Some(suppress) = lookup(
+        settings,
+        &["inlayHints", "variableTypes", "suppressWhenTypeMatchesName"],
+    )
+    .and_then(Value::as_bool)
+    {
+        inlay_hints.insert(
+            "includeInlayVariableTypeHintsWhenTypeMatchesName".to_string(),
+            Value::Bool(!suppress),
+        );
+    }
+    if !inlay_hints.is_empty() {
+        scope.insert("inlay_hints".to_string(), Value::Object(inlay_hints));
+    }
+
+    let mut preferences = Map::new();
+    for (key, native) in PREFERENCES {
+        if let Some(value) = lookup(settings, &["preferences", key]) {
+            preferences.insert(native.to_string(), value.clone());
+        }
+    }
+    if let Some(value) = lookup(settings, &["suggest", "autoImports"]) {
+        preferences.insert(
+            "include_completions_for_module_exports".to_string(),
+            value.clone(),
+        );
+    }
+    if let Some(value) = lookup(
+        settings,
+        &["suggest", "includeCompletionsForImportStatements"],
+    ) {
+        preferences.insert(
+            "include_completions_for_import_statements".to_string(),
+            value.clone(),
+        );
+    }
+    if !preferences.is_empty() {
+        scope.insert("preferences".to_string(), Value::Object(preferences));
+    }
+
+    if let Some(format) = settings.get("format").and_then(Value::as_object) {
+        let mut format_options = Map::new();
+        let mut spacing = Map::new();
+        for (key, value) in format {
+            if key == "semicolons" {
+                format_options.insert("semicolons".to_string(), value.clone());
+            } else if key.starts_with("insertSpace") || key.starts_with("placeOpenBrace") {
+                spacing.insert(key.clone(), value.clone());
+            }
+        }
+        if !spacing.is_empty() {
+            format_options.insert("spacing".to_string(), Value::Object(spacing));
+        }
+        if !format_options.is_empty() {
+            scope.insert("format_options".to_string(), Value::Object(format_options));
+        }
+    }
+
+    scope
+}
+
+/// Plugin-wide settings that only exist under `typescript.*` in VS Code.
+fn translate_global(settings: &Map<String, Value>, native: &mut Map<String, Value>) {
+    let mut tsserver = Map::new();
+    if let Some(tsdk) = settings.get("tsdk") {
+        tsserver.insert("tsdk".to_string(), tsdk.clone());
+    }
+    if let Some(locale) = settings.get("locale").filter(|locale| *locale != "auto") {
+        tsserver.insert("locale".to_string(), locale.clone());
+    }
+    if let Some(memory) = lookup(settings, &["tsserver", "maxTsServerMemory"]) {
+        tsserver.insert("max_old_space_size".to_string(), memory.clone());
+    }
+    if let Some(paths) = lookup(settings, &["tsserver", "pluginPaths"]) {
+        tsserver.insert("plugin_probe_dirs".to_string(), paths.clone());
+    }
+    if !tsserver.is_empty() {
+        native.insert("tsserver".to_string(), Value::Object(tsserver));
+    }
+
+    if let Some(value) = lookup(settings, &["suggest", "completeFunctionCalls"]) {
+        native.insert("complete_function_calls".to_string(), value.clone());
+    }
+
+    let references = lookup(settings, &["referencesCodeLens", "enabled"]).and_then(Value::as_bool);
+    let implementations =
+        lookup(settings, &["implementationsCodeLens", "enabled"]).and_then(Value::as_bool);
+    if references.is_some() || implementations.is_some() {
+        let mode = match (
+            references.unwrap_or(false),
+            implementations.unwrap_or(false),
+        ) {
+            (true, true) => "all",
+            (true, false) => "references_only",
+            (false, true) => "implementations_only",
+            (false, false) => "off",
+        };
+        native.insert("code_lens".to_string(), Value::from(mode));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn dotted_and_nested_keys_translate_alike() {
+        let dotted = json!({
+            "typescript.inlayHints.parameterNames.enabled": "all",
+            "typescript.preferences.importModuleSpecifier": "relative",
+            "typescript.tsdk": "node_modules/typescript/lib",
+        });
+        let nested = json!({
+            "typescript": {
+                "inlayHints": { "parameterNames": { "enabled": "all" } },
+                "preferences": { "importModuleSpecifier": "relative" },
+                "tsdk": "node_modules/typescript/lib",
+            }
+        });
+        let expected = json!({
+            "typescript": {
+                "inlay_hints": { "includeInlayParameterNameHints": "all" },
+                "preferences": { "import_module_specifier_preference": "relative" },
+            },
+            "tsserver": { "tsdk": "node_modules/typescript/lib" },
+        });
+        assert_eq!(
+            translate(dotted.as_object().unwrap()),
+            Some(expected.clone())
+        );
+        assert_eq!(translate(nested.as_object().unwrap()), Some(expected));
+    }
+
+    #[test]
+    fn code_lens_toggles_combine_into_a_mode() {
+        let settings = json!({
+            "typescript.referencesCodeLens.enabled": true,
+            "typescript.implementationsCodeLens.enabled": true,
+        });
+        let native = translate(settings.as_object().unwrap()).expect("translated");
+        assert_eq!(native["code_lens"], json!("all"));
+    }
+
+    #[test]
+    fn native_settings_are_left_alone() {
+        let settings = json!({ "typescript": { "enable_inlay_hints": true } });
+        assert_eq!(translate(settings.as_object().unwrap()), None);
+    }
+}
@@ -0,0 +1,528 @@
+//! Talking to a running daemon from the command line:
+//! `ts-bridge daemon status|stop|restart|reload|warm` send control requests the daemon
+//! answers on its LSP listener when they arrive instead of `initialize`, and
+//! `ts-bridge connect` proxies stdio to it. Both find the daemon through the
+//! discovery file it writes on startup.
+
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use lsp_server::{Message, Request, RequestId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Overrides where the discovery file lives.
+pub const DISCOVERY_FILE_ENV: &str = "TS_BRIDGE_DISCOVERY_FILE";
+
+/// Projects, sessions and tsserver processes of the daemon.
+pub const STATUS_METHOD: &str = "ts-bridge/daemon/status";
+/// Shuts every project down, then exits once the reply is sent.
+pub const STOP_METHOD: &str = "ts-bridge/daemon/stop";
+/// Like stop, then starts the daemon again with its original arguments.
+pub const RESTART_METHOD: &str = "ts-bridge/daemon/restart";
+/// Re-reads the daemon's settings file and applies it, and the request's
+/// params, without dropping sessions.
+pub const RELOAD_METHOD: &str = "ts-bridge/daemon/reload";
+/// Starts the project at `params.path` before any editor attaches to it.
+/// Sessions may send it too, to warm a sibling project.
+pub const PRELOAD_METHOD: &str = "ts-bridge/preloadProject";
+
+pub fn is_control_method(method: &str) -> bool {
+    matches!(
+        method,
+        STATUS_METHOD | STOP_METHOD | RESTART_METHOD | RELOAD_METHOD | PRELOAD_METHOD
+    )
+}
+
+/// Where a daemon listens: `--listen HOST:PORT`, `--socket PATH` or, on
+/// Windows, `--pipe NAME`.
+#[derive(Debug, Clone)]
+pub enum DaemonEndpoint {
+    Tcp(SocketAddr),
+    Socket(PathBuf),
+    /// Full pipe path, `\\.\pipe\NAME`.
+    Pipe(String),
+}
+
+impl std::fmt::Display for DaemonEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Socket(path) => write!(f, "{}", path.display()),
+            Self::Pipe(path) => write!(f, "{path}"),
+        }
+    }
+}
+
+/// `NAME` -> `\\.\pipe\NAME`; full pipe paths are kept as they are.
+pub fn pipe_path(name: &str) -> String {
+    const PREFIX: &str = r"\\.\pipe\";
+    if name.len() >= PREFIX.len() && name[..PREFIX.len()].eq_ignore_ascii_case(PREFIX) {
+        name.to_string()
+    } else {
+        format!("{PREFIX}{name}")
+    }
+}
+
+/// What a daemon advertises about itself in the discovery file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discovery {
+    pub pid: u32,
+    pub version: String,
+    /// `HOST:PORT` of a TCP daemon.
+    pub addr: Option<String>,
+    /// Socket path of a daemon started with `--socket`.
+    pub socket: Option<PathBuf>,
+    /// Pipe path of a daemon started with `--pipe`.
+    pub pipe: Option<String>,
+    pub started_epoch_seconds: u64,
+}
+
+impl Discovery {
+    pub fn endpoint(&self) -> anyhow::Result<DaemonEndpoint> {
+        if let Some(socket) = &self.socket {
+            return Ok(DaemonEndpoint::Socket(socket.clone()));
+        }
+        if let Some(pipe) = &self.pipe {
+            return Ok(DaemonEndpoint::Pipe(pipe.clone()));
+        }
+        match &self.addr {
+            Some(addr) => {
+                Ok(DaemonEndpoint::Tcp(addr.parse().with_context(|| {
+                    format!("invalid daemon address {addr}")
+                })?))
+            }
+            None => Err(anyhow!("discovery file names no address")),
+        }
+    }
+}
+
+/// `$TS_BRIDGE_DISCOVERY_FILE`, else `daemon.json` in a `ts-bridge`
+/// directory under `$XDG_RUNTIME_DIR`, or in `ts-bridge-UID` under the temp
+/// dir, which other users can write to.
+pub fn discovery_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(DISCOVERY_FILE_ENV).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(runtime) => PathBuf::from(runtime).join("ts-bridge"),
+        None => std::env::temp_dir().join(user_dir_name()),
+    };
+    dir.join("daemon.json")
+}
+
+#[cfg(unix)]
+fn user_dir_name() -> String {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    format!("ts-bridge-{}", unsafe { libc::geteuid() })
+}
+
+/// The Windows temp dir is already per user.
+#[cfg(not(unix))]
+fn user_dir_name() -> String {
+    "ts-bridge".to_string()
+}
+
+/// Writes `discovery` to `path`, replacing what an earlier daemon left. A
+/// missing directory is created readable by the current user only; one
+/// owned by someone else is refused.
+pub fn write_discovery(path: &Path, discovery: &Discovery) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent)?;
+        check_owner(parent)?;
+    }
+    let staged = path.with_extension("json.tmp");
+    std::fs::write(&staged, serde_json::to_vec_pretty(discovery)?)?;
+    std::fs::rename(&staged, path)
+}
+
+/// Reads the discovery file at `path`, refusing one another user owns: it
+/// could point commands at an endpoint of theirs.
+pub fn read_discovery(path: &Path) -> anyhow::Result<Discovery> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("no daemon discovery file at {}", path.display()))?;
+    check_owner(path).with_context(|| format!("refusing {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("parse {}", path.display()))
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
+#[cfg(unix)]
+fn check_owner(path: &Path) -> io::Result<()> {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    owned_by(path, unsafe { libc::geteuid() })
+}
+
+#[cfg(not(unix))]
+fn check_owner(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn owned_by(path: &Path, uid: u32) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let owner = std::fs::metadata(path)?.uid();
+    if owner == uid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is owned by uid {owner}, not {uid}", path.display()),
+        ))
+    }
+}
+
+/// The daemon to talk to: the flags, then `TS_BRIDGE_DAEMON_SOCKET` /
+/// `TS_BRIDGE_DAEMON_PIPE` / `TS_BRIDGE_DAEMON_LISTEN`, then the discovery
+/// file the daemon wrote.
+pub fn resolve_endpoint(explicit: Option<DaemonEndpoint>) -> anyhow::Result<DaemonEndpoint> {
+    if let Some(endpoint) = explicit {
+        return Ok(endpoint);
+    }
+    if let Ok(socket) = std::env::var("TS_BRIDGE_DAEMON_SOCKET") {
+        return Ok(DaemonEndpoint::Socket(socket.into()));
+    }
+    if let Ok(pipe) = std::env::var("TS_BRIDGE_DAEMON_PIPE") {
+        return Ok(DaemonEndpoint::Pipe(pipe_path(&pipe)));
+    }
+    if let Ok(listen) = std::env::var("TS_BRIDGE_DAEMON_LISTEN") {
+        return Ok(DaemonEndpoint::Tcp(
+            listen.parse().context("parse TS_BRIDGE_DAEMON_LISTEN")?,
+        ));
+    }
+    let path = discovery_path();
+    read_discovery(&path)
+        .context("is the daemon running? pass --listen, --socket or --pipe to reach it directly")?
+        .endpoint()
+}
+
+/// Removes the file at `path` if it still describes process `pid`; a newer
+/// daemon may have taken it over.
+pub fn remove_discovery(path: &Path, pid: u32) {
+    if read_discovery(path).is_ok_and(|discovery| discovery.pid == pid) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// A daemon connection that can be split into its two directions, on
+/// either end.
+pub(crate) trait CloneableStream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn close_write(&self) -> io::Result<()>;
+    /// Makes reads fail with `WouldBlock` or `TimedOut` after `timeout`.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl CloneableStream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn close_write(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl CloneableStream for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn close_write(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Pipes cannot be half-closed; the daemon ends the session on `exit`.
+/// Their reads have no timeout, so pipe clients are never sent heartbeats;
+/// Windows closes a pipe whose client process died.
+#[cfg(windows)]
+impl CloneableStream for crate::pipe::PipeStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.clone())
+    }
+
+    fn close_write(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sends `method` with `params` to the daemon at `endpoint` and returns its
+/// result.
+pub fn request(endpoint: &DaemonEndpoint, method: &str, params: Value) -> anyhow::Result<Value> {
+    match endpoint {
+        DaemonEndpoint::Tcp(addr) => exchange(connect_tcp(addr)?, method, params),
+        #[cfg(unix)]
+        DaemonEndpoint::Socket(path) => exchange(connect_unix(path)?, method, params),
+        #[cfg(not(unix))]
+        DaemonEndpoint::Socket(_) => Err(unix_unsupported()),
+        #[cfg(windows)]
+        DaemonEndpoint::Pipe(path) => exchange(connect_pipe(path)?, method, params),
+        #[cfg(not(windows))]
+        DaemonEndpoint::Pipe(_) => Err(pipe_unsupported()),
+    }
+}
+
+/// `ts-bridge connect`: relays stdin to the daemon and its replies to stdout
+/// until the daemon closes the connection.
+pub fn proxy_stdio(endpoint: &DaemonEndpoint) -> anyhow::Result<()> {
+    match endpoint {
+        DaemonEndpoint::Tcp(addr) => proxy(connect_tcp(addr)?),
+        #[cfg(unix)]
+        DaemonEndpoint::Socket(path) => proxy(connect_unix(path)?),
+        #[cfg(not(unix))]
+        DaemonEndpoint::Socket(_) => Err(unix_unsupported()),
+        #[cfg(windows)]
+        DaemonEndpoint::Pipe(path) => proxy(connect_pipe(path)?),
+        #[cfg(not(windows))]
+        DaemonEndpoint::Pipe(_) => Err(pipe_unsupported()),
+    }
+}
+
+fn connect_tcp(addr: &SocketAddr) -> anyhow::Result<TcpStream> {
+    TcpStream::connect(addr).with_context(|| format!("connect to daemon at {addr}"))
+}
+
+#[cfg(unix)]
+fn connect_unix(path: &Path) -> anyhow::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(path)
+        .with_context(|| format!("connect to daemon at {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> anyhow::Error {
+    anyhow!("unix domain sockets are not supported on this platform")
+}
+
+#[cfg(windows)]
+fn connect_pipe(path: &str) -> anyhow::Result<crate::pipe::PipeStream> {
+    crate::pipe::PipeStream::connect(path).with_context(|| format!("connect to daemon at {path}"))
+}
+
+#[cfg(not(windows))]
+fn pipe_unsupported() -> anyhow::Error {
+    anyhow!("named pipes are only supported on Windows")
+}
+
+fn proxy<S: CloneableStream>(stream: S) -> anyhow::Result<()> {
+    let mut upstream = stream.try_clone().context("clone daemon stream")?;
+    thread::spawn(move || {
+        let _ = relay(&mut io::stdin().lock(), &mut upstream);
+        let _ = upstream.close_write();
+    });
+    let mut downstream = stream;
+    relay(&mut downstream, &mut io::stdout().lock()).context("relay daemon output")
+}
+
+/// Copies `from` into `to`, flushing after every read so LSP messages are
+/// not held back waiting for a newline.
+fn relay(from: &mut impl Read, to: &mut impl Write) -> io::Result<()> {
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = from.read(&mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        to.write_all(&buf[..read])?;
+        to.flush()?;
+    }
+}
+
+fn exchange<S: Read + Write>(mut stream: S, method: &str, params: Value) -> anyhow::Result<Value> {
+    Message::Request(Request::new(RequestId::from(1), method.to_string(), params))
+        .write(&mut stream)
+        .context("send daemon control request")?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        match Message::read(&mut reader).context("read daemon reply")? {
+            Some(Message::Response(response)) => {
+                return match response.error {
+                    Some(error) => Err(anyhow!("daemon refused {method}: {}", error.message)),
+                    None => Ok(response.result.unwrap_or(Value::Null)),
+                };
+            }
+            Some(_) => continue,
+            None => return Err(anyhow!("daemon closed the connection without replying")),
+        }
+    }
+}
+
+/// Human-readable form of a [`STATUS_METHOD`] result.
+pub fn format_status(status: &Value) -> String {
+    let text = |value: &Value| value.as_str().unwrap_or("?").to_string();
+    let mut out = format!(
+        "ts-bridge {} daemon (pid {}) on {}, up {}\n",
+        text(&status["version"]),
+        status["pid"],
+        text(&status["endpoint"]),
+        format_duration(status["uptime_seconds"].as_u64().unwrap_or_default()),
+    );
+    match status["idle_ttl_seconds"].as_u64() {
+        Some(ttl) => out.push_str(&format!("idle TTL {}\n", format_duration(ttl))),
+        None => out.push_str("idle eviction off\n"),
+    }
+    if let Some(max) = status["max_projects"].as_u64() {
+        out.push_str(&format!("at most {max} project(s)\n"));
+    }
+    if let Some(max) = status["max_sessions_per_project"].as_u64() {
+        out.push_str(&format!("at most {max} session(s) per project\n"));
+    }
+    let projects = status["projects"].as_array().cloned().unwrap_or_default();
+    if projects.is_empty() {
+        out.push_str("no projects\n");
+    }
+    for project in &projects {
+        let tsserver = &project["tsserver"];
+        let sessions = project["session_count"].as_u64().unwrap_or_default();
+        out.push_str(&format!(
+            "{}  {}  {} session{}",
+            text(&project["label"]),
+            text(&project["root"]),
+            sessions,
+            if sessions == 1 { "" } else { "s" },
+        ));
+        if let Some(version) = tsserver["version"].as_str() {
+            out.push_str(&format!("  TypeScript {version}"));
+        }
+        for server in ["syntax", "semantic"] {
+            if let Some(pid) = tsserver[format!("{server}_pid")].as_u64() {
+                out.push_str(&format!("  {server} pid {pid}"));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `3725` -> `1h 2m`.
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn discovery_file_round_trips_and_is_only_removed_by_its_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ts-bridge").join("daemon.json");
+        let discovery = Discovery {
+            pid: 4242,
+            version: "0.2.3".to_string(),
+            addr: Some("127.0.0.1:41234".to_string()),
+            socket: None,
+            pipe: None,
+            started_epoch_seconds: 1_700_000_000,
+        };
+        write_discovery(&path, &discovery).unwrap();
+        let read = read_discovery(&path).unwrap();
+        assert_eq!(read, discovery);
+        assert!(
+            matches!(read.endpoint().unwrap(), DaemonEndpoint::Tcp(addr) if addr.port() == 41234)
+        );
+
+        remove_discovery(&path, 1);
+        assert!(path.exists());
+        remove_discovery(&path, 4242);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discovery_files_of_other_users_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path().join("ts-bridge");
+        let path = parent.join("daemon.json");
+        let discovery = Discovery {
+            pid: 4242,
+            version: "0.2.3".to_string(),
+            addr: Some("127.0.0.1:41234".to_string()),
+            socket: None,
+            pipe: None,
+            started_epoch_seconds: 1_700_000_000,
+        };
+        write_discovery(&path, &discovery).unwrap();
+        let mode = std::fs::metadata(&parent).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // SAFETY: geteuid has no preconditions and cannot fail.
+        let uid = unsafe { libc::geteuid() };
+        assert!(owned_by(&path, uid).is_ok());
+        let err = owned_by(&path, uid + 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn discovery_falls_back_to_a_per_user_temp_dir() {
+        let name = user_dir_name();
+        assert!(name.starts_with("ts-bridge"));
+        #[cfg(unix)]
+        assert_eq!(name, format!("ts-bridge-{}", unsafe { libc::geteuid() }));
+    }
+
+    #[test]
+    fn pipe_names_expand_to_pipe_paths() {
+        assert_eq!(pipe_path("ts-bridge"), r"\\.\pipe\ts-bridge");
+        assert_eq!(pipe_path(r"\\.\PIPE\ts-bridge"), r"\\.\PIPE\ts-bridge");
+    }
+
+    #[test]
+    fn status_lists_projects_and_their_servers() {
+        let status = json!({
+            "version": "0.2.3",
+            "pid": 4242,
+            "endpoint": "127.0.0.1:7777",
+            "uptime_seconds": 3725,
+            "idle_ttl_seconds": null,
+            "projects": [{
+                "label": "web",
+                "root": "/repo/web",
+                "session_count": 1,
+                "tsserver": { "version": "5.6.2", "syntax_pid": 100, "semantic_pid": null },
+            }],
+        });
+        assert_eq!(
+            format_status(&status),
+            "ts-bridge 0.2.3 daemon (pid 4242) on 127.0.0.1:7777, up 1h 2m\n\
+             idle eviction off\n\
+             web  /repo/web  1 session  TypeScript 5.6.2  syntax pid 100\n"
+        );
+    }
+}
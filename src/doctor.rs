@@ -0,0 +1,469 @@
+//! `ts-bridge doctor`: checks the environment a project would run in —
+//! settings files, node, the tsserver the provider picks, plugins, the
+//! project's tsconfig and the daemon — and says how to fix what is off.
+//! Nothing is started: tsserver is located with [`Provider::preview`], so the
+//! bundled TypeScript is not installed either.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::{Config, TsserverLaunchOptions};
+use crate::control;
+use crate::process::{MIN_NODE_VERSION, ProcessError, node_version, resolve_node};
+use crate::protocol::diagnostics::MIN_REGION_TYPESCRIPT_VERSION;
+use crate::provider::{BinarySource, Provider, TsserverBinary};
+use crate::server::{MIN_TYPESCRIPT_VERSION, parse_typescript_version, workspace_config};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Worth knowing, nothing to fix.
+    Note,
+    Warning,
+    /// ts-bridge will not work until this is fixed.
+    Error,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub check: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub root: PathBuf,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// The worst status found; `doctor` exits 1 on [`Status::Error`].
+    pub fn status(&self) -> Status {
+        self.findings
+            .iter()
+            .map(|finding| finding.status)
+            .max()
+            .unwrap_or(Status::Ok)
+    }
+
+    fn push(&mut self, check: &'static str, status: Status, detail: String, hint: Option<&str>) {
+        self.findings.push(Finding {
+            check,
+            status,
+            detail,
+            hint: hint.map(str::to_string),
+        });
+    }
+}
+
+/// Diagnoses the project at `root` with the settings it would get in the
+/// editor (settings files and `TS_BRIDGE_*` overrides).
+pub fn run(root: &Path) -> Report {
+    let mut report = Report {
+        root: root.to_path_buf(),
+        findings: Vec::new(),
+    };
+    let (config, problems) = workspace_config(root);
+    check_settings(&mut report, problems);
+    check_node(&mut report, &config.plugin().tsserver);
+    let binary = check_tsserver(&mut report, &config, root);
+    check_plugins(
+        &mut report,
+        &config.plugin().tsserver,
+        binary.as_ref(),
+        root,
+    );
+    check_project(&mut report, root);
+    check_daemon(&mut report);
+    report
+}
+
+/// `[ok] node: ...` lines with indented hints, then a verdict.
+pub fn render(report: &Report) -> String {
+    let mut out = format!(
+        "ts-bridge {} in {}\n",
+        env!("CARGO_PKG_VERSION"),
+        report.root.display()
+    );
+    for finding in &report.findings {
+        let _ = writeln!(
+            out,
+            "[{}] {}: {}",
+            finding.status.label(),
+            finding.check,
+            finding.detail
+        );
+        if let Some(hint) = &finding.hint {
+            let _ = writeln!(out, "    fix: {hint}");
+        }
+    }
+    let verdict = match report.status() {
+        Status::Ok | Status::Note => "no problems found",
+        Status::Warning => "ts-bridge should work, but see the warnings above",
+        Status::Error => "ts-bridge cannot start tsserver until the errors above are fixed",
+    };
+    let _ = writeln!(out, "{verdict}");
+    out
+}
+
+fn check_settings(report: &mut Report, problems: Vec<String>) {
+    if problems.is_empty() {
+        report.push(
+            "settings",
+            Status::Ok,
+            "settings files are valid".into(),
+            None,
+        );
+    }
+    for problem in problems {
+        report.push(
+            "settings",
+            Status::Warning,
+            problem,
+            Some("fix or remove the setting; it is ignored until then"),
+        );
+    }
+}
+
+fn check_node(report: &mut Report, launch: &TsserverLaunchOptions) {
+    match resolve_node(launch.node_path.as_deref()) {
+        Ok(node) => {
+            let version = node_version(&node).unwrap_or_else(|| "unknown version".into());
+            report.push(
+                "node",
+                Status::Ok,
+                format!("{} ({version})", node.display()),
+                None,
+            );
+        }
+        Err(ProcessError::NodeTooOld { node, version }) => report.push(
+            "node",
+            Status::Error,
+            format!("{} is {version}", node.display()),
+            Some(&format!(
+                "install Node.js {}.{} or newer, or point `tsserver.node_path` at one",
+                MIN_NODE_VERSION.0, MIN_NODE_VERSION.1
+            )),
+        ),
+        Err(err) => report.push(
+            "node",
+            Status::Error,
+            err.to_string(),
+            Some("install Node.js or set `tsserver.node_path`"),
+        ),
+    }
+}
+
+fn check_tsserver(report: &mut Report, config: &Config, root: &Path) -> Option<TsserverBinary> {
+    let launch = &config.plugin().tsserver;
+    let binary = match Provider::new(root).preview(launch) {
+        Ok(binary) => binary,
+        Err(err) => {
+            report.push(
+                "tsserver",
+                Status::Error,
+                err.to_string(),
+                Some("run `npm install -D typescript` in the project or set `tsserver.path`"),
+            );
+            return None;
+        }
+    };
+    let version = binary.version.as_deref();
+    report.push(
+        "tsserver",
+        Status::Ok,
+        format!(
+            "{} (TypeScript {}, from {})",
+            binary.executable.display(),
+            version.unwrap_or("unknown"),
+            binary.source.label()
+        ),
+        None,
+    );
+
+    if matches!(binary.source, BinarySource::Bundled) {
+        if binary.executable.is_file() {
+            report.push(
+                "tsserver",
+                Status::Note,
+                "no project TypeScript was found; the bundled copy is used".into(),
+                Some("add `typescript` to the project's devDependencies to pin its version"),
+            );
+        } else {
+            let npm = launch.npm_location.as_deref().unwrap_or("npm");
+            let found = which::which(npm).is_ok();
+            report.push(
+                "tsserver",
+                if found { Status::Note } else { Status::Error },
+                format!("the bundled TypeScript is installed with {npm} on first use"),
+                (!found).then_some(
+                    "install npm, set `tsserver.npm_location`, or add `typescript` to the project",
+                ),
+            );
+        }
+    }
+
+    let (min_major, min_minor) = MIN_TYPESCRIPT_VERSION;
+    let (region_major, region_minor) = MIN_REGION_TYPESCRIPT_VERSION;
+    match version.and_then(parse_typescript_version) {
+        Some(found) if found < MIN_TYPESCRIPT_VERSION => report.push(
+            "tsserver",
+            Status::Warning,
+            format!(
+                "TypeScript {} is older than {min_major}.{min_minor}; the server turns off what it lacks",
+                version.unwrap_or_default()
+            ),
+            Some(&format!(
+                "upgrade the project's `typescript` to {min_major}.{min_minor} or newer"
+            )),
+        ),
+        Some(found) if found < MIN_REGION_TYPESCRIPT_VERSION => report.push(
+            "tsserver",
+            Status::Note,
+            format!(
+                "TypeScript {} lacks region diagnostics and paste edits ({region_major}.{region_minor}+)",
+                version.unwrap_or_default()
+            ),
+            None,
+        ),
+        Some(_) => {}
+        None => report.push(
+            "tsserver",
+            Status::Warning,
+            "could not read the TypeScript version next to tsserver.js".into(),
+            Some("check that tsserver.js sits in a `typescript/lib` directory"),
+        ),
+    }
+    Some(binary)
+}
+
+fn check_plugins(
+    report: &mut Report,
+    launch: &TsserverLaunchOptions,
+    binary: Option<&TsserverBinary>,
+    root: &Path,
+) {
+    let mut probes: Vec<PathBuf> = binary
+        .and_then(|binary| binary.plugin_probe.clone())
+        .into_iter()
+        .collect();
+    for dir in launch.plugin_probe_dirs.iter().chain(
+        launch
+            .plugins
+            .iter()
+            .filter_map(|plugin| plugin.location.as_ref()),
+    ) {
+        let dir = root.join(dir);
+        if dir.is_dir() {
+            report.push(
+                "plugins",
+                Status::Ok,
+                format!("probe location {}", dir.display()),
+                None,
+            );
+        } else {
+            report.push(
+                "plugins",
+                Status::Warning,
+                format!("probe location {} does not exist", dir.display()),
+                Some("fix `tsserver.plugin_probe_dirs` or the plugin's `location`"),
+            );
+        }
+        probes.push(dir);
+    }
+    // tsserver also looks next to the project.
+    probes.push(root.to_path_buf());
+
+    let mut names: Vec<&str> = launch.global_plugins.iter().map(String::as_str).collect();
+    for plugin in &launch.plugins {
+        if !names.contains(&plugin.name.as_str()) {
+            names.push(&plugin.name);
+        }
+    }
+    for name in names {
+        let found = probes
+            .iter()
+            .map(|probe| probe.join("node_modules").join(name))
+            .find(|dir| dir.join("package.json").is_file());
+        match found {
+            Some(dir) => report.push(
+                "plugins",
+                Status::Ok,
+                format!("{name} at {}", dir.display()),
+                None,
+            ),
+            None => report.push(
+                "plugins",
+                Status::Warning,
+                format!("{name} is not installed in any probe location"),
+                Some(&format!(
+                    "run `npm install -D {name}` or set the plugin's `location`"
+                )),
+            ),
+        }
+    }
+}
+
+fn check_project(report: &mut Report, root: &Path) {
+    let config_file = ["tsconfig.json", "jsconfig.json"]
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file());
+    match config_file {
+        Some(path) => report.push("project", Status::Ok, path.display().to_string(), None),
+        None => report.push(
+            "project",
+            Status::Warning,
+            format!("no tsconfig.json or jsconfig.json in {}", root.display()),
+            Some(
+                "run `npx tsc --init`; until then files get an inferred project built from \
+                 `inferred_project_options`",
+            ),
+        ),
+    }
+}
+
+fn check_daemon(report: &mut Report) {
+    let configured = [
+        "TS_BRIDGE_DAEMON_SOCKET",
+        "TS_BRIDGE_DAEMON_PIPE",
+        "TS_BRIDGE_DAEMON_LISTEN",
+    ]
+    .iter()
+    .any(|name| std::env::var_os(name).is_some());
+    let discovery = control::discovery_path();
+    if !configured && !discovery.exists() {
+        report.push(
+            "daemon",
+            Status::Note,
+            "not running; editors start ts-bridge themselves".into(),
+            None,
+        );
+        return;
+    }
+    let status = control::resolve_endpoint(None).and_then(|endpoint| {
+        let status = control::request(&endpoint, control::STATUS_METHOD, Value::Null)?;
+        Ok((endpoint, status))
+    });
+    match status {
+        Ok((endpoint, status)) => {
+            let projects = status["projects"].as_array().map_or(0, Vec::len);
+            let version = status["version"].as_str().unwrap_or("?");
+            report.push(
+                "daemon",
+                Status::Ok,
+                format!("ts-bridge {version} at {endpoint}, {projects} project(s)"),
+                None,
+            );
+            if version != env!("CARGO_PKG_VERSION") {
+                report.push(
+                    "daemon",
+                    Status::Warning,
+                    format!(
+                        "the daemon runs ts-bridge {version}, this binary is {}",
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                    Some("restart it with `ts-bridge daemon restart`"),
+                );
+            }
+        }
+        Err(err) => report.push(
+            "daemon",
+            Status::Warning,
+            format!("{err:#}"),
+            Some(&format!(
+                "start the daemon, or delete the stale {} if it is not meant to run",
+                discovery.display()
+            )),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_tsconfig_and_old_typescript_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("node_modules/typescript/lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(lib.join("tsserver.js"), "").unwrap();
+        std::fs::write(
+            dir.path().join("node_modules/typescript/package.json"),
+            r#"{ "name": "typescript", "version": "3.9.7" }"#,
+        )
+        .unwrap();
+
+        let config = Config::new(Default::default());
+        let mut report = Report {
+            root: dir.path().to_path_buf(),
+            findings: Vec::new(),
+        };
+        check_tsserver(&mut report, &config, dir.path());
+        check_project(&mut report, dir.path());
+
+        let warnings: Vec<&str> = report
+            .findings
+            .iter()
+            .filter(|finding| finding.status == Status::Warning)
+            .map(|finding| finding.detail.as_str())
+            .collect();
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[0].starts_with("TypeScript 3.9.7 is older than 4.7"));
+        assert!(warnings[1].starts_with("no tsconfig.json"));
+        assert_eq!(report.status(), Status::Warning);
+        assert!(render(&report).ends_with("ts-bridge should work, but see the warnings above\n"));
+    }
+
+    #[test]
+    fn typescript_the_server_degrades_is_a_warning_not_a_note() {
+        for (version, status) in [
+            ("4.6.4", Status::Warning),
+            ("5.4.5", Status::Note),
+            ("5.6.3", Status::Ok),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let lib = dir.path().join("node_modules/typescript/lib");
+            std::fs::create_dir_all(&lib).unwrap();
+            std::fs::write(lib.join("tsserver.js"), "").unwrap();
+            std::fs::write(
+                dir.path().join("node_modules/typescript/package.json"),
+                format!(r#"{{ "name": "typescript", "version": "{version}" }}"#),
+            )
+            .unwrap();
+            let mut report = Report {
+                root: dir.path().to_path_buf(),
+                findings: Vec::new(),
+            };
+            check_tsserver(&mut report, &Config::new(Default::default()), dir.path());
+            let worst = report
+                .findings
+                .iter()
+                .filter(|finding| finding.check == "tsserver")
+                .map(|finding| finding.status)
+                .max()
+                .unwrap_or(Status::Ok);
+            assert_eq!(worst, status, "TypeScript {version}: {:?}", report.findings);
+        }
+    }
+}
@@ -7,7 +7,8 @@
 //! tsserver’s 1-D spans without round-tripping through the process.
 
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
 
 use lsp_types::{Position as LspPosition, Range as LspRange, Uri};
 
@@ -61,6 +62,45 @@ impl DocumentStore {
         self.docs.contains_key(uri.as_str())
     }
 
+    pub fn open_count(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Returns the last version reported by the client for an open document.
+    pub fn version(&self, uri: &Uri) -> Option<i32> {
+        self.docs.get(uri.as_str()).and_then(|doc| doc.version)
+    }
+
+    pub fn language_id(&self, uri: &Uri) -> Option<&str> {
+        self.docs.get(uri.as_str())?.language_id.as_deref()
+    }
+
+    /// Returns the text of `line` (without its line break) for an open document.
+    pub fn line_text(&self, uri: &Uri, line: u32) -> Option<&str> {
+        let doc = self.docs.get(uri.as_str())?;
+        let metrics = doc.line_metrics.get(line as usize)?;
+        doc.text
+            .get(metrics.start_byte..metrics.start_byte + metrics.content_bytes)
+    }
+
+    pub fn line_count(&self, uri: &Uri) -> Option<usize> {
+        Some(self.docs.get(uri.as_str())?.line_metrics.len())
+    }
+
+    pub fn text(&self, uri: &Uri) -> Option<&str> {
+        Some(self.docs.get(uri.as_str())?.text.as_str())
+    }
+
+    /// Position just past the last character of an open document.
+    pub fn end_position(&self, uri: &Uri) -> Option<PluginPosition> {
+        let doc = self.docs.get(uri.as_str())?;
+        let last = doc.line_metrics.len().saturating_sub(1);
+        Some(PluginPosition {
+            line: last as u32,
+            character: doc.line_metrics.get(last)?.content_utf16,
+        })
+    }
+
     pub fn open_documents(&self) -> Vec<OpenDocumentSnapshot> {
         self.docs
             .iter()
@@ -286,6 +326,250 @@ pub struct OpenDocumentSnapshot {
     pub language_id: Option<String>,
 }
 
+/// A session's document notification, as its project sees it.
+#[derive(Debug, Clone)]
+pub enum DocumentEvent {
+    Open {
+        uri: Uri,
+        text: String,
+        version: Option<i32>,
+        language_id: Option<String>,
+    },
+    /// `text` is the session's buffer after `changes`. Sessions that own
+    /// the document (see [`SharedDocuments::owners`]) leave it out.
+    Change {
+        uri: Uri,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        text: Option<String>,
+        version: Option<i32>,
+    },
+    Close {
+        uri: Uri,
+    },
+}
+
+/// What tsserver should hear about a [`DocumentEvent`].
+#[derive(Debug, Clone)]
+pub enum SharedSync {
+    /// The session's own update applies as is.
+    Forward,
+    /// tsserver already has this state, or never opened the document.
+    Skip,
+    /// The session's buffer differs from tsserver's copy, so its edit is
+    /// sent as this whole-document replacement instead. The last writer
+    /// wins: edits of the `overwritten` sessions that this session never
+    /// saw are gone from tsserver's copy.
+    Replace {
+        edit: TextDocumentContentChangeEvent,
+        overwritten: Vec<u64>,
+    },
+    /// Like [`SharedSync::Replace`], but the change came without the
+    /// session's text; nothing is sent until the session sends its buffer.
+    Resync,
+}
+
+/// Document URI -> the one session that has it open and in sync, shared
+/// with the sessions so they can skip sending their whole buffer.
+pub type DocumentOwners = Arc<Mutex<HashMap<String, u64>>>;
+
+/// The documents one project's tsserver has open, shared by every session
+/// attached to the project. A file is opened by its first session and
+/// closed with its last; edits are applied in the order they arrive, and a
+/// session whose buffer fell behind another session's edits replaces the
+/// document rather than sending ranges tsserver would misapply. That drops
+/// the other sessions' edits, which [`SharedSync::Replace`] names.
+#[derive(Default)]
+pub struct SharedDocuments {
+    store: DocumentStore,
+    holders: HashMap<String, Holders>,
+    owners: DocumentOwners,
+}
+
+#[derive(Default)]
+struct Holders {
+    open: BTreeSet<u64>,
+    /// Sessions whose buffer matches the shared copy.
+    in_sync: BTreeSet<u64>,
+}
+
+impl SharedDocuments {
+    /// Documents a single session holds in sync with tsserver. Its edits
+    /// apply as is, so it may send them without its text.
+    pub fn owners(&self) -> DocumentOwners {
+        Arc::clone(&self.owners)
+    }
+
+    pub fn apply(&mut self, session: u64, event: &DocumentEvent) -> SharedSync {
+        let sync = self.apply_event(session, event);
+        let uri = match event {
+            DocumentEvent::Open { uri, .. }
+            | DocumentEvent::Change { uri, .. }
+            | DocumentEvent::Close { uri } => uri,
+        };
+        self.publish_owner(uri.as_str());
+        sync
+    }
+
+    fn apply_event(&mut self, session: u64, event: &DocumentEvent) -> SharedSync {
+        match event {
+            DocumentEvent::Open {
+                uri,
+                text,
+                version,
+                language_id,
+            } => {
+                let Some(holders) = self.holders.get_mut(uri.as_str()) else {
+                    self.store.open(uri, text, *version, language_id.clone());
+                    self.holders.insert(
+                        uri.to_string(),
+                        Holders {
+                            open: BTreeSet::from([session]),
+                            in_sync: BTreeSet::from([session]),
+                        },
+                    );
+                    return SharedSync::Forward;
+                };
+                holders.open.insert(session);
+                if self.store.text(uri) == Some(text.as_str()) {
+                    holders.in_sync.insert(session);
+                    return SharedSync::Skip;
+                }
+                self.replace(session, uri, text, *version)
+            }
+            DocumentEvent::Change {
+                uri,
+                changes,
+                text,
+                version,
+            } => {
+                let Some(holders) = self.holders.get(uri.as_str()) else {
+                    return SharedSync::Skip;
+                };
+                if !holders.open.contains(&session) {
+                    return SharedSync::Skip;
+                }
+                if !holders.in_sync.contains(&session) {
+                    return match text {
+                        Some(text) => self.replace(session, uri, text, *version),
+                        None => SharedSync::Resync,
+                    };
+                }
+                self.store.apply_changes(uri, changes, *version);
+                if let Some(holders) = self.holders.get_mut(uri.as_str()) {
+                    holders.in_sync = BTreeSet::from([session]);
+                }
+                SharedSync::Forward
+            }
+            DocumentEvent::Close { uri } => {
+                let Some(holders) = self.holders.get_mut(uri.as_str()) else {
+                    return SharedSync::Skip;
+                };
+                holders.open.remove(&session);
+                holders.in_sync.remove(&session);
+                if !holders.open.is_empty() {
+                    return SharedSync::Skip;
+                }
+                self.holders.remove(uri.as_str());
+                self.store.close(uri);
+                SharedSync::Forward
+            }
+        }
+    }
+
+    /// Forgets `session`, returning the documents nobody else has open,
+    /// which tsserver should close.
+    pub fn release(&mut self, session: u64) -> Vec<String> {
+        let mut closed = Vec::new();
+        let mut touched = Vec::new();
+        self.holders.retain(|uri, holders| {
+            if !holders.open.remove(&session) {
+                return true;
+            }
+            holders.in_sync.remove(&session);
+            touched.push(uri.clone());
+            if holders.open.is_empty() {
+                closed.push(uri.clone());
+                return false;
+            }
+            true
+        });
+        for uri in &closed {
+            if let Ok(parsed) = uri.parse::<Uri>() {
+                self.store.close(&parsed);
+            }
+        }
+        for uri in &touched {
+            self.publish_owner(uri);
+        }
+        closed
+    }
+
+    /// Forgets everything; tsserver restarted without any open files, and
+    /// every session reopens its own.
+    pub fn reset(&mut self) {
+        self.store = DocumentStore::default();
+        self.holders.clear();
+        self.lock_owners().clear();
+    }
+
+    /// Sessions that have `uri` open.
+    pub fn holder_count(&self, uri: &Uri) -> usize {
+        self.holders
+            .get(uri.as_str())
+            .map_or(0, |holders| holders.open.len())
+    }
+
+    fn publish_owner(&self, uri: &str) {
+        let owner = self.holders.get(uri).and_then(|holders| {
+            let mut open = holders.open.iter();
+            match (open.next(), open.next()) {
+                (Some(session), None) if holders.in_sync.contains(session) => Some(*session),
+                _ => None,
+            }
+        });
+        let mut owners = self.lock_owners();
+        match owner {
+            Some(session) => owners.insert(uri.to_string(), session),
+            None => owners.remove(uri),
+        };
+    }
+
+    fn lock_owners(&self) -> std::sync::MutexGuard<'_, HashMap<String, u64>> {
+        self.owners
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn replace(&mut self, session: u64, uri: &Uri, text: &str, version: Option<i32>) -> SharedSync {
+        let end = self.store.end_position(uri).unwrap_or(PluginPosition {
+            line: 0,
+            character: 0,
+        });
+        let language_id = self.store.language_id(uri).map(str::to_string);
+        self.store.open(uri, text, version, language_id);
+        let overwritten = match self.holders.get_mut(uri.as_str()) {
+            Some(holders) => std::mem::replace(&mut holders.in_sync, BTreeSet::from([session]))
+                .into_iter()
+                .filter(|other| *other != session)
+                .collect(),
+            None => Vec::new(),
+        };
+        SharedSync::Replace {
+            edit: TextDocumentContentChangeEvent {
+                range: Some(PluginRange {
+                    start: PluginPosition {
+                        line: 0,
+                        character: 0,
+                    },
+                    end,
+                }),
+                text: text.to_string(),
+            },
+            overwritten,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,4 +680,133 @@ mod tests {
             "close removes snapshot entirely"
         );
     }
+
+    fn open_event(text: &str) -> DocumentEvent {
+        DocumentEvent::Open {
+            uri: sample_uri(),
+            text: text.into(),
+            version: Some(1),
+            language_id: Some("typescript".into()),
+        }
+    }
+
+    fn insert_event(at: u32, inserted: &str, text: &str) -> DocumentEvent {
+        let position = PluginPosition {
+            line: 0,
+            character: at,
+        };
+        DocumentEvent::Change {
+            uri: sample_uri(),
+            changes: vec![TextDocumentContentChangeEvent {
+                range: Some(PluginRange {
+                    start: position.clone(),
+                    end: position,
+                }),
+                text: inserted.into(),
+            }],
+            text: Some(text.into()),
+            version: Some(2),
+        }
+    }
+
+    #[test]
+    fn shared_documents_open_once_and_close_with_the_last_session() {
+        let mut shared = SharedDocuments::default();
+        let uri = sample_uri();
+        let close = DocumentEvent::Close { uri: uri.clone() };
+
+        assert!(matches!(
+            shared.apply(1, &open_event("a")),
+            SharedSync::Forward
+        ));
+        assert!(matches!(
+            shared.apply(2, &open_event("a")),
+            SharedSync::Skip
+        ));
+        assert_eq!(shared.holder_count(&uri), 2);
+
+        assert!(matches!(shared.apply(1, &close), SharedSync::Skip));
+        assert!(matches!(shared.apply(2, &close), SharedSync::Forward));
+        assert_eq!(shared.holder_count(&uri), 0);
+
+        shared.apply(1, &open_event("a"));
+        assert_eq!(shared.release(1), vec![uri.to_string()]);
+    }
+
+    #[test]
+    fn stale_session_edits_replace_the_shared_document() {
+        let mut shared = SharedDocuments::default();
+        let uri = sample_uri();
+        shared.apply(1, &open_event("ab\nc"));
+        shared.apply(2, &open_event("ab\nc"));
+
+        assert!(matches!(
+            shared.apply(1, &insert_event(0, "x", "xab\nc")),
+            SharedSync::Forward
+        ));
+        // Session 2 has not seen session 1's edit, so its range is stale.
+        let SharedSync::Replace { edit, overwritten } =
+            shared.apply(2, &insert_event(2, "y", "ayb\nc"))
+        else {
+            panic!("expected a whole-document replacement");
+        };
+        assert_eq!(edit.text, "ayb\nc");
+        assert_eq!(overwritten, vec![1], "session 1's edit is dropped");
+        let range = edit.range.expect("replacement has a range");
+        assert_eq!((range.end.line, range.end.character), (1, 1));
+        assert_eq!(shared.store.text(&uri), Some("ayb\nc"));
+
+        // Session 1 is now the stale one.
+        assert!(matches!(
+            shared.apply(1, &insert_event(1, "z", "xzab\nc")),
+            SharedSync::Replace { overwritten, .. } if overwritten == [2]
+        ));
+    }
+
+    #[test]
+    fn owners_track_the_sole_in_sync_holder() {
+        let mut shared = SharedDocuments::default();
+        let uri = sample_uri();
+        let owners = shared.owners();
+        let owner = || owners.lock().unwrap().get(uri.as_str()).copied();
+        let without_text = |event: DocumentEvent| match event {
+            DocumentEvent::Change {
+                uri,
+                changes,
+                version,
+                ..
+            } => DocumentEvent::Change {
+                uri,
+                changes,
+                text: None,
+                version,
+            },
+            other => other,
+        };
+
+        shared.apply(1, &open_event("ab\nc"));
+        assert_eq!(owner(), Some(1));
+        assert!(matches!(
+            shared.apply(1, &without_text(insert_event(0, "x", "xab\nc"))),
+            SharedSync::Forward
+        ));
+        assert_eq!(shared.store.text(&uri), Some("xab\nc"));
+
+        shared.apply(2, &open_event("ab\nc"));
+        assert_eq!(owner(), None, "a second session shares the document");
+        shared.apply(2, &insert_event(0, "y", "yab\nc"));
+        shared.release(2);
+        assert_eq!(owner(), None, "session 1 is behind session 2's edit");
+
+        // A change that raced the ownership check asks for the buffer.
+        assert!(matches!(
+            shared.apply(1, &without_text(insert_event(1, "z", "xzab\nc"))),
+            SharedSync::Resync
+        ));
+        assert!(matches!(
+            shared.apply(1, &insert_event(1, "z", "xzab\nc")),
+            SharedSync::Replace { .. }
+        ));
+        assert_eq!(owner(), Some(1));
+    }
 }
@@ -1,12 +1,26 @@
+#[cfg(unix)]
+mod activation;
 pub mod api;
+pub mod bench;
+pub mod check;
+pub mod codemod;
 pub mod config;
+pub mod control;
+pub mod doctor;
 pub mod documents;
+pub mod logging;
+#[cfg(windows)]
+pub mod pipe;
 pub mod process;
 pub mod protocol;
 pub mod provider;
+pub mod record;
+pub mod replay;
 pub mod rpc;
 pub mod server;
+pub mod telemetry;
 pub mod types;
 pub mod utils;
 
+pub use api::Bridge;
 pub use server::{DaemonConfig, run_daemon_server, run_stdio_server};
@@ -0,0 +1,314 @@
+//! Process-wide logging. Without a log file this is plain `env_logger` on
+//! stderr. With one (`--log-file` or `TS_BRIDGE_LOG_FILE`) every record is a
+//! JSON line tagged with the session and project that logged it, and the file
+//! is rotated by size, so a long-running daemon shared by several editors can
+//! be debugged after the fact. [`set_level`] overrides `RUST_LOG` at runtime
+//! when the daemon reloads its settings.
+
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::LevelFilter;
+use serde_json::{Map, Value, json};
+
+pub const LOG_FILE_ENV: &str = "TS_BRIDGE_LOG_FILE";
+
+/// Size at which the log file is rotated.
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated files kept next to the live one (`ts-bridge.log.1` is the newest).
+const KEPT_LOGS: usize = 3;
+
+thread_local! {
+    static CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+/// Identifiers attached to records logged from the current thread.
+#[derive(Debug, Clone, Default)]
+struct LogContext {
+    session: Option<u64>,
+    project: Option<String>,
+}
+
+static INIT: Once = Once::new();
+
+/// `LevelFilter as usize` set by [`set_level`], or [`NO_OVERRIDE`].
+static LEVEL_OVERRIDE: AtomicUsize = AtomicUsize::new(NO_OVERRIDE);
+/// The most verbose level `RUST_LOG` lets through, restored by
+/// `set_level(None)`.
+static DEFAULT_MAX_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+const NO_OVERRIDE: usize = usize::MAX;
+
+/// Installs the global logger; later calls do nothing. `log_file` falls back
+/// to `TS_BRIDGE_LOG_FILE`, and `RUST_LOG` filters either way (file logs
+/// default to `info`).
+pub fn init(log_file: Option<&Path>) {
+    INIT.call_once(|| install(log_file));
+}
+
+fn install(log_file: Option<&Path>) {
+    let env_file = std::env::var_os(LOG_FILE_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let Some(path) = log_file.map(Path::to_path_buf).or(env_file) else {
+        register(
+            env_logger::Builder::from_default_env().build(),
+            env_logger::Builder::new()
+                .filter_level(LevelFilter::Trace)
+                .build(),
+        );
+        return;
+    };
+    let file = match RotatingFile::open(path.clone(), MAX_LOG_BYTES, KEPT_LOGS) {
+        Ok(file) => file,
+        Err(err) => {
+            register(
+                env_logger::Builder::from_default_env().build(),
+                env_logger::Builder::new()
+                    .filter_level(LevelFilter::Trace)
+                    .build(),
+            );
+            log::warn!(
+                "cannot open log file {}: {err}; logging to stderr",
+                path.display()
+            );
+            return;
+        }
+    };
+    register(
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).build(),
+        env_logger::Builder::new()
+            .filter_level(LevelFilter::Trace)
+            .format(|buf, record| {
+                let line = CONTEXT.with(|context| record_json(record, &context.borrow()));
+                writeln!(buf, "{line}")
+            })
+            .target(env_logger::Target::Pipe(Box::new(file)))
+            .build(),
+    );
+}
+
+/// Installs a logger that decides with `filter` (the `RUST_LOG` one) unless
+/// a level was set at runtime, and writes through `writer`, which lets
+/// everything through.
+fn register(filter: env_logger::Logger, writer: env_logger::Logger) {
+    let max_level = filter.filter();
+    if log::set_boxed_logger(Box::new(Logger { filter, writer })).is_ok() {
+        DEFAULT_MAX_LEVEL.store(max_level as usize, Ordering::Relaxed);
+        log::set_max_level(max_level);
+    }
+}
+
+/// Logs at `level` from now on, whatever `RUST_LOG` says; `None` goes back
+/// to `RUST_LOG`.
+pub fn set_level(level: Option<LevelFilter>) {
+    LEVEL_OVERRIDE.store(
+        level.map_or(NO_OVERRIDE, |level| level as usize),
+        Ordering::Relaxed,
+    );
+    log::set_max_level(level.unwrap_or_else(|| {
+        level_from_usize(DEFAULT_MAX_LEVEL.load(Ordering::Relaxed)).unwrap_or(LevelFilter::Off)
+    }));
+}
+
+/// The level [`set_level`] put in place, if any.
+pub fn level_override() -> Option<LevelFilter> {
+    level_from_usize(LEVEL_OVERRIDE.load(Ordering::Relaxed))
+}
+
+fn level_from_usize(value: usize) -> Option<LevelFilter> {
+    LevelFilter::iter().find(|level| *level as usize == value)
+}
+
+struct Logger {
+    filter: env_logger::Logger,
+    writer: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        match level_override() {
+            Some(level) => metadata.level() <= level,
+            None => self.filter.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let enabled = match level_override() {
+            Some(level) => record.level() <= level,
+            None => self.filter.matches(record),
+        };
+        if enabled {
+            self.writer.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.writer.flush();
+    }
+}
+
+/// Tags records logged from this thread with `session`.
+pub fn set_session(session: u64) {
+    CONTEXT.with(|context| context.borrow_mut().session = Some(session));
+}
+
+/// Tags records logged from this thread with `project`.
+pub fn set_project(project: &str) {
+    CONTEXT.with(|context| context.borrow_mut().project = Some(project.to_string()));
+}
+
+fn record_json(record: &log::Record<'_>, context: &LogContext) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+    let mut line = Map::new();
+    line.insert("ts_ms".to_string(), json!(timestamp));
+    line.insert("level".to_string(), json!(record.level().as_str()));
+    line.insert("target".to_string(), json!(record.target()));
+    line.insert("message".to_string(), json!(record.args().to_string()));
+    if let Some(session) = context.session {
+        line.insert("session".to_string(), json!(session));
+    }
+    if let Some(project) = &context.project {
+        line.insert("project".to_string(), json!(project));
+    }
+    Value::Object(line)
+}
+
+/// Appends to `path`, moving it to `path.1` (and older files up by one)
+/// before a write would take it past `max_bytes`.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.path)?;
+        } else {
+            for index in (1..self.keep).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_carry_the_thread_context() {
+        let context = LogContext {
+            session: Some(7),
+            project: Some("web".to_string()),
+        };
+        let line = record_json(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("ts_bridge::server")
+                .args(format_args!("slow hover"))
+                .build(),
+            &context,
+        );
+        assert_eq!(line["level"], json!("WARN"));
+        assert_eq!(line["message"], json!("slow hover"));
+        assert_eq!(line["session"], json!(7));
+        assert_eq!(line["project"], json!("web"));
+        assert!(line["ts_ms"].is_u64());
+    }
+
+    #[test]
+    fn runtime_level_overrides_and_restores_the_default() {
+        set_level(Some(LevelFilter::Debug));
+        assert_eq!(level_override(), Some(LevelFilter::Debug));
+        assert_eq!(log::max_level(), LevelFilter::Debug);
+        set_level(None);
+        assert_eq!(level_override(), None);
+    }
+
+    #[test]
+    fn log_file_rotates_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ts-bridge.log");
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("ts-bridge.log.1")).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("ts-bridge.log.2")).unwrap(),
+            "second\n"
+        );
+        assert!(!dir.path().join("ts-bridge.log.3").exists());
+    }
+}
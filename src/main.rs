@@ -1,51 +1,620 @@
-use anyhow::Context;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use clap::{Args, FromArgMatches, Parser, Subcommand};
+
+/// Bridges LSP clients to tsserver. Without a command, serves LSP on stdio.
+#[derive(Debug, Parser)]
+#[command(name = "ts-bridge", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(flatten)]
+    globals: Globals,
+    /// Record LSP and tsserver traffic to FILE
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+const GLOBAL_HEADING: &str = "Global options";
+
+/// Flags every command accepts, anywhere on the command line.
+#[derive(Debug, Clone, Default, Args)]
+struct Globals {
+    /// error, warn, info, debug, trace or off; overrides RUST_LOG
+    #[arg(long, global = true, help_heading = GLOBAL_HEADING, value_name = "LEVEL", value_parser = parse_log_level)]
+    log_level: Option<log::LevelFilter>,
+    /// Write JSON-lines logs to PATH instead of stderr
+    #[arg(long, global = true, help_heading = GLOBAL_HEADING, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Settings file applied over the discovered ones (for `daemon`: the
+    /// daemon settings file)
+    #[arg(long, global = true, help_heading = GLOBAL_HEADING, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the shared daemon, or control a running one
+    #[command(args_conflicts_with_subcommands = true)]
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonAction>,
+        #[command(flatten)]
+        serve: Box<DaemonServe>,
+    },
+    /// Relay stdio to a running daemon
+    Connect {
+        #[command(flatten)]
+        endpoint: Endpoint,
+    },
+    /// Print a project's diagnostics and fail on errors
+    Check {
+        /// tsconfig.json/jsconfig.json, or its directory
+        #[arg(short, long, value_name = "PATH", conflicts_with = "targets")]
+        project: Option<PathBuf>,
+        /// human, json or github
+        #[arg(long, value_name = "FORMAT", default_value = "human")]
+        format: ts_bridge::check::OutputFormat,
+        /// Files, directories or globs to check instead of the project
+        #[arg(value_name = "FILE|DIR|GLOB")]
+        targets: Vec<String>,
+    },
+    /// Organize imports or rename a file on disk
+    #[command(subcommand)]
+    Codemod(CodemodCommand),
+    /// Report what a project would run with and what looks wrong
+    Doctor {
+        /// The project directory (default: the working directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        root: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Play a recorded session against a fresh bridge
+    Replay {
+        #[arg(value_name = "FILE")]
+        recording: PathBuf,
+        /// The recorded connection to play (default: the first)
+        #[arg(long, value_name = "N")]
+        connection: Option<u64>,
+        /// Send each message once the previous request is answered
+        #[arg(long)]
+        fast: bool,
+    },
+    /// Time hover, completion and diagnostics
+    Bench {
+        #[arg(value_name = "PROJECT", default_value = ".")]
+        project: PathBuf,
+        /// The file to open (default: the largest source file)
+        #[arg(long, value_name = "FILE")]
+        file: Option<PathBuf>,
+        /// Cycles to time
+        #[arg(short = 'n', long, value_name = "N", default_value_t = ts_bridge::bench::DEFAULT_ITERATIONS)]
+        iterations: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DaemonAction {
+    /// Print the running daemon's projects and sessions
+    Status(Control),
+    /// Stop the running daemon
+    Stop(Control),
+    /// Restart the running daemon's projects
+    Restart(Control),
+    /// Re-read the daemon settings file, then apply the given overrides;
+    /// `--log-level` sets the daemon's level
+    Reload {
+        /// Stop projects nobody used for this long
+        #[arg(long, value_name = "SECONDS|off", value_parser = parse_duration_or_off)]
+        idle_ttl: Option<OrOff<Duration>>,
+        /// Most projects kept running
+        #[arg(long, value_name = "N|off", value_parser = parse_count_or_off)]
+        max_projects: Option<OrOff<usize>>,
+        #[command(flatten)]
+        control: Control,
+    },
+    /// Start the project at PATH so the first editor does not wait for it
+    Warm {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+        #[command(flatten)]
+        control: Control,
+    },
+}
+
+/// How `daemon status|stop|restart|reload|warm` reach the daemon; the
+/// discovery file names it by default.
+#[derive(Debug, Args)]
+struct Control {
+    #[command(flatten)]
+    endpoint: Endpoint,
+    /// Print the daemon's answer as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+struct Endpoint {
+    /// The daemon's TCP address
+    #[arg(long, value_name = "HOST:PORT")]
+    listen: Option<SocketAddr>,
+    /// The daemon's Unix socket
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+    /// The daemon's Windows named pipe
+    #[arg(long, value_name = "NAME")]
+    pipe: Option<String>,
+}
+
+impl Endpoint {
+    fn resolve(self) -> anyhow::Result<ts_bridge::control::DaemonEndpoint> {
+        use ts_bridge::control::DaemonEndpoint;
+        let explicit = match (self.listen, self.socket, self.pipe) {
+            (Some(addr), _, _) => Some(DaemonEndpoint::Tcp(addr)),
+            (_, Some(path), _) => Some(DaemonEndpoint::Socket(path)),
+            (_, _, Some(name)) => Some(DaemonEndpoint::Pipe(ts_bridge::control::pipe_path(&name))),
+            _ => None,
+        };
+        ts_bridge::control::resolve_endpoint(explicit)
+    }
+}
+
+/// The daemon's own flags. Each falls back to its `TS_BRIDGE_DAEMON_*`
+/// variable, so `ts-bridge daemon` and `TS_BRIDGE_DAEMON=1 ts-bridge` read
+/// the same environment.
+#[derive(Debug, Args)]
+struct DaemonServe {
+    #[command(flatten)]
+    transport: Transport,
+    /// Socket permission bits, such as 0600
+    #[arg(long, value_name = "MODE", env = "TS_BRIDGE_DAEMON_SOCKET_MODE", value_parser = parse_socket_mode)]
+    socket_mode: Option<u32>,
+    /// Stop projects nobody used for this long
+    #[arg(long, value_name = "SECONDS|off", env = "TS_BRIDGE_DAEMON_IDLE_TTL", value_parser = parse_duration_or_off)]
+    idle_ttl: Option<OrOff<Duration>>,
+    /// Most projects kept running
+    #[arg(long, value_name = "N|off", env = "TS_BRIDGE_DAEMON_MAX_PROJECTS", value_parser = parse_count_or_off)]
+    max_projects: Option<OrOff<usize>>,
+    /// Most editors attached to one project
+    #[arg(long, value_name = "N|off", env = "TS_BRIDGE_DAEMON_MAX_SESSIONS_PER_PROJECT", value_parser = parse_count_or_off)]
+    max_sessions_per_project: Option<OrOff<usize>>,
+    /// tsserver heap limit for projects at or below ROOT; repeatable
+    #[arg(long, value_name = "ROOT=MB", env = "TS_BRIDGE_DAEMON_PROJECT_MAX_MEMORY", value_delimiter = PATH_LIST_SEPARATOR, value_parser = parse_project_memory)]
+    project_max_memory: Vec<(PathBuf, u32)>,
+    /// How long a dropped session may be resumed
+    #[arg(long, value_name = "SECONDS|off", env = "TS_BRIDGE_DAEMON_RESUME_WINDOW", value_parser = parse_duration_or_off)]
+    resume_window: Option<OrOff<Duration>>,
+    /// Drop clients that stay silent this long after a heartbeat
+    #[arg(long, value_name = "SECONDS|off", env = "TS_BRIDGE_DAEMON_CLIENT_TIMEOUT", value_parser = parse_duration_or_off)]
+    client_timeout: Option<OrOff<Duration>>,
+    /// Exit after this long without work
+    #[arg(long, value_name = "SECONDS|off", env = "TS_BRIDGE_DAEMON_EXIT_WHEN_IDLE", value_parser = parse_duration_or_off)]
+    exit_when_idle: Option<OrOff<Duration>>,
+    /// Record LSP and tsserver traffic to FILE
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+}
+
+/// Where the daemon listens. A flag replaces the transports named by
+/// `TS_BRIDGE_DAEMON_LISTEN`/`_SOCKET`/`_PIPE`; two flags are an error. clap
+/// would count those variables in the conflict, so [`Transport::or_env`]
+/// reads them instead.
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+struct Transport {
+    /// Listen on TCP
+    #[arg(long, value_name = "HOST:PORT")]
+    listen: Option<SocketAddr>,
+    /// Listen on a Unix socket
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+    /// Listen on a Windows named pipe
+    #[arg(long, value_name = "NAME")]
+    pipe: Option<String>,
+}
+
+impl Transport {
+    fn or_env(self) -> anyhow::Result<Self> {
+        if self.listen.is_some() || self.socket.is_some() || self.pipe.is_some() {
+            return Ok(self);
+        }
+        let listen = match std::env::var("TS_BRIDGE_DAEMON_LISTEN") {
+            Ok(listen) => Some(listen.parse().context("parse TS_BRIDGE_DAEMON_LISTEN")?),
+            Err(_) => None,
+        };
+        Ok(Self {
+            listen,
+            socket: std::env::var_os("TS_BRIDGE_DAEMON_SOCKET").map(PathBuf::from),
+            pipe: std::env::var("TS_BRIDGE_DAEMON_PIPE").ok(),
+        })
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum CodemodCommand {
+    /// Organize the imports of every matched file
+    OrganizeImports {
+        #[arg(value_name = "FILE|DIR|GLOB", required = true)]
+        targets: Vec<String>,
+    },
+    /// Move a file or directory and rewrite the imports that point into it
+    RenameFile {
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+}
+
+/// A limit that `off` disables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OrOff<T>(Option<T>);
+
+#[cfg(windows)]
+const PATH_LIST_SEPARATOR: char = ';';
+#[cfg(not(windows))]
+const PATH_LIST_SEPARATOR: char = ':';
 
 fn main() -> anyhow::Result<()> {
-    let mut args = std::env::args().skip(1);
-    let Some(first) = args.next() else {
-        if let Some(config) = daemon_config_from_env()? {
-            return ts_bridge::run_daemon_server(config);
+    let cli = match Cli::try_parse_from(std::env::args_os()) {
+        Ok(cli) => cli,
+        // Help and version go to stdout with status 0, errors to stderr.
+        Err(err) => err.exit(),
+    };
+    run(cli)
+}
+
+fn run(cli: Cli) -> anyhow::Result<()> {
+    let Cli {
+        globals,
+        record,
+        command,
+    } = cli;
+    let Some(command) = command else {
+        if record.is_none() && daemon_enabled_by_env()? {
+            return ts_bridge::run_daemon_server(DaemonServe::from_env()?.into_config(globals)?);
         }
+        globals.apply()?;
+        ts_bridge::record::init(record.as_deref());
         return ts_bridge::run_stdio_server();
     };
 
-    match first.as_str() {
-        "daemon" => {
-            let config = parse_daemon_args(args)?;
-            ts_bridge::run_daemon_server(config)
+    match command {
+        Command::Daemon {
+            action: None,
+            serve,
+        } => ts_bridge::run_daemon_server(serve.into_config(globals)?),
+        Command::Daemon {
+            action: Some(action),
+            ..
+        } => run_daemon_action(action, globals),
+        Command::Connect { endpoint } => {
+            globals.apply()?;
+            ts_bridge::control::proxy_stdio(&endpoint.resolve()?)
+        }
+        Command::Check {
+            project,
+            format,
+            targets,
+        } => {
+            let options = ts_bridge::check::CheckOptions { targets, project };
+            // Exit 1 is for diagnostics; a check that could not run exits 2
+            // so CI can tell the two apart.
+            let files = match globals.apply().and_then(|()| {
+                let cwd = current_dir()?;
+                let files = ts_bridge::check::run(&options, &cwd)?;
+                print!("{}", ts_bridge::check::render(format, &files, &cwd));
+                Ok(files)
+            }) {
+                Ok(files) => files,
+                Err(err) => {
+                    eprintln!("Error: {err:?}");
+                    std::process::exit(2);
+                }
+            };
+            if ts_bridge::check::has_errors(&files) {
+                std::process::exit(1);
+            }
+            Ok(())
         }
-        "-V" | "--version" => {
-            print_version();
+        Command::Codemod(codemod) => {
+            globals.apply()?;
+            let codemod = match codemod {
+                CodemodCommand::OrganizeImports { targets } => {
+                    ts_bridge::codemod::Codemod::OrganizeImports { targets }
+                }
+                CodemodCommand::RenameFile { old, new } => {
+                    ts_bridge::codemod::Codemod::RenameFile { old, new }
+                }
+            };
+            let cwd = current_dir()?;
+            let changed = ts_bridge::codemod::run(&codemod, &cwd)?;
+            print!("{}", ts_bridge::codemod::render(&codemod, &changed, &cwd));
             Ok(())
         }
-        "-h" | "--help" => {
-            print_usage();
+        Command::Doctor { root, json } => {
+            globals.apply()?;
+            let root = std::fs::canonicalize(&root)
+                .with_context(|| format!("resolve {}", root.display()))?;
+            let report = ts_bridge::doctor::run(&root);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", ts_bridge::doctor::render(&report));
+            }
+            if report.status() == ts_bridge::doctor::Status::Error {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Replay {
+            recording,
+            connection,
+            fast,
+        } => {
+            globals.apply()?;
+            let options = ts_bridge::replay::ReplayOptions {
+                recording,
+                connection,
+                fast,
+            };
+            let summary = ts_bridge::replay::run(&options)?;
+            print!("{}", ts_bridge::replay::render(&summary));
+            Ok(())
+        }
+        Command::Bench {
+            project,
+            file,
+            iterations,
+        } => {
+            globals.apply()?;
+            let options = ts_bridge::bench::BenchOptions {
+                project,
+                file,
+                iterations,
+            };
+            let report = ts_bridge::bench::run(&options)?;
+            print!("{}", ts_bridge::bench::render(&report));
             Ok(())
         }
-        other => Err(anyhow::anyhow!("unknown subcommand {other}")),
     }
 }
 
-fn daemon_config_from_env() -> anyhow::Result<Option<ts_bridge::DaemonConfig>> {
-    let Some(value) = std::env::var("TS_BRIDGE_DAEMON").ok() else {
-        return Ok(None);
-    };
-    if !parse_env_bool("TS_BRIDGE_DAEMON", &value)? {
-        return Ok(None);
+impl Globals {
+    /// Sets up logging and the settings file for a command that runs here.
+    fn apply(&self) -> anyhow::Result<()> {
+        ts_bridge::logging::init(self.log_file.as_deref());
+        if self.log_level.is_some() {
+            ts_bridge::logging::set_level(self.log_level);
+        }
+        if let Some(path) = &self.config {
+            let path = std::fs::canonicalize(path)
+                .with_context(|| format!("--config {}", path.display()))?;
+            if !path.is_file() {
+                anyhow::bail!("--config {} is not a file", path.display());
+            }
+            ts_bridge::config::set_settings_file(path);
+        }
+        Ok(())
     }
+}
 
-    let mut config = ts_bridge::DaemonConfig::default();
-    if let Ok(listen) = std::env::var("TS_BRIDGE_DAEMON_LISTEN") {
-        config.listen = Some(listen.parse()?);
+impl DaemonServe {
+    /// The daemon flags as `TS_BRIDGE_DAEMON=1 ts-bridge` gets them: from
+    /// the environment alone.
+    fn from_env() -> anyhow::Result<Self> {
+        let command = Self::augment_args(clap::Command::new("ts-bridge"));
+        let matches = command.try_get_matches_from(["ts-bridge"])?;
+        Ok(Self::from_arg_matches(&matches)?)
     }
-    if let Ok(socket) = std::env::var("TS_BRIDGE_DAEMON_SOCKET") {
-        config.socket = Some(socket.into());
+
+    /// The global flags name the daemon's log file, log level and settings
+    /// file; the level and settings file fall back to
+    /// `TS_BRIDGE_DAEMON_LOG_LEVEL` and `TS_BRIDGE_DAEMON_CONFIG`.
+    fn into_config(self, globals: Globals) -> anyhow::Result<ts_bridge::DaemonConfig> {
+        let defaults = ts_bridge::DaemonConfig::default();
+        let transport = self.transport.or_env()?;
+        let log_level = match (
+            globals.log_level,
+            std::env::var("TS_BRIDGE_DAEMON_LOG_LEVEL"),
+        ) {
+            (Some(level), _) => Some(level),
+            (None, Ok(level)) => {
+                Some(parse_log_level(&level).context("parse TS_BRIDGE_DAEMON_LOG_LEVEL")?)
+            }
+            (None, Err(_)) => None,
+        };
+        Ok(ts_bridge::DaemonConfig {
+            listen: transport.listen,
+            socket: transport.socket,
+            socket_mode: self.socket_mode.unwrap_or(defaults.socket_mode),
+            pipe: transport
+                .pipe
+                .map(|name| ts_bridge::control::pipe_path(&name)),
+            idle_ttl: self.idle_ttl.map_or(defaults.idle_ttl, |limit| limit.0),
+            max_projects: self
+                .max_projects
+                .map_or(defaults.max_projects, |limit| limit.0),
+            max_sessions_per_project: self
+                .max_sessions_per_project
+                .map_or(defaults.max_sessions_per_project, |limit| limit.0),
+            project_max_memory: self.project_max_memory,
+            resume_window: self
+                .resume_window
+                .map_or(defaults.resume_window, |limit| limit.0),
+            client_timeout: self
+                .client_timeout
+                .map_or(defaults.client_timeout, |limit| limit.0),
+            log_file: globals.log_file,
+            log_level,
+            settings_file: globals
+                .config
+                .or_else(|| std::env::var_os("TS_BRIDGE_DAEMON_CONFIG").map(PathBuf::from)),
+            exit_when_idle: self
+                .exit_when_idle
+                .map_or(defaults.exit_when_idle, |limit| limit.0),
+            record: self.record,
+        })
+    }
+}
+
+/// Whether `TS_BRIDGE_DAEMON` asks plain `ts-bridge` to run the daemon.
+fn daemon_enabled_by_env() -> anyhow::Result<bool> {
+    match std::env::var("TS_BRIDGE_DAEMON") {
+        Ok(value) => parse_env_bool("TS_BRIDGE_DAEMON", &value),
+        Err(_) => Ok(false),
     }
-    if let Ok(idle_ttl) = std::env::var("TS_BRIDGE_DAEMON_IDLE_TTL") {
-        config.idle_ttl =
-            parse_idle_ttl(&idle_ttl).with_context(|| "parse TS_BRIDGE_DAEMON_IDLE_TTL")?;
+}
+
+fn run_daemon_action(action: DaemonAction, globals: Globals) -> anyhow::Result<()> {
+    match action {
+        DaemonAction::Status(control) => {
+            run_daemon_control(ts_bridge::control::STATUS_METHOD, control, globals)
+        }
+        DaemonAction::Stop(control) => {
+            run_daemon_control(ts_bridge::control::STOP_METHOD, control, globals)
+        }
+        DaemonAction::Restart(control) => {
+            run_daemon_control(ts_bridge::control::RESTART_METHOD, control, globals)
+        }
+        DaemonAction::Reload {
+            idle_ttl,
+            max_projects,
+            control,
+        } => run_daemon_reload(idle_ttl, max_projects, control, globals),
+        DaemonAction::Warm { path, control } => run_daemon_warm(path, control, globals),
     }
-    Ok(Some(config))
+}
+
+fn run_daemon_control(method: &str, control: Control, globals: Globals) -> anyhow::Result<()> {
+    globals.apply()?;
+    let endpoint = control.endpoint.resolve()?;
+
+    let result = ts_bridge::control::request(&endpoint, method, serde_json::Value::Null)?;
+    if control.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else if method == ts_bridge::control::STATUS_METHOD {
+        print!("{}", ts_bridge::control::format_status(&result));
+    } else {
+        let verb = if method == ts_bridge::control::RESTART_METHOD {
+            "restarting"
+        } else {
+            "stopped"
+        };
+        println!(
+            "daemon at {endpoint} {verb} ({} project(s) shut down)",
+            result["stopped_projects"]
+        );
+    }
+    Ok(())
+}
+
+/// `ts-bridge daemon reload`: the daemon re-reads its settings file, then
+/// applies `--idle-ttl`, `--max-projects` and `--log-level` when given.
+fn run_daemon_reload(
+    idle_ttl: Option<OrOff<Duration>>,
+    max_projects: Option<OrOff<usize>>,
+    control: Control,
+    globals: Globals,
+) -> anyhow::Result<()> {
+    let mut overrides = serde_json::Map::new();
+    if let Some(OrOff(ttl)) = idle_ttl {
+        let value = ttl.map_or_else(|| "off".into(), |ttl| ttl.as_secs().into());
+        overrides.insert("idle_ttl".into(), value);
+    }
+    if let Some(OrOff(count)) = max_projects {
+        overrides.insert(
+            "max_projects".into(),
+            count.map_or_else(|| "off".into(), Into::into),
+        );
+    }
+    if let Some(level) = globals.log_level {
+        overrides.insert(
+            "log_level".into(),
+            level.as_str().to_ascii_lowercase().into(),
+        );
+    }
+    // The level is the daemon's, not this process's.
+    Globals {
+        log_level: None,
+        ..globals
+    }
+    .apply()?;
+    let endpoint = control.endpoint.resolve()?;
+
+    let result = ts_bridge::control::request(
+        &endpoint,
+        ts_bridge::control::RELOAD_METHOD,
+        serde_json::Value::Object(overrides),
+    )?;
+    if control.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+    let idle_ttl = match result["idle_ttl_seconds"].as_u64() {
+        Some(seconds) => format!("{seconds}s"),
+        None => "off".to_string(),
+    };
+    let max_projects = match result["max_projects"].as_u64() {
+        Some(count) => count.to_string(),
+        None => "unlimited".to_string(),
+    };
+    println!(
+        "daemon at {endpoint} reloaded: idle TTL {idle_ttl}, max projects {max_projects}, log level {}",
+        result["log_level"].as_str().unwrap_or("from RUST_LOG")
+    );
+    Ok(())
+}
+
+/// `ts-bridge daemon warm PATH`: has the daemon start the project at PATH
+/// so the first editor to open it does not wait for tsserver.
+fn run_daemon_warm(path: PathBuf, control: Control, globals: Globals) -> anyhow::Result<()> {
+    globals.apply()?;
+    // The daemon runs elsewhere, so relative paths are resolved here.
+    let root =
+        std::fs::canonicalize(&path).with_context(|| format!("resolve {}", path.display()))?;
+    let endpoint = control.endpoint.resolve()?;
+
+    let result = ts_bridge::control::request(
+        &endpoint,
+        ts_bridge::control::PRELOAD_METHOD,
+        serde_json::json!({ "path": root }),
+    )?;
+    if control.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or("?").to_string();
+    if result["already_running"].as_bool() == Some(true) {
+        println!(
+            "project {} ({}) was already running",
+            text(&result["name"]),
+            text(&result["root"])
+        );
+        return Ok(());
+    }
+    let loaded = match result["config_file"].as_str() {
+        Some(config_file) => format!("loaded {config_file}"),
+        None => "no tsconfig.json or jsconfig.json at its root".to_string(),
+    };
+    println!(
+        "project {} ({}) warm: TypeScript {}, {loaded}",
+        text(&result["name"]),
+        text(&result["root"]),
+        result["tsserver"]["version"].as_str().unwrap_or("unknown"),
+    );
+    Ok(())
+}
+
+fn current_dir() -> anyhow::Result<PathBuf> {
+    std::env::current_dir()
+        .and_then(std::fs::canonicalize)
+        .context("read the working directory")
 }
 
 fn parse_env_bool(name: &str, value: &str) -> anyhow::Result<bool> {
@@ -53,16 +622,16 @@ fn parse_env_bool(name: &str, value: &str) -> anyhow::Result<bool> {
     match lowered.as_str() {
         "1" | "true" | "yes" | "on" => Ok(true),
         "0" | "false" | "no" | "off" => Ok(false),
-        _ => Err(anyhow::anyhow!(
+        _ => Err(anyhow!(
             "{name} must be one of 1,true,yes,on,0,false,no,off"
         )),
     }
 }
 
-fn parse_idle_ttl(value: &str) -> anyhow::Result<Option<std::time::Duration>> {
+fn parse_duration_or_off(value: &str) -> anyhow::Result<OrOff<Duration>> {
     let trimmed = value.trim();
     if trimmed.eq_ignore_ascii_case("off") || trimmed == "0" {
-        return Ok(None);
+        return Ok(OrOff(None));
     }
     let (number, unit) = match trimmed.chars().last() {
         Some('s') => (&trimmed[..trimmed.len() - 1], 1),
@@ -72,70 +641,171 @@ fn parse_idle_ttl(value: &str) -> anyhow::Result<Option<std::time::Duration>> {
     };
     let amount: u64 = number
         .parse()
-        .with_context(|| "daemon idle TTL must be a number of seconds or use s/m/h suffix")?;
+        .with_context(|| "expected a number of seconds (or with an s/m/h suffix) or off")?;
     let seconds = amount.saturating_mul(unit);
-    Ok(Some(std::time::Duration::from_secs(seconds)))
-}
-
-fn parse_daemon_args<I>(mut args: I) -> anyhow::Result<ts_bridge::DaemonConfig>
-where
-    I: Iterator<Item = String>,
-{
-    let mut config = ts_bridge::DaemonConfig::default();
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "--listen" => {
-                let value = args
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("--listen requires HOST:PORT"))?;
-                config.listen = Some(value.parse()?);
-            }
-            "--socket" => {
-                let value = args
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("--socket requires PATH"))?;
-                config.socket = Some(value.into());
-            }
-            "--idle-ttl" => {
-                let value = args
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("--idle-ttl requires SECONDS|off"))?;
-                config.idle_ttl = parse_idle_ttl(&value).with_context(|| "parse --idle-ttl")?;
-            }
-            "-h" | "--help" => {
-                print_daemon_usage();
-                std::process::exit(0);
-            }
-            _ if arg.starts_with("--listen=") => {
-                let value = arg.trim_start_matches("--listen=");
-                config.listen = Some(value.parse()?);
-            }
-            _ if arg.starts_with("--socket=") => {
-                let value = arg.trim_start_matches("--socket=");
-                config.socket = Some(value.into());
-            }
-            _ if arg.starts_with("--idle-ttl=") => {
-                let value = arg.trim_start_matches("--idle-ttl=");
-                config.idle_ttl = parse_idle_ttl(value).with_context(|| "parse --idle-ttl")?;
-            }
-            other => return Err(anyhow::anyhow!("unknown daemon flag {other}")),
-        }
+    Ok(OrOff(Some(Duration::from_secs(seconds))))
+}
+
+fn parse_count_or_off(value: &str) -> anyhow::Result<OrOff<usize>> {
+    let trimmed = value.trim();
+    if trimmed.eq_ignore_ascii_case("off") {
+        return Ok(OrOff(None));
+    }
+    match trimmed.parse::<usize>() {
+        Ok(0) | Err(_) => Err(anyhow!("expected a positive number or off")),
+        Ok(count) => Ok(OrOff(Some(count))),
     }
-    Ok(config)
 }
 
-fn print_usage() {
-    eprintln!(
-        "Usage:\n  ts-bridge\n  ts-bridge daemon [--listen HOST:PORT] [--socket PATH] [--idle-ttl SECONDS|off]\n"
-    );
+/// `ROOT=MB`, a project root and its tsserver heap limit.
+fn parse_project_memory(value: &str) -> anyhow::Result<(PathBuf, u32)> {
+    let (root, megabytes) = value
+        .rsplit_once('=')
+        .filter(|(root, _)| !root.is_empty())
+        .ok_or_else(|| anyhow!("project memory limit must look like ROOT=MB"))?;
+    let megabytes = megabytes
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|megabytes| *megabytes > 0)
+        .ok_or_else(|| anyhow!("project memory limit must be a positive number of MB"))?;
+    Ok((root.into(), megabytes))
 }
 
-fn print_daemon_usage() {
-    eprintln!(
-        "Usage:\n  ts-bridge daemon [--listen HOST:PORT] [--socket PATH] [--idle-ttl SECONDS|off]\n"
-    );
+/// `error`, `warn`, `info`, `debug`, `trace` or `off`.
+fn parse_log_level(value: &str) -> anyhow::Result<log::LevelFilter> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("expected error, warn, info, debug, trace or off"))
 }
 
-fn print_version() {
-    println!("ts-bridge {}", env!("CARGO_PKG_VERSION"));
+/// Octal permission bits: `600`, `0600` or `0o600`.
+fn parse_socket_mode(value: &str) -> anyhow::Result<u32> {
+    let trimmed = value.trim();
+    let digits = trimmed.strip_prefix("0o").unwrap_or(trimmed);
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => Err(anyhow!(
+            "socket mode must be octal permission bits such as 0600"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+    use clap::error::ErrorKind;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("ts-bridge").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn the_command_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn global_flags_are_accepted_after_the_command() {
+        let cli = parse(&["check", "src", "--log-level=debug", "--config", "a.toml"]).unwrap();
+        assert_eq!(cli.globals.log_level, Some(log::LevelFilter::Debug));
+        assert_eq!(cli.globals.config, Some(PathBuf::from("a.toml")));
+        let Some(Command::Check { targets, .. }) = cli.command else {
+            panic!("expected check");
+        };
+        assert_eq!(targets, ["src"]);
+    }
+
+    #[test]
+    fn help_and_errors_are_returned_to_the_caller() {
+        assert_eq!(
+            parse(&["bench", "--help"]).unwrap_err().kind(),
+            ErrorKind::DisplayHelp
+        );
+        assert_eq!(
+            parse(&["help", "daemon"]).unwrap_err().kind(),
+            ErrorKind::DisplayHelp
+        );
+        assert_eq!(
+            parse(&["check", "--bogus"]).unwrap_err().kind(),
+            ErrorKind::UnknownArgument
+        );
+        assert_eq!(
+            parse(&["--record", "out.jsonl", "check"])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn daemon_transports_are_exclusive() {
+        let err = parse(&["daemon", "--listen", "127.0.0.1:1", "--socket", "x.sock"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn daemon_flags_become_its_config() {
+        let cli = parse(&[
+            "daemon",
+            "--socket",
+            "x.sock",
+            "--idle-ttl",
+            "off",
+            "--max-projects=4",
+            "--log-level",
+            "warn",
+        ])
+        .unwrap();
+        let Some(Command::Daemon {
+            action: None,
+            serve,
+        }) = cli.command
+        else {
+            panic!("expected daemon");
+        };
+        let config = serve.into_config(cli.globals).unwrap();
+        assert_eq!(config.socket, Some(PathBuf::from("x.sock")));
+        assert_eq!(config.idle_ttl, None);
+        assert_eq!(config.max_projects, Some(4));
+        assert_eq!(config.log_level, Some(log::LevelFilter::Warn));
+        assert_eq!(
+            config.resume_window,
+            ts_bridge::DaemonConfig::default().resume_window
+        );
+        assert_eq!(
+            config.client_timeout,
+            ts_bridge::DaemonConfig::default().client_timeout
+        );
+    }
+
+    #[test]
+    fn reload_takes_overrides_and_an_endpoint() {
+        let cli = parse(&[
+            "daemon",
+            "reload",
+            "--idle-ttl",
+            "30m",
+            "--log-level",
+            "debug",
+            "--socket",
+            "x.sock",
+        ])
+        .unwrap();
+        let Some(Command::Daemon {
+            action: Some(DaemonAction::Reload {
+                idle_ttl, control, ..
+            }),
+            ..
+        }) = cli.command
+        else {
+            panic!("expected daemon reload");
+        };
+        assert_eq!(idle_ttl, Some(OrOff(Some(Duration::from_secs(1800)))));
+        assert_eq!(control.endpoint.socket, Some(PathBuf::from("x.sock")));
+        assert_eq!(cli.globals.log_level, Some(log::LevelFilter::Debug));
+    }
 }
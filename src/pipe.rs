@@ -0,0 +1,220 @@
+//! Windows named pipes for the daemon: `ts-bridge daemon --pipe NAME`
+//! listens on `\\.\pipe\NAME`, refusing clients on other machines, and hands
+//! each connection to its own session the way the unix socket listener does.
+//!
+//! Pipes are opened for overlapped I/O so a connection's reader and writer
+//! threads can block on the same pipe at once.
+
+use std::ffi::OsStr;
+use std::io;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::ptr;
+use std::sync::Arc;
+
+use windows_sys::Win32::Foundation::{
+    ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, GENERIC_READ,
+    GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, FILE_SHARE_NONE,
+    OPEN_EXISTING, PIPE_ACCESS_DUPLEX, ReadFile, WriteFile,
+};
+use windows_sys::Win32::System::IO::{GetOverlappedResult, OVERLAPPED};
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT, WaitNamedPipeW,
+};
+use windows_sys::Win32::System::Threading::CreateEventW;
+
+const BUFFER_SIZE: u32 = 64 * 1024;
+/// How long a client waits for the daemon to open a free pipe instance.
+const BUSY_WAIT_MS: u32 = 5_000;
+
+/// The server end: one pipe instance waits for a client at a time, and the
+/// next is created as soon as it is taken.
+pub struct PipeListener {
+    path: Vec<u16>,
+    pending: Option<OwnedHandle>,
+}
+
+impl PipeListener {
+    /// Creates the first instance of `path`, failing when another process
+    /// already serves a pipe by that name.
+    pub fn bind(path: &str) -> io::Result<Self> {
+        let path = wide(path);
+        let pending = create_instance(&path, true)?;
+        Ok(Self {
+            path,
+            pending: Some(pending),
+        })
+    }
+
+    pub fn accept(&mut self) -> io::Result<PipeStream> {
+        let handle = match self.pending.take() {
+            Some(handle) => handle,
+            None => create_instance(&self.path, false)?,
+        };
+        let connected = Overlapped::new()?.run(handle.as_raw_handle(), |overlapped| unsafe {
+            ConnectNamedPipe(handle.as_raw_handle(), overlapped)
+        });
+        match connected {
+            Err(err) if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) => {
+                return Err(err);
+            }
+            _ => {}
+        }
+        // Clients arriving while this one is served find the next instance.
+        match create_instance(&self.path, false) {
+            Ok(next) => self.pending = Some(next),
+            Err(err) => log::warn!("cannot open the next pipe instance: {err}"),
+        }
+        Ok(PipeStream(Arc::new(handle)))
+    }
+}
+
+/// One connected pipe; clones share the handle, so reading and writing can
+/// happen on different threads.
+#[derive(Clone)]
+pub struct PipeStream(Arc<OwnedHandle>);
+
+impl PipeStream {
+    /// Opens the client end of `path`, waiting while every instance is busy.
+    pub fn connect(path: &str) -> io::Result<Self> {
+        let path = wide(path);
+        loop {
+            let handle = unsafe {
+                CreateFileW(
+                    path.as_ptr(),
+                    GENERIC_READ | GENERIC_WRITE,
+                    FILE_SHARE_NONE,
+                    ptr::null(),
+                    OPEN_EXISTING,
+                    FILE_FLAG_OVERLAPPED,
+                    ptr::null_mut(),
+                )
+            };
+            if handle != INVALID_HANDLE_VALUE {
+                return Ok(Self(Arc::new(unsafe {
+                    OwnedHandle::from_raw_handle(handle)
+                })));
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(ERROR_PIPE_BUSY as i32) {
+                return Err(err);
+            }
+            if unsafe { WaitNamedPipeW(path.as_ptr(), BUSY_WAIT_MS) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+
+    fn handle(&self) -> HANDLE {
+        self.0.as_raw_handle()
+    }
+}
+
+impl io::Read for PipeStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        let handle = self.handle();
+        let read = Overlapped::new()?.run(handle, |overlapped| unsafe {
+            ReadFile(handle, buf.as_mut_ptr(), len, ptr::null_mut(), overlapped)
+        });
+        match read {
+            Ok(read) => Ok(read as usize),
+            // The other end closed the pipe.
+            Err(err) if err.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl io::Write for PipeStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        let handle = self.handle();
+        let written = Overlapped::new()?.run(handle, |overlapped| unsafe {
+            WriteFile(handle, buf.as_ptr(), len, ptr::null_mut(), overlapped)
+        })?;
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An `OVERLAPPED` with its own event, for one operation at a time.
+struct Overlapped {
+    raw: OVERLAPPED,
+    _event: OwnedHandle,
+}
+
+impl Overlapped {
+    fn new() -> io::Result<Self> {
+        let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+        if event.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let raw = OVERLAPPED {
+            hEvent: event,
+            ..Default::default()
+        };
+        Ok(Self {
+            raw,
+            _event: unsafe { OwnedHandle::from_raw_handle(event) },
+        })
+    }
+
+    /// Starts an operation on `handle` and waits for it, returning the
+    /// number of bytes it transferred.
+    fn run(
+        mut self,
+        handle: HANDLE,
+        start: impl FnOnce(*mut OVERLAPPED) -> windows_sys::core::BOOL,
+    ) -> io::Result<u32> {
+        if start(&mut self.raw) == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+                return Err(err);
+            }
+        }
+        let mut transferred = 0;
+        if unsafe { GetOverlappedResult(handle, &self.raw, &mut transferred, 1) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(transferred)
+    }
+}
+
+fn create_instance(path: &[u16], first: bool) -> io::Result<OwnedHandle> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let handle = unsafe {
+        CreateNamedPipeW(
+            path.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+}
+
+fn wide(path: &str) -> Vec<u16> {
+    OsStr::new(path)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect()
+}
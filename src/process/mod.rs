@@ -5,17 +5,25 @@
 //! Tracks child Node processes, implements the `Content-Length` framed protocol,
 //! and exposes cancellation pipes
 
+mod node;
+pub use node::{MIN_NODE_VERSION, npm_beside, resolve as resolve_node, version as node_version};
+
 use std::fs::{self, OpenOptions};
 use std::io::{BufRead, BufReader, Read, Write};
-use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::process::{Child, ChildStderr, ChildStdout, Command, ExitStatus, Stdio};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{Receiver, Sender, unbounded};
-use serde_json::Value;
+use serde_json::{Value, json};
 use tempfile::TempDir;
 
-use crate::config::TsserverLaunchOptions;
+use crate::config::{DebugServer, ESSENTIAL_ENV, TsserverLaunchOptions, TsserverTransport};
 use crate::provider::TsserverBinary;
+use crate::record::Direction;
+
+/// How long [`TsserverProcess::shutdown`] waits after `exit` before killing.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
 
 /// Represents an owned tsserver instance (syntax or semantic).
 pub struct TsserverProcess {
@@ -41,43 +49,140 @@ impl TsserverProcess {
             return Ok(());
         }
 
-        let mut command = Command::new("node");
-        let server_label = match self.kind {
-            ServerKind::Syntax => "syntax",
-            ServerKind::Semantic => "semantic",
-        };
-        command.env("TS_LSP_RS_SERVER_KIND", server_label);
+        let node = node::resolve(self.launch.node_path.as_deref())?;
+        let mut command = Command::new(node);
+        self.apply_env(&mut command);
+        command.env("TS_LSP_RS_SERVER_KIND", self.kind.as_str());
         self.apply_node_args(&mut command);
         command.arg(&self.binary.executable);
         self.apply_tsserver_args(&mut command)?;
-        command.arg("--stdio");
-        command.stdin(Stdio::piped());
-        command.stdout(Stdio::piped());
-        command.stderr(Stdio::inherit());
-
-        let mut child = command.spawn().map_err(ProcessError::Spawn)?;
-        let stdout = child.stdout.take().ok_or(ProcessError::MissingStdout)?;
-        let stdin = child.stdin.take().ok_or(ProcessError::MissingStdin)?;
-
         let cancellation_dir = TempDir::new().map_err(ProcessError::CreateCancellationDir)?;
+        // The trailing `*` makes tsserver poll for `seq_<request seq>` per
+        // request instead of a single shared pipe file.
+        command.arg("--cancellationPipeName");
+        command.arg(cancellation_dir.path().join("seq_*"));
+        command.stderr(Stdio::piped());
+
         let (tx, rx) = unbounded();
-        let reader_handle = spawn_reader(stdout, tx);
+        let (mut child, writer, reader_handle) = match self.transport() {
+            TsserverTransport::Stdio => {
+                command.arg("--stdio");
+                command.stdin(Stdio::piped());
+                command.stdout(Stdio::piped());
+                let mut child = command.spawn().map_err(ProcessError::Spawn)?;
+                let stdout = child.stdout.take().ok_or(ProcessError::MissingStdout)?;
+                let stdin = child.stdin.take().ok_or(ProcessError::MissingStdin)?;
+                let writer: Box<dyn Write + Send> = Box::new(stdin);
+                let pid = child.id();
+                (child, writer, spawn_reader(stdout, self.kind, pid, tx))
+            }
+            #[cfg(unix)]
+            TsserverTransport::Ipc => {
+                use std::os::unix::net::UnixStream;
+
+                // Node opens the IPC channel on the fd named by
+                // `NODE_CHANNEL_FD`; handing over our end of a socket pair as
+                // stdin avoids remapping descriptors by hand.
+                let (ours, theirs) = UnixStream::pair().map_err(ProcessError::Ipc)?;
+                command.arg("--useNodeIpc");
+                command.env("NODE_CHANNEL_FD", "0");
+                command.stdin(Stdio::from(std::os::fd::OwnedFd::from(theirs)));
+                command.stdout(Stdio::null());
+                let child = command.spawn().map_err(ProcessError::Spawn)?;
+                let reader = ours.try_clone().map_err(ProcessError::Ipc)?;
+                let writer: Box<dyn Write + Send> = Box::new(ours);
+                let pid = child.id();
+                (child, writer, spawn_ipc_reader(reader, self.kind, pid, tx))
+            }
+            #[cfg(not(unix))]
+            TsserverTransport::Ipc => unreachable!("transport() falls back to stdio"),
+        };
+        let stderr = child.stderr.take().ok_or(ProcessError::MissingStderr)?;
+
+        let (stderr_tx, stderr_rx) = unbounded();
+        let stderr_handle = spawn_stderr_reader(stderr, self.kind, stderr_tx);
 
         self.child = Some(ChildHandles {
             child,
-            stdin,
+            writer,
             cancellation_dir,
             response_rx: rx,
+            stderr_rx,
             reader_handle: Some(reader_handle),
+            stderr_handle: Some(stderr_handle),
+            started_at: Instant::now(),
         });
 
         Ok(())
     }
 
+    fn transport(&self) -> TsserverTransport {
+        if cfg!(not(unix)) && self.launch.transport == TsserverTransport::Ipc {
+            log::warn!("tsserver ipc transport needs Unix sockets; using stdio");
+            return TsserverTransport::Stdio;
+        }
+        self.launch.transport
+    }
+
+    fn apply_env(&self, command: &mut Command) {
+        if let Some(allowlist) = &self.launch.env_allowlist {
+            command.env_clear();
+            let inherited = ESSENTIAL_ENV
+                .iter()
+                .copied()
+                .chain(allowlist.iter().map(String::as_str));
+            for name in inherited {
+                if let Some(value) = std::env::var_os(name) {
+                    command.env(name, value);
+                }
+            }
+        }
+        for (name, value) in &self.launch.env {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+        if let Some(options) = &self.launch.node_options {
+            command.env("NODE_OPTIONS", options);
+        }
+    }
+
     fn apply_node_args(&self, command: &mut Command) {
         if let Some(limit) = self.launch.max_old_space_size {
             command.arg(format!("--max-old-space-size={limit}"));
         }
+        if let Some(flag) = self.inspect_flag() {
+            log::info!("tsserver {:?} started with {flag}", self.kind);
+            command.arg(flag);
+        }
+        command.args(&self.binary.node_args);
+    }
+
+    /// `--inspect[-brk]=<port>` for the configured debug server, mirroring the
+    /// `TSS_DEBUG`/`TSS_DEBUG_BRK` variables editors use for the same purpose.
+    fn inspect_flag(&self) -> Option<String> {
+        let target = match self.launch.debug_server {
+            DebugServer::Syntax => ServerKind::Syntax,
+            DebugServer::Semantic => ServerKind::Semantic,
+        };
+        if self.kind != target {
+            return None;
+        }
+        let from_env = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<u16>().ok())
+        };
+        let (port, brk) = match self.launch.debug_port {
+            Some(port) => (port, self.launch.debug_break),
+            None => match from_env("TSS_DEBUG_BRK") {
+                Some(port) => (port, true),
+                None => (from_env("TSS_DEBUG")?, false),
+            },
+        };
+        let flag = if brk { "--inspect-brk" } else { "--inspect" };
+        Some(format!("{flag}={port}"))
     }
 
     fn apply_tsserver_args(&self, command: &mut Command) -> Result<(), ProcessError> {
@@ -105,12 +210,41 @@ impl TsserverProcess {
             probe_locations.push(binary_probe.clone());
         }
         probe_locations.extend(self.launch.plugin_probe_dirs.iter().cloned());
+        probe_locations.extend(
+            self.launch
+                .plugins
+                .iter()
+                .filter_map(|plugin| plugin.location.clone()),
+        );
         for location in probe_locations {
             command.arg("--pluginProbeLocations");
             command.arg(location);
         }
 
-        for plugin in &self.launch.global_plugins {
+        if self.launch.disable_automatic_typing_acquisition {
+            command.arg("--disableAutomaticTypingAcquisition");
+        } else {
+            // The typings installer's begin/end events only reach the client
+            // with telemetry enabled.
+            command.arg("--enableTelemetry");
+            if let Some(npm) = &self.launch.npm_location {
+                command.arg("--npmLocation");
+                command.arg(npm);
+            }
+        }
+
+        let mut global_plugins: Vec<&str> = self
+            .launch
+            .global_plugins
+            .iter()
+            .map(String::as_str)
+            .collect();
+        for plugin in &self.launch.plugins {
+            if !global_plugins.contains(&plugin.name.as_str()) {
+                global_plugins.push(&plugin.name);
+            }
+        }
+        for plugin in global_plugins {
             command.arg("--globalPlugins");
             command.arg(plugin);
         }
@@ -127,27 +261,23 @@ impl TsserverProcess {
             return Ok(None);
         };
         fs::create_dir_all(dir).map_err(ProcessError::LogDirectory)?;
-        let mut path = dir.clone();
-        let suffix = match self.kind {
-            ServerKind::Syntax => "syntax",
-            ServerKind::Semantic => "semantic",
-        };
-        path.push(format!("tsserver.{suffix}.log"));
-        Ok(Some(path))
+        Ok(Some(log_file_path(dir, self.kind)))
     }
 
-    /// Sends a JSON payload to tsserver using the newline-delimited framing that
-    /// `tsserver --stdio` expects (it only *emits* Content-Length headers).
+    /// Sends a JSON payload to tsserver using newline-delimited framing, which
+    /// both `tsserver --stdio` (it only *emits* Content-Length headers) and the
+    /// Node IPC channel expect.
     pub fn write(&mut self, payload: &Value) -> Result<(), ProcessError> {
         let child = self.child.as_mut().ok_or(ProcessError::NotStarted)?;
         let mut serialized = serde_json::to_string(payload).map_err(ProcessError::Serialize)?;
         serialized.push('\n');
         log::trace!("tsserver {:?} <= {}", self.kind, serialized.trim_end());
+        crate::record::tsserver(self.kind, child.child.id(), Direction::Out, payload);
         child
-            .stdin
+            .writer
             .write_all(serialized.as_bytes())
             .map_err(ProcessError::Write)?;
-        child.stdin.flush().map_err(ProcessError::Write)?;
+        child.writer.flush().map_err(ProcessError::Write)?;
         Ok(())
     }
 
@@ -169,9 +299,52 @@ impl TsserverProcess {
             .map(|handles| handles.response_rx.clone())
     }
 
+    pub fn stderr_rx(&self) -> Option<Receiver<String>> {
+        self.child.as_ref().map(|handles| handles.stderr_rx.clone())
+    }
+
+    /// Sends tsserver the `exit` command so it can flush its log and stop the
+    /// typings installer, then kills it if it is still running after
+    /// [`SHUTDOWN_GRACE`].  Returns the exit status, if the child was running.
+    pub fn shutdown(&mut self) -> Option<ExitStatus> {
+        let exit = json!({ "seq": 0, "type": "request", "command": "exit" });
+        if let Err(err) = self.write(&exit) {
+            log::debug!("tsserver {:?} exit request failed: {err}", self.kind);
+        }
+        let mut handles = self.child.take()?;
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            match handles.child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) => thread::sleep(Duration::from_millis(20)),
+                Err(_) => break,
+            }
+        }
+        log::warn!("tsserver {:?} ignored exit; killing it", self.kind);
+        let _ = handles.child.kill();
+        handles.child.wait().ok()
+    }
+
+    /// Exit status if the child has terminated since it was started.
+    pub fn exit_status(&mut self) -> Option<ExitStatus> {
+        let handles = self.child.as_mut()?;
+        handles.child.try_wait().ok().flatten()
+    }
+
     pub fn pid(&self) -> Option<u32> {
         self.child.as_ref().map(|handles| handles.child.id())
     }
+
+    /// Time since the child was spawned.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.child
+            .as_ref()
+            .map(|handles| handles.started_at.elapsed())
+    }
+
+    pub fn binary(&self) -> &TsserverBinary {
+        &self.binary
+    }
 }
 
 impl Drop for TsserverProcess {
@@ -183,18 +356,38 @@ impl Drop for TsserverProcess {
     }
 }
 
+/// Where a `kind` tsserver logs when `tsserver.log_directory` is `dir`.
+pub fn log_file_path(dir: &std::path::Path, kind: ServerKind) -> std::path::PathBuf {
+    dir.join(format!("tsserver.{}.log", kind.as_str()))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ServerKind {
     Syntax,
     Semantic,
 }
 
+impl ServerKind {
+    /// `syntax` or `semantic`, the name logs, recordings and the LSP
+    /// extensions use.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServerKind::Syntax => "syntax",
+            ServerKind::Semantic => "semantic",
+        }
+    }
+}
+
 struct ChildHandles {
     child: Child,
-    stdin: ChildStdin,
+    /// tsserver's stdin, or our end of the IPC socket.
+    writer: Box<dyn Write + Send>,
     cancellation_dir: TempDir,
     response_rx: Receiver<Value>,
+    stderr_rx: Receiver<String>,
     reader_handle: Option<thread::JoinHandle<()>>,
+    stderr_handle: Option<thread::JoinHandle<()>>,
+    started_at: Instant,
 }
 
 impl Drop for ChildHandles {
@@ -202,15 +395,24 @@ impl Drop for ChildHandles {
         if let Some(handle) = self.reader_handle.take() {
             let _ = handle.join();
         }
+        if let Some(handle) = self.stderr_handle.take() {
+            let _ = handle.join();
+        }
     }
 }
 
-fn spawn_reader(stdout: ChildStdout, tx: Sender<Value>) -> thread::JoinHandle<()> {
+fn spawn_reader(
+    stdout: ChildStdout,
+    kind: ServerKind,
+    pid: u32,
+    tx: Sender<Value>,
+) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         let mut reader = BufReader::new(stdout);
         loop {
             match read_message(&mut reader) {
                 Ok(message) => {
+                    crate::record::tsserver(kind, pid, Direction::In, &message);
                     let _ = tx.send(message);
                 }
                 Err(ProcessError::Eof) => break,
@@ -220,6 +422,48 @@ fn spawn_reader(stdout: ChildStdout, tx: Sender<Value>) -> thread::JoinHandle<()
     })
 }
 
+/// Node IPC messages are one JSON document per line.
+#[cfg(unix)]
+fn spawn_ipc_reader(
+    stream: std::os::unix::net::UnixStream,
+    kind: ServerKind,
+    pid: u32,
+    tx: Sender<Value>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match serde_json::from_str(&line) {
+                Ok(message) => {
+                    crate::record::tsserver(kind, pid, Direction::In, &message);
+                    let _ = tx.send(message);
+                }
+                Err(err) => log::debug!("ignoring malformed tsserver ipc message: {err}"),
+            }
+        }
+    })
+}
+
+/// Logs every stderr line tagged with the server kind and hands it on so the
+/// project can forward it to clients.
+fn spawn_stderr_reader(
+    stderr: ChildStderr,
+    kind: ServerKind,
+    tx: Sender<String>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            log::warn!("tsserver {kind:?} stderr: {line}");
+            let _ = tx.send(line);
+        }
+    })
+}
+
 fn read_message<T: Read>(reader: &mut BufReader<T>) -> Result<Value, ProcessError> {
     let mut header = String::new();
     loop {
@@ -252,12 +496,26 @@ pub enum ProcessError {
     NotStarted,
     #[error("failed to spawn tsserver: {0}")]
     Spawn(std::io::Error),
+    #[error("node not found ({0}); install Node.js or set `tsserver.node_path`")]
+    NodeNotFound(String),
+    #[error(
+        "{node:?} reports version {version:?} but tsserver needs at least v{}.{}; \
+         upgrade Node.js or point `tsserver.node_path` at a newer one",
+        MIN_NODE_VERSION.0,
+        MIN_NODE_VERSION.1
+    )]
+    NodeTooOld {
+        node: std::path::PathBuf,
+        version: String,
+    },
     #[error("failed to create cancellation directory: {0}")]
     CreateCancellationDir(std::io::Error),
     #[error("tsserver stdout missing (stdio must be piped)")]
     MissingStdout,
     #[error("tsserver stdin missing (stdio must be piped)")]
     MissingStdin,
+    #[error("tsserver stderr missing (stdio must be piped)")]
+    MissingStderr,
     #[error("failed to serialize payload: {0}")]
     Serialize(serde_json::Error),
     #[error("failed to write to tsserver stdin: {0}")]
@@ -270,6 +528,8 @@ pub enum ProcessError {
     InvalidHeader,
     #[error("io error while reading tsserver stdout: {0}")]
     Read(std::io::Error),
+    #[error("failed to set up tsserver ipc channel: {0}")]
+    Ipc(std::io::Error),
     #[error("failed to prepare tsserver log directory: {0}")]
     LogDirectory(std::io::Error),
 }
@@ -0,0 +1,152 @@
+//! Locates the `node` executable tsserver runs under and checks its version.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::ProcessError;
+
+/// Oldest node TypeScript 5.x supports.
+pub const MIN_NODE_VERSION: (u32, u32) = (14, 17);
+
+/// Picks `node_path` when configured, otherwise `node` on PATH, otherwise the
+/// default node of volta, fnm or nvm (editors launched from a GUI often miss
+/// the shell setup that puts those on PATH). A bare `node_path` such as
+/// `node18` is looked up on PATH. The result is checked against
+/// [`MIN_NODE_VERSION`].
+pub fn resolve(node_path: Option<&Path>) -> Result<PathBuf, ProcessError> {
+    let node = match node_path {
+        Some(path) if is_bare_name(path) => which::which(path)
+            .map_err(|err| ProcessError::NodeNotFound(format!("{path:?} is not on PATH: {err}")))?,
+        Some(path) if path.exists() && !path.is_dir() => path.to_path_buf(),
+        Some(path) => {
+            return Err(ProcessError::NodeNotFound(format!(
+                "{path:?} does not exist"
+            )));
+        }
+        None => find_node().ok_or_else(|| {
+            ProcessError::NodeNotFound(
+                "not on PATH and no volta/fnm/nvm install was found".to_string(),
+            )
+        })?,
+    };
+    let output = Command::new(&node)
+        .arg("--version")
+        .output()
+        .map_err(|err| ProcessError::NodeNotFound(format!("{node:?} failed to run: {err}")))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match parse_version(&version) {
+        Some(found) if found >= MIN_NODE_VERSION => Ok(node),
+        _ => Err(ProcessError::NodeTooOld { node, version }),
+    }
+}
+
+/// The npm that ships with `node` (the `bin` directory of every node install
+/// holds both), falling back to npm on PATH.
+pub fn npm_beside(node: &Path) -> PathBuf {
+    let executable = if cfg!(windows) { "npm.cmd" } else { "npm" };
+    node.parent()
+        .map(|dir| dir.join(executable))
+        .filter(|npm| npm.exists())
+        .or_else(|| which::which(executable).ok())
+        .unwrap_or_else(|| PathBuf::from(executable))
+}
+
+/// What `node --version` prints, e.g. `v20.11.1`.
+pub fn version(node: &Path) -> Option<String> {
+    let output = Command::new(node).arg("--version").output().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+/// `node18` rather than `./node18` or `/usr/bin/node18`.
+fn is_bare_name(path: &Path) -> bool {
+    path.components().count() == 1 && path.parent() == Some(Path::new(""))
+}
+
+fn find_node() -> Option<PathBuf> {
+    if let Ok(path) = which::which("node") {
+        return Some(path);
+    }
+    let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+    let home = var("HOME");
+    let executable = if cfg!(windows) { "node.exe" } else { "node" };
+
+    let volta = var("VOLTA_HOME")
+        .or_else(|| home.as_ref().map(|home| home.join(".volta")))
+        .map(|dir| dir.join("bin").join(executable));
+    let fnm = var("FNM_DIR")
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share/fnm")))
+        .map(|dir| dir.join("aliases/default/bin").join(executable));
+    let nvm = var("NVM_BIN").map(|dir| dir.join(executable)).or_else(|| {
+        let dir = var("NVM_DIR").or_else(|| home.as_ref().map(|home| home.join(".nvm")))?;
+        newest_nvm_node(&dir.join("versions/node"), executable)
+    });
+
+    [volta, fnm, nvm]
+        .into_iter()
+        .flatten()
+        .find(|candidate| candidate.is_file())
+}
+
+fn newest_nvm_node(versions: &Path, executable: &str) -> Option<PathBuf> {
+    std::fs::read_dir(versions)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let version = parse_version(&name)?;
+            Some((version, entry.path().join("bin").join(executable)))
+        })
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, node)| node)
+}
+
+/// `v18.17.1` -> `(18, 17)`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_node_versions() {
+        assert_eq!(parse_version("v18.17.1\n"), Some((18, 17)));
+        assert_eq!(parse_version("20.0.0"), Some((20, 0)));
+        assert_eq!(parse_version("garbage"), None);
+        assert!(parse_version("v14.16.0").unwrap() < MIN_NODE_VERSION);
+    }
+
+    #[test]
+    fn missing_node_path_is_reported() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let err = resolve(Some(&dir.path().join("node"))).expect_err("missing node");
+        assert!(matches!(err, ProcessError::NodeNotFound(_)));
+    }
+
+    #[test]
+    fn bare_names_are_looked_up_on_path() {
+        assert!(is_bare_name(Path::new("node18")));
+        assert!(!is_bare_name(Path::new("./node18")));
+        assert!(!is_bare_name(Path::new("/usr/bin/node")));
+        let err = resolve(Some(Path::new("ts-bridge-no-such-node"))).expect_err("not on PATH");
+        assert!(matches!(err, ProcessError::NodeNotFound(message) if message.contains("PATH")));
+        if let Ok(node) = which::which("node") {
+            assert_eq!(resolve(Some(Path::new("node"))).ok(), Some(node));
+        }
+    }
+
+    #[test]
+    fn npm_is_found_next_to_node() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let npm = dir
+            .path()
+            .join(if cfg!(windows) { "npm.cmd" } else { "npm" });
+        std::fs::write(&npm, "").expect("write npm");
+        assert_eq!(npm_beside(&dir.path().join("node")), npm);
+    }
+}
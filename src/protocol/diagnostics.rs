@@ -1,12 +1,36 @@
-use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Uri};
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, NumberOrString,
+    Uri,
+};
 use serde_json::{Value, json};
 
+use crate::config::{DiagnosticLevel, DiagnosticsSettings};
 use crate::protocol::NotificationSpec;
 use crate::rpc::{Priority, Route};
-use crate::utils::{file_path_to_uri, tsserver_range_from_value_lsp};
+use crate::types::Range;
+use crate::utils::{
+    file_path_to_uri, lsp_range_to_tsserver, tsserver_range_from_value_lsp,
+    tsserver_span_to_location,
+};
 
 const REQUEST_COMPLETED: &str = "requestCompleted";
 
+/// Sent by the editor on InsertLeave/BufWrite when `publish_diagnostic_on` is
+/// `insert_leave`; params are `{ textDocument: { uri } }`, or empty for every
+/// document edited since the last request.
+pub const REQUEST_METHOD: &str = "ts-bridge/requestDiagnostics";
+
+/// Sent by the editor when the visible part of a document changes; params are
+/// `{ textDocument: { uri }, ranges: Range[] }`. Large files get their
+/// semantic diagnostics for these ranges first.
+pub const VISIBLE_RANGES_METHOD: &str = "ts-bridge/visibleRanges";
+
+/// First TypeScript release that accepts ranges in `geterr` and answers with
+/// `regionSemanticDiag`.
+pub const MIN_REGION_TYPESCRIPT_VERSION: (u32, u32) = (5, 6);
+
+const REGION_SEMANTIC_DIAG: &str = "regionSemanticDiag";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DiagnosticsKind {
     Syntax,
@@ -36,6 +60,15 @@ pub enum DiagnosticsEvent {
     Completed {
         request_seq: u64,
     },
+    /// Semantic diagnostics for the requested ranges only; the full
+    /// `semanticDiag` for the file follows.
+    Region {
+        request_seq: Option<u64>,
+        uri: Uri,
+        diagnostics: Vec<Diagnostic>,
+        /// The spans tsserver checked; empty when it did not say.
+        ranges: Vec<lsp_types::Range>,
+    },
 }
 
 pub fn request_for_file(file: &str) -> NotificationSpec {
@@ -54,7 +87,46 @@ pub fn request_for_file(file: &str) -> NotificationSpec {
     }
 }
 
-pub fn parse_tsserver_event(payload: &Value) -> Option<DiagnosticsEvent> {
+/// `geterrForProject`: diagnostics for every file of the project `file`
+/// belongs to, from the server that loads projects.
+pub fn request_for_project(file: &str) -> NotificationSpec {
+    NotificationSpec {
+        route: Route::SemanticPreferred,
+        payload: json!({
+            "command": "geterrForProject",
+            "arguments": {
+                "file": file,
+                "delay": 0,
+            }
+        }),
+        priority: Priority::Low,
+    }
+}
+
+/// Like [`request_for_file`], but asks for the semantic diagnostics of
+/// `ranges` before the whole file.
+pub fn request_for_file_ranges(file: &str, ranges: &[Range]) -> NotificationSpec {
+    let ranges = ranges
+        .iter()
+        .map(|range| {
+            let range = lsp_range_to_tsserver(range);
+            json!({
+                "startLine": range.start.line,
+                "startOffset": range.start.offset,
+                "endLine": range.end.line,
+                "endOffset": range.end.offset,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut spec = request_for_file(file);
+    spec.payload["arguments"]["files"] = json!([{ "file": file, "ranges": ranges }]);
+    spec
+}
+
+pub fn parse_tsserver_event(
+    payload: &Value,
+    settings: &DiagnosticsSettings,
+) -> Option<DiagnosticsEvent> {
     if payload.get("type")?.as_str()? != "event" {
         return None;
     }
@@ -66,7 +138,10 @@ pub fn parse_tsserver_event(payload: &Value) -> Option<DiagnosticsEvent> {
             .and_then(|value| value.as_u64())?;
         return Some(DiagnosticsEvent::Completed { request_seq: seq });
     }
-    let kind = DiagnosticsKind::from_event_name(event_name)?;
+    let kind = DiagnosticsKind::from_event_name(event_name);
+    if kind.is_none() && event_name != REGION_SEMANTIC_DIAG {
+        return None;
+    }
 
     let body = payload.get("body")?;
     let file = body.get("file")?.as_str()?;
@@ -80,25 +155,72 @@ pub fn parse_tsserver_event(payload: &Value) -> Option<DiagnosticsEvent> {
 
     let lsp_diagnostics = diagnostics
         .into_iter()
-        .filter_map(convert_diagnostic)
+        .filter_map(|diagnostic| convert_diagnostic(diagnostic, settings))
         .collect::<Vec<_>>();
 
-    Some(DiagnosticsEvent::Report {
-        request_seq,
-        kind,
-        uri,
-        diagnostics: lsp_diagnostics,
+    Some(match kind {
+        Some(kind) => DiagnosticsEvent::Report {
+            request_seq,
+            kind,
+            uri,
+            diagnostics: lsp_diagnostics,
+        },
+        None => DiagnosticsEvent::Region {
+            request_seq,
+            uri,
+            diagnostics: lsp_diagnostics,
+            ranges: body
+                .get("spans")
+                .and_then(|spans| spans.as_array())
+                .map(|spans| {
+                    spans
+                        .iter()
+                        .filter_map(tsserver_range_from_value_lsp)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        },
     })
 }
 
-fn convert_diagnostic(value: Value) -> Option<Diagnostic> {
+/// Returns `None` for malformed entries and for codes in
+/// `diagnostics.ignore_codes`.
+pub(crate) fn convert_diagnostic(
+    value: Value,
+    settings: &DiagnosticsSettings,
+) -> Option<Diagnostic> {
     let range = tsserver_range_from_value_lsp(&value)?;
     let message = value.get("text")?.as_str()?.to_string();
-    let severity = map_severity(value.get("category").and_then(|v| v.as_str()));
-    let code = value
+    let number = value
         .get("code")
         .and_then(|c| c.as_i64())
-        .map(|code| NumberOrString::Number(code as i32));
+        .map(|code| code as i32);
+    if number.is_some_and(|number| settings.ignore_codes.contains(&number)) {
+        return None;
+    }
+    let severity = match number.and_then(|number| settings.severity_overrides.get(&number)) {
+        Some(level) => Some(level_severity(*level)),
+        None => map_severity(value.get("category").and_then(|v| v.as_str())),
+    };
+    let code = number.map(NumberOrString::Number);
+    let related_information = value
+        .get("relatedInformation")
+        .and_then(|related| related.as_array())
+        .map(|related| {
+            related
+                .iter()
+                .filter_map(convert_related_information)
+                .collect::<Vec<_>>()
+        })
+        .filter(|related| !related.is_empty());
+    let tags = [
+        ("reportsUnnecessary", DiagnosticTag::UNNECESSARY),
+        ("reportsDeprecated", DiagnosticTag::DEPRECATED),
+    ]
+    .into_iter()
+    .filter(|(field, _)| reports(&value, field))
+    .map(|(_, tag)| tag)
+    .collect::<Vec<_>>();
 
     Some(Diagnostic {
         range,
@@ -106,10 +228,37 @@ fn convert_diagnostic(value: Value) -> Option<Diagnostic> {
         code,
         source: Some("tsserver".to_string()),
         message,
+        related_information,
+        tags: (!tags.is_empty()).then_some(tags),
         ..Diagnostic::default()
     })
 }
 
+/// tsserver sets `reportsUnnecessary`/`reportsDeprecated` to `{}` rather than
+/// `true`.
+fn reports(value: &Value, field: &str) -> bool {
+    value
+        .get(field)
+        .is_some_and(|flag| !flag.is_null() && *flag != Value::Bool(false))
+}
+
+/// Entries without a `span` (plain chained messages) have no location to
+/// point at and are dropped.
+fn convert_related_information(value: &Value) -> Option<DiagnosticRelatedInformation> {
+    let location = tsserver_span_to_location(value.get("span")?)?;
+    let message = value.get("message")?.as_str()?.to_string();
+    Some(DiagnosticRelatedInformation { location, message })
+}
+
+fn level_severity(level: DiagnosticLevel) -> DiagnosticSeverity {
+    match level {
+        DiagnosticLevel::Error => DiagnosticSeverity::ERROR,
+        DiagnosticLevel::Warning => DiagnosticSeverity::WARNING,
+        DiagnosticLevel::Information => DiagnosticSeverity::INFORMATION,
+        DiagnosticLevel::Hint => DiagnosticSeverity::HINT,
+    }
+}
+
 fn map_severity(category: Option<&str>) -> Option<DiagnosticSeverity> {
     match category {
         Some("error") => Some(DiagnosticSeverity::ERROR),
@@ -137,6 +286,14 @@ mod tests {
         assert_eq!(files, &[json!("/workspace/foo.ts")]);
     }
 
+    #[test]
+    fn request_for_project_targets_the_project_server() {
+        let spec = request_for_project("/workspace/foo.ts");
+        assert_eq!(spec.route, Route::SemanticPreferred);
+        assert_eq!(spec.payload["command"], "geterrForProject");
+        assert_eq!(spec.payload["arguments"]["file"], "/workspace/foo.ts");
+    }
+
     #[test]
     fn parse_tsserver_event_converts_diagnostics() {
         let payload = json!({
@@ -155,7 +312,7 @@ mod tests {
             }
         });
 
-        match parse_tsserver_event(&payload) {
+        match parse_tsserver_event(&payload, &DiagnosticsSettings::default()) {
             Some(DiagnosticsEvent::Report {
                 uri,
                 diagnostics,
@@ -173,6 +330,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn convert_diagnostic_maps_related_information() {
+        let diagnostic = convert_diagnostic(json!({
+            "start": { "line": 3, "offset": 7 },
+            "end": { "line": 3, "offset": 10 },
+            "text": "Type 'string' is not assignable to type 'number'.",
+            "category": "error",
+            "code": 2322,
+            "relatedInformation": [
+                {
+                    "span": {
+                        "file": "/workspace/types.ts",
+                        "start": { "line": 2, "offset": 3 },
+                        "end": { "line": 2, "offset": 8 },
+                    },
+                    "message": "The expected type comes from property 'count' which is declared here on type 'Props'",
+                    "category": "message",
+                    "code": 6500,
+                },
+                { "message": "no location", "category": "message", "code": 1 },
+            ]
+        }), &DiagnosticsSettings::default())
+        .expect("diagnostic converts");
+
+        let related = diagnostic.related_information.expect("related information");
+        assert_eq!(related.len(), 1);
+        assert_eq!(
+            related[0].location.uri.as_str(),
+            "file:///workspace/types.ts"
+        );
+        assert_eq!(related[0].location.range.start.line, 1);
+        assert_eq!(related[0].location.range.start.character, 2);
+        assert!(
+            related[0]
+                .message
+                .starts_with("The expected type comes from")
+        );
+    }
+
+    #[test]
+    fn convert_diagnostic_tags_unnecessary_and_deprecated_code() {
+        let diagnostic = |extra: Value| {
+            let mut value = json!({
+                "start": { "line": 1, "offset": 7 },
+                "end": { "line": 1, "offset": 10 },
+                "text": "'foo' is declared but its value is never read.",
+                "category": "suggestion",
+                "code": 6133,
+            });
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            convert_diagnostic(value, &DiagnosticsSettings::default()).expect("diagnostic converts")
+        };
+
+        assert_eq!(
+            diagnostic(json!({ "reportsUnnecessary": {} })).tags,
+            Some(vec![DiagnosticTag::UNNECESSARY])
+        );
+        assert_eq!(
+            diagnostic(json!({ "reportsDeprecated": {}, "reportsUnnecessary": false })).tags,
+            Some(vec![DiagnosticTag::DEPRECATED])
+        );
+        assert_eq!(diagnostic(json!({})).tags, None);
+    }
+
+    #[test]
+    fn convert_diagnostic_applies_ignored_codes_and_severity_overrides() {
+        let diagnostic = |code| {
+            json!({
+                "start": { "line": 1, "offset": 1 },
+                "end": { "line": 1, "offset": 4 },
+                "text": "noisy",
+                "category": "error",
+                "code": code,
+            })
+        };
+        let settings = DiagnosticsSettings {
+            ignore_codes: [80001].into_iter().collect(),
+            severity_overrides: [(2304, DiagnosticLevel::Hint)].into_iter().collect(),
+            ..DiagnosticsSettings::default()
+        };
+
+        assert!(convert_diagnostic(diagnostic(80001), &settings).is_none());
+        let overridden = convert_diagnostic(diagnostic(2304), &settings).expect("kept");
+        assert_eq!(overridden.severity, Some(DiagnosticSeverity::HINT));
+        let untouched = convert_diagnostic(diagnostic(2322), &settings).expect("kept");
+        assert_eq!(untouched.severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn request_for_file_ranges_sends_one_based_ranges() {
+        let range: Range = serde_json::from_value(json!({
+            "start": { "line": 99, "character": 0 },
+            "end": { "line": 149, "character": 4 },
+        }))
+        .unwrap();
+        let spec = request_for_file_ranges("/workspace/big.ts", &[range]);
+        assert_eq!(
+            spec.payload.pointer("/arguments/files"),
+            Some(&json!([{
+                "file": "/workspace/big.ts",
+                "ranges": [{ "startLine": 100, "startOffset": 1, "endLine": 150, "endOffset": 5 }],
+            }]))
+        );
+    }
+
+    #[test]
+    fn parse_tsserver_event_reads_region_diagnostics() {
+        let payload = json!({
+            "type": "event",
+            "event": "regionSemanticDiag",
+            "body": {
+                "file": "/workspace/big.ts",
+                "diagnostics": [],
+                "spans": [{
+                    "start": { "line": 101, "offset": 1 },
+                    "end": { "line": 151, "offset": 5 },
+                }],
+            }
+        });
+        match parse_tsserver_event(&payload, &DiagnosticsSettings::default()) {
+            Some(DiagnosticsEvent::Region { ranges, .. }) => assert_eq!(
+                ranges,
+                vec![lsp_types::Range::new(
+                    lsp_types::Position::new(100, 0),
+                    lsp_types::Position::new(150, 4),
+                )]
+            ),
+            other => panic!("expected region event, got {other:?}"),
+        }
+    }
+
     #[test]
     fn parse_tsserver_event_detects_completion_events() {
         let payload = json!({
@@ -181,7 +472,7 @@ mod tests {
             "body": { "request_seq": 99 }
         });
 
-        match parse_tsserver_event(&payload) {
+        match parse_tsserver_event(&payload, &DiagnosticsSettings::default()) {
             Some(DiagnosticsEvent::Completed { request_seq }) => assert_eq!(request_seq, 99),
             other => panic!("expected completion event, got {other:?}"),
         }
@@ -18,6 +18,12 @@ pub mod workspace;
 pub enum AdapterResult {
     Ready(Value),
     Continue(RequestSpec),
+    /// Streams `value` to the client as a `$/progress` partial result (using
+    /// the request's `partialResultToken`) before continuing with `next`.
+    Partial {
+        value: Value,
+        next: RequestSpec,
+    },
 }
 
 impl AdapterResult {
@@ -46,14 +52,6 @@ pub struct NotificationSpec {
 
 pub fn route_request(method: &str, params: Value) -> Option<RequestSpec> {
     match method {
-        lsp_types::request::HoverRequest::METHOD => {
-            let params: lsp_types::HoverParams = serde_json::from_value(params).ok()?;
-            Some(text_document::hover::handle(params))
-        }
-        lsp_types::request::Completion::METHOD => {
-            let params: lsp_types::CompletionParams = serde_json::from_value(params).ok()?;
-            Some(text_document::completion::handle(params))
-        }
         lsp_types::request::ResolveCompletionItem::METHOD => {
             let item: lsp_types::CompletionItem = serde_json::from_value(params).ok()?;
             text_document::completion_resolve::handle(item)
@@ -63,6 +61,11 @@ pub fn route_request(method: &str, params: Value) -> Option<RequestSpec> {
                 serde_json::from_value(params).ok()?;
             Some(text_document::definition::handle(params))
         }
+        lsp_types::request::GotoDeclaration::METHOD => {
+            let params: lsp_types::request::GotoDeclarationParams =
+                serde_json::from_value(params).ok()?;
+            Some(text_document::declaration::handle(params))
+        }
         lsp_types::request::SignatureHelpRequest::METHOD => {
             let params: lsp_types::SignatureHelpParams = serde_json::from_value(params).ok()?;
             Some(text_document::signature_help::handle(params))
@@ -99,11 +102,28 @@ pub fn route_request(method: &str, params: Value) -> Option<RequestSpec> {
             let action: lsp_types::CodeAction = serde_json::from_value(params).ok()?;
             text_document::code_action_resolve::handle(action)
         }
+        lsp_types::request::CodeLensResolve::METHOD => {
+            let lens: lsp_types::CodeLens = serde_json::from_value(params).ok()?;
+            text_document::code_lens::handle_resolve(lens)
+        }
         lsp_types::request::Formatting::METHOD => {
             let params: lsp_types::DocumentFormattingParams =
                 serde_json::from_value(params).ok()?;
             Some(text_document::formatting::handle(params))
         }
+        lsp_types::request::OnTypeFormatting::METHOD => {
+            let params: lsp_types::DocumentOnTypeFormattingParams =
+                serde_json::from_value(params).ok()?;
+            Some(text_document::on_type_formatting::handle(params))
+        }
+        lsp_types::request::FoldingRangeRequest::METHOD => {
+            let params: lsp_types::FoldingRangeParams = serde_json::from_value(params).ok()?;
+            Some(text_document::folding_range::handle(params))
+        }
+        lsp_types::request::SelectionRangeRequest::METHOD => {
+            let params: lsp_types::SelectionRangeParams = serde_json::from_value(params).ok()?;
+            Some(text_document::selection_range::handle(params))
+        }
         lsp_types::request::SemanticTokensFullRequest::METHOD => {
             let params: lsp_types::SemanticTokensParams = serde_json::from_value(params).ok()?;
             Some(text_document::semantic_tokens::handle_full(params))
@@ -121,6 +141,11 @@ pub fn route_request(method: &str, params: Value) -> Option<RequestSpec> {
             let params: lsp_types::RenameFilesParams = serde_json::from_value(params).ok()?;
             workspace::rename::handle(params)
         }
+        text_document::paste_edits::METHOD => {
+            let params: text_document::paste_edits::PasteEditsParams =
+                serde_json::from_value(params).ok()?;
+            Some(text_document::paste_edits::handle(params))
+        }
         lsp_types::request::ExecuteCommand::METHOD => {
             let params: lsp_types::ExecuteCommandParams = serde_json::from_value(params).ok()?;
             workspace::execute_command::handle(params)
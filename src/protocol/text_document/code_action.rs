@@ -7,14 +7,17 @@
 //! fixes (missing imports, unreachable code, etc.).  Results are converted into
 //! `CodeAction` entries with ready-to-apply workspace edits.  When tsserver also
 //! reports a `fixId`, we surface a companion "fix all" action that is resolved
-//! lazily via `codeAction/resolve`.
+//! lazily via `codeAction/resolve`.  Refactors come from a chained
+//! `getApplicableRefactors` request and are listed under `refactor.*` kinds;
+//! their edits are computed on resolve through `getEditsForRefactor`.
 
 use std::collections::HashMap;
 
 use anyhow::{Context, Result};
 use lsp_types::{
-    CodeAction, CodeActionContext, CodeActionKind, CodeActionOrCommand, CodeActionParams,
-    CodeActionResponse, Diagnostic, NumberOrString, TextEdit, Uri, WorkspaceEdit,
+    CodeAction, CodeActionContext, CodeActionDisabled, CodeActionKind, CodeActionOrCommand,
+    CodeActionParams, CodeActionResponse, Command, Diagnostic, NumberOrString, Range, TextEdit,
+    Uri, WorkspaceEdit,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -30,6 +33,8 @@ pub enum CodeActionData {
     FixAll(FixAllData),
     #[serde(rename = "organizeImports")]
     OrganizeImports(OrganizeImportsData),
+    #[serde(rename = "refactor")]
+    Refactor(RefactorData),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,14 +46,84 @@ pub struct FixAllData {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrganizeImportsData {
     pub file: String,
+    /// tsserver `OrganizeImportsMode`; `None` means a full organize.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// File-wide source actions listed alongside organize imports. Each one is
+/// resolved lazily through the same payloads as the matching `TSB*` command.
+struct SourceAction {
+    kind: &'static str,
+    title: &'static str,
+    fix_id: Option<&'static str>,
+    organize_mode: Option<&'static str>,
+}
+
+const SOURCE_ACTIONS: &[SourceAction] = &[
+    SourceAction {
+        kind: "source.addMissingImports.ts",
+        title: "Add all missing imports",
+        fix_id: Some("fixMissingImport"),
+        organize_mode: None,
+    },
+    SourceAction {
+        kind: "source.removeUnused.ts",
+        title: "Remove all unused code",
+        fix_id: Some("unusedIdentifier_delete"),
+        organize_mode: None,
+    },
+    SourceAction {
+        kind: "source.removeUnusedImports.ts",
+        title: "Remove unused imports",
+        fix_id: None,
+        organize_mode: Some("RemoveUnused"),
+    },
+];
+
+/// Kinds advertised in the server capabilities.
+pub fn source_action_kinds() -> impl Iterator<Item = CodeActionKind> {
+    SOURCE_ACTIONS
+        .iter()
+        .map(|action| CodeActionKind::from(action.kind.to_string()))
+}
+
+/// Everything `getEditsForRefactor` needs to reproduce a listed refactor.
+/// Interactive refactors ("Move to file") additionally need `target_file`,
+/// which the client fills in after asking the user for a destination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefactorData {
+    pub file: String,
+    pub range: Range,
+    pub refactor: String,
+    pub action: String,
+    #[serde(
+        default,
+        rename = "targetFile",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_file: Option<String>,
+}
+
+/// Command attached to interactive refactors; see `workspace/executeCommand`.
+pub const MOVE_TO_FILE_COMMAND: &str = "TSBMoveToFile";
+
+#[derive(Debug, Serialize, Deserialize)]
 struct AdapterContext {
     file: String,
     context: CodeActionContext,
     #[serde(default, rename = "includeOrganize")]
     include_organize: bool,
+    #[serde(default)]
+    range: Option<Range>,
+    #[serde(default, rename = "includeRefactors")]
+    include_refactors: bool,
+    /// Source action kinds explicitly requested through `only`.
+    #[serde(default, rename = "sourceActions")]
+    source_actions: Vec<String>,
+    /// Actions gathered by earlier requests in the chain.
+    #[serde(default)]
+    actions: Vec<CodeActionOrCommand>,
 }
 
 pub fn handle(params: CodeActionParams) -> RequestSpec {
@@ -65,8 +140,10 @@ pub fn handle(params: CodeActionParams) -> RequestSpec {
     let wants_organize = context_only
         .as_ref()
         .map(|list| {
-            list.iter()
-                .any(|kind| matches_kind(kind, CodeActionKind::SOURCE_ORGANIZE_IMPORTS.as_str()))
+            list.iter().any(|kind| {
+                matches_kind(kind, CodeActionKind::SOURCE_ORGANIZE_IMPORTS.as_str())
+                    || matches_kind(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS, kind.as_str())
+            })
         })
         .unwrap_or(false);
     let wants_quickfix = context_only
@@ -77,12 +154,27 @@ pub fn handle(params: CodeActionParams) -> RequestSpec {
         })
         .unwrap_or(true);
 
+    let wants_refactor = context_only
+        .as_ref()
+        .map(|list| {
+            list.iter().any(|kind| {
+                matches_kind(kind, CodeActionKind::REFACTOR.as_str())
+                    || matches_kind(&CodeActionKind::REFACTOR, kind.as_str())
+            })
+        })
+        .unwrap_or(true);
+
     let has_filter = context_only
         .as_ref()
         .map(|list| !list.is_empty())
         .unwrap_or(false);
 
-    if wants_organize && !wants_quickfix {
+    let source_actions = context_only
+        .as_ref()
+        .map(|list| requested_source_actions(list))
+        .unwrap_or_default();
+
+    if wants_organize && !wants_quickfix && !wants_refactor && source_actions.is_empty() {
         return organize_imports_request(file);
     }
 
@@ -90,7 +182,24 @@ pub fn handle(params: CodeActionParams) -> RequestSpec {
     // quick fixes so the default picker shows it.
     let include_organize = wants_organize || !has_filter;
 
-    let error_codes = collect_error_codes(&context);
+    if wants_refactor && !wants_quickfix {
+        let adapter_context = AdapterContext {
+            file,
+            context,
+            include_organize,
+            range: Some(range),
+            include_refactors: true,
+            source_actions,
+            actions: Vec::new(),
+        };
+        return refactors_request(adapter_context);
+    }
+
+    let error_codes = if wants_quickfix {
+        collect_error_codes(&context)
+    } else {
+        Vec::new()
+    };
 
     let request = json!({
         "command": "getCodeFixes",
@@ -108,6 +217,9 @@ pub fn handle(params: CodeActionParams) -> RequestSpec {
         "file": file,
         "context": context,
         "includeOrganize": include_organize,
+        "range": range,
+        "includeRefactors": wants_refactor,
+        "sourceActions": source_actions,
     });
 
     RequestSpec {
@@ -120,7 +232,7 @@ pub fn handle(params: CodeActionParams) -> RequestSpec {
 }
 
 fn organize_imports_request(file: String) -> RequestSpec {
-    let request = organize_imports_payload(&file);
+    let request = organize_imports_payload(&file, None);
 
     RequestSpec {
         route: Route::Syntax,
@@ -131,8 +243,36 @@ fn organize_imports_request(file: String) -> RequestSpec {
     }
 }
 
+fn refactors_request(adapter_context: AdapterContext) -> RequestSpec {
+    let range = adapter_context.range.unwrap_or_default();
+    let trigger_reason = match adapter_context.context.trigger_kind {
+        Some(lsp_types::CodeActionTriggerKind::AUTOMATIC) => "implicit",
+        _ => "invoked",
+    };
+    let request = json!({
+        "command": "getApplicableRefactors",
+        "arguments": {
+            "file": adapter_context.file,
+            "startLine": range.start.line + 1,
+            "startOffset": range.start.character + 1,
+            "endLine": range.end.line + 1,
+            "endOffset": range.end.character + 1,
+            "triggerReason": trigger_reason,
+            "includeInteractiveActions": true,
+        }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapt_refactors),
+        response_context: Some(json!(adapter_context)),
+    }
+}
+
 fn adapt_code_actions(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
-    let adapter_ctx: AdapterContext =
+    let mut adapter_ctx: AdapterContext =
         serde_json::from_value(context.cloned().context("code action context missing")?)?;
     let fixes = payload
         .get("body")
@@ -150,8 +290,47 @@ fn adapt_code_actions(payload: &Value, context: Option<&Value>) -> Result<Adapte
         }
     }
 
-    if adapter_ctx.include_organize {
-        if let Some(action) = organize_imports_placeholder(&adapter_ctx.file) {
+    if adapter_ctx.include_refactors && adapter_ctx.range.is_some() {
+        adapter_ctx.actions = actions;
+        return Ok(AdapterResult::Continue(refactors_request(adapter_ctx)));
+    }
+
+    finish_code_actions(actions, &adapter_ctx)
+}
+
+fn adapt_refactors(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut adapter_ctx: AdapterContext =
+        serde_json::from_value(context.cloned().context("code action context missing")?)?;
+    let range = adapter_ctx
+        .range
+        .context("refactor context missing range")?;
+    let refactors = payload
+        .get("body")
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut actions = std::mem::take(&mut adapter_ctx.actions);
+    for refactor in &refactors {
+        for action in build_refactor_actions(refactor, &adapter_ctx, &range) {
+            actions.push(CodeActionOrCommand::CodeAction(action));
+        }
+    }
+
+    finish_code_actions(actions, &adapter_ctx)
+}
+
+fn finish_code_actions(
+    mut actions: Vec<CodeActionOrCommand>,
+    adapter_ctx: &AdapterContext,
+) -> Result<AdapterResult> {
+    if adapter_ctx.include_organize
+        && let Some(action) = organize_imports_placeholder(&adapter_ctx.file)
+    {
+        actions.push(CodeActionOrCommand::CodeAction(action));
+    }
+    for kind in &adapter_ctx.source_actions {
+        if let Some(action) = source_action_placeholder(&adapter_ctx.file, kind) {
             actions.push(CodeActionOrCommand::CodeAction(action));
         }
     }
@@ -161,6 +340,137 @@ fn adapt_code_actions(payload: &Value, context: Option<&Value>) -> Result<Adapte
     )?))
 }
 
+/// Source actions whose kind falls under one of the `only` filters, so both
+/// `source` and the exact `source.addMissingImports.ts` select them.
+fn requested_source_actions(only: &[CodeActionKind]) -> Vec<String> {
+    source_action_kinds()
+        .filter(|kind| {
+            only.iter()
+                .any(|filter| matches_kind(kind, filter.as_str()))
+        })
+        .map(|kind| kind.as_str().to_string())
+        .collect()
+}
+
+fn source_action_placeholder(file: &str, kind: &str) -> Option<CodeAction> {
+    let action = SOURCE_ACTIONS.iter().find(|action| action.kind == kind)?;
+    let data = match action.fix_id {
+        Some(fix_id) => CodeActionData::FixAll(FixAllData {
+            file: file.to_string(),
+            fix_id: fix_id.to_string(),
+        }),
+        None => CodeActionData::OrganizeImports(OrganizeImportsData {
+            file: file.to_string(),
+            mode: action.organize_mode.map(str::to_string),
+        }),
+    };
+    Some(CodeAction {
+        title: action.title.to_string(),
+        kind: Some(CodeActionKind::from(action.kind.to_string())),
+        data: Some(serde_json::to_value(data).ok()?),
+        ..CodeAction::default()
+    })
+}
+
+fn build_refactor_actions(
+    refactor: &Value,
+    ctx: &AdapterContext,
+    range: &Range,
+) -> Vec<CodeAction> {
+    let Some(refactor_name) = refactor.get("name").and_then(|v| v.as_str()) else {
+        return Vec::new();
+    };
+    let entries = refactor
+        .get("actions")
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut actions = Vec::new();
+    for entry in entries {
+        let Some(action_name) = entry.get("name").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let title = entry
+            .get("description")
+            .and_then(|v| v.as_str())
+            .unwrap_or(action_name)
+            .to_string();
+        let kind = refactor_kind(
+            entry.get("kind").and_then(|v| v.as_str()),
+            refactor_name,
+            action_name,
+        );
+        if let Some(only) = &ctx.context.only
+            && !only
+                .iter()
+                .any(|filter| matches_kind(&kind, filter.as_str()))
+        {
+            continue;
+        }
+        let disabled = entry
+            .get("notApplicableReason")
+            .and_then(|v| v.as_str())
+            .map(|reason| CodeActionDisabled {
+                reason: reason.to_string(),
+            });
+        let data = RefactorData {
+            file: ctx.file.clone(),
+            range: *range,
+            refactor: refactor_name.to_string(),
+            action: action_name.to_string(),
+            target_file: None,
+        };
+        let interactive = entry
+            .get("isInteractive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if interactive {
+            // The destination is picked by the user, so there is nothing to
+            // resolve up front: the client runs the command with a target.
+            actions.push(CodeAction {
+                command: Some(Command {
+                    title: title.clone(),
+                    command: MOVE_TO_FILE_COMMAND.to_string(),
+                    arguments: serde_json::to_value(data).ok().map(|data| vec![data]),
+                }),
+                title,
+                kind: Some(kind),
+                disabled,
+                ..CodeAction::default()
+            });
+            continue;
+        }
+        actions.push(CodeAction {
+            title,
+            kind: Some(kind),
+            disabled,
+            data: serde_json::to_value(CodeActionData::Refactor(data)).ok(),
+            ..CodeAction::default()
+        });
+    }
+    actions
+}
+
+/// Newer TypeScript versions report the LSP kind directly; older ones only
+/// expose refactor/action names, so we map the well-known ones by hand.
+fn refactor_kind(reported: Option<&str>, refactor: &str, action: &str) -> CodeActionKind {
+    if let Some(kind) = reported.filter(|kind| kind.starts_with("refactor")) {
+        return CodeActionKind::from(kind.to_string());
+    }
+    let kind = match refactor {
+        "Extract Symbol" if action.starts_with("function_") => "refactor.extract.function",
+        "Extract Symbol" if action.starts_with("constant_") => "refactor.extract.constant",
+        "Extract type" => "refactor.extract.type",
+        "Extract Symbol" => "refactor.extract",
+        "Move to a new file" => "refactor.move.newFile",
+        "Move to file" => "refactor.move.file",
+        "Inline variable" => "refactor.inline.variable",
+        _ => "refactor.rewrite",
+    };
+    CodeActionKind::from(kind.to_string())
+}
+
 fn build_quick_fix(fix: &Value, ctx: &AdapterContext) -> Option<CodeAction> {
     let title = fix.get("description")?.as_str()?.to_string();
     let changes = fix.get("changes")?.as_array()?;
@@ -209,6 +519,7 @@ fn build_fix_all_action(fix: &Value, ctx: &AdapterContext) -> Option<CodeAction>
 fn organize_imports_placeholder(file: &str) -> Option<CodeAction> {
     let data = CodeActionData::OrganizeImports(OrganizeImportsData {
         file: file.to_string(),
+        mode: None,
     });
     Some(CodeAction {
         title: "Organize Imports".to_string(),
@@ -292,8 +603,8 @@ fn matches_kind(kind: &CodeActionKind, needle: &str) -> bool {
     value == needle || value.starts_with(&(needle.to_string() + "."))
 }
 
-pub(crate) fn organize_imports_payload(file: &str) -> Value {
-    json!({
+pub(crate) fn organize_imports_payload(file: &str, mode: Option<&str>) -> Value {
+    let mut payload = json!({
         "command": "organizeImports",
         "arguments": {
             "scope": {
@@ -303,7 +614,11 @@ pub(crate) fn organize_imports_payload(file: &str) -> Value {
                 }
             }
         }
-    })
+    });
+    if let Some(mode) = mode {
+        payload["arguments"]["mode"] = json!(mode);
+    }
+    payload
 }
 
 #[cfg(test)]
@@ -357,6 +672,10 @@ mod tests {
             file: FILE_PATH.to_string(),
             context: sample_context(),
             include_organize: false,
+            range: None,
+            include_refactors: false,
+            source_actions: Vec::new(),
+            actions: Vec::new(),
         };
         let fix = json!({
             "fixId": "fixAllMissingImports",
@@ -399,7 +718,9 @@ mod tests {
         let adapted = adapt_code_actions(&payload, Some(&ctx_value)).expect("adapt");
         let value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready code action response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready code action response")
+            }
         };
         let actions: Vec<_> = match serde_json::from_value::<CodeActionResponse>(value) {
             Ok(actions) => actions,
@@ -477,4 +798,213 @@ mod tests {
             .expect("error codes array");
         assert_eq!(error_codes, &[json!(1234)]);
     }
+
+    #[test]
+    fn handle_requests_refactors_when_only_refactor_kinds_wanted() {
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str(FILE_URI).expect("uri"),
+            },
+            range: Range {
+                start: Position {
+                    line: 1,
+                    character: 2,
+                },
+                end: Position {
+                    line: 1,
+                    character: 9,
+                },
+            },
+            context: CodeActionContext {
+                diagnostics: Vec::new(),
+                only: Some(vec![CodeActionKind::REFACTOR_EXTRACT]),
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let spec = handle(params);
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("getApplicableRefactors"))
+        );
+
+        let payload = json!({
+            "body": [{
+                "name": "Extract Symbol",
+                "description": "Extract function",
+                "actions": [{
+                    "name": "function_scope_0",
+                    "description": "Extract to function in module scope"
+                }, {
+                    "name": "constant_scope_0",
+                    "description": "Extract to constant in enclosing scope",
+                    "notApplicableReason": "Cannot extract empty range."
+                }]
+            }, {
+                "name": "Convert import",
+                "description": "Convert import",
+                "actions": [{
+                    "name": "Convert named imports to namespace import",
+                    "description": "Convert named imports to namespace import",
+                    "kind": "refactor.rewrite.import.namespace"
+                }]
+            }]
+        });
+        let adapted = adapt_refactors(&payload, spec.response_context.as_ref()).expect("adapt");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready refactor actions")
+            }
+        };
+        let actions: CodeActionResponse = serde_json::from_value(value).expect("actions");
+        assert_eq!(actions.len(), 2, "rewrite refactor is filtered by `only`");
+        let CodeActionOrCommand::CodeAction(first) = &actions[0] else {
+            panic!("expected code action");
+        };
+        assert_eq!(
+            first.kind.as_ref().map(|kind| kind.as_str()),
+            Some("refactor.extract.function")
+        );
+        let CodeActionOrCommand::CodeAction(second) = &actions[1] else {
+            panic!("expected code action");
+        };
+        assert!(second.disabled.is_some());
+        let data: CodeActionData =
+            serde_json::from_value(first.data.clone().expect("data")).expect("refactor data");
+        match data {
+            CodeActionData::Refactor(refactor) => {
+                assert_eq!(refactor.refactor, "Extract Symbol");
+                assert_eq!(refactor.action, "function_scope_0");
+                assert_eq!(refactor.range.start.character, 2);
+            }
+            _ => panic!("expected refactor data"),
+        }
+    }
+
+    #[test]
+    fn interactive_move_to_file_becomes_command() {
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str(FILE_URI).expect("uri"),
+            },
+            range: Range::default(),
+            context: CodeActionContext {
+                diagnostics: Vec::new(),
+                only: Some(vec![CodeActionKind::from("refactor.move".to_string())]),
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let spec = handle(params);
+        assert_eq!(
+            spec.payload.pointer("/arguments/includeInteractiveActions"),
+            Some(&json!(true))
+        );
+
+        let payload = json!({
+            "body": [{
+                "name": "Move to file",
+                "description": "Move to file",
+                "actions": [{
+                    "name": "Move to file",
+                    "description": "Move to file",
+                    "kind": "refactor.move.file",
+                    "isInteractive": true
+                }]
+            }]
+        });
+        let adapted = adapt_refactors(&payload, spec.response_context.as_ref()).expect("adapt");
+        let AdapterResult::Ready(value) = adapted else {
+            panic!("expected ready refactor actions");
+        };
+        let actions: CodeActionResponse = serde_json::from_value(value).expect("actions");
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expected code action");
+        };
+        assert!(
+            action.data.is_none(),
+            "interactive refactors are not resolved"
+        );
+        let command = action.command.as_ref().expect("command");
+        assert_eq!(command.command, MOVE_TO_FILE_COMMAND);
+        let data: RefactorData =
+            serde_json::from_value(command.arguments.as_ref().expect("arguments")[0].clone())
+                .expect("refactor data");
+        assert_eq!(data.refactor, "Move to file");
+        assert_eq!(data.target_file, None);
+    }
+
+    #[test]
+    fn source_filter_lists_lazy_source_actions() {
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str(FILE_URI).expect("uri"),
+            },
+            range: Range::default(),
+            context: CodeActionContext {
+                diagnostics: vec![sample_diagnostic(2304)],
+                only: Some(vec![CodeActionKind::SOURCE]),
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let spec = handle(params);
+        assert_eq!(
+            spec.payload.pointer("/arguments/errorCodes"),
+            Some(&json!([])),
+            "quick fixes are not requested for source-only filters"
+        );
+        let adapted =
+            adapt_code_actions(&json!({ "body": [] }), spec.response_context.as_ref()).unwrap();
+        let AdapterResult::Ready(value) = adapted else {
+            panic!("expected ready source actions");
+        };
+        let actions: CodeActionResponse = serde_json::from_value(value).expect("actions");
+        let kinds: Vec<_> = actions
+            .iter()
+            .filter_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.kind.clone(),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                CodeActionKind::from("source.addMissingImports.ts".to_string()),
+                CodeActionKind::from("source.removeUnused.ts".to_string()),
+                CodeActionKind::from("source.removeUnusedImports.ts".to_string()),
+            ]
+        );
+
+        let CodeActionOrCommand::CodeAction(remove_imports) = &actions[3] else {
+            panic!("expected code action");
+        };
+        let data: CodeActionData =
+            serde_json::from_value(remove_imports.data.clone().expect("data")).unwrap();
+        match data {
+            CodeActionData::OrganizeImports(data) => {
+                assert_eq!(data.mode.as_deref(), Some("RemoveUnused"))
+            }
+            _ => panic!("expected organize imports data"),
+        }
+    }
+
+    #[test]
+    fn exact_source_kind_selects_single_action() {
+        let only = [CodeActionKind::from(
+            "source.addMissingImports.ts".to_string(),
+        )];
+        assert_eq!(
+            requested_source_actions(&only),
+            vec!["source.addMissingImports.ts".to_string()]
+        );
+    }
 }
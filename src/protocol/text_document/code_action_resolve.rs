@@ -2,20 +2,26 @@
 //! codeAction/resolve
 //! =============================================================================
 //!
-//! Resolves lazily-evaluated code actions, currently focusing on "fix all".
-//! When a code action stores `CodeActionData::FixAll`, we reissue tsserver’s
-//! `getCombinedCodeFix` to materialize the edits.
+//! Resolves lazily-evaluated code actions: "fix all" (`getCombinedCodeFix`),
+//! organize imports, and refactors (`getEditsForRefactor`).  Refactor edits
+//! may target files that do not exist yet (e.g. "Move to a new file"), so those
+//! are returned as `documentChanges` with an explicit `CreateFile` operation.
 
 use anyhow::{Context, Result};
-use lsp_types::{CodeAction, CodeActionKind};
+use lsp_types::{
+    CodeAction, CodeActionKind, CreateFile, CreateFileOptions, DocumentChangeOperation,
+    DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier, ResourceOp, TextDocumentEdit,
+    TextEdit, WorkspaceEdit,
+};
 use serde_json::{Value, json};
 
 use crate::protocol::text_document::code_action::{
-    CodeActionData, FixAllData, OrganizeImportsData, organize_imports_payload,
+    CodeActionData, FixAllData, OrganizeImportsData, RefactorData, organize_imports_payload,
     workspace_edit_from_tsserver_changes,
 };
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_file_to_uri, tsserver_range_from_value_lsp};
 
 pub fn handle(mut action: CodeAction) -> Option<RequestSpec> {
     let data = action.data.take()?;
@@ -24,9 +30,44 @@ pub fn handle(mut action: CodeAction) -> Option<RequestSpec> {
     match data {
         CodeActionData::FixAll(fix_all) => build_fix_all_request(action, fix_all),
         CodeActionData::OrganizeImports(data) => build_organize_imports_request(action, data),
+        CodeActionData::Refactor(data) => build_refactor_request(action, data),
     }
 }
 
+fn build_refactor_request(action: CodeAction, data: RefactorData) -> Option<RequestSpec> {
+    let request = refactor_edits_payload(&data);
+    let context = serde_json::to_value(action).ok()?;
+
+    Some(RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapt_refactor_response),
+        response_context: Some(context),
+    })
+}
+
+/// `getEditsForRefactor` payload; a chosen `target_file` is forwarded as
+/// `interactiveRefactorArguments` for "Move to file".
+pub(crate) fn refactor_edits_payload(data: &RefactorData) -> Value {
+    let mut arguments = json!({
+        "file": data.file,
+        "startLine": data.range.start.line + 1,
+        "startOffset": data.range.start.character + 1,
+        "endLine": data.range.end.line + 1,
+        "endOffset": data.range.end.character + 1,
+        "refactor": data.refactor,
+        "action": data.action,
+    });
+    if let Some(target) = &data.target_file {
+        arguments["interactiveRefactorArguments"] = json!({ "targetFile": target });
+    }
+    json!({
+        "command": "getEditsForRefactor",
+        "arguments": arguments,
+    })
+}
+
 fn build_fix_all_request(action: CodeAction, fix_all: FixAllData) -> Option<RequestSpec> {
     let request = json!({
         "command": "getCombinedCodeFix",
@@ -56,7 +97,7 @@ fn build_organize_imports_request(
     action: CodeAction,
     data: OrganizeImportsData,
 ) -> Option<RequestSpec> {
-    let request = organize_imports_payload(&data.file);
+    let request = organize_imports_payload(&data.file, data.mode.as_deref());
     let context = serde_json::to_value(action).ok()?;
 
     Some(RequestSpec {
@@ -114,3 +155,184 @@ fn adapt_organize_imports_response(
 
     Ok(AdapterResult::ready(serde_json::to_value(action)?))
 }
+
+fn adapt_refactor_response(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut action: CodeAction =
+        serde_json::from_value(context.cloned().context("missing code action context")?)?;
+    let edits = payload
+        .get("body")
+        .and_then(|body| body.get("edits"))
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    action.edit = refactor_workspace_edit(&edits)?;
+    if action.kind.is_none() {
+        action.kind = Some(CodeActionKind::REFACTOR);
+    }
+
+    Ok(AdapterResult::ready(serde_json::to_value(action)?))
+}
+
+/// Plain `changes` are enough unless tsserver flags an edit with `isNewFile`,
+/// in which case the file must be created before its text edits apply.
+pub(crate) fn refactor_workspace_edit(edits: &[Value]) -> Result<Option<WorkspaceEdit>> {
+    let creates_files = edits.iter().any(|edit| {
+        edit.get("isNewFile")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    });
+    if !creates_files {
+        return Ok(workspace_edit_from_tsserver_changes(edits));
+    }
+
+    let mut operations = Vec::new();
+    for edit in edits {
+        let file = edit
+            .get("fileName")
+            .and_then(|v| v.as_str())
+            .context("refactor edit missing fileName")?;
+        let uri = tsserver_file_to_uri(file).context("invalid refactor file uri")?;
+        if edit
+            .get("isNewFile")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            operations.push(DocumentChangeOperation::Op(ResourceOp::Create(
+                CreateFile {
+                    uri: uri.clone(),
+                    options: Some(CreateFileOptions {
+                        overwrite: Some(false),
+                        ignore_if_exists: Some(true),
+                    }),
+                    annotation_id: None,
+                },
+            )));
+        }
+        let text_edits = edit
+            .get("textChanges")
+            .and_then(|v| v.as_array())
+            .map(|changes| {
+                changes
+                    .iter()
+                    .filter_map(|change| {
+                        Some(OneOf::Left(TextEdit {
+                            range: tsserver_range_from_value_lsp(change)?,
+                            new_text: change.get("newText")?.as_str()?.to_string(),
+                        }))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        operations.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+            edits: text_edits,
+        }));
+    }
+
+    Ok(Some(WorkspaceEdit {
+        changes: None,
+        document_changes: Some(DocumentChanges::Operations(operations)),
+        change_annotations: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, Range};
+
+    fn refactor_action() -> CodeAction {
+        let data = CodeActionData::Refactor(RefactorData {
+            file: "/workspace/app.ts".into(),
+            range: Range {
+                start: Position {
+                    line: 2,
+                    character: 4,
+                },
+                end: Position {
+                    line: 2,
+                    character: 20,
+                },
+            },
+            refactor: "Extract Symbol".into(),
+            action: "function_scope_0".into(),
+            target_file: None,
+        });
+        CodeAction {
+            title: "Extract to function in module scope".into(),
+            kind: Some(CodeActionKind::from(
+                "refactor.extract.function".to_string(),
+            )),
+            data: Some(serde_json::to_value(data).unwrap()),
+            ..CodeAction::default()
+        }
+    }
+
+    #[test]
+    fn resolve_refactor_requests_edits_for_range() {
+        let spec = handle(refactor_action()).expect("refactor spec");
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("getEditsForRefactor"))
+        );
+        let args = spec.payload.get("arguments").expect("arguments");
+        assert_eq!(args.get("startLine"), Some(&json!(3)));
+        assert_eq!(args.get("startOffset"), Some(&json!(5)));
+        assert_eq!(args.get("endOffset"), Some(&json!(21)));
+        assert_eq!(args.get("refactor"), Some(&json!("Extract Symbol")));
+        assert_eq!(args.get("action"), Some(&json!("function_scope_0")));
+    }
+
+    #[test]
+    fn refactor_edits_create_new_files() {
+        let edits = vec![
+            json!({
+                "fileName": "/workspace/app.ts",
+                "textChanges": [{
+                    "start": { "line": 1, "offset": 1 },
+                    "end": { "line": 3, "offset": 1 },
+                    "newText": ""
+                }]
+            }),
+            json!({
+                "fileName": "/workspace/helper.ts",
+                "isNewFile": true,
+                "textChanges": [{
+                    "start": { "line": 1, "offset": 1 },
+                    "end": { "line": 1, "offset": 1 },
+                    "newText": "export function helper() {}\n"
+                }]
+            }),
+        ];
+
+        let edit = refactor_workspace_edit(&edits)
+            .expect("edit builds")
+            .expect("edit present");
+        let Some(DocumentChanges::Operations(ops)) = edit.document_changes else {
+            panic!("expected document change operations");
+        };
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(
+            &ops[1],
+            DocumentChangeOperation::Op(ResourceOp::Create(create))
+                if create.uri.as_str() == "file:///workspace/helper.ts"
+        ));
+    }
+
+    #[test]
+    fn refactor_payload_forwards_target_file() {
+        let data = RefactorData {
+            file: "/workspace/app.ts".into(),
+            range: Range::default(),
+            refactor: "Move to file".into(),
+            action: "Move to file".into(),
+            target_file: Some("/workspace/utils.ts".into()),
+        };
+        let payload = refactor_edits_payload(&data);
+        assert_eq!(
+            payload.pointer("/arguments/interactiveRefactorArguments/targetFile"),
+            Some(&json!("/workspace/utils.ts"))
+        );
+    }
+}
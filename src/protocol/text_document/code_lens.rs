@@ -0,0 +1,391 @@
+//! =============================================================================
+//! textDocument/codeLens & codeLens/resolve
+//! =============================================================================
+//!
+//! Lens anchors come from tsserver’s `navtree` so the initial request stays
+//! cheap: we only decide *where* a references/implementations lens belongs.
+//! Counting happens lazily in `codeLens/resolve`, which issues `references` or
+//! `implementation` for the single lens the editor is about to display.
+
+use anyhow::{Context, Result};
+use lsp_types::{CodeLens, CodeLensParams, Command, Location, Range};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::CodeLensMode;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_range_from_value_lsp, tsserver_span_to_location, uri_to_file_path};
+
+const CMD_NAVTREE: &str = "navtree";
+const CMD_REFERENCES: &str = "references";
+const CMD_IMPLEMENTATION: &str = "implementation";
+/// Client-side command attached to resolved lenses. Arguments follow the
+/// VS Code convention: `[uri, position, locations]`.
+pub const SHOW_REFERENCES_COMMAND: &str = "editor.action.showReferences";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LensKind {
+    References,
+    Implementations,
+}
+
+/// Payload stored in `CodeLens::data` so resolve can rebuild the tsserver
+/// request without consulting the document store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LensData {
+    uri: String,
+    file: String,
+    kind: LensKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NavtreeContext {
+    uri: String,
+    file: String,
+    references: bool,
+    implementations: bool,
+}
+
+pub fn handle(params: CodeLensParams, mode: CodeLensMode) -> RequestSpec {
+    let uri = params.text_document.uri;
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+
+    let request = json!({
+        "command": CMD_NAVTREE,
+        "arguments": { "file": file }
+    });
+    let context = NavtreeContext {
+        uri: uri.to_string(),
+        file,
+        references: mode.references(),
+        implementations: mode.implementations(),
+    };
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Low,
+        on_response: Some(adapt_code_lenses),
+        response_context: Some(json!(context)),
+    }
+}
+
+pub fn handle_resolve(lens: CodeLens) -> Option<RequestSpec> {
+    let data: LensData = serde_json::from_value(lens.data.clone()?).ok()?;
+    let position = lens.range.start;
+    let command = match data.kind {
+        LensKind::References => CMD_REFERENCES,
+        LensKind::Implementations => CMD_IMPLEMENTATION,
+    };
+
+    let request = json!({
+        "command": command,
+        "arguments": {
+            "file": data.file,
+            "line": position.line + 1,
+            "offset": position.character + 1,
+        }
+    });
+
+    Some(RequestSpec {
+        route: Route::SemanticPreferred,
+        payload: request,
+        priority: Priority::Low,
+        on_response: Some(adapt_code_lens_resolve),
+        response_context: Some(serde_json::to_value(lens).ok()?),
+    })
+}
+
+fn adapt_code_lenses(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let ctx: NavtreeContext =
+        serde_json::from_value(context.cloned().context("missing code lens context")?)?;
+    let body = payload
+        .get("body")
+        .context("tsserver navtree missing body")?;
+
+    let mut lenses = Vec::new();
+    collect_lenses(body, None, &ctx, &mut lenses);
+    Ok(AdapterResult::ready(serde_json::to_value(lenses)?))
+}
+
+fn collect_lenses(
+    node: &Value,
+    parent_kind: Option<&str>,
+    ctx: &NavtreeContext,
+    lenses: &mut Vec<CodeLens>,
+) {
+    let kind = node.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+    let modifiers = node
+        .get("kindModifiers")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+
+    if let Some(range) = lens_range(node) {
+        if ctx.references && wants_references_lens(node, kind, modifiers, parent_kind) {
+            lenses.push(unresolved_lens(range, ctx, LensKind::References));
+        }
+        if ctx.implementations && wants_implementations_lens(kind, modifiers, parent_kind) {
+            lenses.push(unresolved_lens(range, ctx, LensKind::Implementations));
+        }
+    }
+
+    if let Some(children) = node.get("childItems").and_then(|v| v.as_array()) {
+        for child in children {
+            collect_lenses(child, Some(kind), ctx, lenses);
+        }
+    }
+}
+
+fn unresolved_lens(range: Range, ctx: &NavtreeContext, kind: LensKind) -> CodeLens {
+    let data = LensData {
+        uri: ctx.uri.clone(),
+        file: ctx.file.clone(),
+        kind,
+    };
+    CodeLens {
+        range,
+        command: None,
+        data: Some(json!(data)),
+    }
+}
+
+/// Mirrors the VS Code TypeScript extension: named declarations and class or
+/// interface members get a references lens, locals inside functions do not.
+fn wants_references_lens(
+    node: &Value,
+    kind: &str,
+    modifiers: &str,
+    parent_kind: Option<&str>,
+) -> bool {
+    if parent_kind == Some("enum") {
+        return true;
+    }
+    match kind {
+        "function" | "interface" | "type" | "enum" => true,
+        "class" => node.get("text").and_then(|v| v.as_str()) != Some("<class>"),
+        "const" | "let" | "var" => has_modifier(modifiers, "export"),
+        "method" | "property" | "getter" | "setter" | "constructor" => {
+            matches!(parent_kind, Some("class") | Some("interface"))
+        }
+        _ => false,
+    }
+}
+
+fn wants_implementations_lens(kind: &str, modifiers: &str, parent_kind: Option<&str>) -> bool {
+    match kind {
+        "interface" => true,
+        "class" | "method" | "property" | "getter" | "setter" => {
+            has_modifier(modifiers, "abstract")
+                || (kind != "class" && parent_kind == Some("interface"))
+        }
+        _ => false,
+    }
+}
+
+fn has_modifier(modifiers: &str, needle: &str) -> bool {
+    modifiers
+        .split(',')
+        .any(|modifier| modifier.trim() == needle)
+}
+
+fn lens_range(node: &Value) -> Option<Range> {
+    if let Some(span) = node.get("nameSpan") {
+        return tsserver_range_from_value_lsp(span);
+    }
+    let span = node.get("spans")?.as_array()?.first()?;
+    let range = tsserver_range_from_value_lsp(span)?;
+    Some(Range {
+        start: range.start,
+        end: range.start,
+    })
+}
+
+fn adapt_code_lens_resolve(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut lens: CodeLens =
+        serde_json::from_value(context.cloned().context("missing code lens context")?)?;
+    let data: LensData = serde_json::from_value(
+        lens.data
+            .clone()
+            .context("code lens missing resolve data")?,
+    )?;
+    let body = payload
+        .get("body")
+        .context("code lens resolve missing body")?;
+
+    let locations = match data.kind {
+        LensKind::References => body
+            .get("refs")
+            .and_then(|v| v.as_array())
+            .map(|refs| {
+                refs.iter()
+                    .filter(|entry| {
+                        !entry
+                            .get("isDefinition")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false)
+                    })
+                    .filter_map(tsserver_span_to_location)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+        LensKind::Implementations => body
+            .as_array()
+            .map(|spans| {
+                spans
+                    .iter()
+                    .filter_map(tsserver_span_to_location)
+                    .filter(|location| {
+                        location.uri.as_str() != data.uri
+                            || location.range.start.line != lens.range.start.line
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+    };
+
+    lens.command = Some(lens_command(&data, &lens, locations));
+    Ok(AdapterResult::ready(serde_json::to_value(lens)?))
+}
+
+fn lens_command(data: &LensData, lens: &CodeLens, locations: Vec<Location>) -> Command {
+    let count = locations.len();
+    let noun = match (data.kind, count) {
+        (LensKind::References, 1) => "reference",
+        (LensKind::References, _) => "references",
+        (LensKind::Implementations, 1) => "implementation",
+        (LensKind::Implementations, _) => "implementations",
+    };
+    Command {
+        title: format!("{count} {noun}"),
+        command: SHOW_REFERENCES_COMMAND.to_string(),
+        arguments: Some(vec![
+            json!(data.uri),
+            json!(lens.range.start),
+            json!(locations),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, TextDocumentIdentifier, Uri};
+    use std::str::FromStr;
+
+    fn params() -> CodeLensParams {
+        CodeLensParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str("file:///workspace/src/shapes.ts").unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    fn navtree() -> Value {
+        json!({
+            "body": {
+                "text": "<global>",
+                "kind": "script",
+                "kindModifiers": "",
+                "spans": [{ "start": { "line": 1, "offset": 1 }, "end": { "line": 20, "offset": 1 } }],
+                "childItems": [{
+                    "text": "Shape",
+                    "kind": "interface",
+                    "kindModifiers": "export",
+                    "spans": [{ "start": { "line": 1, "offset": 1 }, "end": { "line": 3, "offset": 2 } }],
+                    "nameSpan": { "start": { "line": 1, "offset": 18 }, "end": { "line": 1, "offset": 23 } },
+                    "childItems": [{
+                        "text": "area",
+                        "kind": "method",
+                        "kindModifiers": "",
+                        "spans": [{ "start": { "line": 2, "offset": 3 }, "end": { "line": 2, "offset": 18 } }],
+                        "nameSpan": { "start": { "line": 2, "offset": 3 }, "end": { "line": 2, "offset": 7 } }
+                    }]
+                }, {
+                    "text": "helper",
+                    "kind": "const",
+                    "kindModifiers": "",
+                    "spans": [{ "start": { "line": 5, "offset": 7 }, "end": { "line": 5, "offset": 20 } }],
+                    "nameSpan": { "start": { "line": 5, "offset": 7 }, "end": { "line": 5, "offset": 13 } }
+                }]
+            }
+        })
+    }
+
+    fn adapt(mode: CodeLensMode) -> Vec<CodeLens> {
+        let spec = handle(params(), mode);
+        let adapted = adapt_code_lenses(&navtree(), spec.response_context.as_ref())
+            .expect("code lenses adapt");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready code lenses")
+            }
+        };
+        serde_json::from_value(value).expect("code lenses deserialize")
+    }
+
+    #[test]
+    fn adapt_code_lenses_places_anchors_from_navtree() {
+        let lenses = adapt(CodeLensMode::All);
+        // interface: references + implementations, interface method: both too,
+        // non-exported const: none.
+        assert_eq!(lenses.len(), 4);
+        assert_eq!(lenses[0].range.start, Position::new(0, 17));
+        assert!(lenses.iter().all(|lens| lens.command.is_none()));
+        assert!(lenses.iter().all(|lens| lens.range.start.line != 4));
+    }
+
+    #[test]
+    fn adapt_code_lenses_respects_mode() {
+        let references = adapt(CodeLensMode::ReferencesOnly);
+        assert_eq!(references.len(), 2);
+        assert!(references.iter().all(|lens| {
+            lens.data.as_ref().and_then(|d| d.get("kind")) == Some(&json!("references"))
+        }));
+
+        let implementations = adapt(CodeLensMode::ImplementationsOnly);
+        assert_eq!(implementations.len(), 2);
+    }
+
+    #[test]
+    fn resolve_counts_references_excluding_definition() {
+        let lens = adapt(CodeLensMode::ReferencesOnly).remove(0);
+        let spec = handle_resolve(lens).expect("resolve spec");
+        assert_eq!(spec.payload.get("command"), Some(&json!("references")));
+        let args = spec.payload.get("arguments").expect("arguments missing");
+        assert_eq!(args.get("line").and_then(|v| v.as_u64()), Some(1));
+        assert_eq!(args.get("offset").and_then(|v| v.as_u64()), Some(18));
+
+        let payload = json!({
+            "body": {
+                "refs": [{
+                    "file": "/workspace/src/shapes.ts",
+                    "start": { "line": 1, "offset": 18 },
+                    "end": { "line": 1, "offset": 23 },
+                    "isDefinition": true
+                }, {
+                    "file": "/workspace/src/circle.ts",
+                    "start": { "line": 3, "offset": 25 },
+                    "end": { "line": 3, "offset": 30 },
+                    "isDefinition": false
+                }]
+            }
+        });
+        let adapted = adapt_code_lens_resolve(&payload, spec.response_context.as_ref())
+            .expect("resolve adapts");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready code lens")
+            }
+        };
+        let resolved: CodeLens = serde_json::from_value(value).expect("code lens deserialize");
+        let command = resolved.command.expect("command present");
+        assert_eq!(command.title, "1 reference");
+        assert_eq!(command.command, SHOW_REFERENCES_COMMAND);
+    }
+}
@@ -3,15 +3,32 @@
 //! =============================================================================
 //!
 //! Bridges LSP completion requests to tsserver’s `completionInfo` command and
-//! reshapes the entries into `CompletionList` items.
+//! reshapes the entries into `CompletionList` items.  With
+//! `complete_function_calls` enabled and a client that takes snippets,
+//! functions and methods are inserted as `name($1)$0` call snippets straight
+//! from the list; `completionItem/resolve` later fills in the real parameter
+//! placeholders.  Clients without `snippetSupport` get plain text from the
+//! list.
+//!
+//! The session keeps the last list per document keyed by the start of the word
+//! being typed; while the cursor stays inside that word, follow-up requests are
+//! answered with [`reuse_cached`] instead of another `completionInfo` trip.
+//!
+//! `completion.max_entries` caps huge lists (string-literal unions can yield
+//! tens of thousands of entries).  The converted items are ranked by
+//! `sortText`, or by whether they start with the typed word first, cut to the
+//! limit and marked incomplete so the client asks again as the word grows.
 
 use anyhow::{Context, Result};
 use lsp_types::{
-    CompletionItem, CompletionItemTag, CompletionList, CompletionParams, CompletionResponse,
-    CompletionTextEdit, InsertTextFormat, Position, TextEdit,
+    ClientCapabilities, CompletionItem, CompletionItemKind, CompletionItemLabelDetails,
+    CompletionItemTag, CompletionList, CompletionParams, CompletionResponse, CompletionTextEdit,
+    InsertTextFormat, Position, TextEdit,
 };
 use serde_json::{Value, json};
 
+use crate::config::{CommitCharacterSettings, EntriesLimitStrategy};
+use crate::protocol::text_document::completion_resolve::escape_snippet;
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
 use crate::utils::{
@@ -21,7 +38,31 @@ use crate::utils::{
 
 pub const TRIGGER_CHARACTERS: &[&str] = &[".", "\"", "'", "`", "/", "@", "<", "#", " "];
 
-pub fn handle(params: CompletionParams) -> RequestSpec {
+/// Completion-related settings resolved from `PluginSettings`.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionConfig {
+    pub complete_function_calls: bool,
+    /// The client declared `completionItem.snippetSupport`.
+    pub snippet_support: bool,
+    pub max_entries: Option<u32>,
+    pub entries_limit_strategy: EntriesLimitStrategy,
+    pub commit_characters: CommitCharacterSettings,
+}
+
+/// Whether the client expands `InsertTextFormat::Snippet` items.
+pub fn supports_snippets(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.completion.as_ref())
+        .and_then(|completion| completion.completion_item.as_ref())
+        .and_then(|item| item.snippet_support)
+        .unwrap_or(false)
+}
+
+/// `word` is the identifier prefix typed before the cursor; it only matters for
+/// [`EntriesLimitStrategy::Prefix`].
+pub fn handle(params: CompletionParams, config: CompletionConfig, word: &str) -> RequestSpec {
     let CompletionParams {
         text_document_position,
         work_done_progress_params: _,
@@ -74,11 +115,78 @@ pub fn handle(params: CompletionParams) -> RequestSpec {
             "position": {
                 "line": position.line,
                 "character": position.character,
-            }
+            },
+            "completeFunctionCalls": config.complete_function_calls,
+            "snippetSupport": config.snippet_support,
+            "maxEntries": config.max_entries,
+            "entriesLimitStrategy": config.entries_limit_strategy.as_str(),
+            "word": word,
+            "commitCharacters": config.commit_characters,
         })),
     }
 }
 
+/// UTF-16 column where the identifier ending at `character` starts.
+pub fn word_start(line: &str, character: u32) -> u32 {
+    let mut column = 0u32;
+    let mut start = 0u32;
+    for ch in line.chars() {
+        if column >= character {
+            break;
+        }
+        column += ch.len_utf16() as u32;
+        if !is_identifier_char(ch) {
+            start = column;
+        }
+    }
+    start.min(character)
+}
+
+/// The identifier text between [`word_start`] and `character`.
+pub fn typed_word(line: &str, character: u32) -> String {
+    let start = word_start(line, character);
+    let mut column = 0u32;
+    let mut word = String::new();
+    for ch in line.chars() {
+        if column >= character {
+            break;
+        }
+        if column >= start {
+            word.push(ch);
+        }
+        column += ch.len_utf16() as u32;
+    }
+    word
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+/// Replays a cached list for a cursor that moved from `original` to
+/// `current` within the same word. Edits that ended at the old cursor are
+/// stretched to the new one, and the list is marked incomplete so the client
+/// asks again on the next keystroke instead of trusting a stale filter.
+pub fn reuse_cached(
+    list: &CompletionList,
+    original: Position,
+    current: Position,
+) -> CompletionList {
+    let mut items = list.items.clone();
+    for item in &mut items {
+        if let Some(CompletionTextEdit::Edit(edit)) = item.text_edit.as_mut()
+            && edit.range.end == original
+            && edit.range.start <= current
+        {
+            edit.range.end = current;
+        }
+    }
+    CompletionList {
+        is_incomplete: true,
+        items,
+    }
+}
+
 fn adapt_completion(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
     let ctx = context.context("completion context missing")?;
     let file = ctx
@@ -94,6 +202,21 @@ fn adapt_completion(payload: &Value, context: Option<&Value>) -> Result<AdapterR
             line: 0,
             character: 0,
         });
+    let snippet_support = ctx
+        .get("snippetSupport")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let call_snippets = snippet_support
+        && ctx
+            .get("completeFunctionCalls")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+    let commit_characters: CommitCharacterSettings = ctx
+        .get("commitCharacters")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()?
+        .unwrap_or_default();
 
     let body = payload
         .get("body")
@@ -103,18 +226,40 @@ fn adapt_completion(payload: &Value, context: Option<&Value>) -> Result<AdapterR
         .and_then(|value| value.as_array())
         .cloned()
         .unwrap_or_default();
-    let is_incomplete = body
+    let mut is_incomplete = body
         .get("isIncomplete")
         .and_then(|value| value.as_bool())
         .unwrap_or(false);
 
     let mut items = Vec::with_capacity(entries.len());
     for entry in entries {
-        if let Some(item) = convert_entry(&entry, file, &position) {
+        if let Some(mut item) =
+            convert_entry(&entry, file, &position, &commit_characters, snippet_support)
+        {
+            if call_snippets {
+                apply_call_snippet(&mut item, &entry);
+            }
             items.push(item);
         }
     }
 
+    if let Some(max_entries) = ctx
+        .get("maxEntries")
+        .and_then(|v| v.as_u64())
+        .and_then(|v| usize::try_from(v).ok())
+        && items.len() > max_entries
+    {
+        let strategy = ctx
+            .get("entriesLimitStrategy")
+            .and_then(|v| v.as_str())
+            .and_then(EntriesLimitStrategy::parse)
+            .unwrap_or_default();
+        let word = ctx.get("word").and_then(|v| v.as_str()).unwrap_or_default();
+        rank_items(&mut items, strategy, word);
+        items.truncate(max_entries);
+        is_incomplete = true;
+    }
+
     let list = CompletionList {
         is_incomplete,
         items,
@@ -124,7 +269,29 @@ fn adapt_completion(payload: &Value, context: Option<&Value>) -> Result<AdapterR
     )?))
 }
 
-fn convert_entry(entry: &Value, file: &str, position: &Position) -> Option<CompletionItem> {
+/// Stable sort so entries sharing a `sortText` keep tsserver's order.
+fn rank_items(items: &mut [CompletionItem], strategy: EntriesLimitStrategy, word: &str) {
+    let word = word.to_lowercase();
+    items.sort_by_cached_key(|item| {
+        let misses_prefix = strategy == EntriesLimitStrategy::Prefix
+            && !item
+                .filter_text
+                .as_deref()
+                .unwrap_or(&item.label)
+                .to_lowercase()
+                .starts_with(&word);
+        let sort_text = item.sort_text.clone().unwrap_or_else(|| item.label.clone());
+        (misses_prefix, sort_text)
+    });
+}
+
+fn convert_entry(
+    entry: &Value,
+    file: &str,
+    position: &Position,
+    commit_characters: &CommitCharacterSettings,
+    snippet_support: bool,
+) -> Option<CompletionItem> {
     let name = entry.get("name")?.as_str()?.to_string();
     let mut label = name.clone();
     let kind_modifiers = entry.get("kindModifiers").and_then(|v| v.as_str());
@@ -132,9 +299,16 @@ fn convert_entry(entry: &Value, file: &str, position: &Position) -> Option<Compl
         label.push('?');
     }
 
+    // tsserver's snippet entries fall back to the bare name for clients
+    // that would insert `$0` literally.
+    let is_snippet = entry
+        .get("isSnippet")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
     let insert_text = entry
         .get("insertText")
         .and_then(|v| v.as_str())
+        .filter(|_| snippet_support || !is_snippet)
         .unwrap_or(&name)
         .to_string();
 
@@ -149,17 +323,7 @@ fn convert_entry(entry: &Value, file: &str, position: &Position) -> Option<Compl
             .map(|s| s.to_string()),
         filter_text: Some(insert_text.clone()),
         insert_text: Some(insert_text.clone()),
-        insert_text_format: Some(
-            if entry
-                .get("isSnippet")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false)
-            {
-                InsertTextFormat::SNIPPET
-            } else {
-                InsertTextFormat::PLAIN_TEXT
-            },
-        ),
+        insert_text_format: (is_snippet && snippet_support).then_some(InsertTextFormat::SNIPPET),
         ..CompletionItem::default()
     };
 
@@ -172,11 +336,13 @@ fn convert_entry(entry: &Value, file: &str, position: &Position) -> Option<Compl
         }
     }
 
+    item.label_details = label_details(entry);
+
     if is_deprecated(kind_modifiers) {
         item.tags = Some(vec![CompletionItemTag::DEPRECATED]);
     }
 
-    if let Some(chars) = completion_commit_characters(kind) {
+    if let Some(chars) = completion_commit_characters(kind, commit_characters) {
         item.commit_characters = Some(chars);
     }
 
@@ -205,6 +371,76 @@ fn convert_entry(entry: &Value, file: &str, position: &Position) -> Option<Compl
     Some(item)
 }
 
+/// Turns a plain function/method entry into a `name($1)$0` snippet. Entries
+/// whose text tsserver already shaped (`insertText`/`isSnippet`) are left alone.
+fn apply_call_snippet(item: &mut CompletionItem, entry: &Value) {
+    if !matches!(
+        item.kind,
+        Some(CompletionItemKind::FUNCTION | CompletionItemKind::METHOD)
+    ) || entry.get("insertText").is_some()
+        || entry.get("isSnippet").is_some()
+    {
+        return;
+    }
+    let Some(name) = item.insert_text.as_deref() else {
+        return;
+    };
+    let snippet = format!("{}($1)$0", escape_snippet(name));
+    if let Some(CompletionTextEdit::Edit(edit)) = item.text_edit.as_mut() {
+        edit.new_text = snippet.clone();
+    }
+    item.insert_text = Some(snippet);
+    item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+}
+
+/// Auto-import candidates share a label, so the module they come from is shown
+/// as the label description. tsserver's own `labelDetails` (TS 4.7+) wins;
+/// otherwise the specifier comes from `data.moduleSpecifier`, `sourceDisplay`,
+/// or finally the raw `source`.
+fn label_details(entry: &Value) -> Option<CompletionItemLabelDetails> {
+    if let Some(details) = entry.get("labelDetails") {
+        let detail = details
+            .get("detail")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let description = details
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        if detail.is_some() || description.is_some() {
+            return Some(CompletionItemLabelDetails {
+                detail,
+                description,
+            });
+        }
+    }
+
+    entry.get("source")?;
+    let description = entry
+        .get("data")
+        .and_then(|data| data.get("moduleSpecifier"))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .or_else(|| {
+            let parts = entry.get("sourceDisplay")?.as_array()?;
+            let text: String = parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(|v| v.as_str()))
+                .collect();
+            (!text.is_empty()).then_some(text)
+        })
+        .or_else(|| {
+            entry
+                .get("source")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        })?;
+    Some(CompletionItemLabelDetails {
+        detail: None,
+        description: Some(description),
+    })
+}
+
 fn is_deprecated(modifiers: Option<&str>) -> bool {
     modifiers
         .map(|mods| mods.contains("deprecated"))
@@ -228,3 +464,195 @@ fn build_entry_name(entry: &Value, name: &str) -> Value {
     }
     Value::Object(map)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapt(entries: Value, complete_function_calls: bool) -> Vec<CompletionItem> {
+        adapt_list(
+            entries,
+            json!({ "completeFunctionCalls": complete_function_calls }),
+        )
+        .items
+    }
+
+    fn adapt_list(entries: Value, extra_context: Value) -> CompletionList {
+        let mut context = json!({
+            "file": "/workspace/app.ts",
+            "position": { "line": 0, "character": 3 },
+        });
+        for (key, value) in extra_context.as_object().unwrap() {
+            context[key] = value.clone();
+        }
+        let payload = json!({ "body": { "isIncomplete": false, "entries": entries } });
+        let AdapterResult::Ready(value) = adapt_completion(&payload, Some(&context)).unwrap()
+        else {
+            panic!("expected ready completion list");
+        };
+        match serde_json::from_value(value).unwrap() {
+            CompletionResponse::List(list) => list,
+            CompletionResponse::Array(items) => CompletionList {
+                is_incomplete: false,
+                items,
+            },
+        }
+    }
+
+    #[test]
+    fn max_entries_truncates_ranked_list() {
+        let entries = json!([
+            { "name": "zeta", "kind": "const", "sortText": "15" },
+            { "name": "alpha", "kind": "const", "sortText": "11" },
+            { "name": "beta", "kind": "const", "sortText": "12" },
+            { "name": "zed", "kind": "const", "sortText": "13" }
+        ]);
+        let labels = |list: &CompletionList| {
+            list.items
+                .iter()
+                .map(|item| item.label.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let list = adapt_list(entries.clone(), json!({ "maxEntries": 2 }));
+        assert!(list.is_incomplete);
+        assert_eq!(labels(&list), ["alpha", "beta"]);
+
+        let list = adapt_list(
+            entries.clone(),
+            json!({ "maxEntries": 2, "entriesLimitStrategy": "prefix", "word": "Z" }),
+        );
+        assert_eq!(labels(&list), ["zed", "zeta"]);
+
+        let list = adapt_list(entries, json!({ "maxEntries": 10 }));
+        assert!(!list.is_incomplete);
+        assert_eq!(list.items.len(), 4);
+    }
+
+    #[test]
+    fn function_calls_become_snippets_when_enabled_and_supported() {
+        let entries = json!([
+            { "name": "parse", "kind": "function", "sortText": "11" },
+            { "name": "value", "kind": "const", "sortText": "11" }
+        ]);
+
+        let items = adapt_list(
+            entries.clone(),
+            json!({ "completeFunctionCalls": true, "snippetSupport": true }),
+        )
+        .items;
+        assert_eq!(items[0].insert_text.as_deref(), Some("parse($1)$0"));
+        assert_eq!(items[0].insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(items[0].filter_text.as_deref(), Some("parse"));
+        assert_eq!(items[1].insert_text.as_deref(), Some("value"));
+
+        let items = adapt(entries.clone(), true);
+        assert_eq!(items[0].insert_text.as_deref(), Some("parse"));
+        assert_eq!(items[0].insert_text_format, None);
+
+        let items = adapt_list(entries, json!({ "snippetSupport": true })).items;
+        assert_eq!(items[0].insert_text.as_deref(), Some("parse"));
+    }
+
+    #[test]
+    fn snippet_entries_are_plain_text_without_snippet_support() {
+        let entries = json!([{
+            "name": "render",
+            "kind": "method",
+            "sortText": "11",
+            "insertText": "render() {\n  $0\n}",
+            "isSnippet": true
+        }]);
+
+        let items = adapt(entries.clone(), false);
+        assert_eq!(items[0].insert_text.as_deref(), Some("render"));
+        assert_eq!(items[0].insert_text_format, None);
+
+        let items = adapt_list(entries, json!({ "snippetSupport": true })).items;
+        assert_eq!(items[0].insert_text.as_deref(), Some("render() {\n  $0\n}"));
+        assert_eq!(items[0].insert_text_format, Some(InsertTextFormat::SNIPPET));
+    }
+
+    #[test]
+    fn auto_import_entries_describe_their_module() {
+        let items = adapt(
+            json!([{
+                "name": "useState",
+                "kind": "function",
+                "sortText": "16",
+                "hasAction": true,
+                "source": "/workspace/node_modules/@types/react/index",
+                "sourceDisplay": [{ "text": "react", "kind": "text" }],
+                "data": {
+                    "exportName": "useState",
+                    "moduleSpecifier": "react",
+                    "fileName": "/workspace/node_modules/@types/react/index.d.ts"
+                }
+            }, {
+                "name": "helper",
+                "kind": "function",
+                "sortText": "16",
+                "hasAction": true,
+                "source": "/workspace/src/utils",
+                "sourceDisplay": [{ "text": "./utils", "kind": "text" }]
+            }, {
+                "name": "local",
+                "kind": "const",
+                "sortText": "11"
+            }]),
+            false,
+        );
+        let descriptions: Vec<_> = items
+            .iter()
+            .map(|item| {
+                item.label_details
+                    .as_ref()
+                    .and_then(|details| details.description.as_deref())
+            })
+            .collect();
+        assert_eq!(descriptions, vec![Some("react"), Some("./utils"), None]);
+    }
+
+    #[test]
+    fn word_start_stops_at_non_identifier() {
+        assert_eq!(word_start("  foo.barBaz", 12), 6);
+        assert_eq!(word_start("  foo.", 6), 6);
+        assert_eq!(word_start("$el", 3), 0);
+        assert_eq!(typed_word("  foo.barBaz", 9), "bar");
+    }
+
+    #[test]
+    fn reuse_cached_stretches_edits_to_cursor() {
+        let original = Position {
+            line: 0,
+            character: 4,
+        };
+        let list = CompletionList {
+            is_incomplete: false,
+            items: vec![CompletionItem {
+                label: "barBaz".into(),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range: lsp_types::Range {
+                        start: Position {
+                            line: 0,
+                            character: 2,
+                        },
+                        end: original,
+                    },
+                    new_text: "barBaz".into(),
+                })),
+                ..CompletionItem::default()
+            }],
+        };
+        let current = Position {
+            line: 0,
+            character: 6,
+        };
+        let reused = reuse_cached(&list, original, current);
+        assert!(reused.is_incomplete);
+        match &reused.items[0].text_edit {
+            Some(CompletionTextEdit::Edit(edit)) => assert_eq!(edit.range.end, current),
+            other => panic!("unexpected edit: {other:?}"),
+        }
+    }
+}
@@ -179,9 +179,12 @@ fn inject_snippet(item: &mut CompletionItem, details: &Value) {
         .and_then(|value| value.as_array())
     {
         let mut snippet = String::new();
+        // `filter_text` holds the bare name even when the list already
+        // inserted a `name($1)$0` call snippet.
         snippet.push_str(
-            item.insert_text
+            item.filter_text
                 .as_deref()
+                .or(item.insert_text.as_deref())
                 .or_else(|| {
                     item.text_edit.as_ref().map(|edit| match edit {
                         CompletionTextEdit::Edit(edit) => edit.new_text.as_str(),
@@ -227,9 +230,74 @@ fn inject_snippet(item: &mut CompletionItem, details: &Value) {
     }
 }
 
-fn escape_snippet(value: &str) -> String {
+pub(crate) fn escape_snippet(value: &str) -> String {
     value
         .replace('\\', "\\\\")
         .replace('$', "\\$")
         .replace('}', "\\}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(insert_text: &str) -> CompletionItem {
+        let item = CompletionItem {
+            label: "parse".to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            filter_text: Some("parse".to_string()),
+            insert_text: Some(insert_text.to_string()),
+            data: Some(json!({
+                "file": "/workspace/src/index.ts",
+                "position": { "line": 0, "character": 3 },
+                "entryNames": ["parse"],
+            })),
+            ..CompletionItem::default()
+        };
+        let spec = handle(item).expect("resolve spec");
+        let payload = json!({
+            "body": [{
+                "name": "parse",
+                "displayParts": [
+                    { "text": "function", "kind": "keyword" },
+                    { "text": " ", "kind": "space" },
+                    { "text": "parse", "kind": "functionName" },
+                    { "text": "(", "kind": "punctuation" },
+                    { "text": "input", "kind": "parameterName" },
+                    { "text": ":", "kind": "punctuation" },
+                    { "text": " ", "kind": "space" },
+                    { "text": "string", "kind": "keyword" },
+                    { "text": ",", "kind": "punctuation" },
+                    { "text": " ", "kind": "space" },
+                    { "text": "strict", "kind": "parameterName" },
+                    { "text": ")", "kind": "punctuation" }
+                ]
+            }]
+        });
+        match (spec.on_response.expect("adapter"))(&payload, spec.response_context.as_ref())
+            .expect("adapt resolve")
+        {
+            AdapterResult::Ready(value) => serde_json::from_value(value).expect("item"),
+            _ => panic!("expected ready result"),
+        }
+    }
+
+    #[test]
+    fn functions_get_one_placeholder_per_parameter() {
+        let item = resolve("parse");
+        assert_eq!(
+            item.insert_text.as_deref(),
+            Some("parse(${1:input}, ${2:strict})")
+        );
+        assert_eq!(item.insert_text_format, Some(InsertTextFormat::SNIPPET));
+    }
+
+    #[test]
+    fn list_call_snippets_are_replaced_with_placeholders() {
+        let item = resolve("parse($1)$0");
+        assert_eq!(
+            item.insert_text.as_deref(),
+            Some("parse(${1:input}, ${2:strict})")
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! =============================================================================
+//! textDocument/declaration
+//! =============================================================================
+//!
+//! Kept separate from `textDocument/definition`: we call tsserver’s plain
+//! `definition` command and prefer spans that live in declaration files
+//! (`.d.ts`), so users can reach ambient/typed declarations even when a
+//! definition jump would land in source. When the request itself comes from a
+//! declaration file we ask `findSourceDefinition` instead, which is the only
+//! useful direction from there.
+
+use anyhow::{Context, Result};
+use lsp_types::{GotoDefinitionResponse, request::GotoDeclarationParams};
+use serde_json::{Value, json};
+
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_span_to_location_link, uri_to_file_path};
+
+const CMD_DEFINITION: &str = "definition";
+const CMD_SOURCE_DEFINITION: &str = "findSourceDefinition";
+const DECLARATION_SUFFIXES: &[&str] = &[".d.ts", ".d.mts", ".d.cts"];
+
+pub fn handle(params: GotoDeclarationParams) -> RequestSpec {
+    let text_document = params.text_document_position_params.text_document;
+    let uri_string = text_document.uri.to_string();
+    let file_name = uri_to_file_path(text_document.uri.as_str()).unwrap_or(uri_string);
+    let position = params.text_document_position_params.position;
+    let command = if is_declaration_file(&file_name) {
+        CMD_SOURCE_DEFINITION
+    } else {
+        CMD_DEFINITION
+    };
+
+    let request = json!({
+        "command": command,
+        "arguments": {
+            "file": file_name,
+            "line": position.line + 1,
+            "offset": position.character + 1,
+        }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapt_declaration),
+        response_context: None,
+    }
+}
+
+/// Asks a declaration file's request for plain definitions instead, for
+/// TypeScript releases without `findSourceDefinition`.
+pub fn fall_back_from_source_definition(spec: &mut RequestSpec) {
+    if spec.payload.get("command").and_then(Value::as_str) == Some(CMD_SOURCE_DEFINITION) {
+        spec.payload["command"] = json!(CMD_DEFINITION);
+    }
+}
+
+fn adapt_declaration(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+    let command = payload
+        .get("command")
+        .and_then(|cmd| cmd.as_str())
+        .unwrap_or(CMD_DEFINITION);
+    let spans = payload
+        .get("body")
+        .context("tsserver declaration missing body")?
+        .as_array()
+        .context("tsserver declaration body must be array")?;
+
+    let in_declaration_file = |span: &&Value| {
+        span.get("file")
+            .and_then(|file| file.as_str())
+            .is_some_and(is_declaration_file)
+    };
+    let prefer_declarations =
+        command == CMD_DEFINITION && spans.iter().any(|span| in_declaration_file(&span));
+
+    let mut links = Vec::new();
+    for span in spans {
+        if prefer_declarations && !in_declaration_file(&span) {
+            continue;
+        }
+        if let Some(link) = tsserver_span_to_location_link(span, None) {
+            links.push(link);
+        }
+    }
+
+    let response = GotoDefinitionResponse::Link(links);
+    Ok(AdapterResult::ready(serde_json::to_value(response)?))
+}
+
+fn is_declaration_file(path: &str) -> bool {
+    DECLARATION_SUFFIXES
+        .iter()
+        .any(|suffix| path.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams, Uri};
+    use std::str::FromStr;
+
+    fn params(uri: &str) -> GotoDeclarationParams {
+        GotoDeclarationParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_str(uri).unwrap(),
+                },
+                position: Position {
+                    line: 0,
+                    character: 3,
+                },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    fn adapt_links(payload: Value) -> Vec<lsp_types::LocationLink> {
+        let adapted = adapt_declaration(&payload, None).expect("declaration adapts");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready declaration response")
+            }
+        };
+        match serde_json::from_value(value).expect("declaration deserializes") {
+            GotoDefinitionResponse::Link(links) => links,
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn handle_uses_source_definition_inside_declaration_files() {
+        let spec = handle(params("file:///workspace/src/app.ts"));
+        assert_eq!(spec.payload.get("command"), Some(&json!("definition")));
+
+        let spec = handle(params("file:///workspace/types/index.d.ts"));
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("findSourceDefinition"))
+        );
+    }
+
+    #[test]
+    fn declaration_files_ask_for_plain_definitions_before_typescript_4_7() {
+        let mut spec = handle(params("file:///workspace/types/index.d.ts"));
+        fall_back_from_source_definition(&mut spec);
+        assert_eq!(spec.payload.get("command"), Some(&json!("definition")));
+        assert_eq!(spec.payload.pointer("/arguments/offset"), Some(&json!(4)));
+    }
+
+    #[test]
+    fn adapt_declaration_prefers_declaration_file_spans() {
+        let links = adapt_links(json!({
+            "command": "definition",
+            "body": [{
+                "file": "/workspace/src/impl.ts",
+                "start": { "line": 3, "offset": 1 },
+                "end": { "line": 3, "offset": 5 }
+            }, {
+                "file": "/workspace/types/impl.d.ts",
+                "start": { "line": 1, "offset": 18 },
+                "end": { "line": 1, "offset": 22 }
+            }]
+        }));
+        assert_eq!(links.len(), 1);
+        assert!(links[0].target_uri.as_str().ends_with("impl.d.ts"));
+
+        let links = adapt_links(json!({
+            "command": "definition",
+            "body": [{
+                "file": "/workspace/src/impl.ts",
+                "start": { "line": 3, "offset": 1 },
+                "end": { "line": 3, "offset": 5 }
+            }]
+        }));
+        assert_eq!(links.len(), 1, "falls back to every span");
+    }
+}
@@ -69,6 +69,17 @@ pub fn handle(params: DefinitionParams) -> RequestSpec {
     }
 }
 
+/// First TypeScript release with `findSourceDefinition`.
+pub const MIN_SOURCE_DEFINITION_TYPESCRIPT_VERSION: (u32, u32) = (4, 7);
+
+/// Sends a `findSourceDefinition` request as a plain definition one instead,
+/// for TypeScript releases that predate the command.
+pub fn fall_back_from_source_definition(spec: &mut RequestSpec) {
+    if spec.payload.get("command").and_then(Value::as_str) == Some(CMD_SOURCE_DEFINITION) {
+        spec.payload["command"] = json!(CMD_DEFINITION);
+    }
+}
+
 fn adapt_definition(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
     let command = payload
         .get("command")
@@ -175,6 +186,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn source_definition_falls_back_to_plain_definition() {
+        let mut spec = handle(params_with_context(true));
+        fall_back_from_source_definition(&mut spec);
+        assert_eq!(spec.payload.get("command"), Some(&json!(CMD_DEFINITION)));
+        assert_eq!(spec.payload.pointer("/arguments/line"), Some(&json!(3)));
+    }
+
     #[test]
     fn source_definition_flag_deserializes_from_camel_case_context() {
         let raw = json!({
@@ -213,7 +232,9 @@ mod tests {
         let adapted = adapt_definition(&payload, None).expect("definition should adapt");
         let value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready definition response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready definition response")
+            }
         };
         match serde_json::from_value::<GotoDefinitionResponse>(value)
             .expect("response should deserialize")
@@ -252,7 +273,9 @@ mod tests {
         let adapted = adapt_definition(&payload, None).expect("source definition adapts");
         let value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready source definition response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready source definition response")
+            }
         };
         match serde_json::from_value::<GotoDefinitionResponse>(value)
             .expect("response should deserialize")
@@ -0,0 +1,349 @@
+//! =============================================================================
+//! textDocument/diagnostic (pull diagnostics)
+//! =============================================================================
+//!
+//! Pull diagnostics chain tsserver’s synchronous `syntacticDiagnosticsSync`,
+//! `semanticDiagnosticsSync`, and `suggestionDiagnosticsSync` commands and
+//! return a single full report; the suggestion step is skipped when
+//! `diagnostics.include_suggestions` is off.  The session derives the result id
+//! from the document version (plus a generation bumped on any project-wide
+//! change), so a client asking again with the same `previousResultId` gets an
+//! unchanged report without touching tsserver.
+
+use anyhow::{Context, Result};
+use lsp_types::{
+    ClientCapabilities, Diagnostic, DiagnosticOptions, DiagnosticServerCapabilities,
+    DocumentDiagnosticParams, DocumentDiagnosticReport, FullDocumentDiagnosticReport,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::DiagnosticsSettings;
+use crate::protocol::diagnostics::convert_diagnostic;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+pub const IDENTIFIER: &str = "ts-bridge";
+
+const CMD_SYNTACTIC: &str = "syntacticDiagnosticsSync";
+const CMD_SEMANTIC: &str = "semanticDiagnosticsSync";
+const CMD_SUGGESTION: &str = "suggestionDiagnosticsSync";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PullContext {
+    file: String,
+    result_id: String,
+    #[serde(default)]
+    diagnostics: Vec<Diagnostic>,
+    #[serde(default)]
+    settings: DiagnosticsSettings,
+}
+
+/// Whether the client pulls diagnostics itself; such sessions advertise
+/// [`provider`] and get no `publishDiagnostics`.
+pub fn supports_pull(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .text_document
+        .as_ref()
+        .is_some_and(|text_document| text_document.diagnostic.is_some())
+}
+
+/// Whether the client re-pulls on `workspace/diagnostic/refresh`. Reads the
+/// raw capabilities: lsp-types expects `workspace.diagnostic` where the spec
+/// (and every client) says `workspace.diagnostics`.
+pub fn supports_refresh(capabilities: &Value) -> bool {
+    [
+        "/workspace/diagnostics/refreshSupport",
+        "/workspace/diagnostic/refreshSupport",
+    ]
+    .iter()
+    .any(|pointer| capabilities.pointer(pointer) == Some(&Value::Bool(true)))
+}
+
+pub fn provider() -> DiagnosticServerCapabilities {
+    DiagnosticServerCapabilities::Options(DiagnosticOptions {
+        identifier: Some(IDENTIFIER.to_string()),
+        inter_file_dependencies: true,
+        workspace_diagnostics: true,
+        work_done_progress_options: Default::default(),
+    })
+}
+
+/// Builds the result id reported for a document snapshot.
+pub fn result_id(generation: u64, version: Option<i32>) -> String {
+    match version {
+        Some(version) => format!("{generation}:{version}"),
+        None => format!("{generation}:-"),
+    }
+}
+
+/// Answers a pull without consulting tsserver when the client already holds
+/// the report for `result_id`.
+pub fn unchanged_report(result_id: String) -> Value {
+    let report = DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+        related_documents: None,
+        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport { result_id },
+    });
+    json!(report)
+}
+
+pub fn handle(
+    params: DocumentDiagnosticParams,
+    result_id: String,
+    settings: DiagnosticsSettings,
+) -> RequestSpec {
+    let uri = params.text_document.uri;
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+    let context = PullContext {
+        file,
+        result_id,
+        diagnostics: Vec::new(),
+        settings,
+    };
+    step(CMD_SYNTACTIC, context, adapt_syntactic)
+}
+
+fn step(
+    command: &str,
+    context: PullContext,
+    adapter: crate::protocol::ResponseAdapter,
+) -> RequestSpec {
+    let request = json!({
+        "command": command,
+        "arguments": {
+            "file": context.file,
+            "includeLinePosition": false,
+        }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapter),
+        response_context: Some(json!(context)),
+    }
+}
+
+fn adapt_syntactic(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let ctx = collect(payload, context)?;
+    Ok(AdapterResult::Continue(step(
+        CMD_SEMANTIC,
+        ctx,
+        adapt_semantic,
+    )))
+}
+
+fn adapt_semantic(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let ctx = collect(payload, context)?;
+    if !ctx.settings.include_suggestions {
+        return full_report(ctx);
+    }
+    Ok(AdapterResult::Continue(step(
+        CMD_SUGGESTION,
+        ctx,
+        adapt_suggestion,
+    )))
+}
+
+fn adapt_suggestion(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    full_report(collect(payload, context)?)
+}
+
+fn full_report(ctx: PullContext) -> Result<AdapterResult> {
+    let report = DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+        related_documents: None,
+        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+            result_id: Some(ctx.result_id),
+            items: ctx.diagnostics,
+        },
+    });
+    Ok(AdapterResult::ready(serde_json::to_value(report)?))
+}
+
+fn collect(payload: &Value, context: Option<&Value>) -> Result<PullContext> {
+    let mut ctx: PullContext = serde_json::from_value(
+        context
+            .cloned()
+            .context("missing pull diagnostics context")?,
+    )?;
+    let entries = payload
+        .get("body")
+        .and_then(|body| body.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let converted = entries
+        .into_iter()
+        .filter_map(|entry| convert_diagnostic(entry, &ctx.settings))
+        .collect::<Vec<_>>();
+    ctx.diagnostics.extend(converted);
+    Ok(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{TextDocumentIdentifier, Uri};
+    use std::str::FromStr;
+
+    fn params() -> DocumentDiagnosticParams {
+        DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str("file:///workspace/src/main.ts").unwrap(),
+            },
+            identifier: None,
+            previous_result_id: None,
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    fn diagnostic_body(text: &str, category: &str) -> Value {
+        json!({
+            "body": [{
+                "start": { "line": 1, "offset": 1 },
+                "end": { "line": 1, "offset": 4 },
+                "text": text,
+                "category": category,
+                "code": 2304
+            }]
+        })
+    }
+
+    fn next(result: AdapterResult) -> RequestSpec {
+        match result {
+            AdapterResult::Continue(spec) => spec,
+            _ => panic!("expected chained request"),
+        }
+    }
+
+    #[test]
+    fn pull_chains_all_three_sync_commands() {
+        let spec = handle(
+            params(),
+            result_id(3, Some(7)),
+            DiagnosticsSettings::default(),
+        );
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("syntacticDiagnosticsSync"))
+        );
+
+        let adapter = spec.on_response.expect("adapter");
+        let spec = next(
+            adapter(
+                &diagnostic_body("syntax", "error"),
+                spec.response_context.as_ref(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("semanticDiagnosticsSync"))
+        );
+
+        let adapter = spec.on_response.expect("adapter");
+        let spec = next(
+            adapter(
+                &diagnostic_body("semantic", "error"),
+                spec.response_context.as_ref(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("suggestionDiagnosticsSync"))
+        );
+
+        let adapter = spec.on_response.expect("adapter");
+        let value = match adapter(
+            &diagnostic_body("suggestion", "suggestion"),
+            spec.response_context.as_ref(),
+        )
+        .unwrap()
+        {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected final report")
+            }
+        };
+        let report: DocumentDiagnosticReport =
+            serde_json::from_value(value).expect("report deserializes");
+        match report {
+            DocumentDiagnosticReport::Full(full) => {
+                let report = full.full_document_diagnostic_report;
+                assert_eq!(report.result_id.as_deref(), Some("3:7"));
+                let messages = report
+                    .items
+                    .iter()
+                    .map(|diag| diag.message.as_str())
+                    .collect::<Vec<_>>();
+                assert_eq!(messages, vec!["syntax", "semantic", "suggestion"]);
+            }
+            other => panic!("unexpected report: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pull_stops_after_semantic_without_suggestions() {
+        let settings = DiagnosticsSettings {
+            include_suggestions: false,
+            ..DiagnosticsSettings::default()
+        };
+        let spec = handle(params(), result_id(1, Some(1)), settings);
+        let adapter = spec.on_response.expect("adapter");
+        let spec = next(
+            adapter(
+                &diagnostic_body("syntax", "error"),
+                spec.response_context.as_ref(),
+            )
+            .unwrap(),
+        );
+        let adapter = spec.on_response.expect("adapter");
+        let result = adapter(
+            &diagnostic_body("semantic", "error"),
+            spec.response_context.as_ref(),
+        )
+        .unwrap();
+        let AdapterResult::Ready(value) = result else {
+            panic!("expected final report");
+        };
+        let report: DocumentDiagnosticReport = serde_json::from_value(value).unwrap();
+        let DocumentDiagnosticReport::Full(full) = report else {
+            panic!("expected full report");
+        };
+        assert_eq!(full.full_document_diagnostic_report.items.len(), 2);
+    }
+
+    #[test]
+    fn unchanged_report_echoes_result_id() {
+        let report: DocumentDiagnosticReport =
+            serde_json::from_value(unchanged_report("1:2".into())).unwrap();
+        match report {
+            DocumentDiagnosticReport::Unchanged(unchanged) => assert_eq!(
+                unchanged.unchanged_document_diagnostic_report.result_id,
+                "1:2"
+            ),
+            other => panic!("unexpected report: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pull_support_follows_client_capabilities() {
+        let push_only = json!({ "textDocument": { "publishDiagnostics": {} } });
+        let capabilities: ClientCapabilities = serde_json::from_value(push_only.clone()).unwrap();
+        assert!(!supports_pull(&capabilities));
+        assert!(!supports_refresh(&push_only));
+
+        let pull = json!({
+            "textDocument": { "diagnostic": { "dynamicRegistration": false } },
+            "workspace": { "diagnostics": { "refreshSupport": true } }
+        });
+        let capabilities: ClientCapabilities = serde_json::from_value(pull.clone()).unwrap();
+        assert!(supports_pull(&capabilities));
+        assert!(supports_refresh(&pull));
+    }
+}
@@ -0,0 +1,231 @@
+//! =============================================================================
+//! JSDoc templates (tsserver `docCommentTemplate`)
+//! =============================================================================
+//!
+//! Typing `/**` on an otherwise empty line answers the completion request with
+//! a single snippet item instead of the usual `completionInfo` list.  tsserver
+//! builds the block (including one `@param` line per parameter of the function
+//! below); each parameter gets a tab stop and the caret position reported by
+//! tsserver becomes the first one.
+
+use anyhow::{Context, Result};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionList, CompletionResponse, CompletionTextEdit,
+    InsertTextFormat, Position, Range, TextEdit, Uri,
+};
+use serde_json::{Value, json};
+
+use crate::protocol::text_document::completion_resolve::escape_snippet;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+/// Advertised in addition to the regular completion triggers so the template
+/// is offered as soon as the second `*` is typed.
+pub const TRIGGER_CHARACTER: &str = "*";
+
+const LABEL: &str = "/** */";
+
+/// Range the template replaces when the cursor sits right after `/**` (plus
+/// an auto-paired `*/`, if any); `None` when no template should be offered.
+pub fn replace_range(line: &str, position: Position) -> Option<Range> {
+    let split = byte_index(line, position.character)?;
+    let (before, after) = line.split_at(split);
+    let opener = before.trim_start();
+    if !opener.starts_with("/**") || opener.trim_end_matches('*') != "/" {
+        return None;
+    }
+    let trailing = after.trim_start();
+    let closer_end = if trailing.is_empty() {
+        position.character
+    } else if trailing.trim_end() == "*/" {
+        let consumed = after.len() - trailing.len() + 2;
+        position.character + after[..consumed].encode_utf16().count() as u32
+    } else {
+        return None;
+    };
+    Some(Range {
+        start: Position {
+            line: position.line,
+            character: position.character - opener.encode_utf16().count() as u32,
+        },
+        end: Position {
+            line: position.line,
+            character: closer_end,
+        },
+    })
+}
+
+pub fn handle(uri: &Uri, position: Position, range: Range) -> RequestSpec {
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+    RequestSpec {
+        route: Route::Syntax,
+        payload: json!({
+            "command": "docCommentTemplate",
+            "arguments": {
+                "file": file,
+                "line": position.line + 1,
+                "offset": position.character + 1,
+            }
+        }),
+        priority: Priority::Normal,
+        on_response: Some(adapt_template),
+        response_context: Some(json!({ "range": range })),
+    }
+}
+
+fn adapt_template(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let range: Range = serde_json::from_value(
+        context
+            .and_then(|ctx| ctx.get("range"))
+            .cloned()
+            .context("missing docCommentTemplate range")?,
+    )?;
+    let body = payload.get("body");
+    let items = body
+        .and_then(|body| body.get("newText"))
+        .and_then(|v| v.as_str())
+        .filter(|text| !text.is_empty())
+        .map(|text| {
+            let caret = body
+                .and_then(|body| body.get("caretOffset"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as usize;
+            let snippet = template_to_snippet(text, caret);
+            vec![CompletionItem {
+                label: LABEL.to_string(),
+                kind: Some(CompletionItemKind::TEXT),
+                detail: Some("JSDoc comment".to_string()),
+                filter_text: Some("/**".to_string()),
+                sort_text: Some("\0".to_string()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: snippet,
+                })),
+                ..CompletionItem::default()
+            }]
+        })
+        .unwrap_or_default();
+
+    let list = CompletionList {
+        is_incomplete: false,
+        items,
+    };
+    Ok(AdapterResult::ready(serde_json::to_value(
+        CompletionResponse::List(list),
+    )?))
+}
+
+/// Escapes the template, puts the first tab stop at `caret` (a UTF-16 offset)
+/// and one more after every `@param` name.  Continuation lines lose their
+/// indentation because clients re-indent snippets to the insertion column.
+fn template_to_snippet(template: &str, caret: usize) -> String {
+    let split = template
+        .char_indices()
+        .scan(0usize, |units, (index, ch)| {
+            let at = *units;
+            *units += ch.len_utf16();
+            Some((at, index))
+        })
+        .find(|(units, _)| *units >= caret)
+        .map(|(_, index)| index)
+        .unwrap_or(template.len());
+    let marked = format!(
+        "{}\u{0}{}",
+        escape_snippet(&template[..split]),
+        escape_snippet(&template[split..])
+    );
+
+    let mut next_stop = 2;
+    let lines: Vec<String> = marked
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let mut line = if index == 0 {
+                line.to_string()
+            } else {
+                format!(" {}", line.trim_start())
+            };
+            if line.trim_start_matches([' ', '*']).starts_with("@param") {
+                line.push_str(&format!(" ${{{next_stop}}}"));
+                next_stop += 1;
+            }
+            line
+        })
+        .collect();
+    lines.join("\n").replace('\u{0}', "${1}")
+}
+
+fn byte_index(line: &str, character: u32) -> Option<usize> {
+    let mut units = 0u32;
+    for (index, ch) in line.char_indices() {
+        if units >= character {
+            return Some(index);
+        }
+        units += ch.len_utf16() as u32;
+    }
+    (units >= character).then_some(line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn position(character: u32) -> Position {
+        Position { line: 4, character }
+    }
+
+    #[test]
+    fn replace_range_requires_lone_opener() {
+        let range = replace_range("  /**", position(5)).expect("range");
+        assert_eq!(range.start, position(2));
+        assert_eq!(range.end, position(5));
+
+        let range = replace_range("  /** */", position(5)).expect("range");
+        assert_eq!(range.end, position(8), "auto-paired closer is replaced");
+
+        assert!(replace_range("const a = 1; /**", position(16)).is_none());
+        assert!(replace_range("  /* ", position(5)).is_none());
+        assert!(replace_range("  /** text", position(5)).is_none());
+    }
+
+    #[test]
+    fn template_becomes_snippet_item() {
+        let uri = Uri::from_str("file:///w/a.ts").unwrap();
+        let range = replace_range("  /**", position(5)).unwrap();
+        let spec = handle(&uri, position(5), range);
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("docCommentTemplate"))
+        );
+
+        let payload = json!({
+            "body": {
+                "newText": "/**\n   * \n   * @param a\n   * @param b\n   */",
+                "caretOffset": 9
+            }
+        });
+        let AdapterResult::Ready(value) =
+            (spec.on_response.unwrap())(&payload, spec.response_context.as_ref()).unwrap()
+        else {
+            panic!("expected ready list");
+        };
+        let CompletionResponse::List(list) = serde_json::from_value(value).unwrap() else {
+            panic!("expected list");
+        };
+        let item = &list.items[0];
+        assert_eq!(item.label, LABEL);
+        match &item.text_edit {
+            Some(CompletionTextEdit::Edit(edit)) => {
+                assert_eq!(edit.range, range);
+                assert_eq!(
+                    edit.new_text,
+                    "/**\n * ${1}\n * @param a ${2}\n * @param b ${3}\n */"
+                );
+            }
+            other => panic!("unexpected edit: {other:?}"),
+        }
+    }
+}
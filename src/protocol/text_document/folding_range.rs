@@ -0,0 +1,160 @@
+//! =============================================================================
+//! textDocument/foldingRange
+//! =============================================================================
+//!
+//! Issues tsserver’s `getOutliningSpans` command and converts each outlining
+//! span into an LSP `FoldingRange`. Tsserver reports spans that end right after
+//! the closing token (`}`), so code spans stop one line early to keep the
+//! closing brace visible, matching what VS Code shows.
+
+use anyhow::{Context, Result};
+use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams};
+use serde_json::{Value, json};
+
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_range_from_value_lsp, uri_to_file_path};
+
+const CMD_OUTLINING_SPANS: &str = "getOutliningSpans";
+
+pub fn handle(params: FoldingRangeParams) -> RequestSpec {
+    let uri = params.text_document.uri;
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+
+    let request = json!({
+        "command": CMD_OUTLINING_SPANS,
+        "arguments": { "file": file }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Low,
+        on_response: Some(adapt_folding_ranges),
+        response_context: None,
+    }
+}
+
+fn adapt_folding_ranges(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+    let spans = payload
+        .get("body")
+        .context("tsserver getOutliningSpans missing body")?
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut ranges = Vec::with_capacity(spans.len());
+    for span in &spans {
+        if let Some(range) = convert_span(span) {
+            ranges.push(range);
+        }
+    }
+
+    Ok(AdapterResult::ready(serde_json::to_value(ranges)?))
+}
+
+fn convert_span(span: &Value) -> Option<FoldingRange> {
+    let range = tsserver_range_from_value_lsp(span.get("textSpan")?)?;
+    let ts_kind = span.get("kind").and_then(|value| value.as_str());
+    let kind = match ts_kind {
+        Some("comment") => Some(FoldingRangeKind::Comment),
+        Some("region") => Some(FoldingRangeKind::Region),
+        Some("imports") => Some(FoldingRangeKind::Imports),
+        _ => None,
+    };
+
+    let start_line = range.start.line;
+    let mut end_line = range.end.line;
+    if matches!(ts_kind, Some("code")) && range.end.character > 0 {
+        end_line = end_line.saturating_sub(1).max(start_line);
+    }
+    if end_line <= start_line {
+        return None;
+    }
+
+    Some(FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind,
+        collapsed_text: span
+            .get("bannerText")
+            .and_then(|value| value.as_str())
+            .filter(|text| !text.is_empty())
+            .map(|text| text.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapt(payload: Value) -> Vec<FoldingRange> {
+        let adapted = adapt_folding_ranges(&payload, None).expect("folding ranges adapt");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready folding ranges")
+            }
+        };
+        serde_json::from_value(value).expect("folding ranges deserialize")
+    }
+
+    #[test]
+    fn adapt_folding_ranges_maps_kinds() {
+        let ranges = adapt(json!({
+            "body": [{
+                "textSpan": {
+                    "start": { "line": 1, "offset": 1 },
+                    "end": { "line": 3, "offset": 30 }
+                },
+                "kind": "imports"
+            }, {
+                "textSpan": {
+                    "start": { "line": 5, "offset": 1 },
+                    "end": { "line": 8, "offset": 3 }
+                },
+                "kind": "comment"
+            }, {
+                "textSpan": {
+                    "start": { "line": 10, "offset": 1 },
+                    "end": { "line": 20, "offset": 13 }
+                },
+                "kind": "region",
+                "bannerText": "helpers"
+            }]
+        }));
+
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].kind, Some(FoldingRangeKind::Imports));
+        assert_eq!((ranges[0].start_line, ranges[0].end_line), (0, 2));
+        assert_eq!(ranges[1].kind, Some(FoldingRangeKind::Comment));
+        assert_eq!(ranges[2].kind, Some(FoldingRangeKind::Region));
+        assert_eq!(ranges[2].collapsed_text.as_deref(), Some("helpers"));
+    }
+
+    #[test]
+    fn adapt_folding_ranges_keeps_closing_brace_visible_for_code() {
+        let ranges = adapt(json!({
+            "body": [{
+                "textSpan": {
+                    "start": { "line": 2, "offset": 20 },
+                    "end": { "line": 6, "offset": 2 }
+                },
+                "kind": "code"
+            }, {
+                "textSpan": {
+                    "start": { "line": 9, "offset": 10 },
+                    "end": { "line": 10, "offset": 2 }
+                },
+                "kind": "code"
+            }]
+        }));
+
+        assert_eq!(ranges.len(), 1, "single-line folds are dropped");
+        assert_eq!(ranges[0].start_line, 1);
+        assert_eq!(ranges[0].end_line, 4);
+        assert_eq!(ranges[0].kind, None);
+    }
+}
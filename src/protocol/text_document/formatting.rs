@@ -7,7 +7,7 @@
 //! edits, translating them into standard LSP `TextEdit`s.
 
 use anyhow::{Context, Result};
-use lsp_types::{DocumentFormattingParams, TextEdit};
+use lsp_types::{DocumentFormattingParams, Position, Range, TextEdit};
 use serde_json::{Value, json};
 
 use crate::protocol::{AdapterResult, RequestSpec};
@@ -42,7 +42,10 @@ pub fn handle(params: DocumentFormattingParams) -> RequestSpec {
     }
 }
 
-fn adapt_formatting(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+/// Response context key for edits ts-bridge adds to tsserver's own.
+pub(crate) const EXTRA_EDITS_KEY: &str = "extraEdits";
+
+pub(crate) fn adapt_formatting(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
     let edits = payload
         .get("body")
         .context("tsserver format missing body")?
@@ -60,11 +63,14 @@ fn adapt_formatting(payload: &Value, _context: Option<&Value>) -> Result<Adapter
             .to_string();
         lsp_edits.push(TextEdit { range, new_text });
     }
+    if let Some(extra) = context.and_then(|context| context.get(EXTRA_EDITS_KEY)) {
+        lsp_edits.extend(serde_json::from_value::<Vec<TextEdit>>(extra.clone())?);
+    }
 
     Ok(AdapterResult::ready(serde_json::to_value(lsp_edits)?))
 }
 
-fn format_code_settings(options: &lsp_types::FormattingOptions) -> Value {
+pub(crate) fn format_code_settings(options: &lsp_types::FormattingOptions) -> Value {
     let mut payload = serde_json::Map::new();
     payload.insert(
         "convertTabsToSpaces".into(),
@@ -94,3 +100,241 @@ fn format_code_settings(options: &lsp_types::FormattingOptions) -> Value {
 
     Value::Object(payload)
 }
+
+/// Edits deleting every comma that directly precedes a closing `)`, `]` or
+/// `}`, for `format_options.trailing_commas = "remove"`. Strings, template
+/// literals, comments and regular expressions are skipped; array holes such
+/// as `[a, , ]` keep their commas.
+pub(crate) fn trailing_comma_removals(text: &str) -> Vec<TextEdit> {
+    let mut edits = Vec::new();
+    let mut chars = text.chars().peekable();
+    let (mut line, mut column) = (0u32, 0u32);
+    // Last significant character and word, for telling regexes from division.
+    let mut previous: Option<char> = None;
+    let mut word = String::new();
+    let mut in_word = false;
+    // A comma seen last, with the significant character before it.
+    let mut comma: Option<(Position, Option<char>)> = None;
+    // Brace depths at which a template literal's `${` was opened.
+    let mut templates: Vec<usize> = Vec::new();
+    let mut braces = 0usize;
+    let mut in_template = false;
+
+    macro_rules! advance {
+        () => {{
+            let ch = chars.next();
+            match ch {
+                Some('\n') => {
+                    line += 1;
+                    column = 0;
+                }
+                Some(ch) => column += ch.len_utf16() as u32,
+                None => {}
+            }
+            ch
+        }};
+    }
+
+    while let Some(&ch) = chars.peek() {
+        if in_template {
+            advance!();
+            match ch {
+                '\\' => {
+                    advance!();
+                }
+                '`' => {
+                    in_template = false;
+                    previous = Some('`');
+                }
+                '$' if chars.peek() == Some(&'{') => {
+                    advance!();
+                    braces += 1;
+                    templates.push(braces);
+                    in_template = false;
+                    previous = Some('{');
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let start = Position::new(line, column);
+        advance!();
+        if ch.is_whitespace() {
+            in_word = false;
+            continue;
+        }
+        match ch {
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&next| next != '\n') {
+                    advance!();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                advance!();
+                let mut star = false;
+                while let Some(next) = advance!() {
+                    if star && next == '/' {
+                        break;
+                    }
+                    star = next == '*';
+                }
+                continue;
+            }
+            '/' if starts_regex(previous, &word) => {
+                let mut class = false;
+                while let Some(next) = advance!() {
+                    match next {
+                        '\\' => {
+                            advance!();
+                        }
+                        '[' => class = true,
+                        ']' => class = false,
+                        '/' if !class => break,
+                        '\n' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\'' | '"' => {
+                while let Some(next) = advance!() {
+                    match next {
+                        '\\' => {
+                            advance!();
+                        }
+                        '\n' => break,
+                        next if next == ch => break,
+                        _ => {}
+                    }
+                }
+            }
+            '`' => in_template = true,
+            ',' => {
+                comma = Some((start, previous));
+                previous = Some(',');
+                word.clear();
+                in_word = false;
+                continue;
+            }
+            ')' | ']' | '}' => {
+                if let Some((position, before)) = comma
+                    && !matches!(before, None | Some(',' | '[' | '(' | '{'))
+                {
+                    edits.push(TextEdit {
+                        range: Range::new(
+                            position,
+                            Position::new(position.line, position.character + 1),
+                        ),
+                        new_text: String::new(),
+                    });
+                }
+                if ch == '}' {
+                    if templates.last() == Some(&braces) {
+                        templates.pop();
+                        in_template = true;
+                    }
+                    braces = braces.saturating_sub(1);
+                }
+            }
+            '{' => braces += 1,
+            _ => {}
+        }
+        comma = None;
+        if is_word_char(ch) {
+            if !in_word {
+                word.clear();
+            }
+            word.push(ch);
+            in_word = true;
+        } else {
+            word.clear();
+            in_word = false;
+        }
+        previous = Some(ch);
+    }
+    edits
+}
+
+/// Whether a `/` after `previous` (and `word`, when it ends in one) opens a
+/// regular expression rather than dividing.
+fn starts_regex(previous: Option<char>, word: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "return",
+        "typeof",
+        "instanceof",
+        "in",
+        "of",
+        "new",
+        "delete",
+        "void",
+        "throw",
+        "case",
+        "do",
+        "else",
+        "yield",
+        "await",
+    ];
+    match previous {
+        None => true,
+        Some(ch) if is_word_char(ch) => KEYWORDS.contains(&word),
+        Some(ch) => "(,=:[!&|?{};+-*%<>~^".contains(ch),
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(text: &str) -> String {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        for edit in trailing_comma_removals(text).iter().rev() {
+            let start = edit.range.start;
+            assert_eq!(start.line, edit.range.end.line);
+            lines[start.line as usize].remove(start.character as usize);
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn trailing_commas_before_closers_are_removed() {
+        assert_eq!(
+            removed("call(a, b,)\nconst xs = [\n  1,\n  2, // two\n];\nconst o = { a: 1, };"),
+            "call(a, b)\nconst xs = [\n  1,\n  2 // two\n];\nconst o = { a: 1 };"
+        );
+    }
+
+    #[test]
+    fn commas_in_strings_comments_regexes_and_holes_are_kept() {
+        let text = concat!(
+            "const s = \"a,)\" + 'b,]' + `c,} ${[x,]}`;\n",
+            "/* d,) */ const r = /[,]/;\n",
+            "const holes = [1, , ];\n",
+            "function f() { return /,]/.test(s) }",
+        );
+        assert_eq!(removed(text), text.replace("${[x,]}", "${[x]}"));
+    }
+
+    #[test]
+    fn adapter_appends_extra_edits() {
+        let payload = json!({
+            "body": [{
+                "start": { "line": 1, "offset": 1 },
+                "end": { "line": 1, "offset": 3 },
+                "newText": "  "
+            }]
+        });
+        let context = json!({ EXTRA_EDITS_KEY: trailing_comma_removals("f(a,)") });
+        let AdapterResult::Ready(value) = adapt_formatting(&payload, Some(&context)).unwrap()
+        else {
+            panic!("expected ready formatting response");
+        };
+        let edits: Vec<TextEdit> = serde_json::from_value(value).unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[1].range.start, Position::new(0, 3));
+        assert_eq!(edits[1].new_text, "");
+    }
+}
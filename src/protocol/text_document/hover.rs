@@ -7,21 +7,54 @@
 //! `Hover`. The handler keeps the formatting decisions (code fence for the
 //! `displayString`, plain-text docs, and `_@tag_` renders) so the Neovim UX
 //! matches the original plugin.
+//!
+//! `hover.verbosity` is sent as quickinfo's `verbosityLevel` (TypeScript 5.9+),
+//! which expands aliased and truncated types.  The custom
+//! `ts-bridge/expandHover` request takes an explicit `verbosityLevel` (default:
+//! one above the setting) and echoes it back next to tsserver's
+//! `canIncreaseVerbosityLevel`, so clients can keep expanding step by step.
 
 use anyhow::{Context, Result};
-use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, TextDocumentPositionParams};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
 use crate::utils::{tsserver_range_from_value_lsp, uri_to_file_path};
 
-pub fn handle(params: lsp_types::HoverParams) -> RequestSpec {
-    let text_document = params.text_document_position_params.text_document;
+pub const EXPAND_METHOD: &str = "ts-bridge/expandHover";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandHoverParams {
+    #[serde(flatten)]
+    pub text_document_position: TextDocumentPositionParams,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity_level: Option<u32>,
+}
+
+pub fn handle(params: lsp_types::HoverParams, verbosity: u32) -> RequestSpec {
+    quickinfo_request(params.text_document_position_params, verbosity, false)
+}
+
+/// `ts-bridge/expandHover`: like hover, one level above `verbosity` unless
+/// the client asks for a specific level.
+pub fn handle_expand(params: ExpandHoverParams, verbosity: u32) -> RequestSpec {
+    let level = params.verbosity_level.unwrap_or(verbosity + 1);
+    quickinfo_request(params.text_document_position, level, true)
+}
+
+fn quickinfo_request(
+    params: TextDocumentPositionParams,
+    verbosity: u32,
+    expand: bool,
+) -> RequestSpec {
+    let text_document = params.text_document;
     let uri_string = text_document.uri.to_string();
     let file_name = uri_to_file_path(text_document.uri.as_str()).unwrap_or(uri_string);
-    let position = params.text_document_position_params.position;
-    let request = json!({
+    let position = params.position;
+    let mut request = json!({
         "command": "quickinfo",
         "arguments": {
             "file": file_name,
@@ -29,17 +62,20 @@ pub fn handle(params: lsp_types::HoverParams) -> RequestSpec {
             "offset": position.character + 1,
         }
     });
+    if verbosity > 0 {
+        request["arguments"]["verbosityLevel"] = json!(verbosity);
+    }
 
     RequestSpec {
         route: Route::Syntax,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_quickinfo),
-        response_context: None,
+        response_context: expand.then(|| json!({ "verbosityLevel": verbosity })),
     }
 }
 
-fn adapt_quickinfo(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+fn adapt_quickinfo(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
     let body = payload
         .get("body")
         .context("tsserver quickinfo missing body")?;
@@ -73,7 +109,15 @@ fn adapt_quickinfo(payload: &Value, _context: Option<&Value>) -> Result<AdapterR
         range: tsserver_range_from_value_lsp(body),
     };
 
-    Ok(AdapterResult::ready(serde_json::to_value(hover)?))
+    let mut value = serde_json::to_value(hover)?;
+    if let Some(level) = context.and_then(|ctx| ctx.get("verbosityLevel")) {
+        value["verbosityLevel"] = level.clone();
+        value["canIncreaseVerbosityLevel"] = body
+            .get("canIncreaseVerbosityLevel")
+            .cloned()
+            .unwrap_or(Value::Bool(false));
+    }
+    Ok(AdapterResult::ready(value))
 }
 
 fn flatten_symbol_display(
@@ -155,7 +199,7 @@ mod tests {
             work_done_progress_params: Default::default(),
         };
 
-        let spec = handle(params);
+        let spec = handle(params, 0);
         assert_eq!(spec.route, Route::Syntax);
         assert_eq!(spec.priority, Priority::Normal);
         let args = spec.payload.get("arguments").expect("arguments missing");
@@ -165,6 +209,37 @@ mod tests {
         );
         assert_eq!(args.get("line").and_then(|v| v.as_u64()), Some(5));
         assert_eq!(args.get("offset").and_then(|v| v.as_u64()), Some(3));
+        assert!(args.get("verbosityLevel").is_none());
+    }
+
+    #[test]
+    fn expand_hover_raises_verbosity_and_reports_level() {
+        let params: ExpandHoverParams = serde_json::from_value(json!({
+            "textDocument": { "uri": "file:///workspace/foo.ts" },
+            "position": { "line": 0, "character": 6 }
+        }))
+        .unwrap();
+        let spec = handle_expand(params, 1);
+        assert_eq!(
+            spec.payload.pointer("/arguments/verbosityLevel"),
+            Some(&json!(2))
+        );
+
+        let payload = json!({
+            "body": {
+                "displayString": "const user: { name: string; }",
+                "canIncreaseVerbosityLevel": true,
+                "start": { "line": 1, "offset": 7 },
+                "end": { "line": 1, "offset": 11 }
+            }
+        });
+        let AdapterResult::Ready(value) =
+            adapt_quickinfo(&payload, spec.response_context.as_ref()).unwrap()
+        else {
+            panic!("expected ready hover response");
+        };
+        assert_eq!(value.get("verbosityLevel"), Some(&json!(2)));
+        assert_eq!(value.get("canIncreaseVerbosityLevel"), Some(&json!(true)));
     }
 
     #[test]
@@ -185,7 +260,9 @@ mod tests {
         let adapted = adapt_quickinfo(&payload, None).expect("hover should adapt");
         let hover_value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready hover response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready hover response")
+            }
         };
         let hover: LspHover = serde_json::from_value(hover_value).expect("hover deserializes");
         let HoverContents::Markup(content) = hover.contents else {
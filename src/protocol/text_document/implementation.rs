@@ -32,7 +32,7 @@ pub fn handle(params: GotoDefinitionParams) -> RequestSpec {
     });
 
     RequestSpec {
-        route: Route::Syntax,
+        route: Route::SemanticPreferred,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_implementation),
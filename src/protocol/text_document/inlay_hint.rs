@@ -4,20 +4,50 @@
 //!
 //! Bridges LSP inlay hint requests to tsserver’s `provideInlayHints` command,
 //! translating the returned metadata into `InlayHint` entries (respecting label
-//! text, padding flags, and kind tagging).
+//! text, padding flags, and kind tagging).  tsserver has no length preference
+//! for hints, so `maximum_inlay_hint_length` is applied to the labels here.
+//!
+//! Clients that can resolve `label.location` get tsserver's interactive hints
+//! (`interactiveInlayHints`): every display part that points at a declaration
+//! becomes an `InlayHintLabelPart` with a `location`, so types inside a hint
+//! can be followed like regular symbols.
 
 use anyhow::{Context, Result};
-use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, InlayHintParams};
+use lsp_types::{
+    ClientCapabilities, InlayHint, InlayHintKind, InlayHintLabel, InlayHintLabelPart,
+    InlayHintParams,
+};
 use serde_json::{Value, json};
 
 use crate::documents::TextSpan;
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
-use crate::utils::{tsserver_position_value_lsp, uri_to_file_path};
+use crate::utils::{tsserver_position_value_lsp, tsserver_span_to_location, uri_to_file_path};
 
 const CMD_PROVIDE_INLAY_HINTS: &str = "provideInlayHints";
 
-pub fn handle(params: InlayHintParams, span: TextSpan) -> RequestSpec {
+/// Whether the client lists `label.location` among the inlay hint properties
+/// it can resolve, i.e. it knows what to do with located label parts.
+pub fn supports_label_locations(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.inlay_hint.as_ref())
+        .and_then(|inlay_hint| inlay_hint.resolve_support.as_ref())
+        .is_some_and(|support| {
+            support
+                .properties
+                .iter()
+                .any(|property| property == "label.location")
+        })
+}
+
+pub fn handle(
+    params: InlayHintParams,
+    span: TextSpan,
+    max_length: Option<u32>,
+    label_locations: bool,
+) -> RequestSpec {
     let uri = params.text_document.uri;
     let file_path = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
 
@@ -35,19 +65,36 @@ pub fn handle(params: InlayHintParams, span: TextSpan) -> RequestSpec {
         payload: request,
         priority: Priority::Low,
         on_response: Some(adapt_inlay_hints),
-        response_context: None,
+        response_context: Some(json!({
+            "maxLength": max_length,
+            "labelLocations": label_locations,
+        })),
     }
 }
 
-fn adapt_inlay_hints(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+fn adapt_inlay_hints(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
     let entries = payload
         .get("body")
         .and_then(|value| value.as_array())
         .context("tsserver provideInlayHints missing body array")?;
+    let max_length = context
+        .and_then(|ctx| ctx.get("maxLength"))
+        .and_then(|value| value.as_u64())
+        .map(|length| length as usize);
+    let label_locations = context
+        .and_then(|ctx| ctx.get("labelLocations"))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
 
     let mut hints = Vec::with_capacity(entries.len());
     for entry in entries {
-        if let Some(hint) = convert_hint(entry) {
+        if let Some(mut hint) = convert_hint(entry, label_locations) {
+            if let Some(max_length) = max_length {
+                match &mut hint.label {
+                    InlayHintLabel::String(label) => truncate_label(label, max_length),
+                    InlayHintLabel::LabelParts(parts) => truncate_parts(parts, max_length),
+                }
+            }
             hints.push(hint);
         }
     }
@@ -55,11 +102,14 @@ fn adapt_inlay_hints(payload: &Value, _context: Option<&Value>) -> Result<Adapte
     Ok(AdapterResult::ready(serde_json::to_value(hints)?))
 }
 
-fn convert_hint(entry: &Value) -> Option<InlayHint> {
+fn convert_hint(entry: &Value, label_locations: bool) -> Option<InlayHint> {
     let position = entry
         .get("position")
         .and_then(tsserver_position_value_lsp)?;
-    let label = render_label(entry)?;
+    let label = label_locations
+        .then(|| label_parts(entry))
+        .flatten()
+        .or_else(|| render_label(entry))?;
     let kind = match entry.get("kind").and_then(|value| value.as_str()) {
         Some("Type") => Some(InlayHintKind::TYPE),
         Some("Parameter") => Some(InlayHintKind::PARAMETER),
@@ -84,6 +134,61 @@ fn convert_hint(entry: &Value) -> Option<InlayHint> {
     })
 }
 
+/// Keeps the first `max_length - 1` characters and appends `…`.
+fn truncate_label(label: &mut String, max_length: usize) {
+    if label.chars().count() <= max_length {
+        return;
+    }
+    let keep = max_length.saturating_sub(1);
+    let cut = label
+        .char_indices()
+        .nth(keep)
+        .map(|(index, _)| index)
+        .unwrap_or(label.len());
+    label.truncate(cut);
+    label.push('…');
+}
+
+/// Drops whole parts past the budget and shortens the one that crosses it.
+fn truncate_parts(parts: &mut Vec<InlayHintLabelPart>, max_length: usize) {
+    let count = parts.len();
+    let mut remaining = max_length;
+    for (index, part) in parts.iter_mut().enumerate() {
+        let length = part.value.chars().count();
+        if length < remaining || (length == remaining && index + 1 == count) {
+            remaining -= length;
+            continue;
+        }
+        truncate_label(&mut part.value, remaining.max(1));
+        if !part.value.ends_with('…') {
+            part.value.push('…');
+        }
+        parts.truncate(index + 1);
+        return;
+    }
+}
+
+/// Interactive hints: only worth returning as parts when at least one of them
+/// points somewhere.
+fn label_parts(value: &Value) -> Option<InlayHintLabel> {
+    let parts = value.get("displayParts")?.as_array()?;
+    let parts: Vec<InlayHintLabelPart> = parts
+        .iter()
+        .filter_map(|part| {
+            let text = part.get("text")?.as_str()?;
+            Some(InlayHintLabelPart {
+                value: text.to_string(),
+                location: part.get("span").and_then(tsserver_span_to_location),
+                ..InlayHintLabelPart::default()
+            })
+        })
+        .collect();
+    parts
+        .iter()
+        .any(|part| part.location.is_some())
+        .then_some(InlayHintLabel::LabelParts(parts))
+}
+
 fn render_label(value: &Value) -> Option<InlayHintLabel> {
     if let Some(text) = value.get("text").and_then(|v| v.as_str()) {
         if !text.is_empty() {
@@ -107,9 +212,10 @@ fn render_label(value: &Value) -> Option<InlayHintLabel> {
 /// Builds the TypeScript `UserPreferences` slice we forward through the `configure`
 /// command. Toggling inlay hints off funnels every boolean switch to `false`
 /// (or `"none"` for enums) so tsserver stops emitting hint payloads entirely.
-pub fn preferences(enabled: bool) -> Value {
+pub fn preferences(enabled: bool, interactive: bool) -> Value {
     if enabled {
         json!({
+            "interactiveInlayHints": interactive,
             "includeInlayParameterNameHints": "literals",
             "includeInlayParameterNameHintsWhenArgumentMatchesName": false,
             "includeInlayFunctionParameterTypeHints": true,
@@ -120,6 +226,7 @@ pub fn preferences(enabled: bool) -> Value {
         })
     } else {
         json!({
+            "interactiveInlayHints": false,
             "includeInlayParameterNameHints": "none",
             "includeInlayParameterNameHintsWhenArgumentMatchesName": false,
             "includeInlayFunctionParameterTypeHints": false,
@@ -130,3 +237,77 @@ pub fn preferences(enabled: bool) -> Value {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_labels_are_truncated_when_limited() {
+        let payload = json!({
+            "body": [{
+                "text": ": { readonly [K in keyof T]: T[K] }",
+                "position": { "line": 1, "offset": 9 },
+                "kind": "Type"
+            }, {
+                "text": "x:",
+                "position": { "line": 2, "offset": 5 },
+                "kind": "Parameter"
+            }]
+        });
+        let context = json!({ "maxLength": 10 });
+        let AdapterResult::Ready(value) = adapt_inlay_hints(&payload, Some(&context)).unwrap()
+        else {
+            panic!("expected ready hints");
+        };
+        let hints: Vec<InlayHint> = serde_json::from_value(value).unwrap();
+        let labels: Vec<_> = hints
+            .iter()
+            .map(|hint| match &hint.label {
+                InlayHintLabel::String(label) => label.as_str(),
+                InlayHintLabel::LabelParts(_) => panic!("unexpected label parts"),
+            })
+            .collect();
+        assert_eq!(labels, [": { reado…", "x:"]);
+    }
+
+    #[test]
+    fn interactive_parts_keep_locations() {
+        let payload = json!({
+            "body": [{
+                "displayParts": [
+                    { "text": ": " },
+                    {
+                        "text": "User",
+                        "span": {
+                            "file": "/workspace/src/user.ts",
+                            "start": { "line": 3, "offset": 14 },
+                            "end": { "line": 3, "offset": 18 }
+                        }
+                    }
+                ],
+                "position": { "line": 1, "offset": 9 },
+                "kind": "Type"
+            }]
+        });
+        let context = json!({ "labelLocations": true });
+        let AdapterResult::Ready(value) = adapt_inlay_hints(&payload, Some(&context)).unwrap()
+        else {
+            panic!("expected ready hints");
+        };
+        let hints: Vec<InlayHint> = serde_json::from_value(value).unwrap();
+        let InlayHintLabel::LabelParts(parts) = &hints[0].label else {
+            panic!("expected label parts");
+        };
+        assert_eq!(parts[0].location, None);
+        let location = parts[1].location.as_ref().expect("location");
+        assert!(location.uri.as_str().ends_with("/src/user.ts"));
+        assert_eq!(location.range.start.line, 2);
+
+        let AdapterResult::Ready(value) = adapt_inlay_hints(&payload, None).unwrap() else {
+            panic!("expected ready hints");
+        };
+        let hints: Vec<InlayHint> = serde_json::from_value(value).unwrap();
+        assert!(matches!(&hints[0].label, InlayHintLabel::String(label) if label == ": User"));
+    }
+}
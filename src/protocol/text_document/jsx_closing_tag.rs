@@ -0,0 +1,210 @@
+//! =============================================================================
+//! Automatic JSX close tags (tsserver `jsxClosingTag`)
+//! =============================================================================
+//!
+//! Not an LSP request: when a `didChange` types `>` or `/` in a JSX document,
+//! the session asks tsserver for the matching closing tag and pushes it to the
+//! client through `workspace/applyEdit`.  The edit carries the document version
+//! the change produced, so clients reject it if the buffer moved on before
+//! tsserver answered.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use lsp_types::{
+    ApplyWorkspaceEditParams, DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier,
+    Position, Range, TextDocumentEdit, TextEdit, Uri, WorkspaceEdit,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::types::TextDocumentContentChangeEvent;
+use crate::utils::uri_to_file_path;
+
+const TRIGGER_TEXTS: &[&str] = &[">", "/"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClosingTagContext {
+    uri: Uri,
+    version: Option<i32>,
+    position: Position,
+}
+
+/// Whether the document is one of the configured `filetypes`. Clients that
+/// omit `languageId` are matched by extension instead.
+pub fn applies_to(filetypes: &[String], language_id: Option<&str>, uri: &str) -> bool {
+    let extension = Path::new(uri).extension().and_then(|ext| ext.to_str());
+    let language_id = language_id.or(match extension {
+        Some("jsx") => Some("javascriptreact"),
+        Some("tsx") => Some("typescriptreact"),
+        _ => None,
+    });
+    language_id.is_some_and(|id| filetypes.iter().any(|filetype| filetype == id))
+}
+
+/// Cursor position right after a single typed `>` or `/`, or `None` when the
+/// change was anything else (pastes, deletions, multi-cursor edits).
+pub fn trigger_position(changes: &[TextDocumentContentChangeEvent]) -> Option<Position> {
+    let [change] = changes else {
+        return None;
+    };
+    let range = change.range.as_ref()?;
+    if range.start.line != range.end.line
+        || range.start.character != range.end.character
+        || !TRIGGER_TEXTS.contains(&change.text.as_str())
+    {
+        return None;
+    }
+    Some(Position {
+        line: range.start.line,
+        character: range.start.character + 1,
+    })
+}
+
+pub fn handle(uri: &Uri, version: Option<i32>, position: Position) -> Result<RequestSpec> {
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+    let context = ClosingTagContext {
+        uri: uri.clone(),
+        version,
+        position,
+    };
+    Ok(RequestSpec {
+        route: Route::Syntax,
+        payload: json!({
+            "command": "jsxClosingTag",
+            "arguments": {
+                "file": file,
+                "line": position.line + 1,
+                "offset": position.character + 1,
+            }
+        }),
+        priority: Priority::Normal,
+        on_response: Some(adapt_closing_tag),
+        response_context: Some(serde_json::to_value(context)?),
+    })
+}
+
+/// Produces `ApplyWorkspaceEditParams`, or `null` when there is no tag to
+/// close.
+fn adapt_closing_tag(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let ctx: ClosingTagContext =
+        serde_json::from_value(context.cloned().context("missing jsxClosingTag context")?)?;
+    let Some(new_text) = payload
+        .get("body")
+        .and_then(|body| body.get("newText"))
+        .and_then(|v| v.as_str())
+        .filter(|text| !text.is_empty())
+    else {
+        return Ok(AdapterResult::ready(Value::Null));
+    };
+
+    let edit = TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier {
+            uri: ctx.uri,
+            version: ctx.version,
+        },
+        edits: vec![OneOf::Left(TextEdit {
+            range: Range {
+                start: ctx.position,
+                end: ctx.position,
+            },
+            new_text: new_text.to_string(),
+        })],
+    };
+    let params = ApplyWorkspaceEditParams {
+        label: Some("Insert JSX closing tag".to_string()),
+        edit: WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(vec![edit])),
+            ..WorkspaceEdit::default()
+        },
+    };
+    Ok(AdapterResult::ready(serde_json::to_value(params)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn typed(text: &str, line: u32, character: u32) -> Vec<TextDocumentContentChangeEvent> {
+        let position = crate::types::Position { line, character };
+        vec![TextDocumentContentChangeEvent {
+            range: Some(crate::types::Range {
+                start: position.clone(),
+                end: position,
+            }),
+            text: text.to_string(),
+        }]
+    }
+
+    #[test]
+    fn only_single_close_characters_trigger() {
+        assert_eq!(
+            trigger_position(&typed(">", 2, 8)),
+            Some(Position {
+                line: 2,
+                character: 9
+            })
+        );
+        assert!(trigger_position(&typed("/", 0, 0)).is_some());
+        assert!(trigger_position(&typed("a", 2, 8)).is_none());
+        assert!(trigger_position(&typed("<div>", 2, 8)).is_none());
+    }
+
+    #[test]
+    fn filetypes_fall_back_to_extension() {
+        let filetypes = vec!["typescriptreact".to_string()];
+        assert!(applies_to(&filetypes, None, "file:///w/App.tsx"));
+        assert!(applies_to(
+            &filetypes,
+            Some("typescriptreact"),
+            "file:///w/App"
+        ));
+        assert!(!applies_to(&filetypes, None, "file:///w/App.jsx"));
+        assert!(!applies_to(
+            &filetypes,
+            Some("typescript"),
+            "file:///w/a.ts"
+        ));
+    }
+
+    #[test]
+    fn closing_tag_becomes_versioned_edit() {
+        let uri = Uri::from_str("file:///w/App.tsx").unwrap();
+        let position = Position {
+            line: 3,
+            character: 9,
+        };
+        let spec = handle(&uri, Some(7), position).unwrap();
+        assert_eq!(spec.payload.pointer("/arguments/offset"), Some(&json!(10)));
+
+        let adapter = spec.on_response.unwrap();
+        let payload = json!({ "body": { "newText": "</div>", "caretOffset": 0 } });
+        let AdapterResult::Ready(value) =
+            adapter(&payload, spec.response_context.as_ref()).unwrap()
+        else {
+            panic!("expected ready result");
+        };
+        let params: ApplyWorkspaceEditParams = serde_json::from_value(value).unwrap();
+        let Some(DocumentChanges::Edits(edits)) = params.edit.document_changes else {
+            panic!("expected document edits");
+        };
+        assert_eq!(edits[0].text_document.version, Some(7));
+        match &edits[0].edits[0] {
+            OneOf::Left(edit) => {
+                assert_eq!(edit.new_text, "</div>");
+                assert_eq!(edit.range.start, position);
+            }
+            OneOf::Right(_) => panic!("unexpected annotated edit"),
+        }
+
+        let AdapterResult::Ready(value) =
+            adapter(&json!({ "body": {} }), spec.response_context.as_ref()).unwrap()
+        else {
+            panic!("expected ready result");
+        };
+        assert!(value.is_null());
+    }
+}
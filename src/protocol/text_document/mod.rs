@@ -6,20 +6,30 @@
 
 pub mod code_action;
 pub mod code_action_resolve;
+pub mod code_lens;
 pub mod completion;
 pub mod completion_resolve;
+pub mod declaration;
 pub mod definition;
+pub mod diagnostic;
 pub mod did_change;
 pub mod did_close;
 pub mod did_open;
+pub mod doc_comment_template;
 pub mod document_highlight;
 pub mod document_symbol;
+pub mod folding_range;
 pub mod formatting;
 pub mod hover;
 pub mod implementation;
 pub mod inlay_hint;
+pub mod jsx_closing_tag;
+pub mod on_type_formatting;
+pub mod paste_edits;
 pub mod references;
 pub mod rename;
+pub mod selection_range;
 pub mod semantic_tokens;
 pub mod signature_help;
 pub mod type_definition;
+pub mod will_save_wait_until;
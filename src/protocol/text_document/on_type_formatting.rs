@@ -0,0 +1,91 @@
+//! =============================================================================
+//! textDocument/onTypeFormatting
+//! =============================================================================
+//!
+//! Forwards on-type formatting to tsserver’s `formatonkey` command. The LSP
+//! `FormattingOptions` are converted with the same helper used for whole
+//! document formatting, so both paths send identical `FormatCodeSettings`.
+
+use lsp_types::DocumentOnTypeFormattingParams;
+use serde_json::json;
+
+use crate::protocol::RequestSpec;
+use crate::protocol::text_document::formatting::{adapt_formatting, format_code_settings};
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+const CMD_FORMAT_ON_KEY: &str = "formatonkey";
+
+pub const FIRST_TRIGGER_CHARACTER: &str = ";";
+pub const MORE_TRIGGER_CHARACTERS: &[&str] = &["}", "\n"];
+
+pub fn handle(params: DocumentOnTypeFormattingParams) -> RequestSpec {
+    let text_document = params.text_document_position.text_document;
+    let position = params.text_document_position.position;
+    let file = uri_to_file_path(text_document.uri.as_str())
+        .unwrap_or_else(|| text_document.uri.to_string());
+    let options = format_code_settings(&params.options);
+
+    let request = json!({
+        "command": CMD_FORMAT_ON_KEY,
+        "arguments": {
+            "file": file,
+            "line": position.line + 1,
+            "offset": position.character + 1,
+            "key": params.ch,
+            "options": options,
+        }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapt_formatting),
+        response_context: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{
+        FormattingOptions, Position, TextDocumentIdentifier, TextDocumentPositionParams, Uri,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn handle_builds_formatonkey_request_with_options() {
+        let params = DocumentOnTypeFormattingParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_str("file:///workspace/src/index.ts").unwrap(),
+                },
+                position: Position {
+                    line: 4,
+                    character: 12,
+                },
+            },
+            ch: "}".into(),
+            options: FormattingOptions {
+                tab_size: 2,
+                insert_spaces: true,
+                ..Default::default()
+            },
+        };
+
+        let spec = handle(params);
+        assert_eq!(spec.route, Route::Syntax);
+        assert_eq!(spec.payload.get("command"), Some(&json!("formatonkey")));
+        let args = spec.payload.get("arguments").expect("arguments missing");
+        assert_eq!(args.get("line").and_then(|v| v.as_u64()), Some(5));
+        assert_eq!(args.get("offset").and_then(|v| v.as_u64()), Some(13));
+        assert_eq!(args.get("key").and_then(|v| v.as_str()), Some("}"));
+        let options = args.get("options").expect("options missing");
+        assert_eq!(options.get("tabSize").and_then(|v| v.as_u64()), Some(2));
+        assert_eq!(
+            options.get("convertTabsToSpaces").and_then(|v| v.as_bool()),
+            Some(true)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! =============================================================================
+//! ts-bridge/pasteEdits (tsserver `getPasteEdits`, TypeScript 5.6+)
+//! =============================================================================
+//!
+//! Custom request for paste integrations: given the pasted text, where it lands
+//! in the target document and optionally where it was copied from, tsserver
+//! returns the edits that paste it together with any imports the pasted code
+//! needs.  The result is a `WorkspaceEdit` (or `null` when tsserver has nothing
+//! to add).  `TSBPasteEdits` exposes the same request through
+//! `workspace/executeCommand` for clients that cannot send custom requests.
+
+use anyhow::Result;
+use lsp_types::{Range, TextDocumentIdentifier, Uri};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::protocol::text_document::code_action::workspace_edit_from_tsserver_changes;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+pub const METHOD: &str = "ts-bridge/pasteEdits";
+pub const COMMAND: &str = "TSBPasteEdits";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteEditsParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Pasted text, one entry per paste location.
+    pub pasted_text: Vec<String>,
+    /// Ranges the paste replaces, in the document as it was before pasting.
+    pub paste_ranges: Vec<Range>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copied_from: Option<CopiedFrom>,
+}
+
+/// Copy origin; lets tsserver reuse the imports of the source file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopiedFrom {
+    pub uri: Uri,
+    pub ranges: Vec<Range>,
+}
+
+pub fn handle(params: PasteEditsParams) -> RequestSpec {
+    let mut arguments = json!({
+        "file": file_name(&params.text_document.uri),
+        "pastedText": params.pasted_text,
+        "pasteLocations": params.paste_ranges.iter().map(tsserver_span).collect::<Vec<_>>(),
+    });
+    if let Some(copied_from) = &params.copied_from {
+        arguments["copiedFrom"] = json!({
+            "file": file_name(&copied_from.uri),
+            "spans": copied_from.ranges.iter().map(tsserver_span).collect::<Vec<_>>(),
+        });
+    }
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: json!({
+            "command": "getPasteEdits",
+            "arguments": arguments,
+        }),
+        priority: Priority::Normal,
+        on_response: Some(adapt_paste_edits),
+        response_context: None,
+    }
+}
+
+fn adapt_paste_edits(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+    let edits = payload
+        .get("body")
+        .and_then(|body| body.get("edits"))
+        .and_then(|edits| edits.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let edit = workspace_edit_from_tsserver_changes(&edits);
+    Ok(AdapterResult::ready(serde_json::to_value(edit)?))
+}
+
+fn file_name(uri: &Uri) -> String {
+    uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string())
+}
+
+fn tsserver_span(range: &Range) -> Value {
+    json!({
+        "start": { "line": range.start.line + 1, "offset": range.start.character + 1 },
+        "end": { "line": range.end.line + 1, "offset": range.end.character + 1 },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paste_request_includes_copy_origin_and_adapts_edits() {
+        let params: PasteEditsParams = serde_json::from_value(json!({
+            "textDocument": { "uri": "file:///w/src/b.ts" },
+            "pastedText": ["useThing();"],
+            "pasteRanges": [{
+                "start": { "line": 4, "character": 0 },
+                "end": { "line": 4, "character": 0 }
+            }],
+            "copiedFrom": {
+                "uri": "file:///w/src/a.ts",
+                "ranges": [{
+                    "start": { "line": 9, "character": 2 },
+                    "end": { "line": 9, "character": 13 }
+                }]
+            }
+        }))
+        .unwrap();
+        let spec = handle(params);
+        assert_eq!(spec.payload.get("command"), Some(&json!("getPasteEdits")));
+        assert_eq!(
+            spec.payload.pointer("/arguments/pasteLocations/0/start"),
+            Some(&json!({ "line": 5, "offset": 1 }))
+        );
+        assert_eq!(
+            spec.payload.pointer("/arguments/copiedFrom/file"),
+            Some(&json!("/w/src/a.ts"))
+        );
+
+        let payload = json!({
+            "body": {
+                "edits": [{
+                    "fileName": "/w/src/b.ts",
+                    "textChanges": [{
+                        "start": { "line": 1, "offset": 1 },
+                        "end": { "line": 1, "offset": 1 },
+                        "newText": "import { useThing } from \"./a\";\n"
+                    }, {
+                        "start": { "line": 5, "offset": 1 },
+                        "end": { "line": 5, "offset": 1 },
+                        "newText": "useThing();"
+                    }]
+                }]
+            }
+        });
+        let AdapterResult::Ready(value) = (spec.on_response.unwrap())(&payload, None).unwrap()
+        else {
+            panic!("expected ready edit");
+        };
+        let edit: lsp_types::WorkspaceEdit = serde_json::from_value(value).unwrap();
+        let edits: Vec<_> = edit.changes.unwrap().into_values().flatten().collect();
+        assert_eq!(edits.len(), 2);
+    }
+}
@@ -35,7 +35,7 @@ pub fn handle(params: ReferenceParams) -> RequestSpec {
     });
 
     RequestSpec {
-        route: Route::Syntax,
+        route: Route::SemanticPreferred,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_references),
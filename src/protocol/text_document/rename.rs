@@ -79,7 +79,7 @@ pub fn handle(params: RenameParams) -> RequestSpec {
     let context = json!({ "new_text": new_name });
 
     RequestSpec {
-        route: Route::Syntax,
+        route: Route::SemanticPreferred,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_rename),
@@ -0,0 +1,168 @@
+//! =============================================================================
+//! textDocument/selectionRange
+//! =============================================================================
+//!
+//! Sends every requested position to tsserver’s `selectionRange` command in a
+//! single round-trip and rebuilds the nested `parent` chain as LSP
+//! `SelectionRange` values, one per requested position.
+
+use anyhow::{Context, Result};
+use lsp_types::{SelectionRange, SelectionRangeParams};
+use serde_json::{Value, json};
+
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_range_from_value_lsp, uri_to_file_path};
+
+pub fn handle(params: SelectionRangeParams) -> RequestSpec {
+    let uri = params.text_document.uri;
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+    let locations = params
+        .positions
+        .iter()
+        .map(|position| {
+            json!({
+                "line": position.line + 1,
+                "offset": position.character + 1,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let request = json!({
+        "command": "selectionRange",
+        "arguments": {
+            "file": file,
+            "locations": locations,
+        }
+    });
+
+    RequestSpec {
+        route: Route::Syntax,
+        payload: request,
+        priority: Priority::Normal,
+        on_response: Some(adapt_selection_ranges),
+        response_context: None,
+    }
+}
+
+fn adapt_selection_ranges(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+    let items = payload
+        .get("body")
+        .context("tsserver selectionRange missing body")?
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut ranges = Vec::with_capacity(items.len());
+    for item in &items {
+        ranges.push(convert_selection_range(item).context("invalid selectionRange span")?);
+    }
+
+    Ok(AdapterResult::ready(serde_json::to_value(ranges)?))
+}
+
+/// Walks the tsserver chain from the innermost span outwards. The chain is
+/// flattened first so deeply nested expressions do not recurse per level.
+fn convert_selection_range(item: &Value) -> Option<SelectionRange> {
+    let mut spans = Vec::new();
+    let mut current = Some(item);
+    while let Some(node) = current {
+        spans.push(tsserver_range_from_value_lsp(node.get("textSpan")?)?);
+        current = node.get("parent");
+    }
+
+    let mut result: Option<SelectionRange> = None;
+    for range in spans.into_iter().rev() {
+        result = Some(SelectionRange {
+            range,
+            parent: result.map(Box::new),
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, TextDocumentIdentifier, Uri};
+    use std::str::FromStr;
+
+    #[test]
+    fn handle_sends_all_positions() {
+        let params = SelectionRangeParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str("file:///workspace/src/app.ts").unwrap(),
+            },
+            positions: vec![
+                Position {
+                    line: 0,
+                    character: 4,
+                },
+                Position {
+                    line: 9,
+                    character: 0,
+                },
+            ],
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let spec = handle(params);
+        assert_eq!(spec.route, Route::Syntax);
+        assert_eq!(spec.payload.get("command"), Some(&json!("selectionRange")));
+        let args = spec.payload.get("arguments").expect("arguments missing");
+        assert_eq!(
+            args.get("file").and_then(|v| v.as_str()),
+            Some("/workspace/src/app.ts")
+        );
+        assert_eq!(
+            args.get("locations"),
+            Some(&json!([
+                { "line": 1, "offset": 5 },
+                { "line": 10, "offset": 1 }
+            ]))
+        );
+    }
+
+    #[test]
+    fn adapt_selection_ranges_rebuilds_parent_chain() {
+        let payload = json!({
+            "body": [{
+                "textSpan": {
+                    "start": { "line": 1, "offset": 7 },
+                    "end": { "line": 1, "offset": 10 }
+                },
+                "parent": {
+                    "textSpan": {
+                        "start": { "line": 1, "offset": 1 },
+                        "end": { "line": 1, "offset": 20 }
+                    },
+                    "parent": {
+                        "textSpan": {
+                            "start": { "line": 1, "offset": 1 },
+                            "end": { "line": 3, "offset": 1 }
+                        }
+                    }
+                }
+            }]
+        });
+
+        let adapted = adapt_selection_ranges(&payload, None).expect("selection ranges adapt");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready selection ranges")
+            }
+        };
+        let ranges: Vec<SelectionRange> =
+            serde_json::from_value(value).expect("selection ranges deserialize");
+        assert_eq!(ranges.len(), 1);
+        let inner = &ranges[0];
+        assert_eq!(inner.range.start.character, 6);
+        assert_eq!(inner.range.end.character, 9);
+        let parent = inner.parent.as_ref().expect("parent present");
+        assert_eq!(parent.range.end.character, 19);
+        let outer = parent.parent.as_ref().expect("outer parent present");
+        assert_eq!(outer.range.end.line, 2);
+        assert!(outer.parent.is_none());
+    }
+}
@@ -283,7 +283,9 @@ mod tests {
         let adapted = adapt_semantic_tokens(&payload, None).expect("semantic tokens adapt");
         let value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready semantic tokens response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready semantic tokens response")
+            }
         };
         let tokens: SemanticTokens =
             serde_json::from_value(value).expect("semantic tokens deserialize");
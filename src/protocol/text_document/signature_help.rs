@@ -315,7 +315,9 @@ mod tests {
         let adapted = adapt_signature_help(&payload, None).expect("signature help adapts");
         let value = match adapted {
             AdapterResult::Ready(value) => value,
-            AdapterResult::Continue(_) => panic!("expected ready signature help response"),
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready signature help response")
+            }
         };
         let parsed: LspSignatureHelp =
             serde_json::from_value(value).expect("signature help deserializes");
@@ -0,0 +1,214 @@
+//! =============================================================================
+//! textDocument/willSaveWaitUntil
+//! =============================================================================
+//!
+//! Runs the save-time actions enabled in the settings before the client writes
+//! the file: the `TSBFixAll` chain of `getCombinedCodeFix` requests first, then
+//! `organizeImports`.  Every step is computed against the unsaved text, so an
+//! edit that overlaps one accepted from an earlier step is dropped rather than
+//! returned as an invalid `TextEdit[]`.
+
+use std::collections::VecDeque;
+
+use anyhow::{Context, Result};
+use lsp_types::{Range, TextEdit, WillSaveTextDocumentParams};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::protocol::text_document::code_action::organize_imports_payload;
+use crate::protocol::workspace::execute_command::FIX_ALL_CHAIN;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{tsserver_range_from_value_lsp, uri_to_file_path};
+
+const STEP_ORGANIZE_IMPORTS: &str = "organizeImports";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveContext {
+    file: String,
+    /// Remaining steps: fix ids for `getCombinedCodeFix`, or
+    /// [`STEP_ORGANIZE_IMPORTS`].
+    pending: VecDeque<String>,
+    edits: Vec<TextEdit>,
+}
+
+/// Returns `None` when neither save action is enabled so the caller can answer
+/// with an empty edit list without touching tsserver.
+pub fn handle(
+    params: WillSaveTextDocumentParams,
+    organize_imports: bool,
+    fix_all: bool,
+) -> Option<RequestSpec> {
+    let uri = params.text_document.uri;
+    let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+
+    let mut pending = VecDeque::new();
+    if fix_all {
+        pending.extend(FIX_ALL_CHAIN.iter().map(|id| id.to_string()));
+    }
+    if organize_imports {
+        pending.push_back(STEP_ORGANIZE_IMPORTS.to_string());
+    }
+
+    next_step(SaveContext {
+        file,
+        pending,
+        edits: Vec::new(),
+    })
+    .ok()
+    .flatten()
+}
+
+fn next_step(mut ctx: SaveContext) -> Result<Option<RequestSpec>> {
+    let Some(step) = ctx.pending.pop_front() else {
+        return Ok(None);
+    };
+    let payload = if step == STEP_ORGANIZE_IMPORTS {
+        organize_imports_payload(&ctx.file, None)
+    } else {
+        json!({
+            "command": "getCombinedCodeFix",
+            "arguments": {
+                "scope": {
+                    "type": "file",
+                    "args": { "file": ctx.file },
+                },
+                "fixId": step,
+            }
+        })
+    };
+
+    Ok(Some(RequestSpec {
+        route: Route::Syntax,
+        payload,
+        priority: Priority::Const,
+        on_response: Some(adapt_save_step),
+        response_context: Some(serde_json::to_value(ctx)?),
+    }))
+}
+
+fn adapt_save_step(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut ctx: SaveContext = serde_json::from_value(
+        context
+            .cloned()
+            .context("missing willSaveWaitUntil context")?,
+    )?;
+    let body = payload.get("body");
+    // organizeImports answers with the edits directly; getCombinedCodeFix
+    // wraps them in `changes`.
+    let changes = body
+        .and_then(|body| body.get("changes").or(Some(body)))
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    for change in changes
+        .iter()
+        .filter(|change| change.get("fileName").and_then(|v| v.as_str()) == Some(&ctx.file))
+    {
+        let text_changes = change
+            .get("textChanges")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for text_change in &text_changes {
+            let Some(range) = tsserver_range_from_value_lsp(text_change) else {
+                continue;
+            };
+            let Some(new_text) = text_change.get("newText").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            if ctx.edits.iter().any(|edit| overlaps(&edit.range, &range)) {
+                continue;
+            }
+            ctx.edits.push(TextEdit {
+                range,
+                new_text: new_text.to_string(),
+            });
+        }
+    }
+
+    if ctx.pending.is_empty() {
+        return Ok(AdapterResult::ready(serde_json::to_value(ctx.edits)?));
+    }
+    let next = next_step(ctx)?.context("willSaveWaitUntil step vanished")?;
+    Ok(AdapterResult::Continue(next))
+}
+
+/// Touching ranges are fine; two inserts at the same position are not, since
+/// their relative order would be ambiguous.
+fn overlaps(a: &Range, b: &Range) -> bool {
+    if a.start == b.start {
+        return true;
+    }
+    a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{TextDocumentIdentifier, TextDocumentSaveReason, Uri};
+    use std::str::FromStr;
+
+    fn params() -> WillSaveTextDocumentParams {
+        WillSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_str("file:///workspace/app.ts").unwrap(),
+            },
+            reason: TextDocumentSaveReason::MANUAL,
+        }
+    }
+
+    fn file_edit(start_line: u32, end_line: u32, text: &str) -> Value {
+        json!({
+            "fileName": "/workspace/app.ts",
+            "textChanges": [{
+                "start": { "line": start_line, "offset": 1 },
+                "end": { "line": end_line, "offset": 1 },
+                "newText": text
+            }]
+        })
+    }
+
+    #[test]
+    fn handle_skips_tsserver_when_nothing_enabled() {
+        assert!(handle(params(), false, false).is_none());
+    }
+
+    #[test]
+    fn save_actions_chain_and_drop_overlapping_edits() {
+        let spec = handle(params(), true, true).expect("spec");
+        assert_eq!(
+            spec.payload.get("command"),
+            Some(&json!("getCombinedCodeFix"))
+        );
+
+        let mut spec = spec;
+        let mut steps = 0;
+        let edits = loop {
+            steps += 1;
+            let payload = if spec.payload.get("command") == Some(&json!("organizeImports")) {
+                json!({ "body": [file_edit(1, 3, "import { a } from \"a\";\n")] })
+            } else if steps == 1 {
+                json!({ "body": { "changes": [file_edit(2, 2, "await ")] } })
+            } else {
+                json!({ "body": { "changes": [] } })
+            };
+            match (spec.on_response.unwrap())(&payload, spec.response_context.as_ref()).unwrap() {
+                AdapterResult::Continue(next) => spec = next,
+                AdapterResult::Ready(value) => {
+                    break serde_json::from_value::<Vec<TextEdit>>(value).unwrap();
+                }
+                AdapterResult::Partial { .. } => panic!("unexpected partial result"),
+            }
+        };
+
+        assert_eq!(steps, FIX_ALL_CHAIN.len() + 1);
+        assert_eq!(
+            edits.len(),
+            1,
+            "organize edit overlaps the fix and is dropped"
+        );
+        assert_eq!(edits[0].new_text, "await ");
+    }
+}
@@ -0,0 +1,531 @@
+//! =============================================================================
+//! workspace/diagnostic
+//! =============================================================================
+//!
+//! Walks every file of the project that owns an open document. `projectInfo`
+//! supplies the file list, then each file is checked with
+//! `syntacticDiagnosticsSync` followed by `semanticDiagnosticsSync` on the
+//! diagnostics server, like pushed diagnostics. Every file gets a result id
+//! from its document version and the session's diagnostics generation; files
+//! whose id matches one the client sent are reported unchanged without asking
+//! tsserver.
+//!
+//! Each finished file leaves the walk as a partial result. The session
+//! streams those when the client passed a `partialResultToken` and otherwise
+//! gathers them with a [`ReportCollector`], so the chained context only
+//! carries the files still to check.
+
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::{Context, Result};
+use lsp_types::{
+    Diagnostic, FullDocumentDiagnosticReport, UnchangedDocumentDiagnosticReport,
+    WorkspaceDiagnosticParams, WorkspaceDiagnosticReport, WorkspaceDiagnosticReportPartialResult,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceUnchangedDocumentDiagnosticReport,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::DiagnosticsSettings;
+use crate::protocol::diagnostics::convert_diagnostic;
+use crate::protocol::text_document::diagnostic::result_id;
+use crate::protocol::{AdapterResult, RequestSpec};
+use crate::rpc::{Priority, Route};
+use crate::utils::{file_path_to_uri, uri_to_file_path};
+
+const CMD_PROJECT_INFO: &str = "projectInfo";
+const CMD_SYNTACTIC: &str = "syntacticDiagnosticsSync";
+const CMD_SEMANTIC: &str = "semanticDiagnosticsSync";
+
+/// Context of the opening `projectInfo` request; it becomes a [`WalkState`]
+/// once the file list is known.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectInfoState {
+    generation: u64,
+    versions: HashMap<String, i32>,
+    previous: HashMap<String, String>,
+    settings: DiagnosticsSettings,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WalkState {
+    pending: VecDeque<WalkFile>,
+    current: Option<WalkFile>,
+    current_diagnostics: Vec<Diagnostic>,
+    settings: DiagnosticsSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WalkFile {
+    file: String,
+    version: Option<i32>,
+    result_id: String,
+}
+
+/// Builds the opening `projectInfo` request. `anchor_file` must be a file the
+/// session already opened so tsserver knows which project to enumerate;
+/// `versions` holds the session's open documents by file.
+pub fn handle(
+    params: WorkspaceDiagnosticParams,
+    anchor_file: &str,
+    generation: u64,
+    versions: HashMap<String, i32>,
+    settings: DiagnosticsSettings,
+) -> RequestSpec {
+    let previous = params
+        .previous_result_ids
+        .into_iter()
+        .map(|previous| {
+            let file =
+                uri_to_file_path(previous.uri.as_str()).unwrap_or_else(|| previous.uri.to_string());
+            (file, previous.value)
+        })
+        .collect();
+    let state = ProjectInfoState {
+        generation,
+        versions,
+        previous,
+        settings,
+    };
+
+    RequestSpec {
+        route: Route::SemanticPreferred,
+        payload: json!({
+            "command": CMD_PROJECT_INFO,
+            "arguments": {
+                "file": anchor_file,
+                "needFileNameList": true,
+            }
+        }),
+        priority: Priority::Low,
+        on_response: Some(adapt_project_info),
+        response_context: Some(json!(state)),
+    }
+}
+
+/// Walks `files` without asking tsserver for a project file list. Used by
+/// `ts-bridge check` for files named on the command line; each must already be
+/// open so tsserver knows its project.
+pub fn walk_files(files: Vec<String>, settings: DiagnosticsSettings) -> Result<AdapterResult> {
+    advance(WalkState {
+        pending: files
+            .into_iter()
+            .map(|file| WalkFile {
+                file,
+                version: None,
+                result_id: result_id(0, None),
+            })
+            .collect(),
+        settings,
+        ..WalkState::default()
+    })
+}
+
+/// Report returned when no document is open, so there is no project to walk.
+pub fn empty_report() -> Value {
+    json!(WorkspaceDiagnosticReport { items: Vec::new() })
+}
+
+/// Gathers the per-file partial results of a walk for callers that answer
+/// with a single report.
+#[derive(Debug, Default, Clone)]
+pub struct ReportCollector {
+    items: Vec<Value>,
+}
+
+impl ReportCollector {
+    pub fn push(&mut self, partial: Value) {
+        if let Value::Object(mut partial) = partial
+            && let Some(Value::Array(items)) = partial.remove("items")
+        {
+            self.items.extend(items);
+        }
+    }
+
+    /// Folds the walk's final result in and returns the whole report.
+    pub fn finish(mut self, last: Value) -> Value {
+        self.push(last);
+        json!({ "items": self.items })
+    }
+}
+
+fn adapt_project_info(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let info: ProjectInfoState = serde_json::from_value(
+        context
+            .cloned()
+            .context("missing workspace diagnostics context")?,
+    )?;
+    let body = payload
+        .get("body")
+        .context("tsserver projectInfo missing body")?;
+    let files = body
+        .get("fileNames")
+        .and_then(|value| value.as_array())
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|file| file.as_str())
+                .filter(|file| is_project_source(file))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut state = WalkState {
+        settings: info.settings,
+        ..WalkState::default()
+    };
+    let mut unchanged = Vec::new();
+    for file in files {
+        let version = info.versions.get(file).copied();
+        let walk_file = WalkFile {
+            file: file.to_string(),
+            version,
+            result_id: result_id(info.generation, version),
+        };
+        if info.previous.get(file) == Some(&walk_file.result_id) {
+            unchanged.extend(unchanged_report(walk_file));
+        } else {
+            state.pending.push_back(walk_file);
+        }
+    }
+    emit(unchanged, state)
+}
+
+fn adapt_syntactic(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut state = load_state(context)?;
+    collect_diagnostics(payload, &mut state);
+    let file = state
+        .current
+        .as_ref()
+        .map(|current| current.file.clone())
+        .context("workspace diagnostics lost current file")?;
+    Ok(AdapterResult::Continue(file_request(
+        CMD_SEMANTIC,
+        &file,
+        state,
+        adapt_semantic,
+    )?))
+}
+
+fn adapt_semantic(payload: &Value, context: Option<&Value>) -> Result<AdapterResult> {
+    let mut state = load_state(context)?;
+    collect_diagnostics(payload, &mut state);
+    let file = state
+        .current
+        .take()
+        .context("workspace diagnostics lost current file")?;
+    let diagnostics = std::mem::take(&mut state.current_diagnostics);
+    emit(file_report(file, diagnostics).into_iter().collect(), state)
+}
+
+/// Hands `items` out as a partial result ahead of the next file, or as the
+/// final result when none is left.
+fn emit(items: Vec<WorkspaceDocumentDiagnosticReport>, state: WalkState) -> Result<AdapterResult> {
+    if items.is_empty() {
+        return advance(state);
+    }
+    let partial = json!(WorkspaceDiagnosticReportPartialResult { items });
+    match advance(state)? {
+        AdapterResult::Ready(_) => Ok(AdapterResult::ready(partial)),
+        AdapterResult::Continue(next) | AdapterResult::Partial { next, .. } => {
+            Ok(AdapterResult::Partial {
+                value: partial,
+                next,
+            })
+        }
+    }
+}
+
+/// Starts the next file, or finishes the walk when the queue is empty.
+fn advance(mut state: WalkState) -> Result<AdapterResult> {
+    let Some(next) = state.pending.pop_front() else {
+        return Ok(AdapterResult::ready(empty_report()));
+    };
+    let file = next.file.clone();
+    state.current = Some(next);
+    Ok(AdapterResult::Continue(file_request(
+        CMD_SYNTACTIC,
+        &file,
+        state,
+        adapt_syntactic,
+    )?))
+}
+
+fn file_request(
+    command: &str,
+    file: &str,
+    state: WalkState,
+    adapter: crate::protocol::ResponseAdapter,
+) -> Result<RequestSpec> {
+    Ok(RequestSpec {
+        route: Route::SemanticPreferred,
+        payload: json!({
+            "command": command,
+            "arguments": {
+                "file": file,
+                "includeLinePosition": false,
+            }
+        }),
+        priority: Priority::Low,
+        on_response: Some(adapter),
+        response_context: Some(serde_json::to_value(state)?),
+    })
+}
+
+fn load_state(context: Option<&Value>) -> Result<WalkState> {
+    Ok(serde_json::from_value(
+        context
+            .cloned()
+            .context("missing workspace diagnostics context")?,
+    )?)
+}
+
+fn collect_diagnostics(payload: &Value, state: &mut WalkState) {
+    let entries = payload
+        .get("body")
+        .and_then(|body| body.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let converted = entries
+        .into_iter()
+        .filter_map(|entry| convert_diagnostic(entry, &state.settings))
+        .collect::<Vec<_>>();
+    state.current_diagnostics.extend(converted);
+}
+
+fn file_report(
+    file: WalkFile,
+    diagnostics: Vec<Diagnostic>,
+) -> Option<WorkspaceDocumentDiagnosticReport> {
+    let uri = file_path_to_uri(&file.file)?;
+    Some(WorkspaceDocumentDiagnosticReport::Full(
+        WorkspaceFullDocumentDiagnosticReport {
+            uri,
+            version: file.version.map(i64::from),
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(file.result_id),
+                items: diagnostics,
+            },
+        },
+    ))
+}
+
+fn unchanged_report(file: WalkFile) -> Option<WorkspaceDocumentDiagnosticReport> {
+    let uri = file_path_to_uri(&file.file)?;
+    Some(WorkspaceDocumentDiagnosticReport::Unchanged(
+        WorkspaceUnchangedDocumentDiagnosticReport {
+            uri,
+            version: file.version.map(i64::from),
+            unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                result_id: file.result_id,
+            },
+        },
+    ))
+}
+
+/// Skips dependencies and declaration files: users only care about errors in
+/// the sources they own, and checking `node_modules` is slow.
+fn is_project_source(file: &str) -> bool {
+    !file.contains("/node_modules/")
+        && !file.ends_with(".d.ts")
+        && !file.ends_with(".d.mts")
+        && !file.ends_with(".d.cts")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{NumberOrString, PartialResultParams, PreviousResultId, ProgressToken};
+
+    fn params(stream: bool) -> WorkspaceDiagnosticParams {
+        WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: Vec::new(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: PartialResultParams {
+                partial_result_token: stream.then(|| ProgressToken::String("token".into())),
+            },
+        }
+    }
+
+    fn project_info() -> Value {
+        json!({
+            "body": {
+                "configFileName": "/workspace/tsconfig.json",
+                "fileNames": [
+                    "/workspace/node_modules/typescript/lib/lib.d.ts",
+                    "/workspace/src/a.ts",
+                    "/workspace/src/b.ts"
+                ]
+            }
+        })
+    }
+
+    fn diagnostics(text: &str) -> Value {
+        json!({
+            "body": [{
+                "start": { "line": 2, "offset": 1 },
+                "end": { "line": 2, "offset": 5 },
+                "text": text,
+                "category": "error",
+                "code": 2322
+            }]
+        })
+    }
+
+    fn run(spec: &RequestSpec, payload: &Value) -> AdapterResult {
+        (spec.on_response.expect("adapter"))(payload, spec.response_context.as_ref())
+            .expect("adapter succeeds")
+    }
+
+    fn continued(result: AdapterResult) -> RequestSpec {
+        match result {
+            AdapterResult::Continue(spec) => spec,
+            _ => panic!("expected chained request"),
+        }
+    }
+
+    fn spec(previous_result_ids: Vec<PreviousResultId>) -> RequestSpec {
+        let params = WorkspaceDiagnosticParams {
+            previous_result_ids,
+            ..params(false)
+        };
+        let versions = HashMap::from([("/workspace/src/a.ts".to_string(), 4)]);
+        handle(
+            params,
+            "/workspace/src/a.ts",
+            7,
+            versions,
+            DiagnosticsSettings::default(),
+        )
+    }
+
+    fn partial(result: AdapterResult) -> (Value, RequestSpec) {
+        match result {
+            AdapterResult::Partial { value, next } => (value, next),
+            _ => panic!("expected partial result"),
+        }
+    }
+
+    fn ready(result: AdapterResult) -> Value {
+        match result {
+            AdapterResult::Ready(value) => value,
+            _ => panic!("expected final result"),
+        }
+    }
+
+    #[test]
+    fn walk_reports_project_sources_with_result_ids() {
+        let spec = spec(Vec::new());
+        assert_eq!(spec.payload.get("command"), Some(&json!("projectInfo")));
+        assert!(matches!(spec.route, Route::SemanticPreferred));
+
+        let spec = continued(run(&spec, &project_info()));
+        assert_eq!(
+            spec.payload.pointer("/arguments/file"),
+            Some(&json!("/workspace/src/a.ts")),
+            "node_modules declarations are skipped"
+        );
+        assert!(matches!(spec.route, Route::SemanticPreferred));
+        let spec = continued(run(&spec, &json!({ "body": [] })));
+        let (first, spec) = partial(run(&spec, &diagnostics("a broke")));
+        assert_eq!(
+            spec.payload.pointer("/arguments/file"),
+            Some(&json!("/workspace/src/b.ts"))
+        );
+        assert!(
+            !spec
+                .response_context
+                .as_ref()
+                .unwrap()
+                .to_string()
+                .contains("a broke"),
+            "finished reports stay out of the chained context"
+        );
+        let spec = continued(run(&spec, &json!({ "body": [] })));
+        let last = ready(run(&spec, &json!({ "body": [] })));
+
+        let mut collector = ReportCollector::default();
+        collector.push(first);
+        let report: WorkspaceDiagnosticReport =
+            serde_json::from_value(collector.finish(last)).unwrap();
+        assert_eq!(report.items.len(), 2);
+        match &report.items[0] {
+            WorkspaceDocumentDiagnosticReport::Full(full) => {
+                assert!(full.uri.as_str().ends_with("/src/a.ts"));
+                assert_eq!(full.version, Some(4));
+                let report = &full.full_document_diagnostic_report;
+                assert_eq!(report.result_id.as_deref(), Some("7:4"));
+                assert_eq!(report.items.len(), 1);
+                assert_eq!(report.items[0].code, Some(NumberOrString::Number(2322)));
+            }
+            other => panic!("unexpected report: {other:?}"),
+        }
+        match &report.items[1] {
+            WorkspaceDocumentDiagnosticReport::Full(full) => {
+                assert_eq!(full.version, None);
+                assert_eq!(
+                    full.full_document_diagnostic_report.result_id.as_deref(),
+                    Some("7:-")
+                );
+            }
+            other => panic!("unexpected report: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn walk_skips_files_whose_result_id_still_matches() {
+        let previous = |file: &str, value: &str| PreviousResultId {
+            uri: file_path_to_uri(file).unwrap(),
+            value: value.to_string(),
+        };
+        let spec = spec(vec![
+            previous("/workspace/src/a.ts", "7:4"),
+            previous("/workspace/src/b.ts", "6:-"),
+        ]);
+
+        let (value, next) = partial(run(&spec, &project_info()));
+        let unchanged: WorkspaceDiagnosticReportPartialResult =
+            serde_json::from_value(value).unwrap();
+        assert_eq!(unchanged.items.len(), 1);
+        match &unchanged.items[0] {
+            WorkspaceDocumentDiagnosticReport::Unchanged(report) => {
+                assert!(report.uri.as_str().ends_with("/src/a.ts"));
+                assert_eq!(report.unchanged_document_diagnostic_report.result_id, "7:4");
+            }
+            other => panic!("unexpected report: {other:?}"),
+        }
+        assert_eq!(
+            next.payload.pointer("/arguments/file"),
+            Some(&json!("/workspace/src/b.ts")),
+            "a stale id is checked again"
+        );
+    }
+
+    #[test]
+    fn walk_streams_partial_results_when_token_present() {
+        let spec = handle(
+            params(true),
+            "/workspace/src/a.ts",
+            0,
+            HashMap::new(),
+            DiagnosticsSettings::default(),
+        );
+        let spec = continued(run(&spec, &project_info()));
+        let spec = continued(run(&spec, &json!({ "body": [] })));
+        let (value, next) = partial(run(&spec, &diagnostics("a broke")));
+        let partial: WorkspaceDiagnosticReportPartialResult =
+            serde_json::from_value(value).unwrap();
+        assert_eq!(partial.items.len(), 1);
+        assert_eq!(
+            next.payload.pointer("/arguments/file"),
+            Some(&json!("/workspace/src/b.ts"))
+        );
+
+        let spec = continued(run(&next, &json!({ "body": [] })));
+        let value = ready(run(&spec, &json!({ "body": [] })));
+        let last: WorkspaceDiagnosticReportPartialResult = serde_json::from_value(value).unwrap();
+        assert_eq!(last.items.len(), 1, "last file is returned in the response");
+    }
+}
@@ -0,0 +1,232 @@
+//! =============================================================================
+//! workspace/didChangeWatchedFiles
+//! =============================================================================
+//!
+//! Tells tsserver about edits made outside the editor.  Config changes
+//! (`tsconfig*.json`, `jsconfig*.json`, `package.json`) and created or deleted
+//! scripts can change project membership, so they trigger `reloadProjects`.
+//! Scripts changed on disk that the editor does not have open are opened and
+//! closed again in a single `updateOpen`, which makes tsserver re-read them;
+//! open documents are skipped because the editor buffer is authoritative.
+//! The session also drops the diagnostics of [`deleted`] paths.
+//!
+//! Clients only send these events for globs the server registers, so
+//! [`watcher_registration`] is sent via `client/registerCapability` right after
+//! `initialize` when the client supports dynamic registration.
+
+use std::path::Path;
+
+use lsp_types::{
+    ClientCapabilities, DidChangeWatchedFilesParams, DidChangeWatchedFilesRegistrationOptions,
+    FileChangeType, FileSystemWatcher, GlobPattern, Registration, RegistrationParams,
+    notification::{DidChangeWatchedFiles, Notification},
+};
+use serde_json::json;
+
+use crate::protocol::NotificationSpec;
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+pub(crate) const SCRIPT_EXTENSIONS: &[&str] =
+    &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+const WATCHER_REGISTRATION_ID: &str = "ts-bridge-watched-files";
+const WATCHED_GLOBS: &[&str] = &["**/tsconfig*.json", "**/jsconfig.json", "**/package.json"];
+
+pub fn supports_dynamic_registration(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+        .and_then(|watched| watched.dynamic_registration)
+        .unwrap_or(false)
+}
+
+/// `client/registerCapability` params watching the project config files.
+pub fn watcher_registration() -> RegistrationParams {
+    let watchers = WATCHED_GLOBS
+        .iter()
+        .map(|glob| FileSystemWatcher {
+            glob_pattern: GlobPattern::String(glob.to_string()),
+            kind: None,
+        })
+        .collect();
+    RegistrationParams {
+        registrations: vec![Registration {
+            id: WATCHER_REGISTRATION_ID.to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        }],
+    }
+}
+
+/// `is_open` reports whether the editor currently owns the given URI.
+pub fn handle(
+    params: DidChangeWatchedFilesParams,
+    workspace_root: &Path,
+    is_open: impl Fn(&str) -> bool,
+) -> Vec<NotificationSpec> {
+    let mut reload = false;
+    let mut refreshed = Vec::new();
+    for change in &params.changes {
+        let uri = change.uri.as_str();
+        let file = uri_to_file_path(uri).unwrap_or_else(|| uri.to_string());
+        if is_project_config(&file) {
+            reload = true;
+            continue;
+        }
+        if !is_script(&file) {
+            continue;
+        }
+        if change.typ == FileChangeType::CHANGED {
+            if !is_open(uri) && !refreshed.contains(&file) {
+                refreshed.push(file);
+            }
+        } else {
+            reload = true;
+        }
+    }
+
+    if reload {
+        return vec![NotificationSpec {
+            route: Route::Both,
+            payload: json!({ "command": "reloadProjects" }),
+            priority: Priority::Normal,
+        }];
+    }
+    if refreshed.is_empty() {
+        return Vec::new();
+    }
+    let open_files: Vec<_> = refreshed
+        .iter()
+        .map(|file| json!({ "file": file, "projectRootPath": workspace_root.to_string_lossy() }))
+        .collect();
+    vec![NotificationSpec {
+        route: Route::Both,
+        payload: json!({
+            "command": "updateOpen",
+            "arguments": {
+                "openFiles": open_files,
+                "changedFiles": [],
+                "closedFiles": refreshed,
+            }
+        }),
+        priority: Priority::Const,
+    }]
+}
+
+/// URIs reported as deleted; folders included, since a watcher may report
+/// only the directory.
+pub fn deleted(params: &DidChangeWatchedFilesParams) -> Vec<String> {
+    params
+        .changes
+        .iter()
+        .filter(|change| change.typ == FileChangeType::DELETED)
+        .map(|change| change.uri.to_string())
+        .collect()
+}
+
+pub fn is_project_config(file: &str) -> bool {
+    let name = Path::new(file)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    name == "package.json"
+        || ((name.starts_with("tsconfig") || name.starts_with("jsconfig"))
+            && name.ends_with(".json"))
+}
+
+fn is_script(file: &str) -> bool {
+    Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{FileEvent, Uri};
+    use std::str::FromStr;
+
+    fn params(changes: &[(&str, FileChangeType)]) -> DidChangeWatchedFilesParams {
+        DidChangeWatchedFilesParams {
+            changes: changes
+                .iter()
+                .map(|(uri, typ)| FileEvent {
+                    uri: Uri::from_str(uri).unwrap(),
+                    typ: *typ,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn config_change_reloads_projects() {
+        let specs = handle(
+            params(&[
+                ("file:///workspace/tsconfig.json", FileChangeType::CHANGED),
+                ("file:///workspace/src/a.ts", FileChangeType::CHANGED),
+            ]),
+            Path::new("/workspace"),
+            |_| false,
+        );
+        assert_eq!(specs.len(), 1);
+        assert_eq!(
+            specs[0].payload.get("command"),
+            Some(&json!("reloadProjects"))
+        );
+    }
+
+    #[test]
+    fn changed_scripts_are_refreshed_unless_open() {
+        let specs = handle(
+            params(&[
+                ("file:///workspace/src/a.ts", FileChangeType::CHANGED),
+                ("file:///workspace/src/b.ts", FileChangeType::CHANGED),
+                ("file:///workspace/README.md", FileChangeType::CHANGED),
+            ]),
+            Path::new("/workspace"),
+            |uri| uri.ends_with("/b.ts"),
+        );
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].payload.get("command"), Some(&json!("updateOpen")));
+        assert_eq!(
+            specs[0].payload.pointer("/arguments/closedFiles"),
+            Some(&json!(["/workspace/src/a.ts"]))
+        );
+    }
+
+    #[test]
+    fn deleted_lists_only_deletions() {
+        let params = params(&[
+            ("file:///workspace/src/a.ts", FileChangeType::DELETED),
+            ("file:///workspace/src/b.ts", FileChangeType::CREATED),
+            ("file:///workspace/lib", FileChangeType::DELETED),
+        ]);
+        assert_eq!(
+            deleted(&params),
+            vec!["file:///workspace/src/a.ts", "file:///workspace/lib"]
+        );
+    }
+
+    #[test]
+    fn registration_watches_project_configs() {
+        let params = watcher_registration();
+        let registration = &params.registrations[0];
+        assert_eq!(registration.method, "workspace/didChangeWatchedFiles");
+        let globs: Vec<_> = registration
+            .register_options
+            .as_ref()
+            .and_then(|options| options.get("watchers"))
+            .and_then(|watchers| watchers.as_array())
+            .expect("watchers")
+            .iter()
+            .filter_map(|watcher| watcher.get("globPattern").and_then(|v| v.as_str()))
+            .collect();
+        assert_eq!(globs, WATCHED_GLOBS);
+    }
+}
@@ -17,8 +17,14 @@ use lsp_types::{
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-use crate::protocol::text_document::code_action::workspace_edit_from_tsserver_changes;
+use crate::protocol::text_document::code_action::{
+    MOVE_TO_FILE_COMMAND, RefactorData, workspace_edit_from_tsserver_changes,
+};
+use crate::protocol::text_document::code_action_resolve::{
+    refactor_edits_payload, refactor_workspace_edit,
+};
 use crate::protocol::text_document::definition::{self, DefinitionContext, DefinitionParams};
+use crate::protocol::text_document::paste_edits::{self, PasteEditsParams};
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
 use crate::utils::{tsserver_span_to_location, uri_to_file_path};
@@ -29,12 +35,15 @@ const ORGANIZE_MODE_REMOVE_UNUSED: &str = "RemoveUnused";
 
 const FIX_UNUSED_IDENTIFIER: &str = "unusedIdentifier_delete";
 const FIX_MISSING_IMPORT: &str = "fixMissingImport";
-const FIX_ALL_CHAIN: &[&str] = &[
+pub(crate) const FIX_ALL_CHAIN: &[&str] = &[
     "fixClassIncorrectlyImplementsInterface",
     "fixAwaitInSyncFunction",
     "fixUnreachableCode",
 ];
 
+/// Dropped from the advertised commands on TypeScript older than 4.7.
+pub const SOURCE_DEFINITION_COMMAND: &str = "TSBGoToSourceDefinition";
+
 pub const USER_COMMANDS: &[&str] = &[
     "TSBOrganizeImports",
     "TSBSortImports",
@@ -42,10 +51,12 @@ pub const USER_COMMANDS: &[&str] = &[
     "TSBRemoveUnused",
     "TSBAddMissingImports",
     "TSBFixAll",
-    "TSBGoToSourceDefinition",
+    SOURCE_DEFINITION_COMMAND,
     "TSBRenameFile",
     "TSBFileReferences",
     "TSBRestartProject",
+    MOVE_TO_FILE_COMMAND,
+    paste_edits::COMMAND,
 ];
 
 pub fn handle(params: ExecuteCommandParams) -> Option<RequestSpec> {
@@ -57,13 +68,21 @@ pub fn handle(params: ExecuteCommandParams) -> Option<RequestSpec> {
         "TSBRemoveUnused" => combined_code_fix_command(&args, FIX_UNUSED_IDENTIFIER),
         "TSBAddMissingImports" => combined_code_fix_command(&args, FIX_MISSING_IMPORT),
         "TSBFixAll" => fix_all_command(&args),
-        "TSBGoToSourceDefinition" => goto_source_definition_command(&args),
+        SOURCE_DEFINITION_COMMAND => goto_source_definition_command(&args),
         "TSBRenameFile" => rename_file_command(&args),
         "TSBFileReferences" => file_references_command(&args),
+        MOVE_TO_FILE_COMMAND => move_to_file_command(&args),
+        paste_edits::COMMAND => paste_edits_command(&args),
         _ => None,
     }
 }
 
+/// `TSBPasteEdits <PasteEditsParams>`; same result as `ts-bridge/pasteEdits`.
+fn paste_edits_command(args: &[Value]) -> Option<RequestSpec> {
+    let params: PasteEditsParams = serde_json::from_value(args.first()?.clone()).ok()?;
+    Some(paste_edits::handle(params))
+}
+
 fn organize_imports_command(args: &[Value], mode: &str) -> Option<RequestSpec> {
     let target = parse_file_target(args)?;
     let request = json!({
@@ -159,7 +178,7 @@ fn file_references_command(args: &[Value]) -> Option<RequestSpec> {
     });
 
     Some(RequestSpec {
-        route: Route::Syntax,
+        route: Route::SemanticPreferred,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_file_references),
@@ -167,6 +186,26 @@ fn file_references_command(args: &[Value]) -> Option<RequestSpec> {
     })
 }
 
+/// Arguments are the `RefactorData` attached to the "Move to file" code action
+/// plus the destination, either as `targetFile` inside the data or as a second
+/// argument holding a path or `file://` URI.
+fn move_to_file_command(args: &[Value]) -> Option<RequestSpec> {
+    let mut data: RefactorData = serde_json::from_value(args.first()?.clone()).ok()?;
+    if let Some(target) = args.get(1).and_then(|value| value.as_str()) {
+        data.target_file = Some(target.to_string());
+    }
+    let target = data.target_file.take()?;
+    data.target_file = Some(uri_to_file_path(&target).unwrap_or(target));
+
+    Some(RequestSpec {
+        route: Route::Syntax,
+        payload: refactor_edits_payload(&data),
+        priority: Priority::Normal,
+        on_response: Some(adapt_move_to_file),
+        response_context: None,
+    })
+}
+
 fn combined_code_fix_payload(file: &str, fix_id: &str) -> Value {
     json!({
         "command": "getCombinedCodeFix",
@@ -204,6 +243,17 @@ fn adapt_combined_code_fix(payload: &Value, _context: Option<&Value>) -> Result<
     Ok(AdapterResult::ready(serde_json::to_value(edit)?))
 }
 
+fn adapt_move_to_file(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
+    let edits = payload
+        .get("body")
+        .and_then(|body| body.get("edits"))
+        .and_then(|value| value.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let edit = refactor_workspace_edit(&edits)?.unwrap_or_else(empty_workspace_edit);
+    Ok(AdapterResult::ready(serde_json::to_value(edit)?))
+}
+
 fn adapt_file_references(payload: &Value, _context: Option<&Value>) -> Result<AdapterResult> {
     let refs = payload
         .get("body")
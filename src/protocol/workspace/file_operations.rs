@@ -0,0 +1,146 @@
+//! =============================================================================
+//! workspace/didRenameFiles + workspace/didDeleteFiles
+//! =============================================================================
+//!
+//! Once files are gone from their old location, tsserver must stop treating
+//! them as open buffers.  The old paths are closed via `updateOpen`, and
+//! `reloadProjects` follows when a project boundary may have moved (a
+//! `tsconfig.json`/`jsconfig.json`/`package.json`, or a whole folder).
+
+use std::path::Path;
+
+use lsp_types::{
+    DeleteFilesParams, FileOperationFilter, FileOperationPattern, FileOperationPatternKind,
+    FileOperationRegistrationOptions, RenameFilesParams,
+};
+use serde_json::json;
+
+use crate::protocol::NotificationSpec;
+use crate::protocol::workspace::did_change_watched_files::{SCRIPT_EXTENSIONS, is_project_config};
+use crate::protocol::workspace::rename::file_operation_filters;
+use crate::rpc::{Priority, Route};
+use crate::utils::uri_to_file_path;
+
+/// The `willRenameFiles` filters plus the project config files, whose removal
+/// should trigger `reloadProjects`.
+pub fn cleanup_filters(extra_extensions: &[&str]) -> FileOperationRegistrationOptions {
+    let mut options = file_operation_filters(extra_extensions);
+    options.filters.push(FileOperationFilter {
+        scheme: Some("file".to_string()),
+        pattern: FileOperationPattern {
+            glob: "**/{tsconfig*,jsconfig*,package}.json".to_string(),
+            matches: Some(FileOperationPatternKind::File),
+            options: None,
+        },
+    });
+    options
+}
+
+/// URIs that no longer exist after a rename.
+pub fn renamed_away(params: &RenameFilesParams) -> Vec<String> {
+    params
+        .files
+        .iter()
+        .map(|rename| rename.old_uri.clone())
+        .collect()
+}
+
+/// URIs that no longer exist after a delete.
+pub fn deleted(params: &DeleteFilesParams) -> Vec<String> {
+    params.files.iter().map(|file| file.uri.clone()).collect()
+}
+
+/// Builds the tsserver notifications for the removed URIs: `updateOpen` with
+/// every old path closed, then `reloadProjects` if needed.
+pub fn handle(removed: &[String], workspace_root: &Path) -> Vec<NotificationSpec> {
+    let files: Vec<String> = removed
+        .iter()
+        .map(|uri| uri_to_file_path(uri).unwrap_or_else(|| uri.clone()))
+        .collect();
+    if files.is_empty() {
+        return Vec::new();
+    }
+
+    let mut specs = vec![NotificationSpec {
+        route: Route::Both,
+        payload: json!({
+            "command": "updateOpen",
+            "arguments": {
+                "projectRootPath": workspace_root.to_string_lossy(),
+                "openFiles": [],
+                "changedFiles": [],
+                "closedFiles": files,
+            }
+        }),
+        priority: Priority::Const,
+    }];
+    if files.iter().any(|file| affects_projects(file)) {
+        specs.push(NotificationSpec {
+            route: Route::Both,
+            payload: json!({ "command": "reloadProjects" }),
+            priority: Priority::Normal,
+        });
+    }
+    specs
+}
+
+/// Whether `uri` is `removed` itself or lives inside it (folder operations).
+pub fn is_affected(uri: &str, removed: &str) -> bool {
+    uri == removed
+        || uri
+            .strip_prefix(removed.trim_end_matches('/'))
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Config files change which project owns a file; a path without a script
+/// extension is most likely a folder, which can hold either.
+fn affects_projects(file: &str) -> bool {
+    if is_project_config(file) {
+        return true;
+    }
+    !Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext) || ext == "json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_removal_only_closes_old_paths() {
+        let specs = handle(
+            &["file:///workspace/src/a.ts".to_string()],
+            Path::new("/workspace"),
+        );
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].route, Route::Both);
+        assert_eq!(
+            specs[0].payload.pointer("/arguments/closedFiles"),
+            Some(&json!(["/workspace/src/a.ts"]))
+        );
+    }
+
+    #[test]
+    fn config_or_folder_removal_reloads_projects() {
+        for uri in [
+            "file:///workspace/tsconfig.build.json",
+            "file:///workspace/packages/app",
+        ] {
+            let specs = handle(&[uri.to_string()], Path::new("/workspace"));
+            assert_eq!(
+                specs.last().and_then(|spec| spec.payload.get("command")),
+                Some(&json!("reloadProjects")),
+                "{uri} should reload projects"
+            );
+        }
+    }
+
+    #[test]
+    fn is_affected_matches_folder_children() {
+        assert!(is_affected("file:///w/src/a.ts", "file:///w/src"));
+        assert!(is_affected("file:///w/src/a.ts", "file:///w/src/a.ts"));
+        assert!(!is_affected("file:///w/srcs/a.ts", "file:///w/src"));
+    }
+}
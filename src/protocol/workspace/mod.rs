@@ -2,6 +2,9 @@
 //! workspace/* Handlers
 //! =============================================================================
 
+pub mod diagnostic;
+pub mod did_change_watched_files;
 pub mod execute_command;
+pub mod file_operations;
 pub mod rename;
 pub mod symbol;
@@ -3,20 +3,52 @@
 //! =============================================================================
 //!
 //! Chains tsserver `getEditsForFileRename` requests for each file rename so
-//! LSP clients can preview edits before they rename files on disk.
+//! LSP clients can preview edits before they rename files on disk.  The edits
+//! of every rename are merged into one `WorkspaceEdit` that rewrites the
+//! affected import specifiers.
 
 use std::collections::{HashMap, VecDeque};
 
 use anyhow::{Context, Result};
-use lsp_types::{FileRename, RenameFilesParams, WorkspaceEdit};
+use lsp_types::{
+    FileOperationFilter, FileOperationPattern, FileOperationPatternKind,
+    FileOperationRegistrationOptions, FileRename, RenameFilesParams, WorkspaceEdit,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::protocol::text_document::code_action::workspace_edit_from_tsserver_changes;
+use crate::protocol::workspace::did_change_watched_files::SCRIPT_EXTENSIONS;
 use crate::protocol::{AdapterResult, RequestSpec};
 use crate::rpc::{Priority, Route};
 use crate::utils::uri_to_file_path;
 
+/// Filters for the file operation capabilities: script files (plus the
+/// `extra_extensions` tsserver plugins handle) and folders, since moving a
+/// directory changes the specifiers of everything inside it.
+pub fn file_operation_filters(extra_extensions: &[&str]) -> FileOperationRegistrationOptions {
+    let extensions: Vec<&str> = SCRIPT_EXTENSIONS
+        .iter()
+        .copied()
+        .chain(extra_extensions.iter().copied())
+        .collect();
+    let source_file_glob = format!("**/*.{{{}}}", extensions.join(","));
+    let filter = |glob: &str, kind| FileOperationFilter {
+        scheme: Some("file".to_string()),
+        pattern: FileOperationPattern {
+            glob: glob.to_string(),
+            matches: Some(kind),
+            options: None,
+        },
+    };
+    FileOperationRegistrationOptions {
+        filters: vec![
+            filter(&source_file_glob, FileOperationPatternKind::File),
+            filter("**/*", FileOperationPatternKind::Folder),
+        ],
+    }
+}
+
 pub fn handle(params: RenameFilesParams) -> Option<RequestSpec> {
     let mut queue: VecDeque<FileRenameSpec> =
         params.files.into_iter().map(FileRenameSpec::from).collect();
@@ -121,3 +153,77 @@ impl From<FileRename> for FileRenameSpec {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename(old: &str, new: &str) -> FileRename {
+        FileRename {
+            old_uri: old.to_string(),
+            new_uri: new.to_string(),
+        }
+    }
+
+    fn import_edit(file: &str, text: &str) -> Value {
+        json!({
+            "body": [{
+                "fileName": file,
+                "textChanges": [{
+                    "start": { "line": 1, "offset": 20 },
+                    "end": { "line": 1, "offset": 27 },
+                    "newText": text
+                }]
+            }]
+        })
+    }
+
+    #[test]
+    fn renames_chain_into_single_workspace_edit() {
+        let spec = handle(RenameFilesParams {
+            files: vec![
+                rename("file:///workspace/a.ts", "file:///workspace/lib/a.ts"),
+                rename("file:///workspace/b.ts", "file:///workspace/lib/b.ts"),
+            ],
+        })
+        .expect("spec");
+        assert_eq!(
+            spec.payload.pointer("/arguments/oldFilePath"),
+            Some(&json!("/workspace/a.ts"))
+        );
+
+        let next = match (spec.on_response.unwrap())(
+            &import_edit("/workspace/main.ts", "./lib/a"),
+            spec.response_context.as_ref(),
+        )
+        .unwrap()
+        {
+            AdapterResult::Continue(next) => next,
+            _ => panic!("expected second rename request"),
+        };
+        assert_eq!(
+            next.payload.pointer("/arguments/newFilePath"),
+            Some(&json!("/workspace/lib/b.ts"))
+        );
+
+        let value = match (next.on_response.unwrap())(
+            &import_edit("/workspace/main.ts", "./lib/b"),
+            next.response_context.as_ref(),
+        )
+        .unwrap()
+        {
+            AdapterResult::Ready(value) => value,
+            _ => panic!("expected merged edit"),
+        };
+        let edit: WorkspaceEdit = serde_json::from_value(value).unwrap();
+        let (uri, edits) = edit
+            .changes
+            .expect("changes")
+            .into_iter()
+            .next()
+            .expect("main.ts edits");
+        assert_eq!(uri.as_str(), "file:///workspace/main.ts");
+        assert_eq!(edits.len(), 2);
+    }
+}
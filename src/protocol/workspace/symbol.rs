@@ -1,3 +1,10 @@
+//! =============================================================================
+//! workspace/symbol
+//! =============================================================================
+//!
+//! Maps the workspace symbol query onto tsserver’s `navto` command and turns
+//! each `NavtoItem` into a flat symbol entry (kind, container name, location).
+
 use anyhow::Result;
 use lsp_types::{Location, SymbolKind, SymbolTag, WorkspaceSymbolParams};
 use serde::Serialize;
@@ -19,7 +26,7 @@ pub fn handle(params: WorkspaceSymbolParams) -> RequestSpec {
     });
 
     RequestSpec {
-        route: Route::Syntax,
+        route: Route::SemanticPreferred,
         payload: request,
         priority: Priority::Normal,
         on_response: Some(adapt_workspace_symbols),
@@ -83,16 +90,19 @@ fn workspace_symbol_tags(modifiers: &str) -> Option<Vec<SymbolTag>> {
 
 fn document_symbol_kind(kind: &str) -> SymbolKind {
     match kind {
-        "class" => SymbolKind::CLASS,
+        "class" | "local class" => SymbolKind::CLASS,
         "interface" => SymbolKind::INTERFACE,
         "enum" => SymbolKind::ENUM,
+        "enum member" => SymbolKind::ENUM_MEMBER,
         "method" => SymbolKind::METHOD,
-        "function" => SymbolKind::FUNCTION,
+        "function" | "local function" => SymbolKind::FUNCTION,
+        "constructor" => SymbolKind::CONSTRUCTOR,
         "member" | "property" | "getter" | "setter" => SymbolKind::PROPERTY,
-        "var" | "let" | "const" => SymbolKind::VARIABLE,
-        "module" => SymbolKind::MODULE,
+        "var" | "let" | "local var" | "alias" => SymbolKind::VARIABLE,
+        "const" => SymbolKind::CONSTANT,
+        "module" | "script" | "external module name" => SymbolKind::MODULE,
         "namespace" => SymbolKind::NAMESPACE,
-        "type" => SymbolKind::TYPE_PARAMETER,
+        "type" | "type parameter" => SymbolKind::TYPE_PARAMETER,
         _ => SymbolKind::VARIABLE,
     }
 }
@@ -108,3 +118,75 @@ struct WorkspaceSymbol {
     #[serde(skip_serializing_if = "Option::is_none")]
     container_name: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_forwards_query_to_navto() {
+        let params = WorkspaceSymbolParams {
+            query: "Widget".to_string(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let spec = handle(params);
+        assert_eq!(spec.route, Route::SemanticPreferred);
+        assert_eq!(spec.payload.get("command"), Some(&json!("navto")));
+        assert_eq!(
+            spec.payload
+                .get("arguments")
+                .and_then(|args| args.get("searchValue"))
+                .and_then(|value| value.as_str()),
+            Some("Widget")
+        );
+    }
+
+    #[test]
+    fn adapt_workspace_symbols_maps_kind_container_and_tags() {
+        let payload = json!({
+            "body": [{
+                "name": "Widget",
+                "kind": "class",
+                "kindModifiers": "export,deprecated",
+                "containerName": "ui",
+                "textSpan": {
+                    "file": "/workspace/widget.ts",
+                    "start": { "line": 3, "offset": 14 },
+                    "end": { "line": 3, "offset": 20 }
+                }
+            }, {
+                "name": "MAX",
+                "kind": "const",
+                "textSpan": {
+                    "file": "/workspace/limits.ts",
+                    "start": { "line": 1, "offset": 14 },
+                    "end": { "line": 1, "offset": 17 }
+                }
+            }]
+        });
+
+        let adapted = adapt_workspace_symbols(&payload, None).expect("symbols adapt");
+        let value = match adapted {
+            AdapterResult::Ready(value) => value,
+            AdapterResult::Continue(_) | AdapterResult::Partial { .. } => {
+                panic!("expected ready workspace symbols")
+            }
+        };
+        let symbols: Vec<lsp_types::SymbolInformation> =
+            serde_json::from_value(value).expect("symbols deserialize");
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].name, "Widget");
+        assert_eq!(symbols[0].kind, SymbolKind::CLASS);
+        assert_eq!(symbols[0].container_name.as_deref(), Some("ui"));
+        assert_eq!(symbols[0].tags, Some(vec![SymbolTag::DEPRECATED]));
+        assert_eq!(
+            symbols[0].location.uri.to_string(),
+            "file:///workspace/widget.ts"
+        );
+        assert_eq!(symbols[0].location.range.start.line, 2);
+        assert_eq!(symbols[1].kind, SymbolKind::CONSTANT);
+        assert!(symbols[1].container_name.is_none());
+    }
+}
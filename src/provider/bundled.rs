@@ -0,0 +1,296 @@
+//! Fallback TypeScript for folders without a local install. It is installed
+//! with npm into the ts-bridge cache directory the first time it is needed.
+//!
+//! npm writes into a staging directory that is renamed into place once the
+//! install succeeded, and a lock file keeps concurrent ts-bridge processes
+//! from installing at the same time, so a half-written install is never
+//! picked up.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{BinarySource, ProviderError, TsserverBinary};
+
+/// TypeScript version installed as the fallback.
+pub const BUNDLED_TYPESCRIPT_VERSION: &str = "5.6.3";
+/// Overrides where the fallback is cached.
+pub const CACHE_DIR_ENV: &str = "TS_BRIDGE_CACHE_DIR";
+/// How long `npm install` (or waiting for another process's install) may take.
+const INSTALL_TIMEOUT: Duration = Duration::from_secs(300);
+/// A lock this old belongs to a process that died mid-install, since a live
+/// one kills npm after [`INSTALL_TIMEOUT`].
+const STALE_LOCK_AGE: Duration = Duration::from_secs(330);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `npm` is only called when the install is missing.
+pub(super) fn resolve(
+    install: bool,
+    npm: impl FnOnce() -> Result<PathBuf, ProviderError>,
+) -> Result<TsserverBinary, ProviderError> {
+    let Some(root) = cache_root() else {
+        return Err(ProviderError::BundledInstall {
+            dir: PathBuf::new(),
+            reason: "no cache directory (set TS_BRIDGE_CACHE_DIR)".to_string(),
+        });
+    };
+    resolve_in(&root, install, npm, INSTALL_TIMEOUT)
+}
+
+fn resolve_in(
+    root: &Path,
+    install: bool,
+    npm: impl FnOnce() -> Result<PathBuf, ProviderError>,
+    timeout: Duration,
+) -> Result<TsserverBinary, ProviderError> {
+    let dir = install_dir(root);
+    let tsserver = tsserver_path(&dir);
+    if !tsserver.is_file() && install {
+        install_locked(root, &dir, npm, timeout)?;
+    }
+    let mut binary = TsserverBinary::new(tsserver, None, BinarySource::Bundled);
+    binary
+        .version
+        .get_or_insert_with(|| BUNDLED_TYPESCRIPT_VERSION.to_string());
+    Ok(binary)
+}
+
+fn install_locked(
+    root: &Path,
+    dir: &Path,
+    npm: impl FnOnce() -> Result<PathBuf, ProviderError>,
+    timeout: Duration,
+) -> Result<(), ProviderError> {
+    let failed = |reason: String| ProviderError::BundledInstall {
+        dir: dir.to_path_buf(),
+        reason,
+    };
+    fs::create_dir_all(root).map_err(|err| failed(err.to_string()))?;
+    let _lock =
+        InstallLock::acquire(&dir.with_extension("lock"), STALE_LOCK_AGE).map_err(failed)?;
+    // Another process may have finished the install while we waited.
+    if tsserver_path(dir).is_file() {
+        return Ok(());
+    }
+    let npm = npm()?;
+    let staging = tempfile::Builder::new()
+        .prefix(".typescript-")
+        .tempdir_in(root)
+        .map_err(|err| failed(err.to_string()))?;
+    log::info!("installing TypeScript {BUNDLED_TYPESCRIPT_VERSION} into {dir:?}");
+    run_npm_install(&npm, staging.path(), timeout).map_err(failed)?;
+    if !tsserver_path(staging.path()).is_file() {
+        return Err(failed(
+            "npm did not install typescript/lib/tsserver.js".to_string(),
+        ));
+    }
+    // Left over from an install that predates the staging directory.
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|err| failed(err.to_string()))?;
+    }
+    fs::rename(staging.path(), dir).map_err(|err| failed(err.to_string()))
+}
+
+fn run_npm_install(npm: &Path, prefix: &Path, timeout: Duration) -> Result<(), String> {
+    let mut stderr = tempfile::tempfile().map_err(|err| err.to_string())?;
+    let mut child = Command::new(npm)
+        .args([
+            "install",
+            "--no-save",
+            "--no-audit",
+            "--no-fund",
+            "--prefix",
+        ])
+        .arg(prefix)
+        .arg(format!("typescript@{BUNDLED_TYPESCRIPT_VERSION}"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(stderr.try_clone().map_err(|err| err.to_string())?)
+        .spawn()
+        .map_err(|err| format!("failed to run {npm:?}: {err}"))?;
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("npm did not finish within {}s", timeout.as_secs()));
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let mut output = String::new();
+    let _ = stderr.rewind();
+    let _ = stderr.read_to_string(&mut output);
+    Err(match output.trim() {
+        "" => format!("npm exited with {status}"),
+        output => output.to_string(),
+    })
+}
+
+/// A lock file next to the install directory, removed on drop.
+struct InstallLock(PathBuf);
+
+impl InstallLock {
+    /// Waits up to `wait` for another install to finish; waiting for
+    /// [`STALE_LOCK_AGE`] always outlasts a dead holder's lock.
+    fn acquire(path: &Path, wait: Duration) -> Result<Self, String> {
+        let deadline = Instant::now() + wait;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(_) => return Ok(Self(path.to_path_buf())),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale(path) {
+                        let _ = fs::remove_file(path);
+                        continue;
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(format!("another install still holds {path:?}"));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => return Err(format!("failed to create {path:?}: {err}")),
+            }
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn is_stale(lock: &Path) -> bool {
+    File::open(lock)
+        .and_then(|file| file.metadata())
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE)
+}
+
+fn cache_root() -> Option<PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    if let Some(dir) = var(CACHE_DIR_ENV) {
+        return Some(dir);
+    }
+    var("XDG_CACHE_HOME")
+        .or_else(|| var("LOCALAPPDATA"))
+        .or_else(|| var("HOME").map(|home| home.join(".cache")))
+        .map(|dir| dir.join("ts-bridge"))
+}
+
+fn install_dir(root: &Path) -> PathBuf {
+    root.join(format!("typescript-{BUNDLED_TYPESCRIPT_VERSION}"))
+}
+
+fn tsserver_path(dir: &Path) -> PathBuf {
+    dir.join("node_modules")
+        .join("typescript")
+        .join("lib")
+        .join("tsserver.js")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_reports_the_pinned_version_before_installing() {
+        let root = tempfile::tempdir().expect("cache root");
+        let binary = resolve_in(root.path(), false, no_npm, INSTALL_TIMEOUT).expect("preview");
+        assert!(matches!(binary.source, BinarySource::Bundled));
+        assert!(!binary.executable.exists());
+        assert_eq!(binary.version.as_deref(), Some(BUNDLED_TYPESCRIPT_VERSION));
+    }
+
+    #[test]
+    fn cached_install_is_reused_without_npm() {
+        let root = tempfile::tempdir().expect("cache root");
+        let dir = install_dir(root.path());
+        let tsserver = tsserver_path(&dir);
+        fs::create_dir_all(tsserver.parent().unwrap()).expect("create lib");
+        fs::write(&tsserver, "").expect("write tsserver");
+        fs::write(
+            dir.join("node_modules/typescript/package.json"),
+            r#"{ "version": "5.6.3-cached" }"#,
+        )
+        .expect("write package.json");
+
+        let binary = resolve_in(root.path(), true, no_npm, INSTALL_TIMEOUT).expect("cached");
+        assert_eq!(binary.executable, tsserver);
+        assert_eq!(binary.version.as_deref(), Some("5.6.3-cached"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn install_is_staged_and_renamed_into_place() {
+        let root = tempfile::tempdir().expect("cache root");
+        let npm = fake_npm(
+            root.path(),
+            r#"prefix="$6"; mkdir -p "$prefix/node_modules/typescript/lib" && touch "$prefix/node_modules/typescript/lib/tsserver.js""#,
+        );
+        let binary = resolve_in(root.path(), true, || Ok(npm), INSTALL_TIMEOUT).expect("install");
+        assert_eq!(binary.executable, tsserver_path(&install_dir(root.path())));
+        assert!(binary.executable.is_file());
+        let leftovers: Vec<_> = fs::read_dir(root.path())
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".typescript-") || name.ends_with(".lock"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_or_hung_installs_leave_nothing_behind() {
+        let root = tempfile::tempdir().expect("cache root");
+        let npm = fake_npm(root.path(), "echo 'registry unreachable' >&2; exit 1");
+        let err = resolve_in(root.path(), true, || Ok(npm), INSTALL_TIMEOUT).expect_err("fails");
+        assert!(err.to_string().contains("registry unreachable"), "{err}");
+        assert!(!install_dir(root.path()).exists());
+
+        let npm = fake_npm(root.path(), "sleep 5");
+        let timeout = Duration::from_millis(200);
+        let err = resolve_in(root.path(), true, || Ok(npm), timeout).expect_err("times out");
+        assert!(err.to_string().contains("did not finish"), "{err}");
+        assert!(!install_dir(root.path()).exists());
+    }
+
+    #[test]
+    fn held_lock_times_out() {
+        let root = tempfile::tempdir().expect("cache root");
+        let lock = root.path().join("install.lock");
+        let _held = InstallLock::acquire(&lock, POLL_INTERVAL).expect("first lock");
+        let err = InstallLock::acquire(&lock, Duration::from_millis(200)).err();
+        assert!(err.is_some_and(|err| err.contains("another install")));
+    }
+
+    fn no_npm() -> Result<PathBuf, ProviderError> {
+        panic!("npm must not run for a cached install")
+    }
+
+    #[cfg(unix)]
+    fn fake_npm(dir: &Path, script: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let npm = dir.join("fake-npm");
+        fs::write(&npm, format!("#!/bin/sh\n{script}\n")).expect("write npm");
+        fs::set_permissions(&npm, fs::Permissions::from_mode(0o755)).expect("chmod npm");
+        npm
+    }
+}
@@ -2,17 +2,30 @@
 //! Tsserver Provider
 //! =============================================================================
 //!
-//! Responsible for locating `tsserver.js` (local node_modules, Yarn SDK, and
-//! PATH/global fallbacks) and reporting metadata (TypeScript version,
-//! plugin probe location).
+//! Responsible for locating `tsserver.js` (an explicit override, a `tsdk`
+//! directory, local node_modules, the pnpm store, Yarn SDK/PnP, and
+//! PATH/global fallbacks) and reporting metadata (TypeScript version, plugin
+//! probe location).
 
+mod bundled;
+mod pnpm;
+pub use bundled::{BUNDLED_TYPESCRIPT_VERSION, CACHE_DIR_ENV};
+
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use serde_json::Value;
 
+use crate::config::TsserverLaunchOptions;
+use crate::process::{ProcessError, npm_beside, resolve_node};
+
 const MAX_NESTED_SEARCH_DEPTH: usize = 4;
 
+/// Environment fallback for the `tsserver.path` setting.
+pub const TSSERVER_PATH_ENV: &str = "TS_BRIDGE_TSSERVER_PATH";
+
 /// Captures everything needed to spawn a tsserver instance.
 #[derive(Debug, Clone)]
 pub struct TsserverBinary {
@@ -20,6 +33,8 @@ pub struct TsserverBinary {
     pub plugin_probe: Option<PathBuf>,
     pub version: Option<String>,
     pub source: BinarySource,
+    /// Extra node flags placed before the script (e.g. the Yarn PnP runtime).
+    pub node_args: Vec<OsString>,
 }
 
 impl TsserverBinary {
@@ -30,6 +45,7 @@ impl TsserverBinary {
             plugin_probe,
             version,
             source,
+            node_args: Vec::new(),
         }
     }
 }
@@ -38,7 +54,33 @@ impl TsserverBinary {
 pub enum BinarySource {
     LocalNodeModules,
     YarnSdk,
+    /// Found in pnpm's `node_modules/.pnpm` virtual store.
+    PnpmStore,
+    /// Resolved through the Yarn Plug'n'Play runtime (`.pnp.cjs`).
+    YarnPnp,
     GlobalPath,
+    /// `tsserver.path` or `TS_BRIDGE_TSSERVER_PATH`.
+    Explicit,
+    /// The `tsserver.tsdk` directory.
+    Tsdk,
+    /// The pinned TypeScript ts-bridge installs into its cache directory.
+    Bundled,
+}
+
+impl BinarySource {
+    /// Short description shown to users (e.g. in the initialize `serverInfo`).
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::LocalNodeModules => "node_modules",
+            Self::YarnSdk => "Yarn SDK",
+            Self::PnpmStore => "pnpm store",
+            Self::YarnPnp => "Yarn PnP",
+            Self::GlobalPath => "PATH",
+            Self::Explicit => "tsserver.path",
+            Self::Tsdk => "tsdk",
+            Self::Bundled => "bundled",
+        }
+    }
 }
 
 /// Caching the workspace root and lazily resolving
@@ -46,6 +88,9 @@ pub enum BinarySource {
 #[derive(Debug)]
 pub struct Provider {
     workspace_root: PathBuf,
+    /// Node that runs the Yarn PnP probe and the bundled TypeScript's npm,
+    /// resolved the first time it is needed.
+    node: Option<PathBuf>,
 }
 
 impl Provider {
@@ -56,14 +101,22 @@ impl Provider {
             .unwrap_or_else(|_| PathBuf::from("."));
         Self {
             workspace_root: root,
+            node: None,
         }
     }
 
     /// Resolves the tsserver binary by inspecting (in order):
     /// 1. `node_modules/typescript/lib/tsserver.js` in workspace ancestors.
-    /// 2. `.yarn/sdks/typescript/lib/tsserver.js` in ancestors.
-    /// 3. `tsserver` on PATH (via `which`).
+    /// 2. pnpm's virtual store, `node_modules/.pnpm/typescript@*/...`, in ancestors.
+    /// 3. `.yarn/sdks/typescript/lib/tsserver.js` in ancestors.
+    /// 4. Yarn PnP: `.pnp.cjs` in ancestors, asking its runtime for `typescript`.
+    /// 5. `tsserver` on PATH (via `which`).
     pub fn resolve(&mut self) -> Result<TsserverBinary, ProviderError> {
+        self.resolve_with(None)
+    }
+
+    /// [`Provider::resolve`] with the PnP probe run by the configured node.
+    fn resolve_with(&mut self, node_path: Option<&Path>) -> Result<TsserverBinary, ProviderError> {
         if let Some(path) = self.find_local_node_modules() {
             self.reanchor_workspace_root(&path);
             let plugin_probe = path
@@ -78,6 +131,20 @@ impl Provider {
             ));
         }
 
+        if let Some((project_root, path)) = self.find_pnpm_store() {
+            self.workspace_root = project_root.canonicalize().unwrap_or(project_root);
+            let plugin_probe = path
+                .parent()
+                .and_then(|lib| lib.parent())
+                .and_then(|ts| ts.parent())
+                .map(Path::to_path_buf);
+            return Ok(TsserverBinary::new(
+                path,
+                plugin_probe,
+                BinarySource::PnpmStore,
+            ));
+        }
+
         if let Some(path) = self.find_yarn_sdk() {
             self.reanchor_workspace_root(&path);
             let plugin_probe = path
@@ -92,6 +159,10 @@ impl Provider {
             ));
         }
 
+        if let Some(binary) = self.find_yarn_pnp(node_path)? {
+            return Ok(binary);
+        }
+
         if let Some(path) = self.find_global_tsserver()? {
             return Ok(TsserverBinary::new(path, None, BinarySource::GlobalPath));
         }
@@ -101,6 +172,86 @@ impl Provider {
         })
     }
 
+    /// Resolves tsserver for the given launch options:
+    /// `path` (or `TS_BRIDGE_TSSERVER_PATH`) is used verbatim, then a `tsdk`
+    /// directory, then the lookup in [`Provider::resolve`], and finally the
+    /// bundled TypeScript, installed on first use.
+    pub fn resolve_for(
+        &mut self,
+        launch: &TsserverLaunchOptions,
+    ) -> Result<TsserverBinary, ProviderError> {
+        self.lookup(launch, true)
+    }
+
+    /// Like [`Provider::resolve_for`] but never installs the bundled
+    /// TypeScript, so it is cheap enough to answer `initialize` with.
+    pub fn preview(
+        &mut self,
+        launch: &TsserverLaunchOptions,
+    ) -> Result<TsserverBinary, ProviderError> {
+        self.lookup(launch, false)
+    }
+
+    fn lookup(
+        &mut self,
+        launch: &TsserverLaunchOptions,
+        install: bool,
+    ) -> Result<TsserverBinary, ProviderError> {
+        let explicit = launch
+            .path
+            .clone()
+            .or_else(|| std::env::var_os(TSSERVER_PATH_ENV).map(PathBuf::from))
+            .filter(|path| !path.as_os_str().is_empty());
+        if let Some(path) = explicit {
+            if !path.is_file() {
+                return Err(ProviderError::ExplicitPathMissing { path });
+            }
+            let path = path.canonicalize().unwrap_or(path);
+            return Ok(TsserverBinary::new(path, None, BinarySource::Explicit));
+        }
+        if let Some(tsdk) = &launch.tsdk {
+            match self.find_tsdk(tsdk) {
+                Some(binary) => return Ok(binary),
+                None => log::warn!("tsdk {tsdk:?} has no tsserver.js; falling back to lookup"),
+            }
+        }
+        match self.resolve_with(launch.node_path.as_deref()) {
+            Err(ProviderError::NotFound { .. }) if !launch.disable_bundled_typescript => {
+                // npm ships next to node, and npm on PATH may belong to a
+                // different node than the one tsserver runs under.
+                bundled::resolve(install, || match &launch.npm_location {
+                    Some(npm) => Ok(PathBuf::from(npm)),
+                    None => Ok(npm_beside(&self.node(launch.node_path.as_deref())?)),
+                })
+            }
+            result => result,
+        }
+    }
+
+    fn node(&mut self, node_path: Option<&Path>) -> Result<PathBuf, ProviderError> {
+        if let Some(node) = &self.node {
+            return Ok(node.clone());
+        }
+        let node = resolve_node(node_path).map_err(ProviderError::Node)?;
+        Ok(self.node.insert(node).clone())
+    }
+
+    /// Looks for `tsserver.js` in a VS Code style `typescript.tsdk` directory
+    /// (`.../typescript/lib`); relative paths start at the workspace root.
+    pub fn find_tsdk(&self, tsdk: &Path) -> Option<TsserverBinary> {
+        let path = self.workspace_root.join(tsdk).join("tsserver.js");
+        if !path.is_file() {
+            return None;
+        }
+        let path = path.canonicalize().unwrap_or(path);
+        let plugin_probe = path
+            .parent()
+            .and_then(|lib| lib.parent())
+            .and_then(|ts| ts.parent())
+            .map(Path::to_path_buf);
+        Some(TsserverBinary::new(path, plugin_probe, BinarySource::Tsdk))
+    }
+
     fn find_local_node_modules(&self) -> Option<PathBuf> {
         find_upwards(
             &self.workspace_root,
@@ -115,6 +266,46 @@ impl Provider {
         })
     }
 
+    /// pnpm may leave `node_modules/typescript` out (or as a dangling link)
+    /// while the package itself sits in the virtual store. When several
+    /// versions are installed, the one the project's lockfile or
+    /// `package.json` asks for wins, and otherwise the newest.
+    fn find_pnpm_store(&self) -> Option<(PathBuf, PathBuf)> {
+        self.workspace_root.ancestors().find_map(|ancestor| {
+            let store = ancestor.join("node_modules").join(".pnpm");
+            let entries = fs::read_dir(&store).ok()?;
+            let installed: Vec<(Vec<u64>, PathBuf)> = entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    let version = name.strip_prefix("typescript@")?;
+                    let version = version.split('_').next().unwrap_or(version);
+                    let tsserver = entry.path().join("node_modules/typescript/lib/tsserver.js");
+                    tsserver.is_file().then(|| (version_key(version), tsserver))
+                })
+                .collect();
+            let newest = |range: Option<&pnpm::VersionRange>| {
+                installed
+                    .iter()
+                    .filter(|(version, _)| range.is_none_or(|range| range.matches(version)))
+                    .max_by(|a, b| a.0.cmp(&b.0))
+            };
+            let wanted = pnpm::wanted_typescript(ancestor, &self.workspace_root);
+            let chosen = match wanted.as_ref().and_then(|range| newest(Some(range))) {
+                Some(chosen) => chosen,
+                None => {
+                    if wanted.is_some() {
+                        log::warn!(
+                            "the pnpm store in {ancestor:?} lacks the TypeScript the project asks for; using the newest"
+                        );
+                    }
+                    newest(None)?
+                }
+            };
+            Some((ancestor.to_path_buf(), chosen.1.clone()))
+        })
+    }
+
     fn find_yarn_sdk(&self) -> Option<PathBuf> {
         find_upwards(
             &self.workspace_root,
@@ -129,6 +320,67 @@ impl Provider {
         })
     }
 
+    /// PnP installs keep packages in zip archives, so the path can only be
+    /// resolved (and later loaded) with the `.pnp.cjs` runtime preloaded.
+    /// tsserver is CommonJS, so the `.pnp.loader.mjs` ESM loader is not needed.
+    /// A project PnP cannot resolve typescript for falls through to the next
+    /// lookup; a node that cannot run the probe at all is an error.
+    fn find_yarn_pnp(
+        &mut self,
+        node_path: Option<&Path>,
+    ) -> Result<Option<TsserverBinary>, ProviderError> {
+        let Some(pnp) = find_upwards(&self.workspace_root, &[".pnp.cjs"]) else {
+            return Ok(None);
+        };
+        let Some(project_root) = pnp.parent().map(Path::to_path_buf) else {
+            return Ok(None);
+        };
+        let node = self.node(node_path)?;
+        let output = Command::new(&node)
+            .arg("--require")
+            .arg(&pnp)
+            .arg("-e")
+            .arg(PNP_PROBE)
+            .current_dir(&project_root)
+            .output();
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                log::warn!(
+                    "Yarn PnP could not resolve typescript from {project_root:?}: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                return Ok(None);
+            }
+            Err(err) => {
+                return Err(ProviderError::PnpProbe {
+                    node,
+                    reason: err.to_string(),
+                });
+            }
+        };
+        let Ok(probe) = serde_json::from_slice::<Value>(&output.stdout) else {
+            log::warn!("Yarn PnP probe in {project_root:?} printed no JSON");
+            return Ok(None);
+        };
+        let Some(executable) = probe.get("tsserver").and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let version = probe
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        self.workspace_root = project_root;
+        Ok(Some(TsserverBinary {
+            executable: PathBuf::from(executable),
+            plugin_probe: None,
+            version,
+            source: BinarySource::YarnPnp,
+            node_args: vec![OsString::from("--require"), pnp.into_os_string()],
+        }))
+    }
+
     fn find_global_tsserver(&self) -> Result<Option<PathBuf>, ProviderError> {
         match which::which("tsserver") {
             Ok(path) => {
@@ -173,8 +425,36 @@ pub enum ProviderError {
     NotFound { root: PathBuf },
     #[error("failed to invoke `which tsserver`: {0}")]
     PathLookup(which::Error),
+    #[error("configured tsserver {path:?} does not exist")]
+    ExplicitPathMissing { path: PathBuf },
+    #[error("failed to install the bundled TypeScript into {dir:?}: {reason}")]
+    BundledInstall { dir: PathBuf, reason: String },
+    #[error("{0}")]
+    Node(ProcessError),
+    #[error("failed to run {node:?} for the Yarn PnP probe: {reason}")]
+    PnpProbe { node: PathBuf, reason: String },
+}
+
+/// Directory of the nearest `tsconfig.json`/`jsconfig.json` above `file`
+/// that sits strictly inside `workspace_root`, i.e. the monorepo package the
+/// file belongs to.
+pub fn package_root(workspace_root: &Path, file: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(workspace_root) && *dir != workspace_root)
+        .find(|dir| dir.join("tsconfig.json").is_file() || dir.join("jsconfig.json").is_file())
+        .map(Path::to_path_buf)
 }
 
+/// Resolves typescript the way the project itself would (the root workspace
+/// is the issuer) and prints `{"tsserver": ..., "version": ...}`.
+const PNP_PROBE: &str = r#"
+const req = require("module").createRequire(require("path").join(process.cwd(), "package.json"));
+let version = null;
+try { version = req("typescript/package.json").version; } catch {}
+process.stdout.write(JSON.stringify({ tsserver: req.resolve("typescript/lib/tsserver.js"), version }));
+"#;
+
 fn transform_wrapper_to_js(wrapper: PathBuf) -> Option<PathBuf> {
     let mut candidate = wrapper.clone();
     candidate.pop(); // drop tsserver filename
@@ -204,6 +484,14 @@ fn find_upwards(start: &Path, segments: &[&str]) -> Option<PathBuf> {
     None
 }
 
+/// Numeric components of a version such as `5.4.0-beta`, for ordering.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
 fn infer_version(tsserver: &Path) -> Option<String> {
     let lib_dir = tsserver.parent()?;
     let ts_dir = lib_dir.parent()?;
@@ -0,0 +1,139 @@
+//! Which TypeScript in pnpm's virtual store belongs to the project: the
+//! version `pnpm-lock.yaml` installed for its package, or else the newest
+//! one its `package.json` range accepts.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::version_key;
+
+/// The TypeScript versions a package accepts, as `version_key`s: at least
+/// `min`, agreeing with it on the first `fixed` components.
+#[derive(Debug)]
+pub(super) struct VersionRange {
+    min: Vec<u64>,
+    fixed: usize,
+}
+
+impl VersionRange {
+    fn exact(version: &str) -> Self {
+        let min = version_key(version);
+        Self {
+            fixed: min.len(),
+            min,
+        }
+    }
+
+    /// `5.4.2`, `^5.4.2` or `~5.4.2`; other npm ranges are not understood.
+    fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if spec.contains(' ') {
+            return None;
+        }
+        let (fixed, version) = if let Some(version) = spec.strip_prefix('^') {
+            (Some(1), version)
+        } else if let Some(version) = spec.strip_prefix('~') {
+            (Some(2), version)
+        } else {
+            (None, spec.strip_prefix('=').unwrap_or(spec))
+        };
+        let range = Self::exact(version.strip_prefix('v').unwrap_or(version));
+        if range.min.is_empty() {
+            return None;
+        }
+        Some(match fixed {
+            Some(fixed) => Self {
+                fixed: fixed.min(range.fixed),
+                ..range
+            },
+            None => range,
+        })
+    }
+
+    pub(super) fn matches(&self, version: &[u64]) -> bool {
+        version >= self.min.as_slice() && version.get(..self.fixed) == self.min.get(..self.fixed)
+    }
+}
+
+/// The TypeScript `workspace_root`'s package wants from the store under
+/// `root`. Each package from `workspace_root` up to `root` is asked in turn,
+/// lockfile first.
+pub(super) fn wanted_typescript(root: &Path, workspace_root: &Path) -> Option<VersionRange> {
+    let lockfile = fs::read_to_string(root.join("pnpm-lock.yaml")).ok();
+    workspace_root
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .filter(|dir| dir.join("package.json").is_file())
+        .find_map(|dir| {
+            let importer = match dir.strip_prefix(root) {
+                Ok(path) if path.as_os_str().is_empty() => ".".to_string(),
+                Ok(path) => path.to_string_lossy().replace('\\', "/"),
+                Err(_) => return None,
+            };
+            lockfile
+                .as_deref()
+                .and_then(|lockfile| locked_typescript(lockfile, &importer))
+                .map(|version| VersionRange::exact(&version))
+                .or_else(|| declared_typescript(&dir.join("package.json")))
+        })
+}
+
+/// The version of `typescript` the lockfile resolved for `importer`. Reads
+/// only the `importers:` section, in both the inline (`typescript: 5.4.2`)
+/// and the `specifier:`/`version:` layouts pnpm writes.
+fn locked_typescript(lockfile: &str, importer: &str) -> Option<String> {
+    let mut in_importer = false;
+    let mut in_typescript = false;
+    for line in lockfile
+        .lines()
+        .skip_while(|line| line.trim_end() != "importers:")
+        .skip(1)
+    {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match line.len() - line.trim_start().len() {
+            0 => break,
+            2 => {
+                in_importer = text
+                    .strip_suffix(':')
+                    .is_some_and(|key| key.trim_matches(['\'', '"']) == importer);
+                in_typescript = false;
+            }
+            6 if in_importer => {
+                in_typescript = false;
+                if let Some(value) = text.strip_prefix("typescript:") {
+                    match value.trim() {
+                        "" => in_typescript = true,
+                        value => return Some(locked_version(value)),
+                    }
+                }
+            }
+            8 if in_typescript => {
+                if let Some(value) = text.strip_prefix("version:") {
+                    return Some(locked_version(value.trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `5.4.2(peer@1.0.0)` or `5.4.2_peer@1.0.0` -> `5.4.2`.
+fn locked_version(value: &str) -> String {
+    let value = value.trim_matches(['\'', '"']);
+    value.split(['(', '_']).next().unwrap_or(value).to_string()
+}
+
+fn declared_typescript(package_json: &Path) -> Option<VersionRange> {
+    let contents = fs::read_to_string(package_json).ok()?;
+    let json: Value = serde_json::from_str(&contents).ok()?;
+    ["dependencies", "devDependencies"]
+        .iter()
+        .find_map(|field| json.get(field)?.get("typescript")?.as_str())
+        .and_then(VersionRange::parse)
+}
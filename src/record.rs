@@ -0,0 +1,221 @@
+//! Session recordings for bug reports. `--record FILE` (or
+//! `TS_BRIDGE_RECORD=FILE`) writes every LSP message a client exchanges with
+//! ts-bridge and every message ts-bridge exchanges with tsserver to FILE, one
+//! JSON object per line:
+//!
+//! ```json
+//! {"ts_ms":12,"channel":"lsp","direction":"in","connection":1,"message":{...}}
+//! {"ts_ms":15,"channel":"tsserver","direction":"out","server":"syntax","pid":4242,"message":{...}}
+//! ```
+//!
+//! `direction` is seen from ts-bridge: `in` was received, `out` was sent.
+//! `ts_ms` counts from the start of the recording. `ts-bridge replay` drives
+//! the bridge with the client side of a recording; see [`crate::replay`].
+
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::Context;
+use lsp_server::{Connection, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::process::ServerKind;
+
+pub const RECORD_ENV: &str = "TS_BRIDGE_RECORD";
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+
+struct Recorder {
+    started: Instant,
+    file: Mutex<LineWriter<File>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Lsp,
+    Tsserver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub ts_ms: u64,
+    pub channel: Channel,
+    pub direction: Direction,
+    /// Numbers the LSP connections of a daemon in the order they arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub message: Value,
+}
+
+/// Starts recording to `path`, replacing what it held. `path` falls back to
+/// `TS_BRIDGE_RECORD`; without either nothing is recorded. Once recording,
+/// later calls do nothing: a process records to one file.
+pub fn init(path: Option<&Path>) {
+    let env_path = std::env::var_os(RECORD_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from);
+    let Some(path) = path.map(Path::to_path_buf).or(env_path) else {
+        return;
+    };
+    if is_enabled() {
+        return;
+    }
+    let file = match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(err) => {
+            log::warn!("cannot record to {}: {err}", path.display());
+            return;
+        }
+    };
+    let recorder = Recorder {
+        started: Instant::now(),
+        file: Mutex::new(LineWriter::new(file)),
+    };
+    if RECORDER.set(recorder).is_ok() {
+        log::info!("recording LSP and tsserver traffic to {}", path.display());
+    }
+}
+
+/// Whether a recording is being written.
+pub fn is_enabled() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Returns a connection that records what passes through `connection`, or
+/// `connection` itself when nothing is being recorded.
+pub(crate) fn connection(connection: Connection) -> Connection {
+    if !is_enabled() {
+        return connection;
+    }
+    let id = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    let (inbound_tx, inbound_rx) = crossbeam_channel::unbounded();
+    let (outbound_tx, outbound_rx) = crossbeam_channel::unbounded::<Message>();
+    let Connection { sender, receiver } = connection;
+    // Each relay ends when its source closes, which closes the other side in
+    // turn, so the session and the IO threads shut down as they would without it.
+    thread::spawn(move || {
+        for message in receiver {
+            lsp(id, Direction::In, &message);
+            if inbound_tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    thread::spawn(move || {
+        for message in outbound_rx {
+            lsp(id, Direction::Out, &message);
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    Connection {
+        sender: outbound_tx,
+        receiver: inbound_rx,
+    }
+}
+
+fn lsp(connection: u64, direction: Direction, message: &Message) {
+    write(|| Entry {
+        ts_ms: 0,
+        channel: Channel::Lsp,
+        direction,
+        connection: Some(connection),
+        server: None,
+        pid: None,
+        message: serde_json::to_value(message).unwrap_or(Value::Null),
+    });
+}
+
+/// Records a message written to or read from a tsserver process.
+pub(crate) fn tsserver(kind: ServerKind, pid: u32, direction: Direction, message: &Value) {
+    write(|| Entry {
+        ts_ms: 0,
+        channel: Channel::Tsserver,
+        direction,
+        connection: None,
+        server: Some(kind.as_str().to_string()),
+        pid: Some(pid),
+        message: message.clone(),
+    });
+}
+
+fn write(entry: impl FnOnce() -> Entry) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+    let mut entry = entry();
+    entry.ts_ms = recorder.started.elapsed().as_millis() as u64;
+    let Ok(mut line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    line.push('\n');
+    let mut file = recorder
+        .file
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Err(err) = file.write_all(line.as_bytes()) {
+        log::debug!("failed to write the recording: {err}");
+    }
+}
+
+/// Reads a recording back.
+pub fn read(path: &Path) -> anyhow::Result<Vec<Entry>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: not a recording entry", path.display(), index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn entries_round_trip_through_json() {
+        let line = json!({
+            "ts_ms": 7,
+            "channel": "tsserver",
+            "direction": "out",
+            "server": "syntax",
+            "pid": 42,
+            "message": { "seq": 1, "command": "configure" },
+        });
+        let entry: Entry = serde_json::from_value(line.clone()).unwrap();
+        assert_eq!(entry.channel, Channel::Tsserver);
+        assert_eq!(entry.direction, Direction::Out);
+        assert_eq!(entry.connection, None);
+        assert_eq!(serde_json::to_value(&entry).unwrap(), line);
+    }
+}
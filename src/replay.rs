@@ -0,0 +1,464 @@
+//! `ts-bridge replay FILE`: plays the client side of a recording (see
+//! [`crate::record`]) into a fresh in-process session backed by real tsserver
+//! processes. Requests ts-bridge sends to the client are answered with what
+//! the recorded client answered. Each response is timed and compared with
+//! the recorded one, so a bug report can be reproduced and a change measured
+//! against the same traffic. The workspace has to be at the recorded paths.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, anyhow, bail};
+use crossbeam_channel::RecvTimeoutError;
+use lsp_server::{Connection, Message, RequestId, Response};
+use serde_json::Value;
+
+use crate::record::{self, Channel, Direction, Entry};
+
+/// How long a request may stay unanswered before it is reported as such.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayOptions {
+    pub recording: PathBuf,
+    /// The recorded connection to play; defaults to the first one.
+    pub connection: Option<u64>,
+    /// Sends each message as soon as the previous request was answered
+    /// instead of keeping the recorded gaps between messages.
+    pub fast: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplaySummary {
+    /// Response times by LSP method.
+    pub latencies: BTreeMap<String, Vec<Duration>>,
+    /// Requests whose response differs from the recorded one.
+    pub mismatches: Vec<Mismatch>,
+    /// Requests that got no response.
+    pub unanswered: Vec<String>,
+    pub notifications: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub id: RequestId,
+    pub method: String,
+    pub recorded: Value,
+    pub replayed: Value,
+}
+
+/// What the recording holds for one connection.
+#[derive(Debug, Default)]
+struct Script {
+    /// Client requests and notifications with their recorded time.
+    messages: Vec<(u64, Message)>,
+    /// Recorded responses to the client's requests, by request id.
+    responses: HashMap<RequestId, Value>,
+    /// How the client answered ts-bridge's requests, in order, by method.
+    client_replies: HashMap<String, VecDeque<Response>>,
+}
+
+impl Script {
+    fn from_entries(entries: &[Entry], connection: Option<u64>) -> anyhow::Result<Self> {
+        let lsp = entries
+            .iter()
+            .filter(|entry| entry.channel == Channel::Lsp)
+            .collect::<Vec<_>>();
+        let connection = match connection {
+            Some(connection) => connection,
+            None => lsp
+                .iter()
+                .find_map(|entry| entry.connection)
+                .ok_or_else(|| anyhow!("the recording holds no LSP messages"))?,
+        };
+
+        let mut script = Self::default();
+        let mut server_requests = HashMap::new();
+        for entry in lsp
+            .into_iter()
+            .filter(|entry| entry.connection == Some(connection))
+        {
+            let message: Message = serde_json::from_value(entry.message.clone())
+                .context("recorded LSP message is malformed")?;
+            match (entry.direction, message) {
+                (Direction::In, Message::Response(response)) => {
+                    if let Some(method) = server_requests.remove(&response.id) {
+                        script
+                            .client_replies
+                            .entry(method)
+                            .or_insert_with(VecDeque::new)
+                            .push_back(response);
+                    }
+                }
+                (Direction::In, message) => script.messages.push((entry.ts_ms, message)),
+                (Direction::Out, Message::Request(request)) => {
+                    server_requests.insert(request.id, request.method);
+                }
+                (Direction::Out, Message::Response(response)) => {
+                    script
+                        .responses
+                        .insert(response.id.clone(), response_value(&response));
+                }
+                (Direction::Out, Message::Notification(_)) => {}
+            }
+        }
+        if script.messages.is_empty() {
+            bail!("connection {connection} sent nothing to replay");
+        }
+        Ok(script)
+    }
+}
+
+/// Plays the recording and reports how the bridge answered.
+pub fn run(options: &ReplayOptions) -> anyhow::Result<ReplaySummary> {
+    let entries = record::read(&options.recording)?;
+    let mut script = Script::from_entries(&entries, options.connection)?;
+
+    let (server, client) = Connection::memory();
+    let session = thread::spawn(move || crate::server::run_connection_session(server));
+    let mut player = Player {
+        client,
+        client_replies: std::mem::take(&mut script.client_replies),
+        recorded: std::mem::take(&mut script.responses),
+        pending: HashMap::new(),
+        summary: ReplaySummary::default(),
+    };
+
+    let played = player.play(script.messages, options.fast);
+    let Player {
+        client,
+        mut summary,
+        pending,
+        ..
+    } = player;
+    drop(client);
+    // The session's own error explains a replay that could not finish.
+    session
+        .join()
+        .map_err(|_| anyhow!("the replayed session panicked"))??;
+    played?;
+    summary.unanswered.extend(
+        pending
+            .into_values()
+            .map(|(method, _)| method)
+            .filter(|method| method != "shutdown"),
+    );
+    Ok(summary)
+}
+
+/// The mock client.
+struct Player {
+    client: Connection,
+    client_replies: HashMap<String, VecDeque<Response>>,
+    recorded: HashMap<RequestId, Value>,
+    /// Requests sent and not answered yet, with their method and send time.
+    pending: HashMap<RequestId, (String, Instant)>,
+    summary: ReplaySummary,
+}
+
+impl Player {
+    /// Sends the recorded client messages, keeping their pacing unless `fast`.
+    fn play(&mut self, messages: Vec<(u64, Message)>, fast: bool) -> anyhow::Result<()> {
+        let started = Instant::now();
+        let first = messages.first().map_or(0, |(ts_ms, _)| *ts_ms);
+        let mut exited = false;
+        let mut shut_down = false;
+        for (ts_ms, message) in messages {
+            let is_request = matches!(message, Message::Request(_));
+            match &message {
+                // Everything asked before shutdown is answered first, so no
+                // response is lost to the session ending.
+                Message::Request(request) if request.method == "shutdown" => {
+                    self.settle();
+                    shut_down = true;
+                }
+                Message::Notification(notification) if notification.method == "exit" => {
+                    self.settle();
+                    exited = true;
+                }
+                _ if !fast => {
+                    let due = started + Duration::from_millis(ts_ms.saturating_sub(first));
+                    self.pump_until(due);
+                }
+                _ => {}
+            }
+            if exited {
+                // The bridge ends the session on `shutdown`; `exit` may
+                // find nobody listening.
+                let _ = self.send(message);
+                break;
+            }
+            self.send(message)?;
+            if fast && is_request {
+                self.settle();
+            }
+        }
+        if !exited {
+            // A recording of a crash or a killed editor ends mid-session. One
+            // that ends at `shutdown` lacks the `exit` the bridge no longer
+            // read, and its session may already be gone.
+            self.settle();
+            if !shut_down {
+                self.send(Message::Request(lsp_server::Request::new(
+                    RequestId::from("ts-bridge-replay-shutdown".to_string()),
+                    "shutdown".to_string(),
+                    Value::Null,
+                )))?;
+                self.settle();
+            }
+            let _ = self.send(Message::Notification(lsp_server::Notification::new(
+                "exit".to_string(),
+                Value::Null,
+            )));
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, message: Message) -> anyhow::Result<()> {
+        if let Message::Request(request) = &message {
+            self.pending
+                .insert(request.id.clone(), (request.method.clone(), Instant::now()));
+        }
+        self.client
+            .sender
+            .send(message)
+            .map_err(|_| anyhow!("the replayed session ended early"))
+    }
+
+    /// Handles what the bridge sends until `deadline`.
+    fn pump_until(&mut self, deadline: Instant) {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.client.receiver.recv_timeout(timeout) {
+                Ok(message) => self.receive(message),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return,
+            }
+            if Instant::now() >= deadline {
+                return;
+            }
+        }
+    }
+
+    /// Waits for every pending request to be answered, giving up on those
+    /// that take longer than [`RESPONSE_TIMEOUT`].
+    fn settle(&mut self) {
+        while !self.pending.is_empty() {
+            match self.client.receiver.recv_timeout(RESPONSE_TIMEOUT) {
+                Ok(message) => self.receive(message),
+                Err(_) => {
+                    let stuck = std::mem::take(&mut self.pending);
+                    self.summary
+                        .unanswered
+                        .extend(stuck.into_values().map(|(method, _)| method));
+                    return;
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self, message: Message) {
+        match message {
+            Message::Response(response) => {
+                let Some((method, sent)) = self.pending.remove(&response.id) else {
+                    return;
+                };
+                self.summary
+                    .latencies
+                    .entry(method.clone())
+                    .or_default()
+                    .push(sent.elapsed());
+                let replayed = response_value(&response);
+                if let Some(recorded) = self.recorded.get(&response.id)
+                    && *recorded != replayed
+                {
+                    self.summary.mismatches.push(Mismatch {
+                        id: response.id,
+                        method,
+                        recorded: recorded.clone(),
+                        replayed,
+                    });
+                }
+            }
+            Message::Request(request) => {
+                let reply = self
+                    .client_replies
+                    .get_mut(&request.method)
+                    .and_then(VecDeque::pop_front);
+                let response = match reply {
+                    Some(reply) => Response {
+                        id: request.id,
+                        ..reply
+                    },
+                    None => Response::new_ok(request.id, Value::Null),
+                };
+                let _ = self.client.sender.send(Message::Response(response));
+            }
+            Message::Notification(_) => self.summary.notifications += 1,
+        }
+    }
+}
+
+/// The part of a response that is compared: its result, or its error.
+fn response_value(response: &Response) -> Value {
+    match &response.error {
+        Some(error) => {
+            serde_json::json!({ "error": { "code": error.code, "message": error.message } })
+        }
+        None => response.result.clone().unwrap_or(Value::Null),
+    }
+}
+
+/// The `percentile`th (0-100) of `samples`, nearest rank.
+pub(crate) fn percentile(samples: &[Duration], percentile: usize) -> Duration {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    match sorted.len() {
+        0 => Duration::ZERO,
+        len => sorted[((len * percentile).div_ceil(100)).clamp(1, len) - 1],
+    }
+}
+
+/// A `method  count  p50 ms  p95 ms  max ms` table, one row per method;
+/// `ts-bridge bench` prints its results with it too.
+pub(crate) fn latency_table(latencies: &BTreeMap<String, Vec<Duration>>) -> String {
+    let mut out = String::new();
+    let width = latencies
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("method".len());
+    let _ = writeln!(
+        out,
+        "{:width$}  {:>5}  {:>8}  {:>8}  {:>8}",
+        "method", "count", "p50 ms", "p95 ms", "max ms"
+    );
+    for (method, samples) in latencies {
+        let ms = |duration: Duration| format!("{:.1}", duration.as_secs_f64() * 1000.0);
+        let _ = writeln!(
+            out,
+            "{method:width$}  {:>5}  {:>8}  {:>8}  {:>8}",
+            samples.len(),
+            ms(percentile(samples, 50)),
+            ms(percentile(samples, 95)),
+            ms(percentile(samples, 100)),
+        );
+    }
+    out
+}
+
+/// One line per method with its response times, then what differed.
+pub fn render(summary: &ReplaySummary) -> String {
+    let mut out = latency_table(&summary.latencies);
+    let _ = writeln!(out, "{} notification(s) received", summary.notifications);
+    for mismatch in &summary.mismatches {
+        let _ = writeln!(
+            out,
+            "response to {} ({}) differs from the recording:\n  recorded: {}\n  replayed: {}",
+            mismatch.method, mismatch.id, mismatch.recorded, mismatch.replayed
+        );
+    }
+    for method in &summary.unanswered {
+        let _ = writeln!(out, "{method} was never answered");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(ts_ms: u64, direction: Direction, connection: u64, message: Value) -> Entry {
+        Entry {
+            ts_ms,
+            channel: Channel::Lsp,
+            direction,
+            connection: Some(connection),
+            server: None,
+            pid: None,
+            message,
+        }
+    }
+
+    #[test]
+    fn script_keeps_one_connection_and_pairs_client_replies() {
+        let entries = vec![
+            entry(
+                0,
+                Direction::In,
+                2,
+                json!({ "id": 1, "method": "initialize", "params": {} }),
+            ),
+            entry(
+                1,
+                Direction::In,
+                1,
+                json!({ "id": 1, "method": "initialize", "params": {} }),
+            ),
+            entry(
+                2,
+                Direction::Out,
+                1,
+                json!({ "id": 1, "result": { "capabilities": {} } }),
+            ),
+            entry(
+                3,
+                Direction::Out,
+                1,
+                json!({ "id": 7, "method": "workspace/configuration", "params": {} }),
+            ),
+            entry(
+                4,
+                Direction::In,
+                1,
+                json!({ "id": 7, "result": [{ "tsserver": {} }] }),
+            ),
+            entry(
+                5,
+                Direction::In,
+                1,
+                json!({ "method": "initialized", "params": {} }),
+            ),
+        ];
+
+        let script = Script::from_entries(&entries, Some(1)).unwrap();
+        let methods: Vec<(u64, String)> = script
+            .messages
+            .iter()
+            .map(|(ts_ms, message)| match message {
+                Message::Request(request) => (*ts_ms, request.method.clone()),
+                Message::Notification(notification) => (*ts_ms, notification.method.clone()),
+                Message::Response(_) => panic!("client responses are answered live"),
+            })
+            .collect();
+        assert_eq!(
+            methods,
+            [
+                (1, "initialize".to_string()),
+                (5, "initialized".to_string())
+            ]
+        );
+        assert_eq!(
+            script.responses.get(&RequestId::from(1)),
+            Some(&json!({ "capabilities": {} }))
+        );
+        let replies = &script.client_replies["workspace/configuration"];
+        assert_eq!(replies[0].result, Some(json!([{ "tsserver": {} }])));
+
+        let first = Script::from_entries(&entries, None).unwrap();
+        assert_eq!(first.messages.len(), 1, "defaults to the first connection");
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let samples: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(10));
+        assert_eq!(percentile(&samples, 95), Duration::from_millis(19));
+        assert_eq!(percentile(&samples, 100), Duration::from_millis(20));
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+    }
+}
@@ -8,11 +8,17 @@
 //! * handler dispatch into the protocol module tree
 
 mod queue;
+mod recovery;
 pub use queue::{Priority, Request, RequestQueue};
 
+use std::fmt;
+use std::time::{Duration, Instant};
+
 use crossbeam_channel::{Receiver, TryRecvError};
 use serde_json::Value;
 
+use recovery::Recovery;
+
 use crate::config::Config;
 use crate::process::{ServerKind, TsserverProcess};
 use crate::provider::Provider;
@@ -27,6 +33,8 @@ pub struct Service {
     semantic_rx: Option<Receiver<Value>>,
     syntax_queue: RequestQueue,
     semantic_queue: RequestQueue,
+    syntax_recovery: Recovery,
+    semantic_recovery: Recovery,
 }
 
 impl Service {
@@ -40,30 +48,110 @@ impl Service {
             semantic_rx: None,
             syntax_queue: RequestQueue::default(),
             semantic_queue: RequestQueue::default(),
+            syntax_recovery: Recovery::default(),
+            semantic_recovery: Recovery::default(),
         }
     }
 
     /// Bootstraps tsserver processes once
     pub fn start(&mut self) -> Result<(), ServiceError> {
-        let binary = self.provider.resolve().map_err(ServiceError::Provider)?;
-        let launch = self.config.plugin().tsserver.clone();
-        let mut syntax = TsserverProcess::new(ServerKind::Syntax, binary.clone(), launch.clone());
-        syntax.start().map_err(ServiceError::Process)?;
-        self.syntax_rx = syntax.response_rx();
-        self.syntax = Some(syntax);
-
+        self.start_server(ServerKind::Syntax)?;
         if self.config.plugin().separate_diagnostic_server {
-            let mut semantic = TsserverProcess::new(ServerKind::Semantic, binary, launch);
-            semantic.start().map_err(ServiceError::Process)?;
-            self.semantic_rx = semantic.response_rx();
-            self.semantic = Some(semantic);
+            self.start_server(ServerKind::Semantic)?;
         }
+        Ok(())
+    }
 
+    fn start_server(&mut self, kind: ServerKind) -> Result<(), ServiceError> {
+        let launch = self.config.plugin().tsserver.clone();
+        let binary = self
+            .provider
+            .resolve_for(&launch)
+            .map_err(ServiceError::Provider)?;
+        log::info!(
+            "starting {kind:?} tsserver {:?} ({:?}, TypeScript {})",
+            binary.executable,
+            binary.source,
+            binary.version.as_deref().unwrap_or("unknown")
+        );
+        let mut process = TsserverProcess::new(kind, binary, launch);
+        process.start().map_err(ServiceError::Process)?;
+        let rx = process.response_rx();
+        match kind {
+            ServerKind::Syntax => {
+                self.syntax_rx = rx;
+                self.syntax = Some(process);
+                self.syntax_recovery.started(Instant::now());
+            }
+            ServerKind::Semantic => {
+                self.semantic_rx = rx;
+                self.semantic = Some(process);
+                self.semantic_recovery.started(Instant::now());
+            }
+        }
         Ok(())
     }
 
+    /// Notices servers that exited on their own and restarts them once their
+    /// backoff delay has passed.  Called from the project loop.
+    pub fn poll_health(&mut self) -> Vec<HealthEvent> {
+        let now = Instant::now();
+        let mut events = Vec::new();
+        for kind in [ServerKind::Syntax, ServerKind::Semantic] {
+            let (process, recovery) = match kind {
+                ServerKind::Syntax => (&mut self.syntax, &mut self.syntax_recovery),
+                ServerKind::Semantic => (&mut self.semantic, &mut self.semantic_recovery),
+            };
+            if let Some(status) = process.as_mut().and_then(|process| process.exit_status()) {
+                events.push(HealthEvent::Exited {
+                    server: kind,
+                    reason: format!("exited with {status}"),
+                    retry_in: recovery.crashed(now),
+                });
+                self.discard(kind);
+                continue;
+            }
+            if !recovery.restart_due(now) {
+                continue;
+            }
+            match self.start_server(kind) {
+                Ok(()) => events.push(HealthEvent::Restarted { server: kind }),
+                Err(err) => {
+                    let recovery = match kind {
+                        ServerKind::Syntax => &mut self.syntax_recovery,
+                        ServerKind::Semantic => &mut self.semantic_recovery,
+                    };
+                    events.push(HealthEvent::Exited {
+                        server: kind,
+                        reason: format!("failed to restart: {err}"),
+                        retry_in: recovery.crashed(now),
+                    });
+                }
+            }
+        }
+        events
+    }
+
+    fn discard(&mut self, kind: ServerKind) {
+        match kind {
+            ServerKind::Syntax => {
+                self.syntax = None;
+                self.syntax_rx = None;
+                self.syntax_queue.reset();
+            }
+            ServerKind::Semantic => {
+                self.semantic = None;
+                self.semantic_rx = None;
+                self.semantic_queue.reset();
+            }
+        }
+    }
+
     fn syntax_mut(&mut self) -> Result<&mut TsserverProcess, ServiceError> {
         if self.syntax.is_none() {
+            if self.syntax_recovery.is_blocked() {
+                return Err(ServiceError::Recovering);
+            }
             self.start()?;
         }
         self.syntax.as_mut().ok_or(ServiceError::ProcessNotStarted)
@@ -116,20 +204,32 @@ impl Service {
                     });
                 }
             }
+            Route::SemanticPreferred => {
+                let (server, queue) = if self.semantic.is_some() {
+                    (ServerKind::Semantic, &mut self.semantic_queue)
+                } else {
+                    (ServerKind::Syntax, &mut self.syntax_queue)
+                };
+                let seq = queue.enqueue(payload, priority);
+                self.flush_queue(server)?;
+                receipts.push(DispatchReceipt { server, seq });
+            }
         }
 
         Ok(receipts)
     }
 
-    /// Cancels a pending request on both servers.
-    pub fn cancel(&self, seq: u64) -> Result<(), ServiceError> {
-        if let Some(server) = &self.syntax {
-            server.cancel(seq).map_err(ServiceError::Process)?;
-        }
-        if let Some(server) = &self.semantic {
-            server.cancel(seq).map_err(ServiceError::Process)?;
+    /// Cancels a pending request on the server it was dispatched to.  Each
+    /// server numbers its requests independently, so the seq alone is ambiguous.
+    pub fn cancel(&self, server: ServerKind, seq: u64) -> Result<(), ServiceError> {
+        let process = match server {
+            ServerKind::Syntax => self.syntax.as_ref(),
+            ServerKind::Semantic => self.semantic.as_ref(),
+        };
+        match process {
+            Some(process) => process.cancel(seq).map_err(ServiceError::Process),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     /// Drains any ready responses from syntax/semantic readers without blocking.
@@ -144,6 +244,20 @@ impl Service {
         events
     }
 
+    /// Drains stderr lines both servers printed since the last call.
+    pub fn poll_stderr(&self) -> Vec<(ServerKind, String)> {
+        let mut lines = Vec::new();
+        for (kind, process) in [
+            (ServerKind::Syntax, &self.syntax),
+            (ServerKind::Semantic, &self.semantic),
+        ] {
+            if let Some(rx) = process.as_ref().and_then(|process| process.stderr_rx()) {
+                lines.extend(rx.try_iter().map(|line| (kind, line)));
+            }
+        }
+        lines
+    }
+
     pub fn workspace_root(&self) -> &std::path::Path {
         self.provider.workspace_root()
     }
@@ -171,8 +285,31 @@ impl Service {
         Ok(())
     }
 
-    pub fn update_config(&mut self, new_config: Config) {
+    /// Swaps in new settings. Flipping `separate_diagnostic_server` starts or
+    /// stops the semantic server; the new setting is returned when it did.
+    pub fn update_config(&mut self, new_config: Config) -> Option<bool> {
+        let was_separate = self.config.plugin().separate_diagnostic_server;
         self.config = new_config;
+        let separate = self.config.plugin().separate_diagnostic_server;
+        if separate == was_separate {
+            return None;
+        }
+        if separate {
+            // Without a syntax server the next request starts both.
+            if self.syntax.is_some()
+                && let Err(err) = self.start_server(ServerKind::Semantic)
+            {
+                log::warn!("failed to start semantic tsserver: {err}");
+                self.semantic_recovery.crashed(Instant::now());
+            }
+        } else {
+            if let Some(mut process) = self.semantic.take() {
+                process.shutdown();
+            }
+            self.discard(ServerKind::Semantic);
+            self.semantic_recovery.reset();
+        }
+        Some(separate)
     }
 
     pub fn restart(
@@ -181,18 +318,28 @@ impl Service {
         restart_semantic: bool,
     ) -> Result<(), ServiceError> {
         if restart_syntax {
-            self.syntax = None;
-            self.syntax_rx = None;
-            self.syntax_queue.reset();
+            self.discard(ServerKind::Syntax);
+            self.syntax_recovery.reset();
         }
         if restart_semantic {
-            self.semantic = None;
-            self.semantic_rx = None;
-            self.semantic_queue.reset();
+            self.discard(ServerKind::Semantic);
+            self.semantic_recovery.reset();
         }
         Ok(())
     }
 
+    /// Asks both servers to exit, killing whichever does not stop in time.
+    pub fn shutdown(&mut self) {
+        for mut process in [self.syntax.take(), self.semantic.take()]
+            .into_iter()
+            .flatten()
+        {
+            process.shutdown();
+        }
+        self.syntax_rx = None;
+        self.semantic_rx = None;
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
@@ -201,18 +348,50 @@ impl Service {
         &mut self.config
     }
 
+    /// Whether diagnostics run on their own semantic tsserver: the one that
+    /// is up once the servers started, before that the one `start` would
+    /// launch.
+    pub fn semantic_server(&self) -> bool {
+        if self.syntax.is_none() && self.semantic.is_none() {
+            return self.config.plugin().separate_diagnostic_server;
+        }
+        self.semantic.is_some()
+    }
+
     pub fn tsserver_status(&self) -> TsserverStatus {
+        let binary = self
+            .syntax
+            .as_ref()
+            .or(self.semantic.as_ref())
+            .map(|process| process.binary());
         TsserverStatus {
             syntax_pid: self.syntax.as_ref().and_then(|process| process.pid()),
             semantic_pid: self.semantic.as_ref().and_then(|process| process.pid()),
+            semantic_server: self.semantic_server(),
+            syntax_uptime: self.syntax.as_ref().and_then(|process| process.uptime()),
+            semantic_uptime: self.semantic.as_ref().and_then(|process| process.uptime()),
+            executable: binary.map(|binary| binary.executable.clone()),
+            version: binary.and_then(|binary| binary.version.clone()),
+            source: binary.map(|binary| binary.source),
+            queued: self.syntax_queue.len() + self.semantic_queue.len(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Default)]
 pub struct TsserverStatus {
     pub syntax_pid: Option<u32>,
     pub semantic_pid: Option<u32>,
+    /// See [`Service::semantic_server`].
+    pub semantic_server: bool,
+    pub syntax_uptime: Option<Duration>,
+    pub semantic_uptime: Option<Duration>,
+    /// The running tsserver script; `None` until one started.
+    pub executable: Option<std::path::PathBuf>,
+    pub version: Option<String>,
+    pub source: Option<crate::provider::BinarySource>,
+    /// Requests waiting to be written to either server.
+    pub queued: usize,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -223,6 +402,23 @@ pub enum ServiceError {
     Process(#[from] crate::process::ProcessError),
     #[error("syntax process not started yet")]
     ProcessNotStarted,
+    #[error("tsserver exited and is waiting to be restarted")]
+    Recovering,
+}
+
+/// Reported by [`Service::poll_health`].
+#[derive(Debug, Clone)]
+pub enum HealthEvent {
+    /// The process is gone; `retry_in` is `None` once automatic restarts gave
+    /// up and only a manual restart brings it back.
+    Exited {
+        server: ServerKind,
+        reason: String,
+        retry_in: Option<Duration>,
+    },
+    Restarted {
+        server: ServerKind,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -237,11 +433,23 @@ pub struct DispatchReceipt {
     pub seq: u64,
 }
 
+/// `syntax#42`: the server and the `seq` the request was sent with, as it
+/// appears in tsserver's own log.
+impl fmt::Display for DispatchReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.server.as_str(), self.seq)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Route {
     Syntax,
     Semantic,
     Both,
+    /// Project-wide work (references, rename, workspace symbols, ...) that
+    /// should not queue behind edits on the syntax server.  Goes to the
+    /// semantic server when one is running, otherwise to the syntax server.
+    SemanticPreferred,
 }
 
 fn collect_events(kind: ServerKind, rx: &Receiver<Value>, out: &mut Vec<ServerEvent>) {
@@ -1,4 +1,10 @@
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Seqs are drawn from one counter for every tsserver in the process, so a
+/// `(server, seq)` pair identifies a request even when a session talks to
+/// several projects.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Priority {
@@ -16,13 +22,12 @@ pub struct Request {
 
 #[derive(Debug, Default)]
 pub struct RequestQueue {
-    seq: u64,
     queue: VecDeque<Request>,
 }
 
 impl RequestQueue {
     pub fn enqueue(&mut self, mut payload: serde_json::Value, priority: Priority) -> u64 {
-        let seq = self.next_seq();
+        let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
         assign_seq(&mut payload, seq);
         let request = Request {
             seq,
@@ -52,19 +57,16 @@ impl RequestQueue {
     }
 
     pub fn reset(&mut self) {
-        self.seq = 0;
         self.queue.clear();
     }
 
-    fn next_seq(&mut self) -> u64 {
-        let seq = self.seq;
-        self.seq += 1;
-        seq
-    }
-
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
 }
 
 fn assign_seq(payload: &mut serde_json::Value, seq: u64) {
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 5;
+/// A server that ran this long before exiting counts as healthy again, so the
+/// backoff starts over.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Restart bookkeeping for one tsserver process that exited on its own.
+#[derive(Debug, Default)]
+pub struct Recovery {
+    attempts: u32,
+    started_at: Option<Instant>,
+    state: RecoveryState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RecoveryState {
+    #[default]
+    Running,
+    Waiting(Instant),
+    GaveUp,
+}
+
+impl Recovery {
+    pub fn started(&mut self, now: Instant) {
+        self.started_at = Some(now);
+        self.state = RecoveryState::Running;
+    }
+
+    /// Records an exit (or a failed restart) and schedules the next attempt.
+    /// Returns the delay before it, or `None` once the attempts are used up.
+    pub fn crashed(&mut self, now: Instant) -> Option<Duration> {
+        if self
+            .started_at
+            .is_some_and(|started| now.duration_since(started) >= STABLE_AFTER)
+        {
+            self.attempts = 0;
+        }
+        self.started_at = None;
+        if self.attempts >= MAX_ATTEMPTS {
+            self.state = RecoveryState::GaveUp;
+            return None;
+        }
+        let delay = INITIAL_DELAY
+            .saturating_mul(1 << self.attempts)
+            .min(MAX_DELAY);
+        self.attempts += 1;
+        self.state = RecoveryState::Waiting(now + delay);
+        Some(delay)
+    }
+
+    pub fn restart_due(&self, now: Instant) -> bool {
+        matches!(self.state, RecoveryState::Waiting(at) if at <= now)
+    }
+
+    /// While waiting for (or after giving up on) a restart, requests must not
+    /// respawn the server behind the backoff's back.
+    pub fn is_blocked(&self) -> bool {
+        self.state != RecoveryState::Running
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_double_until_attempts_run_out() {
+        let now = Instant::now();
+        let mut recovery = Recovery::default();
+        recovery.started(now);
+
+        let delays: Vec<_> = (0..MAX_ATTEMPTS)
+            .map(|_| recovery.crashed(now).expect("retry scheduled"))
+            .collect();
+        assert_eq!(delays[0], INITIAL_DELAY);
+        assert_eq!(delays[1], INITIAL_DELAY * 2);
+        assert!(recovery.is_blocked());
+        assert!(!recovery.restart_due(now));
+        assert!(recovery.restart_due(now + MAX_DELAY));
+
+        assert_eq!(recovery.crashed(now), None);
+        assert!(recovery.is_blocked());
+        assert!(!recovery.restart_due(now + MAX_DELAY));
+    }
+
+    #[test]
+    fn long_uptime_resets_the_backoff() {
+        let now = Instant::now();
+        let mut recovery = Recovery::default();
+        recovery.started(now);
+        recovery.crashed(now);
+        recovery.started(now);
+        assert_eq!(recovery.crashed(now), Some(INITIAL_DELAY * 2));
+
+        recovery.started(now);
+        assert_eq!(recovery.crashed(now + STABLE_AFTER), Some(INITIAL_DELAY));
+        assert!(recovery.restart_due(now + STABLE_AFTER + INITIAL_DELAY));
+    }
+}
@@ -1,12 +1,12 @@
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::io::{self, BufReader};
-use std::net::{TcpListener, TcpStream};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{self, BufReader, Read};
+use std::net::TcpListener;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, anyhow};
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TryRecvError, bounded, unbounded};
@@ -15,37 +15,92 @@ use lsp_server::{
     Response,
 };
 use lsp_types::{
-    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CompletionOptions,
-    ExecuteCommandOptions, HoverProviderCapability, InitializeParams, InitializeResult,
-    InlayHintOptions, InlayHintServerCapabilities, OneOf, PositionEncodingKind, ProgressParams,
-    ProgressParamsValue, ProgressToken, PublishDiagnosticsParams, RenameOptions,
+    CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CodeLensOptions,
+    CompletionOptions, CompletionTriggerKind, DeclarationCapability,
+    DocumentOnTypeFormattingOptions, ExecuteCommandOptions, FoldingRangeProviderCapability,
+    HoverProviderCapability, InitializeParams, InitializeResult, InlayHintOptions,
+    InlayHintServerCapabilities, OneOf, PositionEncodingKind, ProgressParams, ProgressParamsValue,
+    ProgressToken, PublishDiagnosticsParams, RenameOptions, SelectionRangeProviderCapability,
     ServerCapabilities, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
     TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TypeDefinitionProviderCapability,
     WorkDoneProgress as LspWorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams,
-    WorkDoneProgressEnd, WorkDoneProgressReport,
+    WorkDoneProgressEnd, WorkDoneProgressReport, WorkspaceFileOperationsServerCapabilities,
+    WorkspaceServerCapabilities,
     notification::{
-        DidChangeConfiguration, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
-        Notification as LspNotification, Progress, PublishDiagnostics,
+        Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles,
+        DidCloseTextDocument, DidDeleteFiles, DidOpenTextDocument, DidRenameFiles,
+        DidSaveTextDocument, LogTrace, Notification as LspNotification, Progress,
+        PublishDiagnostics, SetTrace,
     },
     request::{
-        InlayHintRefreshRequest, InlayHintRequest, Request as LspRequest, WorkDoneProgressCreate,
+        ApplyWorkspaceEdit, DocumentDiagnosticRequest, InlayHintRefreshRequest, InlayHintRequest,
+        RegisterCapability, Request as LspRequest, WorkDoneProgressCreate,
+        WorkspaceDiagnosticRefresh, WorkspaceDiagnosticRequest,
     },
 };
 use serde_json::{self, Map, Value, json};
 
-use crate::config::{Config, PluginSettings};
-use crate::documents::{DocumentStore, OpenDocumentSnapshot, TextSpan};
+use crate::config::{
+    Config, DiagnosticPublishMode, LanguageScope, PluginSettings, validate_settings,
+};
+use crate::control::{self, CloneableStream};
+use crate::documents::{
+    DocumentEvent, DocumentOwners, DocumentStore, OpenDocumentSnapshot, SharedDocuments,
+    SharedSync, TextSpan,
+};
 use crate::process::ServerKind;
 use crate::protocol::diagnostics::{DiagnosticsEvent, DiagnosticsKind};
-use crate::protocol::text_document::completion::TRIGGER_CHARACTERS;
+use crate::protocol::text_document::completion::{self, CompletionConfig, TRIGGER_CHARACTERS};
+use crate::protocol::text_document::declaration;
+use crate::protocol::text_document::definition;
+use crate::protocol::text_document::diagnostic as pull_diagnostics;
+use crate::protocol::text_document::doc_comment_template;
+use crate::protocol::text_document::formatting::{self, format_code_settings};
+use crate::protocol::text_document::jsx_closing_tag;
+use crate::protocol::text_document::on_type_formatting::{
+    FIRST_TRIGGER_CHARACTER as ON_TYPE_FIRST_TRIGGER,
+    MORE_TRIGGER_CHARACTERS as ON_TYPE_MORE_TRIGGERS,
+};
 use crate::protocol::text_document::signature_help::TRIGGER_CHARACTERS as SIG_HELP_TRIGGER_CHARACTERS;
+use crate::protocol::workspace::diagnostic::ReportCollector;
+use crate::protocol::workspace::execute_command::SOURCE_DEFINITION_COMMAND;
+use crate::protocol::workspace::{did_change_watched_files, file_operations};
 use crate::protocol::{self, AdapterResult, ResponseAdapter};
-use crate::provider::Provider;
-use crate::rpc::{DispatchReceipt, Priority, Route, ServerEvent, Service, ServiceError};
+use crate::provider::{BUNDLED_TYPESCRIPT_VERSION, BinarySource, Provider, TsserverBinary};
+use crate::rpc::{
+    DispatchReceipt, HealthEvent, Priority, Route, ServerEvent, Service, ServiceError,
+    TsserverStatus,
+};
+use crate::telemetry;
 use crate::utils::uri_to_file_path;
 
 const DEFAULT_INLAY_HINT_SPAN: u32 = 5_000_000;
 const DEFAULT_DAEMON_IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+/// How long a daemon session whose client disconnected can be resumed.
+pub const DEFAULT_RESUME_WINDOW: Duration = Duration::from_secs(2 * 60);
+/// Notification sent to a client that reclaimed its session.
+const RESUME_METHOD: &str = "ts-bridge/resume";
+/// How long a resuming client waits for its old connection's session to let
+/// go.
+const RESUME_HANDOFF_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a daemon client may stay silent before it is sent a heartbeat
+/// request; one left unanswered as long again ends its connection.
+pub const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Server-to-client request sent to silent daemon clients.
+const HEARTBEAT_METHOD: &str = "ts-bridge/heartbeat";
+/// Heartbeat request ids start with this, so their replies are not mistaken
+/// for the session's own.
+const HEARTBEAT_ID_PREFIX: &str = "ts-bridge-heartbeat-";
+/// How often parked sessions nobody resumed in time are ended.
+const PARKED_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+/// Idle time before a daemon TCP connection is probed, so editors that
+/// vanished without closing it are noticed.
+const TCP_KEEPALIVE_TIME: Duration = Duration::from_secs(60);
+/// How long a signalled daemon waits for sessions to forward the shutdown
+/// notice before exiting.
+const SHUTDOWN_NOTICE_GRACE: Duration = Duration::from_millis(200);
+/// Only the daemon's user may connect to its socket by default.
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
 
 fn current_epoch_seconds() -> u64 {
     match SystemTime::now().duration_since(UNIX_EPOCH) {
@@ -54,6 +109,10 @@ fn current_epoch_seconds() -> u64 {
     }
 }
 
+/// How often the eviction loop looks again while idle eviction is off, so a
+/// reload that turns it on is noticed.
+const IDLE_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 fn idle_sweep_interval(idle_ttl: Duration) -> Duration {
     let min_interval = Duration::from_secs(5);
     let max_interval = Duration::from_secs(60);
@@ -70,20 +129,151 @@ fn idle_sweep_interval(idle_ttl: Duration) -> Duration {
 /// Runs the LSP server over stdio. This is the entry-point Neovim (or any LSP
 /// client) will execute.
 pub fn run_stdio_server() -> anyhow::Result<()> {
-    let _ = env_logger::try_init();
-    let registry = ProjectRegistry::new(None);
+    crate::logging::init(None);
+    crate::record::init(None);
+    telemetry::init();
+    let registry = ProjectRegistry::new(None, None, None);
     let (connection, io_threads) = Connection::stdio();
-    run_session(connection, &registry)?;
+    let result = run_session(crate::record::connection(connection), &registry);
+    telemetry::shutdown();
+    result?;
     io_threads.join()?;
 
     Ok(())
 }
 
+/// Runs one session over an in-process connection; `ts-bridge replay` plays
+/// the client side of a recording into it and `ts-bridge bench` times it.
+pub(crate) fn run_connection_session(connection: Connection) -> anyhow::Result<()> {
+    let registry = ProjectRegistry::new(None, None, None);
+    run_session(connection, &registry)
+}
+
 #[derive(Debug)]
 pub struct DaemonConfig {
     pub listen: Option<std::net::SocketAddr>,
     pub socket: Option<PathBuf>,
+    /// Permission bits of the socket file.
+    pub socket_mode: u32,
+    /// Windows named pipe path; see [`crate::control::pipe_path`].
+    pub pipe: Option<String>,
     pub idle_ttl: Option<Duration>,
+    /// Most projects kept running; the least recently used are shut down
+    /// beyond it, idle ones first.
+    pub max_projects: Option<usize>,
+    /// Most editors attached to one project; more are refused.
+    pub max_sessions_per_project: Option<usize>,
+    /// `tsserver.max_old_space_size` (MB) forced on projects at or below
+    /// each root, the deepest root winning.
+    pub project_max_memory: Vec<(PathBuf, u32)>,
+    /// How long the session of a client that disconnected without `exit` is
+    /// kept for it to resume; `None` ends it right away.
+    pub resume_window: Option<Duration>,
+    /// How long a daemon client may stay silent before it must answer a
+    /// heartbeat; `None` never asks.
+    pub client_timeout: Option<Duration>,
+    /// JSON-lines log file; see [`crate::logging`].
+    pub log_file: Option<PathBuf>,
+    /// Overrides `RUST_LOG`; the settings file and reloads may change it.
+    pub log_level: Option<log::LevelFilter>,
+    /// TOML or JSON file with `idle_ttl`, `max_projects` and `log_level`,
+    /// applied over the settings above at startup and re-read on SIGHUP and
+    /// `ts-bridge daemon reload`.
+    pub settings_file: Option<PathBuf>,
+    /// Exits once the daemon has had nothing to do for this long; meant for
+    /// systemd socket activation, which starts it again when needed.
+    pub exit_when_idle: Option<Duration>,
+    /// Records every session's LSP and tsserver traffic; see [`crate::record`].
+    pub record: Option<PathBuf>,
+}
+
+/// The daemon settings a reload changes without dropping sessions.
+#[derive(Debug, Clone, PartialEq)]
+struct DaemonSettings {
+    idle_ttl: Option<Duration>,
+    max_projects: Option<usize>,
+    log_level: Option<log::LevelFilter>,
+}
+
+impl DaemonSettings {
+    fn from_config(config: &DaemonConfig) -> Self {
+        Self {
+            idle_ttl: config.idle_ttl,
+            max_projects: config.max_projects,
+            log_level: config.log_level,
+        }
+    }
+
+    /// `base`, then the settings file, then `overrides` (a reload request's
+    /// params). Keys missing from either keep the value beneath them.
+    fn load(base: &Self, file: Option<&Path>, overrides: &Value) -> anyhow::Result<Self> {
+        let mut settings = base.clone();
+        if let Some(path) = file {
+            let value = crate::config::read_settings_file(path)?;
+            settings
+                .apply(&value)
+                .with_context(|| format!("in {}", path.display()))?;
+        }
+        settings.apply(overrides)?;
+        Ok(settings)
+    }
+
+    fn apply(&mut self, value: &Value) -> anyhow::Result<()> {
+        if value.is_null() {
+            return Ok(());
+        }
+        let map = value
+            .as_object()
+            .ok_or_else(|| anyhow!("daemon settings must be a table"))?;
+        for (key, value) in map {
+            match key.as_str() {
+                "idle_ttl" => {
+                    self.idle_ttl = count_or_off(key, value)?
+                        .filter(|seconds| *seconds > 0)
+                        .map(Duration::from_secs);
+                }
+                "max_projects" => {
+                    self.max_projects = match count_or_off(key, value)? {
+                        Some(0) => return Err(anyhow!("max_projects must be positive or \"off\"")),
+                        count => count.map(|count| count as usize),
+                    };
+                }
+                "log_level" => {
+                    self.log_level = match value {
+                        Value::Null => None,
+                        Value::String(level) => Some(
+                            level
+                                .parse()
+                                .map_err(|_| anyhow!("unknown log_level {level:?}"))?,
+                        ),
+                        _ => return Err(anyhow!("log_level must be a string")),
+                    };
+                }
+                other => return Err(anyhow!("unknown daemon setting {other:?}")),
+            }
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "idle_ttl_seconds": self.idle_ttl.map(|ttl| ttl.as_secs()),
+            "max_projects": self.max_projects,
+            "log_level": self.log_level.map(|level| level.as_str().to_ascii_lowercase()),
+        })
+    }
+}
+
+/// A number, or `"off"` for none.
+fn count_or_off(key: &str, value: &Value) -> anyhow::Result<Option<u64>> {
+    match value {
+        Value::Number(number) => number
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| anyhow!("{key} must be a whole number or \"off\"")),
+        Value::String(text) if text.eq_ignore_ascii_case("off") => Ok(None),
+        _ => Err(anyhow!("{key} must be a whole number or \"off\"")),
+    }
 }
 
 impl Default for DaemonConfig {
@@ -91,35 +281,120 @@ impl Default for DaemonConfig {
         Self {
             listen: None,
             socket: None,
+            socket_mode: DEFAULT_SOCKET_MODE,
+            pipe: None,
             idle_ttl: Some(DEFAULT_DAEMON_IDLE_TTL),
+            max_projects: None,
+            max_sessions_per_project: None,
+            project_max_memory: Vec::new(),
+            resume_window: Some(DEFAULT_RESUME_WINDOW),
+            client_timeout: Some(DEFAULT_CLIENT_TIMEOUT),
+            log_file: None,
+            log_level: None,
+            settings_file: None,
+            exit_when_idle: None,
+            record: None,
         }
     }
 }
 
 pub fn run_daemon_server(config: DaemonConfig) -> anyhow::Result<()> {
-    let _ = env_logger::try_init();
-    if config.listen.is_some() && config.socket.is_some() {
-        return Err(anyhow!("daemon listen and socket cannot be used together"));
+    // Before any thread starts: this clears the activation variables.
+    #[cfg(unix)]
+    let activated = crate::activation::take_listener().context("take systemd socket")?;
+    crate::logging::init(config.log_file.as_deref());
+    crate::record::init(config.record.as_deref());
+    telemetry::init();
+    let transports = [
+        config.listen.is_some(),
+        config.socket.is_some(),
+        config.pipe.is_some(),
+    ];
+    if transports.iter().filter(|set| **set).count() > 1 {
+        return Err(anyhow!(
+            "daemon listen, socket and pipe cannot be used together"
+        ));
     }
 
-    let registry = ProjectRegistry::new(config.idle_ttl);
+    let base = DaemonSettings::from_config(&config);
+    let settings = DaemonSettings::load(&base, config.settings_file.as_deref(), &Value::Null)
+        .context("load daemon settings file")?;
+    crate::logging::set_level(settings.log_level);
+    let mut options = DaemonOptions {
+        base,
+        file: config.settings_file,
+        exit_when_idle: config.exit_when_idle,
+        client_timeout: config.client_timeout,
+        socket_activated: false,
+    };
+
+    let registry = ProjectRegistry::new(
+        settings.idle_ttl,
+        settings.max_projects,
+        config.resume_window,
+    );
+    registry.set_limits(ProjectLimits {
+        max_sessions: config.max_sessions_per_project,
+        max_memory: config
+            .project_max_memory
+            .into_iter()
+            .map(|(root, megabytes)| (normalize_root(root), megabytes))
+            .collect(),
+    });
 
+    #[cfg(unix)]
+    if let Some(listener) = activated {
+        options.socket_activated = true;
+        return match listener {
+            crate::activation::ActivatedListener::Tcp(listener) => {
+                serve_tcp(listener, registry, options)
+            }
+            crate::activation::ActivatedListener::Unix(listener) => {
+                let socket_path = listener
+                    .local_addr()
+                    .ok()
+                    .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+                    .ok_or_else(|| anyhow!("systemd passed a unix socket without a path"))?;
+                serve_unix(listener, socket_path, registry, options)
+            }
+        };
+    }
     if let Some(socket_path) = config.socket {
-        return run_daemon_unix(socket_path, registry);
+        return run_daemon_unix(socket_path, config.socket_mode, registry, options);
+    }
+    if let Some(pipe) = config.pipe {
+        return run_daemon_pipe(pipe, registry, options);
     }
 
     let addr = config
         .listen
         .unwrap_or_else(|| "127.0.0.1:0".parse().expect("valid default addr"));
-    run_daemon_tcp(addr, registry)
+    run_daemon_tcp(addr, registry, options)
 }
 
-fn run_daemon_tcp(addr: std::net::SocketAddr, registry: ProjectRegistry) -> anyhow::Result<()> {
+fn run_daemon_tcp(
+    addr: std::net::SocketAddr,
+    registry: ProjectRegistry,
+    options: DaemonOptions,
+) -> anyhow::Result<()> {
     let listener = TcpListener::bind(addr).context("bind daemon listener")?;
+    serve_tcp(listener, registry, options)
+}
+
+fn serve_tcp(
+    listener: TcpListener,
+    registry: ProjectRegistry,
+    options: DaemonOptions,
+) -> anyhow::Result<()> {
     let bound = listener
         .local_addr()
         .context("resolve daemon listen addr")?;
     log::info!("daemon listening on {bound}");
+    let daemon = Arc::new(DaemonInfo::new(
+        control::DaemonEndpoint::Tcp(bound),
+        options,
+    ));
+    daemon.start(&registry);
 
     loop {
         let (stream, peer) = match listener.accept() {
@@ -129,13 +404,21 @@ fn run_daemon_tcp(addr: std::net::SocketAddr, registry: ProjectRegistry) -> anyh
                 continue;
             }
         };
+        if daemon.is_stopping() {
+            continue;
+        }
         log::info!("daemon accepted connection from {peer}");
         if let Err(err) = stream.set_nodelay(true) {
             log::debug!("failed to set TCP_NODELAY for {peer}: {err}");
         }
+        let keepalive = socket2::TcpKeepalive::new().with_time(TCP_KEEPALIVE_TIME);
+        if let Err(err) = socket2::SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+            log::debug!("failed to enable TCP keepalive for {peer}: {err}");
+        }
         let registry = registry.clone();
+        let daemon = daemon.clone();
         thread::spawn(move || {
-            if let Err(err) = run_stream_session(stream, registry) {
+            if let Err(err) = run_stream_session(stream, registry, daemon) {
                 log::warn!("session from {peer} exited with error: {err:?}");
             }
         });
@@ -143,17 +426,62 @@ fn run_daemon_tcp(addr: std::net::SocketAddr, registry: ProjectRegistry) -> anyh
 }
 
 #[cfg(unix)]
-fn run_daemon_unix(socket_path: PathBuf, registry: ProjectRegistry) -> anyhow::Result<()> {
+fn run_daemon_unix(
+    socket_path: PathBuf,
+    socket_mode: u32,
+    registry: ProjectRegistry,
+    options: DaemonOptions,
+) -> anyhow::Result<()> {
     use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
     use std::os::unix::net::UnixListener;
 
+    if let Some(parent) = socket_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        && !parent.exists()
+    {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)
+            .with_context(|| format!("create socket directory {}", parent.display()))?;
+    }
     if socket_path.exists() {
         fs::remove_file(&socket_path)
             .with_context(|| format!("remove existing socket {}", socket_path.display()))?;
     }
-    let listener = UnixListener::bind(&socket_path)
-        .with_context(|| format!("bind unix socket {}", socket_path.display()))?;
+    // The umask keeps the socket from being reachable with looser
+    // permissions between bind and chmod.
+    let previous_umask = unsafe { libc::umask((!socket_mode & 0o777) as libc::mode_t) };
+    let bound = UnixListener::bind(&socket_path);
+    unsafe { libc::umask(previous_umask) };
+    let listener = bound.with_context(|| format!("bind unix socket {}", socket_path.display()))?;
+    fs::set_permissions(&socket_path, fs::Permissions::from_mode(socket_mode)).with_context(
+        || {
+            format!(
+                "set mode {socket_mode:o} on socket {}",
+                socket_path.display()
+            )
+        },
+    )?;
+    log::info!("socket mode {socket_mode:04o}");
+    serve_unix(listener, socket_path, registry, options)
+}
+
+#[cfg(unix)]
+fn serve_unix(
+    listener: std::os::unix::net::UnixListener,
+    socket_path: PathBuf,
+    registry: ProjectRegistry,
+    options: DaemonOptions,
+) -> anyhow::Result<()> {
     log::info!("daemon listening on {}", socket_path.display());
+    let daemon = Arc::new(DaemonInfo::new(
+        control::DaemonEndpoint::Socket(socket_path),
+        options,
+    ));
+    daemon.start(&registry);
 
     loop {
         let (stream, _) = match listener.accept() {
@@ -163,10 +491,14 @@ fn run_daemon_unix(socket_path: PathBuf, registry: ProjectRegistry) -> anyhow::R
                 continue;
             }
         };
+        if daemon.is_stopping() {
+            continue;
+        }
         log::info!("daemon accepted unix connection");
         let registry = registry.clone();
+        let daemon = daemon.clone();
         thread::spawn(move || {
-            if let Err(err) = run_unix_stream_session(stream, registry) {
+            if let Err(err) = run_stream_session(stream, registry, daemon) {
                 log::warn!("unix session exited with error: {err:?}");
             }
         });
@@ -174,57 +506,475 @@ fn run_daemon_unix(socket_path: PathBuf, registry: ProjectRegistry) -> anyhow::R
 }
 
 #[cfg(not(unix))]
-fn run_daemon_unix(_socket_path: PathBuf, _registry: ProjectRegistry) -> anyhow::Result<()> {
+fn run_daemon_unix(
+    _socket_path: PathBuf,
+    _socket_mode: u32,
+    _registry: ProjectRegistry,
+    _options: DaemonOptions,
+) -> anyhow::Result<()> {
     Err(anyhow!(
         "unix domain sockets are not supported on this platform"
     ))
 }
 
-fn run_stream_session(stream: TcpStream, registry: ProjectRegistry) -> anyhow::Result<()> {
-    let (connection, io_threads) = connection_from_stream(stream)?;
-    run_session(connection, &registry)?;
-    io_threads
-        .join()
-        .context("daemon session IO threads failed")?;
-    Ok(())
+#[cfg(windows)]
+fn run_daemon_pipe(
+    pipe: String,
+    registry: ProjectRegistry,
+    options: DaemonOptions,
+) -> anyhow::Result<()> {
+    let mut listener = crate::pipe::PipeListener::bind(&pipe)
+        .with_context(|| format!("create named pipe {pipe} (is another daemon using it?)"))?;
+    log::info!("daemon listening on {pipe}");
+    let daemon = Arc::new(DaemonInfo::new(
+        control::DaemonEndpoint::Pipe(pipe),
+        options,
+    ));
+    daemon.start(&registry);
+
+    loop {
+        let stream = match listener.accept() {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("daemon accept failed: {err}");
+                continue;
+            }
+        };
+        if daemon.is_stopping() {
+            continue;
+        }
+        log::info!("daemon accepted pipe connection");
+        let registry = registry.clone();
+        let daemon = daemon.clone();
+        thread::spawn(move || {
+            if let Err(err) = run_stream_session(stream, registry, daemon) {
+                log::warn!("pipe session exited with error: {err:?}");
+            }
+        });
+    }
 }
 
-#[cfg(unix)]
-fn run_unix_stream_session(
-    stream: std::os::unix::net::UnixStream,
+#[cfg(not(windows))]
+fn run_daemon_pipe(
+    _pipe: String,
+    _registry: ProjectRegistry,
+    _options: DaemonOptions,
+) -> anyhow::Result<()> {
+    Err(anyhow!("named pipes are only supported on Windows"))
+}
+
+fn run_stream_session<S: CloneableStream>(
+    stream: S,
     registry: ProjectRegistry,
+    daemon: Arc<DaemonInfo>,
 ) -> anyhow::Result<()> {
-    let (connection, io_threads) = connection_from_stream(stream)?;
-    run_session(connection, &registry)?;
+    let _connection = daemon.connection_opened();
+    let (connection, io_threads) = connection_from_stream(stream, daemon.options.client_timeout)?;
+    let connection = crate::record::connection(connection);
+    let exit = serve_daemon_connection(connection, &registry, &daemon)?;
     io_threads
         .join()
         .context("daemon session IO threads failed")?;
+    if let Some(exit) = exit {
+        daemon.exit(exit);
+    }
     Ok(())
 }
 
-trait CloneableStream: io::Read + io::Write + Send + 'static + Sized {
-    fn try_clone(&self) -> io::Result<Self>;
+/// Stops the daemon like `ts-bridge daemon stop` on SIGTERM or SIGINT:
+/// sessions are told, projects shut their tsservers down, and the socket and
+/// discovery file are removed before exiting with 0. SIGHUP reloads the
+/// settings like `ts-bridge daemon reload`.
+#[cfg(unix)]
+fn handle_signals(daemon: Arc<DaemonInfo>, registry: ProjectRegistry) {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let mut signals = match signal_hook::iterator::Signals::new([SIGTERM, SIGINT, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            log::warn!("cannot install the SIGTERM/SIGINT/SIGHUP handler: {err}");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        let mut signals = signals.forever();
+        let signal = loop {
+            match signals.next() {
+                Some(SIGHUP) => {
+                    log::info!("daemon received SIGHUP; reloading settings");
+                    if let Err(err) = daemon.reload(&registry, &Value::Null) {
+                        log::warn!("daemon settings not reloaded: {err:#}");
+                    }
+                }
+                Some(signal) => break signal,
+                None => return,
+            }
+        };
+        let name = if signal == SIGINT {
+            "SIGINT"
+        } else {
+            "SIGTERM"
+        };
+        if !daemon.begin_stop() {
+            return;
+        }
+        log::info!("daemon received {name}; shutting down");
+        let stopped = registry.shutdown_all();
+        log::info!("shut down {stopped} project(s)");
+        // Lets sessions deliver the shutdown notice before the process ends.
+        thread::sleep(SHUTDOWN_NOTICE_GRACE);
+        daemon.exit(DaemonExit::Stop);
+    });
+}
+
+#[cfg(not(unix))]
+fn handle_signals(_daemon: Arc<DaemonInfo>, _registry: ProjectRegistry) {}
+
+/// Serves one daemon connection: an LSP session, or the requests of
+/// `ts-bridge daemon status|stop|restart|reload|warm` when they come before
+/// `initialize`.
+/// Returns how the daemon should exit once the reply has been flushed.
+fn serve_daemon_connection(
+    connection: Connection,
+    registry: &ProjectRegistry,
+    daemon: &DaemonInfo,
+) -> anyhow::Result<Option<DaemonExit>> {
+    while let Ok(message) = connection.receiver.recv() {
+        match message {
+            Message::Request(req) if req.method == "initialize" => {
+                start_session(connection, registry, req.id, req.params)?;
+                return Ok(None);
+            }
+            Message::Request(req) if req.method == control::PRELOAD_METHOD => {
+                log::info!("daemon control request {}", req.method);
+                let response = preload_response(registry, req.id, &req.params);
+                connection.sender.send(response.into())?;
+            }
+            Message::Request(req) if req.method == control::RELOAD_METHOD => {
+                log::info!("daemon control request {}", req.method);
+                let response = match daemon.reload(registry, &req.params) {
+                    Ok(settings) => Response::new_ok(req.id, settings),
+                    Err(err) => Response::new_err(
+                        req.id,
+                        ErrorCode::InvalidParams as i32,
+                        format!("{err:#}"),
+                    ),
+                };
+                connection.sender.send(response.into())?;
+            }
+            Message::Request(req) if control::is_control_method(&req.method) => {
+                log::info!("daemon control request {}", req.method);
+                let (result, exit) = daemon.control(&req.method, registry);
+                connection
+                    .sender
+                    .send(Response::new_ok(req.id, result).into())?;
+                if exit.is_some() {
+                    return Ok(exit);
+                }
+            }
+            Message::Request(req) => {
+                let response = Response::new_err(
+                    req.id,
+                    ErrorCode::ServerNotInitialized as i32,
+                    format!("expected initialize request, got {}", req.method),
+                );
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notif) if notif.method == "exit" => break,
+            _ => {}
+        }
+    }
+    Ok(None)
 }
 
-impl CloneableStream for TcpStream {
-    fn try_clone(&self) -> io::Result<Self> {
-        TcpStream::try_clone(self)
+/// Answers a [`control::PRELOAD_METHOD`] request, from a control connection
+/// or a session.
+fn preload_response(registry: &ProjectRegistry, id: RequestId, params: &Value) -> Response {
+    let Some(path) = params.get("path").and_then(Value::as_str) else {
+        return Response::new_err(
+            id,
+            ErrorCode::InvalidParams as i32,
+            "preloadProject needs a `path`".to_string(),
+        );
+    };
+    match registry.preload(PathBuf::from(path)) {
+        Ok(result) => Response::new_ok(id, result),
+        Err(err) => Response::new_err(id, ErrorCode::RequestFailed as i32, format!("{err:#}")),
     }
 }
 
-#[cfg(unix)]
-impl CloneableStream for std::os::unix::net::UnixStream {
-    fn try_clone(&self) -> io::Result<Self> {
-        std::os::unix::net::UnixStream::try_clone(self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DaemonExit {
+    Stop,
+    Restart,
+}
+
+/// The daemon process itself, as `ts-bridge daemon status` and the
+/// discovery file report it.
+struct DaemonInfo {
+    started: Instant,
+    started_epoch_seconds: u64,
+    endpoint: control::DaemonEndpoint,
+    discovery: PathBuf,
+    /// Set once a stop begins; new connections are turned away.
+    stopping: AtomicBool,
+    /// Connections being served, sessions or control requests.
+    connections: Arc<AtomicUsize>,
+    options: DaemonOptions,
+    /// Settings passed to `ts-bridge daemon reload`. They stay over the
+    /// settings file until another reload changes them, so a SIGHUP does not
+    /// revert them.
+    overrides: Mutex<Map<String, Value>>,
+}
+
+struct DaemonOptions {
+    /// Reloaded settings start from these, taken from the command line and
+    /// environment.
+    base: DaemonSettings,
+    file: Option<PathBuf>,
+    exit_when_idle: Option<Duration>,
+    client_timeout: Option<Duration>,
+    /// The listener came from systemd, which owns the socket and starts the
+    /// daemon again on the next connection.
+    socket_activated: bool,
+}
+
+/// Decrements the connection count when the connection ends.
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl DaemonInfo {
+    fn new(endpoint: control::DaemonEndpoint, options: DaemonOptions) -> Self {
+        Self {
+            started: Instant::now(),
+            started_epoch_seconds: current_epoch_seconds(),
+            endpoint,
+            discovery: control::discovery_path(),
+            stopping: AtomicBool::new(false),
+            connections: Arc::new(AtomicUsize::new(0)),
+            options,
+            overrides: Mutex::new(Map::new()),
+        }
+    }
+
+    /// Advertises the daemon and starts watching for signals and, with
+    /// `--exit-when-idle`, for the daemon going idle.
+    fn start(self: &Arc<Self>, registry: &ProjectRegistry) {
+        if self.options.socket_activated {
+            log::info!("daemon started by systemd socket activation");
+        }
+        self.advertise();
+        handle_signals(self.clone(), registry.clone());
+        exit_when_idle(self.clone(), registry.clone());
+    }
+
+    fn connection_opened(&self) -> ConnectionGuard {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard(self.connections.clone())
+    }
+
+    /// Re-reads the settings file and applies it over the command-line
+    /// settings, then `overrides` merged into those of earlier reloads.
+    /// Sessions and projects stay up; projects beyond a lowered limit, or
+    /// idle past a shorter TTL, are evicted right away. Returns the settings
+    /// now in effect.
+    fn reload(&self, registry: &ProjectRegistry, overrides: &Value) -> anyhow::Result<Value> {
+        let mut kept = self
+            .overrides
+            .lock()
+            .expect("daemon overrides mutex poisoned");
+        let mut merged = kept.clone();
+        match overrides {
+            Value::Null => {}
+            Value::Object(map) => merged.extend(map.clone()),
+            _ => return Err(anyhow!("daemon settings must be a table")),
+        }
+        let merged = Value::Object(merged);
+        let settings =
+            DaemonSettings::load(&self.options.base, self.options.file.as_deref(), &merged)?;
+        if !kept.is_empty() {
+            log::info!("keeping settings from earlier reload requests over the file: {merged}");
+        }
+        if let Value::Object(merged) = merged {
+            *kept = merged;
+        }
+        registry.apply_settings(&settings);
+        crate::logging::set_level(settings.log_level);
+        log::info!("daemon settings reloaded: {}", settings.to_json());
+        Ok(settings.to_json())
+    }
+
+    fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Marks the daemon as stopping; false when a stop is already underway.
+    fn begin_stop(&self) -> bool {
+        !self.stopping.swap(true, Ordering::SeqCst)
+    }
+
+    /// Writes the discovery file `ts-bridge connect` and the control
+    /// subcommands find this daemon through.
+    fn advertise(&self) {
+        let discovery = control::Discovery {
+            pid: std::process::id(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            addr: match &self.endpoint {
+                control::DaemonEndpoint::Tcp(addr) => Some(addr.to_string()),
+                _ => None,
+            },
+            socket: match &self.endpoint {
+                control::DaemonEndpoint::Socket(path) => Some(path.clone()),
+                _ => None,
+            },
+            pipe: match &self.endpoint {
+                control::DaemonEndpoint::Pipe(path) => Some(path.clone()),
+                _ => None,
+            },
+            started_epoch_seconds: self.started_epoch_seconds,
+        };
+        match control::write_discovery(&self.discovery, &discovery) {
+            Ok(()) => log::info!("daemon discovery file {}", self.discovery.display()),
+            Err(err) => log::warn!(
+                "cannot write daemon discovery file {}: {err}",
+                self.discovery.display()
+            ),
+        }
+    }
+
+    fn control(&self, method: &str, registry: &ProjectRegistry) -> (Value, Option<DaemonExit>) {
+        if method == control::STATUS_METHOD {
+            let status = json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "pid": std::process::id(),
+                "endpoint": self.endpoint.to_string(),
+                "uptime_seconds": self.started.elapsed().as_secs(),
+                "idle_ttl_seconds": registry.idle_ttl().map(|ttl| ttl.as_secs()),
+                "max_projects": registry.max_entries(),
+                "max_sessions_per_project": registry.limits().max_sessions,
+                "log_level": crate::logging::level_override()
+                    .map(|level| level.as_str().to_ascii_lowercase()),
+                "projects": registry.status_snapshot(),
+            });
+            return (status, None);
+        }
+        let exit = if method == control::RESTART_METHOD {
+            DaemonExit::Restart
+        } else {
+            DaemonExit::Stop
+        };
+        self.begin_stop();
+        let stopped = registry.shutdown_all();
+        log::info!("daemon {exit:?}: shut down {stopped} project(s)");
+        (json!({ "stopped_projects": stopped }), Some(exit))
+    }
+
+    /// Ends the process, or replaces it with a fresh daemon started with the
+    /// same arguments.
+    fn exit(&self, exit: DaemonExit) -> ! {
+        if let control::DaemonEndpoint::Socket(socket) = &self.endpoint
+            && !self.options.socket_activated
+        {
+            let _ = std::fs::remove_file(socket);
+        }
+        control::remove_discovery(&self.discovery, std::process::id());
+        telemetry::shutdown();
+        // systemd keeps listening and starts a fresh daemon on the next
+        // connection, so exiting is all a restart takes.
+        if exit == DaemonExit::Restart && !self.options.socket_activated {
+            let err = restart_daemon();
+            log::error!("daemon restart failed: {err:?}");
+            std::process::exit(1);
+        }
+        log::info!("daemon stopped");
+        std::process::exit(0)
+    }
+}
+
+/// With `--exit-when-idle`, stops the daemon once it has had no projects,
+/// parked sessions or connections for that long. Socket-activated daemons use
+/// it to hand idle time back to systemd.
+fn exit_when_idle(daemon: Arc<DaemonInfo>, registry: ProjectRegistry) {
+    let Some(after) = daemon.options.exit_when_idle else {
+        return;
+    };
+    let interval = (after / 2).clamp(Duration::from_millis(200), Duration::from_secs(30));
+    thread::spawn(move || {
+        let mut idle_since = None;
+        loop {
+            thread::sleep(interval);
+            if daemon.connections.load(Ordering::SeqCst) > 0 || !registry.is_idle() {
+                idle_since = None;
+                continue;
+            }
+            let since = *idle_since.get_or_insert_with(Instant::now);
+            if since.elapsed() < after || !daemon.begin_stop() {
+                continue;
+            }
+            log::info!("daemon idle for {}s; exiting", after.as_secs());
+            registry.shutdown_all();
+            daemon.exit(DaemonExit::Stop);
+        }
+    });
+}
+
+/// Re-executes this binary with its original arguments; only returns on
+/// failure (on Windows, once the replacement has been spawned).
+fn restart_daemon() -> anyhow::Error {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => return anyhow!(err).context("locate the ts-bridge executable"),
+    };
+    let mut command = std::process::Command::new(exe);
+    command.args(std::env::args_os().skip(1));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        anyhow!(command.exec()).context("re-exec ts-bridge daemon")
+    }
+    #[cfg(not(unix))]
+    {
+        match command.spawn() {
+            Ok(_) => std::process::exit(0),
+            Err(err) => anyhow!(err).context("spawn ts-bridge daemon"),
+        }
     }
 }
 
-fn connection_from_stream<S: CloneableStream>(stream: S) -> anyhow::Result<(Connection, DaemonIo)> {
+/// Splits a daemon stream into an LSP connection. With a `client_timeout`,
+/// a client silent that long is sent a heartbeat request and dropped if it
+/// stays silent as long again, which ends or parks its session.
+fn connection_from_stream<S: CloneableStream>(
+    stream: S,
+    client_timeout: Option<Duration>,
+) -> anyhow::Result<(Connection, DaemonIo)> {
     let reader_stream = stream.try_clone().context("clone daemon stream")?;
+    reader_stream
+        .set_read_timeout(client_timeout)
+        .context("set daemon stream read timeout")?;
     let (reader_sender, reader_receiver) = bounded::<Message>(0);
+    let (writer_sender, writer_receiver) = bounded::<Message>(0);
+    let heartbeat_sender = writer_sender.clone();
     let reader = thread::spawn(move || {
-        let mut buf_read = BufReader::new(reader_stream);
-        while let Some(msg) = Message::read(&mut buf_read)? {
+        let mut buf_read = BufReader::new(HeartbeatReader::new(reader_stream, heartbeat_sender));
+        loop {
+            let msg = match Message::read(&mut buf_read) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => break,
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    log::warn!("daemon client stopped answering heartbeats: {err}");
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            if is_heartbeat_response(&msg) {
+                continue;
+            }
             let is_exit = matches!(&msg, Message::Notification(n) if n.method == "exit");
             if reader_sender.send(msg).is_err() {
                 break;
@@ -236,7 +986,6 @@ fn connection_from_stream<S: CloneableStream>(stream: S) -> anyhow::Result<(Conn
         Ok(())
     });
 
-    let (writer_sender, writer_receiver) = bounded::<Message>(0);
     let (drop_sender, drop_receiver) = bounded::<Message>(0);
     let writer = thread::spawn(move || {
         let mut stream = stream;
@@ -261,6 +1010,78 @@ fn connection_from_stream<S: CloneableStream>(stream: S) -> anyhow::Result<(Conn
     ))
 }
 
+fn is_heartbeat_response(msg: &Message) -> bool {
+    let Message::Response(response) = msg else {
+        return false;
+    };
+    serde_json::to_value(&response.id)
+        .ok()
+        .and_then(|id| id.as_str().map(|id| id.starts_with(HEARTBEAT_ID_PREFIX)))
+        .unwrap_or(false)
+}
+
+/// Reads a daemon stream whose read timeout is the client timeout. The
+/// first timeout sends a heartbeat request; a second one in a row, with no
+/// bytes from the client in between, fails the read with `TimedOut`.
+struct HeartbeatReader<S> {
+    stream: S,
+    heartbeats: Sender<Message>,
+    awaiting_reply: bool,
+    next_id: u64,
+}
+
+impl<S: Read> HeartbeatReader<S> {
+    fn new(stream: S, heartbeats: Sender<Message>) -> Self {
+        Self {
+            stream,
+            heartbeats,
+            awaiting_reply: false,
+            next_id: 0,
+        }
+    }
+
+    fn send_heartbeat(&mut self) -> io::Result<()> {
+        self.next_id += 1;
+        let id = RequestId::from(format!("{HEARTBEAT_ID_PREFIX}{}", self.next_id));
+        self.heartbeats
+            .send(Message::Request(Request::new(
+                id,
+                HEARTBEAT_METHOD.to_string(),
+                Value::Null,
+            )))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "daemon writer closed"))
+    }
+}
+
+impl<S: Read> Read for HeartbeatReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Ok(read) => {
+                    self.awaiting_reply = false;
+                    return Ok(read);
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if self.awaiting_reply {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "no reply to heartbeat",
+                        ));
+                    }
+                    self.send_heartbeat()?;
+                    self.awaiting_reply = true;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
 struct DaemonIo {
     reader: thread::JoinHandle<io::Result<()>>,
     writer: thread::JoinHandle<io::Result<()>>,
@@ -294,22 +1115,88 @@ struct ProjectRegistry {
 }
 
 struct ProjectRegistryState {
-    entries: HashMap<PathBuf, ProjectEntry>,
+    entries: HashMap<ProjectKey, ProjectEntry>,
     max_entries: Option<usize>,
-    #[allow(dead_code)]
     idle_ttl: Option<Duration>,
+    /// Set by [`ProjectRegistry::shutdown_all`].
+    closed: bool,
+    resume_window: Option<Duration>,
+    /// Sessions whose client disconnected, by resume token.
+    parked: HashMap<String, ParkedSession>,
+    /// Every session still alive, parked or not, by resume token; setting
+    /// the flag asks a connected one to park itself for a resuming client.
+    takeovers: HashMap<String, Arc<AtomicBool>>,
+    limits: ProjectLimits,
+}
+
+/// Caps that keep one project from starving the others.
+#[derive(Debug, Clone, Default)]
+struct ProjectLimits {
+    max_sessions: Option<usize>,
+    /// Normalized project roots and their `max_old_space_size`.
+    max_memory: Vec<(PathBuf, u32)>,
+}
+
+impl ProjectLimits {
+    fn max_old_space_size(&self, root: &Path) -> Option<u32> {
+        self.max_memory
+            .iter()
+            .filter(|(prefix, _)| root.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components().count())
+            .map(|(_, megabytes)| *megabytes)
+    }
+}
+
+struct ParkedSession {
+    session: Box<SessionState>,
+    until: Instant,
+}
+
+/// Identifies a shared tsserver project. Workspace projects leave `tsserver`
+/// unset and use whatever `Provider` finds from the root; monorepo packages
+/// that pin their own TypeScript get a separate entry per binary.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ProjectKey {
+    root: PathBuf,
+    tsserver: Option<PathBuf>,
+}
+
+impl ProjectKey {
+    fn workspace(root: PathBuf) -> Self {
+        Self {
+            root: normalize_root(root),
+            tsserver: None,
+        }
+    }
+
+    fn package(root: PathBuf, tsserver: PathBuf) -> Self {
+        Self {
+            root: normalize_root(root),
+            tsserver: Some(tsserver),
+        }
+    }
 }
 
 impl ProjectRegistry {
-    fn new(idle_ttl: Option<Duration>) -> Self {
+    fn new(
+        idle_ttl: Option<Duration>,
+        max_entries: Option<usize>,
+        resume_window: Option<Duration>,
+    ) -> Self {
         let registry = Self {
             inner: Arc::new(Mutex::new(ProjectRegistryState {
                 entries: HashMap::new(),
-                max_entries: None,
+                max_entries,
                 idle_ttl,
+                closed: false,
+                resume_window,
+                parked: HashMap::new(),
+                takeovers: HashMap::new(),
+                limits: ProjectLimits::default(),
             })),
         };
         registry.spawn_eviction_loop();
+        registry.spawn_parked_sweep();
         registry
     }
 
@@ -319,9 +1206,9 @@ impl ProjectRegistry {
             guard
                 .entries
                 .iter()
-                .map(|(root, entry)| {
+                .map(|(key, entry)| {
                     (
-                        root.clone(),
+                        key.clone(),
                         entry.handle.label().to_string(),
                         entry.handle.clone(),
                         entry.last_used.load(Ordering::Relaxed),
@@ -331,23 +1218,30 @@ impl ProjectRegistry {
         };
 
         let mut entries = Vec::with_capacity(seeds.len());
-        for (root, label, handle, last_used) in seeds {
+        for (key, label, handle, last_used) in seeds {
             let status = handle.status().unwrap_or_else(|err| {
                 log::warn!(
                     "failed to fetch status for project {}: {err}",
-                    root.display()
+                    key.root.display()
                 );
                 ProjectThreadStatus::default()
             });
             entries.push(json!({
-                "root": root.to_string_lossy(),
+                "root": key.root.to_string_lossy(),
+                "tsserver_path": key.tsserver.map(|path| path.to_string_lossy().into_owned()),
                 "label": label,
                 "session_count": status.session_count,
                 "session_ids": status.session_ids,
                 "last_used_epoch_seconds": last_used,
                 "tsserver": {
-                    "syntax_pid": status.tsserver_syntax_pid,
-                    "semantic_pid": status.tsserver_semantic_pid,
+                    "syntax_pid": status.tsserver.syntax_pid,
+                    "semantic_pid": status.tsserver.semantic_pid,
+                    "syntax_uptime_seconds": status.tsserver.syntax_uptime.map(|uptime| uptime.as_secs()),
+                    "semantic_uptime_seconds": status.tsserver.semantic_uptime.map(|uptime| uptime.as_secs()),
+                    "executable": status.tsserver.executable.map(|path| path.to_string_lossy().into_owned()),
+                    "version": status.tsserver.version,
+                    "source": status.tsserver.source.map(BinarySource::label),
+                    "queue_depth": status.tsserver.queued,
                 },
             }));
         }
@@ -364,44 +1258,141 @@ impl ProjectRegistry {
     fn register_session(&self, params: &InitializeParams) -> anyhow::Result<SessionInit> {
         let workspace_root =
             workspace_root_from_params(params).unwrap_or_else(|| std::env::current_dir().unwrap());
-        let mut config = Config::new(PluginSettings::default());
-
-        if let Some(options) = params.initialization_options.as_ref()
-            && config.apply_workspace_settings(options)
-        {
-            log::info!("applied initializationOptions to ts-bridge settings");
+        let (mut config, mut settings_problems) = workspace_config(&workspace_root);
+        if let Some(options) = params.initialization_options.as_ref() {
+            settings_problems.extend(validate_settings(options));
+            if config.apply_client_settings(options) {
+                log::info!("applied initializationOptions to ts-bridge settings");
+            }
+        }
+        if config.inherit_client_locale(params.locale.as_deref()) {
+            log::info!("using client locale {:?} for tsserver", params.locale);
         }
 
-        let handle = self.get_or_create(workspace_root.clone(), config.clone())?;
-        let registration = handle.register_session(config)?;
+        let (handle, registration) =
+            self.attach(ProjectKey::workspace(workspace_root.clone()), config)?;
         Ok(SessionInit {
             project: handle,
             events: registration.events,
             config: registration.config,
             workspace_root,
             session_id: registration.session_id,
+            interactive_inlay_hints:
+                crate::protocol::text_document::inlay_hint::supports_label_locations(
+                    &params.capabilities,
+                ),
+            settings_problems,
+            trace: params.trace.unwrap_or(lsp_types::TraceValue::Off),
+            client: ClientFingerprint::of(params),
         })
     }
 
-    fn get_or_create(
-        &self,
-        workspace_root: PathBuf,
-        config: Config,
-    ) -> anyhow::Result<ProjectHandle> {
-        let normalized = normalize_root(workspace_root);
-        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
-        guard.maybe_evict();
-        if let Some(entry) = guard.entries.get_mut(&normalized) {
-            entry.touch();
-            return Ok(entry.handle.clone());
+    /// Starts the project at `root` ahead of its first session: resolves its
+    /// TypeScript, spawns tsserver and has it load the root's tsconfig. The
+    /// project runs with the root's settings files and `TS_BRIDGE_*`
+    /// overrides, as a session without `initializationOptions` would.
+    fn preload(&self, root: PathBuf) -> anyhow::Result<Value> {
+        if !root.is_dir() {
+            return Err(anyhow!("{} is not a directory", root.display()));
         }
-
-        let provider = Provider::new(normalized.clone());
-        let last_used = Arc::new(AtomicU64::new(current_epoch_seconds()));
+        let (mut config, problems) = workspace_config(&root);
+        for problem in problems {
+            log::warn!("preloading {}: {problem}", root.display());
+        }
+        let handle = self.get_or_create(ProjectKey::workspace(root), &mut config)?;
+        let preload = handle.preload()?;
+        let status = handle.status()?;
+        log::info!(
+            "preloaded project {} ({})",
+            handle.label(),
+            preload.config_file.as_deref().map_or_else(
+                || "no tsconfig".to_string(),
+                |path| path.display().to_string()
+            )
+        );
+        Ok(json!({
+            "root": handle.root().to_string_lossy(),
+            "name": handle.label(),
+            "already_running": preload.already_running,
+            "config_file": preload.config_file.map(|path| path.to_string_lossy().into_owned()),
+            "tsserver": {
+                "syntax_pid": status.tsserver.syntax_pid,
+                "semantic_pid": status.tsserver.semantic_pid,
+                "executable": status.tsserver.executable.map(|path| path.to_string_lossy().into_owned()),
+                "version": status.tsserver.version,
+                "source": status.tsserver.source.map(BinarySource::label),
+            },
+        }))
+    }
+
+    /// The running project for `key`, started with `config` when there is
+    /// none. `config` gets the project's memory limit, so sessions register
+    /// with the settings the project runs with.
+    fn get_or_create(&self, key: ProjectKey, config: &mut Config) -> anyhow::Result<ProjectHandle> {
+        self.open_project(key, config, false)
+    }
+
+    /// Registers a session with the project for `key`, starting it when
+    /// needed.
+    fn attach(
+        &self,
+        key: ProjectKey,
+        mut config: Config,
+    ) -> anyhow::Result<(ProjectHandle, SessionRegistration)> {
+        let handle = self.open_project(key, &mut config, true)?;
+        match handle.register_session(config) {
+            Ok(registration) => Ok((handle, registration)),
+            Err(err) => {
+                handle.session_ended();
+                Err(err)
+            }
+        }
+    }
+
+    /// [`Self::get_or_create`]; with `reserve_session` the session cap is
+    /// checked and the slot taken under the same lock, so concurrent
+    /// attaches cannot both get the last one.
+    fn open_project(
+        &self,
+        key: ProjectKey,
+        config: &mut Config,
+        reserve_session: bool,
+    ) -> anyhow::Result<ProjectHandle> {
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        if guard.closed {
+            return Err(anyhow!("the daemon is shutting down"));
+        }
+        guard.evict_idle_entries();
+        let memory_limit = guard.limits.max_old_space_size(&key.root);
+        if let Some(megabytes) = memory_limit {
+            config.plugin_mut().tsserver.max_old_space_size = Some(megabytes);
+        }
+        let max_sessions = guard.limits.max_sessions;
+        if let Some(entry) = guard.entries.get_mut(&key) {
+            if let Some(max) = max_sessions
+                && entry.session_count.load(Ordering::Relaxed) >= max
+            {
+                return Err(anyhow!(
+                    "project {} already has {max} session(s), the daemon's limit",
+                    entry.handle.label()
+                ));
+            }
+            if reserve_session {
+                entry.handle.session_started();
+            } else {
+                entry.touch();
+            }
+            return Ok(entry.handle.clone());
+        }
+
+        let normalized = key.root.clone();
+        let provider = Provider::new(normalized.clone());
+        let last_used = Arc::new(AtomicU64::new(current_epoch_seconds()));
         let session_count = Arc::new(AtomicUsize::new(0));
         let handle = ProjectHandle::spawn(
             normalized.clone(),
-            config,
+            config.clone(),
+            memory_limit,
             provider,
             last_used.clone(),
             session_count.clone(),
@@ -411,28 +1402,190 @@ impl ProjectRegistry {
             last_used,
             session_count,
         };
-        guard.entries.insert(normalized, entry);
+        if reserve_session {
+            handle.session_started();
+        }
+        guard.entries.insert(key.clone(), entry);
+        guard.evict_overflow_entries(Some(&key));
         Ok(handle)
     }
 
-    fn spawn_eviction_loop(&self) {
-        let idle_ttl = {
-            let guard = self.inner.lock().expect("project registry mutex poisoned");
-            guard.idle_ttl
+    /// Shuts every project down and waits for their tsservers to exit;
+    /// returns how many there were.
+    /// Attached sessions are told the daemon is stopping, and no project can
+    /// be opened afterwards.
+    fn shutdown_all(&self) -> usize {
+        let (entries, parked): (Vec<ProjectEntry>, Vec<ParkedSession>) = {
+            let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+            guard.closed = true;
+            (
+                guard.entries.drain().map(|(_, entry)| entry).collect(),
+                guard.parked.drain().map(|(_, parked)| parked).collect(),
+            )
         };
-        let Some(idle_ttl) = idle_ttl else {
+        drop(parked);
+        let pending: Vec<Receiver<()>> = entries
+            .iter()
+            .map(|entry| entry.handle.shutdown(Some(ProjectEvent::DaemonStopping)))
+            .collect();
+        for done in pending {
+            let _ = done.recv();
+        }
+        entries.len()
+    }
+
+    fn idle_ttl(&self) -> Option<Duration> {
+        let guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.idle_ttl
+    }
+
+    /// No projects are running and no session waits to be resumed.
+    fn is_idle(&self) -> bool {
+        let guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.entries.is_empty() && guard.parked.is_empty()
+    }
+
+    /// Whether sessions outlive their connection for a while; only the
+    /// daemon's do.
+    fn resumable(&self) -> bool {
+        let guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.resume_window.is_some()
+    }
+
+    /// Keeps the session of a disconnected client until the resume window
+    /// passes; it stays registered with its projects meanwhile.
+    fn park(&self, session: SessionState) {
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        let Some(window) = guard.resume_window.filter(|_| !guard.closed) else {
+            drop(guard);
+            drop(session);
             return;
         };
-        if idle_ttl.is_zero() {
-            return;
+        log::info!(
+            "session {} disconnected; resumable for {}s",
+            session.session_id,
+            window.as_secs()
+        );
+        guard.parked.insert(
+            session.resume_token.clone(),
+            ParkedSession {
+                session: Box::new(session),
+                until: Instant::now() + window,
+            },
+        );
+    }
+
+    fn track_session(&self, token: &str) -> Arc<AtomicBool> {
+        let takeover = Arc::new(AtomicBool::new(false));
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.takeovers.insert(token.to_string(), takeover.clone());
+        takeover
+    }
+
+    fn forget_session(&self, token: &str) {
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.takeovers.remove(token);
+    }
+
+    /// Hands the session under `token` to a client reconnecting from the
+    /// same workspace `root`. A session whose old connection still looks
+    /// alive (the blip may not have reached it) is asked to let go first.
+    fn resume(&self, token: &str, root: &Path) -> Option<SessionState> {
+        let deadline = Instant::now() + RESUME_HANDOFF_TIMEOUT;
+        let parked = loop {
+            {
+                let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+                if let Some(parked) = guard.parked.get(token) {
+                    if normalize_root(parked.session.workspace_root.clone()) != root {
+                        log::warn!(
+                            "resume token belongs to session {} of another workspace",
+                            parked.session.session_id
+                        );
+                        return None;
+                    }
+                    break guard.parked.remove(token)?;
+                }
+                guard.takeovers.get(token)?.store(true, Ordering::Relaxed);
+            }
+            if Instant::now() >= deadline {
+                log::warn!("session to resume did not release its old connection in time");
+                return None;
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+        (parked.until > Instant::now()).then_some(*parked.session)
+    }
+
+    /// Ends parked sessions nobody resumed in time.
+    fn expire_parked(&self) {
+        let expired: Vec<ParkedSession> = {
+            let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+            let now = Instant::now();
+            let tokens: Vec<String> = guard
+                .parked
+                .iter()
+                .filter(|(_, parked)| parked.until <= now)
+                .map(|(token, _)| token.clone())
+                .collect();
+            tokens
+                .iter()
+                .filter_map(|token| guard.parked.remove(token))
+                .collect()
+        };
+        for parked in expired {
+            log::info!(
+                "session {} was not resumed in time",
+                parked.session.session_id
+            );
         }
+    }
+
+    fn max_entries(&self) -> Option<usize> {
+        let guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.max_entries
+    }
+
+    fn limits(&self) -> ProjectLimits {
+        let guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.limits.clone()
+    }
+
+    fn set_limits(&self, limits: ProjectLimits) {
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.limits = limits;
+    }
+
+    /// Switches to reloaded settings, evicting what they no longer allow.
+    fn apply_settings(&self, settings: &DaemonSettings) {
+        let mut guard = self.inner.lock().expect("project registry mutex poisoned");
+        guard.idle_ttl = settings.idle_ttl;
+        guard.max_entries = settings.max_projects;
+        guard.maybe_evict();
+    }
+
+    fn spawn_eviction_loop(&self) {
+        let registry = self.clone();
+        thread::spawn(move || registry.evict_idle_loop());
+    }
+
+    fn spawn_parked_sweep(&self) {
         let registry = self.clone();
-        thread::spawn(move || registry.evict_idle_loop(idle_ttl));
+        thread::spawn(move || {
+            loop {
+                thread::sleep(PARKED_SWEEP_INTERVAL);
+                registry.expire_parked();
+            }
+        });
     }
 
-    fn evict_idle_loop(self, idle_ttl: Duration) {
-        let sweep_interval = idle_sweep_interval(idle_ttl);
+    /// Sweeps at an interval derived from the current idle TTL, which a
+    /// reload may change.
+    fn evict_idle_loop(self) {
         loop {
+            let sweep_interval = self
+                .idle_ttl()
+                .filter(|ttl| !ttl.is_zero())
+                .map_or(IDLE_RECHECK_INTERVAL, idle_sweep_interval);
             thread::sleep(sweep_interval);
             let mut guard = self.inner.lock().expect("project registry mutex poisoned");
             guard.maybe_evict();
@@ -456,7 +1609,7 @@ impl ProjectEntry {
 impl ProjectRegistryState {
     fn maybe_evict(&mut self) {
         self.evict_idle_entries();
-        self.evict_overflow_entries();
+        self.evict_overflow_entries(None);
     }
 
     fn evict_idle_entries(&mut self) {
@@ -469,23 +1622,28 @@ impl ProjectRegistryState {
         let ttl_secs = idle_ttl.as_secs();
         let now = current_epoch_seconds();
         let mut expired = Vec::new();
-        for (root, entry) in self.entries.iter() {
+        for (key, entry) in self.entries.iter() {
             if entry.session_count.load(Ordering::Relaxed) > 0 {
                 continue;
             }
             let last_used = entry.last_used.load(Ordering::Relaxed);
             if now.saturating_sub(last_used) >= ttl_secs {
-                expired.push(root.clone());
+                expired.push(key.clone());
             }
         }
-        for root in expired {
-            if let Some(entry) = self.entries.remove(&root) {
-                entry.handle.shutdown();
+        for key in expired {
+            if let Some(entry) = self.entries.remove(&key) {
+                entry
+                    .handle
+                    .evict("idle for longer than the idle TTL".to_string());
             }
         }
     }
 
-    fn evict_overflow_entries(&mut self) {
+    /// Shuts down the least recently used projects beyond `max_entries`,
+    /// those without sessions first; `keep` (the project just opened) is
+    /// never chosen. Sessions still attached are told before it happens.
+    fn evict_overflow_entries(&mut self, keep: Option<&ProjectKey>) {
         let Some(max_entries) = self.max_entries else {
             return;
         };
@@ -493,23 +1651,48 @@ impl ProjectRegistryState {
             return;
         }
 
-        let mut candidates = self
-            .entries
-            .iter()
-            .map(|(root, entry)| (entry.last_used.load(Ordering::Relaxed), root.clone()))
-            .collect::<Vec<_>>();
-        candidates.sort_by_key(|(last_used, _)| *last_used);
-        for (_, root) in candidates
-            .into_iter()
-            .take(self.entries.len() - max_entries)
-        {
-            if let Some(entry) = self.entries.remove(&root) {
-                entry.handle.shutdown();
+        let candidates = overflow_candidates(
+            self.entries
+                .iter()
+                .filter(|(key, _)| Some(*key) != keep)
+                .map(|(key, entry)| {
+                    (
+                        key.clone(),
+                        entry.session_count.load(Ordering::Relaxed),
+                        entry.last_used.load(Ordering::Relaxed),
+                    )
+                })
+                .collect(),
+            self.entries.len() - max_entries,
+        );
+        for key in candidates {
+            if let Some(entry) = self.entries.remove(&key) {
+                log::info!(
+                    "evicting project {} (over the {max_entries} project limit)",
+                    entry.handle.label()
+                );
+                entry.handle.evict(format!(
+                    "the daemon keeps at most {max_entries} project(s) running"
+                ));
             }
         }
     }
 }
 
+/// The `count` projects to evict from `(key, sessions, last_used)`: idle
+/// ones before those with sessions, least recently used first.
+fn overflow_candidates(
+    mut projects: Vec<(ProjectKey, usize, u64)>,
+    count: usize,
+) -> Vec<ProjectKey> {
+    projects.sort_by_key(|(_, sessions, last_used)| (*sessions > 0, *last_used));
+    projects
+        .into_iter()
+        .take(count)
+        .map(|(key, _, _)| key)
+        .collect()
+}
+
 fn normalize_root(root: PathBuf) -> PathBuf {
     root.canonicalize().unwrap_or(root)
 }
@@ -521,12 +1704,16 @@ struct ProjectHandle {
     commands: Sender<ProjectCommand>,
     last_used: Arc<AtomicU64>,
     session_count: Arc<AtomicUsize>,
+    owners: DocumentOwners,
 }
 
 impl ProjectHandle {
+    /// `memory_limit` overrides `tsserver.max_old_space_size` for good,
+    /// whatever settings sessions send later.
     fn spawn(
         root: PathBuf,
         config: Config,
+        memory_limit: Option<u32>,
         provider: Provider,
         last_used: Arc<AtomicU64>,
         session_count: Arc<AtomicUsize>,
@@ -534,16 +1721,31 @@ impl ProjectHandle {
         let label = friendly_project_name(&root);
         let (tx, rx) = unbounded();
         let label_clone = label.clone();
-        thread::spawn(move || project_thread(config, provider, label_clone, rx));
+        let root_clone = root.clone();
+        let sessions = ProjectSessions::default();
+        let owners = sessions.documents.owners();
+        thread::spawn(move || {
+            project_thread(
+                config,
+                memory_limit,
+                provider,
+                label_clone,
+                root_clone,
+                sessions,
+                rx,
+            )
+        });
         Self {
             root,
             label,
             commands: tx,
             last_used,
             session_count,
+            owners,
         }
     }
 
+    /// The session's slot is taken by [`ProjectRegistry::attach`].
     fn register_session(&self, config: Config) -> anyhow::Result<SessionRegistration> {
         let session_id = next_session_id();
         let (event_tx, event_rx) = unbounded();
@@ -552,14 +1754,13 @@ impl ProjectHandle {
             .send(ProjectCommand::RegisterSession {
                 session_id,
                 sender: event_tx,
-                config,
+                config: Box::new(config),
                 reply: reply_tx,
             })
             .context("register session with project service")?;
         let confirmed = reply_rx
             .recv()
             .context("receive project session configuration")?;
-        self.session_started();
         Ok(SessionRegistration {
             session_id,
             events: event_rx,
@@ -596,6 +1797,72 @@ impl ProjectHandle {
             .map_err(|err| anyhow!(err))
     }
 
+    /// Applies a session's document notification to the project's shared
+    /// documents, sending `payload` (or a replacement for it) to tsserver.
+    fn sync_document(
+        &self,
+        session_id: SessionId,
+        event: DocumentEvent,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+    ) -> anyhow::Result<Vec<DispatchReceipt>> {
+        self.touch();
+        let (reply_tx, reply_rx) = bounded(0);
+        self.commands
+            .send(ProjectCommand::SyncDocument {
+                session_id,
+                event: Box::new(event),
+                route,
+                payload,
+                priority,
+                reply: Some(reply_tx),
+            })
+            .context("sync document with project service")?;
+        reply_rx
+            .recv()
+            .context("receive project document sync receipt")?
+            .map_err(|err| anyhow!(err))
+    }
+
+    /// Whether `session_id` alone has `uri` open, in sync with tsserver.
+    fn owns(&self, session_id: SessionId, uri: &lsp_types::Uri) -> bool {
+        self.owners
+            .lock()
+            .map(|owners| owners.get(uri.as_str()) == Some(&session_id))
+            .unwrap_or(false)
+    }
+
+    /// Fire-and-forget [`Self::sync_document`] for an owned document's
+    /// edits. Should another session have opened it in the meantime, the
+    /// project asks for the buffer with [`ProjectEvent::Resync`].
+    fn forward_document_edits(
+        &self,
+        session_id: SessionId,
+        event: DocumentEvent,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+    ) -> anyhow::Result<()> {
+        self.touch();
+        self.commands
+            .send(ProjectCommand::SyncDocument {
+                session_id,
+                event: Box::new(event),
+                route,
+                payload,
+                priority,
+                reply: None,
+            })
+            .context("sync document with project service")
+    }
+
+    /// Fire-and-forget: tsserver still answers a cancelled request (with an
+    /// error), which the session no longer tracks.
+    fn cancel(&self, server: ServerKind, seq: u64) {
+        let _ = self.commands.send(ProjectCommand::Cancel { server, seq });
+    }
+
     fn update_config(&self, settings: Value) -> anyhow::Result<ConfigUpdate> {
         self.touch();
         let (reply_tx, reply_rx) = bounded(0);
@@ -623,8 +1890,34 @@ impl ProjectHandle {
             .map_err(|err| anyhow!(err))
     }
 
-    fn shutdown(&self) {
-        let _ = self.commands.send(ProjectCommand::Shutdown);
+    /// Stops the project thread after sending `notice` to attached
+    /// sessions; the returned channel fires once its tsservers are gone.
+    fn shutdown(&self, notice: Option<ProjectEvent>) -> Receiver<()> {
+        let (done_tx, done_rx) = bounded(1);
+        let _ = self.commands.send(ProjectCommand::Shutdown {
+            notice,
+            done: done_tx,
+        });
+        done_rx
+    }
+
+    /// Shuts the project down after telling attached sessions `reason`.
+    fn evict(&self, reason: String) {
+        let _ = self.shutdown(Some(ProjectEvent::Evicting { reason }));
+    }
+
+    /// Starts tsserver and has it load the project's tsconfig, unless it
+    /// already runs.
+    fn preload(&self) -> anyhow::Result<ProjectPreload> {
+        self.touch();
+        let (reply_tx, reply_rx) = bounded(0);
+        self.commands
+            .send(ProjectCommand::Preload { reply: reply_tx })
+            .context("preload project")?;
+        reply_rx
+            .recv()
+            .context("receive project preload result")?
+            .map_err(|err| anyhow!(err))
     }
 
     fn status(&self) -> anyhow::Result<ProjectThreadStatus> {
@@ -672,6 +1965,35 @@ struct SessionInit {
     config: Config,
     workspace_root: PathBuf,
     session_id: SessionId,
+    /// The client resolves `label.location`, so inlay hints are interactive.
+    interactive_inlay_hints: bool,
+    /// Unknown or mistyped keys in the settings applied at startup.
+    settings_problems: Vec<String>,
+    trace: lsp_types::TraceValue,
+    client: ClientFingerprint,
+}
+
+/// What a client said about itself at `initialize`. A client resuming a
+/// session must say the same, since the session was set up for it.
+#[derive(Debug, Clone, PartialEq)]
+struct ClientFingerprint {
+    capabilities: lsp_types::ClientCapabilities,
+    /// `initializationOptions` without `resumeToken`.
+    options: Option<Value>,
+}
+
+impl ClientFingerprint {
+    fn of(params: &InitializeParams) -> Self {
+        let mut options = params.initialization_options.clone();
+        if let Some(Value::Object(map)) = options.as_mut() {
+            map.remove("resumeToken");
+        }
+        Self {
+            capabilities: params.capabilities.clone(),
+            options: options
+                .filter(|options| options.as_object().is_none_or(|map| !map.is_empty())),
+        }
+    }
 }
 
 struct SessionRegistration {
@@ -689,17 +2011,63 @@ struct ConfigUpdate {
 struct ProjectThreadStatus {
     session_count: usize,
     session_ids: Vec<SessionId>,
-    tsserver_syntax_pid: Option<u32>,
-    tsserver_semantic_pid: Option<u32>,
+    tsserver: TsserverStatus,
+}
+
+struct ProjectPreload {
+    /// tsserver was up before the preload; nothing was opened.
+    already_running: bool,
+    /// The tsconfig/jsconfig tsserver was asked to load.
+    config_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 enum ProjectEvent {
     Server(ServerEvent),
-    Restarting { kind: RestartKind },
-    Restarted { kind: RestartKind },
-    RestartFailed { kind: RestartKind, message: String },
-    ConfigUpdated(Config),
+    Restarting {
+        kind: RestartKind,
+    },
+    Restarted {
+        kind: RestartKind,
+    },
+    RestartFailed {
+        kind: RestartKind,
+        message: String,
+    },
+    /// A tsserver exited without being asked to; `retry_in` is `None` once
+    /// automatic restarts gave up.
+    ServerExited {
+        kind: RestartKind,
+        reason: String,
+        retry_in: Option<Duration>,
+    },
+    /// A line tsserver printed to stderr (only with `tsserver.forward_stderr`).
+    Stderr {
+        server: ServerKind,
+        line: String,
+    },
+    ConfigUpdated(Box<Config>),
+    /// `separate_diagnostic_server` flipped and the semantic server was
+    /// started (`enabled`) or stopped.
+    DiagnosticServerToggled {
+        enabled: bool,
+    },
+    /// The registry is shutting the project down under the session.
+    Evicting {
+        reason: String,
+    },
+    /// The daemon is exiting; the connection closes shortly.
+    DaemonStopping,
+    /// The session's edits to `uri` arrived without its text while tsserver
+    /// holds another session's copy; it should send its whole buffer.
+    Resync {
+        uri: lsp_types::Uri,
+    },
+    /// Another session's buffer replaced `uri` in tsserver, dropping this
+    /// session's edits it had not seen.
+    Overwritten {
+        uri: lsp_types::Uri,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -709,6 +2077,15 @@ enum RestartKind {
     Both,
 }
 
+impl From<ServerKind> for RestartKind {
+    fn from(kind: ServerKind) -> Self {
+        match kind {
+            ServerKind::Syntax => Self::Syntax,
+            ServerKind::Semantic => Self::Semantic,
+        }
+    }
+}
+
 impl RestartKind {
     fn from_str(value: &str) -> Option<Self> {
         match value {
@@ -740,7 +2117,7 @@ enum ProjectCommand {
     RegisterSession {
         session_id: SessionId,
         sender: Sender<ProjectEvent>,
-        config: Config,
+        config: Box<Config>,
         reply: Sender<Config>,
     },
     UnregisterSession {
@@ -752,10 +2129,24 @@ enum ProjectCommand {
         priority: Priority,
         reply: Sender<Result<Vec<DispatchReceipt>, ServiceError>>,
     },
+    /// A didOpen/didChange/didClose, applied to the shared documents before
+    /// tsserver sees it. Owned documents' edits come without `reply`.
+    SyncDocument {
+        session_id: SessionId,
+        event: Box<DocumentEvent>,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+        reply: Option<Sender<Result<Vec<DispatchReceipt>, ServiceError>>>,
+    },
     UpdateConfig {
         settings: Value,
         reply: Sender<ConfigUpdate>,
     },
+    Cancel {
+        server: ServerKind,
+        seq: u64,
+    },
     Restart {
         kind: RestartKind,
         reply: Sender<Result<(), ServiceError>>,
@@ -763,26 +2154,85 @@ enum ProjectCommand {
     Status {
         reply: Sender<ProjectThreadStatus>,
     },
-    Shutdown,
+    Preload {
+        reply: Sender<Result<ProjectPreload, ServiceError>>,
+    },
+    Shutdown {
+        /// Broadcast to sessions first, when they should hear about it.
+        notice: Option<ProjectEvent>,
+        done: Sender<()>,
+    },
 }
 
 type SessionId = u64;
 
+/// The sessions attached to one project and the documents they share.
+#[derive(Default)]
+struct ProjectSessions {
+    attached: HashMap<SessionId, Sender<ProjectEvent>>,
+    documents: SharedDocuments,
+}
+
 static SESSION_IDS: AtomicU64 = AtomicU64::new(1);
 
 fn next_session_id() -> SessionId {
     SESSION_IDS.fetch_add(1, Ordering::Relaxed)
 }
 
-fn project_thread(config: Config, provider: Provider, label: String, rx: Receiver<ProjectCommand>) {
+/// 128 bits from the OS random number generator, as hex. Whoever holds the
+/// token can take the session over, so it must not be guessable.
+fn new_resume_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS random number generator unavailable");
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn project_thread(
+    config: Config,
+    memory_limit: Option<u32>,
+    provider: Provider,
+    label: String,
+    root: PathBuf,
+    mut sessions: ProjectSessions,
+    rx: Receiver<ProjectCommand>,
+) {
+    crate::logging::set_project(&label);
     let mut service = Service::new(config.clone(), provider);
     let mut config = config;
-    let mut sessions: HashMap<SessionId, Sender<ProjectEvent>> = HashMap::new();
     let poll_interval = Duration::from_millis(10);
     loop {
         for event in service.poll_responses() {
             broadcast_event(&mut sessions, ProjectEvent::Server(event));
         }
+        let forward_stderr = config.plugin().tsserver.forward_stderr;
+        for (server, line) in service.poll_stderr() {
+            if forward_stderr {
+                broadcast_event(&mut sessions, ProjectEvent::Stderr { server, line });
+            }
+        }
+        for event in service.poll_health() {
+            let event = match event {
+                HealthEvent::Exited {
+                    server,
+                    reason,
+                    retry_in,
+                } => {
+                    log::warn!("project {label}: tsserver {server:?} {reason}");
+                    ProjectEvent::ServerExited {
+                        kind: server.into(),
+                        reason,
+                        retry_in,
+                    }
+                }
+                HealthEvent::Restarted { server } => {
+                    log::info!("project {label}: tsserver {server:?} restarted");
+                    ProjectEvent::Restarted {
+                        kind: server.into(),
+                    }
+                }
+            };
+            broadcast_event(&mut sessions, event);
+        }
 
         let command = match rx.recv_timeout(poll_interval) {
             Ok(command) => command,
@@ -790,11 +2240,22 @@ fn project_thread(config: Config, provider: Provider, label: String, rx: Receive
             Err(RecvTimeoutError::Disconnected) => break,
         };
 
-        if !handle_project_command(command, &mut service, &mut config, &mut sessions, &label) {
+        let mut handle = |command| {
+            handle_project_command(
+                command,
+                &mut service,
+                &mut config,
+                &mut sessions,
+                &label,
+                &root,
+                memory_limit,
+            )
+        };
+        if !handle(command) {
             break;
         }
         while let Ok(command) = rx.try_recv() {
-            if !handle_project_command(command, &mut service, &mut config, &mut sessions, &label) {
+            if !handle(command) {
                 return;
             }
         }
@@ -805,8 +2266,10 @@ fn handle_project_command(
     command: ProjectCommand,
     service: &mut Service,
     config: &mut Config,
-    sessions: &mut HashMap<SessionId, Sender<ProjectEvent>>,
+    sessions: &mut ProjectSessions,
     label: &str,
+    root: &Path,
+    memory_limit: Option<u32>,
 ) -> bool {
     match command {
         ProjectCommand::RegisterSession {
@@ -815,17 +2278,18 @@ fn handle_project_command(
             config: session_config,
             reply,
         } => {
-            if session_config != *config {
+            if *session_config != *config {
                 log::warn!(
                     "session config mismatch for project {label}; using first session settings"
                 );
             }
-            sessions.insert(session_id, sender);
+            sessions.attached.insert(session_id, sender);
             let _ = reply.send(config.clone());
             true
         }
         ProjectCommand::UnregisterSession { session_id } => {
-            sessions.remove(&session_id);
+            sessions.attached.remove(&session_id);
+            release_documents(service, sessions, session_id, root);
             true
         }
         ProjectCommand::Dispatch {
@@ -838,12 +2302,97 @@ fn handle_project_command(
             let _ = reply.send(result);
             true
         }
+        ProjectCommand::SyncDocument {
+            session_id,
+            event,
+            route,
+            payload,
+            priority,
+            reply,
+        } => {
+            let result = match sessions.documents.apply(session_id, &event) {
+                SharedSync::Forward => service.dispatch_request(route, payload, priority),
+                SharedSync::Skip => Ok(Vec::new()),
+                SharedSync::Resync => {
+                    if let (DocumentEvent::Change { uri, .. }, Some(session)) =
+                        (*event, sessions.attached.get(&session_id))
+                    {
+                        let _ = session.send(ProjectEvent::Resync { uri });
+                    }
+                    Ok(Vec::new())
+                }
+                SharedSync::Replace { edit, overwritten } => {
+                    let uri = match *event {
+                        DocumentEvent::Open { uri, .. }
+                        | DocumentEvent::Change { uri, .. }
+                        | DocumentEvent::Close { uri } => uri,
+                    };
+                    if !overwritten.is_empty() {
+                        log::warn!(
+                            "project {label}: session {session_id} replaced {} in tsserver, dropping edits from session(s) {overwritten:?}",
+                            uri.as_str()
+                        );
+                    }
+                    for other in overwritten {
+                        if let Some(session) = sessions.attached.get(&other) {
+                            let _ = session.send(ProjectEvent::Overwritten { uri: uri.clone() });
+                        }
+                    }
+                    let params = crate::types::DidChangeTextDocumentParams {
+                        text_document: crate::types::VersionedTextDocumentIdentifier {
+                            uri: uri.to_string(),
+                            version: None,
+                        },
+                        content_changes: vec![edit],
+                    };
+                    let spec = crate::protocol::text_document::did_change::handle(params, root);
+                    service.dispatch_request(spec.route, spec.payload, spec.priority)
+                }
+            };
+            match reply {
+                Some(reply) => {
+                    let _ = reply.send(result);
+                }
+                None => {
+                    if let Err(err) = result {
+                        log::warn!("project {label}: failed to forward document edits: {err}");
+                    }
+                }
+            }
+            true
+        }
+        ProjectCommand::Cancel { server, seq } => {
+            if let Err(err) = service.cancel(server, seq) {
+                log::debug!("failed to cancel tsserver request {seq} on {server:?}: {err}");
+            }
+            true
+        }
         ProjectCommand::UpdateConfig { settings, reply } => {
-            let changed = config.apply_workspace_settings(&settings);
+            let before = config.clone();
+            let mut changed = config.apply_client_settings(&settings);
+            if let Some(megabytes) = memory_limit
+                && config.plugin().tsserver.max_old_space_size != Some(megabytes)
+            {
+                log::info!(
+                    "project {label}: keeping the daemon's max_old_space_size of {megabytes} MB"
+                );
+                config.plugin_mut().tsserver.max_old_space_size = Some(megabytes);
+                changed = *config != before;
+            }
             if changed {
                 log::info!("project {label} settings updated");
-                service.update_config(config.clone());
-                broadcast_event(sessions, ProjectEvent::ConfigUpdated(config.clone()));
+                let toggled = service.update_config(config.clone());
+                broadcast_event(
+                    sessions,
+                    ProjectEvent::ConfigUpdated(Box::new(config.clone())),
+                );
+                if let Some(enabled) = toggled {
+                    log::info!(
+                        "project {label}: separate diagnostic server {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                    broadcast_event(sessions, ProjectEvent::DiagnosticServerToggled { enabled });
+                }
             }
             let _ = reply.send(ConfigUpdate {
                 changed,
@@ -869,49 +2418,132 @@ fn handle_project_command(
             true
         }
         ProjectCommand::Status { reply } => {
-            let status = service.tsserver_status();
-            let mut session_ids = sessions.keys().copied().collect::<Vec<_>>();
+            let mut session_ids = sessions.attached.keys().copied().collect::<Vec<_>>();
             session_ids.sort_unstable();
             let _ = reply.send(ProjectThreadStatus {
                 session_count: session_ids.len(),
                 session_ids,
-                tsserver_syntax_pid: status.syntax_pid,
-                tsserver_semantic_pid: status.semantic_pid,
+                tsserver: service.tsserver_status(),
             });
             true
         }
-        ProjectCommand::Shutdown => {
-            sessions.clear();
+        ProjectCommand::Preload { reply } => {
+            let _ = reply.send(preload_service(service, root));
+            true
+        }
+        ProjectCommand::Shutdown { notice, done } => {
+            if let Some(notice) = notice {
+                broadcast_event(sessions, notice);
+            }
+            sessions.attached.clear();
+            service.shutdown();
+            let _ = done.send(());
             false
         }
     }
 }
 
-fn broadcast_event(sessions: &mut HashMap<SessionId, Sender<ProjectEvent>>, event: ProjectEvent) {
+fn broadcast_event(sessions: &mut ProjectSessions, event: ProjectEvent) {
+    // Restarted servers have nothing open; sessions reopen their documents
+    // on hearing about it.
+    if matches!(
+        event,
+        ProjectEvent::Restarted { .. } | ProjectEvent::DiagnosticServerToggled { enabled: true }
+    ) {
+        sessions.documents.reset();
+    }
     let mut stale = Vec::new();
-    for (session_id, sender) in sessions.iter() {
+    for (session_id, sender) in sessions.attached.iter() {
         if sender.send(event.clone()).is_err() {
             stale.push(*session_id);
         }
     }
     for session_id in stale {
-        sessions.remove(&session_id);
+        sessions.attached.remove(&session_id);
     }
 }
 
-fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
+/// Starts tsserver for a project nobody has used yet. Opening the root's
+/// tsconfig (or jsconfig) makes tsserver load the configured project; closing
+/// it again leaves the project loaded for the first file an editor opens.
+fn preload_service(service: &mut Service, root: &Path) -> Result<ProjectPreload, ServiceError> {
+    if service.tsserver_status().syntax_pid.is_some() {
+        return Ok(ProjectPreload {
+            already_running: true,
+            config_file: None,
+        });
+    }
+    let config_file = ["tsconfig.json", "jsconfig.json"]
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file());
+    let Some(config_file) = config_file else {
+        service.start()?;
+        return Ok(ProjectPreload {
+            already_running: false,
+            config_file: None,
+        });
+    };
+    let file = config_file.to_string_lossy();
+    for (open, closed) in [
+        (json!([{ "file": file }]), json!([])),
+        (json!([]), json!([file])),
+    ] {
+        let payload = json!({
+            "command": "updateOpen",
+            "arguments": {
+                "projectRootPath": root.to_string_lossy(),
+                "openFiles": open,
+                "changedFiles": [],
+                "closedFiles": closed,
+            }
+        });
+        service.dispatch_request(Route::Both, payload, Priority::Const)?;
+    }
+    Ok(ProjectPreload {
+        already_running: false,
+        config_file: Some(config_file),
+    })
+}
+
+/// Closes the documents only `session_id` still had open.
+fn release_documents(
+    service: &mut Service,
+    sessions: &mut ProjectSessions,
+    session_id: SessionId,
+    root: &Path,
+) {
+    for uri in sessions.documents.release(session_id) {
+        let params = crate::types::DidCloseTextDocumentParams {
+            text_document: crate::types::TextDocumentIdentifier { uri },
+        };
+        let spec = crate::protocol::text_document::did_close::handle(params, root);
+        if let Err(err) = service.dispatch_request(spec.route, spec.payload, spec.priority) {
+            log::debug!("failed to close a released document: {err}");
+        }
+    }
+}
+
+fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
+    let plugin_languages = settings.tsserver.plugin_languages();
     let text_sync = TextDocumentSyncOptions {
         open_close: Some(true),
         change: Some(TextDocumentSyncKind::INCREMENTAL),
         will_save: Some(false),
-        will_save_wait_until: Some(false),
+        will_save_wait_until: Some(settings.organize_imports_on_save || settings.fix_all_on_save),
         save: Some(TextDocumentSyncSaveOptions::SaveOptions(
             lsp_types::SaveOptions::default(),
         )),
     };
     let completion_provider = CompletionOptions {
         resolve_provider: Some(true),
-        trigger_characters: Some(TRIGGER_CHARACTERS.iter().map(|ch| ch.to_string()).collect()),
+        trigger_characters: Some(
+            TRIGGER_CHARACTERS
+                .iter()
+                .chain([&doc_comment_template::TRIGGER_CHARACTER])
+                .map(|ch| ch.to_string())
+                .collect(),
+        ),
         ..CompletionOptions::default()
     };
     let signature_help_provider = SignatureHelpOptions {
@@ -925,13 +2557,32 @@ fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
         ..SignatureHelpOptions::default()
     };
     let code_action_provider = CodeActionProviderCapability::Options(CodeActionOptions {
-        code_action_kinds: Some(vec![
-            CodeActionKind::QUICKFIX,
-            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
-        ]),
+        code_action_kinds: Some(
+            vec![
+                CodeActionKind::QUICKFIX,
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                CodeActionKind::REFACTOR,
+                CodeActionKind::REFACTOR_EXTRACT,
+                CodeActionKind::REFACTOR_INLINE,
+                CodeActionKind::REFACTOR_REWRITE,
+                CodeActionKind::new("refactor.move"),
+            ]
+            .into_iter()
+            .chain(crate::protocol::text_document::code_action::source_action_kinds())
+            .collect(),
+        ),
         resolve_provider: Some(true),
         work_done_progress_options: Default::default(),
     });
+    let on_type_formatting_provider = DocumentOnTypeFormattingOptions {
+        first_trigger_character: ON_TYPE_FIRST_TRIGGER.to_string(),
+        more_trigger_character: Some(
+            ON_TYPE_MORE_TRIGGERS
+                .iter()
+                .map(|ch| ch.to_string())
+                .collect(),
+        ),
+    };
     let rename_provider = OneOf::Right(RenameOptions {
         prepare_provider: Some(true),
         work_done_progress_options: Default::default(),
@@ -945,7 +2596,7 @@ fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
                 work_done_progress_options: Default::default(),
             },
         );
-    let inlay_hint_provider = if settings.enable_inlay_hints {
+    let inlay_hint_provider = if settings.any_inlay_hints_enabled() {
         Some(OneOf::Right(InlayHintServerCapabilities::Options(
             InlayHintOptions {
                 work_done_progress_options: Default::default(),
@@ -955,6 +2606,13 @@ fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
     } else {
         None
     };
+    let code_lens_provider = if settings.code_lens.is_enabled() {
+        Some(CodeLensOptions {
+            resolve_provider: Some(true),
+        })
+    } else {
+        None
+    };
     let execute_command_provider = Some(ExecuteCommandOptions {
         commands: crate::protocol::workspace::execute_command::USER_COMMANDS
             .iter()
@@ -966,6 +2624,7 @@ fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
         position_encoding: Some(PositionEncodingKind::UTF16),
         hover_provider: Some(HoverProviderCapability::Simple(true)),
         definition_provider: Some(OneOf::Left(true)),
+        declaration_provider: Some(DeclarationCapability::Simple(true)),
         references_provider: Some(OneOf::Left(true)),
         type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
         document_symbol_provider: Some(OneOf::Left(true)),
@@ -975,9 +2634,24 @@ fn advertised_capabilities(settings: &PluginSettings) -> ServerCapabilities {
         code_action_provider: Some(code_action_provider),
         rename_provider: Some(rename_provider),
         document_formatting_provider: Some(OneOf::Left(true)),
+        document_on_type_formatting_provider: Some(on_type_formatting_provider),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
         semantic_tokens_provider: Some(semantic_tokens_provider),
         inlay_hint_provider,
+        code_lens_provider,
         execute_command_provider,
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: None,
+            file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                will_rename: Some(crate::protocol::workspace::rename::file_operation_filters(
+                    &plugin_languages,
+                )),
+                did_rename: Some(file_operations::cleanup_filters(&plugin_languages)),
+                did_delete: Some(file_operations::cleanup_filters(&plugin_languages)),
+                ..Default::default()
+            }),
+        }),
         text_document_sync: Some(TextDocumentSyncCapability::Options(text_sync)),
         ..Default::default()
     }
@@ -987,31 +2661,292 @@ fn run_session(connection: Connection, registry: &ProjectRegistry) -> anyhow::Re
     let (init_id, init_params) = connection
         .initialize_start()
         .context("waiting for initialize")?;
+    start_session(connection, registry, init_id, init_params)
+}
+
+/// Answers `initialize` and runs the session until the client exits.
+fn start_session(
+    connection: Connection,
+    registry: &ProjectRegistry,
+    init_id: RequestId,
+    init_params: Value,
+) -> anyhow::Result<()> {
+    let diagnostic_refresh = init_params
+        .get("capabilities")
+        .is_some_and(pull_diagnostics::supports_refresh);
     let params: InitializeParams =
         serde_json::from_value(init_params).context("invalid initialize params")?;
 
-    let session_init = registry.register_session(&params)?;
-    let capabilities = advertised_capabilities(session_init.config.plugin());
+    // From here on, dropping the session unregisters it from its projects.
+    let (mut session, settings_problems) = match resume_parked(registry, &params) {
+        Some(mut session) => {
+            session.connection = connection;
+            (session, Vec::new())
+        }
+        None => {
+            let mut session_init = match registry.register_session(&params) {
+                Ok(session_init) => session_init,
+                Err(err) => {
+                    // Refused (a limit, or the daemon stopping): the client
+                    // hears why instead of seeing the connection drop.
+                    let response = Response::new_err(
+                        init_id,
+                        ErrorCode::RequestFailed as i32,
+                        format!("ts-bridge: {err}"),
+                    );
+                    connection.sender.send(response.into())?;
+                    return Err(err);
+                }
+            };
+            let settings_problems = std::mem::take(&mut session_init.settings_problems);
+            let session = SessionState::new(connection, session_init, registry.clone());
+            (session, settings_problems)
+        }
+    };
+    let resumed = session.resumed;
+    crate::logging::set_session(session.session_id);
+    crate::logging::set_project(session.project.label());
+    let mut capabilities = advertised_capabilities(session.config.plugin());
+    session.pull_diagnostics = pull_diagnostics::supports_pull(&params.capabilities);
+    session.diagnostic_refresh = diagnostic_refresh;
+    session.snippet_support = completion::supports_snippets(&params.capabilities);
+    if session.pull_diagnostics {
+        capabilities.diagnostic_provider = Some(pull_diagnostics::provider());
+    }
+    let binary = Provider::new(&session.workspace_root)
+        .preview(&session.config.plugin().tsserver)
+        .ok();
+    let outdated_typescript = binary
+        .as_ref()
+        .and_then(|binary| binary.version.as_deref())
+        .and_then(|version| {
+            let parsed = parse_typescript_version(version)?;
+            (parsed < MIN_TYPESCRIPT_VERSION).then(|| {
+                (
+                    version.to_string(),
+                    degrade_capabilities(&mut capabilities, parsed),
+                )
+            })
+        });
     let init_result = InitializeResult {
         server_info: Some(lsp_types::ServerInfo {
             name: "ts-bridge".to_string(),
-            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            version: Some(server_version(binary.as_ref())),
         }),
         capabilities,
     };
+    // A daemon project runs with the settings of the session that started
+    // it, so its servers can differ from this session's configuration.
+    let semantic_server = session
+        .project
+        .status()
+        .is_ok_and(|status| status.tsserver.semantic_server);
+    let ready = server_ready_params(
+        binary.as_ref(),
+        &session.workspace_root,
+        semantic_server,
+        registry
+            .resumable()
+            .then_some(session.resume_token.as_str()),
+    );
+    let connection = &session.connection;
     connection
         .initialize_finish(init_id, serde_json::to_value(init_result)?)
         .context("failed to send initialize result")?;
+    if let Some((version, disabled)) = outdated_typescript {
+        let mut message = format!(
+            "ts-bridge: TypeScript {version} is older than {}.{}; upgrade the project's typescript package",
+            MIN_TYPESCRIPT_VERSION.0, MIN_TYPESCRIPT_VERSION.1
+        );
+        if !disabled.is_empty() {
+            message.push_str(&format!(" (disabled: {})", disabled.join(", ")));
+        }
+        log::warn!("{message}");
+        show_message(connection, &message, lsp_types::MessageType::WARNING)?;
+    }
+    connection
+        .sender
+        .send(Message::Notification(ServerNotification::new(
+            SERVER_READY_METHOD.to_string(),
+            ready,
+        )))
+        .context("failed to send serverReady")?;
+    if did_change_watched_files::supports_dynamic_registration(&params.capabilities) {
+        let request = Request::new(
+            next_request_id(),
+            <RegisterCapability as LspRequest>::METHOD.to_string(),
+            serde_json::to_value(did_change_watched_files::watcher_registration())?,
+        );
+        connection
+            .sender
+            .send(Message::Request(request))
+            .context("failed to register file watchers")?;
+    }
+
+    session.bundled_install = binary
+        .as_ref()
+        .filter(|binary| matches!(binary.source, BinarySource::Bundled))
+        .map(|binary| binary.executable.clone())
+        .filter(|tsserver| !tsserver.is_file());
+    session.region_diagnostics = binary
+        .as_ref()
+        .and_then(|binary| binary.version.as_deref())
+        .and_then(parse_typescript_version)
+        .is_some_and(|version| version >= protocol::diagnostics::MIN_REGION_TYPESCRIPT_VERSION);
+    session.source_definition = binary
+        .as_ref()
+        .and_then(|binary| binary.version.as_deref())
+        .and_then(parse_typescript_version)
+        .is_none_or(|version| version >= definition::MIN_SOURCE_DEFINITION_TYPESCRIPT_VERSION);
+    session.report_settings_problems(settings_problems)?;
+    if resumed {
+        session.replay_after_resume()?;
+    }
+    if session.run()? == SessionEnd::Disconnected && registry.resumable() {
+        session.prepare_to_park();
+        registry.park(session);
+    }
+    Ok(())
+}
+
+/// The parked session named by `initializationOptions.resumeToken`, when it
+/// is still waiting and belongs to the same workspace.
+fn resume_parked(registry: &ProjectRegistry, params: &InitializeParams) -> Option<SessionState> {
+    let token = params
+        .initialization_options
+        .as_ref()?
+        .get("resumeToken")?
+        .as_str()?;
+    let root = normalize_root(workspace_root_from_params(params)?);
+    let Some(mut session) = registry.resume(token, &root) else {
+        log::info!("no session to resume for the given token; starting a new one");
+        return None;
+    };
+    if session.client != ClientFingerprint::of(params) {
+        // Ending it here unregisters it from its projects.
+        log::info!(
+            "client capabilities or options differ from session {}; starting a new one",
+            session.session_id
+        );
+        return None;
+    }
+    log::info!("client resumed session {}", session.session_id);
+    // Asked for while the old connection was letting go.
+    session.takeover.store(false, Ordering::Relaxed);
+    session.resumed = true;
+    Some(session)
+}
+
+/// Oldest TypeScript the handlers are written against; `findSourceDefinition`
+/// arrived in 4.7.
+pub(crate) const MIN_TYPESCRIPT_VERSION: (u32, u32) = (4, 7);
+
+/// `5.4.2` / `4.6.0-beta` -> `(5, 4)`.
+pub(crate) fn parse_typescript_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split(['.', '-']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Drops capabilities whose tsserver command does not exist in `version`,
+/// returning what was turned off.
+fn degrade_capabilities(
+    capabilities: &mut ServerCapabilities,
+    version: (u32, u32),
+) -> Vec<&'static str> {
+    let mut disabled = Vec::new();
+    if version < (4, 4) && capabilities.inlay_hint_provider.take().is_some() {
+        disabled.push("inlay hints");
+    }
+    if version < definition::MIN_SOURCE_DEFINITION_TYPESCRIPT_VERSION
+        && let Some(commands) = capabilities
+            .execute_command_provider
+            .as_mut()
+            .map(|provider| &mut provider.commands)
+        && let Some(index) = commands
+            .iter()
+            .position(|command| command == SOURCE_DEFINITION_COMMAND)
+    {
+        commands.remove(index);
+        disabled.push("go to source definition");
+    }
+    disabled
+}
+
+/// `serverInfo.version`, naming the TypeScript that will back the session.
+fn server_version(binary: Option<&TsserverBinary>) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    match binary {
+        Some(binary) => format!(
+            "{version} (TypeScript {} from {})",
+            binary.version.as_deref().unwrap_or("unknown"),
+            binary.source.label()
+        ),
+        None => version.to_string(),
+    }
+}
+
+const TSSERVER_LOG_METHOD: &str = "ts-bridge/tsserverLog";
+const DEFAULT_LOG_TAIL_KB: u64 = 64;
+const MAX_LOG_TAIL_KB: u64 = 4096;
+
+struct LogTail {
+    size: u64,
+    truncated: bool,
+    content: String,
+}
+
+/// The last `max_bytes` of `path`, starting at a line boundary when the file
+/// is longer.
+fn read_log_tail(path: &Path, max_bytes: u64) -> std::io::Result<LogTail> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let truncated = size > max_bytes;
+    if truncated {
+        file.seek(SeekFrom::Start(size - max_bytes))?;
+    }
+    let mut bytes = Vec::new();
+    file.take(max_bytes).read_to_end(&mut bytes)?;
+    if truncated && let Some(newline) = bytes.iter().position(|byte| *byte == b'\n') {
+        bytes.drain(..=newline);
+    }
+    Ok(LogTail {
+        size,
+        truncated,
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+    })
+}
 
-    let mut session = SessionState::new(connection, session_init, registry.clone());
-    let result = session.run();
-    session.project.unregister_session(session.session_id);
-    result
+/// Sent once `initialize` completes, for statusline plugins.
+const SERVER_READY_METHOD: &str = "ts-bridge/serverReady";
+
+/// `session_token` is what the client passes as
+/// `initializationOptions.resumeToken` to reclaim the session after a
+/// reconnect.
+fn server_ready_params(
+    binary: Option<&TsserverBinary>,
+    workspace_root: &Path,
+    semantic_server: bool,
+    session_token: Option<&str>,
+) -> Value {
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "typescript_version": binary.and_then(|binary| binary.version.as_deref()),
+        "tsserver_source": binary.map(|binary| binary.source.label()),
+        "tsserver_path": binary.map(|binary| binary.executable.to_string_lossy()),
+        "workspace_root": workspace_root.to_string_lossy(),
+        "semantic_server": semantic_server,
+        "session_token": session_token,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::CodeLensMode;
     use lsp_types::{Uri, WorkspaceFolder};
 
     #[test]
@@ -1047,6 +2982,992 @@ mod tests {
         );
     }
 
+    #[test]
+    fn server_ready_names_the_typescript_in_use() {
+        let binary = TsserverBinary {
+            executable: PathBuf::from("/repo/node_modules/typescript/lib/tsserver.js"),
+            plugin_probe: None,
+            version: Some("5.6.2".to_string()),
+            source: BinarySource::LocalNodeModules,
+            node_args: Vec::new(),
+        };
+        let params = server_ready_params(Some(&binary), Path::new("/repo"), true, Some("abc"));
+        assert_eq!(params["typescript_version"], json!("5.6.2"));
+        assert_eq!(params["tsserver_source"], json!("node_modules"));
+        assert_eq!(params["workspace_root"], json!("/repo"));
+        assert_eq!(params["semantic_server"], json!(true));
+        assert_eq!(params["session_token"], json!("abc"));
+
+        let params = server_ready_params(None, Path::new("/repo"), false, None);
+        assert_eq!(params["typescript_version"], Value::Null);
+        assert_eq!(params["session_token"], Value::Null);
+    }
+
+    #[test]
+    fn log_tail_starts_at_a_line_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tsserver.semantic.log");
+        std::fs::write(&path, "Info 0 first\nInfo 1 second\nInfo 2 third\n").unwrap();
+
+        let tail = read_log_tail(&path, 20).unwrap();
+        assert_eq!(tail.size, 40);
+        assert!(tail.truncated);
+        assert_eq!(tail.content, "Info 2 third\n");
+
+        let whole = read_log_tail(&path, 1024).unwrap();
+        assert!(!whole.truncated);
+        assert!(whole.content.starts_with("Info 0 first"));
+    }
+
+    #[test]
+    fn outdated_typescript_drops_unsupported_capabilities() {
+        assert_eq!(parse_typescript_version("4.6.4"), Some((4, 6)));
+        assert_eq!(parse_typescript_version("5.0-beta"), Some((5, 0)));
+
+        let mut caps = advertised_capabilities(&PluginSettings::default());
+        assert_eq!(
+            degrade_capabilities(&mut caps, (4, 3)),
+            ["inlay hints", "go to source definition"]
+        );
+        assert!(caps.inlay_hint_provider.is_none());
+        // Declarations fall back to plain definitions in the handler.
+        assert!(caps.declaration_provider.is_some());
+
+        let mut caps = advertised_capabilities(&PluginSettings::default());
+        assert_eq!(
+            degrade_capabilities(&mut caps, (4, 6)),
+            ["go to source definition"]
+        );
+        assert!(caps.inlay_hint_provider.is_some());
+        let commands = &caps.execute_command_provider.unwrap().commands;
+        assert!(
+            !commands
+                .iter()
+                .any(|command| command == SOURCE_DEFINITION_COMMAND)
+        );
+        assert!(commands.iter().any(|command| command == "TSBFixAll"));
+
+        let mut caps = advertised_capabilities(&PluginSettings::default());
+        assert!(degrade_capabilities(&mut caps, MIN_TYPESCRIPT_VERSION).is_empty());
+    }
+
+    #[test]
+    fn advertised_capabilities_gate_code_lens_on_mode() {
+        let caps = advertised_capabilities(&PluginSettings::default());
+        assert!(caps.code_lens_provider.is_none());
+
+        let settings = PluginSettings {
+            code_lens: CodeLensMode::All,
+            ..Default::default()
+        };
+        let caps = advertised_capabilities(&settings);
+        assert_eq!(
+            caps.code_lens_provider
+                .and_then(|options| options.resolve_provider),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn advertised_capabilities_request_will_save_wait_until_for_save_actions() {
+        let will_save = |settings: &PluginSettings| match advertised_capabilities(settings)
+            .text_document_sync
+        {
+            Some(TextDocumentSyncCapability::Options(options)) => options.will_save_wait_until,
+            _ => None,
+        };
+        assert_eq!(will_save(&PluginSettings::default()), Some(false));
+
+        let settings = PluginSettings {
+            organize_imports_on_save: true,
+            ..Default::default()
+        };
+        assert_eq!(will_save(&settings), Some(true));
+    }
+
+    #[test]
+    fn completion_cache_survives_edits_inside_the_cached_word() {
+        let uri = Uri::from_str("file:///workspace/app.ts").unwrap();
+        let key = CompletionCacheKey {
+            uri: uri.to_string(),
+            line: 3,
+            word_start: 4,
+        };
+        let position = lsp_types::Position {
+            line: 3,
+            character: 5,
+        };
+        let edit_at = |character: u32, text: &str| crate::types::TextDocumentContentChangeEvent {
+            range: Some(crate::types::Range {
+                start: crate::types::Position { line: 3, character },
+                end: crate::types::Position { line: 3, character },
+            }),
+            text: text.to_string(),
+        };
+
+        let mut cache = CompletionCache::default();
+        cache.store(key.clone(), position, lsp_types::CompletionList::default());
+        cache.note_changes(&uri, &[edit_at(5, "o")]);
+        let reused = cache
+            .lookup(
+                &key,
+                lsp_types::Position {
+                    line: 3,
+                    character: 6,
+                },
+            )
+            .expect("typing inside the word keeps the cache");
+        assert!(reused.is_incomplete);
+
+        cache.note_changes(&uri, &[edit_at(1, "x")]);
+        assert!(cache.lookup(&key, position).is_none());
+    }
+
+    #[test]
+    fn pending_requests_cancel_returns_every_receipt_for_the_id() {
+        fn adapter(_: &Value, _: Option<&Value>) -> anyhow::Result<AdapterResult> {
+            Ok(AdapterResult::ready(Value::Null))
+        }
+        let receipt = |server, seq| DispatchReceipt { server, seq };
+        let mut pending = PendingRequests::default();
+        pending.track(
+            &[
+                receipt(ServerKind::Syntax, 4),
+                receipt(ServerKind::Semantic, 4),
+            ],
+            RequestId::from(7),
+            adapter,
+            None,
+            None,
+            None,
+        );
+        pending.track(
+            &[receipt(ServerKind::Syntax, 5)],
+            RequestId::from(8),
+            adapter,
+            None,
+            None,
+            None,
+        );
+
+        let mut keys = pending.cancel(&RequestId::from(7));
+        keys.sort_by_key(|key| key.server == ServerKind::Semantic);
+        assert_eq!(
+            keys,
+            vec![
+                PendingKey {
+                    server: ServerKind::Syntax,
+                    seq: 4
+                },
+                PendingKey {
+                    server: ServerKind::Semantic,
+                    seq: 4
+                },
+            ]
+        );
+        assert!(pending.cancel(&RequestId::from(7)).is_empty());
+        assert_eq!(pending.entries.len(), 1);
+    }
+
+    #[test]
+    fn pending_requests_expire_after_the_timeout() {
+        fn adapter(_: &Value, _: Option<&Value>) -> anyhow::Result<AdapterResult> {
+            Ok(AdapterResult::ready(Value::Null))
+        }
+        let mut pending = PendingRequests::new(&PluginSettings {
+            request_timeout_ms: Some(1_000),
+            ..PluginSettings::default()
+        });
+        let receipt = |server, seq| DispatchReceipt { server, seq };
+        pending.track(
+            &[
+                receipt(ServerKind::Syntax, 1),
+                receipt(ServerKind::Semantic, 1),
+            ],
+            RequestId::from(3),
+            adapter,
+            None,
+            None,
+            None,
+        );
+
+        let (responses, keys) = pending.expire(Instant::now());
+        assert!(responses.is_empty() && keys.is_empty());
+
+        let (responses, keys) = pending.expire(Instant::now() + Duration::from_secs(2));
+        assert_eq!(keys.len(), 2);
+        assert_eq!(responses.len(), 1, "one response per LSP request");
+        assert_eq!(
+            responses[0].error.as_ref().map(|err| err.code),
+            Some(ErrorCode::RequestFailed as i32)
+        );
+        assert!(pending.entries.is_empty());
+    }
+
+    #[test]
+    fn superseded_diagnostics_requests_are_dropped() {
+        let uri = Uri::from_str("file:///repo/a.ts").unwrap();
+        let report = |seq| DiagnosticsEvent::Report {
+            kind: DiagnosticsKind::Syntax,
+            request_seq: Some(seq),
+            uri: uri.clone(),
+            diagnostics: vec![lsp_types::Diagnostic::default()],
+        };
+        let mut state = DiagnosticsState::default();
+        state.register_pending(ServerKind::Syntax, 1, "/repo/a.ts");
+        assert_eq!(state.supersede("/repo/a.ts"), vec![(ServerKind::Syntax, 1)]);
+        state.register_pending(ServerKind::Syntax, 2, "/repo/a.ts");
+
+        state.handle_event(ServerKind::Syntax, report(1));
+        state.handle_event(
+            ServerKind::Syntax,
+            DiagnosticsEvent::Completed { request_seq: 1 },
+        );
+        assert!(state.take_changed(|_| true).is_empty());
+
+        state.handle_event(ServerKind::Syntax, report(2));
+        state.handle_event(
+            ServerKind::Syntax,
+            DiagnosticsEvent::Completed { request_seq: 2 },
+        );
+        let published: Vec<_> = state.take_changed(|_| true);
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].0, uri);
+        assert!(state.in_flight.is_empty());
+    }
+
+    #[test]
+    fn suggestion_diagnostics_are_skipped_when_disabled() {
+        let uri = Uri::from_str("file:///repo/a.ts").unwrap();
+        let diagnostic = |message: &str| lsp_types::Diagnostic {
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let mut state = DiagnosticsState::default();
+        state.set_include_suggestions(false);
+        state.register_pending(ServerKind::Syntax, 1, "/repo/a.ts");
+        assert_eq!(state.workload.expected, 1);
+        for (kind, message) in [
+            (DiagnosticsKind::Syntax, "syntax"),
+            (DiagnosticsKind::Suggestion, "suggestion"),
+        ] {
+            state.handle_event(
+                ServerKind::Syntax,
+                DiagnosticsEvent::Report {
+                    kind,
+                    request_seq: Some(1),
+                    uri: uri.clone(),
+                    diagnostics: vec![diagnostic(message)],
+                },
+            );
+        }
+        state.handle_event(
+            ServerKind::Syntax,
+            DiagnosticsEvent::Completed { request_seq: 1 },
+        );
+        let published = state.take_changed(|_| true);
+        assert_eq!(published, vec![(uri, vec![diagnostic("syntax")])]);
+    }
+
+    #[test]
+    fn files_dropped_from_a_project_round_are_cleared() {
+        let a = Uri::from_str("file:///repo/a.ts").unwrap();
+        let b = Uri::from_str("file:///repo/b.ts").unwrap();
+        let diagnostic = lsp_types::Diagnostic {
+            message: "problem".to_string(),
+            ..Default::default()
+        };
+        let mut state = DiagnosticsState::default();
+        let mut round = |seq, uris: &[&Uri]| {
+            state.register_project_round(
+                ServerKind::Semantic,
+                seq,
+                "/repo/a.ts",
+                "/repo/tsconfig.json",
+            );
+            for uri in uris {
+                state.handle_event(
+                    ServerKind::Semantic,
+                    DiagnosticsEvent::Report {
+                        kind: DiagnosticsKind::Semantic,
+                        request_seq: Some(seq),
+                        uri: (*uri).clone(),
+                        diagnostics: vec![diagnostic.clone()],
+                    },
+                );
+            }
+            state.handle_event(
+                ServerKind::Semantic,
+                DiagnosticsEvent::Completed { request_seq: seq },
+            );
+            let mut ready: Vec<_> = state
+                .take_changed(|_| true)
+                .into_iter()
+                .map(|(uri, diagnostics)| (uri.to_string(), diagnostics.len()))
+                .collect();
+            ready.sort();
+            ready
+        };
+        assert_eq!(
+            round(1, &[&a, &b]),
+            vec![
+                ("file:///repo/a.ts".to_string(), 1),
+                ("file:///repo/b.ts".to_string(), 1)
+            ]
+        );
+        // a.ts is unchanged, so only b.ts goes out, emptied.
+        assert_eq!(round(2, &[&a]), vec![("file:///repo/b.ts".to_string(), 0)]);
+        assert_eq!(state.affected("file:///repo"), vec![a]);
+    }
+
+    #[test]
+    fn a_newer_project_round_supersedes_the_older_one() {
+        let mut state = DiagnosticsState::default();
+        assert_eq!(
+            state.register_project_round(
+                ServerKind::Semantic,
+                1,
+                "/repo/a.ts",
+                "/repo/tsconfig.json"
+            ),
+            None
+        );
+        assert_eq!(
+            state.register_project_round(
+                ServerKind::Semantic,
+                2,
+                "/repo/b.ts",
+                "/repo/tsconfig.json"
+            ),
+            Some((ServerKind::Semantic, 1))
+        );
+        state.handle_event(
+            ServerKind::Semantic,
+            DiagnosticsEvent::Report {
+                kind: DiagnosticsKind::Semantic,
+                request_seq: Some(1),
+                uri: Uri::from_str("file:///repo/a.ts").unwrap(),
+                diagnostics: vec![lsp_types::Diagnostic::default()],
+            },
+        );
+        state.handle_event(
+            ServerKind::Semantic,
+            DiagnosticsEvent::Completed { request_seq: 1 },
+        );
+        assert!(state.take_changed(|_| true).is_empty());
+    }
+
+    #[test]
+    fn deleted_folders_clear_the_files_published_below_them() {
+        let a = Uri::from_str("file:///repo/src/a.ts").unwrap();
+        let b = Uri::from_str("file:///repo/lib/b.ts").unwrap();
+        let mut state = DiagnosticsState::default();
+        for uri in [&a, &b] {
+            state.handle_event(
+                ServerKind::Semantic,
+                DiagnosticsEvent::Report {
+                    kind: DiagnosticsKind::Semantic,
+                    request_seq: None,
+                    uri: uri.clone(),
+                    diagnostics: vec![lsp_types::Diagnostic::default()],
+                },
+            );
+        }
+        assert_eq!(state.take_changed(|_| true).len(), 2);
+        // Published sets are remembered even once `latest` is gone.
+        state.latest.clear();
+
+        assert_eq!(state.clear_affected("file:///repo/src"), vec![a]);
+        assert_eq!(state.affected("file:///repo"), vec![b]);
+    }
+
+    #[test]
+    fn region_diagnostics_replace_only_the_checked_ranges() {
+        let uri = Uri::from_str("file:///repo/big.ts").unwrap();
+        let at_line = |line: u32, message: &str| lsp_types::Diagnostic {
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(line, 0),
+                lsp_types::Position::new(line, 4),
+            ),
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let mut state = DiagnosticsState::default();
+        state.handle_event(
+            ServerKind::Semantic,
+            DiagnosticsEvent::Report {
+                kind: DiagnosticsKind::Semantic,
+                request_seq: None,
+                uri: uri.clone(),
+                diagnostics: vec![at_line(10, "above"), at_line(120, "stale")],
+            },
+        );
+        state.take_changed(|_| true);
+
+        state.register_pending(ServerKind::Semantic, 1, "/repo/big.ts");
+        state.handle_event(
+            ServerKind::Semantic,
+            DiagnosticsEvent::Region {
+                request_seq: Some(1),
+                uri: uri.clone(),
+                diagnostics: vec![at_line(130, "visible")],
+                ranges: vec![lsp_types::Range::new(
+                    lsp_types::Position::new(100, 0),
+                    lsp_types::Position::new(150, 0),
+                )],
+            },
+        );
+        let published = state.take_changed(|_| true);
+        assert_eq!(
+            published,
+            vec![(uri, vec![at_line(10, "above"), at_line(130, "visible")])]
+        );
+    }
+
+    #[test]
+    fn ready_diagnostics_are_coalesced_per_uri() {
+        let uri = Uri::from_str("file:///repo/a.ts").unwrap();
+        let other = Uri::from_str("file:///repo/b.ts").unwrap();
+        let report = |uri: &Uri, kind, message: &str| DiagnosticsEvent::Report {
+            kind,
+            request_seq: None,
+            uri: uri.clone(),
+            diagnostics: vec![lsp_types::Diagnostic {
+                message: message.to_string(),
+                ..Default::default()
+            }],
+        };
+        let mut state = DiagnosticsState::default();
+        state.handle_event(
+            ServerKind::Syntax,
+            report(&uri, DiagnosticsKind::Syntax, "a"),
+        );
+        state.handle_event(
+            ServerKind::Syntax,
+            report(&other, DiagnosticsKind::Syntax, "b"),
+        );
+        state.handle_event(
+            ServerKind::Syntax,
+            report(&uri, DiagnosticsKind::Semantic, "c"),
+        );
+        let batch = state.take_changed(|uri| uri.as_str().ends_with("a.ts"));
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].1.len(), 2);
+
+        // The same set again is not re-sent.
+        state.handle_event(
+            ServerKind::Syntax,
+            report(&uri, DiagnosticsKind::Semantic, "c"),
+        );
+        assert!(state.take_changed(|_| true).is_empty());
+    }
+
+    #[test]
+    fn stopped_registry_refuses_new_projects() {
+        let registry = ProjectRegistry::new(None, None, None);
+        assert_eq!(registry.shutdown_all(), 0);
+        let Err(err) = registry.get_or_create(
+            ProjectKey::workspace(PathBuf::from("/repo")),
+            &mut Config::new(PluginSettings::default()),
+        ) else {
+            panic!("a stopped registry opened a project");
+        };
+        assert!(err.to_string().contains("shutting down"));
+    }
+
+    #[test]
+    fn daemon_settings_layer_file_and_overrides_over_flags() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let file = dir.path().join("daemon.toml");
+        std::fs::write(&file, "idle_ttl = 600\nlog_level = \"debug\"\n").expect("write file");
+        let base = DaemonSettings {
+            idle_ttl: Some(Duration::from_secs(30)),
+            max_projects: Some(4),
+            log_level: None,
+        };
+
+        let settings = DaemonSettings::load(&base, Some(&file), &json!({ "max_projects": "off" }))
+            .expect("load settings");
+        assert_eq!(settings.idle_ttl, Some(Duration::from_secs(600)));
+        assert_eq!(settings.max_projects, None);
+        assert_eq!(settings.log_level, Some(log::LevelFilter::Debug));
+
+        // Keys removed from the file fall back to the flags.
+        std::fs::write(&file, "").expect("write file");
+        let settings = DaemonSettings::load(&base, Some(&file), &Value::Null).expect("load");
+        assert_eq!(settings, base);
+
+        assert!(DaemonSettings::load(&base, None, &json!({ "idle_tll": 5 })).is_err());
+        assert!(DaemonSettings::load(&base, None, &json!({ "max_projects": 0 })).is_err());
+        assert!(DaemonSettings::load(&base, None, &json!({ "log_level": "loud" })).is_err());
+    }
+
+    #[test]
+    fn project_limits_cap_sessions_and_memory() {
+        let limits = ProjectLimits {
+            max_sessions: Some(1),
+            max_memory: vec![
+                (PathBuf::from("/repo"), 4096),
+                (PathBuf::from("/repo/huge"), 8192),
+            ],
+        };
+        assert_eq!(
+            limits.max_old_space_size(Path::new("/repo/huge/app")),
+            Some(8192)
+        );
+        assert_eq!(
+            limits.max_old_space_size(Path::new("/repo/small")),
+            Some(4096)
+        );
+        assert_eq!(limits.max_old_space_size(Path::new("/repository")), None);
+
+        let root = PathBuf::from("/repo/huge");
+        let (tx, _rx) = unbounded();
+        let last_used = Arc::new(AtomicU64::new(0));
+        let session_count = Arc::new(AtomicUsize::new(1));
+        let handle = ProjectHandle {
+            root: root.clone(),
+            label: "huge".to_string(),
+            commands: tx,
+            last_used: Arc::clone(&last_used),
+            session_count: Arc::clone(&session_count),
+            owners: DocumentOwners::default(),
+        };
+        let registry = ProjectRegistry::new(None, None, None);
+        registry.set_limits(limits);
+        registry.inner.lock().unwrap().entries.insert(
+            ProjectKey::workspace(root.clone()),
+            ProjectEntry {
+                handle,
+                last_used,
+                session_count,
+            },
+        );
+
+        let mut config = Config::default();
+        let Err(err) = registry.get_or_create(ProjectKey::workspace(root), &mut config) else {
+            panic!("a full project accepted another session");
+        };
+        assert!(err.to_string().contains("already has 1 session"));
+        assert_eq!(config.plugin().tsserver.max_old_space_size, Some(8192));
+    }
+
+    #[test]
+    fn session_slots_are_taken_when_the_cap_is_checked() {
+        let root = PathBuf::from("/repo");
+        let (tx, _rx) = unbounded();
+        let last_used = Arc::new(AtomicU64::new(0));
+        let session_count = Arc::new(AtomicUsize::new(0));
+        let handle = ProjectHandle {
+            root: root.clone(),
+            label: "repo".to_string(),
+            commands: tx,
+            last_used: Arc::clone(&last_used),
+            session_count: Arc::clone(&session_count),
+            owners: DocumentOwners::default(),
+        };
+        let registry = ProjectRegistry::new(None, None, None);
+        registry.set_limits(ProjectLimits {
+            max_sessions: Some(1),
+            max_memory: Vec::new(),
+        });
+        registry.inner.lock().unwrap().entries.insert(
+            ProjectKey::workspace(root.clone()),
+            ProjectEntry {
+                handle,
+                last_used,
+                session_count: Arc::clone(&session_count),
+            },
+        );
+
+        let mut config = Config::default();
+        registry
+            .open_project(ProjectKey::workspace(root.clone()), &mut config, true)
+            .expect("the first session fits");
+        assert_eq!(session_count.load(Ordering::Relaxed), 1);
+        assert!(
+            registry
+                .open_project(ProjectKey::workspace(root.clone()), &mut config, true)
+                .is_err(),
+            "a second attach got the slot the first one reserved"
+        );
+        assert!(
+            registry
+                .get_or_create(ProjectKey::workspace(root), &mut config)
+                .is_err(),
+            "the cap applies to preloads too"
+        );
+        assert_eq!(session_count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn overflow_evicts_idle_projects_before_attached_ones() {
+        let key = |name: &str| ProjectKey::workspace(PathBuf::from(format!("/repo/{name}")));
+        let projects = vec![
+            (key("old-busy"), 1, 10),
+            (key("recent-idle"), 0, 30),
+            (key("old-idle"), 0, 20),
+            (key("recent-busy"), 2, 40),
+        ];
+        assert_eq!(
+            overflow_candidates(projects.clone(), 2),
+            vec![key("old-idle"), key("recent-idle")]
+        );
+        assert_eq!(
+            overflow_candidates(projects, 3),
+            vec![key("old-idle"), key("recent-idle"), key("old-busy")]
+        );
+    }
+
+    #[test]
+    fn dispatch_trace_pairs_request_id_with_seqs() {
+        let receipts = [
+            DispatchReceipt {
+                server: ServerKind::Syntax,
+                seq: 40,
+            },
+            DispatchReceipt {
+                server: ServerKind::Semantic,
+                seq: 41,
+            },
+        ];
+        assert_eq!(
+            dispatch_trace(&RequestId::from(12), &receipts),
+            "12 -> syntax#40, semantic#41"
+        );
+    }
+
+    #[test]
+    fn request_timings_aggregate_per_method() {
+        let mut timings = RequestTimings::default();
+        let received = Instant::now();
+        let ms = Duration::from_millis;
+        timings.start(RequestId::from(1), "textDocument/hover", received);
+        timings.start(RequestId::from(2), "textDocument/hover", received);
+        timings.start(RequestId::from(3), "textDocument/definition", received);
+
+        let timing = timings
+            .finish(&RequestId::from(1), received + ms(8), received + ms(10))
+            .expect("timed");
+        assert_eq!(timing.total, ms(10));
+        assert_eq!(timing.tsserver, ms(8));
+        timings.finish(&RequestId::from(2), received + ms(20), received + ms(30));
+        timings.discard(&RequestId::from(3));
+        assert!(
+            timings
+                .finish(&RequestId::from(3), received, received)
+                .is_none()
+        );
+
+        assert_eq!(
+            timings.snapshot(),
+            json!({
+                "textDocument/hover": {
+                    "count": 2,
+                    "mean_ms": 20.0,
+                    "tsserver_mean_ms": 14.0,
+                    "max_ms": 30.0,
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn pending_requests_fail_server_answers_requests_only_it_owed() {
+        fn adapter(_: &Value, _: Option<&Value>) -> anyhow::Result<AdapterResult> {
+            Ok(AdapterResult::ready(Value::Null))
+        }
+        let receipt = |server, seq| DispatchReceipt { server, seq };
+        let mut pending = PendingRequests::default();
+        pending.track(
+            &[
+                receipt(ServerKind::Syntax, 1),
+                receipt(ServerKind::Semantic, 1),
+            ],
+            RequestId::from(3),
+            adapter,
+            None,
+            None,
+            None,
+        );
+        pending.track(
+            &[receipt(ServerKind::Semantic, 2)],
+            RequestId::from(4),
+            adapter,
+            None,
+            None,
+            None,
+        );
+
+        let responses = pending.fail_server(ServerKind::Semantic, "stopped");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].id, RequestId::from(4));
+        assert_eq!(
+            pending.entries.keys().copied().collect::<Vec<_>>(),
+            vec![PendingKey {
+                server: ServerKind::Syntax,
+                seq: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn eviction_fails_only_the_evicted_projects_requests_and_diagnostics() {
+        fn adapter(_: &Value, _: Option<&Value>) -> anyhow::Result<AdapterResult> {
+            Ok(AdapterResult::ready(Value::Null))
+        }
+        let receipt = |seq| DispatchReceipt {
+            server: ServerKind::Semantic,
+            seq,
+        };
+        let mut pending = PendingRequests::default();
+        pending.track(&[receipt(1)], RequestId::from(3), adapter, None, None, None);
+        pending.track(&[receipt(2)], RequestId::from(4), adapter, None, None, None);
+        pending.assign_slot(&[receipt(2)], Some(0));
+
+        let responses = pending.fail_slot(Some(0), "evicted");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].id, RequestId::from(4));
+        assert_eq!(pending.request_count(), 1);
+
+        let mut state = DiagnosticsState::default();
+        state.register_pending(ServerKind::Semantic, 5, "/repo/app/a.ts");
+        state.register_pending(ServerKind::Semantic, 6, "/repo/pkg/b.ts");
+        state.clear_files(|file| file.starts_with("/repo/pkg/"));
+        assert_eq!(
+            state.pending.keys().copied().collect::<Vec<_>>(),
+            vec![(ServerKind::Semantic, 5)]
+        );
+        assert!(state.in_flight.contains_key("/repo/app/a.ts"));
+        assert!(!state.in_flight.contains_key("/repo/pkg/b.ts"));
+        assert_eq!(state.order[&ServerKind::Semantic], [5]);
+    }
+
+    #[test]
+    fn pending_requests_reject_responses_for_edited_documents() {
+        fn adapter(_: &Value, _: Option<&Value>) -> anyhow::Result<AdapterResult> {
+            Ok(AdapterResult::ready(Value::Null))
+        }
+        let uri = Uri::from_str("file:///w/a.ts").unwrap();
+        let mut documents = DocumentStore::default();
+        documents.open(&uri, "const a = 1;", Some(1), None);
+        let params = json!({
+            "textDocument": { "uri": uri.as_str() },
+            "position": { "line": 0, "character": 6 }
+        });
+        let origin = DocumentVersion::for_request("textDocument/hover", &params, &documents);
+        assert!(origin.is_some());
+        assert!(
+            DocumentVersion::for_request("textDocument/references", &params, &documents).is_none()
+        );
+
+        let mut pending = PendingRequests::default();
+        let receipt = DispatchReceipt {
+            server: ServerKind::Syntax,
+            seq: 3,
+        };
+        pending.track(&[receipt], RequestId::from(1), adapter, None, None, origin);
+        let payload = json!({ "type": "response", "request_seq": 3, "success": true });
+        assert!(
+            pending
+                .reject_stale(ServerKind::Syntax, &payload, &documents)
+                .is_none()
+        );
+
+        documents.open(&uri, "const ab = 1;", Some(2), None);
+        let response = pending
+            .reject_stale(ServerKind::Syntax, &payload, &documents)
+            .expect("stale response is rejected");
+        assert_eq!(
+            response.error.map(|err| err.code),
+            Some(ErrorCode::ContentModified as i32)
+        );
+        assert!(pending.entries.is_empty());
+    }
+
+    #[test]
+    fn files_without_a_project_config_are_inferred() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join("src")).expect("create project");
+        std::fs::write(project.join("tsconfig.json"), "{}").expect("write tsconfig");
+
+        assert!(!is_inferred_project_file(&project.join("src/index.ts")));
+        assert!(is_inferred_project_file(&dir.path().join("script.js")));
+    }
+
+    #[test]
+    fn language_features_cover_document_requests() {
+        assert_eq!(
+            language_feature("textDocument/semanticTokens/range"),
+            Some("semantic_tokens")
+        );
+        assert_eq!(
+            language_feature("textDocument/rangeFormatting"),
+            Some("formatting")
+        );
+        assert_eq!(language_feature("workspace/symbol"), None);
+    }
+
+    #[test]
+    fn payload_file_reads_the_file_each_command_targets() {
+        let quickinfo = json!({ "command": "quickinfo", "arguments": { "file": "/w/a.ts" } });
+        assert_eq!(payload_file(&quickinfo), Some("/w/a.ts"));
+        let geterr = json!({ "command": "geterr", "arguments": { "files": ["/w/b.ts"] } });
+        assert_eq!(payload_file(&geterr), Some("/w/b.ts"));
+        let update = json!({
+            "command": "updateOpen",
+            "arguments": { "openFiles": [{ "file": "/w/c.ts", "fileContent": "" }] }
+        });
+        assert_eq!(payload_file(&update), Some("/w/c.ts"));
+        let rename = json!({
+            "command": "getEditsForFileRename",
+            "arguments": { "oldFilePath": "/w/d.ts", "newFilePath": "/x/d.ts" }
+        });
+        assert_eq!(payload_file(&rename), Some("/w/d.ts"));
+        let fix_all = json!({
+            "command": "getCombinedCodeFix",
+            "arguments": { "scope": { "type": "file", "args": { "file": "/w/e.ts" } }, "fixId": "f" }
+        });
+        assert_eq!(payload_file(&fix_all), Some("/w/e.ts"));
+        let configure = json!({ "command": "configure", "arguments": {} });
+        assert_eq!(payload_file(&configure), None);
+    }
+
+    #[test]
+    fn split_payload_regroups_file_lists_by_project() {
+        let slot_of = |file: &str| file.starts_with("/w/pkg/").then_some(0);
+        let update = json!({
+            "command": "updateOpen",
+            "arguments": {
+                "openFiles": [
+                    { "file": "/w/a.ts", "fileContent": "a" },
+                    { "file": "/w/pkg/b.ts", "fileContent": "b" }
+                ],
+                "changedFiles": [],
+                "closedFiles": ["/w/pkg/c.ts", "/w/d.ts"]
+            }
+        });
+        let groups = split_payload(update, slot_of);
+        assert_eq!(
+            groups,
+            vec![
+                (
+                    None,
+                    json!({
+                        "command": "updateOpen",
+                        "arguments": {
+                            "openFiles": [{ "file": "/w/a.ts", "fileContent": "a" }],
+                            "changedFiles": [],
+                            "closedFiles": ["/w/d.ts"]
+                        }
+                    })
+                ),
+                (
+                    Some(0),
+                    json!({
+                        "command": "updateOpen",
+                        "arguments": {
+                            "openFiles": [{ "file": "/w/pkg/b.ts", "fileContent": "b" }],
+                            "changedFiles": [],
+                            "closedFiles": ["/w/pkg/c.ts"]
+                        }
+                    })
+                ),
+            ]
+        );
+
+        let geterr = json!({
+            "command": "geterr",
+            "arguments": { "files": [{ "file": "/w/pkg/e.ts", "ranges": [] }], "delay": 0 }
+        });
+        let groups = split_payload(geterr.clone(), slot_of);
+        assert_eq!(groups, vec![(Some(0), geterr)]);
+
+        let rename = json!({
+            "command": "getEditsForFileRename",
+            "arguments": { "oldFilePath": "/w/pkg/f.ts", "newFilePath": "/w/f.ts" }
+        });
+        assert_eq!(
+            split_payload(rename.clone(), slot_of),
+            vec![(Some(0), rename)]
+        );
+    }
+
+    #[test]
+    fn typings_installer_events_are_recognised() {
+        let begin = json!({
+            "seq": 0,
+            "type": "event",
+            "event": "beginInstallTypes",
+            "body": { "eventId": 1, "packages": ["@types/lodash", "@types/node"] }
+        });
+        assert_eq!(
+            TypingsEvent::parse(&begin),
+            Some(TypingsEvent::Begin {
+                packages: vec!["@types/lodash".to_string(), "@types/node".to_string()],
+            })
+        );
+
+        let end = json!({
+            "seq": 0,
+            "type": "event",
+            "event": "endInstallTypes",
+            "body": { "eventId": 1, "packages": ["@types/lodash"], "success": false }
+        });
+        assert_eq!(
+            TypingsEvent::parse(&end),
+            Some(TypingsEvent::End {
+                packages: vec!["@types/lodash".to_string()],
+                success: false,
+            })
+        );
+
+        let failed = json!({
+            "seq": 0,
+            "type": "event",
+            "event": "typesInstallerInitializationFailed",
+            "body": { "message": "npm not found" }
+        });
+        assert_eq!(
+            TypingsEvent::parse(&failed),
+            Some(TypingsEvent::InitializationFailed {
+                message: "npm not found".to_string(),
+            })
+        );
+
+        let other = json!({ "seq": 0, "type": "event", "event": "projectLoadingStart" });
+        assert_eq!(TypingsEvent::parse(&other), None);
+    }
+
+    #[test]
+    fn project_loading_events_carry_the_project_name() {
+        let start = json!({
+            "seq": 0,
+            "type": "event",
+            "event": "projectLoadingStart",
+            "body": { "projectName": "/repo/tsconfig.json", "reason": "Creating possible configured project" }
+        });
+        assert_eq!(
+            ProjectLoadingEvent::parse(&start),
+            Some(ProjectLoadingEvent::Start {
+                project: "/repo/tsconfig.json".to_string(),
+            })
+        );
+        let finish = json!({
+            "seq": 0,
+            "type": "event",
+            "event": "projectLoadingFinish",
+            "body": { "projectName": "/repo/tsconfig.json" }
+        });
+        assert_eq!(
+            ProjectLoadingEvent::parse(&finish),
+            Some(ProjectLoadingEvent::Finish {
+                project: "/repo/tsconfig.json".to_string(),
+            })
+        );
+    }
+
     #[test]
     fn tsserver_configure_args_override_inlay_hint_preferences() {
         let mut preferences = Map::new();
@@ -1065,7 +3986,7 @@ mod tests {
             ..Default::default()
         });
 
-        let args = tsserver_configure_args(&config);
+        let args = tsserver_configure_args(&config, false);
         let preferences = args
             .get("preferences")
             .and_then(|value| value.as_object())
@@ -1095,7 +4016,7 @@ mod tests {
             ..Default::default()
         });
 
-        let args = tsserver_configure_args(&config);
+        let args = tsserver_configure_args(&config, false);
         let format_options = args
             .get("formatOptions")
             .and_then(|value| value.as_object())
@@ -1109,6 +4030,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tsserver_configure_args_forward_maximum_hover_length() {
+        let config = Config::new(PluginSettings {
+            maximum_hover_length: Some(500),
+            ..Default::default()
+        });
+
+        let args = tsserver_configure_args(&config, false);
+        assert_eq!(
+            args.get("preferences")
+                .and_then(|preferences| preferences.get("maximumHoverLength")),
+            Some(&Value::from(500))
+        );
+    }
+
+    #[test]
+    fn file_format_options_prefer_user_settings_over_the_client() {
+        let mut settings = PluginSettings::default();
+        settings.tsserver_format_options.insert(
+            "insertSpaceBeforeFunctionParenthesis".to_string(),
+            json!(true),
+        );
+        settings.format_options.indent_size = Some(4);
+        settings.format_options.quote_style = Some("single".to_string());
+        let client = lsp_types::FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        };
+
+        let args = file_format_configure_args("/w/a.ts", &settings, None, Some(&client));
+        assert_eq!(args["file"], json!("/w/a.ts"));
+        assert_eq!(args["formatOptions"]["indentSize"], json!(4));
+        assert_eq!(args["formatOptions"]["tabSize"], json!(2));
+        assert_eq!(
+            args["formatOptions"]["insertSpaceBeforeFunctionParenthesis"],
+            json!(true)
+        );
+        assert_eq!(args["preferences"]["quotePreference"], json!("single"));
+
+        settings.typescript.format_options.quote_style = Some("double".to_string());
+        let args =
+            file_format_configure_args("/w/a.ts", &settings, Some(LanguageScope::TypeScript), None);
+        assert_eq!(args["preferences"]["quotePreference"], json!("double"));
+    }
+
+    #[test]
+    fn scope_settings_layer_over_global_configure_args() {
+        let mut config = Config::default();
+        config.apply_workspace_settings(&json!({
+            "enable_inlay_hints": false,
+            "preferences": { "quote_preference": "double" },
+            "typescript": {
+                "enable_inlay_hints": true,
+                "preferences": { "quote_preference": "single" },
+                "format_options": { "semicolons": "insert" }
+            }
+        }));
+
+        let ts = file_scope_configure_args("/w/a.ts", &config, LanguageScope::TypeScript, false);
+        assert_eq!(ts["file"], json!("/w/a.ts"));
+        assert_eq!(ts["preferences"]["quotePreference"], json!("single"));
+        assert_eq!(
+            ts["preferences"]["includeInlayVariableTypeHints"],
+            json!(true)
+        );
+        assert_eq!(ts["formatOptions"]["semicolons"], json!("insert"));
+
+        let js = file_scope_configure_args("/w/a.js", &config, LanguageScope::JavaScript, false);
+        assert_eq!(js["preferences"]["quotePreference"], json!("double"));
+        assert_eq!(
+            js["preferences"]["includeInlayVariableTypeHints"],
+            json!(false)
+        );
+        assert_eq!(js["formatOptions"].get("semicolons"), None);
+    }
+
+    #[test]
+    fn plugin_languages_become_deferred_file_extensions() {
+        let mut config = Config::default();
+        config.apply_workspace_settings(&json!({
+            "tsserver": {
+                "plugins": [{ "name": "@vue/typescript-plugin", "languages": ["vue"] }]
+            }
+        }));
+
+        let args = tsserver_configure_args(&config, false);
+        assert_eq!(
+            args.get("extraFileExtensions"),
+            Some(&json!([{ "extension": ".vue", "isMixedContent": false, "scriptKind": 7 }]))
+        );
+        let caps = advertised_capabilities(config.plugin());
+        let will_rename = caps
+            .workspace
+            .and_then(|workspace| workspace.file_operations)
+            .and_then(|operations| operations.will_rename)
+            .expect("willRename filters");
+        assert!(will_rename.filters[0].pattern.glob.ends_with(",vue}"));
+    }
+
     #[test]
     #[allow(deprecated)]
     fn workspace_root_from_params_prefers_root_path() {
@@ -1168,6 +4189,7 @@ mod tests {
             commands: tx,
             last_used: Arc::clone(&last_used),
             session_count: Arc::clone(&session_count),
+            owners: DocumentOwners::default(),
         };
 
         let entry = ProjectEntry {
@@ -1178,9 +4200,14 @@ mod tests {
 
         let registry = ProjectRegistry {
             inner: Arc::new(Mutex::new(ProjectRegistryState {
-                entries: HashMap::from([(root.clone(), entry)]),
+                entries: HashMap::from([(ProjectKey::workspace(root.clone()), entry)]),
                 max_entries: None,
                 idle_ttl: None,
+                closed: false,
+                resume_window: None,
+                parked: HashMap::new(),
+                takeovers: HashMap::new(),
+                limits: ProjectLimits::default(),
             })),
         };
 
@@ -1190,8 +4217,15 @@ mod tests {
                     let _ = reply.send(ProjectThreadStatus {
                         session_count: 2,
                         session_ids: vec![1, 2],
-                        tsserver_syntax_pid: Some(100),
-                        tsserver_semantic_pid: Some(200),
+                        tsserver: TsserverStatus {
+                            syntax_pid: Some(100),
+                            semantic_pid: Some(200),
+                            syntax_uptime: Some(Duration::from_secs(42)),
+                            version: Some("5.6.2".to_string()),
+                            source: Some(BinarySource::LocalNodeModules),
+                            queued: 3,
+                            ..TsserverStatus::default()
+                        },
                     });
                 }
             }
@@ -1228,7 +4262,33 @@ mod tests {
             tsserver.get("semantic_pid").and_then(|v| v.as_u64()),
             Some(200)
         );
+        assert_eq!(tsserver.get("syntax_uptime_seconds"), Some(&json!(42)));
+        assert_eq!(tsserver.get("semantic_uptime_seconds"), Some(&Value::Null));
+        assert_eq!(tsserver.get("version"), Some(&json!("5.6.2")));
+        assert_eq!(tsserver.get("source"), Some(&json!("node_modules")));
+        assert_eq!(tsserver.get("queue_depth"), Some(&json!(3)));
+    }
+}
+
+/// Settings for a project at `workspace_root` before the client has a say:
+/// its settings files, then `TS_BRIDGE_*` overrides (which
+/// [`Config::apply_client_settings`] re-applies over the client's settings).
+/// Also returns what was wrong with the files.
+pub(crate) fn workspace_config(workspace_root: &Path) -> (Config, Vec<String>) {
+    let mut config = Config::new(PluginSettings::default());
+    let mut settings_problems = Vec::new();
+    for (path, problems) in config.apply_config_files(workspace_root) {
+        log::info!("applied settings file {}", path.display());
+        settings_problems.extend(
+            problems
+                .into_iter()
+                .map(|problem| format!("{}: {problem}", path.display())),
+        );
+    }
+    if config.apply_env_overrides() {
+        log::info!("applied TS_BRIDGE_* environment overrides to ts-bridge settings");
     }
+    (config, settings_problems)
 }
 
 #[allow(deprecated)]
@@ -1267,16 +4327,94 @@ struct SessionState {
     pending: PendingRequests,
     diag_state: DiagnosticsState,
     progress: LoadingProgress,
+    typings_progress: LoadingProgress,
+    /// Configured projects tsserver reported `projectLoadingStart` for and
+    /// has not finished loading.
+    loading_projects: HashSet<String>,
     restart_progress: RestartProgress,
     documents: DocumentStore,
     inlay_cache: InlayHintCache,
+    completion_cache: CompletionCache,
     tsserver_configure: TsserverConfigureState,
+    packages: PackageProjects,
+    /// Open documents of a disabled language, which tsserver never saw.
+    unsynced_documents: HashSet<String>,
+    /// Whether any file-scoped `configure` for a language scope went out.
+    scoped_configure_sent: bool,
+    /// Settings problems already shown, so each is reported once.
+    reported_settings_problems: HashSet<String>,
+    timings: RequestTimings,
+    /// `$/setTrace` level; `verbose` traces each request's latency.
+    trace: lsp_types::TraceValue,
+    /// Edited files waiting for `ts-bridge/requestDiagnostics` under
+    /// `publish_diagnostic_on = "insert_leave"`.
+    deferred_diagnostics: HashSet<String>,
+    /// Edited files and when their debounced `geterr` is due.
+    debounced_diagnostics: HashMap<String, Instant>,
+    /// Ranges the editor reported visible through `ts-bridge/visibleRanges`,
+    /// by file.
+    visible_ranges: HashMap<String, (lsp_types::Uri, Vec<crate::types::Range>)>,
+    /// tsserver understands ranged `geterr` requests.
+    region_diagnostics: bool,
+    /// tsserver has `findSourceDefinition`; without it, source definition
+    /// requests get plain definitions.
+    source_definition: bool,
+    /// The client pulls diagnostics, so no `geterr` goes out for it and
+    /// nothing is pushed.
+    pull_diagnostics: bool,
+    /// The client re-pulls on `workspace/diagnostic/refresh`.
+    diagnostic_refresh: bool,
+    /// The client expands snippet completions.
+    snippet_support: bool,
+    /// The bundled `tsserver.js` the first request will have to install.
+    bundled_install: Option<PathBuf>,
+    install_progress: LoadingProgress,
+    interactive_inlay_hints: bool,
     registry: ProjectRegistry,
+    /// Bumped whenever project-wide state changes so pull diagnostic result
+    /// ids from before the change are never reported as unchanged.
+    diagnostic_generation: u64,
+    /// The registry shut the workspace project down; the next client message
+    /// registers the session with a fresh one.
+    evicted: bool,
+    /// Reclaims this session after a reconnect; see [`resume_parked`].
+    resume_token: String,
+    /// Set when a reconnecting client claims the session.
+    takeover: Arc<AtomicBool>,
+    /// The current client reconnected to the session rather than starting it.
+    resumed: bool,
+    client: ClientFingerprint,
+}
+
+/// How [`SessionState::run`] returned.
+#[derive(Debug, PartialEq, Eq)]
+enum SessionEnd {
+    /// `shutdown`/`exit`, or the session failed.
+    Exited,
+    /// The client went away without saying so.
+    Disconnected,
+}
+
+/// Runs however the session ends (error, panic or a vanished client), so
+/// its projects stop counting it.
+impl Drop for SessionState {
+    fn drop(&mut self) {
+        for package in &self.packages.projects {
+            package.handle.unregister_session(package.session_id);
+        }
+        self.project.unregister_session(self.session_id);
+        self.registry.forget_session(&self.resume_token);
+    }
 }
 
 impl SessionState {
     fn new(connection: Connection, init: SessionInit, registry: ProjectRegistry) -> Self {
         let project_label = init.project.label().to_string();
+        let pending = PendingRequests::new(init.config.plugin());
+        let mut diag_state = DiagnosticsState::default();
+        diag_state.set_include_suggestions(init.config.plugin().diagnostics.include_suggestions);
+        let resume_token = new_resume_token();
+        let takeover = registry.track_session(&resume_token);
         Self {
             connection,
             project: init.project,
@@ -1285,18 +4423,73 @@ impl SessionState {
             workspace_root: init.workspace_root,
             session_id: init.session_id,
             project_label,
-            pending: PendingRequests::default(),
-            diag_state: DiagnosticsState::default(),
+            pending,
+            diag_state,
             progress: LoadingProgress::new(init.session_id),
+            typings_progress: LoadingProgress::with_prefix("ts-bridge-typings", init.session_id),
+            loading_projects: HashSet::new(),
             restart_progress: RestartProgress::new(init.session_id),
             documents: DocumentStore::default(),
             inlay_cache: InlayHintCache::default(),
-            tsserver_configure: TsserverConfigureState::default(),
+            completion_cache: CompletionCache::default(),
+            tsserver_configure: TsserverConfigureState::new(init.interactive_inlay_hints),
+            packages: PackageProjects::default(),
+            unsynced_documents: HashSet::new(),
+            scoped_configure_sent: false,
+            reported_settings_problems: HashSet::new(),
+            timings: RequestTimings::default(),
+            trace: init.trace,
+            deferred_diagnostics: HashSet::new(),
+            debounced_diagnostics: HashMap::new(),
+            visible_ranges: HashMap::new(),
+            region_diagnostics: false,
+            source_definition: true,
+            pull_diagnostics: false,
+            diagnostic_refresh: false,
+            snippet_support: false,
+            bundled_install: None,
+            install_progress: LoadingProgress::with_prefix("ts-bridge-install", init.session_id),
+            interactive_inlay_hints: init.interactive_inlay_hints,
             registry,
+            diagnostic_generation: 0,
+            evicted: false,
+            resume_token,
+            takeover,
+            resumed: false,
+            client: init.client,
+        }
+    }
+
+    /// Logs each settings problem not reported before to the client and shows
+    /// one warning summarizing them.
+    fn report_settings_problems(&mut self, problems: Vec<String>) -> anyhow::Result<()> {
+        let fresh: Vec<String> = problems
+            .into_iter()
+            .filter(|problem| self.reported_settings_problems.insert(problem.clone()))
+            .collect();
+        if fresh.is_empty() {
+            return Ok(());
+        }
+        for problem in &fresh {
+            log::warn!("settings: {problem}");
+            log_message(
+                &self.connection,
+                &format!("ts-bridge settings: {problem}"),
+                lsp_types::MessageType::WARNING,
+            )?;
         }
+        let message = match fresh.as_slice() {
+            [only] => format!("ts-bridge: ignoring setting {only}"),
+            [first, rest @ ..] => format!(
+                "ts-bridge: ignoring setting {first} and {} more (see the log)",
+                rest.len()
+            ),
+            [] => unreachable!(),
+        };
+        show_message(&self.connection, &message, lsp_types::MessageType::WARNING)
     }
 
-    fn run(&mut self) -> anyhow::Result<()> {
+    fn run(&mut self) -> anyhow::Result<SessionEnd> {
         if let Err(err) = self.progress.begin(
             &self.connection,
             "ts-bridge",
@@ -1307,13 +4500,19 @@ impl SessionState {
 
         let poll_interval = Duration::from_millis(10);
         loop {
+            if self.takeover.swap(false, Ordering::Relaxed) {
+                log::info!("a reconnecting client took the session over");
+                return Ok(SessionEnd::Disconnected);
+            }
             self.drain_project_events()?;
+            self.expire_requests()?;
+            self.flush_debounced_diagnostics();
 
             match self.connection.receiver.recv_timeout(poll_interval) {
                 Ok(message) => match message {
                     Message::Request(req) => {
                         if self.handle_request(req)? {
-                            break;
+                            return Ok(SessionEnd::Exited);
                         }
                     }
                     Message::Response(resp) => {
@@ -1321,22 +4520,463 @@ impl SessionState {
                     }
                     Message::Notification(notif) => {
                         if self.handle_notification(notif)? {
-                            break;
+                            return Ok(SessionEnd::Exited);
                         }
                     }
                 },
                 Err(RecvTimeoutError::Timeout) => continue,
-                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(SessionEnd::Disconnected),
+            }
+        }
+    }
+
+    /// Drops what only the old connection could use: its requests get no
+    /// answer once it is gone.
+    fn prepare_to_park(&mut self) {
+        for response in self.pending.fail_all("client disconnected") {
+            self.timings.discard(&response.id);
+        }
+        self.resumed = false;
+    }
+
+    /// Brings a reconnected client up to date: it is sent the documents the
+    /// session still has open and the diagnostics it was last shown.
+    fn replay_after_resume(&mut self) -> anyhow::Result<()> {
+        let documents: Vec<Value> = self
+            .documents
+            .open_documents()
+            .into_iter()
+            .map(|snapshot| {
+                json!({
+                    "uri": snapshot.uri,
+                    "languageId": snapshot.language_id,
+                    "version": snapshot.version,
+                    "text": snapshot.text,
+                })
+            })
+            .collect();
+        self.connection
+            .sender
+            .send(Message::Notification(ServerNotification::new(
+                RESUME_METHOD.to_string(),
+                json!({
+                    "session_id": self.session_id,
+                    "documents": documents,
+                }),
+            )))?;
+        for (uri, diagnostics) in self.diag_state.published() {
+            publish_diagnostics(
+                &self.connection,
+                PublishDiagnosticsParams {
+                    uri,
+                    diagnostics,
+                    version: None,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Sends a request to the project serving its file, configuring that
+    /// project's tsserver first. A payload listing files of several projects
+    /// is split so each project gets its own files; one naming no file at all
+    /// (`reloadProjects`) goes to every live project.
+    fn dispatch(
+        &mut self,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+    ) -> anyhow::Result<Vec<DispatchReceipt>> {
+        if payload.get("command").and_then(Value::as_str) == Some("reloadProjects") {
+            self.packages.roots.clear();
+        }
+        let targets = if payload_file(&payload).is_some() {
+            split_payload(payload, |file| self.project_for_file(Path::new(file)))
+        } else {
+            std::iter::once(None)
+                .chain(
+                    (0..self.packages.projects.len())
+                        .filter(|&index| !self.packages.projects[index].evicted)
+                        .map(Some),
+                )
+                .map(|slot| (slot, payload.clone()))
+                .collect()
+        };
+        let mut receipts = Vec::new();
+        for (slot, payload) in targets {
+            receipts.extend(self.dispatch_to(slot, route, payload, priority)?);
+        }
+        Ok(receipts)
+    }
+
+    fn dispatch_to(
+        &mut self,
+        slot: Option<usize>,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+    ) -> anyhow::Result<Vec<DispatchReceipt>> {
+        self.begin_bundled_install();
+        if let Err(err) = self.configure_project(slot) {
+            log::warn!("failed to configure tsserver: {err}");
+        }
+        let result = self
+            .project_at(slot)
+            .dispatch_request(route, payload, priority);
+        self.end_bundled_install(result.as_ref().err());
+        result
+    }
+
+    /// Like [`Self::dispatch`] for a document notification, which the
+    /// project reconciles with other sessions' copies of the document.
+    /// Documents whose URI does not parse (no `event`) bypass the sharing.
+    /// A change without `text` is forwarded as is when the session owns the
+    /// document; otherwise the buffer is attached so the project can replace
+    /// a stale copy.
+    fn send_document(
+        &mut self,
+        event: Option<DocumentEvent>,
+        route: Route,
+        payload: Value,
+        priority: Priority,
+    ) -> anyhow::Result<Vec<DispatchReceipt>> {
+        let Some(mut event) = event else {
+            return self.dispatch(route, payload, priority);
+        };
+        let slot = self.route_project(&payload);
+        self.begin_bundled_install();
+        if let Err(err) = self.configure_project(slot) {
+            log::warn!("failed to configure tsserver: {err}");
+        }
+        let session_id = slot.map_or(self.session_id, |index| {
+            self.packages.projects[index].session_id
+        });
+        let project = self.project_at(slot);
+        let result = match &mut event {
+            DocumentEvent::Change {
+                uri,
+                text: text @ None,
+                ..
+            } if !project.owns(session_id, uri) => {
+                *text = self.documents.text(uri).map(str::to_string);
+                project.sync_document(session_id, event, route, payload, priority)
+            }
+            DocumentEvent::Change { text: None, .. } => project
+                .forward_document_edits(session_id, event, route, payload, priority)
+                .map(|()| Vec::new()),
+            _ => project.sync_document(session_id, event, route, payload, priority),
+        };
+        self.end_bundled_install(result.as_ref().err());
+        result
+    }
+
+    /// Sends the whole buffer of `uri` after the project turned down its
+    /// edits for being made against another session's copy.
+    fn resync_document(&mut self, uri: lsp_types::Uri) {
+        if self.unsynced_documents.contains(uri.as_str()) {
+            return;
+        }
+        let Some(text) = self.documents.text(&uri).map(str::to_string) else {
+            return;
+        };
+        let version = self.documents.version(&uri);
+        let params = crate::types::DidChangeTextDocumentParams {
+            text_document: crate::types::VersionedTextDocumentIdentifier {
+                uri: uri.to_string(),
+                version,
+            },
+            content_changes: Vec::new(),
+        };
+        let event = DocumentEvent::Change {
+            uri,
+            changes: Vec::new(),
+            text: Some(text),
+            version,
+        };
+        let spec = crate::protocol::text_document::did_change::handle(params, &self.workspace_root);
+        if let Err(err) = self.send_document(Some(event), spec.route, spec.payload, spec.priority) {
+            log::warn!("failed to resync document: {err}");
+        }
+    }
+
+    /// The request that first starts tsserver waits for the bundled
+    /// TypeScript to install (bounded by a timeout); show that as progress
+    /// rather than a silent stall.
+    fn begin_bundled_install(&mut self) {
+        if self.bundled_install.is_none() {
+            return;
+        }
+        if let Err(err) = self.install_progress.begin(
+            &self.connection,
+            "Installing TypeScript",
+            &format!("typescript@{BUNDLED_TYPESCRIPT_VERSION} (no local install found)"),
+        ) {
+            log::debug!("install progress begin failed: {err:?}");
+        }
+    }
+
+    fn end_bundled_install(&mut self, error: Option<&anyhow::Error>) {
+        let Some(tsserver) = &self.bundled_install else {
+            return;
+        };
+        let message = if tsserver.is_file() {
+            self.bundled_install = None;
+            format!("Installed TypeScript {BUNDLED_TYPESCRIPT_VERSION}")
+        } else {
+            let reason = error.map_or("tsserver.js is still missing".to_string(), |err| {
+                format!("{err:#}")
+            });
+            format!("Failed to install TypeScript: {reason}")
+        };
+        if let Err(err) = self.install_progress.end(&self.connection, &message) {
+            log::debug!("install progress end failed: {err:?}");
+        }
+    }
+
+    /// Picks the project for the file a tsserver payload is about: the
+    /// package project when the file's nearest tsconfig resolves to its own
+    /// TypeScript, otherwise the workspace project (`None`).
+    fn route_project(&mut self, payload: &Value) -> Option<usize> {
+        self.project_for_file(Path::new(payload_file(payload)?))
+    }
+
+    fn project_for_file(&mut self, file: &Path) -> Option<usize> {
+        let dir = file.parent()?;
+        let package_root = match self.packages.roots.get(dir) {
+            Some(root) => root.clone(),
+            None => {
+                let root = crate::provider::package_root(&self.workspace_root, file);
+                self.packages.roots.insert(dir.to_path_buf(), root.clone());
+                root
+            }
+        }?;
+        if let Some(slot) = self.packages.slots.get(&package_root) {
+            return *slot;
+        }
+        let slot = self.open_package_project(&package_root);
+        self.packages.slots.insert(package_root, slot);
+        slot
+    }
+
+    fn open_package_project(&mut self, package_root: &Path) -> Option<usize> {
+        let launch = &self.config.plugin().tsserver;
+        let resolve = |root: &Path| {
+            Provider::new(root)
+                .preview(launch)
+                .ok()
+                .map(|binary| normalize_root(binary.executable))
+        };
+        let workspace = self
+            .packages
+            .workspace_tsserver
+            .get_or_insert_with(|| resolve(&self.workspace_root))
+            .clone();
+        let tsserver = resolve(package_root)?;
+        if workspace.as_ref() == Some(&tsserver) {
+            return None;
+        }
+
+        let key = ProjectKey::package(package_root.to_path_buf(), tsserver.clone());
+        match self.registry.attach(key.clone(), self.config.clone()) {
+            Ok((handle, registration)) => {
+                log::info!("package {package_root:?} uses its own tsserver {tsserver:?}");
+                self.packages.projects.push(PackageProject {
+                    key,
+                    handle,
+                    events: registration.events,
+                    session_id: registration.session_id,
+                    configure: TsserverConfigureState::new(self.interactive_inlay_hints),
+                    evicted: false,
+                });
+                Some(self.packages.projects.len() - 1)
+            }
+            Err(err) => {
+                log::warn!("failed to start package project for {package_root:?}: {err}");
+                None
+            }
+        }
+    }
+
+    fn configure_project(&mut self, slot: Option<usize>) -> anyhow::Result<()> {
+        match slot {
+            Some(index) => {
+                let package = &mut self.packages.projects[index];
+                package.configure.ensure(&self.config, &package.handle)
+            }
+            None => self.tsserver_configure.ensure(&self.config, &self.project),
+        }
+    }
+
+    fn project_at(&self, slot: Option<usize>) -> &ProjectHandle {
+        slot.map_or(&self.project, |index| &self.packages.projects[index].handle)
+    }
+
+    /// Pending requests are not tied to a project, so all of them fail; the
+    /// project is registered again on the next client message.
+    fn handle_eviction(&mut self, reason: &str, slot: Option<usize>) -> anyhow::Result<()> {
+        let label = match slot {
+            Some(index) => self.packages.projects[index].handle.label().to_string(),
+            None => self.project_label.clone(),
+        };
+        let message = format!(
+            "ts-bridge: project {label} was shut down: {reason}; it restarts on the next request"
+        );
+        log::warn!("{message}");
+        match slot {
+            Some(index) => self.packages.projects[index].evicted = true,
+            None => self.evicted = true,
+        }
+        for response in self
+            .pending
+            .fail_slot(slot, "project was shut down by the daemon")
+        {
+            self.timings.discard(&response.id);
+            self.connection.sender.send(response.into())?;
+        }
+        let packages = &self.packages;
+        self.diag_state
+            .clear_files(|file| cached_slot(packages, file) == slot);
+        self.loading_projects
+            .retain(|project| cached_slot(packages, project) != slot);
+        self.diagnostic_generation += 1;
+        show_message(&self.connection, &message, lsp_types::MessageType::WARNING)
+    }
+
+    fn handle_daemon_stopping(&mut self) -> anyhow::Result<()> {
+        log::warn!("daemon is shutting down");
+        for response in self.pending.fail_all("ts-bridge daemon is shutting down") {
+            self.timings.discard(&response.id);
+            self.connection.sender.send(response.into())?;
+        }
+        show_message(
+            &self.connection,
+            "ts-bridge: the daemon is shutting down",
+            lsp_types::MessageType::WARNING,
+        )
+    }
+
+    /// Registers the session again with the projects the registry evicted
+    /// and reopens the documents so the fresh tsserver sees their contents.
+    fn reattach_evicted_projects(&mut self) -> anyhow::Result<()> {
+        let mut reattached = false;
+        if self.evicted {
+            let key = ProjectKey::workspace(self.workspace_root.clone());
+            match self.register_with(key) {
+                Ok((handle, registration)) => {
+                    self.project = handle;
+                    self.events = registration.events;
+                    self.session_id = registration.session_id;
+                    crate::logging::set_session(self.session_id);
+                    self.evicted = false;
+                    reattached = true;
+                }
+                Err(err) => log::warn!("failed to restart project {}: {err}", self.project_label),
+            }
+        }
+        for index in 0..self.packages.projects.len() {
+            if !self.packages.projects[index].evicted {
+                continue;
+            }
+            let key = self.packages.projects[index].key.clone();
+            match self.register_with(key) {
+                Ok((handle, registration)) => {
+                    let package = &mut self.packages.projects[index];
+                    package.handle = handle;
+                    package.events = registration.events;
+                    package.session_id = registration.session_id;
+                    package.evicted = false;
+                    reattached = true;
+                }
+                Err(err) => log::warn!("failed to restart package project: {err}"),
+            }
+        }
+        if !reattached {
+            return Ok(());
+        }
+        log::info!("re-registered session with evicted projects");
+        self.invalidate_configure();
+        self.reopen_documents()
+    }
+
+    fn register_with(
+        &self,
+        key: ProjectKey,
+    ) -> anyhow::Result<(ProjectHandle, SessionRegistration)> {
+        self.registry.attach(key, self.config.clone())
+    }
+
+    fn invalidate_configure(&mut self) {
+        self.tsserver_configure.invalidate();
+        for package in &mut self.packages.projects {
+            package.configure.invalidate();
+        }
+    }
+
+    /// Seqs are unique across projects, so cancelling everywhere only
+    /// reaches the tsserver that owns the request.
+    fn cancel_tsserver_request(&self, key: PendingKey) {
+        self.project.cancel(key.server, key.seq);
+        for package in &self.packages.projects {
+            package.handle.cancel(key.server, key.seq);
+        }
+    }
+
+    fn restart_packages(&self, kind: RestartKind) {
+        for package in &self.packages.projects {
+            if let Err(err) = package.handle.restart(kind) {
+                log::warn!(
+                    "failed to restart package project {}: {err}",
+                    package.handle.label()
+                );
             }
         }
+    }
+
+    fn expire_requests(&mut self) -> anyhow::Result<()> {
+        let (responses, keys) = self.pending.expire(Instant::now());
+        for key in keys {
+            log::warn!("tsserver {:?} request {} timed out", key.server, key.seq);
+            self.cancel_tsserver_request(key);
+        }
+        for response in responses {
+            self.timings.discard(&response.id);
+            self.connection.sender.send(response.into())?;
+        }
+        Ok(())
+    }
 
+    /// Records the latency of a reply to `id`, whose last tsserver response
+    /// arrived at `responded`, and traces it when the client asked for
+    /// verbose tracing.
+    fn finish_request_timing(&mut self, id: &RequestId, responded: Instant) -> anyhow::Result<()> {
+        let Some(timing) = self.timings.finish(id, responded, Instant::now()) else {
+            return Ok(());
+        };
+        if self.trace != lsp_types::TraceValue::Verbose {
+            return Ok(());
+        }
+        let params = lsp_types::LogTraceParams {
+            message: format!(
+                "{} ({id}) answered in {:.1}ms",
+                timing.method,
+                timing.total.as_secs_f64() * 1000.0
+            ),
+            verbose: Some(format!(
+                "tsserver responded after {:.1}ms, reply built in {:.1}ms",
+                timing.tsserver.as_secs_f64() * 1000.0,
+                (timing.total - timing.tsserver).as_secs_f64() * 1000.0
+            )),
+        };
+        let notif =
+            ServerNotification::new(LogTrace::METHOD.to_string(), serde_json::to_value(params)?);
+        self.connection.sender.send(Message::Notification(notif))?;
         Ok(())
     }
 
     fn drain_project_events(&mut self) -> anyhow::Result<()> {
         loop {
             match self.events.try_recv() {
-                Ok(event) => self.handle_project_event(event)?,
+                Ok(event) => self.handle_project_event(event, None)?,
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => {
                     log::warn!("project event channel closed for {}", self.project_label);
@@ -1344,47 +4984,110 @@ impl SessionState {
                 }
             }
         }
+        for index in 0..self.packages.projects.len() {
+            while let Ok(event) = self.packages.projects[index].events.try_recv() {
+                self.handle_project_event(event, Some(index))?;
+            }
+        }
+        self.publish_ready_diagnostics()
+    }
+
+    /// Sends what this tick's events left ready, once per document and only
+    /// where it differs from what the client already shows.
+    fn publish_ready_diagnostics(&mut self) -> anyhow::Result<()> {
+        let documents = &self.documents;
+        for (uri, diagnostics) in self.diag_state.take_changed(|uri| documents.is_open(uri)) {
+            publish_diagnostics(
+                &self.connection,
+                PublishDiagnosticsParams {
+                    uri,
+                    diagnostics,
+                    version: None,
+                },
+            )?;
+        }
         Ok(())
     }
 
-    fn handle_project_event(&mut self, event: ProjectEvent) -> anyhow::Result<()> {
+    /// `slot` names the package project the event came from (`None` for the
+    /// workspace project).
+    fn handle_project_event(
+        &mut self,
+        event: ProjectEvent,
+        slot: Option<usize>,
+    ) -> anyhow::Result<()> {
         match event {
-            ProjectEvent::Server(event) => self.handle_server_event(event),
+            ProjectEvent::Server(event) => self.handle_server_event(event, slot),
             ProjectEvent::ConfigUpdated(config) => {
-                self.config = config;
-                self.tsserver_configure.invalidate();
+                self.config = *config;
+                self.diag_state
+                    .set_include_suggestions(self.config.plugin().diagnostics.include_suggestions);
+                self.pending.set_timeout(self.config.plugin());
+                if self.config.plugin().publish_diagnostic_on == DiagnosticPublishMode::Change {
+                    self.flush_deferred_diagnostics(None);
+                }
+                self.invalidate_configure();
+                self.configure_open_file_scopes();
                 self.inlay_cache.clear();
+                self.completion_cache.clear();
+                self.diagnostic_generation += 1;
+                Ok(())
+            }
+            ProjectEvent::DiagnosticServerToggled { enabled } => {
+                self.handle_diagnostic_server_toggle(enabled)
+            }
+            ProjectEvent::Evicting { reason } => self.handle_eviction(&reason, slot),
+            ProjectEvent::DaemonStopping => self.handle_daemon_stopping(),
+            ProjectEvent::Resync { uri } => {
+                self.resync_document(uri);
                 Ok(())
             }
+            ProjectEvent::Overwritten { uri } => {
+                let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+                show_message(
+                    &self.connection,
+                    &format!(
+                        "ts-bridge: another editor's copy of {file} replaced yours in tsserver; your next edit to it sends your buffer again"
+                    ),
+                    lsp_types::MessageType::WARNING,
+                )
+            }
             ProjectEvent::Restarting { kind } => self.handle_restart_start(kind),
             ProjectEvent::Restarted { kind } => self.handle_restart_complete(kind),
+            ProjectEvent::Stderr { server, line } => {
+                let label = RestartKind::from(server).label();
+                log_message(
+                    &self.connection,
+                    &format!("[tsserver {label}] {line}"),
+                    lsp_types::MessageType::LOG,
+                )
+            }
+            ProjectEvent::ServerExited {
+                kind,
+                reason,
+                retry_in,
+            } => self.handle_server_exit(kind, &reason, retry_in),
             ProjectEvent::RestartFailed { kind, message } => {
                 self.handle_restart_failure(kind, &message)
             }
         }
     }
 
-    fn handle_server_event(&mut self, event: ServerEvent) -> anyhow::Result<()> {
-        if let Some(diag_event) = protocol::diagnostics::parse_tsserver_event(&event.payload) {
+    fn handle_server_event(
+        &mut self,
+        event: ServerEvent,
+        slot: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if let Some(diag_event) = protocol::diagnostics::parse_tsserver_event(
+            &event.payload,
+            &self.config.plugin().diagnostics,
+        ) {
             let stage_label = match &diag_event {
                 DiagnosticsEvent::Report { kind, .. } => Some(stage_text(*kind)),
                 DiagnosticsEvent::Completed { .. } => Some("finalizing diagnostics"),
+                DiagnosticsEvent::Region { .. } => Some("checking the visible ranges"),
             };
             self.diag_state.handle_event(event.server, diag_event);
-            while let Some((uri, diagnostics)) = self.diag_state.take_ready() {
-                if !self.documents.is_open(&uri) {
-                    self.diag_state.clear_file(&uri);
-                    continue;
-                }
-                publish_diagnostics(
-                    &self.connection,
-                    PublishDiagnosticsParams {
-                        uri,
-                        diagnostics,
-                        version: None,
-                    },
-                )?;
-            }
             if self.diag_state.has_pending() {
                 let message = if let Some(stage) = stage_label {
                     format!("Analyzing {} — {stage}", self.project_label)
@@ -1398,7 +5101,7 @@ impl SessionState {
                 ) {
                     log::debug!("work-done progress report failed: {err:?}");
                 }
-            } else {
+            } else if self.loading_projects.is_empty() {
                 if let Err(err) = self.progress.end(
                     &self.connection,
                     &format!("Language features ready in {}", self.project_label),
@@ -1410,53 +5113,279 @@ impl SessionState {
             return Ok(());
         }
 
+        if let Some(typings) = TypingsEvent::parse(&event.payload) {
+            return self.handle_typings_event(typings);
+        }
+
+        if let Some(loading) = ProjectLoadingEvent::parse(&event.payload) {
+            self.handle_project_loading_event(loading);
+            return Ok(());
+        }
+        if event.payload.get("event").and_then(Value::as_str) == Some("projectsUpdatedInBackground")
+        {
+            if let Err(err) = self.progress.report(
+                &self.connection,
+                &format!("Updating {}", self.project_label),
+                None,
+            ) {
+                log::debug!("work-done progress report failed: {err:?}");
+            }
+            let open_files: Vec<String> = event
+                .payload
+                .pointer("/body/openFiles")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|file| file.as_str().map(str::to_string))
+                .collect();
+            self.request_project_diagnostics(&open_files);
+            return Ok(());
+        }
+
+        let responded = Instant::now();
+        if let Some(response) =
+            self.pending
+                .reject_stale(event.server, &event.payload, &self.documents)
+        {
+            self.timings.discard(&response.id);
+            self.connection.sender.send(response.into())?;
+            return Ok(());
+        }
+        let project = match slot {
+            Some(index) => &self.packages.projects[index].handle,
+            None => &self.project,
+        };
         if let Some(response) = self.pending.resolve(
             event.server,
             &event.payload,
             &mut self.inlay_cache,
-            &self.project,
+            &mut self.completion_cache,
+            project,
+            &self.connection,
         )? {
+            let id = response.id.clone();
             self.connection.sender.send(response.into())?;
+            self.finish_request_timing(&id, responded)?;
         } else {
             log::trace!("tsserver {:?} -> {}", event.server, event.payload);
         }
         Ok(())
     }
 
+    /// `ts-bridge/tsserverLog`: the last `kb` KiB (default 64) of the
+    /// `server` tsserver's log, the semantic one by default when it runs.
+    fn tsserver_log(&self, params: &Value) -> Result<Value, (ErrorCode, String)> {
+        let plugin = self.config.plugin();
+        let Some(dir) = &plugin.tsserver.log_directory else {
+            return Err((
+                ErrorCode::InvalidRequest,
+                "tsserver.log_directory is not configured".to_string(),
+            ));
+        };
+        let server = match params.get("server").and_then(Value::as_str) {
+            None if plugin.separate_diagnostic_server => ServerKind::Semantic,
+            None | Some("syntax") => ServerKind::Syntax,
+            Some("semantic") => ServerKind::Semantic,
+            Some(other) => {
+                return Err((
+                    ErrorCode::InvalidParams,
+                    format!("unknown tsserver {other:?}; expected syntax or semantic"),
+                ));
+            }
+        };
+        let kb = params
+            .get("kb")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_LOG_TAIL_KB)
+            .clamp(1, MAX_LOG_TAIL_KB);
+        let path = crate::process::log_file_path(dir, server);
+        let tail = read_log_tail(&path, kb * 1024).map_err(|err| {
+            (
+                ErrorCode::InternalError,
+                format!("cannot read {}: {err}", path.display()),
+            )
+        })?;
+        Ok(json!({
+            "server": server.as_str(),
+            "path": path.to_string_lossy(),
+            "size": tail.size,
+            "truncated": tail.truncated,
+            "content": tail.content,
+        }))
+    }
+
+    /// `ts-bridge/status`: every project the process runs plus this
+    /// session's view of its own.
+    fn status(&self) -> Value {
+        json!({
+            "projects": self.registry.status_snapshot(),
+            "idle_ttl_seconds": self.registry.idle_ttl().map(|ttl| ttl.as_secs()),
+            "session": {
+                "id": self.session_id,
+                "project_root": self.project.root().to_string_lossy(),
+                "workspace_root": self.workspace_root.to_string_lossy(),
+                "pending_requests": self.pending.request_count(),
+                "open_documents": self.documents.open_count(),
+                "latency": self.timings.snapshot(),
+            },
+        })
+    }
+
+    /// Shows which tsconfig tsserver is loading on the session progress,
+    /// which stays open until every load and pending diagnostics finish.
+    fn handle_project_loading_event(&mut self, event: ProjectLoadingEvent) {
+        let result = match event {
+            ProjectLoadingEvent::Start { project } => {
+                let message = format!("Loading {} project...", self.project_name(&project));
+                self.loading_projects.insert(project);
+                self.progress
+                    .begin(&self.connection, "ts-bridge", &message)
+                    .and_then(|_| self.progress.report(&self.connection, &message, None))
+            }
+            ProjectLoadingEvent::Finish { project } => {
+                let message = format!("Loaded {} project", self.project_name(&project));
+                self.loading_projects.remove(&project);
+                if self.loading_projects.is_empty() && !self.diag_state.has_pending() {
+                    self.progress.end(&self.connection, &message)
+                } else {
+                    self.progress.report(&self.connection, &message, None)
+                }
+            }
+        };
+        if let Err(err) = result {
+            log::debug!("project loading progress failed: {err:?}");
+        }
+    }
+
+    /// `project` relative to the workspace root when it is inside it.
+    fn project_name(&self, project: &str) -> String {
+        Path::new(project)
+            .strip_prefix(&self.workspace_root)
+            .map(|relative| relative.display().to_string())
+            .unwrap_or_else(|_| project.to_string())
+    }
+
+    /// Typings installs hold up the first completions in a JavaScript
+    /// project, so they get their own progress and failures are shown.
+    fn handle_typings_event(&mut self, event: TypingsEvent) -> anyhow::Result<()> {
+        let mut failure = None;
+        let result = match event {
+            TypingsEvent::Begin { packages } => self.typings_progress.begin(
+                &self.connection,
+                "Installing typings",
+                &packages.join(", "),
+            ),
+            TypingsEvent::End { packages, success } => {
+                let message = if success {
+                    format!("Installed typings: {}", packages.join(", "))
+                } else {
+                    let message = format!("Failed to install typings: {}", packages.join(", "));
+                    failure = Some(format!(
+                        "ts-bridge: {message}; completions for those packages stay untyped"
+                    ));
+                    message
+                };
+                self.typings_progress.end(&self.connection, &message)
+            }
+            TypingsEvent::InitializationFailed { message } => {
+                log::warn!("tsserver typings installer failed to start: {message}");
+                failure = Some(format!(
+                    "ts-bridge: the typings installer failed to start ({message}); check tsserver.npm_location"
+                ));
+                self.typings_progress
+                    .begin(&self.connection, "Installing typings", "")
+                    .and_then(|_| {
+                        self.typings_progress.end(
+                            &self.connection,
+                            &format!("Typings installer unavailable: {message}"),
+                        )
+                    })
+            }
+        };
+        if let Err(err) = result {
+            log::debug!("typings progress notification failed: {err:?}");
+        }
+        match failure {
+            Some(message) => {
+                show_message(&self.connection, &message, lsp_types::MessageType::WARNING)
+            }
+            None => Ok(()),
+        }
+    }
+
     fn handle_notification(&mut self, notif: ServerNotification) -> anyhow::Result<bool> {
         if notif.method == "exit" {
             return Ok(true);
         }
+        self.reattach_evicted_projects()?;
         if notif.method == "ts-bridge/control" {
             self.handle_control_notification(notif.params)?;
             return Ok(false);
         }
+        if notif.method == SetTrace::METHOD {
+            let params: lsp_types::SetTraceParams = serde_json::from_value(notif.params)?;
+            self.trace = params.value;
+            return Ok(false);
+        }
+        if notif.method == Cancel::METHOD {
+            let params: lsp_types::CancelParams = serde_json::from_value(notif.params)?;
+            self.cancel_request(params.id)?;
+            return Ok(false);
+        }
         if notif.method == DidOpenTextDocument::METHOD {
             let params: crate::types::DidOpenTextDocumentParams =
                 serde_json::from_value(notif.params)?;
-            if let Ok(uri) = lsp_types::Uri::from_str(&params.text_document.uri) {
+            let parsed_uri = lsp_types::Uri::from_str(&params.text_document.uri).ok();
+            if let Some(uri) = &parsed_uri {
                 self.documents.open(
-                    &uri,
+                    uri,
                     &params.text_document.text,
                     Some(params.text_document.version),
                     params.text_document.language_id.clone(),
                 );
-                self.inlay_cache.invalidate(&uri);
+                self.inlay_cache.invalidate(uri);
+                self.completion_cache.invalidate(uri);
             }
+            let language = params.text_document.language_id.as_deref();
+            if !self.config.plugin().language_enabled(language) {
+                log::debug!(
+                    "not syncing {} (language {language:?} is disabled)",
+                    params.text_document.uri
+                );
+                self.unsynced_documents
+                    .insert(params.text_document.uri.clone());
+                return Ok(false);
+            }
+            let diagnostics_enabled = self
+                .config
+                .plugin()
+                .language_feature_enabled(language, "diagnostics");
+            let uri_string = params.text_document.uri.clone();
+            self.diagnostic_generation += 1;
             let file_for_diagnostics = uri_to_file_path(params.text_document.uri.as_str())
                 .unwrap_or_else(|| params.text_document.uri.to_string());
+            let event = parsed_uri.map(|uri| DocumentEvent::Open {
+                uri,
+                text: params.text_document.text.clone(),
+                version: Some(params.text_document.version),
+                language_id: params.text_document.language_id.clone(),
+            });
             let spec =
                 crate::protocol::text_document::did_open::handle(params, &self.workspace_root);
-            if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-                log::warn!("failed to configure tsserver: {err}");
+            if let Err(err) = self.send_document(event, spec.route, spec.payload, spec.priority) {
+                log::warn!("failed to dispatch didOpen: {err}");
             }
-            if let Err(err) = self
-                .project
-                .dispatch_request(spec.route, spec.payload, spec.priority)
+            if is_inferred_project_file(Path::new(&file_for_diagnostics))
+                && let Err(err) = self
+                    .tsserver_configure
+                    .note_inferred_file(&self.config, &self.project)
             {
-                log::warn!("failed to dispatch didOpen: {err}");
+                log::warn!("failed to send inferred project options: {err}");
+            }
+            self.configure_file_scope(&uri_string);
+            if diagnostics_enabled {
+                self.request_file_diagnostics(&file_for_diagnostics);
             }
-            self.request_file_diagnostics(&file_for_diagnostics);
             if let Err(err) = self.progress.report(
                 &self.connection,
                 &format!("Analyzing {} — scheduling diagnostics", self.project_label),
@@ -1469,28 +5398,58 @@ impl SessionState {
         if notif.method == DidChangeTextDocument::METHOD {
             let params: crate::types::DidChangeTextDocumentParams =
                 serde_json::from_value(notif.params)?;
+            let mut jsx_close_trigger = None;
+            let mut diagnostics_enabled = true;
+            let mut event = None;
             if let Ok(uri) = lsp_types::Uri::from_str(&params.text_document.uri) {
                 self.documents.apply_changes(
                     &uri,
                     &params.content_changes,
                     params.text_document.version,
                 );
+                if self.unsynced_documents.contains(uri.as_str()) {
+                    return Ok(false);
+                }
+                event = self.documents.is_open(&uri).then(|| DocumentEvent::Change {
+                    uri: uri.clone(),
+                    changes: params.content_changes.clone(),
+                    text: None,
+                    version: params.text_document.version,
+                });
+                diagnostics_enabled = self
+                    .config
+                    .plugin()
+                    .language_feature_enabled(self.documents.language_id(&uri), "diagnostics");
                 self.inlay_cache.invalidate(&uri);
+                self.completion_cache
+                    .note_changes(&uri, &params.content_changes);
+                let jsx_close_tag = &self.config.plugin().jsx_close_tag;
+                if jsx_close_tag.enable
+                    && jsx_closing_tag::applies_to(
+                        &jsx_close_tag.filetypes,
+                        self.documents.language_id(&uri),
+                        uri.as_str(),
+                    )
+                    && let Some(position) =
+                        jsx_closing_tag::trigger_position(&params.content_changes)
+                {
+                    jsx_close_trigger = Some((uri, params.text_document.version, position));
+                }
             }
+            self.diagnostic_generation += 1;
             let file_for_diagnostics = uri_to_file_path(params.text_document.uri.as_str())
                 .unwrap_or_else(|| params.text_document.uri.to_string());
             let spec =
                 crate::protocol::text_document::did_change::handle(params, &self.workspace_root);
-            if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-                log::warn!("failed to configure tsserver: {err}");
-            }
-            if let Err(err) = self
-                .project
-                .dispatch_request(spec.route, spec.payload, spec.priority)
-            {
+            if let Err(err) = self.send_document(event, spec.route, spec.payload, spec.priority) {
                 log::warn!("failed to dispatch didChange: {err}");
             }
-            self.request_file_diagnostics(&file_for_diagnostics);
+            if let Some((uri, version, position)) = jsx_close_trigger {
+                self.request_jsx_closing_tag(&uri, version, position);
+            }
+            if diagnostics_enabled {
+                self.schedule_file_diagnostics(file_for_diagnostics);
+            }
             if let Err(err) = self.progress.report(
                 &self.connection,
                 &format!("Analyzing {} — scheduling diagnostics", self.project_label),
@@ -1504,45 +5463,111 @@ impl SessionState {
             let params: crate::types::DidCloseTextDocumentParams =
                 serde_json::from_value(notif.params)?;
             let uri = params.text_document.uri.clone();
-            if let Ok(parsed) = lsp_types::Uri::from_str(&uri) {
-                self.documents.close(&parsed);
-                self.inlay_cache.invalidate(&parsed);
-                self.diag_state.clear_file(&parsed);
+            if self.unsynced_documents.remove(&uri) {
+                if let Ok(parsed) = lsp_types::Uri::from_str(&uri) {
+                    self.documents.close(&parsed);
+                }
+                return Ok(false);
             }
+            let file = uri_to_file_path(&uri).unwrap_or_else(|| uri.clone());
+            self.deferred_diagnostics.remove(&file);
+            self.debounced_diagnostics.remove(&file);
+            self.visible_ranges.remove(&file);
+            let parsed = lsp_types::Uri::from_str(&uri).ok();
+            if let Some(parsed) = &parsed {
+                self.documents.close(parsed);
+                self.inlay_cache.invalidate(parsed);
+                self.completion_cache.invalidate(parsed);
+                self.diag_state.clear_file(parsed);
+            }
+            self.diagnostic_generation += 1;
+            let event = parsed.map(|uri| DocumentEvent::Close { uri });
             let spec =
                 crate::protocol::text_document::did_close::handle(params, &self.workspace_root);
-            if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-                log::warn!("failed to configure tsserver: {err}");
-            }
-            if let Err(err) = self
-                .project
-                .dispatch_request(spec.route, spec.payload, spec.priority)
-            {
+            if let Err(err) = self.send_document(event, spec.route, spec.payload, spec.priority) {
                 log::warn!("failed to dispatch didClose: {err}");
             }
             clear_client_diagnostics(&self.connection, uri)?;
             return Ok(false);
         }
+        if notif.method == protocol::diagnostics::VISIBLE_RANGES_METHOD {
+            self.update_visible_ranges(&notif.params);
+            return Ok(false);
+        }
+        if notif.method == protocol::diagnostics::REQUEST_METHOD
+            || notif.method == DidSaveTextDocument::METHOD
+        {
+            let file = notif
+                .params
+                .pointer("/textDocument/uri")
+                .and_then(Value::as_str)
+                .map(|uri| uri_to_file_path(uri).unwrap_or_else(|| uri.to_string()));
+            self.flush_deferred_diagnostics(file.as_deref());
+            return Ok(false);
+        }
         if notif.method == DidChangeConfiguration::METHOD {
             let params: lsp_types::DidChangeConfigurationParams =
                 serde_json::from_value(notif.params)?;
+            self.report_settings_problems(validate_settings(&params.settings))?;
+            for package in &self.packages.projects {
+                if let Err(err) = package.handle.update_config(params.settings.clone()) {
+                    log::warn!("failed to update package project settings: {err}");
+                }
+            }
             let update = self.project.update_config(params.settings)?;
             self.config = update.config;
+            self.pending.set_timeout(self.config.plugin());
             if update.changed {
                 log::info!("workspace settings reloaded from didChangeConfiguration");
-                self.tsserver_configure.invalidate();
-                // TODO: restart auxiliary tsserver processes when toggles require it.
+                self.invalidate_configure();
+                self.configure_open_file_scopes();
+                self.diagnostic_generation += 1;
+                self.refresh_pull_diagnostics()?;
             }
             return Ok(false);
         }
-        if let Some(spec) = protocol::route_notification(&notif.method, notif.params.clone()) {
-            if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-                log::warn!("failed to configure tsserver: {err}");
+        if notif.method == DidRenameFiles::METHOD || notif.method == DidDeleteFiles::METHOD {
+            let removed = if notif.method == DidRenameFiles::METHOD {
+                let params: lsp_types::RenameFilesParams = serde_json::from_value(notif.params)?;
+                file_operations::renamed_away(&params)
+            } else {
+                let params: lsp_types::DeleteFilesParams = serde_json::from_value(notif.params)?;
+                file_operations::deleted(&params)
+            };
+            self.handle_removed_files(&removed)?;
+            return Ok(false);
+        }
+        if notif.method == DidChangeWatchedFiles::METHOD {
+            let params: lsp_types::DidChangeWatchedFilesParams =
+                serde_json::from_value(notif.params)?;
+            self.clear_deleted_diagnostics(&did_change_watched_files::deleted(&params))?;
+            let specs = did_change_watched_files::handle(params, &self.workspace_root, |uri| {
+                lsp_types::Uri::from_str(uri)
+                    .map(|uri| self.documents.is_open(&uri))
+                    .unwrap_or(false)
+            });
+            if specs.is_empty() {
+                return Ok(false);
             }
-            if let Err(err) = self
-                .project
-                .dispatch_request(spec.route, spec.payload, spec.priority)
-            {
+            for spec in specs {
+                if let Err(err) = self.dispatch(spec.route, spec.payload, spec.priority) {
+                    log::warn!("failed to dispatch watched file change: {err}");
+                }
+            }
+            // Disk changes can affect any open document, so cached hints and
+            // pull diagnostic result ids are stale; push diagnostics are redone.
+            self.inlay_cache.clear();
+            self.completion_cache.clear();
+            self.diagnostic_generation += 1;
+            self.refresh_pull_diagnostics()?;
+            for snapshot in self.documents.open_documents() {
+                let file = uri_to_file_path(&snapshot.uri).unwrap_or(snapshot.uri);
+                self.request_file_diagnostics(&file);
+            }
+            return Ok(false);
+        }
+        if let Some(spec) = protocol::route_notification(&notif.method, notif.params.clone()) {
+            if let Err(err) = self.dispatch(spec.route, spec.payload, spec.priority) {
                 log::warn!("failed to dispatch notification {}: {err}", notif.method);
             }
         } else {
@@ -1551,6 +5576,89 @@ impl SessionState {
         Ok(false)
     }
 
+    /// Cancels the tsserver requests still serving `id` and answers it with
+    /// `RequestCancelled`.  Requests that already completed are left alone.
+    fn cancel_request(&mut self, id: lsp_types::NumberOrString) -> anyhow::Result<()> {
+        let id = match id {
+            lsp_types::NumberOrString::Number(id) => RequestId::from(id),
+            lsp_types::NumberOrString::String(id) => RequestId::from(id),
+        };
+        let keys = self.pending.cancel(&id);
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.timings.discard(&id);
+        for key in keys {
+            self.cancel_tsserver_request(key);
+        }
+        let response = Response::new_err(
+            id,
+            ErrorCode::RequestCanceled as i32,
+            "request cancelled".to_string(),
+        );
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    /// Forgets documents and diagnostics for URIs that were renamed or deleted
+    /// on disk, including everything below a removed folder.
+    fn handle_removed_files(&mut self, removed: &[String]) -> anyhow::Result<()> {
+        let mut closed: Vec<String> = removed.to_vec();
+        for snapshot in self.documents.open_documents() {
+            if !closed.contains(&snapshot.uri)
+                && removed
+                    .iter()
+                    .any(|uri| file_operations::is_affected(&snapshot.uri, uri))
+            {
+                closed.push(snapshot.uri);
+            }
+        }
+
+        let mut cleared: Vec<lsp_types::Uri> = Vec::new();
+        for uri in &closed {
+            if let Ok(parsed) = lsp_types::Uri::from_str(uri) {
+                self.documents.close(&parsed);
+                self.inlay_cache.invalidate(&parsed);
+                self.completion_cache.invalidate(&parsed);
+                cleared.push(parsed);
+            }
+        }
+        for uri in removed {
+            for stale in self.diag_state.clear_affected(uri) {
+                if !cleared.contains(&stale) {
+                    cleared.push(stale);
+                }
+            }
+        }
+        self.diagnostic_generation += 1;
+        self.refresh_pull_diagnostics()?;
+
+        for spec in file_operations::handle(&closed, &self.workspace_root) {
+            if let Err(err) = self.dispatch(spec.route, spec.payload, spec.priority) {
+                log::warn!("failed to dispatch file operation cleanup: {err}");
+            }
+        }
+        for uri in cleared {
+            clear_client_diagnostics(&self.connection, uri.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Drops the diagnostics shown for files deleted on disk. Open documents
+    /// keep theirs; the editor buffer still exists.
+    fn clear_deleted_diagnostics(&mut self, deleted: &[String]) -> anyhow::Result<()> {
+        for uri in deleted {
+            for stale in self.diag_state.affected(uri) {
+                if self.documents.is_open(&stale) {
+                    continue;
+                }
+                self.diag_state.clear_file(&stale);
+                clear_client_diagnostics(&self.connection, stale.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
     fn handle_control_notification(&mut self, params: Value) -> anyhow::Result<()> {
         let Some(action) = params.get("action").and_then(|value| value.as_str()) else {
             log::warn!("control notification missing action");
@@ -1577,6 +5685,7 @@ impl SessionState {
                 return Ok(());
             }
         }
+        self.restart_packages(restart.kind);
         if let Err(err) = self.project.restart(restart.kind) {
             log::warn!("control restart failed: {err}");
         }
@@ -1584,6 +5693,7 @@ impl SessionState {
     }
 
     fn handle_request(&mut self, req: Request) -> anyhow::Result<bool> {
+        let received = Instant::now();
         let lsp_server::Request { id, method, params } = req;
 
         if method == "shutdown" {
@@ -1603,9 +5713,25 @@ impl SessionState {
             return Ok(false);
         }
 
+        self.reattach_evicted_projects()?;
+
         if method == "ts-bridge/status" {
-            let projects = self.registry.status_snapshot();
-            let response = Response::new_ok(id, json!({ "projects": projects }));
+            let response = Response::new_ok(id, self.status());
+            self.connection.sender.send(response.into())?;
+            return Ok(false);
+        }
+
+        if method == control::PRELOAD_METHOD {
+            let response = preload_response(&self.registry, id, &params);
+            self.connection.sender.send(response.into())?;
+            return Ok(false);
+        }
+
+        if method == TSSERVER_LOG_METHOD {
+            let response = match self.tsserver_log(&params) {
+                Ok(result) => Response::new_ok(id, result),
+                Err((code, message)) => Response::new_err(id, code as i32, message),
+            };
             self.connection.sender.send(response.into())?;
             return Ok(false);
         }
@@ -1626,20 +5752,34 @@ impl SessionState {
             }
         }
 
+        if let Some(feature) = language_feature(&method)
+            && !self.language_feature_enabled(&params, feature)
+        {
+            let result = if method == DocumentDiagnosticRequest::METHOD {
+                json!({ "kind": "full", "items": [] })
+            } else {
+                Value::Null
+            };
+            let response = Response::new_ok(id, result);
+            self.connection.sender.send(response.into())?;
+            return Ok(false);
+        }
+
+        let origin = DocumentVersion::for_request(&method, &params, &self.documents);
         let params_value = params;
-        let spec: Option<protocol::RequestSpec>;
+        let mut spec: Option<protocol::RequestSpec>;
         let mut postprocess = None;
 
         if method == InlayHintRequest::METHOD {
-            let enabled = self.config.plugin().enable_inlay_hints;
-            if !enabled {
+            let hint_params: lsp_types::InlayHintParams =
+                serde_json::from_value(params_value.clone())
+                    .context("invalid inlay hint params")?;
+            let scope = self.document_scope(hint_params.text_document.uri.as_str());
+            if !self.config.plugin().inlay_hints_enabled(scope) {
                 let response = Response::new_ok(id, Value::Array(Vec::new()));
                 self.connection.sender.send(response.into())?;
                 return Ok(false);
             }
-            let hint_params: lsp_types::InlayHintParams =
-                serde_json::from_value(params_value.clone())
-                    .context("invalid inlay hint params")?;
             if let Some(cached) = self.inlay_cache.lookup(&hint_params) {
                 let response = Response::new_ok(id, serde_json::to_value(cached)?);
                 self.connection.sender.send(response.into())?;
@@ -1659,13 +5799,201 @@ impl SessionState {
             spec = Some(crate::protocol::text_document::inlay_hint::handle(
                 hint_params,
                 span,
+                self.config.plugin().maximum_inlay_hint_length,
+                self.interactive_inlay_hints,
+            ));
+        } else if method == DocumentDiagnosticRequest::METHOD {
+            let diag_params: lsp_types::DocumentDiagnosticParams =
+                serde_json::from_value(params_value).context("invalid diagnostic params")?;
+            let result_id = pull_diagnostics::result_id(
+                self.diagnostic_generation,
+                self.documents.version(&diag_params.text_document.uri),
+            );
+            if diag_params.previous_result_id.as_deref() == Some(result_id.as_str()) {
+                let response = Response::new_ok(id, pull_diagnostics::unchanged_report(result_id));
+                self.connection.sender.send(response.into())?;
+                return Ok(false);
+            }
+            spec = Some(pull_diagnostics::handle(
+                diag_params,
+                result_id,
+                self.config.plugin().diagnostics.clone(),
+            ));
+        } else if method == WorkspaceDiagnosticRequest::METHOD {
+            let diag_params: lsp_types::WorkspaceDiagnosticParams =
+                serde_json::from_value(params_value)
+                    .context("invalid workspace diagnostic params")?;
+            let open = self
+                .documents
+                .open_documents()
+                .into_iter()
+                .map(|doc| (uri_to_file_path(&doc.uri).unwrap_or(doc.uri), doc.version))
+                .collect::<Vec<_>>();
+            let Some(anchor) = open.iter().map(|(file, _)| file.clone()).min() else {
+                let response =
+                    Response::new_ok(id, protocol::workspace::diagnostic::empty_report());
+                self.connection.sender.send(response.into())?;
+                return Ok(false);
+            };
+            postprocess = Some(
+                match diag_params
+                    .partial_result_params
+                    .partial_result_token
+                    .clone()
+                {
+                    Some(token) => PostProcess::PartialResults { token },
+                    None => PostProcess::CollectReports {
+                        collector: ReportCollector::default(),
+                    },
+                },
+            );
+            let versions = open
+                .into_iter()
+                .filter_map(|(file, version)| Some((file, version?)))
+                .collect();
+            spec = Some(protocol::workspace::diagnostic::handle(
+                diag_params,
+                &anchor,
+                self.diagnostic_generation,
+                versions,
+                self.config.plugin().diagnostics.clone(),
+            ));
+        } else if method == lsp_types::request::CodeLensRequest::METHOD {
+            let mode = self.config.plugin().code_lens;
+            if !mode.is_enabled() {
+                let response = Response::new_ok(id, Value::Array(Vec::new()));
+                self.connection.sender.send(response.into())?;
+                return Ok(false);
+            }
+            let lens_params: lsp_types::CodeLensParams =
+                serde_json::from_value(params_value).context("invalid code lens params")?;
+            spec = Some(crate::protocol::text_document::code_lens::handle(
+                lens_params,
+                mode,
+            ));
+        } else if method == lsp_types::request::Completion::METHOD {
+            let completion_params: lsp_types::CompletionParams =
+                serde_json::from_value(params_value).context("invalid completion params")?;
+            let position = completion_params.text_document_position.position;
+            let uri = &completion_params.text_document_position.text_document.uri;
+            let trigger_character = completion_params
+                .context
+                .as_ref()
+                .and_then(|ctx| ctx.trigger_character.clone());
+            let doc_comment_range = self
+                .documents
+                .line_text(uri, position.line)
+                .and_then(|line| doc_comment_template::replace_range(line, position));
+            if let Some(range) = doc_comment_range {
+                spec = Some(doc_comment_template::handle(uri, position, range));
+            } else if trigger_character.as_deref() == Some(doc_comment_template::TRIGGER_CHARACTER)
+            {
+                let response = Response::new_ok(
+                    id,
+                    serde_json::to_value(lsp_types::CompletionList::default())?,
+                );
+                self.connection.sender.send(response.into())?;
+                return Ok(false);
+            } else {
+                let triggered_by_character =
+                    completion_params.context.as_ref().is_some_and(|ctx| {
+                        ctx.trigger_kind == CompletionTriggerKind::TRIGGER_CHARACTER
+                    });
+                let mut word = String::new();
+                if let Some(line) = self.documents.line_text(uri, position.line) {
+                    word = crate::protocol::text_document::completion::typed_word(
+                        line,
+                        position.character,
+                    );
+                    let key = CompletionCacheKey {
+                        uri: uri.to_string(),
+                        line: position.line,
+                        word_start: crate::protocol::text_document::completion::word_start(
+                            line,
+                            position.character,
+                        ),
+                    };
+                    if !triggered_by_character
+                        && let Some(cached) = self.completion_cache.lookup(&key, position)
+                    {
+                        let response = Response::new_ok(id, serde_json::to_value(cached)?);
+                        self.connection.sender.send(response.into())?;
+                        return Ok(false);
+                    }
+                    postprocess = Some(PostProcess::Completion { key, position });
+                }
+                let plugin = self.config.plugin();
+                let config = CompletionConfig {
+                    complete_function_calls: plugin.complete_function_calls,
+                    snippet_support: self.snippet_support,
+                    max_entries: plugin.completion.max_entries,
+                    entries_limit_strategy: plugin.completion.entries_limit_strategy,
+                    commit_characters: plugin.completion.commit_characters.clone(),
+                };
+                spec = Some(crate::protocol::text_document::completion::handle(
+                    completion_params,
+                    config,
+                    &word,
+                ));
+            }
+        } else if method == lsp_types::request::HoverRequest::METHOD {
+            let hover_params: lsp_types::HoverParams =
+                serde_json::from_value(params_value).context("invalid hover params")?;
+            spec = Some(crate::protocol::text_document::hover::handle(
+                hover_params,
+                self.config.plugin().hover.verbosity,
+            ));
+        } else if method == crate::protocol::text_document::hover::EXPAND_METHOD {
+            let expand_params: crate::protocol::text_document::hover::ExpandHoverParams =
+                serde_json::from_value(params_value).context("invalid expandHover params")?;
+            spec = Some(crate::protocol::text_document::hover::handle_expand(
+                expand_params,
+                self.config.plugin().hover.verbosity,
             ));
+        } else if method == lsp_types::request::WillSaveWaitUntil::METHOD {
+            let plugin = self.config.plugin();
+            let (organize, fix_all) = (plugin.organize_imports_on_save, plugin.fix_all_on_save);
+            let save_params: lsp_types::WillSaveTextDocumentParams =
+                serde_json::from_value(params_value).context("invalid willSaveWaitUntil params")?;
+            spec = crate::protocol::text_document::will_save_wait_until::handle(
+                save_params,
+                organize,
+                fix_all,
+            );
+            if spec.is_none() {
+                let response = Response::new_ok(id, Value::Array(Vec::new()));
+                self.connection.sender.send(response.into())?;
+                return Ok(false);
+            }
         } else {
+            if method == lsp_types::request::Formatting::METHOD
+                || method == lsp_types::request::OnTypeFormatting::METHOD
+            {
+                self.configure_file_formatting(&params_value);
+            }
+            let comma_edits = (method == lsp_types::request::Formatting::METHOD)
+                .then(|| self.trailing_comma_edits(&params_value))
+                .flatten();
             spec = protocol::route_request(&method, params_value);
+            if let Some(edits) = comma_edits
+                && let Some(spec) = spec.as_mut()
+            {
+                spec.response_context = Some(json!({ formatting::EXTRA_EDITS_KEY: edits }));
+            }
+        }
+        if !self.source_definition
+            && let Some(spec) = spec.as_mut()
+        {
+            if method == lsp_types::request::GotoDeclaration::METHOD {
+                declaration::fall_back_from_source_definition(spec);
+            } else {
+                definition::fall_back_from_source_definition(spec);
+            }
         }
 
         if let Some(spec) = spec {
-            if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
+            let slot = self.route_project(&spec.payload);
+            if let Err(err) = self.configure_project(slot) {
                 let response = Response::new_err(
                     id,
                     ErrorCode::InternalError as i32,
@@ -1675,7 +6003,7 @@ impl SessionState {
                 return Ok(false);
             }
             match self
-                .project
+                .project_at(slot)
                 .dispatch_request(spec.route, spec.payload, spec.priority)
             {
                 Ok(receipts) => {
@@ -1688,13 +6016,18 @@ impl SessionState {
                             );
                             self.connection.sender.send(response.into())?;
                         } else {
+                            self.timings.start(id.clone(), &method, received);
+                            let span = self.timings.span(&id);
                             self.pending.track(
                                 &receipts,
                                 id,
                                 adapter,
                                 spec.response_context,
                                 postprocess.clone(),
+                                origin,
                             );
+                            self.pending.assign_slot(&receipts, slot);
+                            self.pending.trace(&receipts, &span);
                         }
                     } else {
                         let response = Response::new_err(
@@ -1756,6 +6089,7 @@ impl SessionState {
             }
         }
 
+        self.restart_packages(restart.kind);
         match self.project.restart(restart.kind) {
             Ok(()) => {
                 let response = Response::new_ok(id, Value::Null);
@@ -1774,65 +6108,260 @@ impl SessionState {
         Ok(())
     }
 
-    fn handle_restart_start(&mut self, kind: RestartKind) -> anyhow::Result<()> {
-        let responses = self
-            .pending
-            .fail_all("tsserver restart canceled outstanding requests");
-        for response in responses {
-            self.connection.sender.send(response.into())?;
+    fn handle_restart_start(&mut self, kind: RestartKind) -> anyhow::Result<()> {
+        let responses = self
+            .pending
+            .fail_all("tsserver restart canceled outstanding requests");
+        for response in responses {
+            self.connection.sender.send(response.into())?;
+        }
+        self.timings.clear_started();
+
+        self.diag_state.clear();
+        self.loading_projects.clear();
+        self.inlay_cache.clear();
+        self.completion_cache.clear();
+        self.invalidate_configure();
+        self.diagnostic_generation += 1;
+        if let Err(err) =
+            self.restart_progress
+                .begin(&self.connection, "Restarting TypeScript server", kind)
+        {
+            log::debug!("restart progress begin failed: {err:?}");
+        }
+        Ok(())
+    }
+
+    fn handle_restart_complete(&mut self, kind: RestartKind) -> anyhow::Result<()> {
+        self.reopen_documents()?;
+        if let Err(err) =
+            self.restart_progress
+                .end(&self.connection, "TypeScript server restarted", kind)
+        {
+            log::debug!("restart progress end failed: {err:?}");
+        }
+        Ok(())
+    }
+
+    /// Requests owed by a stopped semantic server never complete, and a new
+    /// one has no files open, so diagnostics start over on whichever servers
+    /// now run.
+    fn handle_diagnostic_server_toggle(&mut self, enabled: bool) -> anyhow::Result<()> {
+        let responses = self
+            .pending
+            .fail_server(ServerKind::Semantic, "semantic tsserver was stopped");
+        for response in responses {
+            self.timings.discard(&response.id);
+            self.connection.sender.send(response.into())?;
+        }
+        self.diag_state.clear();
+        self.diagnostic_generation += 1;
+        if enabled {
+            self.invalidate_configure();
+            return self.reopen_documents();
+        }
+        for snapshot in self.documents.open_documents() {
+            if self.unsynced_documents.contains(&snapshot.uri) {
+                continue;
+            }
+            let file = uri_to_file_path(snapshot.uri.as_str()).unwrap_or(snapshot.uri);
+            self.request_file_diagnostics(&file);
+        }
+        Ok(())
+    }
+
+    fn handle_server_exit(
+        &mut self,
+        kind: RestartKind,
+        reason: &str,
+        retry_in: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        self.handle_restart_start(kind)?;
+        match retry_in {
+            Some(delay) => show_message(
+                &self.connection,
+                &format!(
+                    "ts-bridge: {} tsserver {reason}; restarting in {:.1}s",
+                    kind.label(),
+                    delay.as_secs_f32()
+                ),
+                lsp_types::MessageType::WARNING,
+            ),
+            None => self.handle_restart_failure(
+                kind,
+                &format!("tsserver {reason}; run TSBRestartProject to try again"),
+            ),
+        }
+    }
+
+    fn handle_restart_failure(&mut self, kind: RestartKind, message: &str) -> anyhow::Result<()> {
+        if let Err(err) =
+            self.restart_progress
+                .end(&self.connection, "TypeScript server restart failed", kind)
+        {
+            log::debug!("restart progress end failed: {err:?}");
+        }
+        show_message(
+            &self.connection,
+            &format!("ts-bridge restart failed: {message}"),
+            lsp_types::MessageType::ERROR,
+        )?;
+        Ok(())
+    }
+
+    /// Asks tsserver for the closing tag; the answer is pushed to the client as
+    /// `workspace/applyEdit` instead of a response.
+    fn request_jsx_closing_tag(
+        &mut self,
+        uri: &lsp_types::Uri,
+        version: Option<i32>,
+        position: lsp_types::Position,
+    ) {
+        let spec = match jsx_closing_tag::handle(uri, version, position) {
+            Ok(spec) => spec,
+            Err(err) => {
+                log::warn!("failed to build jsxClosingTag request: {err:?}");
+                return;
+            }
+        };
+        let Some(adapter) = spec.on_response else {
+            return;
+        };
+        let slot = self.route_project(&spec.payload);
+        match self.dispatch(spec.route, spec.payload, spec.priority) {
+            Ok(receipts) => {
+                self.pending.track(
+                    &receipts,
+                    next_request_id(),
+                    adapter,
+                    spec.response_context,
+                    Some(PostProcess::ApplyEdit),
+                    None,
+                );
+                self.pending.assign_slot(&receipts, slot);
+            }
+            Err(err) => log::warn!("failed to dispatch jsxClosingTag: {err}"),
+        }
+    }
+
+    /// Pull clients hold reports they will not ask about again until told
+    /// the project changed under them.
+    fn refresh_pull_diagnostics(&self) -> anyhow::Result<()> {
+        if !self.diagnostic_refresh {
+            return Ok(());
+        }
+        let request = Request::new(
+            next_request_id(),
+            <WorkspaceDiagnosticRefresh as LspRequest>::METHOD.to_string(),
+            Value::Null,
+        );
+        self.connection
+            .sender
+            .send(Message::Request(request))
+            .context("failed to send workspace/diagnostic/refresh")
+    }
+
+    /// Edits only request diagnostics by themselves with
+    /// `publish_diagnostic_on = "change"`, after `diagnostics.debounce_ms`
+    /// without further edits; otherwise the file waits for the editor's
+    /// `ts-bridge/requestDiagnostics` (or a save).
+    fn schedule_file_diagnostics(&mut self, file: String) {
+        match self.config.plugin().publish_diagnostic_on {
+            DiagnosticPublishMode::Change => match self.config.plugin().diagnostics.debounce_ms {
+                0 => self.request_file_diagnostics(&file),
+                debounce_ms => {
+                    let deadline = Instant::now() + Duration::from_millis(u64::from(debounce_ms));
+                    self.debounced_diagnostics.insert(file, deadline);
+                }
+            },
+            DiagnosticPublishMode::InsertLeave => {
+                self.deferred_diagnostics.insert(file);
+            }
+        }
+    }
+
+    /// Requests diagnostics for `file` (or every file) edited since the last
+    /// flush.
+    fn flush_deferred_diagnostics(&mut self, file: Option<&str>) {
+        let files: Vec<String> = match file {
+            Some(file) => self.deferred_diagnostics.take(file).into_iter().collect(),
+            None => self.deferred_diagnostics.drain().collect(),
+        };
+        for file in files {
+            self.request_file_diagnostics(&file);
         }
+    }
 
-        self.diag_state.clear();
-        self.inlay_cache.clear();
-        self.tsserver_configure.invalidate();
-        if let Err(err) =
-            self.restart_progress
-                .begin(&self.connection, "Restarting TypeScript server", kind)
-        {
-            log::debug!("restart progress begin failed: {err:?}");
+    fn update_visible_ranges(&mut self, params: &Value) {
+        let Some(uri) = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .and_then(|uri| lsp_types::Uri::from_str(uri).ok())
+        else {
+            log::debug!("visibleRanges notification without a document");
+            return;
+        };
+        let file = uri_to_file_path(uri.as_str()).unwrap_or_else(|| uri.to_string());
+        let ranges: Vec<crate::types::Range> = params
+            .get("ranges")
+            .cloned()
+            .and_then(|ranges| serde_json::from_value(ranges).ok())
+            .unwrap_or_default();
+        if ranges.is_empty() {
+            self.visible_ranges.remove(&file);
+        } else {
+            self.visible_ranges.insert(file, (uri, ranges));
         }
-        Ok(())
     }
 
-    fn handle_restart_complete(&mut self, kind: RestartKind) -> anyhow::Result<()> {
-        self.reopen_documents()?;
-        if let Err(err) =
-            self.restart_progress
-                .end(&self.connection, "TypeScript server restarted", kind)
-        {
-            log::debug!("restart progress end failed: {err:?}");
+    /// The visible ranges to check first when `file` is longer than
+    /// `diagnostics.region_line_threshold`.
+    fn region_ranges(&self, file: &str) -> Option<&[crate::types::Range]> {
+        let threshold = self.config.plugin().diagnostics.region_line_threshold;
+        if !self.region_diagnostics || threshold == 0 {
+            return None;
         }
-        Ok(())
+        let (uri, ranges) = self.visible_ranges.get(file)?;
+        let lines = self.documents.line_count(uri)?;
+        (lines > threshold as usize).then_some(ranges.as_slice())
     }
 
-    fn handle_restart_failure(&mut self, kind: RestartKind, message: &str) -> anyhow::Result<()> {
-        if let Err(err) =
-            self.restart_progress
-                .end(&self.connection, "TypeScript server restart failed", kind)
-        {
-            log::debug!("restart progress end failed: {err:?}");
+    /// Sends the debounced requests whose quiet period is over.
+    fn flush_debounced_diagnostics(&mut self) {
+        if self.debounced_diagnostics.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let due: Vec<String> = self
+            .debounced_diagnostics
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(file, _)| file.clone())
+            .collect();
+        for file in due {
+            self.debounced_diagnostics.remove(&file);
+            self.request_file_diagnostics(&file);
         }
-        show_message(
-            &self.connection,
-            &format!("ts-bridge restart failed: {message}"),
-            lsp_types::MessageType::ERROR,
-        )?;
-        Ok(())
     }
 
+    /// A newer `geterr` for the same file makes the older ones pointless, so
+    /// they are cancelled and their results dropped.
     fn request_file_diagnostics(&mut self, file: &str) {
-        let spec = protocol::diagnostics::request_for_file(file);
-        if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-            log::warn!("failed to configure tsserver: {err}");
+        if self.pull_diagnostics {
+            return;
         }
-        match self
-            .project
-            .dispatch_request(spec.route, spec.payload, spec.priority)
-        {
+        for (server, seq) in self.diag_state.supersede(file) {
+            self.cancel_tsserver_request(PendingKey { server, seq });
+        }
+        let spec = match self.region_ranges(file) {
+            Some(ranges) => protocol::diagnostics::request_for_file_ranges(file, ranges),
+            None => protocol::diagnostics::request_for_file(file),
+        };
+        match self.dispatch(spec.route, spec.payload, spec.priority) {
             Ok(receipts) => {
                 for receipt in receipts {
                     self.diag_state
-                        .register_pending(receipt.server, receipt.seq);
+                        .register_pending(receipt.server, receipt.seq, file);
                 }
             }
             Err(err) => {
@@ -1841,6 +6370,150 @@ impl SessionState {
         }
     }
 
+    /// One `geterrForProject` per configured project among `open_files`, so
+    /// files that left a project get their diagnostics cleared. Files in
+    /// inferred projects are left to their own `geterr`.
+    fn request_project_diagnostics(&mut self, open_files: &[String]) {
+        if self.pull_diagnostics {
+            return;
+        }
+        let mut projects: BTreeMap<PathBuf, &str> = BTreeMap::new();
+        for file in open_files {
+            if let Some(config) = project_config_file(Path::new(file)) {
+                projects.entry(config).or_insert(file);
+            }
+        }
+        for (config, file) in projects {
+            let project = config.to_string_lossy();
+            let spec = protocol::diagnostics::request_for_project(file);
+            match self.dispatch(spec.route, spec.payload, spec.priority) {
+                Ok(receipts) => {
+                    for receipt in receipts {
+                        if let Some((server, seq)) = self.diag_state.register_project_round(
+                            receipt.server,
+                            receipt.seq,
+                            file,
+                            &project,
+                        ) {
+                            self.cancel_tsserver_request(PendingKey { server, seq });
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::warn!("failed to dispatch geterrForProject for {file}: {err}");
+                }
+            }
+        }
+    }
+
+    /// tsserver formats with the options stored for the file and ignores the
+    /// ones on the `format` request, so the client's `FormattingOptions`
+    /// merged with the user's settings go out through a file-scoped
+    /// `configure` first.
+    fn configure_file_formatting(&mut self, params: &Value) {
+        let Some(uri) = params.pointer("/textDocument/uri").and_then(Value::as_str) else {
+            return;
+        };
+        let file = uri_to_file_path(uri).unwrap_or_else(|| uri.to_string());
+        let scope = self.document_scope(uri);
+        let client_options = params
+            .get("options")
+            .cloned()
+            .and_then(|options| serde_json::from_value(options).ok());
+        let request = json!({
+            "command": "configure",
+            "arguments": file_format_configure_args(
+                &file,
+                self.config.plugin(),
+                scope,
+                client_options.as_ref(),
+            ),
+        });
+        if let Err(err) = self.dispatch(Route::Both, request, Priority::Const) {
+            log::warn!("failed to configure formatting for {file}: {err}");
+        }
+    }
+
+    /// Edits removing the document's trailing commas when
+    /// `format_options.trailing_commas` is `remove`. JSX files are left
+    /// alone, since their text may hold unmatched quotes.
+    fn trailing_comma_edits(&self, params: &Value) -> Option<Value> {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)?;
+        let settings = self.config.plugin();
+        let trailing_commas = self
+            .document_scope(uri)
+            .and_then(|scope| {
+                settings
+                    .scope(scope)
+                    .format_options
+                    .trailing_commas
+                    .as_deref()
+            })
+            .or(settings.format_options.trailing_commas.as_deref());
+        if trailing_commas != Some("remove") || uri.ends_with(".jsx") || uri.ends_with(".tsx") {
+            return None;
+        }
+        let text = self.documents.text(&lsp_types::Uri::from_str(uri).ok()?)?;
+        serde_json::to_value(formatting::trailing_comma_removals(text)).ok()
+    }
+
+    fn document_scope(&self, uri: &str) -> Option<LanguageScope> {
+        let language = lsp_types::Uri::from_str(uri)
+            .ok()
+            .and_then(|uri| self.documents.language_id(&uri).map(str::to_string));
+        let file = uri_to_file_path(uri).unwrap_or_else(|| uri.to_string());
+        LanguageScope::of(language.as_deref(), &file)
+    }
+
+    /// Sends the `javascript`/`typescript` scope settings for one open
+    /// document as a file-scoped `configure`. Skipped until a scope is
+    /// configured; once one was, files keep being configured so removing an
+    /// override resets them.
+    fn configure_file_scope(&mut self, uri: &str) {
+        if !self.config.plugin().has_scoped_settings() && !self.scoped_configure_sent {
+            return;
+        }
+        let Some(scope) = self.document_scope(uri) else {
+            return;
+        };
+        let file = uri_to_file_path(uri).unwrap_or_else(|| uri.to_string());
+        let request = json!({
+            "command": "configure",
+            "arguments": file_scope_configure_args(
+                &file,
+                &self.config,
+                scope,
+                self.interactive_inlay_hints,
+            ),
+        });
+        match self.dispatch(Route::Both, request, Priority::Const) {
+            Ok(_) => self.scoped_configure_sent = true,
+            Err(err) => log::warn!("failed to configure {file} for its language scope: {err}"),
+        }
+    }
+
+    fn configure_open_file_scopes(&mut self) {
+        for snapshot in self.documents.open_documents() {
+            if !self.unsynced_documents.contains(&snapshot.uri) {
+                self.configure_file_scope(&snapshot.uri);
+            }
+        }
+    }
+
+    /// Checks the `languages` overrides for the document a request targets.
+    fn language_feature_enabled(&self, params: &Value, feature: &str) -> bool {
+        let language = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .and_then(|uri| lsp_types::Uri::from_str(uri).ok())
+            .and_then(|uri| self.documents.language_id(&uri).map(str::to_string));
+        self.config
+            .plugin()
+            .language_feature_enabled(language.as_deref(), feature)
+    }
+
     fn reopen_documents(&mut self) -> anyhow::Result<()> {
         let open_documents = self.documents.open_documents();
         for snapshot in open_documents {
@@ -1850,6 +6523,17 @@ impl SessionState {
     }
 
     fn reopen_document(&mut self, snapshot: OpenDocumentSnapshot) -> anyhow::Result<()> {
+        if self.unsynced_documents.contains(&snapshot.uri) {
+            return Ok(());
+        }
+        let event = lsp_types::Uri::from_str(&snapshot.uri)
+            .ok()
+            .map(|uri| DocumentEvent::Open {
+                uri,
+                text: snapshot.text.clone(),
+                version: snapshot.version,
+                language_id: snapshot.language_id.clone(),
+            });
         let params = crate::types::DidOpenTextDocumentParams {
             text_document: crate::types::TextDocumentItem {
                 uri: snapshot.uri.clone(),
@@ -1859,16 +6543,11 @@ impl SessionState {
             },
         };
         let spec = crate::protocol::text_document::did_open::handle(params, &self.workspace_root);
-        if let Err(err) = self.tsserver_configure.ensure(&self.config, &self.project) {
-            log::warn!("failed to configure tsserver: {err}");
-        }
-        if let Err(err) = self
-            .project
-            .dispatch_request(spec.route, spec.payload, spec.priority)
-        {
+        if let Err(err) = self.send_document(event, spec.route, spec.payload, spec.priority) {
             log::warn!("failed to dispatch reopened didOpen: {err}");
             return Ok(());
         }
+        self.configure_file_scope(&snapshot.uri);
         let file_for_diagnostics = uri_to_file_path(snapshot.uri.as_str()).unwrap_or(snapshot.uri);
         self.request_file_diagnostics(&file_for_diagnostics);
         Ok(())
@@ -1929,6 +6608,23 @@ fn show_message(
     Ok(())
 }
 
+fn log_message(
+    connection: &Connection,
+    message: &str,
+    kind: lsp_types::MessageType,
+) -> anyhow::Result<()> {
+    let params = lsp_types::LogMessageParams {
+        typ: kind,
+        message: message.to_string(),
+    };
+    let notif = ServerNotification::new(
+        lsp_types::notification::LogMessage::METHOD.to_string(),
+        serde_json::to_value(params)?,
+    );
+    connection.sender.send(Message::Notification(notif))?;
+    Ok(())
+}
+
 fn clear_client_diagnostics(connection: &Connection, uri_str: String) -> anyhow::Result<()> {
     let uri =
         lsp_types::Uri::from_str(&uri_str).context("invalid URI while clearing diagnostics")?;
@@ -1957,9 +6653,32 @@ fn publish_diagnostics(
 #[derive(Default)]
 struct PendingRequests {
     entries: HashMap<PendingKey, PendingEntry>,
+    /// `request_timeout_ms`; applied to every tsserver request as it is tracked.
+    timeout: Option<Duration>,
 }
 
 impl PendingRequests {
+    fn new(settings: &PluginSettings) -> Self {
+        let mut pending = Self::default();
+        pending.set_timeout(settings);
+        pending
+    }
+
+    /// Client requests still waiting on tsserver.
+    fn request_count(&self) -> usize {
+        self.entries
+            .values()
+            .map(|entry| &entry.id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn set_timeout(&mut self, settings: &PluginSettings) {
+        self.timeout = settings
+            .request_timeout_ms
+            .map(|ms| Duration::from_millis(u64::from(ms)));
+    }
+
     fn track(
         &mut self,
         receipts: &[DispatchReceipt],
@@ -1967,7 +6686,10 @@ impl PendingRequests {
         adapter: ResponseAdapter,
         context: Option<Value>,
         postprocess: Option<PostProcess>,
+        origin: Option<DocumentVersion>,
     ) {
+        log::debug!("request {}", dispatch_trace(&id, receipts));
+        let dispatched = Instant::now();
         for receipt in receipts {
             self.entries.insert(
                 PendingKey {
@@ -1976,20 +6698,57 @@ impl PendingRequests {
                 },
                 PendingEntry {
                     id: id.clone(),
+                    slot: None,
                     adapter,
                     context: context.clone(),
                     postprocess: postprocess.clone(),
+                    origin: origin.clone(),
+                    deadline: self.timeout.map(|timeout| dispatched + timeout),
+                    dispatched,
+                    span: telemetry::Span::default(),
+                    parent: telemetry::Span::default(),
                 },
             );
         }
     }
 
+    /// Records that the requests just tracked went to package project `slot`.
+    fn assign_slot(&mut self, receipts: &[DispatchReceipt], slot: Option<usize>) {
+        for receipt in receipts {
+            let key = PendingKey {
+                server: receipt.server,
+                seq: receipt.seq,
+            };
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.slot = slot;
+            }
+        }
+    }
+
+    /// Opens a tsserver span under `parent` for each request just tracked.
+    fn trace(&mut self, receipts: &[DispatchReceipt], parent: &telemetry::Span) {
+        for receipt in receipts {
+            let key = PendingKey {
+                server: receipt.server,
+                seq: receipt.seq,
+            };
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.span = telemetry::Span::start("tsserver", Some(parent), entry.dispatched);
+                entry.span.set_str("tsserver.request", &receipt.to_string());
+                entry.span.set_str("lsp.request_id", &entry.id.to_string());
+                entry.parent = parent.clone();
+            }
+        }
+    }
+
     fn resolve(
         &mut self,
         server: ServerKind,
         payload: &Value,
         inlay_cache: &mut InlayHintCache,
+        completion_cache: &mut CompletionCache,
         project: &ProjectHandle,
+        connection: &Connection,
     ) -> anyhow::Result<Option<Response>> {
         if payload
             .get("type")
@@ -2017,54 +6776,65 @@ impl PendingRequests {
             .get("success")
             .and_then(|value| value.as_bool())
             .unwrap_or(false);
+        log::debug!(
+            "request {} resolved by {} after {:.1}ms{}",
+            entry.id,
+            DispatchReceipt {
+                server,
+                seq: request_seq,
+            },
+            entry.dispatched.elapsed().as_secs_f64() * 1000.0,
+            if success { "" } else { " (failed)" }
+        );
+        entry.span.set_bool("tsserver.success", success);
+        entry.span.end();
+
+        if matches!(entry.postprocess, Some(PostProcess::ApplyEdit)) {
+            if !success {
+                log::debug!("server-initiated tsserver request failed: {payload}");
+                return Ok(None);
+            }
+            match (entry.adapter)(payload, entry.context.as_ref()) {
+                Ok(AdapterResult::Ready(params)) if !params.is_null() => {
+                    let request = Request::new(
+                        next_request_id(),
+                        <ApplyWorkspaceEdit as LspRequest>::METHOD.to_string(),
+                        params,
+                    );
+                    connection.sender.send(Message::Request(request))?;
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("failed to adapt tsserver response: {err:?}"),
+            }
+            return Ok(None);
+        }
 
         if success {
             match (entry.adapter)(payload, entry.context.as_ref()) {
                 Ok(AdapterResult::Ready(result)) => {
-                    if let Some(postprocess) = entry.postprocess {
-                        postprocess.apply(&result, inlay_cache)?;
-                    }
+                    let result = match entry.postprocess {
+                        Some(PostProcess::CollectReports { collector }) => collector.finish(result),
+                        Some(postprocess) => {
+                            postprocess.apply(&result, inlay_cache, completion_cache)?;
+                            result
+                        }
+                        None => result,
+                    };
                     Ok(Some(Response::new_ok(entry.id, result)))
                 }
                 Ok(AdapterResult::Continue(next_spec)) => {
-                    let request_id = entry.id;
-                    let postprocess = entry.postprocess;
-                    let Some(adapter) = next_spec.on_response else {
-                        return Ok(Some(Response::new_err(
-                            request_id,
-                            ErrorCode::InternalError as i32,
-                            "handler missing response adapter".to_string(),
-                        )));
-                    };
-                    match project.dispatch_request(
-                        next_spec.route,
-                        next_spec.payload,
-                        next_spec.priority,
-                    ) {
-                        Ok(receipts) => {
-                            if receipts.is_empty() {
-                                Ok(Some(Response::new_err(
-                                    request_id,
-                                    ErrorCode::InternalError as i32,
-                                    "tsserver route produced no requests".to_string(),
-                                )))
-                            } else {
-                                self.track(
-                                    &receipts,
-                                    request_id,
-                                    adapter,
-                                    next_spec.response_context,
-                                    postprocess,
-                                );
-                                Ok(None)
-                            }
+                    Ok(self.continue_chain(entry, next_spec, project))
+                }
+                Ok(AdapterResult::Partial { value, next }) => {
+                    let mut entry = entry;
+                    match &mut entry.postprocess {
+                        Some(PostProcess::PartialResults { token }) => {
+                            send_partial_result(connection, token, value)?;
                         }
-                        Err(err) => Ok(Some(Response::new_err(
-                            request_id,
-                            ErrorCode::InternalError as i32,
-                            format!("failed to dispatch tsserver request: {err}"),
-                        ))),
+                        Some(PostProcess::CollectReports { collector }) => collector.push(value),
+                        _ => log::warn!("dropping partial result without a partialResultToken"),
                     }
+                    Ok(self.continue_chain(entry, next, project))
                 }
                 Err(err) => Ok(Some(Response::new_err(
                     entry.id,
@@ -2085,10 +6855,137 @@ impl PendingRequests {
         }
     }
 
+    /// Sends the next request of `entry`'s chain to the same project.
+    fn continue_chain(
+        &mut self,
+        entry: PendingEntry,
+        next_spec: protocol::RequestSpec,
+        project: &ProjectHandle,
+    ) -> Option<Response> {
+        let Some(adapter) = next_spec.on_response else {
+            return Some(Response::new_err(
+                entry.id,
+                ErrorCode::InternalError as i32,
+                "handler missing response adapter".to_string(),
+            ));
+        };
+        match project.dispatch_request(next_spec.route, next_spec.payload, next_spec.priority) {
+            Ok(receipts) => {
+                if receipts.is_empty() {
+                    Some(Response::new_err(
+                        entry.id,
+                        ErrorCode::InternalError as i32,
+                        "tsserver route produced no requests".to_string(),
+                    ))
+                } else {
+                    self.track(
+                        &receipts,
+                        entry.id,
+                        adapter,
+                        next_spec.response_context,
+                        entry.postprocess,
+                        entry.origin,
+                    );
+                    self.assign_slot(&receipts, entry.slot);
+                    self.trace(&receipts, &entry.parent);
+                    None
+                }
+            }
+            Err(err) => Some(Response::new_err(
+                entry.id,
+                ErrorCode::InternalError as i32,
+                format!("failed to dispatch tsserver request: {err}"),
+            )),
+        }
+    }
+
+    /// Answers a response with `ContentModified` when the document it was
+    /// computed for has been edited since the request was made; the result
+    /// would point at positions that no longer exist.
+    fn reject_stale(
+        &mut self,
+        server: ServerKind,
+        payload: &Value,
+        documents: &DocumentStore,
+    ) -> Option<Response> {
+        if payload.get("type").and_then(|kind| kind.as_str()) != Some("response") {
+            return None;
+        }
+        let key = PendingKey {
+            server,
+            seq: payload.get("request_seq")?.as_u64()?,
+        };
+        let origin = self.entries.get(&key)?.origin.as_ref()?;
+        if origin.is_current(documents) {
+            return None;
+        }
+        let entry = self.entries.remove(&key)?;
+        Some(Response::new_err(
+            entry.id,
+            ErrorCode::ContentModified as i32,
+            "document changed before the response arrived".to_string(),
+        ))
+    }
+
+    /// Removes every request whose deadline passed by `now`.  Returns one
+    /// `RequestFailed` response per LSP request plus the tsserver requests to
+    /// cancel; sibling requests for the same id (`Route::Both`) go with it.
+    fn expire(&mut self, now: Instant) -> (Vec<Response>, Vec<PendingKey>) {
+        let expired: HashSet<RequestId> = self
+            .entries
+            .values()
+            .filter(|entry| entry.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|entry| entry.id.clone())
+            .collect();
+        if expired.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut keys = Vec::new();
+        let mut responses = Vec::new();
+        let mut answered = HashSet::new();
+        self.entries.retain(|key, entry| {
+            if !expired.contains(&entry.id) {
+                return true;
+            }
+            keys.push(*key);
+            let server_initiated = matches!(entry.postprocess, Some(PostProcess::ApplyEdit));
+            if !server_initiated && answered.insert(entry.id.clone()) {
+                responses.push(Response::new_err(
+                    entry.id.clone(),
+                    ErrorCode::RequestFailed as i32,
+                    "tsserver did not respond in time".to_string(),
+                ));
+            }
+            false
+        });
+        (responses, keys)
+    }
+
+    /// Stops tracking every tsserver request made on behalf of `id` and returns
+    /// their keys so they can be cancelled.
+    fn cancel(&mut self, id: &RequestId) -> Vec<PendingKey> {
+        let keys: Vec<PendingKey> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                &entry.id == id && !matches!(entry.postprocess, Some(PostProcess::ApplyEdit))
+            })
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &keys {
+            self.entries.remove(key);
+        }
+        keys
+    }
+
     fn fail_all(&mut self, message: &str) -> Vec<Response> {
         let mut responses = Vec::new();
         let mut seen = HashSet::new();
         for entry in self.entries.values() {
+            if matches!(entry.postprocess, Some(PostProcess::ApplyEdit)) {
+                continue;
+            }
             if seen.insert(entry.id.clone()) {
                 responses.push(Response::new_err(
                     entry.id.clone(),
@@ -2100,24 +6997,246 @@ impl PendingRequests {
         self.entries.clear();
         responses
     }
+
+    /// Drops the requests dispatched to `server`, failing the client requests
+    /// no other server is still answering.
+    fn fail_server(&mut self, server: ServerKind, message: &str) -> Vec<Response> {
+        let mut failed = Vec::new();
+        self.entries.retain(|key, entry| {
+            if key.server != server {
+                return true;
+            }
+            if !matches!(entry.postprocess, Some(PostProcess::ApplyEdit)) {
+                failed.push(entry.id.clone());
+            }
+            false
+        });
+        let mut seen = HashSet::new();
+        failed
+            .into_iter()
+            .filter(|id| !self.entries.values().any(|entry| &entry.id == id))
+            .filter(|id| seen.insert(id.clone()))
+            .map(|id| Response::new_err(id, ErrorCode::InternalError as i32, message.to_string()))
+            .collect()
+    }
+
+    /// Drops the requests sent to package project `slot`, failing the client
+    /// requests among them.
+    fn fail_slot(&mut self, slot: Option<usize>, message: &str) -> Vec<Response> {
+        let mut failed = Vec::new();
+        self.entries.retain(|_, entry| {
+            if entry.slot != slot {
+                return true;
+            }
+            if !matches!(entry.postprocess, Some(PostProcess::ApplyEdit)) {
+                failed.push(entry.id.clone());
+            }
+            false
+        });
+        let mut seen = HashSet::new();
+        failed
+            .into_iter()
+            .filter(|id| seen.insert(id.clone()))
+            .map(|id| Response::new_err(id, ErrorCode::InternalError as i32, message.to_string()))
+            .collect()
+    }
+}
+
+/// Per-method latency of client requests answered through tsserver, from
+/// receipt to the reply, with the share spent waiting on tsserver.
+#[derive(Default)]
+struct RequestTimings {
+    started: HashMap<RequestId, StartedRequest>,
+    methods: BTreeMap<String, LatencyStats>,
+}
+
+struct StartedRequest {
+    method: String,
+    received: Instant,
+    span: telemetry::Span,
+}
+
+#[derive(Default)]
+struct LatencyStats {
+    count: u64,
+    total: Duration,
+    tsserver: Duration,
+    max: Duration,
+}
+
+struct RequestTiming {
+    method: String,
+    total: Duration,
+    tsserver: Duration,
+}
+
+impl RequestTimings {
+    fn start(&mut self, id: RequestId, method: &str, received: Instant) {
+        let span = telemetry::Span::start(method, None, received);
+        span.set_str("rpc.method", method);
+        span.set_str("lsp.request_id", &id.to_string());
+        self.started.insert(
+            id,
+            StartedRequest {
+                method: method.to_string(),
+                received,
+                span,
+            },
+        );
+    }
+
+    /// The span of the in-flight request `id`, to parent its tsserver spans.
+    fn span(&self, id: &RequestId) -> telemetry::Span {
+        self.started
+            .get(id)
+            .map(|started| started.span.clone())
+            .unwrap_or_default()
+    }
+
+    /// Records the reply to `id`: the tsserver response completing it arrived
+    /// at `responded` and the reply went out at `replied`.
+    fn finish(
+        &mut self,
+        id: &RequestId,
+        responded: Instant,
+        replied: Instant,
+    ) -> Option<RequestTiming> {
+        let StartedRequest {
+            method,
+            received,
+            span,
+        } = self.started.remove(id)?;
+        span.end();
+        let total = replied.saturating_duration_since(received);
+        let tsserver = responded.saturating_duration_since(received).min(total);
+        let stats = self.methods.entry(method.clone()).or_default();
+        stats.count += 1;
+        stats.total += total;
+        stats.tsserver += tsserver;
+        stats.max = stats.max.max(total);
+        Some(RequestTiming {
+            method,
+            total,
+            tsserver,
+        })
+    }
+
+    /// Forgets a request answered without a tsserver result (cancelled,
+    /// timed out, stale), so failures do not skew the figures.
+    fn discard(&mut self, id: &RequestId) {
+        if let Some(started) = self.started.remove(id) {
+            started.span.fail("answered without a tsserver result");
+            started.span.end();
+        }
+    }
+
+    fn clear_started(&mut self) {
+        self.started.clear();
+    }
+
+    /// `{ method: { count, mean_ms, tsserver_mean_ms, max_ms } }`.
+    fn snapshot(&self) -> Value {
+        let millis = |duration: Duration| (duration.as_secs_f64() * 10_000.0).round() / 10.0;
+        let methods: Map<String, Value> = self
+            .methods
+            .iter()
+            .map(|(method, stats)| {
+                let count = stats.count.max(1) as u32;
+                (
+                    method.clone(),
+                    json!({
+                        "count": stats.count,
+                        "mean_ms": millis(stats.total / count),
+                        "tsserver_mean_ms": millis(stats.tsserver / count),
+                        "max_ms": millis(stats.max),
+                    }),
+                )
+            })
+            .collect();
+        Value::Object(methods)
+    }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 struct PendingKey {
     server: ServerKind,
     seq: u64,
 }
 
+/// `12 -> syntax#40, semantic#41`: the LSP request id and every tsserver
+/// `seq` it was sent as, so one request can be followed through both logs.
+fn dispatch_trace(id: &RequestId, receipts: &[DispatchReceipt]) -> String {
+    let receipts: Vec<String> = receipts.iter().map(ToString::to_string).collect();
+    format!("{id} -> {}", receipts.join(", "))
+}
+
 struct PendingEntry {
     id: RequestId,
+    /// Package project the request went to; `None` for the workspace one.
+    slot: Option<usize>,
     adapter: ResponseAdapter,
     context: Option<Value>,
     postprocess: Option<PostProcess>,
+    origin: Option<DocumentVersion>,
+    deadline: Option<Instant>,
+    dispatched: Instant,
+    /// Covers the wait on tsserver, under the client request's span.
+    span: telemetry::Span,
+    parent: telemetry::Span,
+}
+
+/// Document version a position-sensitive request was made against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DocumentVersion {
+    uri: lsp_types::Uri,
+    version: Option<i32>,
+}
+
+impl DocumentVersion {
+    /// Requests whose results are only meaningful for the exact text they were
+    /// computed on.
+    const METHODS: &[&str] = &[
+        <lsp_types::request::Completion as LspRequest>::METHOD,
+        <lsp_types::request::HoverRequest as LspRequest>::METHOD,
+        <lsp_types::request::SignatureHelpRequest as LspRequest>::METHOD,
+        crate::protocol::text_document::hover::EXPAND_METHOD,
+    ];
+
+    fn for_request(method: &str, params: &Value, documents: &DocumentStore) -> Option<Self> {
+        if !Self::METHODS.contains(&method) {
+            return None;
+        }
+        let uri = params.pointer("/textDocument/uri")?.as_str()?;
+        let uri = lsp_types::Uri::from_str(uri).ok()?;
+        let version = documents.version(&uri);
+        Some(Self { uri, version })
+    }
+
+    fn is_current(&self, documents: &DocumentStore) -> bool {
+        documents.version(&self.uri) == self.version
+    }
 }
 
 #[derive(Clone)]
 enum PostProcess {
-    InlayHints { key: HintCacheKey },
+    InlayHints {
+        key: HintCacheKey,
+    },
+    /// Carries the client's `partialResultToken` through chained requests.
+    PartialResults {
+        token: ProgressToken,
+    },
+    /// Gathers the partial results of a walk the client did not stream.
+    CollectReports {
+        collector: ReportCollector,
+    },
+    Completion {
+        key: CompletionCacheKey,
+        position: lsp_types::Position,
+    },
+    /// Server-initiated request: the adapted value is sent to the client as
+    /// `workspace/applyEdit` params and nobody waits for a response.
+    ApplyEdit,
 }
 
 impl PostProcess {
@@ -2127,63 +7246,489 @@ impl PostProcess {
         }
     }
 
-    fn apply(self, value: &Value, cache: &mut InlayHintCache) -> anyhow::Result<()> {
+    fn apply(
+        self,
+        value: &Value,
+        cache: &mut InlayHintCache,
+        completion_cache: &mut CompletionCache,
+    ) -> anyhow::Result<()> {
         match self {
             PostProcess::InlayHints { key } => {
                 let hints: Vec<lsp_types::InlayHint> = serde_json::from_value(value.clone())
                     .context("failed to decode inlay hint response payload")?;
                 cache.store(key, hints);
             }
+            PostProcess::PartialResults { .. }
+            | PostProcess::CollectReports { .. }
+            | PostProcess::ApplyEdit => {}
+            PostProcess::Completion { key, position } => {
+                let response: lsp_types::CompletionResponse = serde_json::from_value(value.clone())
+                    .context("failed to decode completion response payload")?;
+                if let lsp_types::CompletionResponse::List(list) = response {
+                    completion_cache.store(key, position, list);
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Last completion list per document. It stays valid while edits only touch
+/// the word it was computed for, so fast typing is served without asking
+/// tsserver again.
+#[derive(Default)]
+struct CompletionCache {
+    entries: HashMap<String, CachedCompletion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompletionCacheKey {
+    uri: String,
+    line: u32,
+    word_start: u32,
+}
+
+struct CachedCompletion {
+    key: CompletionCacheKey,
+    position: lsp_types::Position,
+    list: lsp_types::CompletionList,
+}
+
+impl CompletionCache {
+    fn lookup(
+        &self,
+        key: &CompletionCacheKey,
+        position: lsp_types::Position,
+    ) -> Option<lsp_types::CompletionList> {
+        let cached = self.entries.get(&key.uri)?;
+        if cached.key != *key {
+            return None;
+        }
+        Some(crate::protocol::text_document::completion::reuse_cached(
+            &cached.list,
+            cached.position,
+            position,
+        ))
+    }
+
+    fn store(
+        &mut self,
+        key: CompletionCacheKey,
+        position: lsp_types::Position,
+        list: lsp_types::CompletionList,
+    ) {
+        // tsserver flags lists it truncated; those must be re-queried.
+        if list.is_incomplete {
+            self.entries.remove(&key.uri);
+            return;
+        }
+        self.entries.insert(
+            key.uri.clone(),
+            CachedCompletion {
+                key,
+                position,
+                list,
+            },
+        );
+    }
+
+    /// Keeps the entry only when every change stays inside the cached word's
+    /// line, at or after the word start, without adding line breaks.
+    fn note_changes(
+        &mut self,
+        uri: &lsp_types::Uri,
+        changes: &[crate::types::TextDocumentContentChangeEvent],
+    ) {
+        let Some(cached) = self.entries.get(uri.as_str()) else {
+            return;
+        };
+        let within_word = changes.iter().all(|change| {
+            change.range.as_ref().is_some_and(|range| {
+                range.start.line == cached.key.line
+                    && range.end.line == cached.key.line
+                    && range.start.character >= cached.key.word_start
+            }) && !change.text.contains(['\n', '\r'])
+        });
+        if !within_word {
+            self.entries.remove(uri.as_str());
+        }
+    }
+
+    fn invalidate(&mut self, uri: &lsp_types::Uri) {
+        self.entries.remove(uri.as_str());
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
 #[derive(Default)]
 struct InlayHintCache {
     entries: HashMap<HintCacheKey, Vec<lsp_types::InlayHint>>,
 }
 
+/// The package project `file` was last routed to (`None` for the workspace
+/// project), from the routing caches alone.
+fn cached_slot(packages: &PackageProjects, file: &str) -> Option<usize> {
+    let dir = Path::new(file).parent()?;
+    let root = packages.roots.get(dir)?.as_ref()?;
+    packages.slots.get(root).copied().flatten()
+}
+
+/// Monorepo packages whose nearest tsconfig resolves to a different
+/// TypeScript than the workspace; each gets its own project.
 #[derive(Default)]
+struct PackageProjects {
+    projects: Vec<PackageProject>,
+    /// Package root -> index into `projects` (`None` = workspace project).
+    slots: HashMap<PathBuf, Option<usize>>,
+    /// Directory -> its package root, so routing does not stat every
+    /// ancestor per request. Cleared whenever `reloadProjects` is sent,
+    /// i.e. when a tsconfig/jsconfig may have appeared or gone.
+    roots: HashMap<PathBuf, Option<PathBuf>>,
+    /// tsserver the workspace project resolves to, computed on first use.
+    workspace_tsserver: Option<Option<PathBuf>>,
+}
+
+struct PackageProject {
+    key: ProjectKey,
+    handle: ProjectHandle,
+    events: Receiver<ProjectEvent>,
+    session_id: SessionId,
+    configure: TsserverConfigureState,
+    /// Shut down by the registry; registered again on the next message.
+    evicted: bool,
+}
+
+/// Feature name a `languages.<id>.<feature> = false` override uses for a
+/// document request.
+fn language_feature(method: &str) -> Option<&'static str> {
+    let feature = match method {
+        "textDocument/hover" => "hover",
+        "textDocument/completion" => "completion",
+        "textDocument/signatureHelp" => "signature_help",
+        "textDocument/definition" => "definition",
+        "textDocument/declaration" => "declaration",
+        "textDocument/typeDefinition" => "type_definition",
+        "textDocument/implementation" => "implementation",
+        "textDocument/references" => "references",
+        "textDocument/documentHighlight" => "document_highlight",
+        "textDocument/documentSymbol" => "document_symbol",
+        "textDocument/codeAction" => "code_action",
+        "textDocument/codeLens" => "code_lens",
+        "textDocument/formatting"
+        | "textDocument/rangeFormatting"
+        | "textDocument/onTypeFormatting" => "formatting",
+        "textDocument/rename" | "textDocument/prepareRename" => "rename",
+        "textDocument/foldingRange" => "folding_range",
+        "textDocument/selectionRange" => "selection_range",
+        "textDocument/semanticTokens/full"
+        | "textDocument/semanticTokens/full/delta"
+        | "textDocument/semanticTokens/range" => "semantic_tokens",
+        "textDocument/inlayHint" => "inlay_hints",
+        "textDocument/diagnostic" => "diagnostics",
+        _ => return None,
+    };
+    Some(feature)
+}
+
+/// Argument lists whose entries each name a file (a path, `{ file }` or
+/// `{ fileName }`), in the order `updateOpen` applies them.
+const FILE_LISTS: &[&str] = &["files", "openFiles", "changedFiles", "closedFiles"];
+
+/// The file a tsserver request is about, used to pick its project. Requests
+/// spanning several files go to the project of their primary one: the first
+/// entry of a [`FILE_LISTS`] list, the renamed file of
+/// `getEditsForFileRename` and the scoped file of `getCombinedCodeFix`.
+fn payload_file(payload: &Value) -> Option<&str> {
+    let args = payload.get("arguments")?;
+    single_payload_file(args).or_else(|| {
+        FILE_LISTS
+            .iter()
+            .find_map(|key| list_entry_file(args.get(*key)?.get(0)?))
+    })
+}
+
+fn single_payload_file(args: &Value) -> Option<&str> {
+    args.get("file")
+        .and_then(Value::as_str)
+        .or_else(|| args.get("oldFilePath")?.as_str())
+        .or_else(|| args.get("scope")?.get("args")?.get("file")?.as_str())
+}
+
+fn list_entry_file(entry: &Value) -> Option<&str> {
+    entry
+        .as_str()
+        .or_else(|| entry.get("file")?.as_str())
+        .or_else(|| entry.get("fileName")?.as_str())
+}
+
+/// Splits a payload by the project (`slot_of`) owning each file it names.
+/// Payloads about one file go whole to its project; [`FILE_LISTS`] entries
+/// are regrouped so every project receives only its own.
+fn split_payload(
+    mut payload: Value,
+    mut slot_of: impl FnMut(&str) -> Option<usize>,
+) -> Vec<(Option<usize>, Value)> {
+    let Some(args) = payload.get_mut("arguments") else {
+        return vec![(None, payload)];
+    };
+    if let Some(file) = single_payload_file(args) {
+        let slot = slot_of(file);
+        return vec![(slot, payload)];
+    }
+    let mut lists = Vec::new();
+    for key in FILE_LISTS {
+        if let Some(Value::Array(entries)) = args.get_mut(*key) {
+            lists.push((*key, std::mem::take(entries)));
+        }
+    }
+    let mut groups: Vec<(Option<usize>, Value)> = Vec::new();
+    for (key, entries) in lists {
+        for entry in entries {
+            let slot = list_entry_file(&entry).and_then(&mut slot_of);
+            let index = match groups.iter().position(|(group, _)| *group == slot) {
+                Some(index) => index,
+                None => {
+                    groups.push((slot, payload.clone()));
+                    groups.len() - 1
+                }
+            };
+            if let Some(Value::Array(list)) = groups[index].1["arguments"].get_mut(key) {
+                list.push(entry);
+            }
+        }
+    }
+    groups
+}
+
 struct TsserverConfigureState {
     last_args: Option<Map<String, Value>>,
+    interactive_inlay_hints: bool,
+    /// Set once a file outside any tsconfig/jsconfig has been opened; inferred
+    /// project options are only sent from then on.
+    inferred_files: bool,
+    last_inferred_options: Option<Map<String, Value>>,
+    last_plugin_configs: Option<Vec<(String, Value)>>,
 }
 
 impl TsserverConfigureState {
+    fn new(interactive_inlay_hints: bool) -> Self {
+        Self {
+            last_args: None,
+            interactive_inlay_hints,
+            inferred_files: false,
+            last_inferred_options: None,
+            last_plugin_configs: None,
+        }
+    }
+
     fn ensure(&mut self, config: &Config, project: &ProjectHandle) -> anyhow::Result<()> {
-        let args = tsserver_configure_args(config);
-        if self.last_args.as_ref() == Some(&args) {
+        let args = tsserver_configure_args(config, self.interactive_inlay_hints);
+        if self.last_args.as_ref() != Some(&args) {
+            let request = json!({
+                "command": "configure",
+                "arguments": args,
+            });
+            let _ = project
+                .dispatch_request(Route::Both, request, Priority::Const)
+                .context("failed to dispatch tsserver configure request")?;
+            self.last_args = Some(args);
+        }
+        self.ensure_plugin_configs(config, project)?;
+        self.ensure_inferred_options(config, project)
+    }
+
+    fn ensure_plugin_configs(
+        &mut self,
+        config: &Config,
+        project: &ProjectHandle,
+    ) -> anyhow::Result<()> {
+        let configs: Vec<(String, Value)> = config
+            .plugin()
+            .tsserver
+            .plugins
+            .iter()
+            .filter_map(|plugin| Some((plugin.name.clone(), plugin.config.clone()?)))
+            .collect();
+        if self.last_plugin_configs.as_ref() == Some(&configs) {
+            return Ok(());
+        }
+        for (name, configuration) in &configs {
+            let request = json!({
+                "command": "configurePlugin",
+                "arguments": { "pluginName": name, "configuration": configuration },
+            });
+            let _ = project
+                .dispatch_request(Route::Both, request, Priority::Const)
+                .with_context(|| format!("failed to dispatch configurePlugin for {name}"))?;
+        }
+        self.last_plugin_configs = Some(configs);
+        Ok(())
+    }
+
+    fn ensure_inferred_options(
+        &mut self,
+        config: &Config,
+        project: &ProjectHandle,
+    ) -> anyhow::Result<()> {
+        if !self.inferred_files {
+            return Ok(());
+        }
+        let options = config.plugin().inferred_project_options.compiler_options();
+        if self.last_inferred_options.as_ref() == Some(&options) {
             return Ok(());
         }
         let request = json!({
-            "command": "configure",
-            "arguments": args,
+            "command": "compilerOptionsForInferredProjects",
+            "arguments": { "options": options },
         });
         let _ = project
             .dispatch_request(Route::Both, request, Priority::Const)
-            .context("failed to dispatch tsserver configure request")?;
-        self.last_args = Some(args);
+            .context("failed to dispatch compilerOptionsForInferredProjects")?;
+        self.last_inferred_options = Some(options);
         Ok(())
     }
 
+    /// Records that an inferred-project file was opened and sends the
+    /// inferred project options if this is the first one.
+    fn note_inferred_file(
+        &mut self,
+        config: &Config,
+        project: &ProjectHandle,
+    ) -> anyhow::Result<()> {
+        self.inferred_files = true;
+        self.ensure_inferred_options(config, project)
+    }
+
     fn invalidate(&mut self) {
         self.last_args = None;
+        self.last_inferred_options = None;
+        self.last_plugin_configs = None;
+    }
+}
+
+/// The nearest tsconfig.json/jsconfig.json above `file`, which configures
+/// the project tsserver puts it in.
+fn project_config_file(file: &Path) -> Option<PathBuf> {
+    file.ancestors().skip(1).find_map(|dir| {
+        ["tsconfig.json", "jsconfig.json"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Whether tsserver will put `file` in an inferred project, i.e. no
+/// tsconfig.json/jsconfig.json sits in any directory above it.
+fn is_inferred_project_file(file: &Path) -> bool {
+    file.is_absolute() && project_config_file(file).is_none()
+}
+
+/// Per-file `configure` arguments for formatting: `tsserver.format_options`,
+/// then the client's options, then `format_options` and the scope's
+/// `format_options`, later ones winning. File-scoped settings replace
+/// tsserver's global ones, hence the merge.
+fn file_format_configure_args(
+    file: &str,
+    settings: &PluginSettings,
+    scope: Option<LanguageScope>,
+    client_options: Option<&lsp_types::FormattingOptions>,
+) -> Value {
+    let mut format_options = settings.tsserver_format_options.clone();
+    if let Some(Value::Object(client)) = client_options.map(format_code_settings) {
+        format_options.extend(client);
+    }
+    format_options.extend(settings.format_options.tsserver_options());
+    if let Some(scope) = scope {
+        format_options.extend(settings.scope(scope).format_options.tsserver_options());
     }
+
+    let mut args = json!({
+        "file": file,
+        "formatOptions": format_options,
+    });
+    let quote_style = scope
+        .and_then(|scope| settings.scope(scope).format_options.quote_style.as_ref())
+        .or(settings.format_options.quote_style.as_ref());
+    if let Some(quote_style) = quote_style {
+        args["preferences"] = json!({ "quotePreference": quote_style });
+    }
+    args
+}
+
+/// Per-file `configure` arguments for a document's language scope: the
+/// global preferences and format options with the scope's layered on top.
+/// tsserver merges file preferences over the global ones, but file format
+/// options replace them, so both are sent in full.
+fn file_scope_configure_args(
+    file: &str,
+    config: &Config,
+    scope: LanguageScope,
+    interactive_inlay_hints: bool,
+) -> Value {
+    let settings = config.plugin();
+    let scoped = settings.scope(scope);
+    let mut preferences =
+        match tsserver_configure_args(config, interactive_inlay_hints).remove("preferences") {
+            Some(Value::Object(preferences)) => preferences,
+            _ => Map::new(),
+        };
+    preferences.extend(scoped.preferences.tsserver_preferences());
+    let inlay_hints_enabled = settings.inlay_hints_enabled(Some(scope));
+    if let Value::Object(inlay) = crate::protocol::text_document::inlay_hint::preferences(
+        inlay_hints_enabled,
+        interactive_inlay_hints,
+    ) {
+        preferences.extend(inlay);
+    }
+    if inlay_hints_enabled {
+        preferences.extend(scoped.inlay_hints.clone());
+    }
+
+    let mut format_options = settings.tsserver_format_options.clone();
+    format_options.extend(settings.format_options.tsserver_options());
+    format_options.extend(scoped.format_options.tsserver_options());
+
+    json!({
+        "file": file,
+        "preferences": preferences,
+        "formatOptions": format_options,
+    })
 }
 
-fn tsserver_configure_args(config: &Config) -> Map<String, Value> {
+/// `ts.ScriptKind.Deferred`: the file's content is handled by a plugin.
+const SCRIPT_KIND_DEFERRED: u32 = 7;
+
+pub(crate) fn tsserver_configure_args(
+    config: &Config,
+    interactive_inlay_hints: bool,
+) -> Map<String, Value> {
     let mut args = Map::new();
 
     // Merge user preferences with the inlay hint gate so `enable_inlay_hints`
     // always wins for inlay-specific keys.
-    let mut preferences = config.plugin().tsserver_preferences.clone();
-    let inlay_preferences =
-        crate::protocol::text_document::inlay_hint::preferences(config.plugin().enable_inlay_hints);
+    let mut preferences = config.plugin().preferences.tsserver_preferences();
+    preferences.extend(config.plugin().tsserver_preferences.clone());
+    let inlay_preferences = crate::protocol::text_document::inlay_hint::preferences(
+        config.plugin().enable_inlay_hints,
+        interactive_inlay_hints,
+    );
     if let Some(map) = inlay_preferences.as_object() {
         for (key, value) in map {
             preferences.insert(key.clone(), value.clone());
         }
     }
+    if config.plugin().complete_function_calls {
+        preferences.insert(
+            "includeCompletionsWithSnippetText".to_string(),
+            Value::Bool(true),
+        );
+    }
+    if let Some(length) = config.plugin().maximum_hover_length {
+        preferences.insert("maximumHoverLength".to_string(), Value::from(length));
+    }
     args.insert("preferences".to_string(), Value::Object(preferences));
 
     if !config.plugin().tsserver_format_options.is_empty() {
@@ -2193,6 +7738,30 @@ fn tsserver_configure_args(config: &Config) -> Map<String, Value> {
         );
     }
 
+    // Documents of plugin languages (e.g. `.vue`) are opened without a script
+    // kind; tsserver defers them to the plugin via these extensions.
+    let plugin_languages = config.plugin().tsserver.plugin_languages();
+    if !plugin_languages.is_empty() {
+        let extensions: Vec<Value> = plugin_languages
+            .iter()
+            .map(|language| {
+                json!({
+                    "extension": format!(".{language}"),
+                    "isMixedContent": false,
+                    "scriptKind": SCRIPT_KIND_DEFERRED,
+                })
+            })
+            .collect();
+        args.insert("extraFileExtensions".to_string(), Value::Array(extensions));
+    }
+
+    if !config.plugin().tsserver.watch_options.is_empty() {
+        args.insert(
+            "watchOptions".to_string(),
+            Value::Object(config.plugin().tsserver.watch_options.clone()),
+        );
+    }
+
     args
 }
 
@@ -2253,34 +7822,153 @@ impl RangeFingerprint {
 #[derive(Default)]
 struct DiagnosticsState {
     pending: HashMap<(ServerKind, u64), PendingDiagnosticsEntry>,
+    /// Outstanding `geterr` requests per file.
+    in_flight: HashMap<String, Vec<(ServerKind, u64)>>,
     order: HashMap<ServerKind, VecDeque<u64>>,
     latest: HashMap<lsp_types::Uri, FileDiagnostics>,
     ready: VecDeque<(lsp_types::Uri, Vec<lsp_types::Diagnostic>)>,
     workload: Workload,
+    /// `diagnostics.include_suggestions` is off; `suggestionDiag` events are
+    /// neither waited for nor published.
+    skip_suggestions: bool,
+    /// What the client was last sent, for URIs where that was non-empty.
+    /// Survives `clear`, since the client keeps showing them.
+    published: HashMap<lsp_types::Uri, Vec<lsp_types::Diagnostic>>,
+    /// The outstanding `geterrForProject` per server and project config.
+    project_rounds: HashMap<(ServerKind, String), u64>,
+    /// URIs each server's last completed `geterrForProject` reported on, by
+    /// project config.
+    reported: HashMap<(ServerKind, String), HashSet<lsp_types::Uri>>,
 }
 
 impl DiagnosticsState {
-    fn register_pending(&mut self, server: ServerKind, seq: u64) {
+    /// Applies `diagnostics.include_suggestions`. Turning suggestions off
+    /// republishes files that were showing some without them.
+    fn set_include_suggestions(&mut self, include: bool) {
+        if self.skip_suggestions != include {
+            return;
+        }
+        self.skip_suggestions = !include;
+        if include {
+            return;
+        }
+        let mut emptied = Vec::new();
+        for (uri, latest) in &mut self.latest {
+            if latest.suggestion.is_empty() {
+                continue;
+            }
+            latest.suggestion.clear();
+            let combined = latest.collect();
+            if combined.is_empty() {
+                emptied.push(uri.clone());
+            }
+            self.ready.push_back((uri.clone(), combined));
+        }
+        for uri in emptied {
+            self.latest.remove(&uri);
+        }
+    }
+
+    fn register_pending(&mut self, server: ServerKind, seq: u64, file: &str) {
+        self.order.entry(server).or_default().push_back(seq);
+        let entry = PendingDiagnosticsEntry::new(server, file, !self.skip_suggestions);
+        self.workload.add_expected(entry.progress.expected_count());
+        self.pending.insert((server, seq), entry);
+        self.in_flight
+            .entry(file.to_string())
+            .or_default()
+            .push((server, seq));
+    }
+
+    /// Registers a `geterrForProject` for the project configured by
+    /// `project`. An older round for the same project is superseded and
+    /// returned for cancellation.
+    fn register_project_round(
+        &mut self,
+        server: ServerKind,
+        seq: u64,
+        file: &str,
+        project: &str,
+    ) -> Option<(ServerKind, u64)> {
         self.order.entry(server).or_default().push_back(seq);
-        let entry = PendingDiagnosticsEntry::new(server);
+        let mut entry = PendingDiagnosticsEntry::new(server, file, !self.skip_suggestions);
+        entry.project = Some(project.to_string());
         self.workload.add_expected(entry.progress.expected_count());
         self.pending.insert((server, seq), entry);
+        let previous = self
+            .project_rounds
+            .insert((server, project.to_string()), seq)?;
+        let entry = self.pending.get_mut(&(server, previous))?;
+        entry.superseded = true;
+        Some((server, previous))
+    }
+
+    /// Marks the outstanding requests for `file` as superseded, so what they
+    /// still report is dropped, and returns them for cancellation.
+    fn supersede(&mut self, file: &str) -> Vec<(ServerKind, u64)> {
+        let keys = self.in_flight.remove(file).unwrap_or_default();
+        for key in &keys {
+            if let Some(entry) = self.pending.get_mut(key) {
+                entry.superseded = true;
+            }
+        }
+        keys
+    }
+
+    /// The request a diagnostics event belongs to; events without a
+    /// `request_seq` belong to the oldest outstanding one.
+    fn event_key(&self, server: ServerKind, request_seq: Option<u64>) -> Option<(ServerKind, u64)> {
+        request_seq.map(|seq| (server, seq)).or_else(|| {
+            self.order
+                .get(&server)
+                .and_then(|queue| queue.front().copied())
+                .map(|seq| (server, seq))
+        })
     }
 
     fn handle_event(&mut self, server: ServerKind, event: DiagnosticsEvent) {
         match event {
+            DiagnosticsEvent::Region {
+                uri,
+                diagnostics,
+                request_seq,
+                ranges,
+            } => {
+                // Shown right away in place of the file's semantic
+                // diagnostics inside the checked ranges; the ones outside
+                // stay until the full `semanticDiag` replaces them all.
+                let entry = self
+                    .event_key(server, request_seq)
+                    .and_then(|key| self.pending.get(&key));
+                if entry.is_some_and(|entry| entry.superseded) {
+                    return;
+                }
+                let mut shown = self.latest.get(&uri).cloned().unwrap_or_default();
+                if let Some(reported) = entry.and_then(|entry| entry.files.get(&uri)) {
+                    shown.syntax = reported.syntax.clone();
+                }
+                if ranges.is_empty() {
+                    shown.semantic.clear();
+                } else {
+                    shown.semantic.retain(|diagnostic| {
+                        !ranges
+                            .iter()
+                            .any(|range| ranges_overlap(range, &diagnostic.range))
+                    });
+                }
+                shown.semantic.extend(diagnostics);
+                self.ready.push_back((uri, shown.collect()));
+            }
             DiagnosticsEvent::Report {
                 uri,
                 diagnostics,
                 request_seq,
                 kind,
             } => {
-                let key = request_seq.map(|seq| (server, seq)).or_else(|| {
-                    self.order
-                        .get(&server)
-                        .and_then(|queue| queue.front().copied())
-                        .map(|seq| (server, seq))
-                });
+                let key = self.event_key(server, request_seq);
+                if kind == DiagnosticsKind::Suggestion && self.skip_suggestions {
+                    return;
+                }
                 if let Some(key) = key {
                     if let Some(entry) = self.pending.get_mut(&key) {
                         entry
@@ -2310,6 +7998,30 @@ impl DiagnosticsState {
                             queue.remove(pos);
                         }
                     }
+                    if let Some(keys) = self.in_flight.get_mut(&entry.file) {
+                        keys.retain(|pending| *pending != key);
+                        if keys.is_empty() {
+                            self.in_flight.remove(&entry.file);
+                        }
+                    }
+                    entry
+                        .span
+                        .set_bool("diagnostics.superseded", entry.superseded);
+                    entry
+                        .span
+                        .set_int("diagnostics.files", entry.files.len() as i64);
+                    entry.span.end();
+                    if let Some(project) = &entry.project {
+                        let round = (server, project.clone());
+                        if self.project_rounds.get(&round) == Some(&request_seq) {
+                            self.project_rounds.remove(&round);
+                        }
+                    }
+                    if entry.superseded {
+                        entry.files.clear();
+                    } else if let Some(project) = entry.project.clone() {
+                        self.clear_dropped(server, project, &entry);
+                    }
                     for (uri, diags) in entry.files.into_iter() {
                         let combined = diags.collect();
                         if combined.is_empty() {
@@ -2328,8 +8040,75 @@ impl DiagnosticsState {
         }
     }
 
-    fn take_ready(&mut self) -> Option<(lsp_types::Uri, Vec<lsp_types::Diagnostic>)> {
-        self.ready.pop_front()
+    /// Clears URIs the previous `geterrForProject` for `project` reported on
+    /// but this one did not, which is how tsserver shows a file leaving the
+    /// project.
+    fn clear_dropped(
+        &mut self,
+        server: ServerKind,
+        project: String,
+        entry: &PendingDiagnosticsEntry,
+    ) {
+        let Some(previous) = self
+            .reported
+            .insert((server, project), entry.files.keys().cloned().collect())
+        else {
+            return;
+        };
+        for uri in previous
+            .iter()
+            .filter(|uri| !entry.files.contains_key(*uri))
+        {
+            let shown = self.latest.remove(uri).is_some();
+            if shown || self.published.contains_key(uri) {
+                self.ready.push_back((uri.clone(), Vec::new()));
+            }
+        }
+    }
+
+    /// Drains the ready sets, keeping the last one per URI and dropping
+    /// those the client already shows, and records the rest as published.
+    /// URIs `is_open` rejects are forgotten instead.
+    fn take_changed(
+        &mut self,
+        is_open: impl Fn(&lsp_types::Uri) -> bool,
+    ) -> Vec<(lsp_types::Uri, Vec<lsp_types::Diagnostic>)> {
+        let mut batch: Vec<(lsp_types::Uri, Vec<lsp_types::Diagnostic>)> = Vec::new();
+        for (uri, diagnostics) in self.ready.drain(..) {
+            match batch.iter_mut().find(|(queued, _)| *queued == uri) {
+                Some(queued) => queued.1 = diagnostics,
+                None => batch.push((uri, diagnostics)),
+            }
+        }
+        let mut changed = Vec::new();
+        for (uri, diagnostics) in batch {
+            if !is_open(&uri) {
+                self.clear_file(&uri);
+                continue;
+            }
+            let shown = self.published.get(&uri).map_or(&[][..], Vec::as_slice);
+            if shown == diagnostics.as_slice() {
+                continue;
+            }
+            if diagnostics.is_empty() {
+                self.published.remove(&uri);
+            } else {
+                self.published.insert(uri.clone(), diagnostics.clone());
+            }
+            changed.push((uri, diagnostics));
+        }
+        changed
+    }
+
+    /// Everything the client was last sent, sorted by URI.
+    fn published(&self) -> Vec<(lsp_types::Uri, Vec<lsp_types::Diagnostic>)> {
+        let mut published: Vec<_> = self
+            .published
+            .iter()
+            .map(|(uri, diagnostics)| (uri.clone(), diagnostics.clone()))
+            .collect();
+        published.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        published
     }
 
     fn progress_percent(&self) -> Option<u32> {
@@ -2355,31 +8134,101 @@ impl DiagnosticsState {
 
     fn clear(&mut self) {
         self.pending.clear();
+        self.in_flight.clear();
         self.order.clear();
         self.latest.clear();
         self.ready.clear();
+        self.project_rounds.clear();
+        self.reported.clear();
         self.workload.reset();
     }
 
+    /// Like [`Self::clear`], for the files (and project configs) `owned`
+    /// says belong to a project that went away.
+    fn clear_files(&mut self, mut owned: impl FnMut(&str) -> bool) {
+        let mut owned_uri =
+            |uri: &lsp_types::Uri| uri_to_file_path(uri.as_str()).is_some_and(|file| owned(&file));
+        self.latest.retain(|uri, _| !owned_uri(uri));
+        self.ready.retain(|(uri, _)| !owned_uri(uri));
+        let mut dropped = HashSet::new();
+        self.pending.retain(|key, entry| {
+            let keep = !owned(&entry.file);
+            if !keep {
+                dropped.insert(*key);
+            }
+            keep
+        });
+        self.in_flight.retain(|file, _| !owned(file));
+        for (server, order) in &mut self.order {
+            order.retain(|seq| !dropped.contains(&(*server, *seq)));
+        }
+        self.project_rounds
+            .retain(|(_, project), _| !owned(project));
+        self.reported.retain(|(_, project), _| !owned(project));
+        if self.pending.is_empty() {
+            self.workload.reset();
+        }
+    }
+
     fn clear_file(&mut self, uri: &lsp_types::Uri) {
         self.latest.remove(uri);
+        self.published.remove(uri);
         self.ready.retain(|(ready_uri, _)| ready_uri != uri);
         for entry in self.pending.values_mut() {
             entry.files.remove(uri);
         }
     }
+
+    /// Clears `removed` and, for folders, every file below it. Returns the
+    /// URIs that had diagnostics so the client can be told to drop them.
+    fn clear_affected(&mut self, removed: &str) -> Vec<lsp_types::Uri> {
+        let stale = self.affected(removed);
+        for uri in &stale {
+            self.clear_file(uri);
+        }
+        stale
+    }
+
+    /// URIs at or below `removed` that have, or were published with,
+    /// diagnostics.
+    fn affected(&self, removed: &str) -> Vec<lsp_types::Uri> {
+        let mut affected: Vec<lsp_types::Uri> = self
+            .latest
+            .keys()
+            .chain(self.published.keys())
+            .filter(|uri| file_operations::is_affected(uri.as_str(), removed))
+            .cloned()
+            .collect();
+        affected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        affected.dedup();
+        affected
+    }
 }
 
 struct PendingDiagnosticsEntry {
+    /// The file the `geterr` asked about.
+    file: String,
+    /// The project config of a `geterrForProject`; `None` for `geterr`.
+    project: Option<String>,
     files: HashMap<lsp_types::Uri, FileDiagnostics>,
     progress: StepProgress,
+    /// A newer request for `file` went out; results are discarded.
+    superseded: bool,
+    span: telemetry::Span,
 }
 
 impl PendingDiagnosticsEntry {
-    fn new(server: ServerKind) -> Self {
+    fn new(server: ServerKind, file: &str, include_suggestions: bool) -> Self {
+        let span = telemetry::Span::start("diagnostics", None, Instant::now());
+        span.set_str("tsserver.file", file);
+        span.set_str("tsserver.server", server.as_str());
         Self {
+            file: file.to_string(),
+            project: None,
             files: HashMap::new(),
-            progress: StepProgress::for_server(server),
+            progress: StepProgress::for_server(server, include_suggestions),
+            superseded: false,
+            span,
         }
     }
 }
@@ -2399,12 +8248,12 @@ struct StepProgress {
 }
 
 impl StepProgress {
-    fn for_server(server: ServerKind) -> Self {
+    fn for_server(server: ServerKind, include_suggestions: bool) -> Self {
         match server {
             ServerKind::Syntax => Self {
                 syntax: StepState::expected(true),
                 semantic: StepState::expected(false),
-                suggestion: StepState::expected(true),
+                suggestion: StepState::expected(include_suggestions),
             },
             ServerKind::Semantic => Self {
                 syntax: StepState::expected(false),
@@ -2499,6 +8348,90 @@ impl Workload {
     }
 }
 
+/// tsserver loading a configured project (`projectName` is the tsconfig path).
+#[derive(Debug, PartialEq, Eq)]
+enum ProjectLoadingEvent {
+    Start { project: String },
+    Finish { project: String },
+}
+
+impl ProjectLoadingEvent {
+    fn parse(payload: &Value) -> Option<Self> {
+        if payload.get("type")?.as_str()? != "event" {
+            return None;
+        }
+        let project = || {
+            payload
+                .pointer("/body/projectName")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        match payload.get("event")?.as_str()? {
+            "projectLoadingStart" => Some(Self::Start { project: project() }),
+            "projectLoadingFinish" => Some(Self::Finish { project: project() }),
+            _ => None,
+        }
+    }
+}
+
+/// Automatic typing acquisition events; tsserver only emits them when
+/// launched with `--enableTelemetry`.
+#[derive(Debug, PartialEq, Eq)]
+enum TypingsEvent {
+    Begin {
+        packages: Vec<String>,
+    },
+    End {
+        packages: Vec<String>,
+        success: bool,
+    },
+    InitializationFailed {
+        message: String,
+    },
+}
+
+impl TypingsEvent {
+    fn parse(payload: &Value) -> Option<Self> {
+        if payload.get("type")?.as_str()? != "event" {
+            return None;
+        }
+        let body = payload.get("body");
+        let packages = || {
+            body.and_then(|body| body.get("packages"))
+                .and_then(Value::as_array)
+                .map(|packages| {
+                    packages
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        match payload.get("event")?.as_str()? {
+            "beginInstallTypes" => Some(Self::Begin {
+                packages: packages(),
+            }),
+            "endInstallTypes" => Some(Self::End {
+                packages: packages(),
+                success: body
+                    .and_then(|body| body.get("success"))
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true),
+            }),
+            "typesInstallerInitializationFailed" => Some(Self::InitializationFailed {
+                message: body
+                    .and_then(|body| body.get("message"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
 struct LoadingProgress {
     token: ProgressToken,
     created: bool,
@@ -2507,7 +8440,11 @@ struct LoadingProgress {
 
 impl LoadingProgress {
     fn new(session_id: SessionId) -> Self {
-        let token = ProgressToken::String(format!("ts-bridge:{}:{session_id}", std::process::id()));
+        Self::with_prefix("ts-bridge", session_id)
+    }
+
+    fn with_prefix(prefix: &str, session_id: SessionId) -> Self {
+        let token = ProgressToken::String(format!("{prefix}:{}:{session_id}", std::process::id()));
         Self {
             token,
             created: false,
@@ -2672,6 +8609,21 @@ impl RestartProgress {
     }
 }
 
+/// `ProgressParamsValue` only models work-done payloads, so partial results
+/// are sent as a raw `$/progress` notification.
+fn send_partial_result(
+    connection: &Connection,
+    token: &ProgressToken,
+    value: Value,
+) -> anyhow::Result<()> {
+    let notif = ServerNotification::new(
+        Progress::METHOD.to_string(),
+        json!({ "token": token, "value": value }),
+    );
+    connection.sender.send(Message::Notification(notif))?;
+    Ok(())
+}
+
 fn send_progress(connection: &Connection, params: ProgressParams) -> anyhow::Result<()> {
     let notif =
         ServerNotification::new(Progress::METHOD.to_string(), serde_json::to_value(params)?);
@@ -2712,6 +8664,12 @@ fn friendly_project_name(root: &Path) -> String {
         .unwrap_or_else(|| root.display().to_string())
 }
 
+/// Whether a diagnostic at `diagnostic` falls in a `regionSemanticDiag` span;
+/// tsserver reports the ones that touch the span, so the ends count.
+fn ranges_overlap(span: &lsp_types::Range, diagnostic: &lsp_types::Range) -> bool {
+    diagnostic.start <= span.end && span.start <= diagnostic.end
+}
+
 fn stage_text(kind: DiagnosticsKind) -> &'static str {
     match kind {
         DiagnosticsKind::Syntax => "running syntax checks",
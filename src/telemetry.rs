@@ -0,0 +1,166 @@
+//! OpenTelemetry spans for client requests, the tsserver requests they are
+//! dispatched as, and diagnostics cycles. Built with the `otel` cargo feature
+//! and switched on at runtime by the standard OTLP variables
+//! (`OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`,
+//! plus `OTEL_EXPORTER_OTLP_HEADERS`, `OTEL_SERVICE_NAME`, ...). Spans are
+//! exported over OTLP/HTTP from a background thread. Without the feature, or
+//! without an endpoint, every [`Span`] is inert.
+
+pub use imp::{Span, init, shutdown};
+
+#[cfg(feature = "otel")]
+mod imp {
+    use std::sync::OnceLock;
+    use std::time::{Instant, SystemTime};
+
+    use opentelemetry::trace::{Status, TraceContextExt, Tracer};
+    use opentelemetry::{Context, KeyValue, global};
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+
+    const TRACER: &str = "ts-bridge";
+    const ENDPOINT_ENV: [&str; 2] = [
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+    ];
+
+    static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+    /// Installs the OTLP exporter when an endpoint is configured and
+    /// `OTEL_SDK_DISABLED` is not `true`; later calls do nothing.
+    pub fn init() {
+        if PROVIDER.get().is_some() || !configured() {
+            return;
+        }
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(err) => {
+                log::warn!("cannot start the OTLP span exporter: {err}");
+                return;
+            }
+        };
+        let mut resource = Resource::builder()
+            .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")));
+        if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+            resource = resource.with_service_name(TRACER);
+        }
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build();
+        global::set_tracer_provider(provider.clone());
+        if PROVIDER.set(provider).is_ok() {
+            log::info!("exporting OpenTelemetry spans over OTLP");
+        }
+    }
+
+    /// Flushes spans still buffered by the exporter.
+    pub fn shutdown() {
+        if let Some(provider) = PROVIDER.get()
+            && let Err(err) = provider.shutdown()
+        {
+            log::debug!("OpenTelemetry shutdown failed: {err}");
+        }
+    }
+
+    fn configured() -> bool {
+        let disabled = std::env::var("OTEL_SDK_DISABLED")
+            .is_ok_and(|value| value.eq_ignore_ascii_case("true"));
+        !disabled
+            && ENDPOINT_ENV
+                .iter()
+                .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+    }
+
+    /// A span, ended explicitly with [`Span::end`] or when the last clone is
+    /// dropped. Clones refer to the same span, so one can be kept as the
+    /// parent of later spans.
+    #[derive(Clone, Default)]
+    pub struct Span(Option<Context>);
+
+    impl Span {
+        /// Starts `name` as of `started`, under `parent` when it is recording.
+        pub fn start(name: &str, parent: Option<&Span>, started: Instant) -> Self {
+            if PROVIDER.get().is_none() {
+                return Self(None);
+            }
+            let parent = parent
+                .and_then(|parent| parent.0.clone())
+                .unwrap_or_default();
+            let start = SystemTime::now()
+                .checked_sub(started.elapsed())
+                .unwrap_or_else(SystemTime::now);
+            let tracer = global::tracer(TRACER);
+            let span = tracer
+                .span_builder(name.to_string())
+                .with_start_time(start)
+                .start_with_context(&tracer, &parent);
+            Self(Some(parent.with_span(span)))
+        }
+
+        pub fn set_str(&self, key: &'static str, value: &str) {
+            self.set(KeyValue::new(key, value.to_string()));
+        }
+
+        pub fn set_int(&self, key: &'static str, value: i64) {
+            self.set(KeyValue::new(key, value));
+        }
+
+        pub fn set_bool(&self, key: &'static str, value: bool) {
+            self.set(KeyValue::new(key, value));
+        }
+
+        /// Marks the span as failed with `message`.
+        pub fn fail(&self, message: &str) {
+            if let Some(context) = &self.0 {
+                context
+                    .span()
+                    .set_status(Status::error(message.to_string()));
+            }
+        }
+
+        pub fn end(&self) {
+            if let Some(context) = &self.0 {
+                context.span().end();
+            }
+        }
+
+        fn set(&self, attribute: KeyValue) {
+            if let Some(context) = &self.0 {
+                context.span().set_attribute(attribute);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+mod imp {
+    use std::time::Instant;
+
+    pub fn init() {}
+
+    pub fn shutdown() {}
+
+    /// Stand-in for the `otel` build's span; records nothing.
+    #[derive(Clone, Default)]
+    pub struct Span(());
+
+    impl Span {
+        pub fn start(_name: &str, _parent: Option<&Span>, _started: Instant) -> Self {
+            Self(())
+        }
+
+        pub fn set_str(&self, _key: &'static str, _value: &str) {}
+
+        pub fn set_int(&self, _key: &'static str, _value: i64) {}
+
+        pub fn set_bool(&self, _key: &'static str, _value: bool) {}
+
+        pub fn fail(&self, _message: &str) {}
+
+        pub fn end(&self) {}
+    }
+}
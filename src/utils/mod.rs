@@ -13,6 +13,7 @@ use lsp_types::{CompletionItemKind, Location, LocationLink, Uri};
 use serde_json::Value;
 use url::Url;
 
+use crate::config::CommitCharacterSettings;
 use crate::types::{Position, Range, TextDocumentContentChangeEvent, TextDocumentItem};
 
 /// Converts an LSP `Range` into the tsserver 1-based coordinate space.
@@ -93,12 +94,17 @@ pub fn lsp_text_doc_to_tsserver_entry(
     workspace_root: Option<&Path>,
 ) -> serde_json::Value {
     let file = uri_to_file_path(&doc.uri).unwrap_or_else(|| doc.uri.clone());
-    let script_kind = script_kind_from_language(doc.language_id.as_deref());
     let mut entry = serde_json::json!({
         "file": file,
         "fileContent": doc.text,
-        "scriptKindName": script_kind,
     });
+    // Other languages (e.g. `vue`) belong to a tsserver plugin, which picks
+    // the script kind from the extension.
+    if let Some(script_kind) = script_kind_from_language(doc.language_id.as_deref())
+        && let Some(obj) = entry.as_object_mut()
+    {
+        obj.insert("scriptKindName".to_string(), serde_json::json!(script_kind));
+    }
 
     if let Some(root) = workspace_root {
         if let Some(obj) = entry.as_object_mut() {
@@ -112,13 +118,14 @@ pub fn lsp_text_doc_to_tsserver_entry(
     entry
 }
 
-fn script_kind_from_language(lang: Option<&str>) -> &'static str {
+fn script_kind_from_language(lang: Option<&str>) -> Option<&'static str> {
     match lang {
-        Some("javascript") => "JS",
-        Some("javascriptreact") => "JSX",
-        Some("typescriptreact") => "TSX",
-        Some("json") => "JSON",
-        _ => "TS",
+        Some("javascript") => Some("JS"),
+        Some("javascriptreact") => Some("JSX"),
+        Some("typescriptreact") => Some("TSX"),
+        Some("json") => Some("JSON"),
+        Some("typescript") | None => Some("TS"),
+        Some(_) => None,
     }
 }
 
@@ -251,7 +258,45 @@ pub fn completion_item_kind_from_tsserver(kind: Option<&str>) -> CompletionItemK
     }
 }
 
-pub fn completion_commit_characters(kind: CompletionItemKind) -> Option<Vec<String>> {
+/// Commit characters for `kind`, honouring the user's toggle and per-kind
+/// overrides before falling back to the defaults below.
+pub fn completion_commit_characters(
+    kind: CompletionItemKind,
+    settings: &CommitCharacterSettings,
+) -> Option<Vec<String>> {
+    if !settings.enabled {
+        return None;
+    }
+    if let Some(chars) = completion_kind_name(kind).and_then(|name| settings.overrides.get(name)) {
+        return (!chars.is_empty()).then(|| chars.clone());
+    }
+    default_commit_characters(kind)
+}
+
+/// Settings key for a completion item kind (LSP names in snake case).
+fn completion_kind_name(kind: CompletionItemKind) -> Option<&'static str> {
+    Some(match kind {
+        CompletionItemKind::TEXT => "text",
+        CompletionItemKind::METHOD => "method",
+        CompletionItemKind::FUNCTION => "function",
+        CompletionItemKind::CONSTRUCTOR => "constructor",
+        CompletionItemKind::FIELD => "field",
+        CompletionItemKind::VARIABLE => "variable",
+        CompletionItemKind::CLASS => "class",
+        CompletionItemKind::INTERFACE => "interface",
+        CompletionItemKind::MODULE => "module",
+        CompletionItemKind::PROPERTY => "property",
+        CompletionItemKind::ENUM => "enum",
+        CompletionItemKind::KEYWORD => "keyword",
+        CompletionItemKind::FILE => "file",
+        CompletionItemKind::ENUM_MEMBER => "enum_member",
+        CompletionItemKind::CONSTANT => "constant",
+        CompletionItemKind::TYPE_PARAMETER => "type_parameter",
+        _ => return None,
+    })
+}
+
+fn default_commit_characters(kind: CompletionItemKind) -> Option<Vec<String>> {
     match kind {
         CompletionItemKind::CLASS => Some(vec![".".into(), ",".into(), "(".into()]),
         CompletionItemKind::CONSTANT => Some(vec![".".into(), "?".into()]),
@@ -287,6 +332,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn commit_characters_honour_settings() {
+        let mut settings = CommitCharacterSettings::default();
+        assert_eq!(
+            completion_commit_characters(CompletionItemKind::METHOD, &settings),
+            Some(vec!["(".to_string()])
+        );
+
+        settings
+            .overrides
+            .insert("method".into(), vec!["(".into(), ".".into()]);
+        settings.overrides.insert("class".into(), Vec::new());
+        assert_eq!(
+            completion_commit_characters(CompletionItemKind::METHOD, &settings),
+            Some(vec!["(".to_string(), ".".to_string()])
+        );
+        assert_eq!(
+            completion_commit_characters(CompletionItemKind::CLASS, &settings),
+            None
+        );
+
+        settings.enabled = false;
+        assert_eq!(
+            completion_commit_characters(CompletionItemKind::METHOD, &settings),
+            None
+        );
+    }
+
     #[test]
     fn lsp_range_to_tsserver_is_one_based() {
         let input = range(0, 0, 4, 15); // first line/column in LSP space
@@ -349,4 +422,16 @@ mod tests {
             json!(root.to_string_lossy().to_string())
         );
     }
+
+    #[test]
+    fn plugin_languages_are_opened_without_a_script_kind() {
+        let doc = TextDocumentItem {
+            uri: "file:///tmp/App.vue".to_string(),
+            language_id: Some("vue".to_string()),
+            version: 1,
+            text: "<script setup lang=\"ts\"></script>".to_string(),
+        };
+        let entry = lsp_text_doc_to_tsserver_entry(&doc, None);
+        assert!(entry.get("scriptKindName").is_none());
+    }
 }
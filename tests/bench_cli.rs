@@ -0,0 +1,71 @@
+//! `ts-bridge bench` times hover, completion and pull diagnostics against a
+//! project through an in-process session. A scripted tsserver answers.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use assert_cmd::Command;
+
+/// Answers every request: hover text, one completion, no diagnostics.
+const CASES: &str = r#"
+    case "quickinfo":
+      return reply(request, { kind: "const", kindModifiers: "", start: { line: 1, offset: 1 }, end: { line: 1, offset: 2 },
+        displayString: "const a: 1", documentation: "", tags: [] });
+    case "completionInfo":
+      return reply(request, { isGlobalCompletion: false, isMemberCompletion: false, isNewIdentifierLocation: false,
+        entries: [{ name: "twice", kind: "function", kindModifiers: "export", sortText: "11" }] });
+    case "syntacticDiagnosticsSync":
+    case "semanticDiagnosticsSync":
+    case "suggestionDiagnosticsSync":
+      return reply(request, []);
+"#;
+
+#[test]
+fn bench_reports_percentiles_per_request_type() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    common::install_tsserver(dir.path(), &common::scripted_tsserver("", CASES));
+    fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+    fs::write(dir.path().join("small.ts"), "export const a = 1;\n").unwrap();
+    fs::write(
+        dir.path().join("large.ts"),
+        "import { a } from \"./small\";\n\nexport function twice(value: number) {\n  return value * a;\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .arg("bench")
+        .arg(dir.path())
+        .args(["--iterations", "3"])
+        .env_remove("TS_BRIDGE_RECORD")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.starts_with("file: "), "{stdout}");
+    assert!(
+        stdout.lines().next().unwrap().ends_with("large.ts"),
+        "{stdout}"
+    );
+    for method in [
+        "textDocument/completion",
+        "textDocument/diagnostic",
+        "textDocument/hover",
+    ] {
+        assert!(
+            stdout
+                .lines()
+                .any(|line| line.starts_with(method) && line.contains("  3  ")),
+            "{method} should have 3 samples: {stdout}"
+        );
+    }
+    assert!(!stdout.contains("error response"), "{stdout}");
+}
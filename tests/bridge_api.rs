@@ -0,0 +1,88 @@
+//! `ts_bridge::Bridge` answers typed requests without an LSP client. A
+//! scripted tsserver keeps the open file's text and answers from it.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use lsp_types::{HoverContents, Position};
+use ts_bridge::Bridge;
+
+/// Applies `updateOpen` to its copy of each file; quick info shows the text
+/// of the file, references point at the first character of every line.
+const SETUP: &str = r#"
+const files = {};
+
+function offsetOf(text, { line, offset }) {
+  const lines = text.split("\n");
+  return lines.slice(0, line - 1).reduce((sum, line) => sum + line.length + 1, 0) + offset - 1;
+}
+"#;
+
+const CASES: &str = r#"
+    case "updateOpen":
+      for (const open of args.openFiles) files[open.file] = open.fileContent;
+      for (const changed of args.changedFiles) {
+        for (const change of changed.textChanges) {
+          const text = files[changed.fileName];
+          files[changed.fileName] = text.slice(0, offsetOf(text, change.start)) + change.newText + text.slice(offsetOf(text, change.end));
+        }
+      }
+      for (const closed of args.closedFiles) delete files[closed];
+      return reply(request, true);
+    case "quickinfo":
+      return reply(request, { kind: "const", kindModifiers: "", start: { line: args.line, offset: args.offset },
+        end: { line: args.line, offset: args.offset + 1 }, displayString: files[args.file], documentation: "", tags: [] });
+    case "references":
+      return reply(request, { refs: files[args.file].split("\n").map((_, index) => ({
+        file: args.file, start: { line: index + 1, offset: 1 }, end: { line: index + 1, offset: 2 } })) });
+"#;
+
+fn hover_text(bridge: &mut Bridge, file: &str) -> String {
+    let hover = bridge.hover(file, Position::new(0, 0)).unwrap().unwrap();
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("hover should be markdown");
+    };
+    markup.value
+}
+
+#[test]
+fn bridge_tracks_documents_and_answers_typed_requests() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    common::install_tsserver(dir.path(), &common::scripted_tsserver(SETUP, CASES));
+    fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+    fs::write(dir.path().join("main.ts"), "const a = 1;\n").unwrap();
+
+    let mut bridge = Bridge::start(dir.path()).unwrap();
+    bridge.open_from_disk("main.ts").unwrap();
+    assert_eq!(
+        hover_text(&mut bridge, "main.ts"),
+        "```typescript\nconst a = 1;\n\n```"
+    );
+
+    bridge
+        .change("main.ts", "const é = 1;\nconst b = é;")
+        .unwrap();
+    assert_eq!(
+        hover_text(&mut bridge, "main.ts"),
+        "```typescript\nconst é = 1;\nconst b = é;\n```"
+    );
+    let references = bridge
+        .references(bridge.root().join("main.ts"), Position::new(0, 6), true)
+        .unwrap();
+    assert_eq!(
+        references
+            .iter()
+            .map(|location| location.range.start.line)
+            .collect::<Vec<_>>(),
+        [0, 1]
+    );
+
+    bridge.close("main.ts").unwrap();
+    assert!(bridge.change("main.ts", "").is_err());
+    bridge.shutdown();
+}
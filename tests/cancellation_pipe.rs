@@ -0,0 +1,135 @@
+//! Exercises the cancellation pipe against a fake tsserver that polls for
+//! `seq_<seq>` the way tsserver does, and, when run with `--ignored`, against
+//! a real one. Point `TS_BRIDGE_TEST_TSSERVER` at a `tsserver.js` (or have
+//! `tsserver` on PATH) for the latter.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::process::{ServerKind, TsserverProcess};
+use ts_bridge::provider::{BinarySource, Provider, TsserverBinary};
+
+const FILES: usize = 8;
+
+/// The fake tsserver's pipe name and an `event` helper beside `reply`.
+const SETUP: &str = r#"
+const pipe = process.argv[process.argv.indexOf("--cancellationPipeName") + 1];
+function event(name, body) {
+  const json = JSON.stringify({ seq: 0, type: "event", event: name, body });
+  process.stdout.write(`Content-Length: ${Buffer.byteLength(json)}\r\n\r\n${json}`);
+}
+"#;
+
+/// Checks for `--cancellationPipeName` with the request's seq in place of
+/// its `*` before each file, as tsserver does between semantic checks.
+const GETERR: &str = r#"
+    case "geterr": {
+      for (const file of args.files) {
+        if (fs.existsSync(pipe.replace(/\*$/, String(request.seq)))) break;
+        event("semanticDiag", { file, diagnostics: [] });
+      }
+      return event("requestCompleted", { request_seq: request.seq });
+    }
+"#;
+
+fn locate_tsserver(workspace: &Path) -> Option<TsserverBinary> {
+    if let Some(path) = std::env::var_os("TS_BRIDGE_TEST_TSSERVER") {
+        return Some(TsserverBinary {
+            executable: PathBuf::from(path),
+            plugin_probe: None,
+            version: None,
+            source: BinarySource::GlobalPath,
+            node_args: Vec::new(),
+        });
+    }
+    Provider::new(workspace).resolve().ok()
+}
+
+#[test]
+fn cancelled_geterr_stops_before_semantic_checks() {
+    if !common::has_node() {
+        return;
+    }
+    let workspace = tempfile::tempdir().expect("workspace");
+    let binary = common::script_binary(workspace.path(), &common::scripted_tsserver(SETUP, GETERR));
+    check_cancelled_geterr(workspace.path(), binary);
+}
+
+#[test]
+#[ignore = "needs a real tsserver: set TS_BRIDGE_TEST_TSSERVER or put tsserver on PATH"]
+fn cancelled_geterr_stops_a_real_tsserver() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    let binary = locate_tsserver(workspace.path()).expect("no tsserver available");
+    check_cancelled_geterr(workspace.path(), binary);
+}
+
+fn check_cancelled_geterr(workspace: &Path, binary: TsserverBinary) {
+    let files: Vec<String> = (0..FILES)
+        .map(|index| {
+            let path = workspace.join(format!("file{index}.ts"));
+            let body: String = (0..200)
+                .map(|n| {
+                    format!("export function f{n}(value: number): string {{ return value; }}\n")
+                })
+                .collect();
+            fs::write(&path, body).expect("write source file");
+            path.to_string_lossy().into_owned()
+        })
+        .collect();
+
+    let mut server = TsserverProcess::new(
+        ServerKind::Semantic,
+        binary,
+        TsserverLaunchOptions::default(),
+    );
+    server.start().expect("start tsserver");
+    let rx = server.response_rx().expect("response channel");
+
+    let open_files: Vec<Value> = files.iter().map(|file| json!({ "file": file })).collect();
+    server
+        .write(&json!({
+            "seq": 0,
+            "type": "request",
+            "command": "updateOpen",
+            "arguments": { "openFiles": open_files, "changedFiles": [], "closedFiles": [] }
+        }))
+        .expect("open files");
+
+    // Cancelling before the request is even sent makes the outcome independent
+    // of how fast tsserver checks each file.
+    server.cancel(1).expect("touch cancellation file");
+    server
+        .write(&json!({
+            "seq": 1,
+            "type": "request",
+            "command": "geterr",
+            "arguments": { "files": files, "delay": 0 }
+        }))
+        .expect("send geterr");
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut semantic_events = 0;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let message = rx
+            .recv_timeout(remaining)
+            .expect("tsserver should finish the cancelled geterr");
+        match message.get("event").and_then(|event| event.as_str()) {
+            Some("semanticDiag") => semantic_events += 1,
+            Some("requestCompleted") if message.pointer("/body/request_seq") == Some(&json!(1)) => {
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(
+        semantic_events < FILES,
+        "cancelled geterr still checked every file ({semantic_events} semanticDiag events)"
+    );
+}
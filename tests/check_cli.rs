@@ -0,0 +1,105 @@
+//! `ts-bridge check` runs the workspace diagnostics walk against tsserver and
+//! fails on errors. A scripted tsserver answers the requests it sends.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use assert_cmd::Command;
+
+/// Reports TS2322 on the first line of every file that mentions `BROKEN`.
+const CASES: &str = r#"
+    case "projectInfo": {
+      const dir = path.dirname(args.file);
+      const fileNames = fs.readdirSync(dir).filter((name) => name.endsWith(".ts")).map((name) => path.join(dir, name));
+      return reply(request, { configFileName: path.join(dir, "tsconfig.json"), fileNames });
+    }
+    case "semanticDiagnosticsSync":
+      if (fs.readFileSync(args.file, "utf8").includes("BROKEN")) {
+        return reply(request, [{
+          start: { line: 1, offset: 7 }, end: { line: 1, offset: 13 },
+          text: "Type 'string' is not assignable to type 'number'.", category: "error", code: 2322,
+        }]);
+      }
+      return reply(request, []);
+    case "syntacticDiagnosticsSync":
+      return reply(request, []);
+"#;
+
+fn project(dir: &Path) {
+    common::install_tsserver(dir, &common::scripted_tsserver("", CASES));
+    fs::write(dir.join("tsconfig.json"), "{}").unwrap();
+    fs::write(dir.join("broken.ts"), "const BROKEN: number = \"1\";\n").unwrap();
+    fs::write(dir.join("clean.ts"), "export const ok = 1;\n").unwrap();
+}
+
+fn check(dir: &Path, args: &[&str]) -> Output {
+    Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .current_dir(dir)
+        .arg("check")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn project_errors_are_reported_and_fail_the_check() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = check(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "stdout: {stdout}");
+    assert!(
+        stdout.starts_with(
+            "broken.ts:1:7 - error TS2322: Type 'string' is not assignable to type 'number'.\n"
+        ),
+        "{stdout}"
+    );
+    assert!(
+        stdout.ends_with("Found 1 error(s) and 0 warning(s) in 1 of 2 file(s).\n"),
+        "{stdout}"
+    );
+
+    let output = check(dir.path(), &["--format", "json"]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries[0]["file"], "broken.ts");
+    assert_eq!(entries[0]["code"], 2322);
+    assert_eq!(entries[0]["endColumn"], 13);
+}
+
+#[test]
+fn globs_select_the_files_to_check() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = check(dir.path(), &["c*.ts"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No problems found in 1 file(s).\n"
+    );
+
+    let output = check(dir.path(), &["--format=github", "*.ts"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "::error file=broken.ts,line=1,col=7,endLine=1,endColumn=13,title=TS2322::Type 'string' is not assignable to type 'number'.\n"
+    );
+
+    let output = check(dir.path(), &["src/**/*.ts"]);
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "a check that cannot run is not a failed one"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("matched no files"));
+}
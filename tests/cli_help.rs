@@ -0,0 +1,73 @@
+//! Every command prints its usage for `--help` and `ts-bridge help COMMAND`,
+//! and the global flags are accepted after the command.
+
+use assert_cmd::Command;
+
+fn ts_bridge(args: &[&str]) -> std::process::Output {
+    Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .args(args)
+        .env_remove("TS_BRIDGE_DAEMON")
+        .env_remove("TS_BRIDGE_DAEMON_LISTEN")
+        .env_remove("TS_BRIDGE_DAEMON_SOCKET")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn help_flag_and_help_command_print_the_same_usage() {
+    for command in [
+        "daemon", "connect", "check", "codemod", "doctor", "replay", "bench",
+    ] {
+        let flag = ts_bridge(&[command, "--help"]);
+        assert!(flag.status.success(), "{command} --help failed");
+        let stdout = String::from_utf8_lossy(&flag.stdout);
+        assert!(
+            stdout.contains(&format!("Usage: ts-bridge {command}")),
+            "{command}: {stdout}"
+        );
+        assert!(stdout.contains("Global options:"), "{command}: {stdout}");
+        assert!(stdout.contains("--log-level <LEVEL>"));
+
+        let help = ts_bridge(&["help", command]);
+        assert!(help.status.success());
+        assert_eq!(help.stdout, flag.stdout);
+    }
+}
+
+#[test]
+fn unknown_flags_name_the_command_usage() {
+    let output = ts_bridge(&["check", "--bogus"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unexpected argument '--bogus'"), "{stderr}");
+    assert!(stderr.contains("Usage: ts-bridge check"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn a_transport_flag_replaces_the_one_from_the_environment() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("daemon.sock");
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .args(["daemon", "--pipe", "ts-bridge-test", "--socket"])
+        .arg(&socket)
+        .env("TS_BRIDGE_DAEMON_LISTEN", "127.0.0.1:1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+
+    // An unparsable variable shows the flag is used in its place.
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .args(["daemon", "--exit-when-idle", "1", "--socket"])
+        .arg(&socket)
+        .env("TS_BRIDGE_DAEMON_LISTEN", "not an address")
+        .env("XDG_RUNTIME_DIR", dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
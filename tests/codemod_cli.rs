@@ -0,0 +1,128 @@
+//! `ts-bridge codemod` runs the editor's organize-imports and file-rename
+//! commands against tsserver and writes the edits to disk. A scripted
+//! tsserver computes the edits.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use assert_cmd::Command;
+
+/// Sorts the import lines at the top of a file, and answers file renames by
+/// rewriting `"./<old name>"` specifiers next to the moved file.
+const CASES: &str = r#"
+    case "organizeImports": {
+      const file = args.scope.args.file;
+      const lines = fs.readFileSync(file, "utf8").split("\n");
+      const count = lines.findIndex((line) => !line.startsWith("import "));
+      const sorted = lines.slice(0, count).sort();
+      return reply(request, [{ fileName: file, textChanges: [{
+        start: { line: 1, offset: 1 }, end: { line: count + 1, offset: 1 }, newText: sorted.map((line) => line + "\n").join(""),
+      }] }]);
+    }
+    case "getEditsForFileRename": {
+      const dir = path.dirname(args.oldFilePath);
+      const from = `"./${path.basename(args.oldFilePath, ".ts")}"`;
+      const to = `"./${path.relative(dir, args.newFilePath).replace(/\.ts$/, "")}"`;
+      const edits = [];
+      for (const name of fs.readdirSync(dir).filter((name) => name.endsWith(".ts"))) {
+        const file = path.join(dir, name);
+        const textChanges = [];
+        fs.readFileSync(file, "utf8").split("\n").forEach((text, index) => {
+          const column = text.indexOf(from);
+          if (column >= 0) {
+            textChanges.push({ start: { line: index + 1, offset: column + 1 }, end: { line: index + 1, offset: column + 1 + from.length }, newText: to });
+          }
+        });
+        if (textChanges.length > 0) edits.push({ fileName: file, textChanges });
+      }
+      return reply(request, edits);
+    }
+"#;
+
+fn project(dir: &Path) {
+    common::install_tsserver(dir, &common::scripted_tsserver("", CASES));
+    fs::write(dir.join("tsconfig.json"), "{}").unwrap();
+    fs::write(
+        dir.join("main.ts"),
+        "import { b } from \"./b\";\nimport { a } from \"./a\";\n\nconsole.log(a, b);\n",
+    )
+    .unwrap();
+    fs::write(dir.join("a.ts"), "export const a = 1;\n").unwrap();
+    fs::write(dir.join("b.ts"), "export const b = 2;\n").unwrap();
+}
+
+fn codemod(dir: &Path, args: &[&str]) -> Output {
+    Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .current_dir(dir)
+        .arg("codemod")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn organize_imports_rewrites_the_matched_files() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = codemod(dir.path(), &["organize-imports", "*.ts"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "main.ts\nOrganized imports in 1 file(s).\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("main.ts")).unwrap(),
+        "import { a } from \"./a\";\nimport { b } from \"./b\";\n\nconsole.log(a, b);\n"
+    );
+
+    let output = codemod(dir.path(), &["organize-imports", "main.ts"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Organized imports in 0 file(s).\n"
+    );
+}
+
+#[test]
+fn rename_file_moves_the_file_and_its_importers() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    project(dir.path());
+
+    let output = codemod(dir.path(), &["rename-file", "a.ts", "lib/a.ts"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "main.ts\nRenamed a.ts to lib/a.ts; rewrote 1 file(s).\n"
+    );
+    assert!(!dir.path().join("a.ts").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/a.ts")).unwrap(),
+        "export const a = 1;\n"
+    );
+    assert!(
+        fs::read_to_string(dir.path().join("main.ts"))
+            .unwrap()
+            .contains("import { a } from \"./lib/a\";")
+    );
+
+    let output = codemod(dir.path(), &["rename-file", "b.ts", "main.ts"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+}
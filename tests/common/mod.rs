@@ -0,0 +1,110 @@
+//! Helpers shared by the integration tests: scripted stand-ins for
+//! `tsserver.js` and LSP framing for tests that talk to the bridge over a
+//! pipe or socket.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use ts_bridge::provider::{BinarySource, TsserverBinary};
+
+/// Whether `node` is installed. Tests that need it return early (and pass)
+/// when it is not.
+pub fn has_node() -> bool {
+    if which::which("node").is_ok() {
+        return true;
+    }
+    eprintln!("skipping: node is not installed");
+    false
+}
+
+/// A tsserver answering each request line with `reply(request, body)`.
+/// `setup` runs once before the first request; `cases` are the `case`
+/// clauses of a `switch (request.command)` that sees `request` and its
+/// `args`. `exit` stops the script and any other command gets an empty
+/// successful response.
+pub fn scripted_tsserver(setup: &str, cases: &str) -> String {
+    format!(
+        r#"
+const fs = require("fs");
+const path = require("path");
+const readline = require("readline");
+
+function reply(request, body) {{
+  const json = JSON.stringify({{ seq: 0, type: "response", request_seq: request.seq, command: request.command, success: true, body }});
+  process.stdout.write(`Content-Length: ${{Buffer.byteLength(json)}}\r\n\r\n${{json}}`);
+}}
+{setup}
+readline.createInterface({{ input: process.stdin }}).on("line", (line) => {{
+  const request = JSON.parse(line);
+  const args = request.arguments || {{}};
+  switch (request.command) {{
+{cases}
+    case "exit":
+      return process.exit(0);
+    default:
+      return reply(request, undefined);
+  }}
+}});
+"#
+    )
+}
+
+/// Installs `script` as the project's `node_modules/typescript/lib/tsserver.js`,
+/// where the provider looks first.
+pub fn install_tsserver(root: &Path, script: &str) {
+    let lib = root.join("node_modules/typescript/lib");
+    fs::create_dir_all(&lib).expect("create fake typescript");
+    fs::write(lib.join("tsserver.js"), script).expect("write fake tsserver");
+}
+
+/// A locally installed tsserver at `executable`, without version or plugin
+/// probe.
+pub fn tsserver_binary(executable: PathBuf) -> TsserverBinary {
+    TsserverBinary {
+        executable,
+        plugin_probe: None,
+        version: None,
+        source: BinarySource::LocalNodeModules,
+        node_args: Vec::new(),
+    }
+}
+
+/// Writes `script` to `dir/tsserver.js` and returns the binary running it.
+pub fn script_binary(dir: &Path, script: &str) -> TsserverBinary {
+    let executable = dir.join("tsserver.js");
+    fs::write(&executable, script).expect("write fake tsserver");
+    tsserver_binary(executable)
+}
+
+/// Writes one `Content-Length` framed LSP message.
+pub fn send(stream: &mut impl Write, message: Value) {
+    let body = message.to_string();
+    write!(stream, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    stream.flush().unwrap();
+}
+
+/// Reads one `Content-Length` framed LSP message.
+pub fn read(reader: &mut impl BufRead) -> Value {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        assert!(
+            reader.read_line(&mut line).unwrap() > 0,
+            "connection closed"
+        );
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length: ") {
+            length = value.parse().unwrap();
+        }
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
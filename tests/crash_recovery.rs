@@ -0,0 +1,90 @@
+//! Drives `Service::poll_health` with a stand-in `tsserver.js` that exits as
+//! soon as it starts, so every start looks like a crash.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use ts_bridge::config::{Config, PluginSettings};
+use ts_bridge::process::ServerKind;
+use ts_bridge::provider::Provider;
+use ts_bridge::rpc::{HealthEvent, Service};
+
+fn wait_for_event(service: &mut Service, timeout: Duration) -> HealthEvent {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(event) = service.poll_health().into_iter().next() {
+            return event;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "no health event within {timeout:?}"
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn exited_tsserver_is_restarted_with_backoff() {
+    if !common::has_node() {
+        return;
+    }
+    let workspace = tempfile::tempdir().expect("workspace");
+    common::install_tsserver(workspace.path(), "process.exit(3);\n");
+
+    let config = Config::new(PluginSettings {
+        separate_diagnostic_server: false,
+        ..PluginSettings::default()
+    });
+    let mut service = Service::new(config, Provider::new(workspace.path()));
+    service.start().expect("spawn fake tsserver");
+
+    let first_delay = match wait_for_event(&mut service, Duration::from_secs(10)) {
+        HealthEvent::Exited {
+            server, retry_in, ..
+        } => {
+            assert_eq!(server, ServerKind::Syntax);
+            retry_in.expect("first crash schedules a restart")
+        }
+        other => panic!("expected exit, got {other:?}"),
+    };
+
+    match wait_for_event(&mut service, first_delay + Duration::from_secs(10)) {
+        HealthEvent::Restarted { server } => assert_eq!(server, ServerKind::Syntax),
+        other => panic!("expected restart, got {other:?}"),
+    }
+
+    match wait_for_event(&mut service, Duration::from_secs(10)) {
+        HealthEvent::Exited { retry_in, .. } => {
+            assert!(retry_in.expect("second restart scheduled") > first_delay);
+        }
+        other => panic!("expected second exit, got {other:?}"),
+    }
+}
+
+#[test]
+fn crashed_semantic_server_is_not_reported_as_running() {
+    if !common::has_node() {
+        return;
+    }
+    let workspace = tempfile::tempdir().expect("workspace");
+    common::install_tsserver(
+        workspace.path(),
+        "if (process.env.TS_LSP_RS_SERVER_KIND === \"semantic\") process.exit(3);\nprocess.stdin.resume();\n",
+    );
+
+    let config = Config::new(PluginSettings {
+        separate_diagnostic_server: true,
+        ..PluginSettings::default()
+    });
+    let mut service = Service::new(config, Provider::new(workspace.path()));
+    assert!(service.semantic_server(), "start would launch one");
+    service.start().expect("spawn fake tsservers");
+
+    match wait_for_event(&mut service, Duration::from_secs(10)) {
+        HealthEvent::Exited { server, .. } => assert_eq!(server, ServerKind::Semantic),
+        other => panic!("expected exit, got {other:?}"),
+    }
+    assert!(!service.semantic_server());
+    assert!(!service.tsserver_status().semantic_server);
+}
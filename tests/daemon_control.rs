@@ -0,0 +1,695 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn ts_bridge(discovery: &Path) -> Command {
+    let mut command = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"));
+    command
+        .env("TS_BRIDGE_DISCOVERY_FILE", discovery)
+        .env_remove("TS_BRIDGE_DAEMON_SOCKET")
+        .env_remove("TS_BRIDGE_DAEMON_PIPE")
+        .env_remove("TS_BRIDGE_DAEMON_LISTEN");
+    command
+}
+
+fn wait_for_discovery(discovery: &Path) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !discovery.exists() {
+        assert!(Instant::now() < deadline, "daemon never advertised itself");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Reads messages until the notification `method` arrives.
+fn wait_for_notification(reader: &mut impl BufRead, method: &str) -> serde_json::Value {
+    loop {
+        let message = common::read(reader);
+        if message["method"] == method {
+            return message["params"].clone();
+        }
+    }
+}
+
+fn wait_for_exit(daemon: &mut Child) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(exit) = daemon.try_wait().unwrap() {
+            assert!(exit.success());
+            return;
+        }
+        assert!(Instant::now() < deadline, "daemon did not exit");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn status_and_stop_reach_a_running_daemon() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("run").join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+    let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    let advertised: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&discovery).unwrap()).unwrap();
+    assert_eq!(advertised["pid"], daemon.id());
+    assert_eq!(advertised["socket"], socket.to_string_lossy().as_ref());
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status", "--socket"])
+        .arg(&socket)
+        .output()
+        .unwrap();
+    assert!(status.status.success());
+    let stdout = String::from_utf8(status.stdout).unwrap();
+    assert!(stdout.contains(&format!("pid {}", daemon.id())), "{stdout}");
+    assert!(stdout.contains("no projects"), "{stdout}");
+
+    // `connect` finds the daemon through the discovery file and relays
+    // whatever the client sends.
+    let mut connect = ts_bridge(&discovery)
+        .arg("connect")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = connect.stdin.take().unwrap();
+    common::send(
+        &mut stdin,
+        serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "ts-bridge/daemon/status" }),
+    );
+    drop(stdin);
+    let relayed = String::from_utf8(connect.wait_with_output().unwrap().stdout).unwrap();
+    assert!(relayed.starts_with("Content-Length: "), "{relayed}");
+    assert!(
+        relayed.contains(&format!("\"pid\":{}", daemon.id())),
+        "{relayed}"
+    );
+
+    let stop = ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    assert!(stop.status.success());
+    assert!(String::from_utf8(stop.stdout).unwrap().contains("stopped"));
+
+    wait_for_exit(&mut daemon);
+    assert!(!socket.exists());
+    assert!(!discovery.exists());
+}
+
+#[test]
+fn sigterm_removes_the_socket_and_discovery_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--socket-mode", "0660", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+    let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+
+    let killed = Command::new("kill")
+        .args(["-TERM", &daemon.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    wait_for_exit(&mut daemon);
+    assert!(!socket.exists());
+    assert!(!discovery.exists());
+}
+
+#[test]
+fn silent_client_is_dropped_after_an_unanswered_heartbeat() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args([
+            "daemon",
+            "--resume-window",
+            "off",
+            "--client-timeout",
+            "1",
+            "--socket",
+        ])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+
+    let root = dir.path().join("workspace");
+    std::fs::create_dir(&root).unwrap();
+    let mut client = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    common::send(
+        &mut client,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "rootPath": root, "capabilities": {} },
+        }),
+    );
+    assert_eq!(common::read(&mut reader)["id"], 1);
+    // The editor hangs: the connection stays open but nothing answers.
+    let heartbeat = common::read(&mut reader);
+    assert_eq!(heartbeat["method"], "ts-bridge/heartbeat");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let status = ts_bridge(&discovery)
+            .args(["daemon", "status"])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(status.stdout).unwrap();
+        if stdout.contains("workspace") && stdout.contains(" 0 sessions") {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "session never went away: {stdout}"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(client);
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn reconnecting_client_resumes_its_session() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+
+    let root = dir.path().join("workspace");
+    // A tsserver that never answers keeps the test off the network.
+    common::install_tsserver(&root, "process.stdin.resume();\n");
+    let uri = format!("file://{}/main.ts", root.display());
+    let client = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    let mut writer = client;
+    common::send(
+        &mut writer,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "rootPath": root, "capabilities": {} },
+        }),
+    );
+    common::send(
+        &mut writer,
+        serde_json::json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    );
+    let ready = wait_for_notification(&mut reader, "ts-bridge/serverReady");
+    let token = ready["session_token"].as_str().unwrap().to_string();
+    common::send(
+        &mut writer,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": {
+                "uri": uri, "languageId": "typescript", "version": 3, "text": "let a = 1;\n",
+            } },
+        }),
+    );
+    // The network blips: the connection drops without shutdown/exit.
+    thread::sleep(Duration::from_millis(200));
+    drop(writer);
+    drop(reader);
+
+    let client = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    let mut writer = client;
+    common::send(
+        &mut writer,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "rootPath": root,
+                "capabilities": {},
+                "initializationOptions": { "resumeToken": token },
+            },
+        }),
+    );
+    common::send(
+        &mut writer,
+        serde_json::json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    );
+    let resumed = wait_for_notification(&mut reader, "ts-bridge/resume");
+    let documents = resumed["documents"].as_array().unwrap();
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0]["uri"], uri.as_str());
+    assert_eq!(documents[0]["version"], 3);
+    assert_eq!(documents[0]["text"], "let a = 1;\n");
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(status.stdout).unwrap();
+    assert!(stdout.contains(" 1 session"), "{stdout}");
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn resume_with_different_capabilities_starts_a_new_session() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+
+    let root = dir.path().join("workspace");
+    common::install_tsserver(&root, "process.stdin.resume();\n");
+    let initialize = |capabilities: serde_json::Value, options: serde_json::Value| {
+        let client = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut writer = client;
+        common::send(
+            &mut writer,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "rootPath": root,
+                    "capabilities": capabilities,
+                    "initializationOptions": options,
+                },
+            }),
+        );
+        common::send(
+            &mut writer,
+            serde_json::json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        );
+        let ready = wait_for_notification(&mut reader, "ts-bridge/serverReady");
+        let token = ready["session_token"].as_str().unwrap().to_string();
+        (token, reader, writer)
+    };
+
+    let (token, reader, writer) = initialize(serde_json::json!({}), serde_json::Value::Null);
+    assert_eq!(token.len(), 32);
+    thread::sleep(Duration::from_millis(200));
+    drop(writer);
+    drop(reader);
+
+    let snippets = serde_json::json!({
+        "textDocument": { "completion": { "completionItem": { "snippetSupport": true } } }
+    });
+    let (new_token, _reader, _writer) =
+        initialize(snippets, serde_json::json!({ "resumeToken": token }));
+    assert_ne!(new_token, token, "the old session was resumed");
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(status.stdout).unwrap();
+    assert!(stdout.contains(" 1 session"), "{stdout}");
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn evicted_project_is_reattached_on_the_next_request() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--max-projects", "1", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+
+    let tsserver = common::scripted_tsserver(
+        "",
+        r#"
+    case "quickinfo":
+      return reply(request, { kind: "let", kindModifiers: "", start: { line: 1, offset: 5 }, end: { line: 1, offset: 6 },
+        displayString: "let a: number", documentation: "", tags: [] });"#,
+    );
+    let attach = |name: &str| {
+        let root = dir.path().join(name);
+        common::install_tsserver(&root, &tsserver);
+        let client = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut writer = client;
+        common::send(
+            &mut writer,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": { "rootPath": root, "capabilities": {} },
+            }),
+        );
+        common::send(
+            &mut writer,
+            serde_json::json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        );
+        wait_for_notification(&mut reader, "ts-bridge/serverReady");
+        (root, reader, writer)
+    };
+
+    let (root_a, mut reader_a, mut writer_a) = attach("workspace-a");
+    let uri = format!("file://{}/main.ts", root_a.display());
+    common::send(
+        &mut writer_a,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": {
+                "uri": uri, "languageId": "typescript", "version": 1, "text": "let a = 1;\n",
+            } },
+        }),
+    );
+    // A second project pushes the first one out.
+    let (_root_b, _reader_b, _writer_b) = attach("workspace-b");
+    loop {
+        let message = wait_for_notification(&mut reader_a, "window/showMessage");
+        if message["message"]
+            .as_str()
+            .unwrap()
+            .contains("was shut down")
+        {
+            break;
+        }
+    }
+
+    common::send(
+        &mut writer_a,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/hover",
+            "params": { "textDocument": { "uri": uri }, "position": { "line": 0, "character": 4 } },
+        }),
+    );
+    let response = loop {
+        let message = common::read(&mut reader_a);
+        if message["id"] == 2 && message.get("method").is_none() {
+            break message;
+        }
+    };
+    assert!(response.get("error").is_none(), "{response}");
+    assert!(
+        response["result"]["contents"]
+            .to_string()
+            .contains("let a: number"),
+        "{response}"
+    );
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(status.stdout).unwrap();
+    let line = stdout
+        .lines()
+        .find(|line| line.contains("workspace-a"))
+        .unwrap_or_else(|| panic!("project not running again: {stdout}"));
+    assert!(line.contains(" 1 session"), "{stdout}");
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn warm_starts_a_project_before_any_editor_attaches() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+
+    let root = dir.path().join("workspace");
+    common::install_tsserver(&root, "process.stdin.resume();\n");
+    std::fs::write(root.join("tsconfig.json"), "{}").unwrap();
+
+    let warm = ts_bridge(&discovery)
+        .args(["daemon", "warm"])
+        .arg(&root)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(warm.status.success(), "{warm:?}");
+    let result: serde_json::Value = serde_json::from_slice(&warm.stdout).unwrap();
+    assert_eq!(result["already_running"], false);
+    assert!(
+        result["config_file"]
+            .as_str()
+            .unwrap()
+            .ends_with("tsconfig.json"),
+        "{result}"
+    );
+    assert!(result["tsserver"]["syntax_pid"].is_u64(), "{result}");
+
+    let again = ts_bridge(&discovery)
+        .args(["daemon", "warm"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(again.stdout).unwrap();
+    assert!(stdout.contains("already running"), "{stdout}");
+
+    let missing = ts_bridge(&discovery)
+        .args(["daemon", "warm"])
+        .arg(dir.path().join("nowhere"))
+        .output()
+        .unwrap();
+    assert!(!missing.status.success());
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status", "--json"])
+        .output()
+        .unwrap();
+    let status: serde_json::Value = serde_json::from_slice(&status.stdout).unwrap();
+    let projects = status["projects"].as_array().unwrap();
+    assert_eq!(projects.len(), 1, "{status}");
+    assert_eq!(projects[0]["session_count"], 0);
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn sighup_and_reload_apply_new_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    let settings = dir.path().join("daemon.toml");
+    std::fs::write(&settings, "idle_ttl = 600\n").unwrap();
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--max-projects", "4", "--config"])
+        .arg(&settings)
+        .arg("--socket")
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_for_discovery(&discovery);
+    let status = |discovery: &Path| -> serde_json::Value {
+        let output = ts_bridge(discovery)
+            .args(["daemon", "status", "--json"])
+            .output()
+            .unwrap();
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    assert_eq!(status(&discovery)["idle_ttl_seconds"], 600);
+
+    std::fs::write(&settings, "idle_ttl = 900\nlog_level = \"debug\"\n").unwrap();
+    let hup = Command::new("kill")
+        .args(["-HUP", &daemon.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(hup.success());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while status(&discovery)["idle_ttl_seconds"] != 900 {
+        assert!(Instant::now() < deadline, "SIGHUP did not reload settings");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(status(&discovery)["log_level"], "debug");
+
+    let reload = ts_bridge(&discovery)
+        .args([
+            "daemon",
+            "reload",
+            "--max-projects",
+            "2",
+            "--log-level=warn",
+        ])
+        .output()
+        .unwrap();
+    assert!(reload.status.success(), "{reload:?}");
+    let stdout = String::from_utf8(reload.stdout).unwrap();
+    assert!(
+        stdout.contains("idle TTL 900s, max projects 2, log level warn"),
+        "{stdout}"
+    );
+    let current = status(&discovery);
+    assert_eq!(current["max_projects"], 2);
+    assert_eq!(current["log_level"], "warn");
+
+    // A later SIGHUP re-reads the file but keeps what reload set.
+    std::fs::write(&settings, "idle_ttl = 1200\nlog_level = \"debug\"\n").unwrap();
+    let hup = Command::new("kill")
+        .args(["-HUP", &daemon.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(hup.success());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while status(&discovery)["idle_ttl_seconds"] != 1200 {
+        assert!(Instant::now() < deadline, "SIGHUP did not reload settings");
+        thread::sleep(Duration::from_millis(50));
+    }
+    let current = status(&discovery);
+    assert_eq!(current["max_projects"], 2);
+    assert_eq!(current["log_level"], "warn");
+
+    // A broken file leaves the settings as they were.
+    std::fs::write(&settings, "idle_ttl = \"soon\"\n").unwrap();
+    let refused = ts_bridge(&discovery)
+        .args(["daemon", "reload"])
+        .output()
+        .unwrap();
+    assert!(!refused.status.success());
+    assert_eq!(status(&discovery)["idle_ttl_seconds"], 1200);
+
+    ts_bridge(&discovery)
+        .args(["daemon", "stop"])
+        .output()
+        .unwrap();
+    wait_for_exit(&mut daemon);
+}
+
+#[test]
+fn socket_activated_daemon_serves_the_inherited_socket_and_exits_when_idle() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("ts-bridge.sock");
+    let discovery = dir.path().join("daemon.json");
+    // What a systemd socket unit would hold open for the service.
+    let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let fd = listener.as_raw_fd();
+
+    // `exec` keeps the shell's pid, so `LISTEN_PID` names the daemon.
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("LISTEN_PID=$$ exec \"$@\"")
+        .arg("sh")
+        .arg(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .args(["daemon", "--exit-when-idle", "1"])
+        .env("TS_BRIDGE_DISCOVERY_FILE", &discovery)
+        .env("LISTEN_FDS", "1")
+        .stderr(Stdio::null());
+    unsafe {
+        command.pre_exec(move || {
+            let moved = if fd == 3 {
+                libc::fcntl(3, libc::F_SETFD, 0)
+            } else {
+                libc::dup2(fd, 3)
+            };
+            if moved == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut daemon = command.spawn().unwrap();
+    wait_for_discovery(&discovery);
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status", "--json"])
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "{status:?}");
+    let status: serde_json::Value = serde_json::from_slice(&status.stdout).unwrap();
+    assert_eq!(status["pid"], daemon.id());
+    assert_eq!(status["endpoint"], socket.to_string_lossy().as_ref());
+
+    // Nothing to do: the daemon hands the socket back to systemd and exits.
+    wait_for_exit(&mut daemon);
+    assert!(socket.exists());
+    assert!(!discovery.exists());
+    drop(listener);
+}
@@ -0,0 +1,85 @@
+#![cfg(windows)]
+
+mod common;
+
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ts_bridge::pipe::PipeStream;
+
+fn ts_bridge(discovery: &Path) -> Command {
+    let mut command = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"));
+    command
+        .env("TS_BRIDGE_DISCOVERY_FILE", discovery)
+        .env_remove("TS_BRIDGE_DAEMON_SOCKET")
+        .env_remove("TS_BRIDGE_DAEMON_PIPE")
+        .env_remove("TS_BRIDGE_DAEMON_LISTEN");
+    command
+}
+
+#[test]
+fn messages_round_trip_over_a_named_pipe() {
+    let dir = tempfile::tempdir().unwrap();
+    let discovery = dir.path().join("daemon.json");
+    let name = format!("ts-bridge-test-{}", std::process::id());
+    let path = ts_bridge::control::pipe_path(&name);
+    let mut daemon = ts_bridge(&discovery)
+        .args(["daemon", "--pipe", &name])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut stream = loop {
+        match PipeStream::connect(&path) {
+            Ok(stream) => break stream,
+            Err(err) => {
+                assert!(
+                    Instant::now() < deadline,
+                    "daemon never opened {path}: {err}"
+                );
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    };
+    let mut reader = BufReader::new(stream.clone());
+    for id in 1..=2 {
+        common::send(
+            &mut stream,
+            serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": "ts-bridge/daemon/status" }),
+        );
+        let reply = common::read(&mut reader);
+        assert_eq!(reply["id"], id);
+        assert_eq!(reply["result"]["pid"], daemon.id(), "{reply}");
+    }
+    drop(reader);
+    drop(stream);
+
+    let status = ts_bridge(&discovery)
+        .args(["daemon", "status", "--pipe", &name])
+        .output()
+        .unwrap();
+    assert!(status.status.success());
+    let stdout = String::from_utf8(status.stdout).unwrap();
+    assert!(stdout.contains(&format!("pid {}", daemon.id())), "{stdout}");
+
+    let stop = ts_bridge(&discovery)
+        .args(["daemon", "stop", "--pipe", &name])
+        .output()
+        .unwrap();
+    assert!(stop.status.success());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(exit) = daemon.try_wait().unwrap() {
+            assert!(exit.success());
+            break;
+        }
+        assert!(Instant::now() < deadline, "daemon did not exit");
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(!discovery.exists());
+}
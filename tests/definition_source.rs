@@ -39,7 +39,9 @@ fn source_definition_locations_convert_to_location_links() {
         .expect("definition adapter should convert response");
     let value = match adapted {
         protocol::AdapterResult::Ready(value) => value,
-        protocol::AdapterResult::Continue(_) => panic!("expected ready definition response"),
+        protocol::AdapterResult::Continue(_) | protocol::AdapterResult::Partial { .. } => {
+            panic!("expected ready definition response")
+        }
     };
     match serde_json::from_value::<GotoDefinitionResponse>(value)
         .expect("adapter must emit a valid LSP response")
@@ -0,0 +1,194 @@
+//! With the default `publish_diagnostic_on = "insert_leave"`, edits do not
+//! ask tsserver for diagnostics; `ts-bridge/requestDiagnostics` or a save
+//! does.
+#![cfg(unix)]
+
+mod common;
+
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{Value, json};
+
+/// The syntax server, which also answers hovers, logs every `geterr` file
+/// next to the script; both complete the request.
+const CASES: &str = r#"
+    case "geterr":
+      if (process.env.TS_LSP_RS_SERVER_KIND === "syntax")
+        fs.appendFileSync(path.join(__dirname, "geterr.log"), args.files.join("\n") + "\n");
+      return event("requestCompleted", { request_seq: request.seq });
+"#;
+
+const SETUP: &str = r#"
+function event(name, body) {
+  const json = JSON.stringify({ seq: 0, type: "event", event: name, body });
+  process.stdout.write(`Content-Length: ${Buffer.byteLength(json)}\r\n\r\n${json}`);
+}
+"#;
+
+struct Session {
+    bridge: Child,
+    writer: ChildStdin,
+    reader: BufReader<ChildStdout>,
+    log: PathBuf,
+    uri: String,
+    next_id: i64,
+}
+
+impl Session {
+    /// Starts the bridge in `root` and opens `main.ts`.
+    fn open(root: &Path) -> Self {
+        common::install_tsserver(root, &common::scripted_tsserver(SETUP, CASES));
+        let mut bridge = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+            .current_dir(root)
+            .env_remove("TS_BRIDGE_RECORD")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let writer = bridge.stdin.take().unwrap();
+        let reader = BufReader::new(bridge.stdout.take().unwrap());
+        let mut session = Self {
+            bridge,
+            writer,
+            reader,
+            log: root.join("node_modules/typescript/lib/geterr.log"),
+            uri: format!("file://{}/main.ts", root.display()),
+            next_id: 1,
+        };
+        session.request(
+            "initialize",
+            json!({ "rootPath": root, "capabilities": {} }),
+        );
+        session.notify("initialized", json!({}));
+        let uri = session.uri.clone();
+        session.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": uri, "languageId": "typescript", "version": 1, "text": "let a = 1;\n",
+            } }),
+        );
+        session
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        common::send(
+            &mut self.writer,
+            json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        );
+    }
+
+    /// Sends a request and reads until its response, answering requests
+    /// from the server.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        common::send(
+            &mut self.writer,
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }),
+        );
+        loop {
+            let message = common::read(&mut self.reader);
+            if message.get("method").is_some() && message.get("id").is_some() {
+                common::send(
+                    &mut self.writer,
+                    json!({ "jsonrpc": "2.0", "id": message["id"], "result": null }),
+                );
+            } else if message["id"] == id && message.get("method").is_none() {
+                return message;
+            }
+        }
+    }
+
+    fn edit(&mut self, version: i32) {
+        let uri = self.uri.clone();
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{
+                    "range": {
+                        "start": { "line": 0, "character": 8 },
+                        "end": { "line": 0, "character": 9 },
+                    },
+                    "text": version.to_string(),
+                }],
+            }),
+        );
+    }
+
+    /// How many `geterr` requests reached the syntax server. A hover round
+    /// trip through it first makes sure everything sent before has arrived.
+    fn geterr_count(&mut self) -> usize {
+        let uri = self.uri.clone();
+        self.request(
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": uri }, "position": { "line": 0, "character": 4 } }),
+        );
+        std::fs::read_to_string(&self.log)
+            .unwrap_or_default()
+            .lines()
+            .count()
+    }
+
+    /// Closing stdin ends the session.
+    fn close(mut self) {
+        drop(self.writer);
+        self.bridge.wait().unwrap();
+    }
+}
+
+#[test]
+fn edits_wait_for_request_diagnostics() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let mut session = Session::open(&root);
+    let opened = session.geterr_count();
+    assert!(opened > 0, "opening a file asks for its diagnostics");
+
+    session.edit(2);
+    session.edit(3);
+    assert_eq!(session.geterr_count(), opened, "edits are buffered");
+
+    let uri = session.uri.clone();
+    session.notify(
+        "ts-bridge/requestDiagnostics",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    let flushed = session.geterr_count();
+    assert_eq!(flushed, opened * 2, "the buffered file is checked once");
+
+    session.notify(
+        "ts-bridge/requestDiagnostics",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    assert_eq!(session.geterr_count(), flushed, "nothing is left to flush");
+    session.close();
+}
+
+#[test]
+fn save_flushes_buffered_diagnostics() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let mut session = Session::open(&root);
+    let opened = session.geterr_count();
+
+    session.edit(2);
+    assert_eq!(session.geterr_count(), opened);
+
+    let uri = session.uri.clone();
+    session.notify(
+        "textDocument/didSave",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    assert_eq!(session.geterr_count(), opened * 2);
+    session.close();
+}
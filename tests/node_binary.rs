@@ -0,0 +1,37 @@
+//! `tsserver.node_path` is honoured and an outdated node is rejected before
+//! tsserver is spawned.
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::process::{ProcessError, ServerKind, TsserverProcess};
+
+#[test]
+fn outdated_node_is_reported_with_its_version() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let node = dir.path().join("node");
+    fs::write(&node, "#!/bin/sh\necho v12.22.0\n").expect("write fake node");
+    fs::set_permissions(&node, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    let binary = common::tsserver_binary(PathBuf::from("tsserver.js"));
+    let launch = TsserverLaunchOptions {
+        node_path: Some(node.clone()),
+        ..TsserverLaunchOptions::default()
+    };
+    let mut server = TsserverProcess::new(ServerKind::Syntax, binary, launch);
+    match server.start() {
+        Err(ProcessError::NodeTooOld {
+            node: found,
+            version,
+        }) => {
+            assert_eq!(found, node);
+            assert_eq!(version, "v12.22.0");
+        }
+        other => panic!("expected NodeTooOld, got {:?}", other.err()),
+    }
+}
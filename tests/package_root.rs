@@ -0,0 +1,24 @@
+//! Monorepo package detection: a file belongs to the package of its nearest
+//! tsconfig/jsconfig below the workspace root.
+
+use std::fs;
+
+use ts_bridge::provider::package_root;
+
+#[test]
+fn package_root_is_the_nearest_tsconfig_inside_the_workspace() {
+    let workspace = tempfile::tempdir().expect("temp dir");
+    let root = workspace.path();
+    fs::write(root.join("tsconfig.json"), "{}").expect("write root tsconfig");
+    let package = root.join("packages/app");
+    fs::create_dir_all(package.join("src")).expect("create package");
+    fs::write(package.join("tsconfig.json"), "{}").expect("write package tsconfig");
+    fs::create_dir_all(root.join("scripts")).expect("create scripts");
+
+    assert_eq!(
+        package_root(root, &package.join("src/index.ts")),
+        Some(package.clone())
+    );
+    assert_eq!(package_root(root, &root.join("scripts/build.ts")), None);
+    assert_eq!(package_root(root, &root.join("index.ts")), None);
+}
@@ -0,0 +1,99 @@
+//! pnpm layouts where `node_modules/typescript` is missing but the package
+//! sits in the `node_modules/.pnpm` virtual store.
+
+use std::fs;
+use std::path::Path;
+
+use ts_bridge::provider::{BinarySource, Provider};
+
+fn store_typescript(root: &Path, entry: &str) {
+    let lib = root
+        .join("node_modules/.pnpm")
+        .join(entry)
+        .join("node_modules/typescript/lib");
+    fs::create_dir_all(&lib).expect("create store entry");
+    fs::write(lib.join("tsserver.js"), "").expect("write tsserver");
+}
+
+#[test]
+fn newest_typescript_in_the_virtual_store_is_used() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    store_typescript(workspace.path(), "typescript@4.9.5");
+    store_typescript(workspace.path(), "typescript@5.10.2");
+    store_typescript(workspace.path(), "typescript@5.4.0_peer-hash");
+    fs::create_dir_all(workspace.path().join("node_modules/.pnpm/typescript@6.0.0"))
+        .expect("create incomplete store entry");
+    let package = workspace.path().join("packages/app");
+    fs::create_dir_all(&package).expect("create package");
+
+    let mut provider = Provider::new(&package);
+    let binary = provider.resolve().expect("tsserver from the pnpm store");
+    assert!(matches!(binary.source, BinarySource::PnpmStore));
+    assert!(
+        binary
+            .executable
+            .ends_with("typescript@5.10.2/node_modules/typescript/lib/tsserver.js"),
+        "{:?}",
+        binary.executable
+    );
+    assert_eq!(
+        provider.workspace_root(),
+        workspace.path().canonicalize().unwrap()
+    );
+}
+
+#[test]
+fn lockfile_picks_the_packages_typescript_over_the_newest() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    store_typescript(workspace.path(), "typescript@4.9.5");
+    store_typescript(workspace.path(), "typescript@5.4.2");
+    let package = workspace.path().join("packages/app");
+    fs::create_dir_all(&package).expect("create package");
+    fs::write(
+        package.join("package.json"),
+        r#"{ "devDependencies": { "typescript": ">=4.9 <5" } }"#,
+    )
+    .expect("write package.json");
+    fs::write(
+        workspace.path().join("pnpm-lock.yaml"),
+        "lockfileVersion: '9.0'\n\nimporters:\n\n  .:\n    devDependencies:\n      typescript:\n        specifier: ^5.4.0\n        version: 5.4.2\n\n  packages/app:\n    devDependencies:\n      typescript:\n        specifier: '>=4.9 <5'\n        version: 4.9.5(@types/node@20.0.0)\n\npackages:\n\n  typescript@5.4.2:\n    resolution: {integrity: sha512-x}\n",
+    )
+    .expect("write lockfile");
+
+    let binary = Provider::new(&package)
+        .resolve()
+        .expect("tsserver from the pnpm store");
+    assert!(
+        binary
+            .executable
+            .ends_with("typescript@4.9.5/node_modules/typescript/lib/tsserver.js"),
+        "{:?}",
+        binary.executable
+    );
+}
+
+#[test]
+fn package_json_range_picks_among_store_versions() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    store_typescript(workspace.path(), "typescript@5.4.2");
+    store_typescript(workspace.path(), "typescript@5.4.5");
+    store_typescript(workspace.path(), "typescript@5.6.3");
+    fs::write(
+        workspace.path().join("package.json"),
+        r#"{ "devDependencies": { "typescript": "~5.4.0" } }"#,
+    )
+    .expect("write package.json");
+    let package = workspace.path().join("packages/app");
+    fs::create_dir_all(&package).expect("create package");
+
+    let binary = Provider::new(&package)
+        .resolve()
+        .expect("tsserver from the pnpm store");
+    assert!(
+        binary
+            .executable
+            .ends_with("typescript@5.4.5/node_modules/typescript/lib/tsserver.js"),
+        "{:?}",
+        binary.executable
+    );
+}
@@ -0,0 +1,140 @@
+//! `--record` captures a session's LSP and tsserver traffic, and
+//! `ts-bridge replay` plays the client side back against a fresh bridge.
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use serde_json::{Value, json};
+
+/// Answers every request; `quickinfo` gets a hover body.
+const CASES: &str = r#"
+    case "quickinfo":
+      return reply(request, { kind: "let", kindModifiers: "", start: { line: 1, offset: 5 }, end: { line: 1, offset: 6 },
+        displayString: "let a: number", documentation: "", tags: [] });
+"#;
+
+/// Reads until the response to `id`, answering requests from the server.
+fn response(reader: &mut impl BufRead, writer: &mut impl Write, id: i64) -> Value {
+    loop {
+        let message = common::read(reader);
+        if message.get("method").is_some() && message.get("id").is_some() {
+            common::send(
+                writer,
+                json!({ "jsonrpc": "2.0", "id": message["id"], "result": null }),
+            );
+        } else if message["id"] == id && message.get("method").is_none() {
+            return message;
+        }
+    }
+}
+
+#[test]
+fn recorded_session_replays_with_the_same_answers() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    common::install_tsserver(&root, &common::scripted_tsserver("", CASES));
+    std::fs::write(root.join("tsconfig.json"), "{}").unwrap();
+    let recording = root.join("session.jsonl");
+    let uri = format!("file://{}/main.ts", root.display());
+
+    let mut bridge = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .arg("--record")
+        .arg(&recording)
+        .current_dir(&root)
+        .env_remove("TS_BRIDGE_RECORD")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut writer = bridge.stdin.take().unwrap();
+    let mut reader = BufReader::new(bridge.stdout.take().unwrap());
+    common::send(
+        &mut writer,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": { "rootPath": root, "capabilities": {} } }),
+    );
+    response(&mut reader, &mut writer, 1);
+    common::send(
+        &mut writer,
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    );
+    common::send(
+        &mut writer,
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": {
+            "uri": uri, "languageId": "typescript", "version": 1, "text": "let a = 1;\n",
+        } } }),
+    );
+    common::send(
+        &mut writer,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+            "textDocument": { "uri": uri }, "position": { "line": 0, "character": 4 },
+        } }),
+    );
+    let hover = response(&mut reader, &mut writer, 2);
+    assert!(
+        hover["result"]["contents"]
+            .to_string()
+            .contains("let a: number"),
+        "{hover}"
+    );
+    common::send(
+        &mut writer,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+    );
+    response(&mut reader, &mut writer, 3);
+    common::send(&mut writer, json!({ "jsonrpc": "2.0", "method": "exit" }));
+    drop(writer);
+    assert!(bridge.wait().unwrap().success());
+
+    let entries: Vec<Value> = std::fs::read_to_string(&recording)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let has = |channel: &str, direction: &str, field: &str, value: &str| {
+        entries.iter().any(|entry| {
+            entry["channel"] == channel
+                && entry["direction"] == direction
+                && entry["message"][field] == value
+        })
+    };
+    assert!(has("lsp", "in", "method", "textDocument/hover"));
+    assert!(has("tsserver", "out", "command", "quickinfo"));
+    assert!(has("tsserver", "in", "command", "quickinfo"));
+    assert!(entries.iter().any(|entry| entry["channel"] == "lsp"
+        && entry["direction"] == "out"
+        && entry["message"]["id"] == 2));
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("ts-bridge"))
+        .arg("replay")
+        .arg(&recording)
+        .arg("--fast")
+        .current_dir(&root)
+        .env_remove("TS_BRIDGE_RECORD")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.starts_with("textDocument/hover ") && line.contains("  1  ")),
+        "{stdout}"
+    );
+    assert!(
+        !stdout.contains("response to textDocument/hover"),
+        "the hover answer should match the recording: {stdout}"
+    );
+    assert!(!stdout.contains("never answered"), "{stdout}");
+}
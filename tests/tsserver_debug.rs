@@ -0,0 +1,46 @@
+//! `tsserver.debug_port` starts the chosen server under the node inspector.
+
+mod common;
+
+use std::time::Duration;
+
+use ts_bridge::config::{DebugServer, TsserverLaunchOptions};
+use ts_bridge::process::{ServerKind, TsserverProcess};
+
+fn exec_argv(kind: ServerKind, launch: TsserverLaunchOptions) -> Option<String> {
+    if !common::has_node() {
+        return None;
+    }
+    let dir = tempfile::tempdir().expect("temp dir");
+    let binary = common::script_binary(
+        dir.path(),
+        "console.error('execArgv ' + JSON.stringify(process.execArgv));\n",
+    );
+    let mut server = TsserverProcess::new(kind, binary, launch);
+    server.start().expect("start fake tsserver");
+    let rx = server.stderr_rx().expect("stderr channel");
+    loop {
+        // The inspector announces itself on stderr before the script runs.
+        let line = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("execArgv report");
+        if let Some(argv) = line.strip_prefix("execArgv ") {
+            return Some(argv.to_string());
+        }
+    }
+}
+
+#[test]
+fn inspector_is_only_enabled_for_the_chosen_server() {
+    let launch = TsserverLaunchOptions {
+        debug_port: Some(0),
+        debug_server: DebugServer::Semantic,
+        ..TsserverLaunchOptions::default()
+    };
+    let Some(semantic) = exec_argv(ServerKind::Semantic, launch.clone()) else {
+        return;
+    };
+    assert_eq!(semantic, r#"["--inspect=0"]"#);
+    let syntax = exec_argv(ServerKind::Syntax, launch).expect("syntax argv");
+    assert_eq!(syntax, "[]");
+}
@@ -0,0 +1,70 @@
+//! `tsserver.env`, `tsserver.node_options` and `tsserver.env_allowlist` shape
+//! the child's environment.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::process::{ServerKind, TsserverProcess};
+
+const REPORT_ENV: &str = r#"
+const pick = (name) => process.env[name] ?? null;
+console.error(JSON.stringify({
+  set: pick("TSB_TEST_VALUE"),
+  removed: pick("CARGO_PKG_NAME"),
+  cargo: pick("CARGO"),
+  nodeOptions: pick("NODE_OPTIONS"),
+}));
+"#;
+
+fn child_env(launch: TsserverLaunchOptions) -> Option<Value> {
+    if !common::has_node() {
+        return None;
+    }
+    let dir = tempfile::tempdir().expect("temp dir");
+    let binary = common::script_binary(dir.path(), REPORT_ENV);
+    let mut server = TsserverProcess::new(ServerKind::Syntax, binary, launch);
+    server.start().expect("start fake tsserver");
+    let line = server
+        .stderr_rx()
+        .expect("stderr channel")
+        .recv_timeout(Duration::from_secs(10))
+        .expect("env report");
+    Some(serde_json::from_str(&line).expect("json report"))
+}
+
+#[test]
+fn env_entries_and_node_options_reach_the_child() {
+    let launch = TsserverLaunchOptions {
+        env: BTreeMap::from([
+            ("TSB_TEST_VALUE".to_string(), Some("42".to_string())),
+            ("CARGO_PKG_NAME".to_string(), None),
+        ]),
+        node_options: Some("--no-warnings".to_string()),
+        ..TsserverLaunchOptions::default()
+    };
+    let Some(report) = child_env(launch) else {
+        return;
+    };
+    assert_eq!(report["set"], json!("42"));
+    assert_eq!(report["removed"], Value::Null);
+    assert_eq!(report["nodeOptions"], json!("--no-warnings"));
+    assert!(report["cargo"].is_string(), "other variables are inherited");
+}
+
+#[test]
+fn allowlist_drops_everything_else() {
+    let launch = TsserverLaunchOptions {
+        env_allowlist: Some(vec!["CARGO_PKG_NAME".to_string()]),
+        ..TsserverLaunchOptions::default()
+    };
+    let Some(report) = child_env(launch) else {
+        return;
+    };
+    assert_eq!(report["removed"], json!("ts-bridge"));
+    assert_eq!(report["cargo"], Value::Null);
+}
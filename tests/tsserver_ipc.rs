@@ -0,0 +1,48 @@
+//! `tsserver.transport = "ipc"` exchanges messages over Node's IPC channel.
+#![cfg(unix)]
+
+mod common;
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use ts_bridge::config::{TsserverLaunchOptions, TsserverTransport};
+use ts_bridge::process::{ServerKind, TsserverProcess};
+
+#[test]
+fn requests_and_responses_travel_over_node_ipc() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().expect("temp dir");
+    let binary = common::script_binary(
+        dir.path(),
+        r#"
+if (!process.argv.includes("--useNodeIpc")) process.exit(2);
+process.on("message", (request) => {
+  process.send({ seq: 0, type: "response", request_seq: request.seq, command: request.command, success: true });
+  if (request.command === "exit") process.exit(0);
+});
+"#,
+    );
+    let launch = TsserverLaunchOptions {
+        transport: TsserverTransport::Ipc,
+        ..TsserverLaunchOptions::default()
+    };
+    let mut server = TsserverProcess::new(ServerKind::Syntax, binary, launch);
+    server.start().expect("start fake tsserver");
+    let rx = server.response_rx().expect("response channel");
+
+    server
+        .write(&json!({ "seq": 7, "type": "request", "command": "configure" }))
+        .expect("send over ipc");
+    let response = rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("response over ipc");
+    assert_eq!(response.get("request_seq"), Some(&json!(7)));
+    assert_eq!(response.get("command"), Some(&json!("configure")));
+
+    let status = server.shutdown().expect("exit status");
+    assert!(status.success(), "exit should arrive over ipc: {status}");
+}
@@ -0,0 +1,89 @@
+//! `tsserver.path` bypasses the node_modules/Yarn/PATH lookup and `tsserver.tsdk`
+//! takes precedence over it.
+
+use std::fs;
+use std::path::PathBuf;
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::provider::{BinarySource, Provider, ProviderError};
+
+#[test]
+fn explicit_path_is_used_and_reports_its_version() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    let local = workspace.path().join("node_modules/typescript/lib");
+    fs::create_dir_all(&local).expect("create local typescript");
+    fs::write(local.join("tsserver.js"), "").expect("write local tsserver");
+
+    let toolchain = tempfile::tempdir().expect("toolchain");
+    let lib = toolchain.path().join("typescript/lib");
+    fs::create_dir_all(&lib).expect("create custom typescript");
+    fs::write(lib.join("tsserver.js"), "").expect("write custom tsserver");
+    fs::write(
+        toolchain.path().join("typescript/package.json"),
+        r#"{ "name": "typescript", "version": "5.4.0-fork" }"#,
+    )
+    .expect("write package.json");
+
+    let mut provider = Provider::new(workspace.path());
+    let binary = provider
+        .resolve_for(&TsserverLaunchOptions {
+            path: Some(lib.join("tsserver.js")),
+            ..TsserverLaunchOptions::default()
+        })
+        .expect("explicit tsserver");
+    assert!(matches!(binary.source, BinarySource::Explicit));
+    assert_eq!(
+        binary.executable,
+        lib.join("tsserver.js").canonicalize().unwrap()
+    );
+    assert_eq!(binary.version.as_deref(), Some("5.4.0-fork"));
+    assert!(binary.plugin_probe.is_none());
+}
+
+#[test]
+fn missing_explicit_path_is_an_error() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    let missing = workspace.path().join("nope/tsserver.js");
+    let mut provider = Provider::new(workspace.path());
+    let err = provider
+        .resolve_for(&TsserverLaunchOptions {
+            path: Some(missing.clone()),
+            ..TsserverLaunchOptions::default()
+        })
+        .expect_err("missing tsserver");
+    assert!(matches!(err, ProviderError::ExplicitPathMissing { path } if path == missing));
+}
+
+#[test]
+fn tsdk_is_preferred_over_node_modules() {
+    let workspace = tempfile::tempdir().expect("workspace");
+    let local = workspace.path().join("node_modules/typescript/lib");
+    fs::create_dir_all(&local).expect("create local typescript");
+    fs::write(local.join("tsserver.js"), "").expect("write local tsserver");
+    let sdk = workspace.path().join("tools/typescript/lib");
+    fs::create_dir_all(&sdk).expect("create tsdk");
+    fs::write(sdk.join("tsserver.js"), "").expect("write tsdk tsserver");
+    fs::write(
+        workspace.path().join("tools/typescript/package.json"),
+        r#"{ "version": "5.6.3" }"#,
+    )
+    .expect("write package.json");
+
+    let mut provider = Provider::new(workspace.path());
+    let binary = provider
+        .resolve_for(&TsserverLaunchOptions {
+            tsdk: Some(PathBuf::from("tools/typescript/lib")),
+            ..TsserverLaunchOptions::default()
+        })
+        .expect("tsdk tsserver");
+    assert!(matches!(binary.source, BinarySource::Tsdk));
+    assert_eq!(binary.version.as_deref(), Some("5.6.3"));
+
+    let binary = provider
+        .resolve_for(&TsserverLaunchOptions {
+            tsdk: Some(PathBuf::from("missing/lib")),
+            ..TsserverLaunchOptions::default()
+        })
+        .expect("falls back to node_modules");
+    assert!(matches!(binary.source, BinarySource::LocalNodeModules));
+}
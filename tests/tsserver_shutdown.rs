@@ -0,0 +1,56 @@
+//! `TsserverProcess::shutdown` asks tsserver to exit before resorting to a kill.
+
+mod common;
+
+use std::path::Path;
+use std::time::Instant;
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::process::{SHUTDOWN_GRACE, ServerKind, TsserverProcess};
+
+fn fake_tsserver(dir: &Path, source: &str) -> Option<TsserverProcess> {
+    if !common::has_node() {
+        return None;
+    }
+    let binary = common::script_binary(dir, source);
+    let mut server =
+        TsserverProcess::new(ServerKind::Syntax, binary, TsserverLaunchOptions::default());
+    server.start().expect("start fake tsserver");
+    Some(server)
+}
+
+#[test]
+fn exit_command_stops_tsserver_without_killing_it() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let Some(mut server) = fake_tsserver(
+        dir.path(),
+        r#"
+process.stdin.on("data", (chunk) => {
+  if (chunk.toString().includes('"command":"exit"')) process.exit(0);
+});
+setInterval(() => {}, 1000);
+"#,
+    ) else {
+        return;
+    };
+
+    let status = server.shutdown().expect("exit status");
+    assert!(
+        status.success(),
+        "tsserver should exit on its own: {status}"
+    );
+    assert!(server.pid().is_none());
+}
+
+#[test]
+fn unresponsive_tsserver_is_killed_after_the_grace_period() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let Some(mut server) = fake_tsserver(dir.path(), "setInterval(() => {}, 1000);\n") else {
+        return;
+    };
+
+    let started = Instant::now();
+    let status = server.shutdown().expect("exit status");
+    assert!(!status.success());
+    assert!(started.elapsed() >= SHUTDOWN_GRACE);
+}
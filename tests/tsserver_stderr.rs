@@ -0,0 +1,36 @@
+//! tsserver stderr is piped and surfaced line by line instead of being
+//! inherited by the bridge process.
+
+mod common;
+
+use std::time::Duration;
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::process::{ServerKind, TsserverProcess};
+
+#[test]
+fn stderr_lines_are_captured() {
+    if !common::has_node() {
+        return;
+    }
+    let dir = tempfile::tempdir().expect("temp dir");
+    let binary = common::script_binary(
+        dir.path(),
+        "console.error('first line');\nconsole.error('second line');\n",
+    );
+    let mut server = TsserverProcess::new(
+        ServerKind::Semantic,
+        binary,
+        TsserverLaunchOptions::default(),
+    );
+    server.start().expect("start fake tsserver");
+    let rx = server.stderr_rx().expect("stderr channel");
+
+    let lines: Vec<String> = (0..2)
+        .map(|_| {
+            rx.recv_timeout(Duration::from_secs(10))
+                .expect("stderr line")
+        })
+        .collect();
+    assert_eq!(lines, ["first line", "second line"]);
+}
@@ -0,0 +1,72 @@
+//! Yarn PnP projects have no `node_modules/typescript`; the provider asks the
+//! `.pnp.cjs` runtime instead. The stand-in runtime below maps `typescript/*`
+//! onto a folder outside the project, like PnP's zip cache.
+
+mod common;
+
+use std::fs;
+
+use ts_bridge::config::TsserverLaunchOptions;
+use ts_bridge::provider::{BinarySource, Provider, ProviderError};
+
+#[test]
+fn typescript_is_resolved_through_the_pnp_runtime() {
+    if !common::has_node() {
+        return;
+    }
+    let cache = tempfile::tempdir().expect("cache");
+    let typescript = cache.path().join("typescript");
+    fs::create_dir_all(typescript.join("lib")).expect("create cached typescript");
+    fs::write(typescript.join("lib/tsserver.js"), "").expect("write tsserver");
+    fs::write(
+        typescript.join("package.json"),
+        r#"{ "name": "typescript", "version": "5.5.4" }"#,
+    )
+    .expect("write package.json");
+
+    let project = tempfile::tempdir().expect("project");
+    fs::write(project.path().join("package.json"), "{}").expect("write package.json");
+    let runtime = format!(
+        r#"
+const Module = require("module");
+const original = Module._resolveFilename;
+Module._resolveFilename = function (request, ...rest) {{
+  if (request.startsWith("typescript/")) {{
+    return require("path").join({cache:?}, request);
+  }}
+  return original.call(this, request, ...rest);
+}};
+"#,
+        cache = cache.path().to_str().unwrap()
+    );
+    fs::write(project.path().join(".pnp.cjs"), runtime).expect("write .pnp.cjs");
+    let nested = project.path().join("packages/app");
+    fs::create_dir_all(&nested).expect("create workspace package");
+
+    let mut provider = Provider::new(&nested);
+    let binary = provider.resolve().expect("tsserver through PnP");
+    assert!(matches!(binary.source, BinarySource::YarnPnp));
+    assert_eq!(binary.executable, typescript.join("lib/tsserver.js"));
+    assert_eq!(binary.version.as_deref(), Some("5.5.4"));
+    assert_eq!(binary.node_args.len(), 2);
+    assert_eq!(binary.node_args[0], "--require");
+    assert_eq!(
+        provider.workspace_root(),
+        project.path().canonicalize().unwrap()
+    );
+}
+
+#[test]
+fn pnp_probe_runs_the_configured_node() {
+    let project = tempfile::tempdir().expect("project");
+    fs::write(project.path().join(".pnp.cjs"), "").expect("write .pnp.cjs");
+    let launch = TsserverLaunchOptions {
+        node_path: Some(project.path().join("missing-node")),
+        disable_bundled_typescript: true,
+        ..TsserverLaunchOptions::default()
+    };
+    let err = Provider::new(project.path())
+        .resolve_for(&launch)
+        .expect_err("probe cannot run");
+    assert!(matches!(err, ProviderError::Node(_)), "{err}");
+}